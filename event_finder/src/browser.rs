@@ -0,0 +1,227 @@
+//! # Browser Module
+//!
+//! This module opens a listed event's page in the user's web browser, for `o N` at the
+//! interactive prompt and `--open N`. Launching goes through the `Launcher` trait so tests can
+//! check which URL would have been opened without starting a browser.
+
+use std::io;
+use std::process::{Command, Stdio};
+
+use url::Url;
+
+use crate::data_processing::ProcessedEvent;
+
+/// Something that can open a URL, such as the system's web browser.
+pub trait Launcher {
+    /// Opens `url`, returning once it has been handed off.
+    fn launch(&mut self, url: &str) -> io::Result<()>;
+}
+
+/// Opens URLs in the default browser through the platform's opener: `open` on macOS,
+/// `url.dll`'s handler on Windows, and `xdg-open` elsewhere.
+///
+/// The URL is passed as one argument and never through a shell, where a scraped link's `&` or
+/// `|` would start other commands. Only `http` and `https` URLs are opened, since the openers
+/// also run local files.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SystemBrowser;
+
+impl Launcher for SystemBrowser {
+    fn launch(&mut self, url: &str) -> io::Result<()> {
+        check_web_url(url)?;
+        let mut command = if cfg!(target_os = "macos") {
+            Command::new("open")
+        } else if cfg!(windows) {
+            let mut command = Command::new("rundll32");
+            command.arg("url.dll,FileProtocolHandler");
+            command
+        } else {
+            Command::new("xdg-open")
+        };
+        let status = command
+            .arg(url)
+            .stdin(Stdio::null())
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .status()?;
+        if status.success() {
+            Ok(())
+        } else {
+            Err(io::Error::other(format!(
+                "the browser launcher exited with {}",
+                status
+            )))
+        }
+    }
+}
+
+/// Checks that `url` is a web page, which is all `SystemBrowser` opens.
+fn check_web_url(url: &str) -> io::Result<()> {
+    match Url::parse(url) {
+        Ok(parsed) if parsed.scheme() == "http" || parsed.scheme() == "https" => Ok(()),
+        _ => Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "only http(s) links are opened",
+        )),
+    }
+}
+
+/// Opens the page of one listed event.
+///
+/// # Arguments
+///
+/// * `events` - The events as they were listed, so `events[0]` is number 1.
+/// * `number` - The number of the event to open, counting from 1.
+/// * `launcher` - What opens the URL.
+///
+/// # Returns
+///
+/// What was opened, e.g. `Opened Bluegrass Night: https://example.com/bluegrass`, or why
+/// nothing was: the number isn't listed, the event has no link, or launching failed.
+pub fn open_event(
+    events: &[ProcessedEvent],
+    number: usize,
+    launcher: &mut dyn Launcher,
+) -> Result<String, String> {
+    if events.is_empty() {
+        return Err("no events are listed to open".to_string());
+    }
+    let event = number
+        .checked_sub(1)
+        .and_then(|index| events.get(index))
+        .ok_or_else(|| format!("there is no event {} (pick 1-{})", number, events.len()))?;
+    let url = event.url.trim();
+    if url.is_empty() {
+        return Err(format!(
+            "event {}, {}, has no link to open",
+            number, event.name
+        ));
+    }
+    launcher
+        .launch(url)
+        .map_err(|e| format!("couldn't open {}: {}", url, e))?;
+    Ok(format!("Opened {}: {}", event.name, url))
+}
+
+/// Reads which event to open from the menu's `o 3` or `open 3`.
+///
+/// # Returns
+///
+/// `None` if the input isn't an open command, `Some(None)` for a bare `o` or `open`, which
+/// asks which event, or `Some(Some(text))` with the text after it.
+pub fn parse_open_command(input: &str) -> Option<Option<&str>> {
+    let input = input.trim();
+    let (command, rest) = input.split_once(char::is_whitespace).unwrap_or((input, ""));
+    if !command.eq_ignore_ascii_case("o") && !command.eq_ignore_ascii_case("open") {
+        return None;
+    }
+    let rest = rest.trim();
+    Some((!rest.is_empty()).then_some(rest))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Default)]
+    struct RecordingLauncher {
+        opened: Vec<String>,
+        fail: bool,
+    }
+
+    impl Launcher for RecordingLauncher {
+        fn launch(&mut self, url: &str) -> io::Result<()> {
+            if self.fail {
+                return Err(io::Error::new(io::ErrorKind::NotFound, "no browser"));
+            }
+            self.opened.push(url.to_string());
+            Ok(())
+        }
+    }
+
+    fn events() -> Vec<ProcessedEvent> {
+        vec![
+            ProcessedEvent {
+                name: "Bluegrass Night".to_string(),
+                url: "https://example.com/bluegrass".to_string(),
+                ..Default::default()
+            },
+            ProcessedEvent {
+                name: "Mystery Show".to_string(),
+                url: " ".to_string(),
+                ..Default::default()
+            },
+        ]
+    }
+
+    #[test]
+    fn test_open_event_launches_its_url() {
+        let mut launcher = RecordingLauncher::default();
+
+        assert_eq!(
+            open_event(&events(), 1, &mut launcher),
+            Ok("Opened Bluegrass Night: https://example.com/bluegrass".to_string())
+        );
+        assert_eq!(
+            launcher.opened,
+            vec!["https://example.com/bluegrass".to_string()]
+        );
+    }
+
+    #[test]
+    fn test_open_event_errors_launch_nothing() {
+        let mut launcher = RecordingLauncher::default();
+
+        assert_eq!(
+            open_event(&events(), 2, &mut launcher),
+            Err("event 2, Mystery Show, has no link to open".to_string())
+        );
+        assert_eq!(
+            open_event(&events(), 3, &mut launcher),
+            Err("there is no event 3 (pick 1-2)".to_string())
+        );
+        assert_eq!(
+            open_event(&events(), 0, &mut launcher),
+            Err("there is no event 0 (pick 1-2)".to_string())
+        );
+        assert_eq!(
+            open_event(&[], 1, &mut launcher),
+            Err("no events are listed to open".to_string())
+        );
+        assert!(launcher.opened.is_empty());
+
+        let mut broken = RecordingLauncher {
+            fail: true,
+            ..Default::default()
+        };
+        assert_eq!(
+            open_event(&events(), 1, &mut broken),
+            Err("couldn't open https://example.com/bluegrass: no browser".to_string())
+        );
+    }
+
+    #[test]
+    fn test_system_browser_opens_only_web_pages() {
+        assert!(check_web_url("https://example.com/show?a=1&b=2").is_ok());
+        assert!(check_web_url("http://example.com/").is_ok());
+        for url in [
+            "file:///etc/passwd",
+            "javascript:alert(1)",
+            "C:\\Windows\\calc.exe",
+            "-h",
+            "https://example.com & calc",
+        ] {
+            let error = SystemBrowser.launch(url).unwrap_err();
+            assert_eq!(error.kind(), io::ErrorKind::InvalidInput, "{}", url);
+        }
+    }
+
+    #[test]
+    fn test_parse_open_command() {
+        assert_eq!(parse_open_command("o 3"), Some(Some("3")));
+        assert_eq!(parse_open_command("  OPEN   12 "), Some(Some("12")));
+        assert_eq!(parse_open_command("o"), Some(None));
+        assert_eq!(parse_open_command("opera"), None);
+        assert_eq!(parse_open_command("yes"), None);
+    }
+}
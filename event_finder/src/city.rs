@@ -0,0 +1,246 @@
+//! # City Module
+//!
+//! This module defines the cities a sources file can have entries for. A `[[city]]` entry
+//! names a metro area and what goes with it: the timezone, a home location, and the values
+//! source URLs are templated with, such as Songkick's metro-area id. `--city` or the settings
+//! file's `city` picks one; sources without a `city` are used for every city.
+
+use crate::config::{self, Config, ConfigError, Table};
+use crate::geo::Coordinates;
+use crate::timezone::Zone;
+
+/// The placeholders a source's `url` and `base_url` may contain.
+pub const URL_PLACEHOLDERS: [&str; 3] = ["city", "songkick_metro_id", "songkick_metro_slug"];
+
+/// A city or metro area sources can be picked for.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct City {
+    /// The name `--city` picks and source entries refer to, e.g. `austin`.
+    pub name: String,
+    /// The name shown in the welcome banner, e.g. `Austin`.
+    pub label: String,
+    /// The city's timezone, used unless `--timezone` or the settings file sets one.
+    pub timezone: Option<Zone>,
+    /// Where distances are measured from unless the settings file has a `[home]`.
+    pub home: Option<Coordinates>,
+    /// What geocoding searches are narrowed to unless the settings file sets `near`.
+    pub near: Option<String>,
+    /// Songkick's number for the metro area, e.g. `9179` in `9179-us-austin`.
+    pub songkick_metro_id: Option<u64>,
+    /// Songkick's slug for the metro area, e.g. `us-austin` in `9179-us-austin`.
+    pub songkick_metro_slug: Option<String>,
+}
+
+impl City {
+    /// Fills a URL's placeholders with this city's values.
+    ///
+    /// # Arguments
+    ///
+    /// * `url` - A URL that may contain `{city}`, `{songkick_metro_id}`, or
+    ///   `{songkick_metro_slug}`.
+    ///
+    /// # Returns
+    ///
+    /// The URL for this city, or a message naming the value the city doesn't have.
+    pub fn expand(&self, url: &str) -> Result<String, String> {
+        let mut expanded = url.to_string();
+        for placeholder in placeholders(url) {
+            let value = match placeholder {
+                "city" => Some(self.name.clone()),
+                "songkick_metro_id" => self.songkick_metro_id.map(|id| id.to_string()),
+                "songkick_metro_slug" => self.songkick_metro_slug.clone(),
+                _ => None,
+            };
+            let value = value.ok_or_else(|| {
+                format!(
+                    "city '{}' has no {} for {{{}}}",
+                    self.name, placeholder, placeholder
+                )
+            })?;
+            expanded = expanded.replace(&format!("{{{}}}", placeholder), &value);
+        }
+        Ok(expanded)
+    }
+
+    /// Uses the city's timezone, home, and `near` where the settings file leaves them unset.
+    pub fn apply(&self, config: &mut Config) {
+        config.timezone = config.timezone.or(self.timezone);
+        config.home = config.home.or(self.home);
+        if config.near.is_none() {
+            config.near = self.near.clone();
+        }
+    }
+}
+
+/// Returns the names inside `{...}` in a URL, in order.
+pub fn placeholders(url: &str) -> Vec<&str> {
+    let mut found = Vec::new();
+    let mut rest = url;
+    while let Some((_, after)) = rest.split_once('{') {
+        match after.split_once('}') {
+            Some((name, next)) => {
+                found.push(name);
+                rest = next;
+            }
+            None => break,
+        }
+    }
+    found
+}
+
+/// Reads one `[[city]]` entry of a sources file.
+///
+/// # Returns
+///
+/// The city, or the first mistake found, with its line.
+pub fn parse_city(table: &Table) -> Result<City, ConfigError> {
+    let mut city = City::default();
+    for entry in &table.entries {
+        match entry.key.as_str() {
+            "name" => city.name = config::string(entry)?,
+            "label" => city.label = config::string(entry)?,
+            "timezone" => {
+                let zone = config::string(entry)?
+                    .parse()
+                    .map_err(|message| ConfigError {
+                        line: entry.line,
+                        message,
+                    })?;
+                city.timezone = Some(zone);
+            }
+            "home" => city.home = Some(config::coordinates(entry)?),
+            "near" => city.near = Some(config::string(entry)?),
+            "songkick_metro_id" => match entry.value {
+                config::Value::Integer(id) if id > 0 => city.songkick_metro_id = Some(id as u64),
+                _ => {
+                    return Err(ConfigError {
+                        line: entry.line,
+                        message: "songkick_metro_id should be a whole number".to_string(),
+                    })
+                }
+            },
+            "songkick_metro_slug" => city.songkick_metro_slug = Some(config::string(entry)?),
+            key => {
+                return Err(ConfigError {
+                    line: entry.line,
+                    message: format!("unknown setting '{}' in [[city]]", key),
+                })
+            }
+        }
+    }
+    if city.name.is_empty() {
+        return Err(ConfigError {
+            line: table.line,
+            message: "[[city]] needs a name".to_string(),
+        });
+    }
+    if city.label.is_empty() {
+        city.label = city.name.clone();
+    }
+    Ok(city)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sources::parse_sources;
+
+    fn city(text: &str) -> Result<City, ConfigError> {
+        let tables = config::parse_tables(text).unwrap();
+        parse_city(&tables[1])
+    }
+
+    #[test]
+    fn test_songkick_url_from_city_entry() {
+        let austin = city("[[city]]\nname = \"austin\"\nlabel = \"Austin\"\nsongkick_metro_id = 9179\nsongkick_metro_slug = \"us-austin\"").unwrap();
+
+        assert_eq!(
+            austin.expand("https://www.songkick.com/metro-areas/{songkick_metro_id}-{songkick_metro_slug}/tonight"),
+            Ok("https://www.songkick.com/metro-areas/9179-us-austin/tonight".to_string())
+        );
+        assert_eq!(
+            austin.expand("https://example.com/{city}/events"),
+            Ok("https://example.com/austin/events".to_string())
+        );
+        assert_eq!(
+            austin.expand("https://example.com/events"),
+            Ok("https://example.com/events".to_string())
+        );
+
+        let chicago = city("[[city]]\nname = \"chicago\"").unwrap();
+        assert_eq!(chicago.label, "chicago");
+        assert_eq!(
+            chicago.expand("https://www.songkick.com/metro-areas/{songkick_metro_id}-{songkick_metro_slug}/tonight"),
+            Err("city 'chicago' has no songkick_metro_id for {songkick_metro_id}".to_string())
+        );
+    }
+
+    #[test]
+    fn test_parse_city_errors() {
+        assert_eq!(
+            city("[[city]]\nlabel = \"Austin\"")
+                .unwrap_err()
+                .to_string(),
+            "line 1: [[city]] needs a name"
+        );
+        assert_eq!(
+            city("[[city]]\nname = \"austin\"\nsongkick_metro_id = \"9179\"")
+                .unwrap_err()
+                .line,
+            3
+        );
+        assert_eq!(
+            city("[[city]]\nname = \"austin\"\nstate = \"TX\"")
+                .unwrap_err()
+                .to_string(),
+            "line 3: unknown setting 'state' in [[city]]"
+        );
+        assert!(city("[[city]]\nname = \"x\"\ntimezone = \"Texas\"").is_err());
+    }
+
+    #[test]
+    fn test_city_fills_unset_settings() {
+        let austin = city("[[city]]\nname = \"austin\"\ntimezone = \"America/Chicago\"\nhome = [30.2672, -97.7431]\nnear = \"Austin, TX\"").unwrap();
+        let mut settings = Config {
+            near: Some("Round Rock, TX".to_string()),
+            ..Default::default()
+        };
+
+        austin.apply(&mut settings);
+
+        assert_eq!(settings.timezone, Some("America/Chicago".parse().unwrap()));
+        assert_eq!(
+            settings.home,
+            Some(Coordinates {
+                lat: 30.2672,
+                lng: -97.7431
+            })
+        );
+        assert_eq!(settings.near.as_deref(), Some("Round Rock, TX"));
+    }
+
+    #[test]
+    fn test_example_city_files() {
+        let examples = [
+            (
+                "austin",
+                include_str!("../cities/austin.toml"),
+                "https://www.songkick.com/metro-areas/9179-us-austin/tonight",
+            ),
+            (
+                "chicago",
+                include_str!("../cities/chicago.toml"),
+                "https://www.songkick.com/metro-areas/9426-us-chicago/tonight",
+            ),
+        ];
+        for (name, text, songkick) in examples {
+            let file = parse_sources(text).unwrap();
+            assert_eq!(file.errors, Vec::new(), "{}", name);
+            let city = file.city(Some(name)).unwrap();
+            let (sources, errors) = file.sources_for(city);
+            assert!(errors.is_empty(), "{}: {:?}", name, errors);
+            assert_eq!(sources[0].url, songkick, "{}", name);
+            assert!(city.unwrap().home.is_some(), "{}", name);
+        }
+    }
+}
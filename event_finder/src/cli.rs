@@ -0,0 +1,1842 @@
+//! # CLI Module
+//!
+//! This module parses the command-line flags that adjust how the interactive application runs.
+
+use chrono::NaiveDate;
+use std::io::{self, IsTerminal};
+use std::path::PathBuf;
+
+use event_finder::data_processing::{self, DatePreset, GroupKey, Match, Order, SortKey};
+use event_finder::email;
+use event_finder::geo::Distance;
+use event_finder::history::{HistoryQuery, Since};
+use event_finder::logging::ErrorFormat;
+use event_finder::output::{Format, Styler, DEFAULT_DESCRIPTION_LENGTH};
+use event_finder::prefs::Prefs;
+use event_finder::sources::Category;
+use event_finder::template::Template;
+use event_finder::timezone::Zone;
+use event_finder::webhook::WebhookFormat;
+
+use crate::completions::Shell;
+
+/// The default maximum number of detail pages fetched per source with `--enrich`.
+pub const DEFAULT_ENRICH_LIMIT: usize = 20;
+
+/// A subcommand, run once instead of the interactive menu.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Command {
+    /// List the events of these categories and exit: `music`, `unique`, `general`, or `all`.
+    List(Vec<Category>),
+    /// List the events in the fetch history matching `Options::history` and exit.
+    History,
+    /// List the events of a page read from stdin, parsed as the one `--source` and exit.
+    Parse,
+    /// Print the completion script for a shell and exit.
+    Completions(Shell),
+    /// Print the usage, of the named subcommand if there is one, and exit: `help`, `-h`, or
+    /// `--help`.
+    Help(Option<String>),
+}
+
+impl Command {
+    /// Parses a subcommand name: `history`, `parse`, or any spelling of a `Category`.
+    fn parse(name: &str) -> Result<Command, String> {
+        if name.eq_ignore_ascii_case("history") {
+            return Ok(Command::History);
+        }
+        if name.eq_ignore_ascii_case("parse") {
+            return Ok(Command::Parse);
+        }
+        name.parse::<Category>()
+            .map(|category| Command::List(category.members()))
+            .map_err(|_| format!("unknown command '{}' (expected music, unique, general, all, history, parse, or completions)", name))
+    }
+}
+
+/// Options collected from the command line.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Options {
+    /// The subcommand to run, or `None` for the interactive menu.
+    pub command: Option<Command>,
+    /// How the listing is written.
+    pub format: Format,
+    /// Write structured formats on one line instead of indented.
+    pub compact: bool,
+    /// The line each event is written as, instead of `format`.
+    pub template: Option<Template>,
+    /// End CSV rows in `\r\n` for Excel.
+    pub crlf: bool,
+    /// The file to write the listing to instead of stdout.
+    pub output: Option<PathBuf>,
+    /// Replace the `--output` file if it exists.
+    pub force: bool,
+    /// Never color the output, even on a terminal.
+    pub no_color: bool,
+    /// The sources file to read instead of the default one.
+    pub config: Option<PathBuf>,
+    /// The city whose sources are used, overriding the config file's.
+    pub city: Option<String>,
+    /// Write the built-in sources to the sources file and exit.
+    pub init_config: bool,
+    /// Leave out events listed by an earlier run.
+    pub new_only: bool,
+    /// Remember the fetched events as listed, without listing them, and exit.
+    pub mark_seen: bool,
+    /// Forget which events were listed, and exit.
+    pub reset_seen: bool,
+    /// Show a desktop notification about new events matching the user's interests.
+    pub notify: bool,
+    /// The URL to post the subcommand's listing to.
+    pub webhook: Option<String>,
+    /// The shape of the document posted to `webhook`.
+    pub webhook_format: WebhookFormat,
+    /// The address to email the subcommand's listing to.
+    pub email_to: Option<String>,
+    /// Serve events from snapshots and never touch the network.
+    pub offline: bool,
+    /// The maximum number of description characters printed per event.
+    pub description_length: usize,
+    /// Keep elements with an empty name instead of skipping them, to debug selectors.
+    pub keep_nameless: bool,
+    /// List every field that failed to extract instead of a per-source summary.
+    pub verbose: bool,
+    /// How many times `-v` was given, which picks the log level; see `logging::level_for`.
+    pub verbosity: u8,
+    /// Log only errors.
+    pub quiet: bool,
+    /// How errors and warnings are printed on stderr.
+    pub error_format: ErrorFormat,
+    /// Exit with `exit::PARTIAL` when some sources failed, instead of success.
+    pub strict: bool,
+    /// Fetch each event's detail page to fill in fields the listing left empty.
+    pub enrich: bool,
+    /// The maximum number of detail pages fetched per source when enriching.
+    pub enrich_limit: usize,
+    /// The maximum number of events parsed per source, or `None` for no limit.
+    pub max_events: Option<usize>,
+    /// Report what the named source's selectors match instead of running interactively.
+    pub debug_selectors: Option<String>,
+    /// Print what the run would fetch and how it would be parsed and listed, and exit.
+    pub dry_run: bool,
+    /// Leave out sold-out and cancelled events.
+    pub hide_sold_out: bool,
+    /// The strftime format displayed dates are shown in, or `None` to show them as the source
+    /// wrote them.
+    pub date_format: Option<String>,
+    /// What events are sorted by.
+    pub sort: SortKey,
+    /// Whether events are sorted descending instead of ascending.
+    pub order: Order,
+    /// A named range of days to show events for.
+    pub when: Option<DatePreset>,
+    /// The first day to show events for.
+    pub from: Option<NaiveDate>,
+    /// The last day to show events for.
+    pub to: Option<NaiveDate>,
+    /// Leave out events whose date couldn't be read when a date range is given.
+    pub strict_dates: bool,
+    /// Terms events must mention to be shown.
+    pub search: Vec<String>,
+    /// Whether all or any of the search terms must be mentioned.
+    pub search_mode: Match,
+    /// Words that leave out events mentioning them, on top of the config file's.
+    pub exclude: Vec<String>,
+    /// Match exclusions as whole words.
+    pub whole_word: bool,
+    /// How similar names must be, from 0 to 1, for listings from different sources to be merged.
+    pub merge_threshold: f64,
+    /// Keep events that have already ended.
+    pub include_past: bool,
+    /// How long an event without an end is assumed to last, in minutes.
+    pub assumed_duration: i64,
+    /// The home timezone, overriding the config file's.
+    pub timezone: Option<Zone>,
+    /// What events are grouped under headers by, or `None` for a flat list.
+    pub group_by: Option<GroupKey>,
+    /// The most days an event may span and still be listed on each day by date ranges and
+    /// grouping by date.
+    pub max_span: usize,
+    /// The names of the only sources to fetch, or empty for every source.
+    pub sources: Vec<String>,
+    /// The maximum number of events listed, or `None` for no limit.
+    pub limit: Option<usize>,
+    /// The width `--format table` is drawn for, instead of the terminal's.
+    pub width: Option<usize>,
+    /// The number of the listed event whose page is opened in the browser afterwards.
+    pub open: Option<usize>,
+    /// What the `history` subcommand looks for.
+    pub history: HistoryQuery,
+    /// Refetch and list the events again every this often, until the user quits.
+    pub watch: Option<chrono::Duration>,
+    /// Show one randomly picked event instead of the whole listing.
+    pub random: bool,
+    /// Weight random picks towards events starting soonest.
+    pub soonest: bool,
+    /// The seed for random picks, or `None` for a different pick every run.
+    pub seed: Option<u64>,
+    /// Leave out events lasting longer than this.
+    pub max_duration: Option<chrono::Duration>,
+    /// With `max_duration`, also leave out events whose duration isn't known.
+    pub strict_duration: bool,
+    /// Show only free events.
+    pub free: bool,
+    /// With `free`, also show events whose price isn't known.
+    pub free_or_unknown: bool,
+    /// Leave out events further from home than this.
+    pub within: Option<Distance>,
+    /// Look up venues missing from the config file and the cache with Nominatim.
+    pub geocode: bool,
+    /// Neither use nor remember the menu's preferences.
+    pub no_prefs: bool,
+    /// The flags given whose values remembered preferences don't replace, e.g. `format`.
+    pub given: Vec<&'static str>,
+    /// The categories an empty menu choice fetches, from the remembered preferences.
+    pub menu_default: Vec<Category>,
+}
+
+impl Default for Options {
+    fn default() -> Self {
+        Options {
+            command: None,
+            format: Format::default(),
+            compact: false,
+            template: None,
+            crlf: false,
+            output: None,
+            force: false,
+            no_color: false,
+            config: None,
+            city: None,
+            init_config: false,
+            new_only: false,
+            mark_seen: false,
+            reset_seen: false,
+            notify: false,
+            webhook: None,
+            webhook_format: WebhookFormat::default(),
+            email_to: None,
+            offline: false,
+            description_length: DEFAULT_DESCRIPTION_LENGTH,
+            keep_nameless: false,
+            verbose: false,
+            verbosity: 0,
+            quiet: false,
+            error_format: ErrorFormat::default(),
+            strict: false,
+            enrich: false,
+            enrich_limit: DEFAULT_ENRICH_LIMIT,
+            max_events: None,
+            debug_selectors: None,
+            dry_run: false,
+            hide_sold_out: false,
+            date_format: None,
+            sort: SortKey::Start,
+            order: Order::Ascending,
+            when: None,
+            from: None,
+            to: None,
+            strict_dates: false,
+            search: Vec::new(),
+            search_mode: Match::All,
+            exclude: Vec::new(),
+            whole_word: false,
+            merge_threshold: data_processing::DEFAULT_MERGE_THRESHOLD,
+            include_past: false,
+            assumed_duration: data_processing::DEFAULT_ASSUMED_DURATION_MINUTES,
+            timezone: None,
+            group_by: None,
+            max_span: data_processing::DEFAULT_MAX_SPAN_DAYS,
+            sources: Vec::new(),
+            limit: None,
+            width: None,
+            open: None,
+            history: HistoryQuery::default(),
+            watch: None,
+            random: false,
+            soonest: false,
+            seed: None,
+            max_duration: None,
+            strict_duration: false,
+            free: false,
+            free_or_unknown: false,
+            within: None,
+            geocode: false,
+            no_prefs: false,
+            given: Vec::new(),
+            menu_default: Vec::new(),
+        }
+    }
+}
+
+impl Options {
+    /// Whether stdout gets only the listing, as data or a document, so status lines belong on
+    /// stderr.
+    pub fn writes_data(&self) -> bool {
+        self.format.is_structured() || self.template.is_some()
+    }
+
+    /// Returns the categories the subcommand names, or every one without a subcommand.
+    pub fn categories(&self) -> Vec<Category> {
+        match &self.command {
+            Some(Command::List(categories)) => categories.clone(),
+            Some(Command::History)
+            | Some(Command::Parse)
+            | Some(Command::Completions(_))
+            | Some(Command::Help(_))
+            | None => Category::ALL.to_vec(),
+        }
+    }
+
+    /// Whether the run is a menu session, which starts from the remembered preferences and
+    /// remembers its choices, unless `--no-prefs` is given. `--watch` has its own loop and
+    /// keeps to the command line.
+    pub fn remembers_prefs(&self) -> bool {
+        self.command.is_none() && self.watch.is_none() && !self.no_prefs
+    }
+
+    /// Fills in the remembered `--format`, `--limit`, and `--group-by` the command line left
+    /// out, and the categories an empty menu choice fetches.
+    pub fn apply_prefs(&mut self, prefs: &Prefs) {
+        if let Some(format) = prefs.format.filter(|_| !self.given.contains(&"format")) {
+            self.format = format;
+        }
+        self.limit = self.limit.or(prefs.limit);
+        if !self.given.contains(&"group-by") {
+            self.group_by = self.group_by.or(prefs.group_by);
+        }
+        self.menu_default = prefs.categories.clone();
+    }
+
+    /// Returns the preferences a menu session leaves for the next one, after choosing
+    /// `categories`.
+    pub fn prefs(&self, categories: &[Category]) -> Prefs {
+        Prefs {
+            categories: categories.to_vec(),
+            format: Some(self.format),
+            limit: self.limit,
+            group_by: self.group_by,
+        }
+    }
+
+    /// Returns the styler for the listing and status lines: colored on a terminal, unless
+    /// `--no-color` or `$NO_COLOR` is set, and plain when stdout is a file, a pipe, or data.
+    pub fn styler(&self) -> Styler {
+        let on_terminal = if self.writes_data() {
+            io::stderr().is_terminal()
+        } else {
+            io::stdout().is_terminal()
+        };
+        Styler::detect(on_terminal, self.no_color)
+    }
+
+    /// Returns the styler for listing events, which is plain when they go to `--output`.
+    pub fn listing_styler(&self) -> Styler {
+        if self.output.is_some() || self.writes_data() {
+            Styler::plain()
+        } else {
+            self.styler()
+        }
+    }
+
+    /// Returns the styler for errors and warnings, which always go to stderr.
+    pub fn error_styler(&self) -> Styler {
+        Styler::detect(io::stderr().is_terminal(), self.no_color)
+    }
+
+    /// Returns the days events are shown for, both inclusive, or `None` to show every event.
+    ///
+    /// `--from` without `--to` (or the reverse) leaves the other end open.
+    ///
+    /// # Arguments
+    ///
+    /// * `today` - The day `--when` presets are relative to.
+    pub fn date_range(&self, today: NaiveDate) -> Option<(NaiveDate, NaiveDate)> {
+        match (self.when, self.from, self.to) {
+            (Some(preset), _, _) => Some(preset.range(today)),
+            (None, None, None) => None,
+            (None, from, to) => {
+                Some((from.unwrap_or(NaiveDate::MIN), to.unwrap_or(NaiveDate::MAX)))
+            }
+        }
+    }
+}
+
+/// What a flag takes after it, for shell completion.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FlagValue {
+    /// Nothing: the flag is a switch.
+    None,
+    /// Free text, such as a number, a date, or a URL.
+    Text,
+    /// A path.
+    File,
+    /// One of these words.
+    Choices(&'static [&'static str]),
+    /// The name of a source in the sources file.
+    Source,
+    /// The name of a `[[city]]` in the sources file.
+    City,
+    /// The name of a known timezone.
+    Zone,
+}
+
+/// A flag `parse_args` accepts, as shell completion offers it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Flag {
+    /// The long name, without the leading `--`.
+    pub name: &'static str,
+    /// The one-letter name, without the leading `-`.
+    pub short: Option<char>,
+    /// What the flag takes after it.
+    pub value: FlagValue,
+    /// A one-line description.
+    pub help: &'static str,
+}
+
+/// The subcommands, with a one-line description each.
+pub const COMMANDS: &[(&str, &str)] = &[
+    ("music", "List music events"),
+    ("unique", "List unique events"),
+    ("general", "List general events"),
+    ("all", "List events of every type"),
+    ("history", "List events from past fetches"),
+    ("parse", "List the events of a page piped to stdin"),
+    ("completions", "Print a shell completion script"),
+    ("help", "Print this help, or a command's"),
+];
+
+/// Every flag `parse_args` accepts; a test checks the two agree.
+pub const FLAGS: &[Flag] = &[
+    Flag {
+        name: "offline",
+        short: None,
+        value: FlagValue::None,
+        help: "Serve events from snapshots without the network",
+    },
+    Flag {
+        name: "keep-nameless",
+        short: None,
+        value: FlagValue::None,
+        help: "Keep matched elements without a name",
+    },
+    Flag {
+        name: "verbose",
+        short: Some('v'),
+        value: FlagValue::None,
+        help: "Log more; repeat for more detail",
+    },
+    Flag {
+        name: "quiet",
+        short: Some('q'),
+        value: FlagValue::None,
+        help: "Log only errors",
+    },
+    Flag {
+        name: "error-format",
+        short: None,
+        value: FlagValue::Choices(&["text", "json"]),
+        help: "How errors and warnings are printed",
+    },
+    Flag {
+        name: "strict",
+        short: None,
+        value: FlagValue::None,
+        help: "Fail when some sources fail",
+    },
+    Flag {
+        name: "enrich",
+        short: None,
+        value: FlagValue::None,
+        help: "Fill in missing fields from detail pages",
+    },
+    Flag {
+        name: "enrich-limit",
+        short: None,
+        value: FlagValue::Text,
+        help: "Detail pages fetched per source with --enrich",
+    },
+    Flag {
+        name: "hide-sold-out",
+        short: None,
+        value: FlagValue::None,
+        help: "Leave out sold-out and cancelled events",
+    },
+    Flag {
+        name: "format",
+        short: None,
+        value: FlagValue::Choices(&["plain", "table", "json", "csv", "ics", "markdown", "html"]),
+        help: "How the listing is written",
+    },
+    Flag {
+        name: "compact",
+        short: None,
+        value: FlagValue::None,
+        help: "Write JSON on one line",
+    },
+    Flag {
+        name: "template",
+        short: None,
+        value: FlagValue::Text,
+        help: "Write each event as a line filling in {field}s",
+    },
+    Flag {
+        name: "crlf",
+        short: None,
+        value: FlagValue::None,
+        help: "End CSV rows in CRLF",
+    },
+    Flag {
+        name: "output",
+        short: None,
+        value: FlagValue::File,
+        help: "Write the listing to a file",
+    },
+    Flag {
+        name: "force",
+        short: None,
+        value: FlagValue::None,
+        help: "Replace an existing --output or sources file",
+    },
+    Flag {
+        name: "no-color",
+        short: None,
+        value: FlagValue::None,
+        help: "Never color the output",
+    },
+    Flag {
+        name: "config",
+        short: None,
+        value: FlagValue::File,
+        help: "The sources file to read",
+    },
+    Flag {
+        name: "config-file",
+        short: None,
+        value: FlagValue::File,
+        help: "The sources file to read, like --config",
+    },
+    Flag {
+        name: "init-config",
+        short: None,
+        value: FlagValue::None,
+        help: "Write the built-in sources to the sources file",
+    },
+    Flag {
+        name: "new-only",
+        short: None,
+        value: FlagValue::None,
+        help: "Leave out events listed before",
+    },
+    Flag {
+        name: "mark-seen",
+        short: None,
+        value: FlagValue::None,
+        help: "Remember the fetched events without listing them",
+    },
+    Flag {
+        name: "reset-seen",
+        short: None,
+        value: FlagValue::None,
+        help: "Forget which events were listed",
+    },
+    Flag {
+        name: "notify",
+        short: None,
+        value: FlagValue::None,
+        help: "Notify about new events matching your interests",
+    },
+    Flag {
+        name: "webhook",
+        short: None,
+        value: FlagValue::Text,
+        help: "Post the listing to a webhook URL",
+    },
+    Flag {
+        name: "webhook-format",
+        short: None,
+        value: FlagValue::Choices(&["json", "slack"]),
+        help: "The shape of the webhook post",
+    },
+    Flag {
+        name: "email-to",
+        short: None,
+        value: FlagValue::Text,
+        help: "Email the listing to an address",
+    },
+    Flag {
+        name: "city",
+        short: None,
+        value: FlagValue::City,
+        help: "The city whose sources are used",
+    },
+    Flag {
+        name: "max-span",
+        short: None,
+        value: FlagValue::Text,
+        help: "The most days a multi-day event is listed on",
+    },
+    Flag {
+        name: "description-length",
+        short: None,
+        value: FlagValue::Text,
+        help: "The description characters shown per event",
+    },
+    Flag {
+        name: "width",
+        short: None,
+        value: FlagValue::Text,
+        help: "The width of --format table",
+    },
+    Flag {
+        name: "open",
+        short: None,
+        value: FlagValue::Text,
+        help: "Open the listed event with this number",
+    },
+    Flag {
+        name: "venue",
+        short: None,
+        value: FlagValue::Text,
+        help: "Show history events at a venue",
+    },
+    Flag {
+        name: "seen-since",
+        short: None,
+        value: FlagValue::Text,
+        help: "Show history events seen since a date or span",
+    },
+    Flag {
+        name: "first-seen-since",
+        short: None,
+        value: FlagValue::Text,
+        help: "Show history events first seen since a date or span",
+    },
+    Flag {
+        name: "watch",
+        short: None,
+        value: FlagValue::Text,
+        help: "List again every interval, such as 10m",
+    },
+    Flag {
+        name: "limit",
+        short: None,
+        value: FlagValue::Text,
+        help: "List at most this many events",
+    },
+    Flag {
+        name: "max-events",
+        short: None,
+        value: FlagValue::Text,
+        help: "Parse at most this many events per source",
+    },
+    Flag {
+        name: "sort",
+        short: None,
+        value: FlagValue::Choices(&["date", "name", "location", "score"]),
+        help: "What events are sorted by",
+    },
+    Flag {
+        name: "desc",
+        short: None,
+        value: FlagValue::None,
+        help: "Sort descending",
+    },
+    Flag {
+        name: "group-by",
+        short: None,
+        value: FlagValue::Choices(&["date", "venue", "source", "none"]),
+        help: "List events under a header per group",
+    },
+    Flag {
+        name: "when",
+        short: None,
+        value: FlagValue::Choices(&["today", "tomorrow", "weekend", "week"]),
+        help: "Show events on a range of days",
+    },
+    Flag {
+        name: "from",
+        short: None,
+        value: FlagValue::Text,
+        help: "The first day to show, YYYY-MM-DD",
+    },
+    Flag {
+        name: "to",
+        short: None,
+        value: FlagValue::Text,
+        help: "The last day to show, YYYY-MM-DD",
+    },
+    Flag {
+        name: "strict-dates",
+        short: None,
+        value: FlagValue::None,
+        help: "Leave out events whose date could not be read",
+    },
+    Flag {
+        name: "exclude",
+        short: None,
+        value: FlagValue::Text,
+        help: "Leave out events mentioning a word",
+    },
+    Flag {
+        name: "source",
+        short: None,
+        value: FlagValue::Source,
+        help: "Fetch only this source",
+    },
+    Flag {
+        name: "whole-word",
+        short: None,
+        value: FlagValue::None,
+        help: "Match exclusions as whole words",
+    },
+    Flag {
+        name: "include-past",
+        short: None,
+        value: FlagValue::None,
+        help: "Keep events that have ended",
+    },
+    Flag {
+        name: "max-duration",
+        short: None,
+        value: FlagValue::Text,
+        help: "Leave out events lasting longer, such as 3h",
+    },
+    Flag {
+        name: "strict-duration",
+        short: None,
+        value: FlagValue::None,
+        help: "With --max-duration, leave out events of unknown length",
+    },
+    Flag {
+        name: "free",
+        short: None,
+        value: FlagValue::None,
+        help: "Show only free events",
+    },
+    Flag {
+        name: "free-or-unknown",
+        short: None,
+        value: FlagValue::None,
+        help: "Show free events and those without a price",
+    },
+    Flag {
+        name: "within",
+        short: None,
+        value: FlagValue::Text,
+        help: "Leave out events further from home, such as 5mi",
+    },
+    Flag {
+        name: "geocode",
+        short: None,
+        value: FlagValue::None,
+        help: "Look up venues whose location is not known",
+    },
+    Flag {
+        name: "random",
+        short: None,
+        value: FlagValue::None,
+        help: "Show one random event",
+    },
+    Flag {
+        name: "soonest",
+        short: None,
+        value: FlagValue::None,
+        help: "Pick random events starting soon more often",
+    },
+    Flag {
+        name: "seed",
+        short: None,
+        value: FlagValue::Text,
+        help: "The seed for --random",
+    },
+    Flag {
+        name: "timezone",
+        short: None,
+        value: FlagValue::Zone,
+        help: "The home timezone",
+    },
+    Flag {
+        name: "assumed-duration",
+        short: None,
+        value: FlagValue::Text,
+        help: "Minutes an event without an end lasts",
+    },
+    Flag {
+        name: "merge-threshold",
+        short: None,
+        value: FlagValue::Text,
+        help: "How alike names must be to merge, 0 to 1",
+    },
+    Flag {
+        name: "search",
+        short: None,
+        value: FlagValue::Text,
+        help: "Show events mentioning every term",
+    },
+    Flag {
+        name: "search-any",
+        short: None,
+        value: FlagValue::Text,
+        help: "Show events mentioning any term",
+    },
+    Flag {
+        name: "date-format",
+        short: None,
+        value: FlagValue::Choices(&["iso", "us", "eu", "compact"]),
+        help: "Show dates in a strftime format or preset",
+    },
+    Flag {
+        name: "debug-selectors",
+        short: None,
+        value: FlagValue::Source,
+        help: "Report what a source's selectors match",
+    },
+    Flag {
+        name: "dry-run",
+        short: None,
+        value: FlagValue::None,
+        help: "Show what would be fetched and how, without fetching",
+    },
+    Flag {
+        name: "no-prefs",
+        short: None,
+        value: FlagValue::None,
+        help: "Don't use or remember the menu's choices",
+    },
+    Flag {
+        name: "help",
+        short: Some('h'),
+        value: FlagValue::None,
+        help: "Print this help, or a command's",
+    },
+];
+
+/// Flags only the history subcommand takes.
+const HISTORY_FLAGS: &[&str] = &["venue", "seen-since", "first-seen-since"];
+
+/// Whether `help` lists a flag for a subcommand, per the combinations `parse_args` rejects.
+fn takes(command: &str, flag: &str) -> bool {
+    let listing_only = [
+        "watch",
+        "mark-seen",
+        "new-only",
+        "random",
+        "dry-run",
+        "webhook",
+        "webhook-format",
+        "email-to",
+    ];
+    match command {
+        "history" => !listing_only.contains(&flag),
+        "parse" => {
+            !HISTORY_FLAGS.contains(&flag)
+                && !listing_only.contains(&flag)
+                && !["enrich", "enrich-limit", "geocode"].contains(&flag)
+        }
+        "completions" => ["config", "config-file", "help"].contains(&flag),
+        _ => !HISTORY_FLAGS.contains(&flag),
+    }
+}
+
+/// Writes the usage `help`, `-h`, and `--help` print.
+///
+/// # Arguments
+///
+/// * `command` - The subcommand to describe, or `None` for the program.
+///
+/// # Returns
+///
+/// The subcommands and every flag, or how the subcommand is run and the flags it takes.
+pub fn usage(command: Option<&str>) -> String {
+    // The subcommand's own name, however it was spelled; `help` describes the program
+    let topic = command.and_then(|name| match Command::parse(name) {
+        Ok(Command::History) => Some("history".to_string()),
+        Ok(Command::Parse) => Some("parse".to_string()),
+        Ok(_) => name
+            .parse::<Category>()
+            .ok()
+            .map(|category| category.to_string()),
+        Err(_) if name.eq_ignore_ascii_case("completions") => Some("completions".to_string()),
+        Err(_) => None,
+    });
+    let mut out = match topic.as_deref() {
+        None => {
+            let mut out = "Usage: event_finder [COMMAND] [FLAGS]\n\nWithout a command, shows the interactive menu.\n\nCommands:\n".to_string();
+            let width = COMMANDS
+                .iter()
+                .map(|(name, _)| name.len())
+                .max()
+                .unwrap_or(0);
+            for (name, help) in COMMANDS {
+                out.push_str(&format!("  {:width$}  {}\n", name, help, width = width));
+            }
+            out
+        }
+        Some("completions") => {
+            "Usage: event_finder completions <bash|zsh|fish|powershell>\n".to_string()
+        }
+        Some("parse") => "Usage: event_finder parse --source NAME [FLAGS] < PAGE\n".to_string(),
+        Some(name) => format!("Usage: event_finder {} [FLAGS]\n", name),
+    };
+    if let Some(name) = &topic {
+        let about = COMMANDS
+            .iter()
+            .find(|(command, _)| command == name)
+            .map_or("", |(_, help)| help);
+        out.push_str(&format!("\n{}.\n", about));
+    }
+    let flags: Vec<(String, &str)> = FLAGS
+        .iter()
+        .filter(|flag| topic.as_deref().is_none_or(|name| takes(name, flag.name)))
+        .map(|flag| {
+            let short = flag
+                .short
+                .map(|c| format!("-{}, ", c))
+                .unwrap_or_else(|| "    ".to_string());
+            let value = match flag.value {
+                FlagValue::None => "",
+                FlagValue::Text => " <VALUE>",
+                FlagValue::File => " <FILE>",
+                FlagValue::Choices(_) => " <CHOICE>",
+                FlagValue::Source => " <SOURCE>",
+                FlagValue::City => " <CITY>",
+                FlagValue::Zone => " <ZONE>",
+            };
+            (format!("{}--{}{}", short, flag.name, value), flag.help)
+        })
+        .collect();
+    let width = flags.iter().map(|(flag, _)| flag.len()).max().unwrap_or(0);
+    out.push_str("\nFlags:\n");
+    for (flag, help) in &flags {
+        out.push_str(&format!("  {:width$}  {}\n", flag, help, width = width));
+    }
+    if topic.is_none() {
+        out.push_str("\nRun `event_finder help COMMAND` for the flags a command takes.\n");
+    }
+    out
+}
+
+/// Parses a `--from`/`--to` day written as `YYYY-MM-DD`.
+fn parse_day(flag: &str, value: &str) -> Result<NaiveDate, String> {
+    NaiveDate::parse_from_str(value, "%Y-%m-%d")
+        .map_err(|_| format!("invalid {} '{}' (expected YYYY-MM-DD)", flag, value))
+}
+
+/// Parses command-line arguments (excluding the program name) into `Options`.
+///
+/// # Arguments
+///
+/// * `args` - The arguments to parse.
+///
+/// # Returns
+///
+/// The parsed `Options`, or a message describing the first invalid argument.
+pub fn parse_args<I: IntoIterator<Item = String>>(args: I) -> Result<Options, String> {
+    let mut options = Options::default();
+    let mut args = args.into_iter();
+    // The subcommand as written, and whether its usage is asked for instead
+    let mut named: Option<String> = None;
+    let mut help = false;
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--offline" => options.offline = true,
+            "--keep-nameless" => options.keep_nameless = true,
+            "--verbose" => {
+                options.verbose = true;
+                options.verbosity = options.verbosity.saturating_add(1);
+            }
+            // -v, -vv, -vvv
+            flag if flag.len() > 1
+                && flag.starts_with('-')
+                && flag[1..].bytes().all(|b| b == b'v') =>
+            {
+                options.verbose = true;
+                options.verbosity = options.verbosity.saturating_add((flag.len() - 1) as u8);
+            }
+            "-q" | "--quiet" => options.quiet = true,
+            "--error-format" => {
+                let value = args.next().ok_or("--error-format needs a value")?;
+                options.error_format = value.parse()?;
+            }
+            "--strict" => options.strict = true,
+            "--enrich" => options.enrich = true,
+            "--hide-sold-out" => options.hide_sold_out = true,
+            "--enrich-limit" => {
+                let value = args.next().ok_or("--enrich-limit needs a value")?;
+                options.enrich_limit = value
+                    .parse()
+                    .map_err(|_| format!("invalid --enrich-limit '{}'", value))?;
+            }
+            "--format" => {
+                let value = args.next().ok_or("--format needs a value")?;
+                options.format = value.parse()?;
+                options.given.push("format");
+            }
+            "--compact" => options.compact = true,
+            "--template" => {
+                let value = args.next().ok_or("--template needs a value")?;
+                options.template = Some(value.parse().map_err(|e| format!("--template: {}", e))?);
+            }
+            "--crlf" => options.crlf = true,
+            "--force" => options.force = true,
+            "--no-color" => options.no_color = true,
+            "--output" => {
+                options.output = Some(PathBuf::from(args.next().ok_or("--output needs a path")?))
+            }
+            // `--config-file` for those who expect the long name
+            "--config" | "--config-file" => {
+                options.config = Some(PathBuf::from(args.next().ok_or("--config needs a path")?))
+            }
+            "--init-config" => options.init_config = true,
+            "--new-only" => options.new_only = true,
+            "--mark-seen" => options.mark_seen = true,
+            "--reset-seen" => options.reset_seen = true,
+            "--notify" => options.notify = true,
+            "--webhook" => options.webhook = Some(args.next().ok_or("--webhook needs a URL")?),
+            "--email-to" => {
+                let value = args.next().ok_or("--email-to needs an address")?;
+                if !value.contains('@') || email::check_address(&value).is_err() {
+                    return Err(format!(
+                        "invalid --email-to '{}' (expected an email address)",
+                        value
+                    ));
+                }
+                options.email_to = Some(value);
+            }
+            "--webhook-format" => {
+                let value = args.next().ok_or("--webhook-format needs a value")?;
+                options.webhook_format = value.parse()?;
+            }
+            "--city" => options.city = Some(args.next().ok_or("--city needs a city name")?),
+            "--max-span" => {
+                let value = args.next().ok_or("--max-span needs a value")?;
+                options.max_span =
+                    value.parse().ok().filter(|&days| days > 0).ok_or_else(|| {
+                        format!("invalid --max-span '{}' (expected a number of days)", value)
+                    })?;
+            }
+            "--description-length" => {
+                let value = args.next().ok_or("--description-length needs a value")?;
+                options.description_length = value
+                    .parse()
+                    .map_err(|_| format!("invalid --description-length '{}'", value))?;
+            }
+            "--width" => {
+                let value = args.next().ok_or("--width needs a value")?;
+                options.width = Some(
+                    value
+                        .parse()
+                        .ok()
+                        .filter(|width| *width > 0)
+                        .ok_or_else(|| format!("invalid --width '{}'", value))?,
+                );
+            }
+            "--open" => {
+                let value = args.next().ok_or("--open needs an event number")?;
+                options.open = Some(value.parse().ok().filter(|number| *number > 0).ok_or_else(
+                    || format!("invalid --open '{}' (expected an event's number)", value),
+                )?);
+            }
+            "--venue" => {
+                options.history.venue = Some(args.next().ok_or("--venue needs a venue name")?)
+            }
+            "--seen-since" | "--first-seen-since" => {
+                let value = args
+                    .next()
+                    .ok_or_else(|| format!("{} needs a date or a span such as 7d", arg))?;
+                let since = Since::parse(&value).map_err(|e| format!("{}: {}", arg, e))?;
+                if arg == "--seen-since" {
+                    options.history.seen_since = Some(since);
+                } else {
+                    options.history.first_seen_since = Some(since);
+                }
+            }
+            "--watch" => {
+                let value = args.next().ok_or("--watch needs an interval")?;
+                options.watch = Some(
+                    data_processing::parse_duration(&value)
+                        .map_err(|e| format!("--watch: {}", e))?,
+                );
+            }
+            "--limit" => {
+                let value = args.next().ok_or("--limit needs a value")?;
+                options.limit = Some(
+                    value
+                        .parse()
+                        .ok()
+                        .filter(|limit| *limit > 0)
+                        .ok_or_else(|| format!("invalid --limit '{}'", value))?,
+                );
+            }
+            "--max-events" => {
+                let value = args.next().ok_or("--max-events needs a value")?;
+                options.max_events = Some(
+                    value
+                        .parse()
+                        .map_err(|_| format!("invalid --max-events '{}'", value))?,
+                );
+            }
+            "--sort" => {
+                let value = args.next().ok_or("--sort needs a value")?;
+                options.sort = value.parse()?;
+            }
+            "--desc" => options.order = Order::Descending,
+            "--group-by" => {
+                let value = args.next().ok_or("--group-by needs a value")?;
+                options.group_by = if value.eq_ignore_ascii_case("none") {
+                    None
+                } else {
+                    Some(value.parse()?)
+                };
+                options.given.push("group-by");
+            }
+            "--when" => {
+                let value = args.next().ok_or("--when needs a value")?;
+                options.when = Some(value.parse()?);
+            }
+            "--from" => {
+                let value = args.next().ok_or("--from needs a date")?;
+                options.from = Some(parse_day("--from", &value)?);
+            }
+            "--to" => {
+                let value = args.next().ok_or("--to needs a date")?;
+                options.to = Some(parse_day("--to", &value)?);
+            }
+            "--strict-dates" => options.strict_dates = true,
+            "--exclude" => options
+                .exclude
+                .push(args.next().ok_or("--exclude needs a value")?),
+            "--source" => options
+                .sources
+                .push(args.next().ok_or("--source needs a source name")?),
+            "--whole-word" => options.whole_word = true,
+            "--include-past" => options.include_past = true,
+            "--max-duration" => {
+                let value = args.next().ok_or("--max-duration needs a value")?;
+                options.max_duration = Some(
+                    data_processing::parse_duration(&value)
+                        .map_err(|e| format!("--max-duration: {}", e))?,
+                );
+            }
+            "--strict-duration" => options.strict_duration = true,
+            "--free" => options.free = true,
+            "--within" => {
+                let value = args.next().ok_or("--within needs a distance")?;
+                options.within = Some(value.parse().map_err(|e| format!("--within: {}", e))?);
+            }
+            "--geocode" => options.geocode = true,
+            "--free-or-unknown" => {
+                options.free = true;
+                options.free_or_unknown = true;
+            }
+            "--random" => options.random = true,
+            "--soonest" => options.soonest = true,
+            "--seed" => {
+                let value = args.next().ok_or("--seed needs a value")?;
+                options.seed = Some(
+                    value
+                        .parse()
+                        .map_err(|_| format!("invalid --seed '{}'", value))?,
+                );
+            }
+            "--timezone" => {
+                let value = args.next().ok_or("--timezone needs a value")?;
+                options.timezone = Some(value.parse()?);
+            }
+            "--assumed-duration" => {
+                let value = args.next().ok_or("--assumed-duration needs a value")?;
+                options.assumed_duration = value
+                    .parse()
+                    .ok()
+                    .filter(|minutes| *minutes > 0)
+                    .ok_or_else(|| {
+                        format!("invalid --assumed-duration '{}' (expected minutes)", value)
+                    })?;
+            }
+            "--merge-threshold" => {
+                let value = args.next().ok_or("--merge-threshold needs a value")?;
+                options.merge_threshold = value
+                    .parse()
+                    .ok()
+                    .filter(|t| (0.0..=1.0).contains(t))
+                    .ok_or_else(|| {
+                        format!(
+                            "invalid --merge-threshold '{}' (expected a number from 0 to 1)",
+                            value
+                        )
+                    })?;
+            }
+            "--search" | "--search-any" => {
+                let value = args
+                    .next()
+                    .ok_or_else(|| format!("{} needs a value", arg))?;
+                options
+                    .search
+                    .extend(value.split_whitespace().map(str::to_string));
+                if arg == "--search-any" {
+                    options.search_mode = Match::Any;
+                }
+            }
+            "--date-format" => {
+                let value = args.next().ok_or("--date-format needs a value")?;
+                options.date_format = Some(data_processing::resolve_date_format(&value)?);
+            }
+            "--dry-run" => options.dry_run = true,
+            "--no-prefs" => options.no_prefs = true,
+            "--debug-selectors" => {
+                options.debug_selectors =
+                    Some(args.next().ok_or("--debug-selectors needs a source name")?);
+            }
+            "-h" | "--help" => help = true,
+            "help" if named.is_none() => help = true,
+            "completions" if named.is_none() => {
+                named = Some(arg.clone());
+                match args.next() {
+                    Some(flag) if flag == "-h" || flag == "--help" => help = true,
+                    None if help => {}
+                    shell => {
+                        let shell = shell
+                            .ok_or("completions needs a shell: bash, zsh, fish, or powershell")?;
+                        options.command = Some(Command::Completions(shell.parse()?));
+                    }
+                }
+            }
+            name if !name.starts_with('-') && named.is_none() => {
+                options.command = Some(Command::parse(name)?);
+                named = Some(arg.clone());
+            }
+            other => return Err(format!("unknown argument '{}'", other)),
+        }
+    }
+    if help {
+        // Usage is printed however the rest of the line would have combined
+        return Ok(Options {
+            command: Some(Command::Help(named)),
+            ..Options::default()
+        });
+    }
+    if options.when.is_some() && (options.from.is_some() || options.to.is_some()) {
+        return Err("--when can't be combined with --from or --to".to_string());
+    }
+    if let (Some(from), Some(to)) = (options.from, options.to) {
+        if from > to {
+            return Err(format!("--from {} is after --to {}", from, to));
+        }
+    }
+    if options.command == Some(Command::History) {
+        if options.watch.is_some() || options.mark_seen || options.random || options.new_only {
+            return Err("history lists past fetches and can't be combined with --watch, --mark-seen, --new-only, or --random".to_string());
+        }
+    } else if !options.history.is_empty() {
+        return Err(
+            "--venue, --seen-since, and --first-seen-since only apply to the history subcommand"
+                .to_string(),
+        );
+    }
+    if options.command == Some(Command::Parse) {
+        if options.sources.len() != 1 {
+            return Err("parse reads the page on stdin as one source, so it needs exactly one --source NAME".to_string());
+        }
+        if options.watch.is_some()
+            || options.mark_seen
+            || options.new_only
+            || options.enrich
+            || options.geocode
+        {
+            return Err("parse lists the piped page without the network or the seen events, so it can't be combined with --watch, --mark-seen, --new-only, --enrich, or --geocode".to_string());
+        }
+    }
+    if options.dry_run {
+        let runs = matches!(options.command, None | Some(Command::List(_)));
+        if !runs || options.init_config || options.reset_seen || options.debug_selectors.is_some() {
+            return Err("--dry-run shows what the menu or music, unique, general, or all would fetch, so it can't be combined with history, parse, --init-config, --reset-seen, or --debug-selectors".to_string());
+        }
+    }
+    if options.watch.is_some() {
+        if options.writes_data() || options.output.is_some() {
+            return Err("--watch lists events on the terminal, so it can't be combined with --template, --output, or a --format other than plain or table".to_string());
+        }
+        if options.random || options.open.is_some() {
+            return Err("--watch can't be combined with --random or --open".to_string());
+        }
+    }
+    if options.webhook.is_some() {
+        if !matches!(options.command, Some(Command::List(_)))
+            || options.watch.is_some()
+            || options.random
+        {
+            return Err("--webhook posts a subcommand's listing, so it needs music, unique, general, or all, and can't be combined with --watch or --random".to_string());
+        }
+        if options.offline {
+            return Err(
+                "--webhook needs network access, so it can't be combined with --offline"
+                    .to_string(),
+            );
+        }
+    }
+    if options.email_to.is_some() {
+        if !matches!(options.command, Some(Command::List(_)))
+            || options.watch.is_some()
+            || options.random
+        {
+            return Err("--email-to sends a subcommand's listing, so it needs music, unique, general, or all, and can't be combined with --watch or --random".to_string());
+        }
+        if options.offline {
+            return Err(
+                "--email-to needs network access, so it can't be combined with --offline"
+                    .to_string(),
+            );
+        }
+    }
+    Ok(options)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(args: &[&str]) -> Result<Options, String> {
+        parse_args(args.iter().map(|a| a.to_string()))
+    }
+
+    #[test]
+    fn test_parse_args_defaults() {
+        assert_eq!(parse(&[]), Ok(Options::default()));
+    }
+
+    #[test]
+    fn test_help_names_the_subcommand_it_is_about() {
+        let help = |args: &[&str]| parse(args).map(|options| options.command);
+        assert_eq!(help(&["--help"]), Ok(Some(Command::Help(None))));
+        assert_eq!(
+            help(&["-h", "--watch", "10m", "--random"]),
+            Ok(Some(Command::Help(None)))
+        );
+        assert_eq!(
+            help(&["help", "music"]),
+            Ok(Some(Command::Help(Some("music".to_string()))))
+        );
+        assert_eq!(
+            help(&["history", "-h"]),
+            Ok(Some(Command::Help(Some("history".to_string()))))
+        );
+        assert_eq!(
+            help(&["completions", "--help"]),
+            Ok(Some(Command::Help(Some("completions".to_string()))))
+        );
+        assert_eq!(
+            help(&["help", "completions"]),
+            Ok(Some(Command::Help(Some("completions".to_string()))))
+        );
+        assert!(help(&["help", "bogus"]).is_err());
+
+        let general = usage(None);
+        assert!(general.starts_with("Usage: event_finder [COMMAND] [FLAGS]\n"));
+        for (command, _) in COMMANDS {
+            assert!(
+                general.contains(&format!("\n  {} ", command)),
+                "{}",
+                command
+            );
+        }
+        for flag in FLAGS {
+            assert!(
+                general.contains(&format!("--{}", flag.name)),
+                "{}",
+                flag.name
+            );
+        }
+        assert!(general.contains("  -h, --help "));
+        assert_eq!(usage(Some("help")), general);
+
+        let music = usage(Some("m"));
+        assert!(
+            music.starts_with("Usage: event_finder music [FLAGS]\n\nList music events.\n"),
+            "{}",
+            music
+        );
+        assert!(music.contains("--format <CHOICE>") && !music.contains("--venue"));
+        let history = usage(Some("history"));
+        assert!(history.contains("--venue <VALUE>") && !history.contains("--watch"));
+        assert!(usage(Some("parse"))
+            .starts_with("Usage: event_finder parse --source NAME [FLAGS] < PAGE\n"));
+        assert!(!usage(Some("parse")).contains("--geocode"));
+        assert!(usage(Some("completions"))
+            .starts_with("Usage: event_finder completions <bash|zsh|fish|powershell>\n"));
+        assert!(!usage(Some("completions")).contains("--format"));
+    }
+
+    #[test]
+    fn test_apply_prefs_fills_in_what_the_command_line_left_out() {
+        let prefs = Prefs {
+            categories: vec![Category::Music, Category::Unique],
+            format: Some(Format::Table),
+            limit: Some(10),
+            group_by: Some(GroupKey::Venue),
+        };
+
+        let mut options = parse(&[]).unwrap();
+        options.apply_prefs(&prefs);
+        assert_eq!(
+            (options.format, options.limit, options.group_by),
+            (Format::Table, Some(10), Some(GroupKey::Venue))
+        );
+        assert_eq!(options.menu_default, prefs.categories);
+        assert_eq!(options.prefs(&[Category::Music, Category::Unique]), prefs);
+
+        // Even flags given their default values win
+        let mut options =
+            parse(&["--format", "plain", "--limit", "3", "--group-by", "none"]).unwrap();
+        options.apply_prefs(&prefs);
+        assert_eq!(
+            (options.format, options.limit, options.group_by),
+            (Format::Plain, Some(3), None)
+        );
+
+        assert!(parse(&[]).unwrap().remembers_prefs());
+        assert!(!parse(&["--no-prefs"]).unwrap().remembers_prefs());
+        assert!(!parse(&["music"]).unwrap().remembers_prefs());
+        assert!(!parse(&["--watch", "10m"]).unwrap().remembers_prefs());
+    }
+
+    #[test]
+    fn test_parse_args_commands() {
+        assert_eq!(
+            parse(&["music"]).unwrap().command,
+            Some(Command::List(vec![Category::Music]))
+        );
+        assert_eq!(
+            parse(&["All"]).unwrap().command,
+            Some(Command::List(Category::ALL.to_vec()))
+        );
+        assert_eq!(
+            parse(&["gen"]).unwrap().command,
+            Some(Command::List(vec![Category::General]))
+        );
+        assert_eq!(
+            parse(&["4"]).unwrap().command,
+            Some(Command::List(Category::ALL.to_vec()))
+        );
+        let options = parse(&["--offline", "unique", "--limit", "5", "--format", "plain"]).unwrap();
+        assert_eq!(
+            (options.command, options.limit, options.format),
+            (
+                Some(Command::List(vec![Category::Unique])),
+                Some(5),
+                Format::Plain
+            )
+        );
+
+        let options = parse(&[
+            "history",
+            "--venue",
+            "Ryman",
+            "--seen-since",
+            "30d",
+            "--format",
+            "csv",
+        ])
+        .unwrap();
+        assert_eq!(options.command, Some(Command::History));
+        assert_eq!(
+            options.history,
+            HistoryQuery {
+                venue: Some("Ryman".to_string()),
+                seen_since: Some(Since::Ago(chrono::Duration::days(30))),
+                first_seen_since: None,
+            }
+        );
+        assert!(parse(&["history", "--first-seen-since", "soon"])
+            .unwrap_err()
+            .starts_with("--first-seen-since: invalid time 'soon'"));
+        assert!(parse(&["music", "--venue", "Ryman"])
+            .unwrap_err()
+            .starts_with("--venue, --seen-since, and --first-seen-since only apply"));
+        assert!(parse(&["history", "--watch", "10m"]).is_err());
+
+        let options = parse(&[
+            "parse",
+            "--config-file",
+            "custom.toml",
+            "--source",
+            "mysite",
+            "--format",
+            "json",
+        ])
+        .unwrap();
+        assert_eq!(
+            (options.command, options.config, options.sources),
+            (
+                Some(Command::Parse),
+                Some(PathBuf::from("custom.toml")),
+                vec!["mysite".to_string()]
+            )
+        );
+        assert!(parse(&["parse"])
+            .unwrap_err()
+            .starts_with("parse reads the page on stdin as one source"));
+        assert!(parse(&["parse", "--source", "a", "--source", "b"]).is_err());
+        assert!(parse(&["parse", "--source", "a", "--enrich"])
+            .unwrap_err()
+            .contains("without the network"));
+
+        assert_eq!(
+            parse(&["completions", "fish"]).unwrap().command,
+            Some(Command::Completions(Shell::Fish))
+        );
+        assert_eq!(
+            parse(&["--config", "broken.toml", "completions", "PowerShell"])
+                .unwrap()
+                .command,
+            Some(Command::Completions(Shell::Powershell))
+        );
+        assert_eq!(
+            parse(&["completions"]),
+            Err("completions needs a shell: bash, zsh, fish, or powershell".to_string())
+        );
+        assert_eq!(
+            parse(&["completions", "tcsh"]),
+            Err("unknown shell 'tcsh' (expected bash, zsh, fish, or powershell)".to_string())
+        );
+
+        assert_eq!(parse(&["concerts"]), Err("unknown command 'concerts' (expected music, unique, general, all, history, parse, or completions)".to_string()));
+        assert_eq!(
+            parse(&["music", "general"]),
+            Err("unknown argument 'general'".to_string())
+        );
+    }
+
+    #[test]
+    fn test_parse_args_flags() {
+        let options = parse(&[
+            "--offline",
+            "--description-length",
+            "40",
+            "--keep-nameless",
+            "-v",
+        ])
+        .unwrap();
+        assert!(options.offline);
+        assert!(options.verbose);
+        assert_eq!(options.verbosity, 1);
+        assert!(options.keep_nameless);
+        assert_eq!(parse(&["-vv"]).unwrap().verbosity, 2);
+        assert_eq!(parse(&["-v", "--verbose", "-vvv"]).unwrap().verbosity, 5);
+        assert!(parse(&["--quiet"]).unwrap().quiet);
+        let strict = parse(&["music", "--strict", "--error-format", "json"]).unwrap();
+        assert_eq!(
+            (strict.strict, strict.error_format),
+            (true, ErrorFormat::Json)
+        );
+        assert!(parse(&["--error-format", "yaml"]).is_err());
+        assert_eq!(parse(&["-vx"]), Err("unknown argument '-vx'".to_string()));
+        assert_eq!(options.description_length, 40);
+
+        let options = parse(&["--enrich", "--enrich-limit", "5"]).unwrap();
+        assert!(options.enrich);
+        assert_eq!(options.enrich_limit, 5);
+        assert_eq!(options.max_events, None);
+
+        assert_eq!(parse(&["--max-events", "25"]).unwrap().max_events, Some(25));
+        assert!(parse(&["--hide-sold-out"]).unwrap().hide_sold_out);
+        let options = parse(&["--sort", "name", "--desc"]).unwrap();
+        assert_eq!(
+            (options.sort, options.order),
+            (SortKey::Name, Order::Descending)
+        );
+        assert_eq!(
+            parse(&["--group-by", "venue"]).unwrap().group_by,
+            Some(GroupKey::Venue)
+        );
+        assert_eq!(parse(&["--max-span", "3"]).unwrap().max_span, 3);
+        assert_eq!(
+            parse(&["--group-by", "venue", "--group-by", "none"])
+                .unwrap()
+                .group_by,
+            None
+        );
+        assert_eq!(
+            parse(&["--source", "songkick", "--source", "perto"])
+                .unwrap()
+                .sources,
+            vec!["songkick", "perto"]
+        );
+        assert_eq!(parse(&["--limit", "20"]).unwrap().limit, Some(20));
+        assert_eq!(
+            parse(&["--max-duration", "3h"]).unwrap().max_duration,
+            Some(chrono::Duration::hours(3))
+        );
+        assert!(parse(&["--max-duration", "soon"])
+            .unwrap_err()
+            .starts_with("--max-duration: invalid duration 'soon'"));
+        let free = parse(&["--free"]).unwrap();
+        assert_eq!((free.free, free.free_or_unknown), (true, false));
+        let near = parse(&["--within", "5mi", "--geocode"]).unwrap();
+        assert_eq!(
+            (near.within, near.geocode),
+            (Some(Distance { miles: 5.0 }), true)
+        );
+        assert!(parse(&["--within", "nearby"])
+            .unwrap_err()
+            .starts_with("--within: invalid distance"));
+        let free = parse(&["--free-or-unknown"]).unwrap();
+        assert_eq!((free.free, free.free_or_unknown), (true, true));
+        let random = parse(&["--random", "--soonest", "--seed", "42"]).unwrap();
+        assert_eq!(
+            (random.random, random.soonest, random.seed),
+            (true, true, Some(42))
+        );
+        assert_eq!(
+            parse(&["--limit", "0"]),
+            Err("invalid --limit '0'".to_string())
+        );
+        let options = parse(&["--format", "table", "--width", "100"]).unwrap();
+        assert_eq!(
+            (options.format, options.width, options.writes_data()),
+            (Format::Table, Some(100), false)
+        );
+        assert_eq!(
+            parse(&["--width", "wide"]),
+            Err("invalid --width 'wide'".to_string())
+        );
+        assert_eq!(parse(&["all", "--open", "3"]).unwrap().open, Some(3));
+        assert_eq!(
+            parse(&["--open", "0"]),
+            Err("invalid --open '0' (expected an event's number)".to_string())
+        );
+        let options = parse(&["--search", "jazz brunch"]).unwrap();
+        assert_eq!(
+            (options.search, options.search_mode),
+            (vec!["jazz".to_string(), "brunch".to_string()], Match::All)
+        );
+        assert_eq!(
+            parse(&["--search-any", "jazz blues"]).unwrap().search_mode,
+            Match::Any
+        );
+        let options = parse(&[
+            "--exclude",
+            "trivia night",
+            "--exclude",
+            "karaoke",
+            "--whole-word",
+        ])
+        .unwrap();
+        assert_eq!(
+            options.exclude,
+            vec!["trivia night".to_string(), "karaoke".to_string()]
+        );
+        assert!(options.whole_word);
+        assert_eq!(
+            parse(&["--merge-threshold", "0.5"])
+                .unwrap()
+                .merge_threshold,
+            0.5
+        );
+        let options = parse(&["--include-past", "--assumed-duration", "90"]).unwrap();
+        assert_eq!((options.include_past, options.assumed_duration), (true, 90));
+        assert_eq!(
+            parse(&["--timezone", "UTC"])
+                .unwrap()
+                .timezone
+                .map(|z| z.to_string())
+                .as_deref(),
+            Some("UTC")
+        );
+        assert_eq!(
+            parse(&["--date-format", "%b %-d"])
+                .unwrap()
+                .date_format
+                .as_deref(),
+            Some("%b %-d")
+        );
+        assert_eq!(
+            parse(&["--date-format", "eu"])
+                .unwrap()
+                .date_format
+                .as_deref(),
+            Some("%a %-d %b, %H:%M")
+        );
+        assert_eq!(
+            parse(&["--debug-selectors", "perto"])
+                .unwrap()
+                .debug_selectors
+                .as_deref(),
+            Some("perto")
+        );
+    }
+
+    #[test]
+    fn test_parse_args_errors() {
+        assert!(parse(&["--description-length"]).is_err());
+        assert!(parse(&["--description-length", "lots"]).is_err());
+        assert!(parse(&["--enrich-limit", "-1"]).is_err());
+        assert!(parse(&["--max-events"]).is_err());
+        assert!(parse(&["--debug-selectors"]).is_err());
+        assert!(parse(&["--date-format", "%Q"]).is_err());
+        assert!(parse(&["--max-span", "0"]).is_err());
+        assert!(parse(&["--format", "yaml"]).is_err());
+        assert!(parse(&["music", "--dry-run"]).unwrap().dry_run);
+        assert!(parse(&["history", "--dry-run"])
+            .unwrap_err()
+            .starts_with("--dry-run shows what"));
+        let options = parse(&["all", "--format", "JSON", "--compact"]).unwrap();
+        assert_eq!((options.format, options.compact), (Format::Json, true));
+        let options = parse(&["--output", "plans.csv", "--format", "csv", "--crlf"]).unwrap();
+        assert_eq!(
+            (options.format, options.output, options.crlf),
+            (Format::Csv, Some(PathBuf::from("plans.csv")), true)
+        );
+        assert!(parse(&["--output", "plans.txt", "--force"]).unwrap().force);
+        let options = parse(&["--no-color"]).unwrap();
+        assert!(options.no_color);
+        assert!(!options.styler().is_colored() && !options.error_styler().is_colored());
+        let options = parse(&["--config", "mine.toml", "--init-config"]).unwrap();
+        assert_eq!(
+            (options.config, options.init_config),
+            (Some(PathBuf::from("mine.toml")), true)
+        );
+        assert!(parse(&["--config"]).is_err());
+        let options = parse(&["music", "--new-only", "--mark-seen"]).unwrap();
+        assert_eq!(
+            (options.new_only, options.mark_seen, options.reset_seen),
+            (true, true, false)
+        );
+        assert!(parse(&["--reset-seen"]).unwrap().reset_seen);
+        assert!(parse(&["music", "--notify"]).unwrap().notify);
+        let options = parse(&[
+            "music",
+            "--webhook",
+            "https://hooks.example.com/T1",
+            "--webhook-format",
+            "slack",
+        ])
+        .unwrap();
+        assert_eq!(
+            (options.webhook.as_deref(), options.webhook_format),
+            (Some("https://hooks.example.com/T1"), WebhookFormat::Slack)
+        );
+        assert!(parse(&["--webhook", "https://hooks.example.com/T1"])
+            .unwrap_err()
+            .starts_with("--webhook posts a subcommand's listing"));
+        assert!(parse(&[
+            "music",
+            "--webhook",
+            "https://hooks.example.com/T1",
+            "--offline"
+        ])
+        .is_err());
+        assert!(parse(&["--webhook-format", "xml"]).is_err());
+        assert_eq!(
+            parse(&["all", "--email-to", "someone@example.com"])
+                .unwrap()
+                .email_to
+                .as_deref(),
+            Some("someone@example.com")
+        );
+        assert_eq!(
+            parse(&["all", "--email-to", "someone"]),
+            Err("invalid --email-to 'someone' (expected an email address)".to_string())
+        );
+        assert!(parse(&[
+            "all",
+            "--email-to",
+            "me@example.com\r\nBcc: you@example.com"
+        ])
+        .is_err());
+        assert!(parse(&["--email-to", "someone@example.com"])
+            .unwrap_err()
+            .starts_with("--email-to sends a subcommand's listing"));
+        assert_eq!(
+            parse(&["--city", "austin"]).unwrap().city.as_deref(),
+            Some("austin")
+        );
+        let options = parse(&["--template", "{name} @ {location:20}"]).unwrap();
+        assert_eq!(
+            options.template,
+            Some("{name} @ {location:20}".parse().unwrap())
+        );
+        assert!(options.writes_data());
+        assert!(parse(&["--template", "{venue}"])
+            .unwrap_err()
+            .starts_with("--template: unknown field 'venue'"));
+        assert!(parse(&["--sort", "price"]).is_err());
+        assert!(parse(&["--group-by", "price"]).is_err());
+        assert!(parse(&["--search"]).is_err());
+        assert!(parse(&["--exclude"]).is_err());
+        assert!(parse(&["--merge-threshold", "2"]).is_err());
+        assert!(parse(&["--assumed-duration", "0"]).is_err());
+        assert!(parse(&["--timezone", "Nashville"]).is_err());
+        assert!(parse(&["--from", "May 2"]).is_err());
+        assert!(parse(&["--from", "2024-05-04", "--to", "2024-05-03"]).is_err());
+        assert!(parse(&["--when", "weekend", "--to", "2024-05-03"]).is_err());
+        assert_eq!(
+            parse(&["music", "--watch", "10m"]).unwrap().watch,
+            Some(chrono::Duration::minutes(10))
+        );
+        assert!(parse(&["--watch", "10s"])
+            .unwrap_err()
+            .starts_with("--watch: invalid duration '10s'"));
+        assert!(parse(&["--watch", "1h", "--format", "json"])
+            .unwrap_err()
+            .starts_with("--watch lists events on the terminal"));
+        assert!(parse(&["--watch", "1h", "--random"]).is_err());
+        assert!(parse(&["--bogus"]).is_err());
+    }
+
+    #[test]
+    fn test_flags_match_parse_args() {
+        for flag in FLAGS {
+            let name = format!("--{}", flag.name);
+            let args = match flag.value {
+                FlagValue::None => vec![name.clone()],
+                _ => vec![name.clone(), "1".to_string()],
+            };
+            assert_ne!(
+                parse_args(args),
+                Err(format!("unknown argument '{}'", name))
+            );
+            if let Some(short) = flag.short {
+                assert!(parse(&[&format!("-{}", short)]).is_ok(), "-{}", short);
+            }
+        }
+        // Every flag the parser matches is listed; flags only appear quoted on their own there
+        let source = include_str!("cli.rs");
+        let parser = &source[..source.find("#[cfg(test)]").unwrap()];
+        let listed: Vec<String> = FLAGS
+            .iter()
+            .map(|flag| format!("\"--{}\"", flag.name))
+            .collect();
+        for quoted in regex::Regex::new(r#""--[a-z-]+""#)
+            .unwrap()
+            .find_iter(parser)
+        {
+            assert!(
+                listed.iter().any(|flag| flag == quoted.as_str()),
+                "{} is missing from FLAGS",
+                quoted.as_str()
+            );
+        }
+    }
+
+    #[test]
+    fn test_date_range() {
+        let today = NaiveDate::from_ymd_opt(2024, 5, 1).unwrap();
+        let day = |d: u32| NaiveDate::from_ymd_opt(2024, 5, d).unwrap();
+        assert_eq!(parse(&[]).unwrap().date_range(today), None);
+        assert_eq!(
+            parse(&["--when", "tomorrow"]).unwrap().date_range(today),
+            Some((day(2), day(2)))
+        );
+
+        let options = parse(&["--from", "2024-05-03", "--strict-dates"]).unwrap();
+        assert!(options.strict_dates);
+        assert_eq!(options.date_range(today), Some((day(3), NaiveDate::MAX)));
+        assert_eq!(
+            parse(&["--from", "2024-05-03", "--to", "2024-05-04"])
+                .unwrap()
+                .date_range(today),
+            Some((day(3), day(4)))
+        );
+    }
+}
@@ -0,0 +1,363 @@
+//! # Completions Module
+//!
+//! This module writes the shell completion scripts the `completions` subcommand prints. The
+//! scripts are generated from `cli::COMMANDS` and `cli::FLAGS`, so they offer what
+//! `cli::parse_args` accepts, with the source and city names of the sources file as static
+//! lists.
+
+use std::fmt;
+use std::str::FromStr;
+
+use event_finder::timezone::Zone;
+
+use crate::cli::{FlagValue, COMMANDS, FLAGS};
+
+/// The program name the scripts complete.
+const PROGRAM: &str = "event_finder";
+
+/// A shell that completion scripts can be written for.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Shell {
+    Bash,
+    Zsh,
+    Fish,
+    Powershell,
+}
+
+/// The names `completions` takes, which the second argument of the subcommand completes to.
+const SHELLS: [&str; 4] = ["bash", "zsh", "fish", "powershell"];
+
+impl FromStr for Shell {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "bash" => Ok(Shell::Bash),
+            "zsh" => Ok(Shell::Zsh),
+            "fish" => Ok(Shell::Fish),
+            "powershell" | "pwsh" => Ok(Shell::Powershell),
+            _ => Err(format!(
+                "unknown shell '{}' (expected bash, zsh, fish, or powershell)",
+                s
+            )),
+        }
+    }
+}
+
+impl fmt::Display for Shell {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(SHELLS[*self as usize])
+    }
+}
+
+/// The names a `FlagValue::Source` or `FlagValue::City` completes to.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct Names {
+    /// The sources file's source names.
+    pub sources: Vec<String>,
+    /// The sources file's city names.
+    pub cities: Vec<String>,
+}
+
+/// Writes the completion script for a shell.
+///
+/// Names that a shell word list can't hold as they are, e.g. ones with spaces or quotes, are
+/// left out rather than escaped for each shell.
+///
+/// # Arguments
+///
+/// * `shell` - The shell to write the script for.
+/// * `names` - The source and city names to offer.
+///
+/// # Returns
+///
+/// The script, to be sourced or saved where the shell looks for completions.
+pub fn script(shell: Shell, names: &Names) -> String {
+    match shell {
+        Shell::Bash => bash(names),
+        Shell::Zsh => zsh(names),
+        Shell::Fish => fish(names),
+        Shell::Powershell => powershell(names),
+    }
+}
+
+/// The words a flag's value completes to, or `None` if it is free text or a path.
+fn choices(value: FlagValue, names: &Names) -> Option<Vec<String>> {
+    let words: Vec<String> = match value {
+        FlagValue::None | FlagValue::Text | FlagValue::File => return None,
+        FlagValue::Choices(words) => words.iter().map(|w| w.to_string()).collect(),
+        FlagValue::Source => names.sources.clone(),
+        FlagValue::City => names.cities.clone(),
+        FlagValue::Zone => Zone::names().map(str::to_string).collect(),
+    };
+    Some(words.into_iter().filter(|w| is_plain(w)).collect())
+}
+
+/// Whether a name can go in a shell word list unquoted.
+fn is_plain(word: &str) -> bool {
+    !word.is_empty()
+        && word
+            .chars()
+            .all(|c| c.is_alphanumeric() || "-_.+@:/".contains(c))
+}
+
+/// The words offered where a subcommand or a flag may go.
+fn top_level_words() -> Vec<String> {
+    let commands = COMMANDS.iter().map(|(name, _)| name.to_string());
+    let flags = FLAGS.iter().flat_map(|flag| {
+        std::iter::once(format!("--{}", flag.name))
+            .chain(flag.short.map(|short| format!("-{}", short)))
+    });
+    commands.chain(flags).collect()
+}
+
+fn bash(names: &Names) -> String {
+    let mut cases = String::new();
+    let takes = |wanted: fn(FlagValue) -> bool| -> String {
+        FLAGS
+            .iter()
+            .filter(|flag| wanted(flag.value))
+            .map(|flag| format!("--{}", flag.name))
+            .collect::<Vec<_>>()
+            .join("|")
+    };
+    cases.push_str(&format!("        {})\n            compopt -o filenames 2>/dev/null\n            COMPREPLY=($(compgen -f -- \"$cur\"))\n            return ;;\n",
+        takes(|value| value == FlagValue::File)));
+    cases.push_str(&format!(
+        "        {})\n            COMPREPLY=()\n            return ;;\n",
+        takes(|value| value == FlagValue::Text)
+    ));
+    for flag in FLAGS {
+        if let Some(words) = choices(flag.value, names) {
+            cases.push_str(&format!("        --{})\n            COMPREPLY=($(compgen -W \"{}\" -- \"$cur\"))\n            return ;;\n", flag.name, words.join(" ")));
+        }
+    }
+    cases.push_str(&format!("        completions)\n            COMPREPLY=($(compgen -W \"{}\" -- \"$cur\"))\n            return ;;\n", SHELLS.join(" ")));
+    format!(
+        "# bash completion for {program}; load it with: source <({program} completions bash)\n\
+         _{program}() {{\n    \
+             local cur=\"${{COMP_WORDS[COMP_CWORD]}}\" prev=\"${{COMP_WORDS[COMP_CWORD-1]}}\"\n    \
+             case \"$prev\" in\n{cases}    esac\n    \
+             COMPREPLY=($(compgen -W \"{words}\" -- \"$cur\"))\n\
+         }}\n\
+         complete -F _{program} {program}\n",
+        program = PROGRAM,
+        cases = cases,
+        words = top_level_words().join(" ")
+    )
+}
+
+/// Escapes a description for a zsh `_arguments` spec.
+fn zsh_help(help: &str) -> String {
+    help.replace('\'', "'\\''")
+        .replace('[', "\\[")
+        .replace(']', "\\]")
+        .replace(':', "\\:")
+}
+
+fn zsh(names: &Names) -> String {
+    let mut specs = String::new();
+    for flag in FLAGS {
+        let action = match flag.value {
+            FlagValue::None => String::new(),
+            FlagValue::Text => format!(":{}: ", flag.name),
+            FlagValue::File => format!(":{}:_files", flag.name),
+            value => format!(
+                ":{}:({})",
+                flag.name,
+                choices(value, names).unwrap_or_default().join(" ")
+            ),
+        };
+        let help = zsh_help(flag.help);
+        // Every flag may be repeated: -v counts, and --source and --exclude add up
+        specs.push_str(&format!(
+            "        '*--{}[{}]{}' \\\n",
+            flag.name, help, action
+        ));
+        if let Some(short) = flag.short {
+            specs.push_str(&format!("        '*-{}[{}]{}' \\\n", short, help, action));
+        }
+    }
+    let commands: Vec<String> = COMMANDS
+        .iter()
+        .map(|(name, help)| format!("'{}:{}'", name, zsh_help(help)))
+        .collect();
+    format!(
+        "#compdef {program}\n\
+         # zsh completion for {program}; save it as _{program} in a directory on $fpath, or load it with:\n\
+         # source <({program} completions zsh)\n\
+         _{program}() {{\n    \
+             local state\n    \
+             local -a commands=({commands})\n    \
+             _arguments -s \\\n{specs}        '1:command:->command' \\\n        '2:shell:->shell'\n    \
+             case $state in\n        \
+                 command) _describe command commands ;;\n        \
+                 shell) (( ${{words[(I)completions]}} )) && _values shell {shells} ;;\n    \
+             esac\n\
+         }}\n\
+         if [ \"$funcstack[1]\" = \"_{program}\" ]; then\n    \
+             _{program} \"$@\"\n\
+         else\n    \
+             compdef _{program} {program}\n\
+         fi\n",
+        program = PROGRAM, commands = commands.join(" "), specs = specs, shells = SHELLS.join(" "))
+}
+
+fn fish(names: &Names) -> String {
+    let quote = |text: &str| format!("'{}'", text.replace('\\', "\\\\").replace('\'', "\\'"));
+    let mut lines = vec![
+        format!(
+            "# fish completion for {}; save it as ~/.config/fish/completions/{}.fish",
+            PROGRAM, PROGRAM
+        ),
+        format!("complete -c {} -f", PROGRAM),
+    ];
+    for (name, help) in COMMANDS {
+        lines.push(format!(
+            "complete -c {} -n __fish_use_subcommand -a {} -d {}",
+            PROGRAM,
+            name,
+            quote(help)
+        ));
+    }
+    lines.push(format!(
+        "complete -c {} -n '__fish_seen_subcommand_from completions' -a {}",
+        PROGRAM,
+        quote(&SHELLS.join(" "))
+    ));
+    for flag in FLAGS {
+        let short = flag
+            .short
+            .map(|short| format!(" -s {}", short))
+            .unwrap_or_default();
+        let value = match flag.value {
+            FlagValue::None => String::new(),
+            FlagValue::Text => " -x".to_string(),
+            FlagValue::File => " -r -F".to_string(),
+            value => format!(
+                " -x -a {}",
+                quote(&choices(value, names).unwrap_or_default().join(" "))
+            ),
+        };
+        lines.push(format!(
+            "complete -c {}{} -l {}{} -d {}",
+            PROGRAM,
+            short,
+            flag.name,
+            value,
+            quote(flag.help)
+        ));
+    }
+    lines.join("\n") + "\n"
+}
+
+fn powershell(names: &Names) -> String {
+    let list = |words: &[String]| {
+        words
+            .iter()
+            .map(|w| format!("'{}'", w.replace('\'', "''")))
+            .collect::<Vec<_>>()
+            .join(", ")
+    };
+    let mut cases = String::new();
+    for flag in FLAGS {
+        if let Some(words) = choices(flag.value, names) {
+            cases.push_str(&format!(
+                "        '--{}' {{ @({}) }}\n",
+                flag.name,
+                list(&words)
+            ));
+        }
+    }
+    let shells: Vec<String> = SHELLS.iter().map(|s| s.to_string()).collect();
+    cases.push_str(&format!(
+        "        'completions' {{ @({}) }}\n",
+        list(&shells)
+    ));
+    // Other flags that take a value complete to nothing, so PowerShell falls back to paths
+    let takes_value: Vec<String> = FLAGS
+        .iter()
+        .filter(|flag| flag.value != FlagValue::None)
+        .map(|flag| format!("--{}", flag.name))
+        .collect();
+    format!(
+        "# PowerShell completion for {program}; load it with:\n\
+         # {program} completions powershell | Out-String | Invoke-Expression\n\
+         Register-ArgumentCompleter -Native -CommandName {program} -ScriptBlock {{\n    \
+             param($wordToComplete, $commandAst, $cursorPosition)\n    \
+             $words = @($commandAst.CommandElements | ForEach-Object {{ $_.ToString() }})\n    \
+             $previous = if ($wordToComplete -eq '') {{ $words[-1] }} elseif ($words.Count -gt 1) {{ $words[-2] }} else {{ '' }}\n    \
+             $valueFlags = @({value_flags})\n    \
+             $candidates = switch ($previous) {{\n{cases}        \
+                 default {{ if ($valueFlags -contains $previous) {{ @() }} else {{ @({words}) }} }}\n    \
+             }}\n    \
+             $candidates | Where-Object {{ $_ -like \"$wordToComplete*\" }} | ForEach-Object {{\n        \
+                 [System.Management.Automation.CompletionResult]::new($_, $_, 'ParameterValue', $_)\n    \
+             }}\n\
+         }}\n",
+        program = PROGRAM, value_flags = list(&takes_value), cases = cases, words = list(&top_level_words()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn names() -> Names {
+        Names {
+            sources: vec![
+                "songkick".to_string(),
+                "nashville.com".to_string(),
+                "Bob's Bar".to_string(),
+            ],
+            cities: vec!["nashville".to_string()],
+        }
+    }
+
+    #[test]
+    fn test_every_shell_completes_the_subcommands_and_names() {
+        for shell in [Shell::Bash, Shell::Zsh, Shell::Fish, Shell::Powershell] {
+            let script = script(shell, &names());
+
+            assert!(!script.is_empty());
+            for (command, _) in COMMANDS {
+                assert!(script.contains(command), "{} is missing {}", shell, command);
+            }
+            assert!(
+                script.contains("--format") || script.contains("-l format"),
+                "{}",
+                shell
+            );
+            assert!(
+                script.contains("nashville.com") && script.contains("America/Chicago"),
+                "{}",
+                shell
+            );
+            assert!(!script.contains("Bob's Bar"), "{}", shell);
+        }
+    }
+
+    #[test]
+    fn test_bash_completes_flag_values() {
+        let script = script(Shell::Bash, &names());
+
+        assert!(script.contains("        --source)\n            COMPREPLY=($(compgen -W \"songkick nashville.com\" -- \"$cur\"))"), "{}", script);
+        assert!(script.contains("        --when)\n            COMPREPLY=($(compgen -W \"today tomorrow weekend week\" -- \"$cur\"))"));
+        assert!(script.contains("--output|--config|--config-file)"));
+        assert!(script.ends_with("complete -F _event_finder event_finder\n"));
+    }
+
+    #[test]
+    fn test_zsh_and_fish_escape_descriptions() {
+        assert!(script(Shell::Zsh, &names()).contains("'*--debug-selectors[Report what a source'\\''s selectors match]:debug-selectors:(songkick nashville.com)'"));
+        assert!(script(Shell::Fish, &names()).contains("complete -c event_finder -l debug-selectors -x -a 'songkick nashville.com' -d 'Report what a source\\'s selectors match'"));
+        assert!(script(Shell::Fish, &names())
+            .contains("complete -c event_finder -s q -l quiet -d 'Log only errors'"));
+    }
+
+    #[test]
+    fn test_shell_names() {
+        assert_eq!("pwsh".parse(), Ok(Shell::Powershell));
+        assert_eq!("Zsh".parse(), Ok(Shell::Zsh));
+        assert_eq!(Shell::Powershell.to_string(), "powershell");
+    }
+}
@@ -0,0 +1,961 @@
+//! # Config Module
+//!
+//! This module loads the user's settings file (`$EVENT_FINDER_CONFIG`, or
+//! `~/.config/event_finder/config.toml` by default). The file is written in a subset of TOML:
+//! `[table]` and `[[array]]` headers, `key = value` lines, `#` comments, and values that are
+//! strings, integers, floats, booleans, or arrays of them. A missing file means the defaults.
+
+use std::env;
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use crate::data_processing::{self, Interest, MergePreferences, VenueAliases};
+use crate::email::EmailSettings;
+use crate::error::Error;
+use crate::geo::Coordinates;
+use crate::timezone::Zone;
+
+/// The user's settings.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct Config {
+    /// The city whose sources are used, from `city`, if set.
+    pub city: Option<String>,
+    /// The home timezone, if set.
+    pub timezone: Option<Zone>,
+    /// The strftime format dates are shown in, from `date_format` (a preset name or a format).
+    pub date_format: Option<String>,
+    /// How many days a listed event is remembered for `--new-only`, from `seen_days`.
+    pub seen_days: Option<i64>,
+    /// The file the fetch history is kept in, from `history_file`.
+    pub history_file: Option<PathBuf>,
+    /// Rules that leave events out of the listing.
+    pub filters: Filters,
+    /// The `[venue_aliases]` table, matched by prefix when `[venues]` sets `prefix_match`.
+    pub venue_aliases: VenueAliases,
+    /// The `[interests]` table: terms and how much each adds to an event's score.
+    pub interests: Vec<Interest>,
+    /// The `[merge]` table: which listing's value each field keeps when duplicates are merged.
+    pub merge: MergePreferences,
+    /// The `[home]` table's `lat` and `lng`, which distances are measured from.
+    pub home: Option<Coordinates>,
+    /// The `[home]` table's `near`, which geocoding searches are narrowed to.
+    pub near: Option<String>,
+    /// The `[venue_coordinates]` table: venues' `[lat, lng]`, used before any geocoding.
+    pub venue_coordinates: Vec<(String, Coordinates)>,
+    /// The `[email]` table: the mail server `--email-to` sends through.
+    pub email: EmailSettings,
+    /// The `[ticketmaster]` table's `api_key`, which Ticketmaster sources are fetched with.
+    pub ticketmaster_api_key: Option<String>,
+}
+
+/// The `[filters]` table.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct Filters {
+    /// Words that leave out events mentioning them in their name or description.
+    pub exclude: Vec<String>,
+    /// Venues whose events are never shown.
+    pub blocked_venues: Vec<String>,
+    /// Match `exclude` and `blocked_venues` as whole words instead of anywhere in the text.
+    pub whole_word: bool,
+}
+
+/// An error in the settings file, with the line it was found on.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ConfigError {
+    /// The 1-based line number.
+    pub line: usize,
+    pub message: String,
+}
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "line {}: {}", self.line, self.message)
+    }
+}
+
+/// A value in the settings file.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Value {
+    String(String),
+    Integer(i64),
+    Float(f64),
+    Bool(bool),
+    Array(Vec<Value>),
+}
+
+impl Value {
+    fn type_name(&self) -> &'static str {
+        match self {
+            Value::String(_) => "a string",
+            Value::Integer(_) => "an integer",
+            Value::Float(_) => "a float",
+            Value::Bool(_) => "a boolean",
+            Value::Array(_) => "an array",
+        }
+    }
+}
+
+/// A `key = value` line.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Entry {
+    pub key: String,
+    pub value: Value,
+    /// The 1-based line the entry starts on.
+    pub line: usize,
+}
+
+/// The entries under one table header; the entries before any header have an empty name.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Table {
+    pub name: String,
+    /// `true` for a `[[name]]` header, which starts one element of an array of tables.
+    pub is_array: bool,
+    pub entries: Vec<Entry>,
+    /// The 1-based line of the header, or 0 for the entries before any header.
+    pub line: usize,
+}
+
+/// Returns the path of the settings file.
+pub fn config_path() -> PathBuf {
+    if let Some(path) = env::var_os("EVENT_FINDER_CONFIG") {
+        return PathBuf::from(path);
+    }
+    config_dir().join("config.toml")
+}
+
+/// Returns the path of the sources file, which defines the sites events are fetched from.
+pub fn sources_path() -> PathBuf {
+    if let Some(path) = env::var_os("EVENT_FINDER_SOURCES") {
+        return PathBuf::from(path);
+    }
+    config_dir().join("sources.toml")
+}
+
+/// Returns the directory user-editable settings are kept in.
+pub fn config_dir() -> PathBuf {
+    if let Some(dir) = env::var_os("XDG_CONFIG_HOME") {
+        return PathBuf::from(dir).join("event_finder");
+    }
+    let home = env::var_os("HOME")
+        .or_else(|| env::var_os("USERPROFILE"))
+        .unwrap_or_default();
+    PathBuf::from(home).join(".config").join("event_finder")
+}
+
+/// Loads the settings file.
+///
+/// # Arguments
+///
+/// * `path` - The file to read.
+///
+/// # Returns
+///
+/// The settings, the defaults if the file doesn't exist, or an `Error::Config` naming the file
+/// and the problem (`Error::Io` if it can't be read).
+pub fn load(path: &Path) -> Result<Config, Error> {
+    match fs::read_to_string(path) {
+        Ok(text) => parse_config(&text).map_err(|error| Error::Config {
+            path: path.to_path_buf(),
+            error,
+        }),
+        Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(Config::default()),
+        Err(e) => Err(Error::Io(io::Error::new(
+            e.kind(),
+            format!("reading config file {}: {}", path.display(), e),
+        ))),
+    }
+}
+
+/// Parses the text of a settings file.
+///
+/// Unknown tables and keys are errors, so a misspelled setting isn't silently ignored.
+///
+/// # Arguments
+///
+/// * `text` - The file's contents.
+///
+/// # Returns
+///
+/// The settings, or the first error found.
+pub fn parse_config(text: &str) -> Result<Config, ConfigError> {
+    let mut config = Config::default();
+    let (mut aliases, mut prefix_match) = (Vec::new(), false);
+    let (mut home_lat, mut home_lng) = (None, None);
+    for table in parse_tables(text)? {
+        match (table.name.as_str(), table.is_array) {
+            ("", false) => {
+                for entry in &table.entries {
+                    match entry.key.as_str() {
+                        "city" => config.city = Some(string(entry)?),
+                        "timezone" => {
+                            let name = string(entry)?;
+                            let zone = name.parse().map_err(|message| ConfigError {
+                                line: entry.line,
+                                message,
+                            })?;
+                            config.timezone = Some(zone);
+                        }
+                        "date_format" => {
+                            let format = data_processing::resolve_date_format(&string(entry)?)
+                                .map_err(|message| ConfigError {
+                                    line: entry.line,
+                                    message,
+                                })?;
+                            config.date_format = Some(format);
+                        }
+                        "history_file" => config.history_file = Some(PathBuf::from(string(entry)?)),
+                        "seen_days" => match entry.value {
+                            Value::Integer(days) if days > 0 => config.seen_days = Some(days),
+                            Value::Integer(_) => {
+                                return Err(ConfigError {
+                                    line: entry.line,
+                                    message: "seen_days should be at least 1".to_string(),
+                                });
+                            }
+                            _ => return Err(type_error(entry, "an integer")),
+                        },
+                        _ => {
+                            return Err(ConfigError {
+                                line: entry.line,
+                                message: format!("unknown setting '{}'", entry.key),
+                            })
+                        }
+                    }
+                }
+            }
+            ("filters", false) => {
+                for entry in &table.entries {
+                    match entry.key.as_str() {
+                        "exclude" => config.filters.exclude = string_array(entry)?,
+                        "blocked_venues" => config.filters.blocked_venues = string_array(entry)?,
+                        "whole_word" => config.filters.whole_word = boolean(entry)?,
+                        _ => return Err(unknown_key(entry, "filters")),
+                    }
+                }
+            }
+            ("venue_aliases", false) => {
+                for entry in &table.entries {
+                    aliases.push((entry.key.clone(), string(entry)?));
+                }
+            }
+            ("interests", false) => {
+                for entry in &table.entries {
+                    let weight = match entry.value {
+                        Value::Integer(weight) => weight,
+                        _ => return Err(type_error(entry, "an integer weight")),
+                    };
+                    config.interests.push(Interest {
+                        term: entry.key.clone(),
+                        weight,
+                    });
+                }
+            }
+            ("merge", false) => {
+                for entry in &table.entries {
+                    let prefer = string(entry)?.parse().map_err(|message| ConfigError {
+                        line: entry.line,
+                        message,
+                    })?;
+                    config
+                        .merge
+                        .set(&entry.key, prefer)
+                        .map_err(|message| ConfigError {
+                            line: entry.line,
+                            message,
+                        })?;
+                }
+            }
+            ("home", false) => {
+                for entry in &table.entries {
+                    match entry.key.as_str() {
+                        "lat" => home_lat = Some((number(entry)?, entry.line)),
+                        "lng" | "lon" => home_lng = Some((number(entry)?, entry.line)),
+                        "near" => config.near = Some(string(entry)?),
+                        _ => return Err(unknown_key(entry, "home")),
+                    }
+                }
+            }
+            ("venue_coordinates", false) => {
+                for entry in &table.entries {
+                    config
+                        .venue_coordinates
+                        .push((entry.key.clone(), coordinates(entry)?));
+                }
+            }
+            ("email", false) => {
+                for entry in &table.entries {
+                    match entry.key.as_str() {
+                        "smtp_url" => config.email.smtp_url = Some(string(entry)?),
+                        "username" => config.email.username = Some(string(entry)?),
+                        "password" => config.email.password = Some(string(entry)?),
+                        "from" => config.email.from = Some(string(entry)?),
+                        "send_empty" => config.email.send_empty = boolean(entry)?,
+                        _ => return Err(unknown_key(entry, "email")),
+                    }
+                }
+            }
+            ("ticketmaster", false) => {
+                for entry in &table.entries {
+                    match entry.key.as_str() {
+                        "api_key" => config.ticketmaster_api_key = Some(string(entry)?),
+                        _ => return Err(unknown_key(entry, "ticketmaster")),
+                    }
+                }
+            }
+            ("venues", false) => {
+                for entry in &table.entries {
+                    match entry.key.as_str() {
+                        "prefix_match" => prefix_match = boolean(entry)?,
+                        _ => return Err(unknown_key(entry, "venues")),
+                    }
+                }
+            }
+            _ => {
+                return Err(ConfigError {
+                    line: table.line,
+                    message: format!("unknown setting '{}'", table.name),
+                })
+            }
+        }
+    }
+    config.venue_aliases = VenueAliases::new(aliases, prefix_match);
+    config.home = match (home_lat, home_lng) {
+        (Some((lat, line)), Some((lng, _))) => {
+            Some(Coordinates::new(lat, lng).map_err(|message| ConfigError { line, message })?)
+        }
+        (None, None) => None,
+        (Some((_, line)), None) | (None, Some((_, line))) => {
+            return Err(ConfigError {
+                line,
+                message: "[home] needs both lat and lng".to_string(),
+            });
+        }
+    };
+    Ok(config)
+}
+
+fn unknown_key(entry: &Entry, table: &str) -> ConfigError {
+    ConfigError {
+        line: entry.line,
+        message: format!("unknown setting '{}' in [{}]", entry.key, table),
+    }
+}
+
+fn type_error(entry: &Entry, expected: &str) -> ConfigError {
+    ConfigError {
+        line: entry.line,
+        message: format!(
+            "{} should be {}, not {}",
+            entry.key,
+            expected,
+            entry.value.type_name()
+        ),
+    }
+}
+
+/// Reads an entry that must be a string.
+pub fn string(entry: &Entry) -> Result<String, ConfigError> {
+    match &entry.value {
+        Value::String(s) => Ok(s.clone()),
+        _ => Err(type_error(entry, "a string")),
+    }
+}
+
+/// Reads an entry that must be an array of strings.
+pub fn string_array(entry: &Entry) -> Result<Vec<String>, ConfigError> {
+    match &entry.value {
+        Value::Array(items) => items
+            .iter()
+            .map(|item| match item {
+                Value::String(s) => Ok(s.clone()),
+                _ => Err(type_error(entry, "an array of strings")),
+            })
+            .collect(),
+        _ => Err(type_error(entry, "an array of strings")),
+    }
+}
+
+/// Reads an entry that must be a number, integer or float.
+pub fn number(entry: &Entry) -> Result<f64, ConfigError> {
+    match entry.value {
+        Value::Integer(n) => Ok(n as f64),
+        Value::Float(n) => Ok(n),
+        _ => Err(type_error(entry, "a number")),
+    }
+}
+
+/// Reads an entry that must be a `[lat, lng]` pair.
+pub fn coordinates(entry: &Entry) -> Result<Coordinates, ConfigError> {
+    let pair: Vec<f64> = match &entry.value {
+        Value::Array(items) => items
+            .iter()
+            .map(|item| match item {
+                Value::Integer(n) => Some(*n as f64),
+                Value::Float(n) => Some(*n),
+                _ => None,
+            })
+            .collect::<Option<_>>()
+            .unwrap_or_default(),
+        _ => Vec::new(),
+    };
+    match pair.as_slice() {
+        [lat, lng] => Coordinates::new(*lat, *lng).map_err(|message| ConfigError {
+            line: entry.line,
+            message,
+        }),
+        _ => Err(type_error(entry, "a [lat, lng] pair")),
+    }
+}
+
+/// Reads an entry that must be a boolean.
+pub fn boolean(entry: &Entry) -> Result<bool, ConfigError> {
+    match entry.value {
+        Value::Bool(b) => Ok(b),
+        _ => Err(type_error(entry, "true or false")),
+    }
+}
+
+/// Splits a settings file into its tables.
+///
+/// # Arguments
+///
+/// * `text` - The file's contents.
+///
+/// # Returns
+///
+/// The tables in file order, starting with the (possibly empty) table of entries before the
+/// first header, or the first syntax error.
+pub fn parse_tables(text: &str) -> Result<Vec<Table>, ConfigError> {
+    let mut tables = vec![Table {
+        name: String::new(),
+        is_array: false,
+        entries: Vec::new(),
+        line: 0,
+    }];
+    let mut lines = text.lines().enumerate().map(|(i, line)| (i + 1, line));
+    while let Some((number, line)) = lines.next() {
+        let trimmed = strip_comment(line).trim().to_string();
+        if trimmed.is_empty() {
+            continue;
+        }
+        let error = |message: String| ConfigError {
+            line: number,
+            message,
+        };
+        if let Some(header) = trimmed.strip_prefix('[') {
+            let (name, is_array) = match header.strip_prefix('[') {
+                Some(rest) => (rest.strip_suffix("]]"), true),
+                None => (header.strip_suffix(']'), false),
+            };
+            let name = name
+                .map(str::trim)
+                .filter(|n| is_bare_key(n))
+                .ok_or_else(|| error(format!("invalid table header '{}'", trimmed)))?;
+            if !is_array && tables.iter().any(|t| t.name == name) {
+                return Err(error(format!("table [{}] is defined twice", name)));
+            }
+            tables.push(Table {
+                name: name.to_string(),
+                is_array,
+                entries: Vec::new(),
+                line: number,
+            });
+            continue;
+        }
+
+        let (key, value) = find_unquoted(&trimmed, '=')
+            .map(|i| (&trimmed[..i], &trimmed[i + 1..]))
+            .ok_or_else(|| error(format!("expected 'key = value', found '{}'", trimmed)))?;
+        let key =
+            parse_key(key.trim()).ok_or_else(|| error(format!("invalid key '{}'", key.trim())))?;
+        // Arrays may continue over several lines until their brackets close
+        let mut value = value.trim().to_string();
+        while value.starts_with('[') && !brackets_closed(&value) {
+            let (_, next) = lines
+                .next()
+                .ok_or_else(|| error(format!("unclosed array for '{}'", key)))?;
+            value.push(' ');
+            value.push_str(strip_comment(next).trim());
+        }
+        let value =
+            parse_value(&value).map_err(|message| error(format!("{}: {}", key, message)))?;
+        let table = tables
+            .last_mut()
+            .expect("the header-less table is always present");
+        if table.entries.iter().any(|e| e.key == key) {
+            return Err(error(format!("'{}' is set twice", key)));
+        }
+        table.entries.push(Entry {
+            key,
+            value,
+            line: number,
+        });
+    }
+    Ok(tables)
+}
+
+fn is_bare_key(key: &str) -> bool {
+    !key.is_empty()
+        && key
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-')
+}
+
+/// Reads a key, which is bare (`blocked_venues`) or quoted (`"The Ryman"`).
+fn parse_key(key: &str) -> Option<String> {
+    if is_bare_key(key) {
+        return Some(key.to_string());
+    }
+    match parse_value(key) {
+        Ok(Value::String(s)) if !s.is_empty() => Some(s),
+        _ => None,
+    }
+}
+
+/// Removes a `#` comment, leaving `#` inside strings alone.
+fn strip_comment(line: &str) -> &str {
+    find_unquoted(line, '#').map_or(line, |i| &line[..i])
+}
+
+/// Returns the byte index of the first `target` outside a string, such as the `=` after a
+/// quoted key that has one of its own.
+fn find_unquoted(text: &str, target: char) -> Option<usize> {
+    let mut quote = None;
+    let mut escaped = false;
+    for (i, c) in text.char_indices() {
+        match (quote, c) {
+            (Some('"'), '\\') if !escaped => {
+                escaped = true;
+                continue;
+            }
+            (Some(q), _) if c == q && !escaped => quote = None,
+            (None, '"' | '\'') => quote = Some(c),
+            (None, _) if c == target => return Some(i),
+            _ => {}
+        }
+        escaped = false;
+    }
+    None
+}
+
+/// Returns `true` once every `[` outside a string has been closed.
+fn brackets_closed(text: &str) -> bool {
+    let mut depth = 0i32;
+    let mut quote = None;
+    let mut escaped = false;
+    for c in text.chars() {
+        match (quote, c) {
+            (Some('"'), '\\') if !escaped => {
+                escaped = true;
+                continue;
+            }
+            (Some(q), _) if c == q && !escaped => quote = None,
+            (None, '"' | '\'') => quote = Some(c),
+            (None, '[') => depth += 1,
+            (None, ']') => depth -= 1,
+            _ => {}
+        }
+        escaped = false;
+    }
+    depth <= 0
+}
+
+/// Parses a whole value, rejecting anything left over after it.
+fn parse_value(text: &str) -> Result<Value, String> {
+    let chars: Vec<char> = text.chars().collect();
+    let mut pos = 0;
+    let value = read_value(&chars, &mut pos)?;
+    skip_spaces(&chars, &mut pos);
+    if pos < chars.len() {
+        return Err(format!(
+            "unexpected '{}' after the value",
+            chars[pos..].iter().collect::<String>()
+        ));
+    }
+    Ok(value)
+}
+
+fn skip_spaces(chars: &[char], pos: &mut usize) {
+    while *pos < chars.len() && chars[*pos].is_whitespace() {
+        *pos += 1;
+    }
+}
+
+fn read_value(chars: &[char], pos: &mut usize) -> Result<Value, String> {
+    skip_spaces(chars, pos);
+    match chars.get(*pos) {
+        None => Err("missing value".to_string()),
+        Some('"') => read_basic_string(chars, pos).map(Value::String),
+        Some('\'') => {
+            let start = *pos + 1;
+            let end = chars[start..]
+                .iter()
+                .position(|&c| c == '\'')
+                .ok_or("unclosed string")?;
+            *pos = start + end + 1;
+            Ok(Value::String(chars[start..start + end].iter().collect()))
+        }
+        Some('[') => {
+            *pos += 1;
+            let mut items = Vec::new();
+            loop {
+                skip_spaces(chars, pos);
+                if chars.get(*pos) == Some(&']') {
+                    *pos += 1;
+                    return Ok(Value::Array(items));
+                }
+                items.push(read_value(chars, pos)?);
+                skip_spaces(chars, pos);
+                match chars.get(*pos) {
+                    Some(',') => *pos += 1,
+                    Some(']') => {}
+                    _ => return Err("expected ',' or ']' in array".to_string()),
+                }
+            }
+        }
+        Some(_) => {
+            let start = *pos;
+            while *pos < chars.len()
+                && !matches!(chars[*pos], ',' | ']')
+                && !chars[*pos].is_whitespace()
+            {
+                *pos += 1;
+            }
+            let word: String = chars[start..*pos].iter().collect();
+            match word.as_str() {
+                "true" => Ok(Value::Bool(true)),
+                "false" => Ok(Value::Bool(false)),
+                _ => {
+                    let number = word.replace('_', "");
+                    if let Ok(integer) = number.parse() {
+                        Ok(Value::Integer(integer))
+                    } else if let (true, Ok(float)) =
+                        (number.contains(['.', 'e', 'E']), number.parse())
+                    {
+                        Ok(Value::Float(float))
+                    } else {
+                        Err(format!("invalid value '{}' (strings need quotes)", word))
+                    }
+                }
+            }
+        }
+    }
+}
+
+fn read_basic_string(chars: &[char], pos: &mut usize) -> Result<String, String> {
+    let mut out = String::new();
+    *pos += 1;
+    while let Some(&c) = chars.get(*pos) {
+        *pos += 1;
+        match c {
+            '"' => return Ok(out),
+            '\\' => {
+                let escaped = chars.get(*pos).ok_or("unclosed string")?;
+                *pos += 1;
+                out.push(match escaped {
+                    'n' => '\n',
+                    't' => '\t',
+                    '"' => '"',
+                    '\\' => '\\',
+                    other => return Err(format!("unknown escape '\\{}'", other)),
+                });
+            }
+            _ => out.push(c),
+        }
+    }
+    Err("unclosed string".to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::data_processing::Prefer;
+
+    #[test]
+    fn test_parse_config_filters() {
+        let text = r#"
+            # Things I never want to see
+            [filters]
+            exclude = ["trivia", 'karaoke']  # nights
+            blocked_venues = [
+                "Tootsie's Orchid Lounge",
+                "The #1 Bar",
+            ]
+            whole_word = true
+        "#;
+
+        let config = parse_config(text).unwrap();
+
+        assert_eq!(
+            config.filters,
+            Filters {
+                exclude: vec!["trivia".to_string(), "karaoke".to_string()],
+                blocked_venues: vec![
+                    "Tootsie's Orchid Lounge".to_string(),
+                    "The #1 Bar".to_string()
+                ],
+                whole_word: true,
+            }
+        );
+        assert_eq!(parse_config(""), Ok(Config::default()));
+        assert_eq!(
+            parse_config("timezone = \"Europe/London\"")
+                .unwrap()
+                .timezone,
+            Some("Europe/London".parse().unwrap())
+        );
+        assert_eq!(
+            parse_config("date_format = \"iso\"")
+                .unwrap()
+                .date_format
+                .as_deref(),
+            Some("%Y-%m-%dT%H:%M:%S")
+        );
+        assert_eq!(
+            parse_config("city = \"austin\"").unwrap().city.as_deref(),
+            Some("austin")
+        );
+        assert_eq!(parse_config("seen_days = 30").unwrap().seen_days, Some(30));
+        let email = parse_config("[email]\nsmtp_url = \"smtps://smtp.example.com\"\nfrom = \"events@example.com\"\nsend_empty = true").unwrap().email;
+        assert_eq!(
+            (email.smtp_url.as_deref(), email.sender(), email.send_empty),
+            (
+                Some("smtps://smtp.example.com"),
+                Some("events@example.com"),
+                true
+            )
+        );
+        assert!(parse_config("[email]\nserver = \"smtp.example.com\"").is_err());
+
+        assert_eq!(
+            parse_config("[ticketmaster]\napi_key = \"s3cret\"")
+                .unwrap()
+                .ticketmaster_api_key
+                .as_deref(),
+            Some("s3cret")
+        );
+        assert!(parse_config("[ticketmaster]\nkey = \"s3cret\"").is_err());
+        assert!(parse_config("seen_days = 0").is_err());
+        assert_eq!(
+            parse_config("history_file = \"/tmp/events.json\"")
+                .unwrap()
+                .history_file,
+            Some(PathBuf::from("/tmp/events.json"))
+        );
+    }
+
+    #[test]
+    fn test_parse_config_venue_aliases() {
+        let text = r#"
+            [venue_aliases]
+            "The Ryman" = "Ryman Auditorium"
+            "Ryman Aud." = "Ryman Auditorium"
+
+            [venues]
+            prefix_match = true
+        "#;
+
+        let aliases = parse_config(text).unwrap().venue_aliases;
+
+        assert_eq!(aliases.canonical("the ryman"), Some("Ryman Auditorium"));
+        assert_eq!(
+            aliases.canonical("Ryman Aud. (Nashville)"),
+            Some("Ryman Auditorium")
+        );
+        assert_eq!(
+            parse_config("[venue_aliases]\nRyman = 1")
+                .unwrap_err()
+                .to_string(),
+            "line 2: Ryman should be a string, not an integer"
+        );
+    }
+
+    #[test]
+    fn test_parse_config_interests() {
+        let config =
+            parse_config("[interests]\njazz = 3\n\"The Basement\" = 5\ntrivia = -2").unwrap();
+
+        assert_eq!(
+            config.interests,
+            vec![
+                Interest {
+                    term: "jazz".to_string(),
+                    weight: 3
+                },
+                Interest {
+                    term: "The Basement".to_string(),
+                    weight: 5
+                },
+                Interest {
+                    term: "trivia".to_string(),
+                    weight: -2
+                },
+            ]
+        );
+        assert_eq!(
+            parse_config("[interests]\njazz = true")
+                .unwrap_err()
+                .to_string(),
+            "line 2: jazz should be an integer weight, not a boolean"
+        );
+    }
+
+    #[test]
+    fn test_parse_config_home_and_venue_coordinates() {
+        let config = parse_config("[home]\nlat = 36.178\nlng = -86.751\nnear = \"Nashville, TN\"\n\n[venue_coordinates]\n\"Ryman Auditorium\" = [36.1612, -86.7785]").unwrap();
+
+        assert_eq!(
+            config.home,
+            Some(Coordinates {
+                lat: 36.178,
+                lng: -86.751
+            })
+        );
+        assert_eq!(config.near.as_deref(), Some("Nashville, TN"));
+        assert_eq!(
+            config.venue_coordinates,
+            vec![(
+                "Ryman Auditorium".to_string(),
+                Coordinates {
+                    lat: 36.1612,
+                    lng: -86.7785
+                }
+            )]
+        );
+        assert_eq!(
+            parse_config("[home]\nlat = 36").unwrap_err().to_string(),
+            "line 2: [home] needs both lat and lng"
+        );
+        assert_eq!(
+            parse_config("[home]\nlat = 100\nlng = 0")
+                .unwrap_err()
+                .to_string(),
+            "line 2: latitude 100 is outside -90 to 90"
+        );
+        assert_eq!(
+            parse_config("[venue_coordinates]\nRyman = [36.1]")
+                .unwrap_err()
+                .to_string(),
+            "line 2: Ryman should be a [lat, lng] pair, not an array"
+        );
+        assert_eq!(parse_value("1.5e2"), Ok(Value::Float(150.0)));
+        assert!(parse_value("12abc").is_err());
+    }
+
+    #[test]
+    fn test_parse_config_merge_preferences() {
+        let config = parse_config("[merge]\ndescription = \"shorter\"\nstart = \"first\"").unwrap();
+
+        assert_eq!(
+            config.merge,
+            MergePreferences {
+                description: Prefer::Shorter,
+                start: Prefer::First,
+                ..MergePreferences::default()
+            }
+        );
+        assert_eq!(
+            parse_config("[merge]\nstart = \"longer\"")
+                .unwrap_err()
+                .to_string(),
+            "line 2: start can prefer first or precise"
+        );
+    }
+
+    #[test]
+    fn test_parse_config_reports_lines() {
+        let error = |text: &str| parse_config(text).unwrap_err().to_string();
+
+        assert_eq!(
+            error("[filters]\nexclude = \"trivia\""),
+            "line 2: exclude should be an array of strings, not a string"
+        );
+        assert_eq!(
+            error("[filters]\n\nwhole_word = yes"),
+            "line 3: whole_word: invalid value 'yes' (strings need quotes)"
+        );
+        assert_eq!(
+            error("[filters]\nblocked_venue = []"),
+            "line 2: unknown setting 'blocked_venue' in [filters]"
+        );
+        assert_eq!(error("[filter]"), "line 1: unknown setting 'filter'");
+        assert_eq!(
+            error("\ntimezone = \"Mars\""),
+            format!("line 2: {}", "Mars".parse::<Zone>().unwrap_err())
+        );
+        assert_eq!(
+            error("timezone = 5"),
+            "line 1: timezone should be a string, not an integer"
+        );
+        assert!(error("date_format = \"%Q\"").starts_with("line 1: invalid date format '%Q'"));
+        assert_eq!(
+            error("[filters]\nexclude = [\"trivia\""),
+            "line 2: unclosed array for 'exclude'"
+        );
+        assert_eq!(
+            error("[filters]\nexclude"),
+            "line 2: expected 'key = value', found 'exclude'"
+        );
+    }
+
+    #[test]
+    fn test_parse_tables_arrays_and_quoted_keys() {
+        let tables = parse_tables("[[source]]\nname = \"a\"\nmax = 1_000\n[[source]]\n\"The Ryman\" = \"Ryman\\tAuditorium\"").unwrap();
+
+        assert_eq!(tables.len(), 3);
+        assert!(tables[1].is_array && tables[2].is_array);
+        assert_eq!(tables[1].entries[1].value, Value::Integer(1000));
+        assert_eq!(
+            tables[2].entries[0],
+            Entry {
+                key: "The Ryman".to_string(),
+                value: Value::String("Ryman\tAuditorium".to_string()),
+                line: 5
+            }
+        );
+    }
+
+    #[test]
+    fn test_quoted_keys_may_hold_equals_signs() {
+        let tables = parse_tables("\"a=b\" = 1\n'x = y' = \"c=d\"").unwrap();
+
+        assert_eq!(tables[0].entries[0].key, "a=b");
+        assert_eq!(tables[0].entries[0].value, Value::Integer(1));
+        assert_eq!(tables[0].entries[1].key, "x = y");
+        assert_eq!(tables[0].entries[1].value, Value::String("c=d".to_string()));
+    }
+
+    #[test]
+    fn test_load_missing_file_is_default() {
+        let path = env::temp_dir().join(format!(
+            "event_finder_config_missing_{}.toml",
+            std::process::id()
+        ));
+        assert_eq!(load(&path).unwrap(), Config::default());
+    }
+
+    #[test]
+    fn test_load_malformed_file_is_config_error() {
+        let path = env::temp_dir().join(format!(
+            "event_finder_config_malformed_{}.toml",
+            std::process::id()
+        ));
+        fs::write(&path, "timezone = \"UTC\"\n[filters\n").unwrap();
+
+        let result = load(&path);
+
+        let _ = fs::remove_file(&path);
+        match result {
+            Err(Error::Config {
+                path: error_path,
+                error,
+            }) => {
+                assert_eq!(error_path, path);
+                assert_eq!(error.line, 2);
+            }
+            other => panic!("expected a config error, got {:?}", other),
+        }
+    }
+}
@@ -1,145 +1,4878 @@
-//! # Data Processing Module
-//!
-//! This module is responsible for processing raw event data into a more usable format.
-//! It includes functionality to clean and format text, parse dates, and transform `Event` 
-//! instances into `ProcessedEvent` instances with more structured and clean data.
-
-use chrono::Local;
-use crate::html_parser::Event;
-
-/// Processes a vector of `Event` instances into `ProcessedEvent` instances.
-///
-/// This function takes raw event data and applies cleaning and formatting to the text and dates.
-/// It ensures that the data is in a consistent and usable format.
-///
-/// # Arguments
-///
-/// * `events` - A vector of `Event` instances representing the raw event data.
-///
-/// # Returns
-///
-/// A vector of `ProcessedEvent` instances with cleaned and formatted data.
-pub fn process_data(events: Vec<Event>) -> Vec<ProcessedEvent> {
-    events.into_iter().map(|event| {
-        ProcessedEvent {
-            name: clean_text(&event.name),
-            start_date: parse_date(&event.start_date, true),
-            end_date: parse_date(&event.end_date, false),
-            location: clean_text(&event.location),
-            url: event.url,
-        }
-    }).collect()
-}
-
-/// Cleans and trims the given text.
-///
-/// # Arguments
-///
-/// * `text` - A string slice representing the text to be cleaned.
-///
-/// # Returns
-///
-/// A `String` with leading and trailing whitespace removed.
-fn clean_text(text: &str) -> String {
-    text.trim().to_string()
-}
-
-/// Parses a date string and returns a formatted date or a default value.
-///
-/// If the date string is empty and `is_start_date` is true, it returns today's date.
-/// If the date string is empty and `is_start_date` is false, it returns "N/A".
-///
-/// # Arguments
-///
-/// * `date_str` - A string slice representing the date to be parsed.
-/// * `is_start_date` - A boolean indicating whether the date is a start date.
-///
-/// # Returns
-///
-/// A `String` representing the parsed date or a default value.
-fn parse_date(date_str: &str, is_start_date: bool) -> String {
-    if date_str.trim().is_empty() {
-        if is_start_date {
-            // For start_date, return today's date if empty
-            today_date()
-        } else {
-            // For end_date, return "N/A"
-            "N/A".to_string()
-        }
-    } else {
-        date_str.trim().to_string() // Return the date as-is
-    }
-}
-
-/// Returns today's date in a formatted string.
-///
-/// # Returns
-///
-/// A `String` representing today's date in the format "%B %e".
-fn today_date() -> String {
-    let today = Local::now();
-    today.format("%B%e").to_string() //%B is the full month name, %e is the day of the month
-}
-
-/// A struct representing a processed event with cleaned and formatted data.
-#[derive(Debug, Clone, PartialEq)]
-pub struct ProcessedEvent {
-    pub name: String,
-    pub start_date: String, 
-    pub end_date: String,    
-    pub location: String,
-    pub url: String,
-}
-
-
-#[cfg(test)]
-mod tests {
-    use super::*;
-
-    #[test]
-    fn test_process_data() {
-        // Create mock raw events
-        let raw_events = vec![
-            Event {
-                name: "Concert".to_string(),
-                start_date: "January 1, 2023".to_string(),
-                end_date: "".to_string(),
-                location: "Park".to_string(),
-                url: "http://example.com/concert".to_string(),
-            },
-            Event {
-                name: "Festival".to_string(),
-                start_date: "January 2, 2023".to_string(),
-                end_date: "January 3, 2023           ".to_string(),
-                location: "Beach".to_string(),
-                url: "http://example.com/festival".to_string(),
-            },
-        ];
-
-        // Call the process_data function
-        let processed_events = process_data(raw_events);
-
-        // Define expected processed events
-        let expected_events = vec![
-            ProcessedEvent {
-                name: "Concert".to_string(),
-                start_date: "January 1, 2023".to_string(),
-                end_date: "N/A".to_string(),
-                location: "Park".to_string(),
-                url: "http://example.com/concert".to_string(),
-            },
-            ProcessedEvent {
-                name: "Festival".to_string(),
-                start_date: "January 2, 2023".to_string(),
-                end_date: "January 3, 2023".to_string(),
-                location: "Beach".to_string(),
-                url: "http://example.com/festival".to_string(),
-            },
-        ];
-
-        // Assertions
-        assert_eq!(processed_events, expected_events);
-    }
-}
-
-
+//! # Data Processing Module
+//!
+//! This module is responsible for processing raw event data into a more usable format.
+//! It includes functionality to clean and format text, parse dates, and transform `Event`
+//! instances into `ProcessedEvent` instances with more structured and clean data.
+
+use std::fmt;
+use std::str::FromStr;
+use std::sync::OnceLock;
+
+use crate::html_parser::{self, Event, EventStatus};
+use crate::sources::{Categories, Category};
+use crate::timezone::Zone;
+use chrono::format::{Item, StrftimeItems};
+use chrono::{
+    DateTime, Datelike, Duration, FixedOffset, NaiveDate, NaiveDateTime, NaiveTime, Weekday,
+};
+use rand::distributions::{Distribution, WeightedIndex};
+use rand::seq::SliceRandom;
+use rand::Rng;
+use regex::Regex;
+use scraper::Html;
+use serde::de::Error as _;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use serde_json::{json, Value};
+use unicode_normalization::char::is_combining_mark;
+use unicode_normalization::UnicodeNormalization;
+use url::Url;
+
+/// Processes a vector of `Event` instances into `ProcessedEvent` instances.
+///
+/// This function takes raw event data and applies cleaning and formatting to the text and dates.
+/// It ensures that the data is in a consistent and usable format.
+///
+/// Dates are read as times in `zone`; dates with a UTC offset are converted to it. Problems
+/// that don't stop an event from being listed, like a start date that couldn't be read, are
+/// returned as warnings next to the events.
+///
+/// # Arguments
+///
+/// * `events` - A vector of `Event` instances representing the raw event data.
+/// * `zone` - The home timezone.
+///
+/// # Returns
+///
+/// A vector of `ProcessedEvent` instances with cleaned and formatted data, and the warnings
+/// about them in event order.
+pub fn process_data(events: Vec<Event>, zone: Zone) -> (Vec<ProcessedEvent>, Vec<DataWarning>) {
+    let options = ProcessingOptions::new(zone);
+    let mut warnings = Vec::new();
+    let processed = events
+        .into_iter()
+        .enumerate()
+        .map(|(event_index, event)| {
+            let (processed, issues) = ProcessedEvent::read_raw(event, &options);
+            warnings.extend(issues.into_iter().map(|(kind, text)| DataWarning {
+                event_index,
+                kind,
+                text,
+            }));
+            processed
+        })
+        .collect();
+    (processed, warnings)
+}
+
+/// Names longer than this many characters are warned about; a selector probably matched a
+/// whole paragraph.
+pub const LONG_NAME_CHARS: usize = 120;
+
+/// A data-quality problem `process_data` found in one event.
+#[derive(Debug, Clone, PartialEq)]
+pub struct DataWarning {
+    /// The position of the event among those given to `process_data`.
+    pub event_index: usize,
+    /// What's wrong.
+    pub kind: DataIssue,
+    /// The text the problem was found in: the unreadable start, the dropped end, the long
+    /// name, or the location as the source gave it.
+    pub text: String,
+}
+
+/// Why a `DataWarning` was recorded.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DataIssue {
+    /// The start date was given but isn't in a format that can be read, so the event can't be
+    /// sorted or filtered by date.
+    UnreadableDate,
+    /// The end is before the start, so it was dropped.
+    EndBeforeStart,
+    /// The name is longer than `LONG_NAME_CHARS`.
+    LongName,
+    /// The source gave a location that is empty once cleaned.
+    EmptyLocation,
+}
+
+/// What processing a raw event depends on besides the event itself.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ProcessingOptions {
+    /// The home timezone dates are read in.
+    pub zone: Zone,
+    /// Today's date, which a missing start date or year is taken from.
+    pub today: NaiveDate,
+}
+
+impl ProcessingOptions {
+    /// Creates options for the zone, with today's date in it.
+    pub fn new(zone: Zone) -> ProcessingOptions {
+        ProcessingOptions {
+            zone,
+            today: zone.today(),
+        }
+    }
+}
+
+/// Gives events without an end one a fixed duration after their start.
+///
+/// Only `end` is set; `end_date` stays `None` so the estimate isn't shown as a date the source
+/// gave. Events starting at midnight are left alone, since they usually have only a date.
+///
+/// # Arguments
+///
+/// * `events` - The processed events.
+/// * `duration` - How long the source's events usually last.
+///
+/// # Returns
+///
+/// The events, with an estimated end where one was missing.
+pub fn fill_missing_ends(events: Vec<ProcessedEvent>, duration: Duration) -> Vec<ProcessedEvent> {
+    events
+        .into_iter()
+        .map(|event| match (event.start, event.end) {
+            (Some(start), None) if start.time() != NaiveTime::MIN => ProcessedEvent {
+                end: Some(start + duration),
+                ..event
+            },
+            _ => event,
+        })
+        .collect()
+}
+
+/// Returns `true` if the source gave the event a start date or time.
+pub fn has_start(event: &Event) -> bool {
+    !clean_text(&event.start_date).is_empty()
+        || event
+            .start_time
+            .as_deref()
+            .is_some_and(|t| !clean_text(t).is_empty())
+}
+
+/// The `chrono` formats tried, in order, for dates written with a year after `normalize_date`.
+const DATE_TIME_FORMATS: &[&str] = &[
+    "%d %B %Y %I:%M %p",
+    "%d %B %Y %H:%M",
+    "%B %d %Y %I:%M %p",
+    "%B %d %Y %H:%M",
+    "%Y-%m-%d %H:%M:%S",
+    "%Y-%m-%d %H:%M",
+    "%Y-%m-%dT%H:%M:%S",
+    "%Y-%m-%dT%H:%M",
+    "%m/%d/%Y %I:%M %p",
+    "%m/%d/%Y %H:%M",
+];
+
+/// The date-only formats tried after `DATE_TIME_FORMATS`; the event starts at midnight.
+const DATE_FORMATS: &[&str] = &["%d %B %Y", "%B %d %Y", "%Y-%m-%d", "%m/%d/%Y"];
+
+/// Parses a date as sources write it into a date and time.
+///
+/// RFC 3339 timestamps (`2024-05-02T19:00:00-05:00`, as JSON-LD gives them) are converted to
+/// `zone`; other dates are taken to be written in it. Written dates such as `Thursday 2 May 2024, 7:30 PM`
+/// or `May 2 @ 7:00 pm` are read after dropping the weekday and punctuation; a date without a
+/// year is taken to be in the coming months. A date without a time starts at midnight. Text no
+/// strict format reads is tried once more with `parse_informal`, so `Doors 7PM` and `Sat 8pm`
+/// are dated too.
+///
+/// # Arguments
+///
+/// * `text` - The date text.
+/// * `today` - Today's date in the home timezone, which missing years and relative days are read from.
+/// * `zone` - The home timezone.
+///
+/// # Returns
+///
+/// The date and time, or `None` if the text isn't a date in any known format.
+pub fn parse_datetime(text: &str, today: NaiveDate, zone: Zone) -> Option<NaiveDateTime> {
+    parse_datetime_traced(text, today, zone).map(|(datetime, _)| datetime)
+}
+
+/// Which pass of `parse_datetime_traced` read a date.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DateParse {
+    /// An RFC 3339 timestamp, or an RFC 2822 one as RSS feeds write, with a UTC offset.
+    Timestamp,
+    /// One of the strict written formats.
+    Strict,
+    /// The tolerant fallback, `parse_informal`.
+    Informal,
+}
+
+/// Displays the pass as `--debug-selectors` shows it, e.g. `informal`.
+impl fmt::Display for DateParse {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(match self {
+            DateParse::Timestamp => "timestamp",
+            DateParse::Strict => "strict format",
+            DateParse::Informal => "informal",
+        })
+    }
+}
+
+/// Parses a date like `parse_datetime`, also returning which pass read it.
+///
+/// # Arguments
+///
+/// * `text` - The date text.
+/// * `today` - Today's date in the home timezone, which years and relative days are read from.
+/// * `zone` - The home timezone.
+///
+/// # Returns
+///
+/// The date and time and the pass that read it, or `None` if no pass could.
+pub fn parse_datetime_traced(
+    text: &str,
+    today: NaiveDate,
+    zone: Zone,
+) -> Option<(NaiveDateTime, DateParse)> {
+    let text = clean_text(text);
+    if let Ok(datetime) = DateTime::parse_from_rfc3339(&text).or_else(|_| parse_rfc2822(&text)) {
+        return Some((zone.to_local(datetime.naive_utc()), DateParse::Timestamp));
+    }
+    parse_strict(&text, today)
+        .map(|datetime| (datetime, DateParse::Strict))
+        .or_else(|| parse_informal(&text, today).map(|datetime| (datetime, DateParse::Informal)))
+}
+
+/// Reads an RFC 2822 date such as an RSS `pubDate`, `Thu, 02 May 2024 19:00:00 -0500`; the
+/// `GMT` and `UT` feeds often write for the offset are read as UTC.
+fn parse_rfc2822(text: &str) -> Result<DateTime<FixedOffset>, chrono::ParseError> {
+    let utc = [" GMT", " UT", " UTC", " Z"]
+        .iter()
+        .find_map(|zone| text.strip_suffix(zone));
+    match utc {
+        Some(rest) => DateTime::parse_from_rfc2822(&format!("{} +0000", rest)),
+        None => DateTime::parse_from_rfc2822(text),
+    }
+}
+
+/// Reads a date in one of `DATE_TIME_FORMATS` or `DATE_FORMATS`, after `normalize_date`.
+fn parse_strict(text: &str, today: NaiveDate) -> Option<NaiveDateTime> {
+    let normalized = normalize_date(text);
+    let with_year = |text: &str| {
+        DATE_TIME_FORMATS
+            .iter()
+            .find_map(|format| NaiveDateTime::parse_from_str(text, format).ok())
+            .or_else(|| {
+                DATE_FORMATS.iter().find_map(|format| {
+                    NaiveDate::parse_from_str(text, format)
+                        .ok()?
+                        .and_hms_opt(0, 0, 0)
+                })
+            })
+    };
+    if let Some(datetime) = with_year(&normalized) {
+        return Some(datetime);
+    }
+
+    // No year: this year, unless that is long past, in which case the listing means next year
+    let datetime = with_year(&add_year(&normalized, today.year()))?;
+    if datetime.date() < today - Duration::days(180) {
+        return with_year(&add_year(&normalized, today.year() + 1));
+    }
+    Some(datetime)
+}
+
+/// Words that say which part of the night a time is for, skipped by `parse_informal`.
+const INFORMAL_FILLER: &[&str] = &[
+    "doors", "door", "open", "opens", "show", "shows", "showtime", "music", "starts", "start",
+    "begins", "at", "from", "this", "night", "evening",
+];
+
+/// Reads the colloquial dates sources write, such as `Doors 7:00 PM`, `Tonight, 9pm`,
+/// `Sat 8pm`, or `May 3 · 19:30`.
+///
+/// The first time of day in the text, in a 12- or 24-hour clock, is the start; an hour alone
+/// after `at` (`Tonight at 9`) is read as evening. Filler like `Doors` and `Show` is skipped,
+/// and what is left is the day: `today` or `tonight`, `tomorrow`, a weekday (the next one,
+/// which may be today), or a date a strict format can read. With no day the event is today,
+/// and with no time it starts at midnight.
+///
+/// # Arguments
+///
+/// * `text` - The date text.
+/// * `today` - Today's date in the home timezone.
+///
+/// # Returns
+///
+/// The date and time, or `None` if the text names neither a day nor a time, or has words that
+/// aren't either.
+fn parse_informal(text: &str, today: NaiveDate) -> Option<NaiveDateTime> {
+    static CLOCK: OnceLock<Regex> = OnceLock::new();
+    static BARE_HOUR: OnceLock<Regex> = OnceLock::new();
+    let clock = CLOCK.get_or_init(|| {
+        Regex::new(r"(?i)\b\d{1,2}(?::\d{2})?\s*[ap]\.?\s*m\b\.?|\b\d{1,2}:\d{2}\b").unwrap()
+    });
+    let bare_hour = BARE_HOUR.get_or_init(|| Regex::new(r"(?i)\bat\s+(\d{1,2})\b").unwrap());
+
+    let text = text
+        .to_lowercase()
+        .replace(" / ", " ")
+        .replace(['·', '•', '|', ',', '@', '–', '—'], " ");
+    let text = text.trim_end_matches(['!', '.']);
+    let (time, rest) = match clock.find(text) {
+        Some(found) => (
+            Some(parse_clock_time(found.as_str())?),
+            clock.replace_all(text, " ").into_owned(),
+        ),
+        None => match bare_hour.captures(text) {
+            Some(caps) => {
+                let hour: u32 = caps[1].parse().ok()?;
+                let hour = if (1..12).contains(&hour) {
+                    hour + 12
+                } else {
+                    hour
+                };
+                (
+                    Some(NaiveTime::from_hms_opt(hour, 0, 0)?),
+                    bare_hour.replace_all(text, " ").into_owned(),
+                )
+            }
+            None => (None, text.to_string()),
+        },
+    };
+    let words: Vec<&str> = rest
+        .split_whitespace()
+        .map(|word| word.trim_end_matches(':'))
+        .filter(|word| !word.is_empty() && !INFORMAL_FILLER.contains(word))
+        .collect();
+    let day = match words.as_slice() {
+        [] if time.is_none() => return None,
+        [] | ["today"] | ["tonight"] => today,
+        ["tomorrow"] | ["tmrw"] => today + Duration::days(1),
+        [word] if parse_weekday(word).is_some() => {
+            let weekday = parse_weekday(word)?;
+            let ahead =
+                (weekday.num_days_from_monday() + 7 - today.weekday().num_days_from_monday()) % 7;
+            today + Duration::days(ahead.into())
+        }
+        words => parse_strict(&words.join(" "), today)
+            .filter(|datetime| datetime.time() == NaiveTime::MIN)?
+            .date(),
+    };
+    Some(day.and_time(time.unwrap_or(NaiveTime::MIN)))
+}
+
+/// Reads a weekday name or its abbreviation, e.g. `saturday`, `sat`, or `sat.`.
+fn parse_weekday(word: &str) -> Option<Weekday> {
+    let word = word.trim_end_matches('.');
+    let names = [
+        "monday",
+        "tuesday",
+        "wednesday",
+        "thursday",
+        "friday",
+        "saturday",
+        "sunday",
+    ];
+    let index = names
+        .iter()
+        .position(|name| word.len() >= 3 && name.starts_with(word))?;
+    Weekday::try_from(index as u8).ok()
+}
+
+/// Rewrites a written date into the shape `DATE_TIME_FORMATS` expect: no weekday, no `@` or
+/// commas, ordinal suffixes removed, and times as `7:30 PM`.
+fn normalize_date(text: &str) -> String {
+    static WEEKDAY: OnceLock<Regex> = OnceLock::new();
+    static ORDINAL: OnceLock<Regex> = OnceLock::new();
+    static TIME: OnceLock<Regex> = OnceLock::new();
+    let weekday = WEEKDAY
+        .get_or_init(|| Regex::new(r"(?i)^(mon|tue|wed|thu|fri|sat|sun)[a-z]*\.?,?\s+").unwrap());
+    let ordinal = ORDINAL.get_or_init(|| Regex::new(r"(?i)\b(\d{1,2})(st|nd|rd|th)\b").unwrap());
+    let time = TIME
+        .get_or_init(|| Regex::new(r"(?i)\b(\d{1,2})(?::(\d{2}))?\s*([ap])\.?m\.?(\s|$)").unwrap());
+
+    let text = weekday.replace(text, "");
+    let text = ordinal.replace_all(&text, "$1");
+    let text = text.replace(['@', ','], " ");
+    let text = time.replace_all(&text, |caps: &regex::Captures| {
+        format!(
+            "{}:{} {}M ",
+            &caps[1],
+            caps.get(2).map_or("00", |m| m.as_str()),
+            caps[3].to_uppercase()
+        )
+    });
+    text.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+/// Inserts a year after the day of a normalized date without one (`May 2 7:00 PM` becomes
+/// `May 2 2024 7:00 PM`).
+fn add_year(text: &str, year: i32) -> String {
+    let mut words: Vec<String> = text.split(' ').map(str::to_string).collect();
+    let date_words = words
+        .iter()
+        .position(|w| w.contains(':'))
+        .unwrap_or(words.len());
+    words.insert(date_words, year.to_string());
+    words.join(" ")
+}
+
+/// Keeps only the events that belong to at least one of the requested categories.
+///
+/// # Arguments
+///
+/// * `events` - The processed events to filter.
+/// * `requested` - The categories to keep.
+///
+/// # Returns
+///
+/// The events with any category in `requested`.
+pub fn filter_by_category(
+    events: Vec<ProcessedEvent>,
+    requested: &[Category],
+) -> Vec<ProcessedEvent> {
+    events
+        .into_iter()
+        .filter(|event| requested.iter().any(|c| event.categories.contains(c)))
+        .collect()
+}
+
+/// The fields `Transform`s can be configured for.
+pub const TRANSFORMABLE_FIELDS: &[&str] = &[
+    "name",
+    "start_date",
+    "end_date",
+    "location",
+    "description",
+    "price",
+];
+
+/// A text cleanup step a source can configure for one of its fields.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Transform {
+    /// `trim`: remove leading and trailing whitespace.
+    Trim,
+    /// `collapse_whitespace`: turn runs of whitespace into single spaces.
+    CollapseWhitespace,
+    /// `strip_emoji`: remove emoji and the joiners and selectors that combine them.
+    StripEmoji,
+    /// `remove_suffix("...")`: remove the text from the end, if it's there.
+    RemoveSuffix(String),
+    /// `strip_chars("...")`: remove each of the characters wherever they appear.
+    StripChars(String),
+    /// `trim_chars("...")`: remove the characters and whitespace from both ends, e.g. trailing pipes.
+    TrimChars(String),
+    /// `titlecase`: capitalize the first letter of each word and lowercase the rest.
+    Titlecase,
+    /// `uppercase`: uppercase everything.
+    Uppercase,
+}
+
+impl Transform {
+    /// Applies the transform to a field's text.
+    pub fn apply(&self, text: &str) -> String {
+        match self {
+            Transform::Trim => text.trim().to_string(),
+            Transform::CollapseWhitespace => text.split_whitespace().collect::<Vec<_>>().join(" "),
+            Transform::StripEmoji => text.chars().filter(|c| !is_emoji(*c)).collect(),
+            Transform::RemoveSuffix(suffix) => text
+                .strip_suffix(suffix.as_str())
+                .unwrap_or(text)
+                .to_string(),
+            Transform::StripChars(chars) => text.chars().filter(|c| !chars.contains(*c)).collect(),
+            Transform::TrimChars(chars) => text
+                .trim_matches(|c: char| c.is_whitespace() || chars.contains(c))
+                .to_string(),
+            Transform::Titlecase => text
+                .split(' ')
+                .map(|word| {
+                    let mut chars = word.chars();
+                    match chars.next() {
+                        Some(first) => first
+                            .to_uppercase()
+                            .chain(chars.flat_map(char::to_lowercase))
+                            .collect(),
+                        None => String::new(),
+                    }
+                })
+                .collect::<Vec<_>>()
+                .join(" "),
+            Transform::Uppercase => text.to_uppercase(),
+        }
+    }
+}
+
+/// Returns `true` for emoji and the characters used to build emoji sequences.
+fn is_emoji(c: char) -> bool {
+    matches!(c as u32,
+        0x1F000..=0x1FAFF // pictographs, emoticons, transport, flags, and supplemental symbols
+        | 0x2600..=0x27BF // miscellaneous symbols and dingbats
+        | 0x2B00..=0x2BFF // arrows and stars such as ⭐
+        | 0xFE0F | 0x200D | 0x20E3)
+}
+
+/// Why a transform name couldn't be parsed.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ParseTransformError(String);
+
+impl fmt::Display for ParseTransformError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+/// Builds a transform that takes a text argument.
+type MakeTransform = fn(String) -> Transform;
+
+impl FromStr for Transform {
+    type Err = ParseTransformError;
+
+    /// Parses a transform as written in a source config, e.g. `titlecase` or
+    /// `remove_suffix(" - Nashville, TN")`. Arguments may be given with or without quotes.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim();
+        let with_argument: [(&str, MakeTransform); 3] = [
+            ("remove_suffix", Transform::RemoveSuffix),
+            ("strip_chars", Transform::StripChars),
+            ("trim_chars", Transform::TrimChars),
+        ];
+        for (name, make) in with_argument {
+            let Some(argument) = s
+                .strip_prefix(name)
+                .and_then(|rest| rest.strip_prefix('(')?.strip_suffix(')'))
+            else {
+                continue;
+            };
+            let argument = argument
+                .strip_prefix('"')
+                .and_then(|a| a.strip_suffix('"'))
+                .unwrap_or(argument);
+            if argument.is_empty() {
+                return Err(ParseTransformError(format!(
+                    "{} needs the text to remove",
+                    name
+                )));
+            }
+            return Ok(make(argument.to_string()));
+        }
+        match s {
+            "trim" => Ok(Transform::Trim),
+            "collapse_whitespace" => Ok(Transform::CollapseWhitespace),
+            "strip_emoji" => Ok(Transform::StripEmoji),
+            "titlecase" => Ok(Transform::Titlecase),
+            "uppercase" => Ok(Transform::Uppercase),
+            _ => Err(ParseTransformError(format!("unknown transform '{}'", s))),
+        }
+    }
+}
+
+/// Runs a source's configured transforms over its processed events.
+///
+/// Transforms that don't parse are skipped; `SiteConfig::validate` reports them. A field
+/// transformed down to nothing is dropped when it is optional.
+///
+/// # Arguments
+///
+/// * `events` - The processed events of one source.
+/// * `transforms` - The transforms per field, in the order they run.
+///
+/// # Returns
+///
+/// The events with the transforms applied.
+pub fn apply_transforms(
+    events: Vec<ProcessedEvent>,
+    transforms: &[(String, Vec<String>)],
+) -> Vec<ProcessedEvent> {
+    if transforms.is_empty() {
+        return events;
+    }
+    let transforms: Vec<(&str, Vec<Transform>)> = transforms
+        .iter()
+        .map(|(field, steps)| {
+            (
+                field.as_str(),
+                steps.iter().filter_map(|step| step.parse().ok()).collect(),
+            )
+        })
+        .collect();
+    let run = |field: &str, text: &str| {
+        transforms
+            .iter()
+            .filter(|(f, _)| *f == field)
+            .flat_map(|(_, steps)| steps)
+            .fold(text.to_string(), |text, step| step.apply(&text))
+    };
+    let run_optional =
+        |field: &str, text: Option<String>| text.map(|t| run(field, &t)).filter(|t| !t.is_empty());
+
+    events
+        .into_iter()
+        .map(|event| ProcessedEvent {
+            name: run("name", &event.name),
+            start_date: run("start_date", &event.start_date),
+            end_date: run_optional("end_date", event.end_date),
+            location: run_optional("location", event.location),
+            description: run_optional("description", event.description),
+            price: run_optional("price", event.price),
+            ..event
+        })
+        .collect()
+}
+
+/// Normalizes tags for display and filtering: cleaned, lowercased, and without duplicates.
+///
+/// # Arguments
+///
+/// * `tags` - The tags as extracted from the source.
+///
+/// # Returns
+///
+/// The normalized tags, in the order they first appeared.
+pub fn normalize_tags(tags: &[String]) -> Vec<String> {
+    let mut normalized: Vec<String> = Vec::new();
+    for tag in tags {
+        let tag = clean_text(tag).to_lowercase();
+        if !tag.is_empty() && !normalized.contains(&tag) {
+            normalized.push(tag);
+        }
+    }
+    normalized
+}
+
+/// What `sort_events` orders events by.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum SortKey {
+    /// The parsed start date and time.
+    #[default]
+    Start,
+    /// The name, ignoring case.
+    Name,
+    /// The location, ignoring case.
+    Location,
+    /// The interest score from `rank_events`, highest first; ties are ordered by start.
+    Score,
+}
+
+impl FromStr for SortKey {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "date" | "start" => Ok(SortKey::Start),
+            "name" => Ok(SortKey::Name),
+            "location" | "venue" => Ok(SortKey::Location),
+            "score" | "interest" => Ok(SortKey::Score),
+            _ => Err(format!(
+                "unknown sort key '{}' (expected date, name, location, or score)",
+                s
+            )),
+        }
+    }
+}
+
+impl fmt::Display for SortKey {
+    /// Writes the name `--sort` takes.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            SortKey::Start => "date",
+            SortKey::Name => "name",
+            SortKey::Location => "location",
+            SortKey::Score => "score",
+        })
+    }
+}
+
+/// The direction `sort_events` sorts in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Order {
+    #[default]
+    Ascending,
+    Descending,
+}
+
+/// Sorts events by a key.
+///
+/// Events missing the key (an unreadable start date or no location) go last in either order,
+/// and events with equal keys keep their listing order.
+///
+/// # Arguments
+///
+/// * `events` - The events to sort in place.
+/// * `key` - What to sort by.
+/// * `order` - Whether to sort ascending or descending.
+pub fn sort_events(events: &mut [ProcessedEvent], key: SortKey, order: Order) {
+    fn directed<T: Ord>(a: Option<T>, b: Option<T>, order: Order) -> std::cmp::Ordering {
+        match (a, b, order) {
+            (Some(a), Some(b), Order::Ascending) => a.cmp(&b),
+            (Some(a), Some(b), Order::Descending) => b.cmp(&a),
+            (a, b, _) => b.is_none().cmp(&a.is_none()).reverse(),
+        }
+    }
+
+    events.sort_by(|a, b| match key {
+        SortKey::Start => directed(a.start, b.start, order)
+            .then_with(|| normalize_key(&a.name).cmp(&normalize_key(&b.name))),
+        SortKey::Name => directed(
+            Some(normalize_key(&a.name)),
+            Some(normalize_key(&b.name)),
+            order,
+        ),
+        SortKey::Location => directed(
+            a.location.as_deref().map(normalize_key),
+            b.location.as_deref().map(normalize_key),
+            order,
+        ),
+        SortKey::Score => directed(
+            Some(std::cmp::Reverse(a.score)),
+            Some(std::cmp::Reverse(b.score)),
+            order,
+        )
+        .then_with(|| directed(a.start, b.start, Order::Ascending)),
+    });
+}
+
+/// Something the user is interested in, such as a genre, an artist, or a venue, and how much.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Interest {
+    pub term: String,
+    /// What a match adds to an event's score; negative for things the user would rather avoid.
+    pub weight: i64,
+}
+
+/// Scores an event against the user's interests.
+///
+/// An interest matches when its term appears as a whole word or phrase, compared with
+/// `normalize_key`, in the event's name, tags, location, or description. Each interest counts once,
+/// however many fields mention it, so the score is the sum of the matched weights.
+///
+/// # Arguments
+///
+/// * `event` - The event to score.
+/// * `interests` - The user's interests.
+///
+/// # Returns
+///
+/// The score, and the terms that matched in the order the interests are listed.
+pub fn score_event(event: &ProcessedEvent, interests: &[Interest]) -> (i64, Vec<String>) {
+    let fields: Vec<String> = std::iter::once(&event.name)
+        .chain(&event.tags)
+        .chain(event.location.as_ref())
+        .chain(event.description.as_ref())
+        .map(|text| normalize_key(text))
+        .collect();
+    let matched: Vec<&Interest> = interests
+        .iter()
+        .filter(|interest| {
+            let term = normalize_key(interest.term.trim());
+            !term.is_empty() && fields.iter().any(|text| contains_word(text, &term))
+        })
+        .collect();
+    (
+        matched.iter().map(|i| i.weight).sum(),
+        matched.iter().map(|i| i.term.clone()).collect(),
+    )
+}
+
+/// Fills in each event's `score` and `matched_interests` with `score_event`.
+pub fn rank_events(events: Vec<ProcessedEvent>, interests: &[Interest]) -> Vec<ProcessedEvent> {
+    events
+        .into_iter()
+        .map(|event| {
+            let (score, matched_interests) = score_event(&event, interests);
+            ProcessedEvent {
+                score,
+                matched_interests,
+                ..event
+            }
+        })
+        .collect()
+}
+
+/// A named span of days, resolved against today's date with `range`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DatePreset {
+    Today,
+    Tomorrow,
+    /// Friday to Sunday of this week, or the rest of it once the weekend has started.
+    Weekend,
+    /// Today and the six days after it.
+    Week,
+}
+
+impl DatePreset {
+    /// Returns the first and last day of the preset, both inclusive.
+    ///
+    /// # Arguments
+    ///
+    /// * `today` - The day the preset is relative to.
+    pub fn range(self, today: NaiveDate) -> (NaiveDate, NaiveDate) {
+        match self {
+            DatePreset::Today => (today, today),
+            DatePreset::Tomorrow => (today + Duration::days(1), today + Duration::days(1)),
+            DatePreset::Weekend => {
+                let weekday = i64::from(today.weekday().num_days_from_monday());
+                let friday = today + Duration::days(4 - weekday);
+                (friday.max(today), friday + Duration::days(2))
+            }
+            DatePreset::Week => (today, today + Duration::days(6)),
+        }
+    }
+}
+
+impl FromStr for DatePreset {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "today" | "tonight" => Ok(DatePreset::Today),
+            "tomorrow" => Ok(DatePreset::Tomorrow),
+            "weekend" => Ok(DatePreset::Weekend),
+            "week" => Ok(DatePreset::Week),
+            _ => Err(format!(
+                "unknown date range '{}' (expected today, tomorrow, weekend, or week)",
+                s
+            )),
+        }
+    }
+}
+
+/// Keeps the events that take place on at least one day between `from` and `to`.
+///
+/// An event with an end counts for every day from its start to its end, so a festival that
+/// started yesterday still shows up today. Events whose start couldn't be read are kept
+/// unless `keep_undated` is `false`.
+///
+/// # Arguments
+///
+/// * `events` - The processed events to filter.
+/// * `from` - The first day of the range, inclusive.
+/// * `to` - The last day of the range, inclusive.
+/// * `keep_undated` - Whether events without a parsed start are kept.
+///
+/// # Returns
+///
+/// The events overlapping the range, in their original order.
+pub fn filter_by_range(
+    events: Vec<ProcessedEvent>,
+    from: NaiveDate,
+    to: NaiveDate,
+    keep_undated: bool,
+) -> Vec<ProcessedEvent> {
+    events
+        .into_iter()
+        .filter(|event| match event.start {
+            Some(start) => {
+                let last = event
+                    .end
+                    .map_or(start.date(), |end| end.date().max(start.date()));
+                start.date() <= to && last >= from
+            }
+            None => keep_undated,
+        })
+        .collect()
+}
+
+/// Events spanning more days than this aren't expanded by `expand_multi_day` by default.
+pub const DEFAULT_MAX_SPAN_DAYS: usize = 14;
+
+/// Lists events that span several days once for each day.
+///
+/// An event whose parsed start and end fall on different days becomes one entry per day,
+/// named e.g. `Festival (day 2 of 3)`. Each entry starts on its day at the original start
+/// time; only the last keeps the end. Events spanning more than `max_days` days are left as
+/// one entry, since an end that far off is more likely bad data or a long-running exhibition
+/// than something to list every day.
+///
+/// # Arguments
+///
+/// * `events` - The processed events.
+/// * `max_days` - The most days an event may span and still be expanded.
+///
+/// # Returns
+///
+/// The events, with each expanded event's days in its place.
+pub fn expand_multi_day(events: Vec<ProcessedEvent>, max_days: usize) -> Vec<ProcessedEvent> {
+    let mut expanded = Vec::with_capacity(events.len());
+    for event in events {
+        let (Some(start), Some(end)) = (event.start, event.end) else {
+            expanded.push(event);
+            continue;
+        };
+        let days = (end.date() - start.date()).num_days() + 1;
+        if days < 2 || days as usize > max_days {
+            expanded.push(event);
+            continue;
+        }
+        for day in 1..=days {
+            let start = (start.date() + Duration::days(day - 1)).and_time(start.time());
+            let last = day == days;
+            expanded.push(ProcessedEvent {
+                name: format!("{} (day {} of {})", event.name, day, days),
+                start_date: match day {
+                    1 => event.start_date.clone(),
+                    _ if start.time() == NaiveTime::MIN => {
+                        start.format(FALLBACK_DATE_FORMAT).to_string()
+                    }
+                    _ => start.format("%B %-d %-I:%M %p").to_string(),
+                },
+                start: Some(start),
+                end_date: if last { event.end_date.clone() } else { None },
+                end: if last { event.end } else { None },
+                ..event.clone()
+            });
+        }
+    }
+    expanded
+}
+
+/// What `group_events` groups events by.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GroupKey {
+    /// The day of the parsed start.
+    Date,
+    /// The location, ignoring case.
+    Venue,
+    /// The source the event was fetched from, or else the site its URL points to.
+    Source,
+}
+
+impl FromStr for GroupKey {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "date" | "day" => Ok(GroupKey::Date),
+            "venue" | "location" => Ok(GroupKey::Venue),
+            "source" | "site" => Ok(GroupKey::Source),
+            _ => Err(format!(
+                "unknown grouping '{}' (expected date, venue, source, or none)",
+                s
+            )),
+        }
+    }
+}
+
+impl fmt::Display for GroupKey {
+    /// Writes the name `--group-by` takes, which prefs.json saves too.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            GroupKey::Date => "date",
+            GroupKey::Venue => "venue",
+            GroupKey::Source => "source",
+        })
+    }
+}
+
+/// The title of the group for events without the grouping key.
+pub const UNKNOWN_GROUP: &str = "Unknown";
+
+/// Splits events into titled groups, e.g. one per day (`Friday, May 3`) or per venue.
+///
+/// Groups appear in the order their first event does, so sorted events give sorted groups,
+/// and events keep their order within a group. Events without the key (no parsed start, no
+/// location, or no URL) go in an `Unknown` group at the end.
+///
+/// # Arguments
+///
+/// * `events` - The processed events, in display order.
+/// * `key` - What to group by.
+///
+/// # Returns
+///
+/// The group titles with their events.
+pub fn group_events(
+    events: Vec<ProcessedEvent>,
+    key: GroupKey,
+) -> Vec<(String, Vec<ProcessedEvent>)> {
+    let mut groups: Vec<(String, Vec<ProcessedEvent>)> = Vec::new();
+    let mut unknown = Vec::new();
+    for event in events {
+        let title = match key {
+            GroupKey::Date => event
+                .start
+                .map(|start| start.format("%A, %B %-d").to_string()),
+            GroupKey::Venue => event.location.clone().filter(|l| !l.trim().is_empty()),
+            GroupKey::Source => event.source.clone().or_else(|| {
+                Url::parse(&event.url)
+                    .ok()
+                    .and_then(|url| Some(url.host_str()?.trim_start_matches("www.").to_string()))
+            }),
+        };
+        let Some(title) = title else {
+            unknown.push(event);
+            continue;
+        };
+        match groups
+            .iter_mut()
+            .find(|(existing, _)| normalize_key(existing) == normalize_key(&title))
+        {
+            Some((_, members)) => members.push(event),
+            None => groups.push((title, vec![event])),
+        }
+    }
+    if !unknown.is_empty() {
+        groups.push((UNKNOWN_GROUP.to_string(), unknown));
+    }
+    groups
+}
+
+/// How `search_events` combines several search terms.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Match {
+    /// Every term must appear.
+    #[default]
+    All,
+    /// At least one term must appear.
+    Any,
+}
+
+/// Keeps the events mentioning the search terms in their name, location, description, or tags.
+///
+/// Matching compares `normalize_key` forms, so `cafe` finds `Café`. With no terms every event is kept.
+///
+/// # Arguments
+///
+/// * `events` - The processed events to search.
+/// * `terms` - The terms to look for.
+/// * `mode` - Whether all or any of the terms must appear.
+///
+/// # Returns
+///
+/// The matching events, in their original order.
+pub fn search_events(
+    events: Vec<ProcessedEvent>,
+    terms: &[String],
+    mode: Match,
+) -> Vec<ProcessedEvent> {
+    let terms: Vec<String> = terms
+        .iter()
+        .map(|t| normalize_key(t))
+        .filter(|t| !t.is_empty())
+        .collect();
+    if terms.is_empty() {
+        return events;
+    }
+    events
+        .into_iter()
+        .filter(|event| {
+            let mut text = normalize_key(&event.name);
+            for field in [&event.location, &event.description].into_iter().flatten() {
+                text.push('\n');
+                text.push_str(&normalize_key(field));
+            }
+            for tag in &event.tags {
+                text.push('\n');
+                text.push_str(&normalize_key(tag));
+            }
+            match mode {
+                Match::All => terms.iter().all(|term| text.contains(term.as_str())),
+                Match::Any => terms.iter().any(|term| text.contains(term.as_str())),
+            }
+        })
+        .collect()
+}
+
+/// The part of an event an `ExcludeRule` looks at.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExcludeField {
+    /// The name and description.
+    Text,
+    /// The location.
+    Venue,
+}
+
+/// A rule leaving out events that mention a word or phrase.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ExcludeRule {
+    pub pattern: String,
+    pub field: ExcludeField,
+    /// Match only where the pattern isn't part of a longer word, so `bar` leaves `Barn` alone.
+    pub whole_word: bool,
+}
+
+impl ExcludeRule {
+    /// Returns `true` if the event mentions the rule's pattern, compared with `normalize_key`.
+    pub fn matches(&self, event: &ProcessedEvent) -> bool {
+        let pattern = normalize_key(&self.pattern);
+        if pattern.is_empty() {
+            return false;
+        }
+        let fields = match self.field {
+            ExcludeField::Text => vec![Some(&event.name), event.description.as_ref()],
+            ExcludeField::Venue => vec![event.location.as_ref()],
+        };
+        fields.into_iter().flatten().any(|text| {
+            let text = normalize_key(text);
+            if self.whole_word {
+                contains_word(&text, &pattern)
+            } else {
+                text.contains(&pattern)
+            }
+        })
+    }
+}
+
+/// Returns `true` if `pattern` appears in `text` where it isn't part of a longer word.
+fn contains_word(text: &str, pattern: &str) -> bool {
+    text.match_indices(pattern).any(|(start, found)| {
+        let before = text[..start].chars().next_back();
+        let after = text[start + found.len()..].chars().next();
+        !before.is_some_and(char::is_alphanumeric) && !after.is_some_and(char::is_alphanumeric)
+    })
+}
+
+/// Drops the events matching any of the exclusion rules.
+///
+/// # Arguments
+///
+/// * `events` - The processed events to filter.
+/// * `rules` - The rules to apply.
+///
+/// # Returns
+///
+/// The events no rule matched, and how many events each rule removed. An event matching
+/// several rules is counted against the first of them only, so the counts add up to the
+/// number of events removed.
+pub fn apply_exclusions(
+    events: Vec<ProcessedEvent>,
+    rules: &[ExcludeRule],
+) -> (Vec<ProcessedEvent>, Vec<usize>) {
+    let mut removed = vec![0; rules.len()];
+    let kept = events
+        .into_iter()
+        .filter(
+            |event| match rules.iter().position(|rule| rule.matches(event)) {
+                Some(index) => {
+                    removed[index] += 1;
+                    false
+                }
+                None => true,
+            },
+        )
+        .collect();
+    (kept, removed)
+}
+
+/// How similar two names must be, from 0 to 1, for `merge_cross_source` to treat events as one.
+pub const DEFAULT_MERGE_THRESHOLD: f64 = 0.8;
+
+/// How far apart two listings' starts may be and still be the same event.
+const MERGE_START_WINDOW_MINUTES: i64 = 30;
+
+/// Merges events that different sources list separately.
+///
+/// Two events are the same when their parsed starts are within 30 minutes, they are at the
+/// same venue (the venue names share a word other than `the`, `at`, and the like, so `The
+/// Ryman` matches `Ryman Auditorium`), and `name_similarity` of their names is at least `threshold`. Events without
+/// a parsed start or a location are never merged. Duplicates are combined with `merge`, the
+/// earlier-listed event first, and take its place in the list.
+///
+/// # Arguments
+///
+/// * `events` - The processed events of every source.
+/// * `threshold` - The name similarity, from 0 to 1, at which events are merged.
+/// * `preferences` - Which listing's value each field keeps.
+///
+/// # Returns
+///
+/// The merged events in their original order, and how many events were merged into others.
+pub fn merge_cross_source(
+    events: Vec<ProcessedEvent>,
+    threshold: f64,
+    preferences: &MergePreferences,
+) -> (Vec<ProcessedEvent>, usize) {
+    let mut merged: Vec<ProcessedEvent> = Vec::with_capacity(events.len());
+    let mut count = 0;
+    for event in events {
+        match merged
+            .iter_mut()
+            .find(|kept| is_same_event(kept, &event, threshold))
+        {
+            Some(kept) => {
+                *kept = merge(std::mem::take(kept), event, preferences);
+                count += 1;
+            }
+            None => merged.push(event),
+        }
+    }
+    (merged, count)
+}
+
+fn is_same_event(a: &ProcessedEvent, b: &ProcessedEvent, threshold: f64) -> bool {
+    let (Some(a_start), Some(b_start)) = (a.start, b.start) else {
+        return false;
+    };
+    if (a_start - b_start).num_minutes().abs() > MERGE_START_WINDOW_MINUTES {
+        return false;
+    }
+    let (Some(a_venue), Some(b_venue)) = (&a.location, &b.location) else {
+        return false;
+    };
+    const FILLER: &[&str] = &["the", "a", "at", "of", "and", "on", "in"];
+    let b_venue = name_tokens(b_venue);
+    if !name_tokens(a_venue)
+        .iter()
+        .any(|token| !FILLER.contains(&token.as_str()) && b_venue.contains(token))
+    {
+        return false;
+    }
+    name_similarity(&a.name, &b.name) >= threshold
+}
+
+/// Which of two listings' values a field keeps when they are merged.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Prefer {
+    /// The first listing's value, unless it has none.
+    First,
+    /// The longer text; the first listing's on a tie.
+    Longer,
+    /// The shorter text, among listings that have one; the first listing's on a tie.
+    Shorter,
+    /// For dates, the more precise one: a timestamp with a UTC offset (as structured data gives
+    /// them) over a date and time, over a date alone, over a date that couldn't be read. The
+    /// first listing's on a tie.
+    Precise,
+}
+
+impl FromStr for Prefer {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "first" => Ok(Prefer::First),
+            "longer" | "longest" => Ok(Prefer::Longer),
+            "shorter" | "shortest" => Ok(Prefer::Shorter),
+            "precise" => Ok(Prefer::Precise),
+            _ => Err(format!(
+                "unknown preference '{}' (expected first, longer, shorter, or precise)",
+                s
+            )),
+        }
+    }
+}
+
+/// The per-field rules `merge` follows.
+///
+/// Text fields take `First`, `Longer`, or `Shorter`; the start and end take `First` or
+/// `Precise`. Other fields always follow the fixed rules described on `merge`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MergePreferences {
+    pub name: Prefer,
+    pub start: Prefer,
+    pub end: Prefer,
+    pub location: Prefer,
+    pub description: Prefer,
+    pub price: Prefer,
+    pub image_url: Prefer,
+}
+
+impl Default for MergePreferences {
+    fn default() -> Self {
+        MergePreferences {
+            name: Prefer::First,
+            start: Prefer::Precise,
+            end: Prefer::Precise,
+            location: Prefer::First,
+            description: Prefer::Longer,
+            price: Prefer::First,
+            image_url: Prefer::First,
+        }
+    }
+}
+
+impl MergePreferences {
+    /// The fields whose preference can be set, as they are named in the config file.
+    pub const FIELDS: &'static [&'static str] = &[
+        "name",
+        "start",
+        "end",
+        "location",
+        "description",
+        "price",
+        "image_url",
+    ];
+
+    /// Sets the preference of a field, checking it suits the field.
+    ///
+    /// # Returns
+    ///
+    /// `Ok(())`, or a message if the field is unknown or can't take the preference.
+    pub fn set(&mut self, field: &str, prefer: Prefer) -> Result<(), String> {
+        let is_date = matches!(field, "start" | "end");
+        let slot = match field {
+            "name" => &mut self.name,
+            "start" => &mut self.start,
+            "end" => &mut self.end,
+            "location" => &mut self.location,
+            "description" => &mut self.description,
+            "price" => &mut self.price,
+            "image_url" => &mut self.image_url,
+            _ => {
+                return Err(format!(
+                    "unknown field '{}' (expected one of {})",
+                    field,
+                    Self::FIELDS.join(", ")
+                ))
+            }
+        };
+        match (is_date, prefer) {
+            (true, Prefer::Longer | Prefer::Shorter) => {
+                Err(format!("{} can prefer first or precise", field))
+            }
+            (false, Prefer::Precise) => {
+                Err(format!("{} can prefer first, longer, or shorter", field))
+            }
+            _ => {
+                *slot = prefer;
+                Ok(())
+            }
+        }
+    }
+}
+
+/// Combines two listings of the same event into one.
+///
+/// Fields with a preference in `preferences` take the preferred listing's value; a listing
+/// without the field never wins over one that has it. The start's text and parsed value are
+/// always taken together, and so are the end's. The rest is fixed:
+///
+/// | Field                | Kept                                                              |
+/// |----------------------|-------------------------------------------------------------------|
+/// | `url`                | `a`'s; `b`'s and both `alt_urls` go in `alt_urls`, without repeats |
+/// | `categories`, `tags` | both, `a`'s first, without repeats                               |
+/// | `status`             | `a`'s, unless it is `Unknown`                                     |
+/// | `source`             | `a`'s, unless it has none                                         |
+/// | `score`              | the higher, with that listing's matched interests                 |
+///
+/// # Arguments
+///
+/// * `a` - The listing met first.
+/// * `b` - The listing merged into it.
+/// * `preferences` - Which listing's value each field keeps.
+///
+/// # Returns
+///
+/// The merged event.
+pub fn merge(
+    a: ProcessedEvent,
+    b: ProcessedEvent,
+    preferences: &MergePreferences,
+) -> ProcessedEvent {
+    fn text(prefer: Prefer, a: Option<String>, b: Option<String>) -> Option<String> {
+        let length = |text: &Option<String>| text.as_ref().map(|t| t.chars().count());
+        match (prefer, length(&a), length(&b)) {
+            (_, None, _) => b,
+            (_, _, None) => a,
+            (Prefer::Longer, Some(x), Some(y)) if y > x => b,
+            (Prefer::Shorter, Some(x), Some(y)) if y < x => b,
+            _ => a,
+        }
+    }
+    fn precision(text: Option<&str>, parsed: Option<NaiveDateTime>) -> u8 {
+        match parsed {
+            None => u8::from(text.is_some_and(|t| !t.trim().is_empty())),
+            Some(_) if text.is_some_and(|t| DateTime::parse_from_rfc3339(t.trim()).is_ok()) => 4,
+            Some(parsed) if parsed.time() != NaiveTime::MIN => 3,
+            Some(_) => 2,
+        }
+    }
+    fn takes_b(prefer: Prefer, a: u8, b: u8) -> bool {
+        match prefer {
+            Prefer::Precise => b > a,
+            _ => a == 0 && b > 0,
+        }
+    }
+
+    let mut merged = a;
+    let start_a = precision(Some(&merged.start_date), merged.start);
+    if takes_b(
+        preferences.start,
+        start_a,
+        precision(Some(&b.start_date), b.start),
+    ) {
+        merged.start_date = b.start_date;
+        merged.start = b.start;
+    }
+    if takes_b(
+        preferences.end,
+        precision(merged.end_date.as_deref(), merged.end),
+        precision(b.end_date.as_deref(), b.end),
+    ) {
+        merged.end_date = b.end_date;
+        merged.end = b.end;
+    }
+    merged.name = text(
+        preferences.name,
+        Some(merged.name).filter(|n| !n.is_empty()),
+        Some(b.name),
+    )
+    .unwrap_or_default();
+    merged.location = text(preferences.location, merged.location, b.location);
+    merged.description = text(preferences.description, merged.description, b.description);
+    merged.price = text(preferences.price, merged.price, b.price);
+    merged.image_url = text(preferences.image_url, merged.image_url, b.image_url);
+
+    if merged.source.is_none() {
+        merged.source = b.source;
+    }
+    if merged.status == EventStatus::Unknown {
+        merged.status = b.status;
+    }
+    if b.score > merged.score {
+        merged.score = b.score;
+        merged.matched_interests = b.matched_interests;
+    }
+    merged.categories.extend(b.categories);
+    for tag in b.tags {
+        if !merged.tags.contains(&tag) {
+            merged.tags.push(tag);
+        }
+    }
+    for url in std::iter::once(b.url).chain(b.alt_urls) {
+        if merged.url.is_empty() {
+            merged.url = url;
+        } else if !url.is_empty() && url != merged.url && !merged.alt_urls.contains(&url) {
+            merged.alt_urls.push(url);
+        }
+    }
+    merged
+}
+
+/// Scores how alike two event names are, from 0 (nothing shared) to 1 (the same).
+///
+/// Names are compared as lowercase words without punctuation or accents. The score is the
+/// better of the word overlap (shared words over the shorter name's words, so `The National`
+/// and `The National w/ Special Guests` score 1) and the edit distance between the names
+/// relative to the longer one, which catches typos.
+pub fn name_similarity(a: &str, b: &str) -> f64 {
+    let (a, b) = (name_tokens(a), name_tokens(b));
+    if a.is_empty() || b.is_empty() {
+        return 0.0;
+    }
+    let shared = a.iter().filter(|token| b.contains(token)).count();
+    let overlap = shared as f64 / a.len().min(b.len()) as f64;
+
+    let (a, b): (Vec<char>, Vec<char>) =
+        (a.join(" ").chars().collect(), b.join(" ").chars().collect());
+    let mut previous: Vec<usize> = (0..=b.len()).collect();
+    for (i, ca) in a.iter().enumerate() {
+        let mut current = vec![i + 1];
+        for (j, cb) in b.iter().enumerate() {
+            let substitution = previous[j] + usize::from(ca != cb);
+            current.push(substitution.min(previous[j + 1] + 1).min(current[j] + 1));
+        }
+        previous = current;
+    }
+    let edit = 1.0 - previous[b.len()] as f64 / a.len().max(b.len()) as f64;
+    overlap.max(edit)
+}
+
+/// Splits a name into the words of its `normalize_key` form.
+fn name_tokens(text: &str) -> Vec<String> {
+    normalize_key(text)
+        .split(' ')
+        .filter(|token| !token.is_empty())
+        .map(str::to_string)
+        .collect()
+}
+
+/// A map from the names sources give a venue to the name it should be shown under, e.g.
+/// "The Ryman" and "Ryman Aud." to "Ryman Auditorium".
+///
+/// Names are matched by `normalize_key`, ignoring case, accents, and punctuation. With prefix matching, a venue also matches an
+/// alias it starts with, as a whole word: "Ryman Aud. - Nashville" matches "Ryman Aud.".
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct VenueAliases {
+    /// Folded variants and their canonical names.
+    aliases: Vec<(String, String)>,
+    prefix_match: bool,
+}
+
+impl VenueAliases {
+    /// Creates a map from `(variant, canonical)` pairs.
+    pub fn new(aliases: Vec<(String, String)>, prefix_match: bool) -> VenueAliases {
+        let aliases = aliases
+            .into_iter()
+            .map(|(variant, canonical)| (normalize_key(variant.trim()), canonical))
+            .collect();
+        VenueAliases {
+            aliases,
+            prefix_match,
+        }
+    }
+
+    /// Returns how many spellings are mapped to a canonical name.
+    pub fn len(&self) -> usize {
+        self.aliases.len()
+    }
+
+    /// Whether no spellings are mapped.
+    pub fn is_empty(&self) -> bool {
+        self.aliases.is_empty()
+    }
+
+    /// Returns the canonical name of a venue, or `None` if no alias matches it.
+    ///
+    /// An exact match wins over prefix matches, and a longer prefix over a shorter one.
+    pub fn canonical(&self, venue: &str) -> Option<&str> {
+        let venue = normalize_key(venue.trim());
+        if let Some((_, canonical)) = self.aliases.iter().find(|(variant, _)| *variant == venue) {
+            return Some(canonical);
+        }
+        if !self.prefix_match {
+            return None;
+        }
+        self.aliases
+            .iter()
+            .filter(|(variant, _)| {
+                venue
+                    .strip_prefix(variant.as_str())
+                    .is_some_and(|rest| !rest.starts_with(char::is_alphanumeric))
+            })
+            .max_by_key(|(variant, _)| variant.len())
+            .map(|(_, canonical)| canonical.as_str())
+    }
+}
+
+/// Replaces each event's location with its canonical name from the alias map.
+///
+/// Venues without an alias are left as they are.
+///
+/// # Arguments
+///
+/// * `events` - The processed events.
+/// * `aliases` - The venue alias map.
+///
+/// # Returns
+///
+/// The events, with aliased locations renamed.
+pub fn canonicalize_venues(
+    events: Vec<ProcessedEvent>,
+    aliases: &VenueAliases,
+) -> Vec<ProcessedEvent> {
+    events
+        .into_iter()
+        .map(|event| {
+            match event
+                .location
+                .as_deref()
+                .and_then(|venue| aliases.canonical(venue))
+            {
+                Some(canonical) => ProcessedEvent {
+                    location: Some(canonical.to_string()),
+                    ..event
+                },
+                None => event,
+            }
+        })
+        .collect()
+}
+
+/// Normalizes text for comparing, so `Café`, `CAFE`, and `cafe!` are all `cafe`.
+///
+/// Compatibility forms are replaced (NFKC: `ﬁ` becomes `fi`, full-width letters become
+/// ASCII), case is folded, accents are removed, apostrophes are dropped (`Tootsie's` is
+/// `tootsies`), and other punctuation becomes a space, with runs of spaces collapsed. The
+/// result is only for comparing, searching, and sorting; events keep their text as written.
+/// Normalizing the result again leaves it unchanged.
+pub fn normalize_key(text: &str) -> String {
+    let folded: String = text
+        .nfkc()
+        .flat_map(char::to_lowercase)
+        .flat_map(|c| match c {
+            'ß' | 'ẞ' => vec!['s', 's'],
+            'ſ' => vec!['s'],
+            'ς' => vec!['σ'],
+            c => vec![c],
+        })
+        .collect();
+    let stripped: String = folded
+        .nfd()
+        .filter(|c| !is_combining_mark(*c))
+        .filter(|c| !matches!(c, '\'' | '’' | '‘' | 'ʼ'))
+        .map(|c| if c.is_alphanumeric() { c } else { ' ' })
+        .collect();
+    stripped.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+/// Describes when an event starts relative to now, e.g. `starts in 2h 15m` or `started 30m ago`.
+///
+/// Under an hour away the time is given in minutes, under a day in hours and minutes, and
+/// beyond that in days.
+///
+/// # Arguments
+///
+/// * `start` - The event's parsed start.
+/// * `now` - The current time, in the same zone as `start`.
+///
+/// # Returns
+///
+/// The description; `starts now` within a minute either way.
+pub fn relative_time(start: NaiveDateTime, now: NaiveDateTime) -> String {
+    let minutes = (start - now).num_minutes();
+    if minutes == 0 {
+        return "starts now".to_string();
+    }
+    let amount = format_span(minutes.abs());
+    if minutes > 0 {
+        format!("starts in {}", amount)
+    } else {
+        format!("started {} ago", amount)
+    }
+}
+
+/// Writes a number of minutes the way `relative_time` does: `45m`, `2h`, `2h 30m`, or `3 days`.
+fn format_span(minutes: i64) -> String {
+    if minutes < 60 {
+        format!("{}m", minutes)
+    } else if minutes < 24 * 60 && minutes % 60 == 0 {
+        format!("{}h", minutes / 60)
+    } else if minutes < 24 * 60 {
+        format!("{}h {}m", minutes / 60, minutes % 60)
+    } else {
+        let days = minutes / (24 * 60);
+        format!("{} day{}", days, if days == 1 { "" } else { "s" })
+    }
+}
+
+/// Returns how long an event lasts, when the source gave both its start and its end.
+///
+/// Ends estimated by `fill_missing_ends` don't count, since they have no `end_date`. An event
+/// listed with only dates (both at midnight) lasts through its last day, so `May 3` to `May 5`
+/// is three days.
+pub fn duration(event: &ProcessedEvent) -> Option<Duration> {
+    let (start, end) = (event.start?, event.end?);
+    event.end_date.as_ref()?;
+    let end = if start.time() == NaiveTime::MIN && end.time() == NaiveTime::MIN {
+        end + Duration::days(1)
+    } else {
+        end
+    };
+    Some(end - start).filter(|d| *d >= Duration::zero())
+}
+
+/// Describes how long an event lasts, e.g. `2h 30m` or `3 days`.
+pub fn format_duration(duration: Duration) -> String {
+    format_span(duration.num_minutes())
+}
+
+/// Parses a duration as written on the command line: `90m`, `2h`, `2h30m`, `1d`, or a bare
+/// number of minutes.
+///
+/// # Returns
+///
+/// The duration, or a message if the text isn't one or is zero.
+pub fn parse_duration(text: &str) -> Result<Duration, String> {
+    let error = || {
+        format!(
+            "invalid duration '{}' (expected e.g. 90m, 2h, or 2h30m)",
+            text
+        )
+    };
+    let compact: String = text
+        .chars()
+        .filter(|c| !c.is_whitespace())
+        .collect::<String>()
+        .to_lowercase();
+    if let Ok(minutes) = compact.parse::<i64>() {
+        return Some(Duration::minutes(minutes))
+            .filter(|d| *d > Duration::zero())
+            .ok_or_else(error);
+    }
+    let (mut total, mut number) = (Duration::zero(), String::new());
+    for c in compact.chars() {
+        if c.is_ascii_digit() {
+            number.push(c);
+            continue;
+        }
+        let amount: i64 = number.parse().map_err(|_| error())?;
+        total = total
+            + match c {
+                'd' => Duration::days(amount),
+                'h' => Duration::hours(amount),
+                'm' => Duration::minutes(amount),
+                _ => return Err(error()),
+            };
+        number.clear();
+    }
+    if !number.is_empty() || total <= Duration::zero() {
+        return Err(error());
+    }
+    Ok(total)
+}
+
+/// Drops events lasting longer than a maximum.
+///
+/// # Arguments
+///
+/// * `events` - The processed events to filter.
+/// * `max` - The longest duration to keep.
+/// * `keep_unknown` - Whether to keep events whose duration isn't known.
+///
+/// # Returns
+///
+/// The events kept, and how many were dropped.
+pub fn filter_by_duration(
+    events: Vec<ProcessedEvent>,
+    max: Duration,
+    keep_unknown: bool,
+) -> (Vec<ProcessedEvent>, usize) {
+    let before = events.len();
+    let kept: Vec<ProcessedEvent> = events
+        .into_iter()
+        .filter(|event| duration(event).map_or(keep_unknown, |d| d <= max))
+        .collect();
+    let dropped = before - kept.len();
+    (kept, dropped)
+}
+
+/// How long an event without an end is assumed to last, in minutes.
+pub const DEFAULT_ASSUMED_DURATION_MINUTES: i64 = 180;
+
+/// Drops events that have already ended.
+///
+/// An event ends at its parsed end, or `assumed_duration` after its start when it has none. A
+/// start at exactly midnight without an end usually means the source gave only a date, so such
+/// events last until the end of that day. Events without a parsed start are kept.
+///
+/// # Arguments
+///
+/// * `events` - The processed events to filter.
+/// * `now` - The current time, in the same zone the event times are written in.
+/// * `assumed_duration` - How long an event without an end lasts.
+///
+/// # Returns
+///
+/// The events still running or yet to start, and how many were dropped.
+pub fn drop_ended(
+    events: Vec<ProcessedEvent>,
+    now: NaiveDateTime,
+    assumed_duration: Duration,
+) -> (Vec<ProcessedEvent>, usize) {
+    let before = events.len();
+    let kept: Vec<ProcessedEvent> = events
+        .into_iter()
+        .filter(|event| {
+            let Some(start) = event.start else {
+                return true;
+            };
+            let end = match event.end {
+                Some(end) if end >= start => end,
+                _ if start.time() == NaiveTime::MIN => start + Duration::days(1),
+                _ => start + assumed_duration,
+            };
+            end > now
+        })
+        .collect();
+    let dropped = before - kept.len();
+    (kept, dropped)
+}
+
+/// Counts describing a listing, shown as a footer after it.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct Summary {
+    pub total: usize,
+    /// Events per source, most first; events without a known source aren't counted.
+    pub sources: Vec<(String, usize)>,
+    /// The number of different venues, by `normalize_key`.
+    pub venues: usize,
+    /// The venues with the most events, up to `TOP_VENUES`, most first.
+    pub top_venues: Vec<(String, usize)>,
+    /// Events starting today, and ones that started earlier and end today or later.
+    pub tonight: usize,
+    /// Events starting after today.
+    pub later: usize,
+    /// Events over before today, listed with `--include-past`; an event started before today
+    /// without an end counts as over.
+    pub past: usize,
+    /// Events whose start couldn't be read.
+    pub undated: usize,
+    pub free: usize,
+    /// Events that cost money, by `classify_price`; events whose price isn't known count as neither.
+    pub paid: usize,
+}
+
+/// How many venues `Summary::top_venues` lists.
+pub const TOP_VENUES: usize = 5;
+
+/// Counts a listing's events by source, venue, day, and price.
+///
+/// # Arguments
+///
+/// * `events` - The listed events.
+/// * `today` - The day that counts as tonight.
+///
+/// # Returns
+///
+/// The counts; sources and venues with the same count keep the order they first appear in.
+pub fn summarize(events: &[ProcessedEvent], today: NaiveDate) -> Summary {
+    fn count(counts: &mut Vec<(String, String, usize)>, key: String, name: &str) {
+        match counts.iter_mut().find(|(k, _, _)| *k == key) {
+            Some((_, _, n)) => *n += 1,
+            None => counts.push((key, name.to_string(), 1)),
+        }
+    }
+    fn most_first(mut counts: Vec<(String, String, usize)>) -> Vec<(String, usize)> {
+        counts.sort_by_key(|&(_, _, n)| std::cmp::Reverse(n));
+        counts.into_iter().map(|(_, name, n)| (name, n)).collect()
+    }
+
+    let mut summary = Summary {
+        total: events.len(),
+        ..Default::default()
+    };
+    let (mut sources, mut venues) = (Vec::new(), Vec::new());
+    for event in events {
+        if let Some(source) = &event.source {
+            count(&mut sources, source.clone(), source);
+        }
+        if let Some(venue) = event.location.as_deref().filter(|v| !v.trim().is_empty()) {
+            count(&mut venues, normalize_key(venue.trim()), venue.trim());
+        }
+        match event.start {
+            Some(start) if start.date() > today => summary.later += 1,
+            Some(start)
+                if start.date() == today || event.end.is_some_and(|end| end.date() >= today) =>
+            {
+                summary.tonight += 1
+            }
+            Some(_) => summary.past += 1,
+            None => summary.undated += 1,
+        }
+        match classify_price(event.price.as_deref(), event.description.as_deref()) {
+            PriceClass::Free => summary.free += 1,
+            PriceClass::Paid => summary.paid += 1,
+            PriceClass::Unknown => {}
+        }
+    }
+    summary.sources = most_first(sources);
+    summary.venues = venues.len();
+    summary.top_venues = most_first(venues);
+    summary.top_venues.truncate(TOP_VENUES);
+    summary
+}
+
+/// Displays the summary as one line, e.g.
+/// `87 events from 3 sources · 14 venues · 21 tonight, 66 later · 5 free, 40 paid`.
+///
+/// Parts with nothing to say, such as prices when no event has one, are left out.
+impl fmt::Display for Summary {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let plural =
+            |n: usize, word: &str| format!("{} {}{}", n, word, if n == 1 { "" } else { "s" });
+        if self.total == 0 {
+            return f.write_str("no events");
+        }
+        let mut parts = vec![plural(self.total, "event")];
+        if !self.sources.is_empty() {
+            parts[0] = format!("{} from {}", parts[0], plural(self.sources.len(), "source"));
+        }
+        if self.venues > 0 {
+            parts.push(plural(self.venues, "venue"));
+        }
+        let mut days = Vec::new();
+        if self.tonight + self.later > 0 {
+            days.push(format!("{} tonight, {} later", self.tonight, self.later));
+        }
+        if self.past > 0 {
+            days.push(format!("{} past", self.past));
+        }
+        if self.undated > 0 {
+            days.push(format!("{} undated", self.undated));
+        }
+        if !days.is_empty() {
+            parts.push(days.join(", "));
+        }
+        if self.free + self.paid > 0 {
+            parts.push(format!("{} free, {} paid", self.free, self.paid));
+        }
+        f.write_str(&parts.join(" · "))
+    }
+}
+
+/// Picks one event at random.
+///
+/// With `favor_soonest`, an event is picked less often the further off it is: one starting
+/// today is twice as likely as one tomorrow and eight times as likely as one a week out.
+/// Events already underway count as starting now, and undated ones as a week out.
+///
+/// # Arguments
+///
+/// * `events` - The events to pick from.
+/// * `now` - The current time, in the same zone the event times are written in.
+/// * `favor_soonest` - Whether to weight the pick towards events starting soonest.
+/// * `rng` - The random number generator; seed it for a repeatable pick.
+///
+/// # Returns
+///
+/// The picked event, or `None` if there are no events.
+pub fn pick_random<'a, R: Rng>(
+    events: &'a [ProcessedEvent],
+    now: NaiveDateTime,
+    favor_soonest: bool,
+    rng: &mut R,
+) -> Option<&'a ProcessedEvent> {
+    if !favor_soonest {
+        return events.choose(rng);
+    }
+    let weights = events.iter().map(|event| {
+        let days = event.start.map_or(7.0, |start| {
+            (start - now).num_minutes().max(0) as f64 / (24.0 * 60.0)
+        });
+        1.0 / (1.0 + days)
+    });
+    let index = WeightedIndex::new(weights).ok()?;
+    events.get(index.sample(rng))
+}
+
+/// Keeps only the first events of a list.
+///
+/// # Arguments
+///
+/// * `events` - The events, in display order.
+/// * `limit` - How many to keep, or `None` to keep them all.
+///
+/// # Returns
+///
+/// The first `limit` events, and how many were left out.
+pub fn limit_events(
+    mut events: Vec<ProcessedEvent>,
+    limit: Option<usize>,
+) -> (Vec<ProcessedEvent>, usize) {
+    let Some(limit) = limit else {
+        return (events, 0);
+    };
+    let left_out = events.len().saturating_sub(limit);
+    events.truncate(limit);
+    (events, left_out)
+}
+
+/// Drops events that can't be attended because they are sold out or cancelled.
+///
+/// # Arguments
+///
+/// * `events` - The processed events to filter.
+///
+/// # Returns
+///
+/// The events whose status doesn't rule them out.
+pub fn hide_unavailable(events: Vec<ProcessedEvent>) -> Vec<ProcessedEvent> {
+    events
+        .into_iter()
+        .filter(|e| !e.status.is_unavailable())
+        .collect()
+}
+
+/// Cleans the given text by stripping markup, decoding entities, and collapsing whitespace.
+///
+/// Entities are decoded once, so escaped text such as `&lt;Band Name&gt;` keeps its angle
+/// brackets; `clean_description` also strips markup that was escaped. Every kind of Unicode whitespace, including non-breaking spaces, counts as a space;
+/// zero-width characters, soft hyphens, and control characters are removed. Zero-width
+/// joiners are kept since they hold emoji sequences together; `strip_emoji` removes them.
+/// Other per-source cleanup (emoji, stray separators) is configured as `Transform`s.
+///
+/// # Arguments
+///
+/// * `text` - A string slice representing the text to be cleaned.
+///
+/// # Returns
+///
+/// A `String` with tags removed, entities decoded, and runs of whitespace collapsed to single spaces.
+pub fn clean_text(text: &str) -> String {
+    collapse(&strip_markup(text))
+}
+
+/// Cleans a JSON-LD description, which sites often fill with HTML that was escaped on its way
+/// into the JSON (`&lt;p&gt;Doors at 7&lt;/p&gt;`): markup is stripped a second time once the
+/// entities are decoded, then the text is cleaned as `clean_text` does.
+///
+/// # Arguments
+///
+/// * `text` - The description as written in the JSON.
+///
+/// # Returns
+///
+/// The description without its markup, escaped or not.
+pub fn clean_description(text: &str) -> String {
+    collapse(&strip_markup(&strip_markup(text)))
+}
+
+/// Drops invisible and control characters and collapses runs of whitespace to single spaces.
+fn collapse(text: &str) -> String {
+    let text: String = text
+        .chars()
+        .filter(|c| c.is_whitespace() || !(c.is_control() || is_invisible(*c)))
+        .collect();
+    text.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+/// Returns `true` for characters that take up no space and separate nothing: zero-width spaces,
+/// word joiners, byte order marks, direction marks, and soft hyphens.
+fn is_invisible(c: char) -> bool {
+    matches!(
+        c,
+        '\u{200B}' | '\u{200C}' | '\u{200E}' | '\u{200F}' | '\u{2060}' | '\u{FEFF}' | '\u{00AD}'
+    )
+}
+
+/// Returns the text content of an HTML fragment, with tags removed and entities decoded.
+fn strip_markup(text: &str) -> String {
+    if !text.contains(['<', '&']) {
+        return text.to_string();
+    }
+    Html::parse_fragment(text).root_element().text().collect()
+}
+
+/// Truncates text to at most `max_chars` characters, cutting at a word boundary and appending an ellipsis.
+///
+/// # Arguments
+///
+/// * `text` - The text to truncate.
+/// * `max_chars` - The maximum length of the result, including the ellipsis.
+///
+/// # Returns
+///
+/// The text unchanged if it fits, otherwise its longest whole-word prefix followed by `…`.
+pub fn truncate_at_word(text: &str, max_chars: usize) -> String {
+    if text.chars().count() <= max_chars {
+        return text.to_string();
+    }
+    let limit = max_chars.saturating_sub(1);
+    let cut: String = text.chars().take(limit).collect();
+    let next_is_space = text.chars().nth(limit).is_some_and(char::is_whitespace);
+    let kept = match cut.rfind(char::is_whitespace) {
+        Some(_) if next_is_space => cut.as_str(),
+        Some(index) => &cut[..index],
+        None => cut.as_str(),
+    };
+    format!("{}…", kept.trim_end())
+}
+
+/// Cleans an optional field, treating a field that is missing or blank after cleaning as unknown.
+///
+/// # Arguments
+///
+/// * `text` - The raw field value, or `None` if the source doesn't provide it.
+///
+/// # Returns
+///
+/// `Some` with the cleaned text, or `None` when there is nothing to show.
+fn clean_optional(text: Option<&str>) -> Option<String> {
+    text.map(clean_text).filter(|t| !t.is_empty())
+}
+
+/// Normalizes raw price text into a displayable form such as `"$25.00"` or `"Free"`.
+///
+/// Amounts are formatted with two decimals and a currency symbol (`$` unless the text names
+/// another currency); two or more amounts become a `low - high` range. A price of zero or
+/// text mentioning "free" becomes `"Free"`. Text without any amount is kept as cleaned text.
+///
+/// # Arguments
+///
+/// * `raw` - The price text extracted from the source, e.g. `"$25"` or `"15-40 USD"`.
+///
+/// # Returns
+///
+/// The normalized price, or `None` if the text is empty.
+pub fn normalize_price(raw: &str) -> Option<String> {
+    let text = clean_text(raw);
+    if text.is_empty() {
+        return None;
+    }
+    if text.to_lowercase().contains("free") {
+        return Some("Free".to_string());
+    }
+
+    let amounts: Vec<f64> = text
+        .split(|c: char| !(c.is_ascii_digit() || c == '.'))
+        .filter_map(|part| part.trim_matches('.').parse::<f64>().ok())
+        .collect();
+    if amounts.is_empty() {
+        return Some(text);
+    }
+
+    let upper = text.to_uppercase();
+    let symbol = if upper.contains('€') || upper.contains("EUR") {
+        "€"
+    } else if upper.contains('£') || upper.contains("GBP") {
+        "£"
+    } else {
+        "$"
+    };
+    let low = amounts.iter().cloned().fold(f64::INFINITY, f64::min);
+    let high = amounts.iter().cloned().fold(f64::NEG_INFINITY, f64::max);
+    if high == 0.0 {
+        Some("Free".to_string())
+    } else if low == high {
+        Some(format!("{}{:.2}", symbol, low))
+    } else {
+        Some(format!("{}{:.2} - {}{:.2}", symbol, low, symbol, high))
+    }
+}
+
+/// Whether an event costs anything, as far as its listing says.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PriceClass {
+    Free,
+    Paid,
+    /// The listing gives no price, or none that settles it (e.g. `Donations welcome`).
+    Unknown,
+}
+
+/// Phrases that mark an event as free, matched as whole words ignoring case.
+const FREE_PHRASES: &[&str] = &["free", "no cover"];
+
+/// Classifies an event's price from its price and description text.
+///
+/// Price text mentioning a free phrase (`FREE before 9pm`) or whose amounts are all zero
+/// (`$0`) is free, and other amounts (`$10-$15`) are paid. Only when the price doesn't settle
+/// it is the description checked for a free phrase.
+///
+/// # Arguments
+///
+/// * `price` - The price text, raw or normalized.
+/// * `description` - The event's description.
+///
+/// # Returns
+///
+/// The event's `PriceClass`.
+pub fn classify_price(price: Option<&str>, description: Option<&str>) -> PriceClass {
+    let says_free = |text: &str| {
+        let text = normalize_key(text);
+        FREE_PHRASES
+            .iter()
+            .any(|phrase| contains_word(&text, phrase))
+    };
+    if let Some(price) = price {
+        if says_free(price) {
+            return PriceClass::Free;
+        }
+        let amounts: Vec<f64> = price
+            .split(|c: char| !(c.is_ascii_digit() || c == '.'))
+            .filter_map(|part| part.trim_matches('.').parse::<f64>().ok())
+            .collect();
+        if !amounts.is_empty() {
+            return if amounts.iter().all(|&amount| amount == 0.0) {
+                PriceClass::Free
+            } else {
+                PriceClass::Paid
+            };
+        }
+    }
+    if description.is_some_and(says_free) {
+        PriceClass::Free
+    } else {
+        PriceClass::Unknown
+    }
+}
+
+/// Keeps only free events.
+///
+/// # Arguments
+///
+/// * `events` - The processed events to filter.
+/// * `keep_unknown` - Whether to keep events whose price isn't known as well.
+///
+/// # Returns
+///
+/// The events kept, and how many were dropped.
+pub fn filter_free(
+    events: Vec<ProcessedEvent>,
+    keep_unknown: bool,
+) -> (Vec<ProcessedEvent>, usize) {
+    let before = events.len();
+    let kept: Vec<ProcessedEvent> = events
+        .into_iter()
+        .filter(|event| {
+            match classify_price(event.price.as_deref(), event.description.as_deref()) {
+                PriceClass::Free => true,
+                PriceClass::Paid => false,
+                PriceClass::Unknown => keep_unknown,
+            }
+        })
+        .collect();
+    let dropped = before - kept.len();
+    (kept, dropped)
+}
+
+/// Parses a start date string and returns a formatted date or a default value.
+///
+/// If the date string is empty, it returns today's date.
+///
+/// # Arguments
+///
+/// * `date_str` - A string slice representing the date to be parsed.
+/// * `today` - Today's date in the home timezone.
+///
+/// # Returns
+///
+/// A `String` representing the parsed date or a default value.
+fn parse_date(date_str: &str, today: NaiveDate) -> String {
+    if date_str.trim().is_empty() {
+        // Return today's date if empty
+        format_day(today, FALLBACK_DATE_FORMAT)
+    } else {
+        date_str.trim().to_string() // Return the date as-is
+    }
+}
+
+/// Combines a date with a time of day that the source showed separately.
+///
+/// An ISO date and a recognizable time (`7:30 PM`, `7pm`, `19:30`) become an ISO timestamp
+/// like `2024-05-02T19:30:00`. A time without a date is taken to be today. Dates and times in
+/// other forms are joined as text.
+///
+/// # Arguments
+///
+/// * `date` - The extracted date text.
+/// * `time` - The extracted time text, if the source has a separate time element.
+/// * `today` - Today's date in the home timezone.
+///
+/// # Returns
+///
+/// The combined start, or the date as `parse_date` returns it when there is no time.
+fn combine_date_time(date: &str, time: Option<&str>, today: NaiveDate) -> String {
+    let date = clean_text(date);
+    let time = match time.map(clean_text).filter(|t| !t.is_empty()) {
+        Some(time) => time,
+        None => return parse_date(&date, today),
+    };
+    let day = if date.is_empty() {
+        Some(today)
+    } else {
+        NaiveDate::parse_from_str(&date, "%Y-%m-%d").ok()
+    };
+    match (day, parse_clock_time(&time)) {
+        (Some(day), Some(clock)) => day.and_time(clock).format("%Y-%m-%dT%H:%M:%S").to_string(),
+        (None, Some(clock)) => format!("{} {}", date, clock.format("%H:%M")),
+        _ => format!(
+            "{} {}",
+            if date.is_empty() {
+                format_day(today, FALLBACK_DATE_FORMAT)
+            } else {
+                date
+            },
+            time
+        ),
+    }
+}
+
+/// Reads a time of day such as `7:30 PM`, `7pm`, `Doors 8 p.m.`, or `19:30`.
+///
+/// # Returns
+///
+/// The time, or `None` if the text has no recognizable time.
+fn parse_clock_time(text: &str) -> Option<NaiveTime> {
+    static TWELVE_HOUR: OnceLock<Regex> = OnceLock::new();
+    static TWENTY_FOUR_HOUR: OnceLock<Regex> = OnceLock::new();
+    let twelve_hour = TWELVE_HOUR
+        .get_or_init(|| Regex::new(r"(?i)\b(\d{1,2})(?::(\d{2}))?\s*([ap])\.?\s*m\b").unwrap());
+    let twenty_four_hour =
+        TWENTY_FOUR_HOUR.get_or_init(|| Regex::new(r"\b(\d{1,2}):(\d{2})\b").unwrap());
+    if let Some(caps) = twelve_hour.captures(text) {
+        let hour: u32 = caps[1].parse().ok()?;
+        let minute: u32 = caps.get(2).map_or(Some(0), |m| m.as_str().parse().ok())?;
+        if !(1..=12).contains(&hour) {
+            return None;
+        }
+        let pm = caps[3].eq_ignore_ascii_case("p");
+        return NaiveTime::from_hms_opt(hour % 12 + if pm { 12 } else { 0 }, minute, 0);
+    }
+    let caps = twenty_four_hour.captures(text)?;
+    NaiveTime::from_hms_opt(caps[1].parse().ok()?, caps[2].parse().ok()?, 0)
+}
+
+/// The format of the date shown for events without a start date, e.g. `May 2`.
+pub const FALLBACK_DATE_FORMAT: &str = "%B %-d";
+
+/// Returns today's date in a formatted string.
+///
+/// # Arguments
+///
+/// * `zone` - The home timezone.
+///
+/// # Returns
+///
+/// A `String` representing today's date in the zone, in `FALLBACK_DATE_FORMAT`.
+fn today_date(zone: Zone) -> String {
+    format_day(zone.today(), FALLBACK_DATE_FORMAT)
+}
+
+/// Formats a day with a strftime format; the time of day is midnight.
+fn format_day(day: NaiveDate, format: &str) -> String {
+    day.and_hms_opt(0, 0, 0)
+        .unwrap_or_default()
+        .format(format)
+        .to_string()
+}
+
+/// Returns `true` if `format` is a strftime format `chrono` can render.
+pub fn is_valid_date_format(format: &str) -> bool {
+    !StrftimeItems::new(format).any(|item| item == Item::Error)
+}
+
+/// The named date formats and the strftime formats they stand for.
+pub const DATE_FORMAT_PRESETS: [(&str, &str); 4] = [
+    ("iso", "%Y-%m-%dT%H:%M:%S"),
+    ("us", "%a %b %-d, %-I:%M %p"),
+    ("eu", "%a %-d %b, %H:%M"),
+    ("compact", "%m-%d %H:%M"),
+];
+
+/// Reads a date format given by name or as a strftime format.
+///
+/// # Arguments
+///
+/// * `value` - One of the `DATE_FORMAT_PRESETS` names (any case), or a strftime format.
+///
+/// # Returns
+///
+/// The strftime format, or an error if `value` is neither a preset nor a format `chrono` can render.
+pub fn resolve_date_format(value: &str) -> Result<String, String> {
+    if let Some((_, format)) = DATE_FORMAT_PRESETS
+        .iter()
+        .find(|(name, _)| name.eq_ignore_ascii_case(value.trim()))
+    {
+        return Ok(format.to_string());
+    }
+    if is_valid_date_format(value) {
+        Ok(value.to_string())
+    } else {
+        let names: Vec<&str> = DATE_FORMAT_PRESETS.iter().map(|(name, _)| *name).collect();
+        Err(format!(
+            "invalid date format '{}' (expected a strftime format or one of {})",
+            value,
+            names.join(", ")
+        ))
+    }
+}
+
+/// Renders a date in a format, or keeps the source's text if it was never read.
+///
+/// # Arguments
+///
+/// * `parsed` - The date as read, or `None` if it couldn't be.
+/// * `raw` - The date as the source wrote it.
+/// * `format` - A strftime format checked with `is_valid_date_format`.
+///
+/// # Returns
+///
+/// The formatted date, or `raw` unchanged.
+pub fn render_date(parsed: Option<NaiveDateTime>, raw: &str, format: &str) -> String {
+    match parsed {
+        Some(parsed) => parsed.format(format).to_string(),
+        None => raw.to_string(),
+    }
+}
+
+/// Rewrites the displayed dates of events in one format.
+///
+/// Dates that parsed are rendered from their typed value; dates that didn't parse keep the
+/// source's text. The today fallback for events without a start date is rendered in the
+/// format too (a source date reading the same would have parsed).
+///
+/// # Arguments
+///
+/// * `events` - The processed events.
+/// * `format` - A strftime format checked with `is_valid_date_format`, e.g. `"%a %b %-d, %-I:%M %p"`.
+/// * `zone` - The home timezone, which parsed dates are already in.
+///
+/// # Returns
+///
+/// The events with their start and end dates reformatted.
+pub fn apply_date_format(
+    events: Vec<ProcessedEvent>,
+    format: &str,
+    zone: Zone,
+) -> Vec<ProcessedEvent> {
+    let fallback = today_date(zone);
+    events
+        .into_iter()
+        .map(|event| ProcessedEvent {
+            start_date: match event.start {
+                None if event.start_date == fallback => format_day(zone.today(), format),
+                start => render_date(start, &event.start_date, format),
+            },
+            // An estimated end isn't shown, so only ends the source gave are formatted
+            end_date: event
+                .end_date
+                .map(|text| render_date(event.end, &text, format)),
+            ..event
+        })
+        .collect()
+}
+
+/// A struct representing a processed event with cleaned and formatted data.
+///
+/// `end_date` and `location` are `None` when the source doesn't provide them or they came back empty.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct ProcessedEvent {
+    /// The name, with whitespace collapsed and entities decoded.
+    pub name: String,
+    /// The start as displayed: the source's text, or today's date if it gave none.
+    pub start_date: String,
+    /// The end as displayed.
+    pub end_date: Option<String>,
+    /// The start as a date and time, or `None` if the source gave none or it couldn't be read.
+    pub start: Option<NaiveDateTime>,
+    /// The end as a date and time, or `None` if the source gave none or it couldn't be read.
+    pub end: Option<NaiveDateTime>,
+    /// The venue.
+    pub location: Option<String>,
+    /// The absolute URL of the event's page.
+    pub url: String,
+    /// The description, cleaned of markup.
+    pub description: Option<String>,
+    /// The price, normalized by `normalize_price`, e.g. `$10.00` or `Free`.
+    pub price: Option<String>,
+    /// The absolute URL of the event's image.
+    pub image_url: Option<String>,
+    /// The categories of the source the event was fetched from.
+    pub categories: Categories,
+    /// The name of the source the event was fetched from, or `None` if it isn't known.
+    pub source: Option<String>,
+    /// Whether the event can still be attended.
+    pub status: EventStatus,
+    /// Genre or category labels, lowercased and without duplicates.
+    pub tags: Vec<String>,
+    /// The URLs of other listings of this event, from duplicates merged into it.
+    pub alt_urls: Vec<String>,
+    /// How well the event matches the user's interests, from `rank_events`; 0 until ranked.
+    pub score: i64,
+    /// The interests the event matched, from `rank_events`.
+    pub matched_interests: Vec<String>,
+    /// How far the venue is from home, in miles, from `geo::annotate_distances`.
+    pub distance: Option<f64>,
+}
+
+// Distances are never NaN, so equality is reflexive.
+impl Eq for ProcessedEvent {}
+
+/// Orders events by their parsed start, undated events last, then by normalized name; the same
+/// order as `sort_events` by start ascending. Only the start and name are compared, so events
+/// that order equal needn't be equal.
+impl Ord for ProcessedEvent {
+    fn cmp(&self, other: &Self) -> std::cmp::Ordering {
+        self.start
+            .is_none()
+            .cmp(&other.start.is_none())
+            .then(self.start.cmp(&other.start))
+            .then_with(|| normalize_key(&self.name).cmp(&normalize_key(&other.name)))
+    }
+}
+
+impl PartialOrd for ProcessedEvent {
+    fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl ProcessedEvent {
+    /// Cleans and reads one raw event, as `process_data` does for each.
+    ///
+    /// # Arguments
+    ///
+    /// * `event` - The event as parsed from the source.
+    /// * `options` - The timezone and date the event's dates are read against.
+    ///
+    /// # Returns
+    ///
+    /// The processed event, not yet scored or located.
+    pub fn from_raw(event: Event, options: &ProcessingOptions) -> ProcessedEvent {
+        ProcessedEvent::read_raw(event, options).0
+    }
+
+    /// Processes a raw event like `from_raw`, also returning the `DataIssue`s found, each with
+    /// the text it was found in.
+    fn read_raw(
+        event: Event,
+        options: &ProcessingOptions,
+    ) -> (ProcessedEvent, Vec<(DataIssue, String)>) {
+        let ProcessingOptions { zone, today } = *options;
+        let mut issues = Vec::new();
+        let start_date = combine_date_time(&event.start_date, event.start_time.as_deref(), today);
+        let end_date = match clean_optional(event.end_time.as_deref()) {
+            // An end time without an end date ends on the start date
+            Some(end_time) => Some(combine_date_time(
+                event.end_date.as_deref().unwrap_or(&event.start_date),
+                Some(&end_time),
+                today,
+            )),
+            None => clean_optional(event.end_date.as_deref()),
+        };
+        // The today fallback for a missing start isn't a date the source gave
+        let start = if has_start(&event) {
+            parse_datetime(&start_date, today, zone)
+        } else {
+            None
+        };
+        if start.is_none() && has_start(&event) {
+            issues.push((DataIssue::UnreadableDate, start_date.clone()));
+        }
+        let end = end_date
+            .as_deref()
+            .and_then(|end| parse_datetime(end, today, zone));
+        // An end read from the start's own element, or before the start, says nothing about the end
+        let (end_date, end) = match (start, end) {
+            _ if end_date.as_deref() == Some(start_date.as_str()) => (None, None),
+            (Some(start), Some(end)) if end < start => {
+                issues.push((DataIssue::EndBeforeStart, end_date.unwrap_or_default()));
+                (None, None)
+            }
+            _ => (end_date, end),
+        };
+        let name = clean_text(&event.name);
+        if name.chars().count() > LONG_NAME_CHARS {
+            issues.push((DataIssue::LongName, name.clone()));
+        }
+        let location = clean_optional(event.location.as_deref());
+        if let (None, Some(raw)) = (&location, &event.location) {
+            issues.push((DataIssue::EmptyLocation, raw.clone()));
+        }
+        let processed = ProcessedEvent {
+            name,
+            start,
+            end,
+            start_date,
+            end_date,
+            location,
+            url: event.url,
+            description: clean_optional(event.description.as_deref()),
+            price: event.price.as_deref().and_then(normalize_price),
+            image_url: event.image_url.filter(|url| !url.is_empty()),
+            categories: event.categories,
+            source: event.source,
+            status: event.status,
+            tags: normalize_tags(&event.tags),
+            alt_urls: Vec::new(),
+            score: 0,
+            matched_interests: Vec::new(),
+            distance: None,
+        };
+        (processed, issues)
+    }
+
+    /// Returns the event in its serialized form, described on the `Serialize` impl.
+    pub fn to_json(&self) -> Value {
+        json!({
+            "id": self.id(),
+            "name": self.name,
+            "start_date": self.start_date,
+            "end_date": self.end_date,
+            "start": self.start.map(|t| t.format(TIMESTAMP_FORMAT).to_string()),
+            "end": self.end.map(|t| t.format(TIMESTAMP_FORMAT).to_string()),
+            "location": self.location,
+            "url": self.url,
+            "description": self.description,
+            "price": self.price,
+            "image_url": self.image_url,
+            "categories": self.categories.iter().map(|c| c.to_string()).collect::<Vec<_>>(),
+            "source": self.source,
+            "status": self.status.to_string(),
+            "tags": self.tags,
+            "alt_urls": self.alt_urls,
+        })
+    }
+
+    /// Reads an event from its serialized form; only `name` is required.
+    ///
+    /// # Returns
+    ///
+    /// The event, or `None` if the value isn't an object with a string `name`.
+    pub fn from_json(json: &Value) -> Option<ProcessedEvent> {
+        let text = |key: &str| json[key].as_str().map(str::to_string);
+        let texts = |key: &str| -> Vec<String> {
+            json[key]
+                .as_array()
+                .map(|items| {
+                    items
+                        .iter()
+                        .filter_map(|t| Some(t.as_str()?.to_string()))
+                        .collect()
+                })
+                .unwrap_or_default()
+        };
+        Some(ProcessedEvent {
+            name: text("name")?,
+            start_date: text("start_date").unwrap_or_default(),
+            end_date: text("end_date"),
+            start: text("start")
+                .and_then(|t| NaiveDateTime::parse_from_str(&t, TIMESTAMP_FORMAT).ok()),
+            end: text("end").and_then(|t| NaiveDateTime::parse_from_str(&t, TIMESTAMP_FORMAT).ok()),
+            location: text("location"),
+            url: text("url").unwrap_or_default(),
+            description: text("description"),
+            price: text("price"),
+            image_url: text("image_url"),
+            categories: texts("categories")
+                .iter()
+                .filter_map(|c| c.parse().ok())
+                .collect(),
+            source: text("source"),
+            status: text("status")
+                .map(|s| EventStatus::from_text(&s))
+                .unwrap_or_default(),
+            tags: texts("tags"),
+            alt_urls: texts("alt_urls"),
+            // Scores and distances depend on the current settings, so they are recomputed rather than saved
+            score: 0,
+            matched_interests: Vec::new(),
+            distance: None,
+        })
+    }
+
+    /// Returns an identifier that stays the same for the same event across runs, as 16 hex
+    /// digits.
+    ///
+    /// The identifier is a hash of the event's URL, normalized with `html_parser::normalize_url`
+    /// so tracking parameters and fragments don't matter. Events without a usable URL are
+    /// identified by their name, start, and location instead (compared by `normalize_key`), so two
+    /// nights of the same show at the same venue differ. The description, price, and other
+    /// details that a listing may reword never affect it.
+    ///
+    /// # Returns
+    ///
+    /// The identifier, e.g. `"a0f3c2e1d4b59687"`.
+    pub fn id(&self) -> String {
+        let key = match html_parser::normalize_url(&self.url) {
+            Some(url) => format!("url:{}", url),
+            None => {
+                let start = match self.start {
+                    Some(start) => start.format("%Y-%m-%dT%H:%M").to_string(),
+                    None => clean_text(&self.start_date),
+                };
+                let location = self
+                    .location
+                    .as_deref()
+                    .map(normalize_key)
+                    .unwrap_or_default();
+                format!(
+                    "event:{}\n{}\n{}",
+                    name_tokens(&self.name).join(" "),
+                    start,
+                    location.trim()
+                )
+            }
+        };
+        format!("{:016x}", fnv1a(key.as_bytes()))
+    }
+}
+
+/// How parsed start and end times are written when events are serialized.
+const TIMESTAMP_FORMAT: &str = "%Y-%m-%dT%H:%M:%S";
+
+/// Serializes an event as an object with these snake_case fields, which are kept stable for
+/// snapshots and exports:
+///
+/// | Field | Value |
+/// |-------|-------|
+/// | `id` | `ProcessedEvent::id`; written for other tools and ignored when reading |
+/// | `name`, `url`, `start_date` | strings, as shown |
+/// | `end_date`, `location`, `description`, `price`, `image_url`, `source` | strings or `null` |
+/// | `start`, `end` | `YYYY-MM-DDTHH:MM:SS` in the home timezone, or `null` if unread |
+/// | `categories` | e.g. `["music"]` |
+/// | `status` | `available`, `sold out`, `cancelled`, `postponed`, or `unknown` |
+/// | `tags`, `alt_urls` | arrays of strings |
+///
+/// The score, matched interests, and distance depend on the current settings and aren't
+/// serialized.
+impl Serialize for ProcessedEvent {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        self.to_json().serialize(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for ProcessedEvent {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let json = Value::deserialize(deserializer)?;
+        ProcessedEvent::from_json(&json)
+            .ok_or_else(|| D::Error::custom("an event must be an object with a string name"))
+    }
+}
+
+/// The 64-bit FNV-1a hash, which unlike `std`'s hashers is fixed and so safe to persist.
+pub(crate) fn fnv1a(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(0xcbf2_9ce4_8422_2325, |hash, byte| {
+        (hash ^ u64::from(*byte)).wrapping_mul(0x0000_0100_0000_01b3)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_process_data() {
+        // Create mock raw events
+        let raw_events = vec![
+            Event {
+                name: "Concert".to_string(),
+                start_date: "January 1, 2023".to_string(),
+                end_date: Some("".to_string()),
+                location: Some("Park".to_string()),
+                url: "http://example.com/concert".to_string(),
+                ..Default::default()
+            },
+            Event {
+                name: "Festival".to_string(),
+                start_date: "January 2, 2023".to_string(),
+                end_date: Some("January 3, 2023           ".to_string()),
+                location: Some("Beach".to_string()),
+                url: "http://example.com/festival".to_string(),
+                ..Default::default()
+            },
+        ];
+
+        // Call the process_data function
+        let processed_events = process_data(raw_events, Zone::default()).0;
+
+        // Define expected processed events
+        let expected_events = vec![
+            ProcessedEvent {
+                name: "Concert".to_string(),
+                start_date: "January 1, 2023".to_string(),
+                end_date: None,
+                start: NaiveDate::from_ymd_opt(2023, 1, 1)
+                    .unwrap()
+                    .and_hms_opt(0, 0, 0),
+                location: Some("Park".to_string()),
+                url: "http://example.com/concert".to_string(),
+                ..Default::default()
+            },
+            ProcessedEvent {
+                name: "Festival".to_string(),
+                start_date: "January 2, 2023".to_string(),
+                end_date: Some("January 3, 2023".to_string()),
+                start: NaiveDate::from_ymd_opt(2023, 1, 2)
+                    .unwrap()
+                    .and_hms_opt(0, 0, 0),
+                end: NaiveDate::from_ymd_opt(2023, 1, 3)
+                    .unwrap()
+                    .and_hms_opt(0, 0, 0),
+                location: Some("Beach".to_string()),
+                url: "http://example.com/festival".to_string(),
+                ..Default::default()
+            },
+        ];
+
+        // Assertions
+        assert_eq!(processed_events, expected_events);
+    }
+
+    #[test]
+    fn test_filter_by_category_matches_any() {
+        let event = |name: &str, categories: &[Category]| ProcessedEvent {
+            name: name.to_string(),
+            categories: categories.iter().copied().collect(),
+            ..Default::default()
+        };
+        let events = vec![
+            event("Both", &[Category::Music, Category::Unique]),
+            event("Music", &[Category::Music]),
+            event("General", &[Category::General]),
+        ];
+
+        let names =
+            |events: Vec<ProcessedEvent>| events.into_iter().map(|e| e.name).collect::<Vec<_>>();
+
+        assert_eq!(
+            names(filter_by_category(events.clone(), &[Category::Unique])),
+            vec!["Both"]
+        );
+        assert_eq!(
+            names(filter_by_category(events.clone(), &[Category::Music])),
+            vec!["Both", "Music"]
+        );
+        assert_eq!(
+            names(filter_by_category(
+                events.clone(),
+                &[Category::General, Category::Unique]
+            )),
+            vec!["Both", "General"]
+        );
+        assert!(filter_by_category(events, &[]).is_empty());
+    }
+
+    #[test]
+    fn test_process_data_fields_not_provided_by_source() {
+        let raw_events = vec![
+            Event {
+                name: "No end, no venue".to_string(),
+                start_date: "May 2".to_string(),
+                ..Default::default()
+            },
+            Event {
+                name: "Blank venue".to_string(),
+                start_date: "May 2".to_string(),
+                end_date: Some("May 3".to_string()),
+                location: Some("   ".to_string()),
+                ..Default::default()
+            },
+        ];
+
+        let processed_events = process_data(raw_events, Zone::default()).0;
+
+        assert_eq!(processed_events[0].end_date, None);
+        assert_eq!(processed_events[0].location, None);
+        assert_eq!(processed_events[1].end_date.as_deref(), Some("May 3"));
+        assert_eq!(processed_events[1].location, None);
+    }
+
+    #[test]
+    fn test_process_data_drops_ends_that_repeat_or_precede_the_start() {
+        let raw = |start: &str, end: &str| Event {
+            name: "Show".to_string(),
+            start_date: start.to_string(),
+            end_date: Some(end.to_string()),
+            ..Default::default()
+        };
+        let raw_events = vec![
+            raw(
+                "Thursday 2 May 2024, 7:00 PM",
+                " Thursday 2 May 2024, 7:00 PM ",
+            ),
+            raw("2024-05-02 19:00", "2024-05-01 22:00"),
+            raw("2024-05-02 19:00", "2024-05-02 22:00"),
+        ];
+
+        let processed = process_data(raw_events, Zone::default()).0;
+
+        let ends: Vec<_> = processed
+            .iter()
+            .map(|e| (e.end_date.as_deref(), e.end))
+            .collect();
+        assert_eq!(
+            ends,
+            vec![
+                (None, None),
+                (None, None),
+                (Some("2024-05-02 22:00"), at("2024-05-02", "22:00"))
+            ]
+        );
+    }
+
+    #[test]
+    fn test_expand_multi_day() {
+        let spanning = |name: &str, start: &str, end: &str| ProcessedEvent {
+            name: name.to_string(),
+            start_date: start.to_string(),
+            end_date: Some(end.to_string()),
+            start: NaiveDateTime::parse_from_str(start, "%Y-%m-%d %H:%M").ok(),
+            end: NaiveDateTime::parse_from_str(end, "%Y-%m-%d %H:%M").ok(),
+            ..Default::default()
+        };
+        let one_day = spanning("Show", "2024-05-03 19:00", "2024-05-03 23:00");
+        let overnight = spanning("Rave", "2024-05-03 22:00", "2024-05-04 04:00");
+        let festival = spanning("Festival", "2024-05-03 11:00", "2024-05-05 22:00");
+        let absurd = spanning("Exhibit", "2024-05-03 10:00", "2025-06-07 17:00");
+
+        assert_eq!(
+            expand_multi_day(vec![one_day.clone()], DEFAULT_MAX_SPAN_DAYS),
+            vec![one_day]
+        );
+        assert_eq!(
+            expand_multi_day(vec![absurd.clone()], DEFAULT_MAX_SPAN_DAYS),
+            vec![absurd.clone()]
+        );
+        assert_eq!(expand_multi_day(vec![absurd], 401).len(), 401);
+
+        let days = expand_multi_day(vec![festival], DEFAULT_MAX_SPAN_DAYS);
+        assert_eq!(
+            names(&days),
+            vec![
+                "Festival (day 1 of 3)",
+                "Festival (day 2 of 3)",
+                "Festival (day 3 of 3)"
+            ]
+        );
+        let starts: Vec<_> = days
+            .iter()
+            .map(|e| (e.start_date.as_str(), e.start))
+            .collect();
+        assert_eq!(
+            starts,
+            vec![
+                ("2024-05-03 11:00", at("2024-05-03", "11:00")),
+                ("May 4 11:00 AM", at("2024-05-04", "11:00")),
+                ("May 5 11:00 AM", at("2024-05-05", "11:00")),
+            ]
+        );
+        let ends: Vec<_> = days
+            .iter()
+            .map(|e| (e.end_date.as_deref(), e.end))
+            .collect();
+        assert_eq!(
+            ends,
+            vec![
+                (None, None),
+                (None, None),
+                (Some("2024-05-05 22:00"), at("2024-05-05", "22:00"))
+            ]
+        );
+        // Expanded days don't overlap, so a one-day range keeps just that day
+        let on_fourth = filter_by_range(days.clone(), day("2024-05-04"), day("2024-05-04"), true);
+        assert_eq!(names(&on_fourth), vec!["Festival (day 2 of 3)"]);
+        assert_eq!(expand_multi_day(days.clone(), DEFAULT_MAX_SPAN_DAYS), days);
+
+        assert_eq!(names(&expand_multi_day(vec![overnight], 1)), vec!["Rave"]);
+    }
+
+    #[test]
+    fn test_process_data_warnings() {
+        let clean = Event {
+            name: "Show".to_string(),
+            start_date: "2024-05-02 19:00".to_string(),
+            end_date: Some("2024-05-02 22:00".to_string()),
+            location: Some("Ryman".to_string()),
+            ..Default::default()
+        };
+        let long_name = "Join us for an evening of music ".repeat(4);
+        let raw_events = vec![
+            clean.clone(),
+            Event {
+                start_date: "Most Thursdays".to_string(),
+                ..clean.clone()
+            },
+            Event {
+                end_date: Some("2024-05-01 22:00".to_string()),
+                ..clean.clone()
+            },
+            Event {
+                name: long_name.clone(),
+                ..clean.clone()
+            },
+            Event {
+                location: Some(" &nbsp; ".to_string()),
+                ..clean.clone()
+            },
+            // No start or location given at all isn't a problem with the data
+            Event {
+                start_date: String::new(),
+                end_date: None,
+                location: None,
+                ..clean.clone()
+            },
+        ];
+
+        let (processed, warnings) = process_data(raw_events, Zone::default());
+
+        assert_eq!(processed.len(), 6);
+        assert_eq!(
+            warnings,
+            vec![
+                DataWarning {
+                    event_index: 1,
+                    kind: DataIssue::UnreadableDate,
+                    text: "Most Thursdays".to_string()
+                },
+                DataWarning {
+                    event_index: 2,
+                    kind: DataIssue::EndBeforeStart,
+                    text: "2024-05-01 22:00".to_string()
+                },
+                DataWarning {
+                    event_index: 3,
+                    kind: DataIssue::LongName,
+                    text: long_name.trim().to_string()
+                },
+                DataWarning {
+                    event_index: 4,
+                    kind: DataIssue::EmptyLocation,
+                    text: " &nbsp; ".to_string()
+                },
+            ]
+        );
+        assert!(process_data(vec![clean; 3], Zone::default()).1.is_empty());
+    }
+
+    #[test]
+    fn test_parse_duration() {
+        assert_eq!(parse_duration("90m"), Ok(Duration::minutes(90)));
+        assert_eq!(parse_duration("2h"), Ok(Duration::hours(2)));
+        assert_eq!(parse_duration("2h30m"), Ok(Duration::minutes(150)));
+        assert_eq!(parse_duration("2H 30M"), Ok(Duration::minutes(150)));
+        assert_eq!(parse_duration("1d"), Ok(Duration::days(1)));
+        assert_eq!(parse_duration("45"), Ok(Duration::minutes(45)));
+        for bad in ["", "0", "0h", "h", "2x", "2h30", "-5", "three hours"] {
+            assert!(parse_duration(bad).is_err(), "{:?} should be rejected", bad);
+        }
+    }
+
+    #[test]
+    fn test_duration_and_filter() {
+        let timed = |name: &str, start: &str, end: Option<&str>| ProcessedEvent {
+            end: end.map(|e| NaiveDateTime::parse_from_str(e, "%Y-%m-%d %H:%M").unwrap()),
+            end_date: end.map(str::to_string),
+            ..sortable(name, Some(start), None)
+        };
+        let events = vec![
+            timed("Concert", "2024-05-02 19:00", Some("2024-05-02 21:30")),
+            timed("Festival", "2024-05-03 00:00", Some("2024-05-05 00:00")),
+            timed("Open end", "2024-05-02 19:00", None),
+            ProcessedEvent {
+                end: at("2024-05-02", "22:00"),
+                ..sortable("Estimated", Some("2024-05-02 19:00"), None)
+            },
+        ];
+
+        let durations: Vec<Option<String>> = events
+            .iter()
+            .map(|e| duration(e).map(format_duration))
+            .collect();
+        assert_eq!(
+            durations,
+            vec![
+                Some("2h 30m".to_string()),
+                Some("3 days".to_string()),
+                None,
+                None
+            ]
+        );
+
+        let (kept, dropped) = filter_by_duration(events.clone(), Duration::hours(3), true);
+        assert_eq!(
+            (names(&kept), dropped),
+            (vec!["Concert", "Open end", "Estimated"], 1)
+        );
+        let (kept, dropped) = filter_by_duration(events, Duration::hours(3), false);
+        assert_eq!((names(&kept), dropped), (vec!["Concert"], 3));
+    }
+
+    #[test]
+    fn test_summarize() {
+        let event = |source: &str,
+                     start: Option<&str>,
+                     venue: Option<&str>,
+                     price: Option<&str>| ProcessedEvent {
+            source: Some(source.to_string()),
+            price: price.map(str::to_string),
+            ..sortable("Show", start, venue)
+        };
+        let events = vec![
+            event(
+                "songkick",
+                Some("2024-05-02 20:00"),
+                Some("Ryman"),
+                Some("$25.00"),
+            ),
+            event(
+                "songkick",
+                Some("2024-05-01 10:00"),
+                Some("ryman "),
+                Some("Free"),
+            ),
+            ProcessedEvent {
+                end: Some(
+                    NaiveDateTime::parse_from_str("2024-05-04 18:00", "%Y-%m-%d %H:%M").unwrap(),
+                ),
+                ..event("perto", Some("2024-04-30 10:00"), Some("Fairgrounds"), None)
+            },
+            event("perto", Some("2024-05-03 19:00"), Some("Station Inn"), None),
+            event("songkick", None, Some("Café Lex"), Some("Free")),
+            event("nashville.com", Some("2024-06-01 19:00"), None, None),
+        ];
+
+        let summary = summarize(&events, day("2024-05-02"));
+
+        assert_eq!(
+            summary.sources,
+            vec![
+                ("songkick".to_string(), 3),
+                ("perto".to_string(), 2),
+                ("nashville.com".to_string(), 1)
+            ]
+        );
+        assert_eq!(summary.top_venues[0], ("Ryman".to_string(), 2));
+        // The event of May 1st is over; the fair that began on April 30th is still on
+        assert_eq!(
+            (
+                summary.venues,
+                summary.tonight,
+                summary.later,
+                summary.past,
+                summary.undated,
+                summary.free,
+                summary.paid
+            ),
+            (4, 2, 2, 1, 1, 2, 1)
+        );
+        assert_eq!(summary.to_string(), "6 events from 3 sources · 4 venues · 2 tonight, 2 later, 1 past, 1 undated · 2 free, 1 paid");
+    }
+
+    #[test]
+    fn test_summarize_edge_cases() {
+        assert_eq!(summarize(&[], day("2024-05-02")).to_string(), "no events");
+
+        let undated = vec![sortable("A", None, None)];
+        assert_eq!(
+            summarize(&undated, day("2024-05-02")).to_string(),
+            "1 event · 1 undated"
+        );
+    }
+
+    #[test]
+    fn test_score_event() {
+        let interests = vec![
+            Interest {
+                term: "jazz".to_string(),
+                weight: 3,
+            },
+            Interest {
+                term: "The Basement".to_string(),
+                weight: 5,
+            },
+            Interest {
+                term: "Comedy".to_string(),
+                weight: 2,
+            },
+            Interest {
+                term: "trivia".to_string(),
+                weight: -4,
+            },
+        ];
+        let mut jazz = sortable("Late Night Jazz", None, Some("The Basement East"));
+        jazz.description = Some("Jazz trio".to_string());
+        let mut comedy = sortable("Open Mic", None, Some("Zanies"));
+        comedy.tags = vec!["comédy".to_string()];
+        let trivia = sortable("Trivia and Jazzercise", None, None);
+        let plain = sortable("Bluegrass Jam", None, Some("Station Inn"));
+
+        assert_eq!(
+            score_event(&jazz, &interests),
+            (8, vec!["jazz".to_string(), "The Basement".to_string()])
+        );
+        assert_eq!(
+            score_event(&comedy, &interests),
+            (2, vec!["Comedy".to_string()])
+        );
+        assert_eq!(
+            score_event(&trivia, &interests),
+            (-4, vec!["trivia".to_string()])
+        );
+        assert_eq!(score_event(&plain, &interests), (0, Vec::new()));
+        assert_eq!(score_event(&jazz, &[]), (0, Vec::new()));
+
+        let mut ranked = rank_events(vec![plain, trivia, comedy, jazz], &interests);
+        sort_events(&mut ranked, SortKey::Score, Order::Ascending);
+        assert_eq!(
+            names(&ranked),
+            vec![
+                "Late Night Jazz",
+                "Open Mic",
+                "Bluegrass Jam",
+                "Trivia and Jazzercise"
+            ]
+        );
+        sort_events(&mut ranked, SortKey::Score, Order::Descending);
+        assert_eq!(names(&ranked)[0], "Trivia and Jazzercise");
+    }
+
+    #[test]
+    fn test_pick_random() {
+        use rand::rngs::StdRng;
+        use rand::SeedableRng;
+
+        let now = at("2024-05-02", "12:00").unwrap();
+        let events = vec![
+            sortable("Tonight", Some("2024-05-02 20:00"), None),
+            sortable("Next month", Some("2024-06-02 20:00"), None),
+            sortable("Undated", None, None),
+        ];
+        let pick = |seed, favor_soonest| {
+            pick_random(
+                &events,
+                now,
+                favor_soonest,
+                &mut StdRng::seed_from_u64(seed),
+            )
+            .map(|e| e.name.clone())
+        };
+
+        assert_eq!(pick(7, false), pick(7, false));
+        assert_eq!(pick(7, true), pick(7, true));
+        assert_eq!(
+            pick_random(&[], now, true, &mut StdRng::seed_from_u64(7)),
+            None
+        );
+
+        let mut rng = StdRng::seed_from_u64(1);
+        let tonight = (0..1000)
+            .filter(|_| pick_random(&events, now, true, &mut rng).unwrap().name == "Tonight")
+            .count();
+        assert!(tonight > 700, "picked tonight's event {} times", tonight);
+    }
+
+    #[test]
+    fn test_limit_events() {
+        let events = || {
+            vec![
+                sortable("A", None, None),
+                sortable("B", None, None),
+                sortable("C", None, None),
+            ]
+        };
+
+        let (kept, left_out) = limit_events(events(), Some(2));
+        assert_eq!((names(&kept), left_out), (vec!["A", "B"], 1));
+        let (kept, left_out) = limit_events(events(), Some(5));
+        assert_eq!((kept.len(), left_out), (3, 0));
+        assert_eq!(limit_events(events(), None).0.len(), 3);
+    }
+
+    #[test]
+    fn test_venue_aliases() {
+        let pairs = |prefix| {
+            VenueAliases::new(
+                vec![
+                    ("The Ryman".to_string(), "Ryman Auditorium".to_string()),
+                    ("Ryman Aud.".to_string(), "Ryman Auditorium".to_string()),
+                    ("Ryman".to_string(), "Ryman Auditorium".to_string()),
+                    ("Café Lexington".to_string(), "Café Lex".to_string()),
+                ],
+                prefix,
+            )
+        };
+        let (exact, prefix) = (pairs(false), pairs(true));
+
+        assert_eq!(exact.canonical("The Ryman"), Some("Ryman Auditorium"));
+        assert_eq!(exact.canonical("  the RYMAN "), Some("Ryman Auditorium"));
+        assert_eq!(exact.canonical("cafe lexington"), Some("Café Lex"));
+        assert_eq!(exact.canonical("Ryman Aud. - Nashville"), None);
+        assert_eq!(
+            prefix.canonical("Ryman Aud. - Nashville"),
+            Some("Ryman Auditorium")
+        );
+        assert_eq!(prefix.canonical("Rymanesque Hall"), None);
+        assert_eq!(prefix.canonical("Station Inn"), None);
+
+        let events = vec![
+            sortable("A", None, Some("ryman aud.")),
+            sortable("B", None, Some("Station Inn")),
+            sortable("C", None, None),
+        ];
+        let renamed = canonicalize_venues(events, &exact);
+        let venues: Vec<_> = renamed.iter().map(|e| e.location.as_deref()).collect();
+        assert_eq!(
+            venues,
+            vec![Some("Ryman Auditorium"), Some("Station Inn"), None]
+        );
+    }
+
+    #[test]
+    fn test_fill_missing_ends() {
+        let mut given = sortable("Given", Some("2024-05-02 19:00"), None);
+        given.end = at("2024-05-02", "20:00");
+        let events = vec![
+            sortable("Concert", Some("2024-05-02 19:00"), None),
+            given,
+            sortable("Fair", Some("2024-05-02 00:00"), None),
+            sortable("Undated", None, None),
+        ];
+
+        let filled = fill_missing_ends(events, Duration::hours(3));
+
+        let ends: Vec<_> = filled.iter().map(|e| e.end).collect();
+        assert_eq!(
+            ends,
+            vec![
+                at("2024-05-02", "22:00"),
+                at("2024-05-02", "20:00"),
+                None,
+                None
+            ]
+        );
+        assert!(filled.iter().all(|e| e.end_date.is_none()));
+        let formatted = apply_date_format(filled, "%H:%M", Zone::default());
+        assert_eq!(formatted[0].end_date, None);
+    }
+
+    #[test]
+    fn test_clean_text_strips_markup_and_whitespace() {
+        assert_eq!(clean_text("  <strong>Band</strong>\n  Name "), "Band Name");
+        assert_eq!(clean_text("Rock &amp; Roll"), "Rock & Roll");
+        assert_eq!(clean_text("&lt;p&gt;Escaped&lt;/p&gt;"), "<p>Escaped</p>");
+        assert_eq!(clean_text("1 < 2"), "1 < 2");
+    }
+
+    #[test]
+    fn test_clean_text_decodes_entities_once() {
+        assert_eq!(clean_text("x&lt;y&gt;z"), "x<y>z");
+        assert_eq!(clean_text("&amp;lt;"), "&lt;");
+        assert_eq!(clean_text("Live: &lt;Band Name&gt;"), "Live: <Band Name>");
+    }
+
+    #[test]
+    fn test_clean_description_strips_escaped_markup() {
+        assert_eq!(
+            clean_description("&lt;p&gt;Doors at 7&lt;/p&gt;&lt;br/&gt;All ages"),
+            "Doors at 7All ages"
+        );
+        assert_eq!(
+            clean_description("<p>Live&nbsp;music &amp;amp; <b>dancing</b></p>"),
+            "Live music & dancing"
+        );
+        assert_eq!(clean_description("  Plain\n text "), "Plain text");
+    }
+
+    #[test]
+    fn test_clean_text_cases() {
+        let cases = [
+            ("", ""),
+            ("   \n\t  ", ""),
+            ("Plain", "Plain"),
+            ("Pretty\n        printed\n    text", "Pretty printed text"),
+            ("Non\u{a0}breaking&nbsp;spaces", "Non breaking spaces"),
+            (
+                "Thin\u{2009}and\u{3000}ideographic\u{2028}spaces",
+                "Thin and ideographic spaces",
+            ),
+            ("Zero\u{200B}width", "Zerowidth"),
+            ("\u{FEFF}Byte order mark", "Byte order mark"),
+            ("Soft\u{00AD}hyphen", "Softhyphen"),
+            ("Bell\u{7}character", "Bellcharacter"),
+            (
+                "Family 👨\u{200D}👩\u{200D}👧",
+                "Family 👨\u{200D}👩\u{200D}👧",
+            ),
+            ("It&#8217;s &quot;live&quot;", "It\u{2019}s \"live\""),
+            ("Double &amp;amp; escaped", "Double &amp; escaped"),
+            ("<p>Stray <br/>tags</p><div>here</div>", "Stray tagshere"),
+            ("<a href=\"/x\">Link</a> text", "Link text"),
+            ("Unclosed <b>bold", "Unclosed bold"),
+            ("5 > 3 & 2 < 4", "5 > 3 & 2 < 4"),
+        ];
+        for (input, expected) in cases {
+            assert_eq!(clean_text(input), expected, "cleaning {:?}", input);
+        }
+    }
+
+    #[test]
+    fn test_truncate_at_word() {
+        assert_eq!(truncate_at_word("short", 10), "short");
+        assert_eq!(truncate_at_word("one two three", 9), "one two…");
+        assert_eq!(truncate_at_word("one two three", 8), "one two…");
+        assert_eq!(truncate_at_word("abcdefghij", 5), "abcd…");
+    }
+
+    #[test]
+    fn test_process_data_cleans_description() {
+        let raw_events = vec![Event {
+            name: "Show".to_string(),
+            description: Some("  An evening of &amp; music\n   under the stars ".to_string()),
+            image_url: Some("http://example.com/a.jpg".to_string()),
+            ..Default::default()
+        }];
+
+        let processed_events = process_data(raw_events, Zone::default()).0;
+
+        assert_eq!(
+            processed_events[0].description.as_deref(),
+            Some("An evening of & music under the stars")
+        );
+        assert_eq!(
+            processed_events[0].image_url.as_deref(),
+            Some("http://example.com/a.jpg")
+        );
+    }
+
+    #[test]
+    fn test_normalize_price() {
+        assert_eq!(normalize_price("$25").as_deref(), Some("$25.00"));
+        assert_eq!(normalize_price("25 USD").as_deref(), Some("$25.00"));
+        assert_eq!(normalize_price("12.5").as_deref(), Some("$12.50"));
+        assert_eq!(
+            normalize_price("15-40 USD").as_deref(),
+            Some("$15.00 - $40.00")
+        );
+        assert_eq!(normalize_price("20 EUR").as_deref(), Some("€20.00"));
+        assert_eq!(normalize_price("0 USD").as_deref(), Some("Free"));
+        assert_eq!(normalize_price("FREE admission").as_deref(), Some("Free"));
+        assert_eq!(
+            normalize_price("Donations welcome").as_deref(),
+            Some("Donations welcome")
+        );
+        assert_eq!(normalize_price("   "), None);
+    }
+
+    #[test]
+    fn test_normalize_key_equal_pairs() {
+        let pairs = [
+            ("Café", "cafe"),
+            ("CAFÉ", "Cafe"),
+            ("Cafe\u{301}", "Café"),
+            ("Tootsie's Orchid Lounge", "TOOTSIES ORCHID LOUNGE"),
+            ("Tootsie’s", "tootsies"),
+            ("Ryman Aud.", "ryman aud"),
+            ("The Basement — East", "the basement east"),
+            ("Straße", "STRASSE"),
+            ("ﬁve points", "five points"),
+            ("ＲＹＭＡＮ", "ryman"),
+            ("  Jazz\tBrunch!! ", "jazz brunch"),
+            ("Ǆemal", "dzemal"),
+        ];
+        for (a, b) in pairs {
+            assert_eq!(normalize_key(a), normalize_key(b), "{:?} vs {:?}", a, b);
+        }
+        assert_ne!(normalize_key("Barn"), normalize_key("Bar"));
+        assert_eq!(normalize_key("!!!"), "");
+    }
+
+    #[test]
+    fn test_normalize_key_is_idempotent() {
+        let pool: Vec<char> = "aZé\u{301}\u{308}ßẞſςİıﬁﬀＡｚ①½ 　\t'’-.!,&/ÆøÅñÇ日本한글Ǆ0٣"
+            .chars()
+            .collect();
+        use rand::rngs::StdRng;
+        use rand::SeedableRng;
+
+        let mut rng = StdRng::seed_from_u64(7);
+        for _ in 0..2000 {
+            let length = rng.gen_range(0..12);
+            let text: String = (0..length)
+                .map(|_| *pool.choose(&mut rng).unwrap())
+                .collect();
+            let once = normalize_key(&text);
+            assert_eq!(normalize_key(&once), once, "{:?}", text);
+            assert!(
+                !once.starts_with(' ') && !once.ends_with(' ') && !once.contains("  "),
+                "{:?}",
+                text
+            );
+        }
+    }
+
+    #[test]
+    fn test_comparisons_use_normalized_keys() {
+        let events = vec![
+            sortable("zebra", None, None),
+            sortable("Éclair", None, None),
+            sortable("apple", None, None),
+        ];
+        let mut sorted = events.clone();
+        sort_events(&mut sorted, SortKey::Name, Order::Ascending);
+        assert_eq!(names(&sorted), vec!["apple", "Éclair", "zebra"]);
+
+        let cafe = ProcessedEvent {
+            name: "Open mic at CAFÉ LEX".to_string(),
+            ..Default::default()
+        };
+        assert_eq!(
+            search_events(vec![cafe.clone()], &["cafe".to_string()], Match::All).len(),
+            1
+        );
+        let aliases = VenueAliases::new(
+            vec![(
+                "Tootsie's".to_string(),
+                "Tootsie's Orchid Lounge".to_string(),
+            )],
+            false,
+        );
+        assert_eq!(
+            aliases.canonical("TOOTSIES"),
+            Some("Tootsie's Orchid Lounge")
+        );
+        assert_eq!(
+            ProcessedEvent {
+                location: Some("Café Lex".to_string()),
+                ..cafe.clone()
+            }
+            .id(),
+            ProcessedEvent {
+                location: Some("cafe lex.".to_string()),
+                ..cafe.clone()
+            }
+            .id()
+        );
+        // Only comparisons change; the text is shown as the source wrote it
+        assert_eq!(
+            search_events(vec![cafe], &["lex".to_string()], Match::All)[0].name,
+            "Open mic at CAFÉ LEX"
+        );
+    }
+
+    #[test]
+    fn test_classify_price() {
+        let cases = [
+            (Some("Free"), None, PriceClass::Free),
+            (Some("$0"), None, PriceClass::Free),
+            (Some("0 USD"), None, PriceClass::Free),
+            (Some("FREE before 9pm"), None, PriceClass::Free),
+            (Some("No Cover"), None, PriceClass::Free),
+            (Some("$10-$15"), None, PriceClass::Paid),
+            (
+                Some("$10.00 - $15.00"),
+                Some("Free parking"),
+                PriceClass::Paid,
+            ),
+            (Some("Donations welcome"), None, PriceClass::Unknown),
+            (
+                Some("Donations welcome"),
+                Some("Free and open to all"),
+                PriceClass::Free,
+            ),
+            (None, Some("No cover charge all night"), PriceClass::Free),
+            (None, Some("Freestyle rap battle"), PriceClass::Unknown),
+            (None, None, PriceClass::Unknown),
+        ];
+        for (price, description, expected) in cases {
+            assert_eq!(
+                classify_price(price, description),
+                expected,
+                "{:?} / {:?}",
+                price,
+                description
+            );
+        }
+    }
+
+    #[test]
+    fn test_filter_free() {
+        let priced = |name: &str, price: Option<&str>| ProcessedEvent {
+            name: name.to_string(),
+            price: price.map(str::to_string),
+            ..Default::default()
+        };
+        let events = vec![
+            priced("Free", Some("Free")),
+            priced("Paid", Some("$20.00")),
+            priced("Unknown", None),
+        ];
+
+        let (kept, dropped) = filter_free(events.clone(), false);
+        assert_eq!((names(&kept), dropped), (vec!["Free"], 2));
+        let (kept, dropped) = filter_free(events, true);
+        assert_eq!((names(&kept), dropped), (vec!["Free", "Unknown"], 1));
+    }
+
+    #[test]
+    fn test_process_data_normalizes_price() {
+        let raw_events = vec![
+            Event {
+                name: "Paid".to_string(),
+                price: Some("<span>$10</span>".to_string()),
+                ..Default::default()
+            },
+            Event {
+                name: "Unknown".to_string(),
+                ..Default::default()
+            },
+        ];
+
+        let processed_events = process_data(raw_events, Zone::default()).0;
+
+        assert_eq!(processed_events[0].price.as_deref(), Some("$10.00"));
+        assert_eq!(processed_events[1].price, None);
+    }
+
+    fn timed(start_date: &str, start_time: Option<&str>) -> Event {
+        Event {
+            name: "Show".to_string(),
+            start_date: start_date.to_string(),
+            start_time: start_time.map(str::to_string),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_process_data_combines_date_and_time() {
+        let events = vec![
+            timed("2024-05-02", Some("7:30 PM")),
+            timed("May 2", Some("Doors 8pm")),
+            Event {
+                end_time: Some("11:00 p.m.".to_string()),
+                ..timed("2024-05-02", Some("19:00"))
+            },
+        ];
+
+        let starts: Vec<(String, Option<String>)> = process_data(events, Zone::default())
+            .0
+            .into_iter()
+            .map(|e| (e.start_date, e.end_date))
+            .collect();
+
+        assert_eq!(
+            starts,
+            vec![
+                ("2024-05-02T19:30:00".to_string(), None),
+                ("May 2 20:00".to_string(), None),
+                (
+                    "2024-05-02T19:00:00".to_string(),
+                    Some("2024-05-02T23:00:00".to_string())
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_process_data_date_or_time_only() {
+        let date_only = process_data(
+            vec![timed("2024-05-02", None), timed("2024-05-02", Some(""))],
+            Zone::default(),
+        )
+        .0;
+        assert!(date_only.iter().all(|e| e.start_date == "2024-05-02"));
+
+        let time_only = process_data(vec![timed("", Some("7:30 PM"))], Zone::default())
+            .0
+            .remove(0);
+        let today = Zone::default().today().format("%Y-%m-%d").to_string();
+        assert_eq!(time_only.start_date, format!("{}T19:30:00", today));
+    }
+
+    #[test]
+    fn test_hide_unavailable() {
+        let event = |name: &str, status: EventStatus| ProcessedEvent {
+            name: name.to_string(),
+            status,
+            ..Default::default()
+        };
+        let events = vec![
+            event("Open", EventStatus::Available),
+            event("Gone", EventStatus::SoldOut),
+            event("Off", EventStatus::Cancelled),
+            event("Later", EventStatus::Postponed),
+            event("Unsure", EventStatus::Unknown),
+        ];
+
+        let names: Vec<String> = hide_unavailable(events)
+            .into_iter()
+            .map(|e| e.name)
+            .collect();
+
+        assert_eq!(names, vec!["Open", "Later", "Unsure"]);
+    }
+
+    #[test]
+    fn test_transform_parse_and_apply() {
+        let parse = |s: &str| s.parse::<Transform>();
+
+        assert_eq!(parse("trim"), Ok(Transform::Trim));
+        assert_eq!(
+            parse(r#"remove_suffix(" - Nashville, TN")"#),
+            Ok(Transform::RemoveSuffix(" - Nashville, TN".to_string()))
+        );
+        assert_eq!(
+            parse("remove_suffix(, TN)"),
+            Ok(Transform::RemoveSuffix(", TN".to_string()))
+        );
+        assert!(parse("remove_suffix()").is_err());
+        assert_eq!(
+            parse("shout").unwrap_err().to_string(),
+            "unknown transform 'shout'"
+        );
+
+        assert_eq!(
+            Transform::StripEmoji.apply("🎷 Jazz Night ⭐️"),
+            " Jazz Night "
+        );
+        assert_eq!(Transform::Titlecase.apply("BLUEGRASS jam"), "Bluegrass Jam");
+        assert_eq!(
+            Transform::RemoveSuffix("!".to_string()).apply("Show!"),
+            "Show"
+        );
+        assert_eq!(Transform::Uppercase.apply("Tpac"), "TPAC");
+        assert_eq!(
+            parse(r#"trim_chars("|")"#),
+            Ok(Transform::TrimChars("|".to_string()))
+        );
+        assert!(parse("strip_chars()")
+            .unwrap_err()
+            .to_string()
+            .contains("strip_chars"));
+        assert_eq!(
+            Transform::TrimChars("|•".to_string()).apply("| Jazz Night | • "),
+            "Jazz Night"
+        );
+        assert_eq!(
+            Transform::StripChars("*".to_string()).apply("**NEW** Show"),
+            "NEW Show"
+        );
+    }
+
+    #[test]
+    fn test_apply_transforms_composes_in_order() {
+        let events = vec![ProcessedEvent {
+            name: "🔥🔥 FRIDAY   NIGHT jazz 🎷".to_string(),
+            location: Some("Exit/In - Nashville, TN".to_string()),
+            description: Some("🎉".to_string()),
+            ..Default::default()
+        }];
+        let transforms = vec![
+            (
+                "name".to_string(),
+                vec![
+                    "strip_emoji".to_string(),
+                    "collapse_whitespace".to_string(),
+                    "titlecase".to_string(),
+                ],
+            ),
+            (
+                "location".to_string(),
+                vec![
+                    r#"remove_suffix(" - Nashville, TN")"#.to_string(),
+                    "uppercase".to_string(),
+                ],
+            ),
+            ("description".to_string(), vec!["strip_emoji".to_string()]),
+        ];
+
+        let event = apply_transforms(events, &transforms).remove(0);
+
+        assert_eq!(event.name, "Friday Night Jazz");
+        assert_eq!(event.location.as_deref(), Some("EXIT/IN"));
+        assert_eq!(event.description, None);
+    }
+
+    #[test]
+    fn test_normalize_tags() {
+        let tags: Vec<String> = ["Jazz", " live MUSIC ", "jazz", "", "Free"]
+            .iter()
+            .map(|t| t.to_string())
+            .collect();
+
+        assert_eq!(normalize_tags(&tags), vec!["jazz", "live music", "free"]);
+    }
+
+    fn at(date: &str, time: &str) -> Option<NaiveDateTime> {
+        Some(
+            NaiveDateTime::parse_from_str(&format!("{} {}", date, time), "%Y-%m-%d %H:%M").unwrap(),
+        )
+    }
+
+    #[test]
+    fn test_parse_datetime_source_formats() {
+        let today = NaiveDate::from_ymd_opt(2024, 4, 20).unwrap();
+        let parse = |text: &str| parse_datetime(text, today, Zone::default());
+
+        // JSON-LD
+        assert_eq!(
+            parse("2024-05-02T19:00:00-05:00"),
+            at("2024-05-02", "19:00")
+        );
+        assert_eq!(parse("2024-05-02T19:00"), at("2024-05-02", "19:00"));
+        assert_eq!(parse("2024-05-02"), at("2024-05-02", "00:00"));
+        // RSS pubDate, converted to Nashville's time (CDT in May)
+        assert_eq!(
+            parse("Thu, 02 May 2024 19:00:00 -0500"),
+            at("2024-05-02", "19:00")
+        );
+        assert_eq!(
+            parse("Fri, 03 May 2024 01:30:00 +0000"),
+            at("2024-05-02", "20:30")
+        );
+        assert_eq!(
+            parse("Fri, 03 May 2024 01:30:00 GMT"),
+            at("2024-05-02", "20:30")
+        );
+        assert_eq!(
+            parse("Fri, 3 May 2024 01:30:00 UT"),
+            at("2024-05-02", "20:30")
+        );
+        assert_eq!(parse("03 May 2024 01:30 GMT"), at("2024-05-02", "20:30"));
+        // songkick
+        assert_eq!(
+            parse("Thursday 2 May 2024, 7:30 PM"),
+            at("2024-05-02", "19:30")
+        );
+        assert_eq!(parse("Thursday 02 May 2024"), at("2024-05-02", "00:00"));
+        // nashville.com
+        assert_eq!(parse("May 2 @ 7:00 pm"), at("2024-05-02", "19:00"));
+        assert_eq!(parse("May 11 @ 8:00 am"), at("2024-05-11", "08:00"));
+        // perto, after its separate time is combined
+        assert_eq!(parse("May 2 20:00"), at("2024-05-02", "20:00"));
+        assert_eq!(parse("January 1, 2023"), at("2023-01-01", "00:00"));
+        assert_eq!(parse("Fri, May 3rd 8pm"), at("2024-05-03", "20:00"));
+    }
+
+    #[test]
+    fn test_parse_datetime_informal() {
+        // A Thursday
+        let today = day("2024-05-02");
+        let cases = [
+            ("Doors 7:00 PM", at("2024-05-02", "19:00")),
+            ("Doors: 7PM", at("2024-05-02", "19:00")),
+            ("Doors open 6:30pm", at("2024-05-02", "18:30")),
+            ("Show 8 p.m.", at("2024-05-02", "20:00")),
+            ("Tonight, 9pm", at("2024-05-02", "21:00")),
+            ("Tonight at 9", at("2024-05-02", "21:00")),
+            ("TONIGHT @ 10:30 PM!", at("2024-05-02", "22:30")),
+            ("Tonight", at("2024-05-02", "00:00")),
+            ("Today 12:00", at("2024-05-02", "12:00")),
+            ("Tomorrow at 8", at("2024-05-03", "20:00")),
+            ("Tomorrow night, 11:15pm", at("2024-05-03", "23:15")),
+            ("tmrw 21:00", at("2024-05-03", "21:00")),
+            ("Sat 8pm", at("2024-05-04", "20:00")),
+            ("Saturday", at("2024-05-04", "00:00")),
+            ("Sun. 7:30 pm", at("2024-05-05", "19:30")),
+            ("Thurs 9pm", at("2024-05-02", "21:00")),
+            ("This Wednesday at 7", at("2024-05-08", "19:00")),
+            ("Mon | Doors 18:00", at("2024-05-06", "18:00")),
+            ("May 3 · 19:30", at("2024-05-03", "19:30")),
+            ("3 May – 8pm", at("2024-05-03", "20:00")),
+            ("Dec 31 · 9pm", at("2024-12-31", "21:00")),
+            ("Doors 7pm / Show 8pm", at("2024-05-02", "19:00")),
+            ("Doors", None),
+            ("Most Thursdays", None),
+            ("Tonight at 25", None),
+            ("Sat 13pm", None),
+            ("Number 9", None),
+        ];
+        for (text, expected) in cases {
+            assert_eq!(
+                parse_datetime(text, today, Zone::default()),
+                expected,
+                "{}",
+                text
+            );
+        }
+    }
+
+    #[test]
+    fn test_parse_datetime_traced_prefers_strict_formats() {
+        let today = day("2024-05-02");
+        let pass =
+            |text: &str| parse_datetime_traced(text, today, Zone::default()).map(|(_, pass)| pass);
+
+        assert_eq!(
+            pass("2024-05-02T19:00:00-05:00"),
+            Some(DateParse::Timestamp)
+        );
+        assert_eq!(
+            pass("Thu, 02 May 2024 19:00:00 GMT"),
+            Some(DateParse::Timestamp)
+        );
+        assert_eq!(pass("May 2 @ 7:00 pm"), Some(DateParse::Strict));
+        assert_eq!(pass("Fri, May 3rd 8pm"), Some(DateParse::Strict));
+        assert_eq!(pass("Doors 7PM"), Some(DateParse::Informal));
+        assert_eq!(pass("Every weekend"), None);
+        assert_eq!(DateParse::Informal.to_string(), "informal");
+    }
+
+    #[test]
+    fn test_parse_datetime_infers_next_year_and_rejects_garbage() {
+        let today = NaiveDate::from_ymd_opt(2024, 12, 20).unwrap();
+
+        assert_eq!(
+            parse_datetime("Jan 4 @ 9:00 pm", today, Zone::default()),
+            at("2025-01-04", "21:00")
+        );
+        assert_eq!(
+            parse_datetime("Dec 19", today, Zone::default()),
+            at("2024-12-19", "00:00")
+        );
+        assert_eq!(
+            parse_datetime("Every weekend", today, Zone::default()),
+            None
+        );
+        assert_eq!(parse_datetime("", today, Zone::default()), None);
+    }
+
+    #[test]
+    fn test_process_data_parses_typed_dates() {
+        let events = vec![
+            Event {
+                name: "A".to_string(),
+                start_date: "2024-05-02T19:00:00-05:00".to_string(),
+                end_date: Some("2024-05-02T22:00:00-05:00".to_string()),
+                ..Default::default()
+            },
+            Event {
+                name: "B".to_string(),
+                start_date: "Sometime soon".to_string(),
+                ..Default::default()
+            },
+            Event {
+                name: "C".to_string(),
+                ..Default::default()
+            },
+        ];
+
+        let processed = process_data(events, Zone::default()).0;
+
+        assert_eq!(
+            (processed[0].start, processed[0].end),
+            (at("2024-05-02", "19:00"), at("2024-05-02", "22:00"))
+        );
+        assert_eq!(processed[1].start_date, "Sometime soon");
+        assert_eq!(processed[1].start, None);
+        assert_eq!(processed[2].start, None);
+    }
+
+    #[test]
+    fn test_fallback_date_format() {
+        assert_eq!(
+            format_day(
+                NaiveDate::from_ymd_opt(2024, 5, 2).unwrap(),
+                FALLBACK_DATE_FORMAT
+            ),
+            "May 2"
+        );
+        assert_eq!(
+            format_day(
+                NaiveDate::from_ymd_opt(2024, 5, 12).unwrap(),
+                FALLBACK_DATE_FORMAT
+            ),
+            "May 12"
+        );
+        assert_eq!(
+            process_data(
+                vec![Event {
+                    name: "A".to_string(),
+                    ..Default::default()
+                }],
+                Zone::default()
+            )
+            .0[0]
+                .start_date,
+            today_date(Zone::default())
+        );
+    }
+
+    #[test]
+    fn test_apply_date_format() {
+        assert!(is_valid_date_format("%a %b %-d, %-I:%M %p"));
+        assert!(!is_valid_date_format("%Q"));
+
+        let events = process_data(
+            vec![
+                Event {
+                    name: "A".to_string(),
+                    start_date: "2024-05-02T19:00:00-05:00".to_string(),
+                    end_date: Some("2024-05-02T22:30".to_string()),
+                    ..Default::default()
+                },
+                Event {
+                    name: "B".to_string(),
+                    start_date: "Most Thursdays".to_string(),
+                    ..Default::default()
+                },
+                Event {
+                    name: "C".to_string(),
+                    ..Default::default()
+                },
+            ],
+            Zone::default(),
+        )
+        .0;
+
+        let formatted = apply_date_format(events, "%a %b %-d, %-I:%M %p", Zone::default());
+
+        assert_eq!(formatted[0].start_date, "Thu May 2, 7:00 PM");
+        assert_eq!(
+            formatted[0].end_date.as_deref(),
+            Some("Thu May 2, 10:30 PM")
+        );
+        assert_eq!(formatted[1].start_date, "Most Thursdays");
+        assert_eq!(
+            formatted[2].start_date,
+            format_day(Zone::default().today(), "%a %b %-d, %-I:%M %p")
+        );
+    }
+
+    #[test]
+    fn test_render_date_presets() {
+        let format = |name: &str| resolve_date_format(name).unwrap();
+        let cases = [
+            (
+                "iso",
+                "2024-05-03T00:00:00",
+                "2024-05-03T12:00:00",
+                "2024-05-03T19:30:00",
+            ),
+            (
+                "us",
+                "Fri May 3, 12:00 AM",
+                "Fri May 3, 12:00 PM",
+                "Fri May 3, 7:30 PM",
+            ),
+            (
+                "eu",
+                "Fri 3 May, 00:00",
+                "Fri 3 May, 12:00",
+                "Fri 3 May, 19:30",
+            ),
+            ("compact", "05-03 00:00", "05-03 12:00", "05-03 19:30"),
+        ];
+        for (name, midnight, noon, evening) in cases {
+            assert_eq!(
+                render_date(at("2024-05-03", "00:00"), "raw", &format(name)),
+                midnight,
+                "{}",
+                name
+            );
+            assert_eq!(
+                render_date(at("2024-05-03", "12:00"), "raw", &format(name)),
+                noon,
+                "{}",
+                name
+            );
+            assert_eq!(
+                render_date(at("2024-05-03", "19:30"), "raw", &format(name)),
+                evening,
+                "{}",
+                name
+            );
+            assert_eq!(
+                render_date(None, "Most Thursdays", &format(name)),
+                "Most Thursdays",
+                "{}",
+                name
+            );
+        }
+        assert_eq!(format("US"), format("us"));
+        assert_eq!(format("%H:%M"), "%H:%M");
+        assert!(resolve_date_format("%Q").is_err());
+    }
+
+    fn sortable(name: &str, start: Option<&str>, location: Option<&str>) -> ProcessedEvent {
+        ProcessedEvent {
+            name: name.to_string(),
+            start: start.map(|s| NaiveDateTime::parse_from_str(s, "%Y-%m-%d %H:%M").unwrap()),
+            location: location.map(str::to_string),
+            ..Default::default()
+        }
+    }
+
+    fn names(events: &[ProcessedEvent]) -> Vec<&str> {
+        events.iter().map(|e| e.name.as_str()).collect()
+    }
+
+    #[test]
+    fn test_sort_events_by_start() {
+        let mut events = vec![
+            sortable("Late", Some("2024-05-02 21:00"), None),
+            sortable("Undated", None, None),
+            sortable("Early", Some("2024-05-02 18:00"), None),
+            sortable("Tomorrow", Some("2024-05-03 12:00"), None),
+        ];
+
+        sort_events(&mut events, SortKey::Start, Order::Ascending);
+        assert_eq!(names(&events), vec!["Early", "Late", "Tomorrow", "Undated"]);
+
+        sort_events(&mut events, SortKey::Start, Order::Descending);
+        assert_eq!(names(&events), vec!["Tomorrow", "Late", "Early", "Undated"]);
+    }
+
+    #[test]
+    fn test_ord_matches_sort_events_by_start() {
+        let events = vec![
+            sortable("Late", Some("2024-05-02 21:00"), None),
+            sortable("zydeco", None, None),
+            sortable("Brunch", Some("2024-05-02 18:00"), None),
+            sortable("Undated", None, None),
+            sortable("art walk", Some("2024-05-02 18:00"), None),
+            sortable("Tomorrow", Some("2024-05-03 12:00"), None),
+        ];
+        let mut sorted = events.clone();
+        sorted.sort();
+        let mut explicit = events;
+        sort_events(&mut explicit, SortKey::Start, Order::Ascending);
+
+        assert_eq!(sorted, explicit);
+        assert_eq!(
+            names(&sorted),
+            vec!["art walk", "Brunch", "Late", "Tomorrow", "Undated", "zydeco"]
+        );
+    }
+
+    #[test]
+    fn test_sort_events_by_name_and_location() {
+        let mut events = vec![
+            sortable("bluegrass jam", None, Some("Station Inn")),
+            sortable("Art Crawl", None, None),
+            sortable("Comedy", None, Some("basement")),
+        ];
+
+        sort_events(&mut events, SortKey::Name, Order::Ascending);
+        assert_eq!(names(&events), vec!["Art Crawl", "bluegrass jam", "Comedy"]);
+        sort_events(&mut events, SortKey::Name, Order::Descending);
+        assert_eq!(names(&events), vec!["Comedy", "bluegrass jam", "Art Crawl"]);
+
+        sort_events(&mut events, SortKey::Location, Order::Ascending);
+        assert_eq!(names(&events), vec!["Comedy", "bluegrass jam", "Art Crawl"]);
+        sort_events(&mut events, SortKey::Location, Order::Descending);
+        assert_eq!(names(&events), vec!["bluegrass jam", "Comedy", "Art Crawl"]);
+
+        assert_eq!("Venue".parse(), Ok(SortKey::Location));
+        assert!("price".parse::<SortKey>().is_err());
+    }
+
+    fn day(text: &str) -> NaiveDate {
+        NaiveDate::parse_from_str(text, "%Y-%m-%d").unwrap()
+    }
+
+    #[test]
+    fn test_filter_by_range_boundaries() {
+        let event = |name: &str, start: &str, end: Option<&str>| ProcessedEvent {
+            name: name.to_string(),
+            start: start
+                .split_once(' ')
+                .and_then(|(date, time)| at(date, time)),
+            end: end
+                .and_then(|end| end.split_once(' '))
+                .and_then(|(date, time)| at(date, time)),
+            ..Default::default()
+        };
+        let events = vec![
+            event("Midnight start", "2024-05-03 00:00", None),
+            event("Late the night before", "2024-05-02 23:59", None),
+            event("Midnight after", "2024-05-05 00:00", None),
+            event("Festival", "2024-05-01 12:00", Some("2024-05-03 01:00")),
+            event("Last night", "2024-05-04 23:00", Some("2024-05-05 02:00")),
+            event("Undated", "", None),
+        ];
+
+        let kept = filter_by_range(events.clone(), day("2024-05-03"), day("2024-05-04"), true);
+        assert_eq!(
+            names(&kept),
+            vec!["Midnight start", "Festival", "Last night", "Undated"]
+        );
+
+        let strict = filter_by_range(events, day("2024-05-03"), day("2024-05-04"), false);
+        assert_eq!(
+            names(&strict),
+            vec!["Midnight start", "Festival", "Last night"]
+        );
+    }
+
+    #[test]
+    fn test_date_preset_ranges() {
+        // 2024-05-01 is a Wednesday
+        let wednesday = day("2024-05-01");
+        assert_eq!(DatePreset::Today.range(wednesday), (wednesday, wednesday));
+        assert_eq!(
+            DatePreset::Tomorrow.range(wednesday),
+            (day("2024-05-02"), day("2024-05-02"))
+        );
+        assert_eq!(
+            DatePreset::Weekend.range(wednesday),
+            (day("2024-05-03"), day("2024-05-05"))
+        );
+        assert_eq!(
+            DatePreset::Weekend.range(day("2024-05-04")),
+            (day("2024-05-04"), day("2024-05-05"))
+        );
+        assert_eq!(
+            DatePreset::Weekend.range(day("2024-05-05")),
+            (day("2024-05-05"), day("2024-05-05"))
+        );
+        assert_eq!(
+            DatePreset::Week.range(wednesday),
+            (wednesday, day("2024-05-07"))
+        );
+        assert_eq!("Tonight".parse(), Ok(DatePreset::Today));
+        assert!("fortnight".parse::<DatePreset>().is_err());
+    }
+
+    #[test]
+    fn test_search_events() {
+        let event = |name: &str, location: Option<&str>, tags: &[&str]| ProcessedEvent {
+            name: name.to_string(),
+            location: location.map(str::to_string),
+            tags: tags.iter().map(|t| t.to_string()).collect(),
+            ..Default::default()
+        };
+        let events = vec![
+            event("Jazz Brunch", Some("Café Roma"), &[]),
+            event("Open Mic", Some("The Basement"), &["jazz", "comedy"]),
+            event("Blues Night", None, &[]),
+        ];
+        let terms = |terms: &[&str]| terms.iter().map(|t| t.to_string()).collect::<Vec<_>>();
+
+        assert_eq!(
+            names(&search_events(
+                events.clone(),
+                &terms(&["JAZZ"]),
+                Match::All
+            )),
+            vec!["Jazz Brunch", "Open Mic"]
+        );
+        assert_eq!(
+            names(&search_events(
+                events.clone(),
+                &terms(&["cafe"]),
+                Match::All
+            )),
+            vec!["Jazz Brunch"]
+        );
+        assert_eq!(
+            names(&search_events(
+                events.clone(),
+                &terms(&["jazz", "comedy"]),
+                Match::All
+            )),
+            vec!["Open Mic"]
+        );
+        assert_eq!(
+            names(&search_events(
+                events.clone(),
+                &terms(&["roma", "blues"]),
+                Match::Any
+            )),
+            vec!["Jazz Brunch", "Blues Night"]
+        );
+        assert_eq!(search_events(events.clone(), &[], Match::All), events);
+    }
+
+    #[test]
+    fn test_apply_exclusions() {
+        let event =
+            |name: &str, description: Option<&str>, location: Option<&str>| ProcessedEvent {
+                name: name.to_string(),
+                description: description.map(str::to_string),
+                location: location.map(str::to_string),
+                ..Default::default()
+            };
+        let events = vec![
+            event("Trivia Night", None, Some("Tootsie's")),
+            event(
+                "Jazz Brunch",
+                Some("Followed by TRIVIA"),
+                Some("Barn Theater"),
+            ),
+            event("Bluegrass Jam", None, Some("The Bar")),
+            event("Jazz at the Bar", None, Some("Station Inn")),
+        ];
+        let rule = |pattern: &str, field: ExcludeField, whole_word: bool| ExcludeRule {
+            pattern: pattern.to_string(),
+            field,
+            whole_word,
+        };
+        let rules = vec![
+            rule("trivia", ExcludeField::Text, false),
+            rule("tootsie's", ExcludeField::Venue, false),
+            rule("bar", ExcludeField::Venue, true),
+            rule("Exit/In", ExcludeField::Venue, false),
+        ];
+
+        let (kept, removed) = apply_exclusions(events.clone(), &rules);
+        assert_eq!(names(&kept), vec!["Jazz at the Bar"]);
+        assert_eq!(removed, vec![2, 0, 1, 0]);
+
+        let (kept, _) = apply_exclusions(events, &[rule("bar", ExcludeField::Venue, false)]);
+        assert_eq!(names(&kept), vec!["Trivia Night", "Jazz at the Bar"]);
+    }
+
+    #[test]
+    fn test_exclusions_win_over_search() {
+        let events = vec![
+            ProcessedEvent {
+                name: "Jazz Trivia".to_string(),
+                ..Default::default()
+            },
+            ProcessedEvent {
+                name: "Jazz Brunch".to_string(),
+                ..Default::default()
+            },
+        ];
+        let found = search_events(events, &["jazz".to_string()], Match::All);
+        let rules = [ExcludeRule {
+            pattern: "trivia".to_string(),
+            field: ExcludeField::Text,
+            whole_word: true,
+        }];
+
+        let (kept, removed) = apply_exclusions(found, &rules);
+
+        assert_eq!(names(&kept), vec!["Jazz Brunch"]);
+        assert_eq!(removed, vec![1]);
+    }
+
+    fn listing(name: &str, start: &str, location: &str, url: &str) -> ProcessedEvent {
+        let (date, time) = start.split_once(' ').unwrap();
+        ProcessedEvent {
+            name: name.to_string(),
+            start: at(date, time),
+            location: Some(location.to_string()),
+            url: url.to_string(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_merge_cross_source_true_duplicates() {
+        let mut songkick = listing(
+            "The National",
+            "2024-05-02 19:00",
+            "Ryman Auditorium",
+            "https://songkick.com/1",
+        );
+        songkick.categories = Categories::from([Category::Music]);
+        songkick.tags = vec!["indie".to_string()];
+        let mut nashville = listing(
+            "The National w/ Special Guests",
+            "2024-05-02 19:30",
+            "The Ryman",
+            "https://nashville.com/2",
+        );
+        nashville.price = Some("$45.00".to_string());
+        nashville.categories = Categories::from([Category::General]);
+        nashville.tags = vec!["indie".to_string(), "rock".to_string()];
+
+        let (merged, count) = merge_cross_source(
+            vec![songkick, nashville],
+            DEFAULT_MERGE_THRESHOLD,
+            &MergePreferences::default(),
+        );
+
+        assert_eq!(count, 1);
+        assert_eq!(merged.len(), 1);
+        assert_eq!(merged[0].name, "The National");
+        assert_eq!(merged[0].price.as_deref(), Some("$45.00"));
+        assert_eq!(
+            merged[0].categories,
+            Categories::from([Category::Music, Category::General])
+        );
+        assert_eq!(merged[0].tags, vec!["indie", "rock"]);
+        assert_eq!(merged[0].alt_urls, vec!["https://nashville.com/2"]);
+    }
+
+    #[test]
+    fn test_merge_cross_source_keeps_near_misses_apart() {
+        let events = vec![
+            listing("The National", "2024-05-02 19:00", "Ryman", "https://a/1"),
+            // Too far apart in time
+            listing("The National", "2024-05-02 19:31", "Ryman", "https://b/1"),
+            // Different act, same slot
+            listing("The Wombats", "2024-05-02 19:00", "Ryman", "https://b/2"),
+            // Same act, different venue
+            listing(
+                "The National",
+                "2024-05-02 19:00",
+                "Station Inn",
+                "https://b/3",
+            ),
+        ];
+
+        let (merged, count) = merge_cross_source(
+            events.clone(),
+            DEFAULT_MERGE_THRESHOLD,
+            &MergePreferences::default(),
+        );
+
+        assert_eq!(count, 0);
+        assert_eq!(merged, events);
+    }
+
+    #[test]
+    fn test_name_similarity() {
+        assert_eq!(name_similarity("The National", "the national!"), 1.0);
+        assert_eq!(
+            name_similarity("The National", "The National w/ Special Guests"),
+            1.0
+        );
+        assert!(name_similarity("Beyoncé Tribute", "Beyonce Tribte") > DEFAULT_MERGE_THRESHOLD);
+        assert!(name_similarity("The National", "The Wombats") < DEFAULT_MERGE_THRESHOLD);
+        assert_eq!(name_similarity("", "Jazz"), 0.0);
+
+        // A looser threshold merges names that only share some words
+        let events = vec![
+            listing(
+                "Jazz Brunch",
+                "2024-05-02 11:00",
+                "Café Roma",
+                "https://a/1",
+            ),
+            listing(
+                "Sunday Jazz Buffet",
+                "2024-05-02 11:00",
+                "Cafe Roma",
+                "https://b/1",
+            ),
+        ];
+        assert_eq!(
+            merge_cross_source(
+                events.clone(),
+                DEFAULT_MERGE_THRESHOLD,
+                &MergePreferences::default()
+            )
+            .1,
+            0
+        );
+        assert_eq!(
+            merge_cross_source(events, 0.5, &MergePreferences::default()).1,
+            1
+        );
+    }
+
+    #[test]
+    fn test_merge_start_precedence() {
+        let start = |start_date: &str, parsed: Option<&str>| ProcessedEvent {
+            start_date: start_date.to_string(),
+            start: parsed.map(|p| NaiveDateTime::parse_from_str(p, "%Y-%m-%d %H:%M").unwrap()),
+            ..Default::default()
+        };
+        let structured = || start("2024-05-02T19:30:00-05:00", Some("2024-05-02 19:30"));
+        let timed = || start("May 2 @ 7:00 pm", Some("2024-05-02 19:00"));
+        let dated = || start("May 2", Some("2024-05-02 00:00"));
+        let unreadable = || start("Most Thursdays", None);
+        let missing = || start("", None);
+        let ranked: [&dyn Fn() -> ProcessedEvent; 5] =
+            [&structured, &timed, &dated, &unreadable, &missing];
+        let precise = MergePreferences::default();
+        let first = MergePreferences {
+            start: Prefer::First,
+            ..MergePreferences::default()
+        };
+
+        for (i, better) in ranked.iter().enumerate() {
+            for (j, other) in ranked.iter().enumerate() {
+                let merged = merge(better(), other(), &precise);
+                // The more precise start wins; on a tie the first listing's is kept
+                let expected = if j < i { other() } else { better() };
+                assert_eq!(
+                    (merged.start_date, merged.start),
+                    (expected.start_date, expected.start),
+                    "{} vs {}",
+                    i,
+                    j
+                );
+            }
+        }
+        assert_eq!(merge(dated(), structured(), &first).start_date, "May 2");
+        assert_eq!(
+            merge(missing(), timed(), &first).start_date,
+            "May 2 @ 7:00 pm"
+        );
+        assert_eq!(
+            merge(unreadable(), timed(), &first).start_date,
+            "Most Thursdays"
+        );
+    }
+
+    #[test]
+    fn test_merge_text_precedence() {
+        let event = |description: Option<&str>| ProcessedEvent {
+            description: description.map(str::to_string),
+            ..Default::default()
+        };
+        let cases = [
+            // (preference, first, second, kept)
+            (
+                Prefer::Longer,
+                Some("Short"),
+                Some("A longer description"),
+                Some("A longer description"),
+            ),
+            (
+                Prefer::Longer,
+                Some("A longer description"),
+                Some("Short"),
+                Some("A longer description"),
+            ),
+            (Prefer::Longer, Some("Same"), Some("Tied"), Some("Same")),
+            (Prefer::Longer, None, Some("Only"), Some("Only")),
+            (Prefer::Longer, Some("Only"), None, Some("Only")),
+            (Prefer::Longer, None, None, None),
+            (
+                Prefer::Shorter,
+                Some("A longer description"),
+                Some("Short"),
+                Some("Short"),
+            ),
+            (Prefer::Shorter, Some("Same"), Some("Tied"), Some("Same")),
+            (Prefer::Shorter, None, Some("Only"), Some("Only")),
+            (
+                Prefer::First,
+                Some("First"),
+                Some("A longer second"),
+                Some("First"),
+            ),
+            (Prefer::First, None, Some("Second"), Some("Second")),
+        ];
+        for (prefer, a, b, kept) in cases {
+            let preferences = MergePreferences {
+                description: prefer,
+                ..MergePreferences::default()
+            };
+            let merged = merge(event(a), event(b), &preferences);
+            assert_eq!(
+                merged.description.as_deref(),
+                kept,
+                "{:?}: {:?} vs {:?}",
+                prefer,
+                a,
+                b
+            );
+        }
+    }
+
+    #[test]
+    fn test_merge_fixed_rules() {
+        let mut a = listing(
+            "The National",
+            "2024-05-02 19:00",
+            "Ryman",
+            "https://a/1?utm=x",
+        );
+        a.tags = vec!["indie".to_string()];
+        a.alt_urls = vec!["https://c/1".to_string()];
+        a.price = None;
+        let mut b = listing(
+            "The National w/ Guests",
+            "2024-05-02 19:00",
+            "Ryman Auditorium",
+            "https://b/1",
+        );
+        b.tags = vec!["rock".to_string(), "indie".to_string()];
+        b.alt_urls = vec!["https://c/1".to_string(), "https://a/1?utm=x".to_string()];
+        b.price = Some("$45.00".to_string());
+        b.status = EventStatus::SoldOut;
+        b.source = Some("nashville.com".to_string());
+        b.score = 3;
+        b.matched_interests = vec!["indie".to_string()];
+
+        let merged = merge(a.clone(), b.clone(), &MergePreferences::default());
+
+        assert_eq!(
+            (merged.name.as_str(), merged.location.as_deref()),
+            ("The National", Some("Ryman"))
+        );
+        assert_eq!(merged.price.as_deref(), Some("$45.00"));
+        assert_eq!(merged.url, "https://a/1?utm=x");
+        assert_eq!(merged.alt_urls, vec!["https://c/1", "https://b/1"]);
+        assert_eq!(merged.tags, vec!["indie", "rock"]);
+        assert_eq!(
+            (merged.status, merged.source.as_deref()),
+            (EventStatus::SoldOut, Some("nashville.com"))
+        );
+        assert_eq!((merged.score, merged.matched_interests.len()), (3, 1));
+
+        a.status = EventStatus::Available;
+        let longer = MergePreferences {
+            name: Prefer::Longer,
+            location: Prefer::Longer,
+            ..MergePreferences::default()
+        };
+        let merged = merge(a, b, &longer);
+        assert_eq!(
+            (merged.name.as_str(), merged.location.as_deref()),
+            ("The National w/ Guests", Some("Ryman Auditorium"))
+        );
+        assert_eq!(merged.status, EventStatus::Available);
+    }
+
+    #[test]
+    fn test_merge_preferences_set() {
+        let mut preferences = MergePreferences::default();
+
+        assert_eq!(
+            preferences.set("description", "shorter".parse().unwrap()),
+            Ok(())
+        );
+        assert_eq!(preferences.description, Prefer::Shorter);
+        assert_eq!(
+            preferences.set("start", Prefer::Longer),
+            Err("start can prefer first or precise".to_string())
+        );
+        assert_eq!(
+            preferences.set("price", Prefer::Precise),
+            Err("price can prefer first, longer, or shorter".to_string())
+        );
+        assert!(preferences
+            .set("venue", Prefer::First)
+            .unwrap_err()
+            .starts_with("unknown field 'venue'"));
+        assert!("best".parse::<Prefer>().is_err());
+    }
+
+    #[test]
+    fn test_drop_ended() {
+        let now = at("2024-05-02", "22:00").unwrap();
+        let mut festival = listing("Festival", "2024-05-01 12:00", "Park", "");
+        festival.end = at("2024-05-03", "12:00");
+        let mut finished = listing("Matinee", "2024-05-02 14:00", "Ryman", "");
+        finished.end = at("2024-05-02", "16:00");
+        let events = vec![
+            listing("Early show", "2024-05-02 18:00", "Ryman", ""),
+            listing("Late show", "2024-05-02 19:30", "Ryman", ""),
+            listing("All day", "2024-05-02 00:00", "Park", ""),
+            festival,
+            finished,
+            ProcessedEvent {
+                name: "Undated".to_string(),
+                ..Default::default()
+            },
+        ];
+
+        let (kept, dropped) = drop_ended(
+            events,
+            now,
+            Duration::minutes(DEFAULT_ASSUMED_DURATION_MINUTES),
+        );
+
+        assert_eq!(
+            names(&kept),
+            vec!["Late show", "All day", "Festival", "Undated"]
+        );
+        assert_eq!(dropped, 2);
+    }
+
+    #[test]
+    fn test_parse_datetime_converts_offsets_to_home_zone() {
+        let today = NaiveDate::from_ymd_opt(2024, 5, 1).unwrap();
+        let utc: Zone = "UTC".parse().unwrap();
+        let new_york: Zone = "America/New_York".parse().unwrap();
+
+        assert_eq!(
+            parse_datetime("2024-05-02T19:00:00-05:00", today, Zone::default()),
+            at("2024-05-02", "19:00")
+        );
+        assert_eq!(
+            parse_datetime("2024-05-02T19:00:00-05:00", today, utc),
+            at("2024-05-03", "00:00")
+        );
+        assert_eq!(
+            parse_datetime("2024-05-02T19:00:00-05:00", today, new_york),
+            at("2024-05-02", "20:00")
+        );
+        // Across the November change Nashville is back on UTC-6
+        assert_eq!(
+            parse_datetime("2024-11-04T01:30:00Z", today, Zone::default()),
+            at("2024-11-03", "19:30")
+        );
+        // Naive dates are taken to be in the home zone already
+        assert_eq!(
+            parse_datetime("May 2 @ 7:00 pm", today, utc),
+            at("2024-05-02", "19:00")
+        );
+    }
+
+    #[test]
+    fn test_relative_time() {
+        let now = at("2024-05-02", "18:00").unwrap();
+        let relative = |date: &str, time: &str| relative_time(at(date, time).unwrap(), now);
+
+        assert_eq!(relative("2024-05-02", "18:00"), "starts now");
+        assert_eq!(relative("2024-05-02", "18:59"), "starts in 59m");
+        assert_eq!(relative("2024-05-02", "19:00"), "starts in 1h");
+        assert_eq!(relative("2024-05-02", "20:15"), "starts in 2h 15m");
+        assert_eq!(relative("2024-05-03", "17:59"), "starts in 23h 59m");
+        assert_eq!(relative("2024-05-03", "18:00"), "starts in 1 day");
+        assert_eq!(relative("2024-05-06", "09:00"), "starts in 3 days");
+        assert_eq!(relative("2024-05-02", "17:30"), "started 30m ago");
+        assert_eq!(relative("2024-05-02", "15:45"), "started 2h 15m ago");
+        assert_eq!(relative("2024-04-30", "12:00"), "started 2 days ago");
+    }
+
+    #[test]
+    fn test_group_events_by_date() {
+        let events = vec![
+            listing("Early", "2024-05-03 18:00", "Ryman", ""),
+            ProcessedEvent {
+                name: "Undated".to_string(),
+                ..Default::default()
+            },
+            listing("Late", "2024-05-03 22:00", "Station Inn", ""),
+            listing("Saturday", "2024-05-04 20:00", "Ryman", ""),
+        ];
+
+        let groups = group_events(events, GroupKey::Date);
+
+        let titles: Vec<(&str, Vec<&str>)> = groups
+            .iter()
+            .map(|(title, events)| (title.as_str(), names(events)))
+            .collect();
+        assert_eq!(
+            titles,
+            vec![
+                ("Friday, May 3", vec!["Early", "Late"]),
+                ("Saturday, May 4", vec!["Saturday"]),
+                ("Unknown", vec!["Undated"]),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_group_events_by_venue_and_source() {
+        let events = vec![
+            listing(
+                "A",
+                "2024-05-03 18:00",
+                "The Ryman",
+                "https://www.songkick.com/1",
+            ),
+            listing(
+                "B",
+                "2024-05-03 19:00",
+                "Station Inn",
+                "https://nashville.com/2",
+            ),
+            listing(
+                "C",
+                "2024-05-03 20:00",
+                "the ryman",
+                "https://songkick.com/3",
+            ),
+            ProcessedEvent {
+                name: "D".to_string(),
+                location: Some(" ".to_string()),
+                ..Default::default()
+            },
+        ];
+
+        let by_venue = group_events(events.clone(), GroupKey::Venue);
+        let titles: Vec<(&str, Vec<&str>)> = by_venue
+            .iter()
+            .map(|(title, events)| (title.as_str(), names(events)))
+            .collect();
+        assert_eq!(
+            titles,
+            vec![
+                ("The Ryman", vec!["A", "C"]),
+                ("Station Inn", vec!["B"]),
+                ("Unknown", vec!["D"])
+            ]
+        );
+
+        let by_source = group_events(events, GroupKey::Source);
+        let titles: Vec<(&str, Vec<&str>)> = by_source
+            .iter()
+            .map(|(title, events)| (title.as_str(), names(events)))
+            .collect();
+        assert_eq!(
+            titles,
+            vec![
+                ("songkick.com", vec!["A", "C"]),
+                ("nashville.com", vec!["B"]),
+                ("Unknown", vec!["D"])
+            ]
+        );
+        assert!(group_events(Vec::new(), GroupKey::Date).is_empty());
+
+        let fetched = ProcessedEvent {
+            name: "E".to_string(),
+            source: Some("perto".to_string()),
+            url: "https://en.perto.com/5".to_string(),
+            ..Default::default()
+        };
+        assert_eq!(group_events(vec![fetched], GroupKey::Source)[0].0, "perto");
+    }
+
+    #[test]
+    fn test_processed_event_serde_round_trip() {
+        let event = ProcessedEvent {
+            name: "Jazz Night".to_string(),
+            start_date: "May 2 @ 7:00 pm".to_string(),
+            end_date: Some("May 2 @ 10:00 pm".to_string()),
+            start: at("2024-05-02", "19:00"),
+            end: at("2024-05-02", "22:00"),
+            location: Some("Café Lex".to_string()),
+            url: "https://example.com/jazz".to_string(),
+            price: Some("Free".to_string()),
+            categories: Categories::from([Category::Music]),
+            source: Some("nashville.com".to_string()),
+            status: EventStatus::SoldOut,
+            tags: vec!["jazz".to_string()],
+            alt_urls: vec!["https://example.org/jazz".to_string()],
+            ..Default::default()
+        };
+
+        let text = serde_json::to_string(&event).unwrap();
+        assert_eq!(
+            serde_json::from_str::<ProcessedEvent>(&text).unwrap(),
+            event
+        );
+
+        let json: Value = serde_json::from_str(&text).unwrap();
+        let mut fields: Vec<&str> = json
+            .as_object()
+            .unwrap()
+            .keys()
+            .map(String::as_str)
+            .collect();
+        fields.sort_unstable();
+        assert_eq!(
+            fields,
+            vec![
+                "alt_urls",
+                "categories",
+                "description",
+                "end",
+                "end_date",
+                "id",
+                "image_url",
+                "location",
+                "name",
+                "price",
+                "source",
+                "start",
+                "start_date",
+                "status",
+                "tags",
+                "url"
+            ]
+        );
+        assert_eq!(
+            (json["start"].as_str(), json["status"].as_str()),
+            (Some("2024-05-02T19:00:00"), Some("sold out"))
+        );
+        assert_eq!(json["categories"], json!(["music"]));
+        // Only the name is required
+        assert_eq!(
+            serde_json::from_str::<ProcessedEvent>(r#"{"name": "Bare"}"#)
+                .unwrap()
+                .name,
+            "Bare"
+        );
+        assert!(serde_json::from_str::<ProcessedEvent>(r#"{"url": "nameless"}"#).is_err());
+    }
+
+    #[test]
+    fn test_from_raw_matches_process_data() {
+        let raw = Event {
+            name: " Jazz  Night ".to_string(),
+            start_date: "2024-05-02".to_string(),
+            start_time: Some("7:30 PM".to_string()),
+            price: Some("$10".to_string()),
+            ..Default::default()
+        };
+        let options = ProcessingOptions {
+            zone: Zone::default(),
+            today: day("2024-04-20"),
+        };
+
+        let processed = ProcessedEvent::from_raw(raw.clone(), &options);
+
+        assert_eq!(
+            (processed.name.as_str(), processed.start),
+            ("Jazz Night", at("2024-05-02", "19:30"))
+        );
+        assert_eq!(processed.price.as_deref(), Some("$10.00"));
+        assert_eq!(process_data(vec![raw], Zone::default()).0, vec![processed]);
+    }
+
+    #[test]
+    fn test_event_id() {
+        let show = listing(
+            "The National",
+            "2024-05-02 19:00",
+            "Ryman",
+            "https://www.songkick.com/concerts/1",
+        );
+        let id = show.id();
+        assert_eq!(id.len(), 16);
+        assert!(id.chars().all(|c| c.is_ascii_hexdigit()));
+        // Fixed across runs and Rust versions
+        assert_eq!(fnv1a(b"a"), 0xaf63_dc4c_8601_ec8c);
+
+        let tracked = ProcessedEvent {
+            url: "https://www.songkick.com/concerts/1/?utm_source=x#tickets".to_string(),
+            ..show.clone()
+        };
+        let reworded = ProcessedEvent {
+            description: Some("Now with openers".to_string()),
+            price: Some("$40.00".to_string()),
+            ..show.clone()
+        };
+        assert_eq!(tracked.id(), id);
+        assert_eq!(reworded.id(), id);
+        assert_ne!(
+            ProcessedEvent {
+                url: "https://www.songkick.com/concerts/2".to_string(),
+                ..show.clone()
+            }
+            .id(),
+            id
+        );
+
+        let no_url = listing("The National", "2024-05-02 19:00", "Ryman", "");
+        let relative_url = listing("the national!", "2024-05-02 19:00", "RYMAN", "/concerts/1");
+        let next_night = listing("The National", "2024-05-03 19:00", "Ryman", "");
+        assert_ne!(no_url.id(), id);
+        assert_eq!(relative_url.id(), no_url.id());
+        assert_ne!(next_night.id(), no_url.id());
+    }
+}
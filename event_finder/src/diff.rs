@@ -0,0 +1,299 @@
+//! # Diff Module
+//!
+//! This module compares two sets of processed events — typically the last saved snapshot and
+//! the current fetch — and reports which events are new, which disappeared, and which changed.
+//! Events are matched by a stable key, and changed events carry a field-level breakdown.
+//! `new_since` tells `--watch` which events appeared since its previous refresh.
+
+use std::collections::HashSet;
+
+use chrono::{DateTime, FixedOffset};
+
+use crate::data_processing::{normalize_key, ProcessedEvent};
+
+/// A single field that differs between two versions of the same event.
+#[derive(Debug, Clone, PartialEq)]
+pub struct FieldChange {
+    pub field: &'static str,
+    pub old: String,
+    pub new: String,
+}
+
+/// An event present in both sets whose details changed.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ChangedEvent {
+    pub old: ProcessedEvent,
+    pub new: ProcessedEvent,
+    pub fields: Vec<FieldChange>,
+}
+
+/// The result of comparing a previous set of events with a current one.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct EventDiff {
+    pub added: Vec<ProcessedEvent>,
+    pub removed: Vec<ProcessedEvent>,
+    pub changed: Vec<ChangedEvent>,
+}
+
+impl EventDiff {
+    /// Returns `true` if nothing was added, removed, or changed.
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.removed.is_empty() && self.changed.is_empty()
+    }
+}
+
+/// Compares the previous and current events.
+///
+/// Events are matched by `event_key`. Dates are compared by the instant they describe when both
+/// sides parse, so a source switching from `-05:00` to UTC notation is not reported as a change.
+///
+/// # Arguments
+///
+/// * `previous` - The events from the last snapshot.
+/// * `current` - The freshly fetched events.
+///
+/// # Returns
+///
+/// An `EventDiff` with added and changed events in `current` order and removed events in
+/// `previous` order.
+pub fn diff_events(previous: &[ProcessedEvent], current: &[ProcessedEvent]) -> EventDiff {
+    let mut diff = EventDiff::default();
+
+    for new in current {
+        let key = event_key(new);
+        match previous.iter().find(|old| event_key(old) == key) {
+            Some(old) => {
+                let fields = field_changes(old, new);
+                if !fields.is_empty() {
+                    diff.changed.push(ChangedEvent {
+                        old: old.clone(),
+                        new: new.clone(),
+                        fields,
+                    });
+                }
+            }
+            None => diff.added.push(new.clone()),
+        }
+    }
+
+    for old in previous {
+        let key = event_key(old);
+        if !current.iter().any(|new| event_key(new) == key) {
+            diff.removed.push(old.clone());
+        }
+    }
+
+    diff
+}
+
+/// Picks out the events that weren't there last time, by their stable `ProcessedEvent::id`.
+///
+/// # Arguments
+///
+/// * `previous` - The ids of the events the previous refresh listed.
+/// * `current` - The events listed now.
+///
+/// # Returns
+///
+/// The ids of the events in `current` whose id isn't in `previous`.
+pub fn new_since(previous: &HashSet<String>, current: &[ProcessedEvent]) -> HashSet<String> {
+    current
+        .iter()
+        .map(ProcessedEvent::id)
+        .filter(|id| !previous.contains(id))
+        .collect()
+}
+
+/// Returns the key used to match the same event across fetches.
+///
+/// The URL identifies an event when present; otherwise the name is used, by `normalize_key`.
+fn event_key(event: &ProcessedEvent) -> String {
+    if event.url.is_empty() {
+        normalize_key(&event.name)
+    } else {
+        event.url.clone()
+    }
+}
+
+/// Lists the fields that differ between two versions of an event.
+fn field_changes(old: &ProcessedEvent, new: &ProcessedEvent) -> Vec<FieldChange> {
+    let mut fields = Vec::new();
+    let mut compare = |field: &'static str, a: &str, b: &str, same: fn(&str, &str) -> bool| {
+        if !same(a, b) {
+            fields.push(FieldChange {
+                field,
+                old: a.to_string(),
+                new: b.to_string(),
+            });
+        }
+    };
+
+    compare("name", &old.name, &new.name, |a, b| a == b);
+    compare("start_date", &old.start_date, &new.start_date, same_date);
+    compare(
+        "end_date",
+        old.end_date.as_deref().unwrap_or(""),
+        new.end_date.as_deref().unwrap_or(""),
+        same_date,
+    );
+    compare(
+        "location",
+        old.location.as_deref().unwrap_or(""),
+        new.location.as_deref().unwrap_or(""),
+        |a, b| a == b,
+    );
+    compare(
+        "status",
+        &old.status.to_string(),
+        &new.status.to_string(),
+        |a, b| a == b,
+    );
+    fields
+}
+
+/// Returns `true` if two date strings describe the same moment.
+///
+/// Strings that do not both parse as RFC 3339 timestamps are compared textually.
+fn same_date(a: &str, b: &str) -> bool {
+    match (parse_instant(a), parse_instant(b)) {
+        (Some(a), Some(b)) => a == b,
+        _ => a == b,
+    }
+}
+
+/// Parses an RFC 3339 timestamp such as `2024-05-02T19:00:00-05:00`.
+fn parse_instant(text: &str) -> Option<DateTime<FixedOffset>> {
+    DateTime::parse_from_rfc3339(text.trim()).ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn event(name: &str, url: &str, start: &str, location: &str) -> ProcessedEvent {
+        ProcessedEvent {
+            name: name.to_string(),
+            start_date: start.to_string(),
+            end_date: None,
+            location: Some(location.to_string()),
+            url: url.to_string(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_diff_events_added_removed_and_changed() {
+        let previous = vec![
+            event("Stays", "http://x/1", "May 2", "Ryman"),
+            event("Goes away", "http://x/2", "May 2", "Basement"),
+            event("Moves", "http://x/3", "May 2", "Exit/In"),
+        ];
+        let current = vec![
+            event("Stays", "http://x/1", "May 2", "Ryman"),
+            event("Moves", "http://x/3", "May 3", "Basement East"),
+            event("Brand new", "http://x/4", "May 4", "Station Inn"),
+        ];
+
+        let diff = diff_events(&previous, &current);
+
+        assert_eq!(diff.added, vec![current[2].clone()]);
+        assert_eq!(diff.removed, vec![previous[1].clone()]);
+        assert_eq!(diff.changed.len(), 1);
+        assert_eq!(diff.changed[0].new.name, "Moves");
+        assert_eq!(
+            diff.changed[0].fields,
+            vec![
+                FieldChange {
+                    field: "start_date",
+                    old: "May 2".to_string(),
+                    new: "May 3".to_string()
+                },
+                FieldChange {
+                    field: "location",
+                    old: "Exit/In".to_string(),
+                    new: "Basement East".to_string()
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_diff_events_ignores_reformatted_but_equal_instant() {
+        let previous = vec![event(
+            "Show",
+            "http://x/1",
+            "2024-05-02T19:00:00-05:00",
+            "Ryman",
+        )];
+        let current = vec![event(
+            "Show",
+            "http://x/1",
+            "2024-05-03T00:00:00+00:00",
+            "Ryman",
+        )];
+
+        let diff = diff_events(&previous, &current);
+
+        assert!(diff.is_empty());
+    }
+
+    #[test]
+    fn test_diff_events_reports_real_instant_change() {
+        let previous = vec![event(
+            "Show",
+            "http://x/1",
+            "2024-05-02T19:00:00-05:00",
+            "Ryman",
+        )];
+        let current = vec![event(
+            "Show",
+            "http://x/1",
+            "2024-05-02T20:00:00-05:00",
+            "Ryman",
+        )];
+
+        let diff = diff_events(&previous, &current);
+
+        assert_eq!(diff.changed.len(), 1);
+        assert_eq!(diff.changed[0].fields[0].field, "start_date");
+    }
+
+    #[test]
+    fn test_diff_events_matches_by_name_without_url() {
+        let previous = vec![event("Open Mic", "", "May 2", "Bar")];
+        let current = vec![event("OPEN MIC", "", "May 2", "Bar")];
+
+        let diff = diff_events(&previous, &current);
+
+        assert!(diff.added.is_empty());
+        assert!(diff.removed.is_empty());
+        assert_eq!(diff.changed[0].fields[0].field, "name");
+    }
+
+    #[test]
+    fn test_new_since_uses_stable_ids() {
+        let previous = vec![
+            event("Stays", "http://x/1", "May 2", "Ryman"),
+            event("Goes away", "http://x/2", "May 2", "Basement"),
+        ];
+        let ids: HashSet<String> = previous.iter().map(ProcessedEvent::id).collect();
+        let current = vec![
+            // The same page, refetched with a reworded name and a tracking parameter
+            event("Stays!", "http://x/1?utm_source=feed", "May 2", "Ryman"),
+            event("Brand new", "http://x/4", "May 4", "Station Inn"),
+        ];
+
+        assert_eq!(new_since(&ids, &current), HashSet::from([current[1].id()]));
+        assert_eq!(new_since(&ids, &previous), HashSet::new());
+        assert_eq!(new_since(&HashSet::new(), &current).len(), 2);
+    }
+
+    #[test]
+    fn test_diff_events_empty_inputs() {
+        assert!(diff_events(&[], &[]).is_empty());
+
+        let current = vec![event("Show", "http://x/1", "May 2", "Ryman")];
+        assert_eq!(diff_events(&[], &current).added.len(), 1);
+        assert_eq!(diff_events(&current, &[]).removed.len(), 1);
+    }
+}
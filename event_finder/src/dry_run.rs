@@ -0,0 +1,420 @@
+//! # Dry Run Module
+//!
+//! This module renders what `--dry-run` prints: each source a run would fetch, with its URLs,
+//! parse strategy, and settings, then how the events would be processed, filtered, and
+//! listed. It only reads the loaded configuration, the response cache, and the snapshots, so
+//! nothing is fetched.
+
+use std::fmt::Write;
+
+use event_finder::config::Config;
+use event_finder::data_processing::{self, ExcludeField, Match, Order};
+use event_finder::geo;
+use event_finder::html_parser::{ParseStrategy, DEFAULT_MAX_PAGES};
+use event_finder::pipeline::Settings;
+use event_finder::snapshot::SnapshotStore;
+use event_finder::sources::{self, EventSource, Source};
+use event_finder::ticketmaster;
+use event_finder::web_requests::ResponseCache;
+
+use crate::cli::Options;
+
+/// Renders the plan of a run.
+///
+/// # Arguments
+///
+/// * `sources` - The sources the run would fetch.
+/// * `settings` - How the pipeline would treat their events.
+/// * `options` - The command-line options.
+/// * `config` - The settings file.
+/// * `cache` - The response cache pages would be revalidated with, or `None` in offline mode.
+/// * `store` - The snapshots offline mode serves.
+///
+/// # Returns
+///
+/// A block per source, then the processing, filters, and output of the listing.
+pub fn render(
+    sources: &[Box<dyn EventSource>],
+    settings: &Settings,
+    options: &Options,
+    config: &Config,
+    cache: Option<&ResponseCache>,
+    store: &SnapshotStore,
+) -> String {
+    let mut out = String::new();
+    let action = if cache.is_some() {
+        "fetch"
+    } else {
+        "serve the snapshots of"
+    };
+    let _ = writeln!(
+        out,
+        "Dry run: nothing is fetched. The run would {} {} source{}:\n",
+        action,
+        sources.len(),
+        plural(sources.len())
+    );
+    for source in sources.iter().map(|source| source.source()) {
+        let categories: Vec<String> = source.categories.iter().map(ToString::to_string).collect();
+        let _ = writeln!(out, "{} ({})", source.name, categories.join(", "));
+        let mut rows = vec![
+            ("url".to_string(), source.url.clone()),
+            ("base_url".to_string(), source.base_url.clone()),
+            ("pages".to_string(), pages(source)),
+        ];
+        rows.push((
+            "cache".to_string(),
+            match cache {
+                Some(cache) => cached(cache, &source.url),
+                None => snapshot(store, &source.name),
+            },
+        ));
+        if source.config.strategy == ParseStrategy::Ticketmaster {
+            // Whether there is one, never the key itself
+            let key = match source.config.api_key {
+                Some(_) => "set".to_string(),
+                None => format!(
+                    "missing ({} or [ticketmaster] api_key), so this source would fail",
+                    ticketmaster::API_KEY_VAR
+                ),
+            };
+            rows.push(("api_key".to_string(), key));
+        }
+        if settings.enrich && cache.is_some() {
+            rows.push((
+                "enrich".to_string(),
+                format!(
+                    "up to {} detail pages, {} ms apart",
+                    settings.enrich_limit,
+                    settings.request_delay.as_millis()
+                ),
+            ));
+        }
+        rows.extend(sources::site_settings(&source.config));
+        write_rows(&mut out, &rows);
+        out.push('\n');
+    }
+
+    out.push_str("Processing\n");
+    let mut rows = vec![("timezone".to_string(), settings.zone.to_string())];
+    if settings.keep_nameless {
+        rows.push(("nameless".to_string(), "kept".to_string()));
+    }
+    if !settings.venue_aliases.is_empty() {
+        rows.push((
+            "venue_aliases".to_string(),
+            format!("{} spellings mapped", settings.venue_aliases.len()),
+        ));
+    }
+    rows.push((
+        "merge".to_string(),
+        format!("names at least {} alike", options.merge_threshold),
+    ));
+    write_rows(&mut out, &rows);
+
+    out.push_str("\nFilters\n");
+    let filters = filters(options, config, settings);
+    if filters.is_empty() {
+        out.push_str("  none\n");
+    }
+    for filter in filters {
+        let _ = writeln!(out, "  {}", filter);
+    }
+
+    out.push_str("\nOutput\n");
+    write_rows(&mut out, &output(options));
+    out
+}
+
+/// Writes rows of a key and a value, with the values lined up.
+fn write_rows(out: &mut String, rows: &[(String, String)]) {
+    let width = rows.iter().map(|(key, _)| key.len()).max().unwrap_or(0);
+    for (key, value) in rows {
+        let _ = writeln!(out, "  {:<width$}  {}", key, value, width = width);
+    }
+}
+
+fn plural(count: usize) -> &'static str {
+    if count == 1 {
+        ""
+    } else {
+        "s"
+    }
+}
+
+/// Which pages of a source's listing are read.
+fn pages(source: &Source) -> String {
+    let max_pages = source.config.max_pages.unwrap_or(DEFAULT_MAX_PAGES);
+    if source.config.strategy == ParseStrategy::Ticketmaster {
+        return format!("up to {}, following the API's pages", max_pages);
+    }
+    match source.config.next_page_selector {
+        Some(_) => format!("up to {}, following next_page_selector", max_pages),
+        None => "the listing only".to_string(),
+    }
+}
+
+/// Whether the listing page would be revalidated against a kept copy or downloaded.
+fn cached(cache: &ResponseCache, url: &str) -> String {
+    let Some(kept) = cache.get(url) else {
+        return "no kept copy; downloaded in full".to_string();
+    };
+    let validator = match (&kept.etag, &kept.last_modified) {
+        (Some(etag), _) => format!("ETag {}", etag),
+        (None, Some(modified)) => format!("Last-Modified {}", modified),
+        (None, None) => {
+            return format!(
+                "a kept copy of {} bytes without a validator; downloaded in full",
+                kept.body.len()
+            )
+        }
+    };
+    format!(
+        "a kept copy of {} bytes, only downloaded again if it changed ({})",
+        kept.body.len(),
+        validator
+    )
+}
+
+/// What offline mode would serve for a source.
+fn snapshot(store: &SnapshotStore, source: &str) -> String {
+    match store.get(source) {
+        Some(events) => format!(
+            "offline: the snapshot of {} event{}",
+            events.len(),
+            plural(events.len())
+        ),
+        None => "offline: no snapshot, so this source would fail".to_string(),
+    }
+}
+
+/// The filters the listing would go through, in the order they are applied.
+fn filters(options: &Options, config: &Config, settings: &Settings) -> Vec<String> {
+    let mut filters = Vec::new();
+    if options.hide_sold_out {
+        filters.push("sold-out and cancelled events are left out".to_string());
+    }
+    if let Some((from, to)) = options.date_range(settings.zone.today()) {
+        let range = match (from == chrono::NaiveDate::MIN, to == chrono::NaiveDate::MAX) {
+            (true, _) => format!("until {}", to),
+            (_, true) => format!("from {}", from),
+            _ => format!("from {} to {}", from, to),
+        };
+        let unread = if options.strict_dates {
+            "left out"
+        } else {
+            "kept"
+        };
+        filters.push(format!(
+            "only events {}; events whose date can't be read are {}",
+            range, unread
+        ));
+    }
+    if let Some(within) = options.within {
+        filters.push(format!(
+            "only events within {} of home",
+            geo::format_distance(within.miles)
+        ));
+    }
+    if !options.include_past {
+        filters.push(format!(
+            "events that have ended are left out, assuming {} minutes for those without an end",
+            options.assumed_duration
+        ));
+    }
+    if let Some(max) = options.max_duration {
+        let unknown = if options.strict_duration {
+            "left out"
+        } else {
+            "kept"
+        };
+        filters.push(format!(
+            "events lasting longer than {} are left out; those of unknown length are {}",
+            data_processing::format_duration(max),
+            unknown
+        ));
+    }
+    if options.free {
+        let unpriced = if options.free_or_unknown {
+            "kept"
+        } else {
+            "left out"
+        };
+        filters.push(format!(
+            "only free events; events without a price are {}",
+            unpriced
+        ));
+    }
+    for rule in crate::exclusion_rules(options, &config.filters) {
+        let target = match rule.field {
+            ExcludeField::Text => "mentioning",
+            ExcludeField::Venue => "at",
+        };
+        let whole = if rule.whole_word {
+            " as a whole word"
+        } else {
+            ""
+        };
+        filters.push(format!(
+            "events {} '{}'{} are left out",
+            target, rule.pattern, whole
+        ));
+    }
+    if options.new_only {
+        filters.push("events listed before are left out".to_string());
+    }
+    if !options.search.is_empty() {
+        let mode = match options.search_mode {
+            Match::All => "every one",
+            Match::Any => "any",
+        };
+        filters.push(format!(
+            "only events mentioning {} of: {}",
+            mode,
+            options.search.join(" ")
+        ));
+    }
+    if let Some(limit) = options.limit {
+        filters.push(format!("at most {} events are listed", limit));
+    }
+    filters
+}
+
+/// How and where the listing would be written, and what would happen after.
+fn output(options: &Options) -> Vec<(String, String)> {
+    let mut rows = Vec::new();
+    let order = match options.order {
+        Order::Ascending => "ascending",
+        Order::Descending => "descending",
+    };
+    rows.push(("sort".to_string(), format!("{}, {}", options.sort, order)));
+    if let Some(group) = options.group_by {
+        rows.push(("group_by".to_string(), group.to_string()));
+    }
+    let format = match &options.template {
+        Some(_) => "a --template line per event".to_string(),
+        None if options.compact => format!("{}, compact", options.format),
+        None if options.crlf => format!("{}, with CRLF row endings", options.format),
+        None => options.format.to_string(),
+    };
+    rows.push(("format".to_string(), format));
+    let to = match &options.output {
+        Some(path) if options.force => path.display().to_string(),
+        Some(path) => format!("{}, unless it exists (--force replaces it)", path.display()),
+        None => "stdout".to_string(),
+    };
+    rows.push(("to".to_string(), to));
+    if let Some(interval) = options.watch {
+        rows.push((
+            "watch".to_string(),
+            format!(
+                "refetched every {}",
+                data_processing::format_duration(interval)
+            ),
+        ));
+    }
+    if options.random {
+        rows.push((
+            "random".to_string(),
+            "one event picked at random".to_string(),
+        ));
+    }
+    if options.mark_seen {
+        rows.push((
+            "seen".to_string(),
+            "the fetched events are marked as seen instead of listed".to_string(),
+        ));
+    }
+    if let Some(url) = &options.webhook {
+        rows.push((
+            "webhook".to_string(),
+            format!("posted to {} as {}", url, options.webhook_format),
+        ));
+    }
+    if let Some(to) = &options.email_to {
+        rows.push(("email".to_string(), format!("sent to {}", to)));
+    }
+    if options.notify {
+        rows.push((
+            "notify".to_string(),
+            "new events matching your interests".to_string(),
+        ));
+    }
+    if let Some(number) = options.open {
+        rows.push((
+            "open".to_string(),
+            format!("event {} in the browser", number),
+        ));
+    }
+    rows
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+
+    #[test]
+    fn test_filters_list_what_the_options_and_config_leave_out() {
+        let options = crate::cli::parse_args(
+            [
+                "--hide-sold-out",
+                "--free",
+                "--exclude",
+                "trivia",
+                "--limit",
+                "5",
+                "--include-past",
+            ]
+            .map(String::from),
+        )
+        .unwrap();
+        let config = Config {
+            filters: event_finder::config::Filters {
+                blocked_venues: vec!["Tootsie's".to_string()],
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+
+        assert_eq!(
+            filters(&options, &config, &Settings::default()),
+            [
+                "sold-out and cancelled events are left out",
+                "only free events; events without a price are left out",
+                "events mentioning 'trivia' are left out",
+                "events at 'Tootsie's' are left out",
+                "at most 5 events are listed",
+            ]
+        );
+        assert!(filters(
+            &Options {
+                include_past: true,
+                ..Default::default()
+            },
+            &Config::default(),
+            &Settings::default()
+        )
+        .is_empty());
+    }
+
+    #[test]
+    fn test_output_rows() {
+        let options = Options {
+            output: Some(PathBuf::from("plans.json")),
+            compact: true,
+            format: event_finder::output::Format::Json,
+            ..Default::default()
+        };
+
+        assert_eq!(
+            output(&options),
+            [
+                ("sort", "date, ascending"),
+                ("format", "json, compact"),
+                ("to", "plans.json, unless it exists (--force replaces it)"),
+            ]
+            .map(|(key, value)| (key.to_string(), value.to_string()))
+        );
+    }
+}
@@ -0,0 +1,675 @@
+//! # Email Module
+//!
+//! This module emails the listing as a digest for `--email-to`. `compose` builds the message:
+//! the HTML report as its main part, the console's plain blocks as the alternative for mail
+//! clients that don't show HTML, and a subject like `Nashville events for Fri May 3 (12
+//! found)`. Sending it goes through the `Mailer` trait, so tests can check the message built
+//! without a mail server.
+
+use std::env;
+use std::fmt::{self, Write as _};
+use std::fs;
+use std::io::{self, Write};
+use std::path::PathBuf;
+use std::process::{Command, Stdio};
+
+use chrono::{DateTime, NaiveDate, NaiveDateTime, Utc};
+
+use crate::data_processing::{self, GroupKey, ProcessedEvent};
+use crate::output::{self, Styler};
+
+/// The environment variables that override the config file's `[email]` table.
+pub const SMTP_URL_VAR: &str = "EVENT_FINDER_SMTP_URL";
+pub const SMTP_USERNAME_VAR: &str = "EVENT_FINDER_SMTP_USERNAME";
+pub const SMTP_PASSWORD_VAR: &str = "EVENT_FINDER_SMTP_PASSWORD";
+pub const EMAIL_FROM_VAR: &str = "EVENT_FINDER_EMAIL_FROM";
+
+/// What a digest without events says.
+pub const NOTHING_TONIGHT: &str = "Nothing tonight: no events matched.";
+
+/// The most characters of base64 per line, as RFC 2045 allows.
+const BASE64_LINE: usize = 76;
+
+/// The mail server and sender, from the config file's `[email]` table and the environment.
+#[derive(Clone, PartialEq, Eq, Default)]
+pub struct EmailSettings {
+    /// The server, e.g. `smtps://smtp.example.com:465` or `smtp://smtp.example.com:587`.
+    pub smtp_url: Option<String>,
+    pub username: Option<String>,
+    pub password: Option<String>,
+    /// The sender's address; the username if it isn't set and is an address.
+    pub from: Option<String>,
+    /// Send a digest saying there is nothing on when no events were found, instead of none.
+    pub send_empty: bool,
+}
+
+impl EmailSettings {
+    /// Overrides the settings with those of the environment variables that are set.
+    ///
+    /// # Arguments
+    ///
+    /// * `var` - Looks up an environment variable, e.g. `|name| env::var(name).ok()`.
+    pub fn with_env(self, var: impl Fn(&str) -> Option<String>) -> EmailSettings {
+        EmailSettings {
+            smtp_url: var(SMTP_URL_VAR).or(self.smtp_url),
+            username: var(SMTP_USERNAME_VAR).or(self.username),
+            password: var(SMTP_PASSWORD_VAR).or(self.password),
+            from: var(EMAIL_FROM_VAR).or(self.from),
+            send_empty: self.send_empty,
+        }
+    }
+
+    /// The sender's address, or `None` if neither `from` nor an address-like username is set.
+    pub fn sender(&self) -> Option<&str> {
+        self.from
+            .as_deref()
+            .or(self.username.as_deref().filter(|name| name.contains('@')))
+    }
+}
+
+impl fmt::Debug for EmailSettings {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("EmailSettings")
+            .field("smtp_url", &self.smtp_url)
+            .field("username", &self.username)
+            .field("password", &self.password.as_ref().map(|_| REDACTED))
+            .field("from", &self.from)
+            .field("send_empty", &self.send_empty)
+            .finish()
+    }
+}
+
+/// What `Debug` shows instead of a password.
+const REDACTED: &str = "[redacted]";
+
+/// Checks that an address can go into a header as it is.
+///
+/// # Arguments
+///
+/// * `address` - The sender's or recipient's address.
+///
+/// # Returns
+///
+/// `Ok(())`, or an `InvalidInput` error if the address has a control character, such as the
+/// line break that would start another header or the body.
+pub fn check_address(address: &str) -> io::Result<()> {
+    if address.chars().any(char::is_control) {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!("email address {:?} has a control character", address),
+        ));
+    }
+    Ok(())
+}
+
+/// An email with an HTML part and its plain-text alternative.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Message {
+    pub from: String,
+    pub to: String,
+    pub subject: String,
+    pub date: DateTime<Utc>,
+    pub text: String,
+    pub html: String,
+}
+
+impl Message {
+    /// Writes the message in the MIME form a mail server takes, as a `multipart/alternative`
+    /// with the text part first, as clients show the last part they can.
+    ///
+    /// Both parts are base64-encoded, so no line is too long to send and none can be mistaken
+    /// for the end of the message.
+    pub fn to_mime(&self) -> String {
+        let boundary = format!(
+            "event-finder-{:016x}",
+            data_processing::fnv1a(format!("{}{}", self.text, self.html).as_bytes())
+        );
+        let mut out = String::new();
+        let _ = write!(
+            out,
+            "From: {}\r\nTo: {}\r\nSubject: {}\r\nDate: {}\r\n",
+            self.from,
+            self.to,
+            encode_header(&self.subject),
+            self.date.to_rfc2822()
+        );
+        let _ = write!(
+            out,
+            "MIME-Version: 1.0\r\nContent-Type: multipart/alternative; boundary=\"{}\"\r\n\r\n",
+            boundary
+        );
+        for (content_type, body) in [("text/plain", &self.text), ("text/html", &self.html)] {
+            let _ = write!(out, "--{}\r\nContent-Type: {}; charset=utf-8\r\nContent-Transfer-Encoding: base64\r\n\r\n", boundary, content_type);
+            for line in base64(body.as_bytes()).as_bytes().chunks(BASE64_LINE) {
+                out.push_str(std::str::from_utf8(line).unwrap_or_default());
+                out.push_str("\r\n");
+            }
+        }
+        let _ = write!(out, "--{}--\r\n", boundary);
+        out
+    }
+}
+
+/// What goes into a digest besides its events.
+#[derive(Debug, Clone)]
+pub struct Digest<'a> {
+    pub from: &'a str,
+    pub to: &'a str,
+    /// The city the events are in, for the subject.
+    pub label: Option<&'a str>,
+    /// The day the digest is for, for the subject.
+    pub day: NaiveDate,
+    /// What the HTML report groups events by.
+    pub group_by: GroupKey,
+    pub description_length: usize,
+    /// The current time, for how far off each start is in the text part.
+    pub now: NaiveDateTime,
+    /// When the message is sent, for its `Date` header.
+    pub sent: DateTime<Utc>,
+}
+
+/// Builds the digest of a listing.
+///
+/// # Arguments
+///
+/// * `events` - The events listed, filtered and limited.
+/// * `digest` - The addresses and how the events are shown.
+///
+/// # Returns
+///
+/// The message, saying there's nothing on if `events` is empty.
+pub fn compose(events: &[ProcessedEvent], digest: &Digest) -> Message {
+    let (text, html) = if events.is_empty() {
+        let html = output::render_html(&[], digest.description_length).replacen(
+            "</body>",
+            &format!("<p>{}</p>\n</body>", NOTHING_TONIGHT),
+            1,
+        );
+        (format!("{}\n", NOTHING_TONIGHT), html)
+    } else {
+        let blocks: Vec<String> = events
+            .iter()
+            .map(|event| {
+                output::render_event(
+                    event,
+                    digest.description_length,
+                    Some(digest.now),
+                    Styler::plain(),
+                )
+            })
+            .collect();
+        let groups = data_processing::group_events(events.to_vec(), digest.group_by);
+        (
+            blocks.join("\n"),
+            output::render_html(&groups, digest.description_length),
+        )
+    };
+    Message {
+        from: digest.from.to_string(),
+        to: digest.to.to_string(),
+        subject: subject(digest.label, digest.day, events.len()),
+        date: digest.sent,
+        text,
+        html,
+    }
+}
+
+/// Writes a digest's subject, e.g. `Nashville events for Fri May 3 (12 found)`.
+pub fn subject(label: Option<&str>, day: NaiveDate, found: usize) -> String {
+    let what = match label {
+        Some(label) => format!("{} events", label),
+        None => "Events".to_string(),
+    };
+    format!("{} for {} ({} found)", what, day.format("%a %b %-d"), found)
+}
+
+/// Something that can send an email, such as an SMTP server.
+pub trait Mailer {
+    /// Sends `message`, returning once the server has accepted it.
+    fn send(&mut self, message: &Message) -> io::Result<()>;
+}
+
+/// Sends email through an SMTP server with `curl`, which speaks SMTP with TLS and login.
+///
+/// With a login, an `smtp://` server must offer STARTTLS, so the password is never sent in
+/// the clear. The login is handed to curl in a file only the user can read, in a new directory
+/// that is removed once curl is done, not as an argument other users could see.
+#[derive(Clone, PartialEq, Eq)]
+pub struct SmtpMailer {
+    url: String,
+    login: Option<(String, String)>,
+}
+
+impl SmtpMailer {
+    /// Creates a mailer for the server in `settings`.
+    ///
+    /// # Returns
+    ///
+    /// The mailer, `None` if no server is set, or an error if the sender's address has a
+    /// control character.
+    pub fn new(settings: &EmailSettings) -> io::Result<Option<SmtpMailer>> {
+        if let Some(sender) = settings.sender() {
+            check_address(sender)?;
+        }
+        let Some(url) = settings.smtp_url.clone() else {
+            return Ok(None);
+        };
+        let login = settings
+            .username
+            .clone()
+            .map(|username| (username, settings.password.clone().unwrap_or_default()));
+        Ok(Some(SmtpMailer { url, login }))
+    }
+}
+
+impl fmt::Debug for SmtpMailer {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SmtpMailer")
+            .field("url", &self.url)
+            .field(
+                "login",
+                &self
+                    .login
+                    .as_ref()
+                    .map(|(username, _)| (username, REDACTED)),
+            )
+            .finish()
+    }
+}
+
+impl Mailer for SmtpMailer {
+    fn send(&mut self, message: &Message) -> io::Result<()> {
+        check_address(&message.from)?;
+        check_address(&message.to)?;
+        let mut command = Command::new("curl");
+        command.args([
+            "--silent",
+            "--show-error",
+            "--url",
+            &self.url,
+            "--mail-from",
+            &message.from,
+            "--mail-rcpt",
+            &message.to,
+            "--upload-file",
+            "-",
+        ]);
+        command.arg(if self.login.is_some() {
+            "--ssl-reqd"
+        } else {
+            "--ssl"
+        });
+        // Removed with the login in it once curl is done, however sending went
+        let _login_dir = match &self.login {
+            Some((username, password)) => {
+                let dir = PrivateDir::create()?;
+                let path = dir.write(
+                    "login.conf",
+                    &format!(
+                        "user = \"{}:{}\"\n",
+                        curl_escape(username),
+                        curl_escape(password)
+                    ),
+                )?;
+                command.arg("--config").arg(&path);
+                Some(dir)
+            }
+            None => None,
+        };
+        run_curl(command, message.to_mime().as_bytes())
+    }
+}
+
+/// Runs curl with `input` on its stdin, failing with what it printed if it fails.
+fn run_curl(mut command: Command, input: &[u8]) -> io::Result<()> {
+    let mut child = command
+        .stdin(Stdio::piped())
+        .stdout(Stdio::null())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(|e| match e.kind() {
+            io::ErrorKind::NotFound => {
+                io::Error::new(e.kind(), "sending email needs curl, which wasn't found")
+            }
+            _ => e,
+        })?;
+    // Dropped once written, which ends curl's input
+    child
+        .stdin
+        .take()
+        .map_or(Ok(()), |mut stdin| stdin.write_all(input))?;
+    let output = child.wait_with_output()?;
+    if output.status.success() {
+        Ok(())
+    } else {
+        Err(io::Error::other(format!(
+            "sending email failed: {}",
+            String::from_utf8_lossy(&output.stderr).trim()
+        )))
+    }
+}
+
+/// A new temporary directory only the user can enter, removed with what is in it when dropped.
+struct PrivateDir {
+    path: PathBuf,
+}
+
+impl PrivateDir {
+    /// Creates the directory under a random name, never reusing one that is already there,
+    /// which another user could have made or linked elsewhere.
+    fn create() -> io::Result<PrivateDir> {
+        let mut builder = fs::DirBuilder::new();
+        #[cfg(unix)]
+        std::os::unix::fs::DirBuilderExt::mode(&mut builder, 0o700);
+        for _ in 0..16 {
+            let path = env::temp_dir().join(format!(
+                "event_finder_smtp_{}_{:016x}",
+                std::process::id(),
+                rand::random::<u64>()
+            ));
+            match builder.create(&path) {
+                Ok(()) => return Ok(PrivateDir { path }),
+                Err(e) if e.kind() == io::ErrorKind::AlreadyExists => continue,
+                Err(e) => return Err(e),
+            }
+        }
+        Err(io::Error::new(
+            io::ErrorKind::AlreadyExists,
+            "couldn't create a private temporary directory",
+        ))
+    }
+
+    /// Writes a new file in the directory that only the user can read.
+    ///
+    /// # Returns
+    ///
+    /// The file's path, or the error creating it, e.g. if it already exists.
+    fn write(&self, name: &str, contents: &str) -> io::Result<PathBuf> {
+        let path = self.path.join(name);
+        let mut options = fs::OpenOptions::new();
+        options.write(true).create_new(true);
+        #[cfg(unix)]
+        std::os::unix::fs::OpenOptionsExt::mode(&mut options, 0o600);
+        options.open(&path)?.write_all(contents.as_bytes())?;
+        Ok(path)
+    }
+}
+
+impl Drop for PrivateDir {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.path);
+    }
+}
+
+/// Escapes text for a quoted value of a curl config file.
+fn curl_escape(text: &str) -> String {
+    text.replace('\\', "\\\\").replace('"', "\\\"")
+}
+
+/// Encodes a header value as RFC 2047 UTF-8 if it isn't plain ASCII.
+fn encode_header(value: &str) -> String {
+    if value.is_ascii() {
+        value.to_string()
+    } else {
+        format!("=?utf-8?B?{}?=", base64(value.as_bytes()))
+    }
+}
+
+/// Encodes bytes as standard, padded base64.
+fn base64(bytes: &[u8]) -> String {
+    const ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+    let mut out = String::with_capacity(bytes.len().div_ceil(3) * 4);
+    for chunk in bytes.chunks(3) {
+        let n = (u32::from(chunk[0]) << 16)
+            | (u32::from(*chunk.get(1).unwrap_or(&0)) << 8)
+            | u32::from(*chunk.get(2).unwrap_or(&0));
+        for i in 0..4 {
+            if i <= chunk.len() {
+                out.push(char::from(ALPHABET[(n >> (18 - 6 * i)) as usize & 63]));
+            } else {
+                out.push('=');
+            }
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    /// Keeps the messages it is asked to send.
+    #[derive(Default)]
+    struct Outbox(Vec<Message>);
+
+    impl Mailer for Outbox {
+        fn send(&mut self, message: &Message) -> io::Result<()> {
+            self.0.push(message.clone());
+            Ok(())
+        }
+    }
+
+    fn digest() -> Digest<'static> {
+        Digest {
+            from: "events@example.com",
+            to: "someone@example.com",
+            label: Some("Nashville"),
+            day: NaiveDate::from_ymd_opt(2024, 5, 3).unwrap(),
+            group_by: GroupKey::Venue,
+            description_length: 160,
+            now: NaiveDate::from_ymd_opt(2024, 5, 3)
+                .unwrap()
+                .and_hms_opt(12, 0, 0)
+                .unwrap(),
+            sent: Utc.with_ymd_and_hms(2024, 5, 3, 17, 0, 0).unwrap(),
+        }
+    }
+
+    fn events() -> Vec<ProcessedEvent> {
+        vec![
+            ProcessedEvent {
+                name: "Bluegrass Night".to_string(),
+                url: "https://example.com/bluegrass".to_string(),
+                start_date: "Fri May 3 8:00 PM".to_string(),
+                location: Some("The Station Inn".to_string()),
+                ..Default::default()
+            },
+            ProcessedEvent {
+                name: "Open Mic".to_string(),
+                location: Some("The Basement".to_string()),
+                ..Default::default()
+            },
+        ]
+    }
+
+    fn decode(base64_lines: &str) -> String {
+        const ALPHABET: &str = "ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+        let digits: Vec<u32> = base64_lines
+            .bytes()
+            .filter(|b| !b"\r\n=".contains(b))
+            .map(|b| ALPHABET.find(char::from(b)).unwrap() as u32)
+            .collect();
+        let bytes: Vec<u8> = digits
+            .chunks(4)
+            .flat_map(|chunk| {
+                let n = chunk
+                    .iter()
+                    .enumerate()
+                    .fold(0, |n, (i, d)| n | d << (18 - 6 * i));
+                let len = chunk.len() - 1;
+                (0..len).map(move |i| (n >> (16 - 8 * i)) as u8)
+            })
+            .collect();
+        String::from_utf8(bytes).unwrap()
+    }
+
+    #[test]
+    fn test_compose_digest() {
+        let message = compose(&events(), &digest());
+
+        assert_eq!(message.subject, "Nashville events for Fri May 3 (2 found)");
+        assert_eq!(
+            (message.from.as_str(), message.to.as_str()),
+            ("events@example.com", "someone@example.com")
+        );
+        assert!(
+            message.html.contains("<h2>The Station Inn</h2>"),
+            "{}",
+            message.html
+        );
+        assert!(message
+            .html
+            .contains("<a href=\"https://example.com/bluegrass\">Bluegrass Night</a>"));
+        assert!(
+            message.text.starts_with("Name: Bluegrass Night\n"),
+            "{}",
+            message.text
+        );
+        assert!(message.text.contains("\n\nName: Open Mic\n"));
+        assert!(!message.text.contains('<'));
+    }
+
+    #[test]
+    fn test_empty_digest_says_nothing_tonight() {
+        let message = compose(
+            &[],
+            &Digest {
+                label: None,
+                ..digest()
+            },
+        );
+
+        assert_eq!(message.subject, "Events for Fri May 3 (0 found)");
+        assert_eq!(message.text, format!("{}\n", NOTHING_TONIGHT));
+        assert!(message
+            .html
+            .contains(&format!("<p>{}</p>\n</body>", NOTHING_TONIGHT)));
+    }
+
+    #[test]
+    fn test_mime_has_both_parts() {
+        let mut outbox = Outbox::default();
+        let message = Message {
+            subject: "Montréal events".to_string(),
+            ..compose(&events(), &digest())
+        };
+        outbox.send(&message).unwrap();
+
+        let mime = outbox.0[0].to_mime();
+        assert!(mime.starts_with("From: events@example.com\r\nTo: someone@example.com\r\nSubject: =?utf-8?B?TW9udHLDqWFsIGV2ZW50cw==?=\r\nDate: Fri, 3 May 2024 17:00:00 +0000\r\n"), "{}", mime);
+        let boundary = mime
+            .split("boundary=\"")
+            .nth(1)
+            .unwrap()
+            .split('"')
+            .next()
+            .unwrap();
+        let parts: Vec<&str> = mime.split(&format!("--{}", boundary)).collect();
+        assert_eq!(parts.len(), 4);
+        assert_eq!(parts[3], "--\r\n");
+        let (text_headers, text_body) = parts[1].split_once("\r\n\r\n").unwrap();
+        assert!(text_headers.contains("Content-Type: text/plain; charset=utf-8"));
+        assert_eq!(decode(text_body), message.text);
+        let (html_headers, html_body) = parts[2].split_once("\r\n\r\n").unwrap();
+        assert!(html_headers.contains("Content-Type: text/html; charset=utf-8"));
+        assert_eq!(decode(html_body), message.html);
+        assert!(mime.lines().all(|line| line.len() <= 998));
+    }
+
+    #[test]
+    fn test_private_dir_holds_the_login_until_dropped() {
+        let dir = PrivateDir::create().unwrap();
+        let path = dir.write("login.conf", "user = \"me:pw\"\n").unwrap();
+        assert_eq!(fs::read_to_string(&path).unwrap(), "user = \"me:pw\"\n");
+        // An existing file, or a link planted in its place, is never written through
+        assert_eq!(
+            dir.write("login.conf", "again").unwrap_err().kind(),
+            io::ErrorKind::AlreadyExists
+        );
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            assert_eq!(
+                fs::metadata(&dir.path).unwrap().permissions().mode() & 0o777,
+                0o700
+            );
+            assert_eq!(
+                fs::metadata(&path).unwrap().permissions().mode() & 0o777,
+                0o600
+            );
+        }
+        let root = dir.path.clone();
+
+        drop(dir);
+
+        assert!(!root.exists());
+    }
+
+    #[test]
+    fn test_base64() {
+        assert_eq!(base64(b""), "");
+        assert_eq!(base64(b"f"), "Zg==");
+        assert_eq!(base64(b"fo"), "Zm8=");
+        assert_eq!(base64(b"foobar"), "Zm9vYmFy");
+    }
+
+    #[test]
+    fn test_settings_from_the_environment() {
+        let settings = EmailSettings {
+            smtp_url: Some("smtp://config.example.com".to_string()),
+            username: Some("me@example.com".to_string()),
+            ..Default::default()
+        };
+        let env =
+            |name: &str| (name == SMTP_URL_VAR).then(|| "smtps://env.example.com".to_string());
+
+        let settings = settings.with_env(env);
+        assert_eq!(
+            settings.smtp_url.as_deref(),
+            Some("smtps://env.example.com")
+        );
+        assert_eq!(settings.sender(), Some("me@example.com"));
+        assert_eq!(
+            EmailSettings {
+                username: Some("me".to_string()),
+                ..Default::default()
+            }
+            .sender(),
+            None
+        );
+        assert_eq!(SmtpMailer::new(&EmailSettings::default()).unwrap(), None);
+    }
+
+    #[test]
+    fn test_addresses_with_line_breaks_are_refused() {
+        let settings = EmailSettings {
+            smtp_url: Some("smtps://smtp.example.com".to_string()),
+            from: Some("me@example.com\r\nBcc: everyone@example.com".to_string()),
+            ..Default::default()
+        };
+        let error = SmtpMailer::new(&settings).unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidInput);
+        assert!(check_address("you@example.com\n\nHello").is_err());
+        assert!(check_address("you@example.com").is_ok());
+    }
+
+    #[test]
+    fn test_debug_hides_the_password() {
+        let settings = EmailSettings {
+            smtp_url: Some("smtps://smtp.example.com".to_string()),
+            username: Some("me@example.com".to_string()),
+            password: Some("hunter2".to_string()),
+            ..Default::default()
+        };
+        let mailer = SmtpMailer::new(&settings).unwrap().unwrap();
+        for debug in [format!("{:?}", settings), format!("{:?}", mailer)] {
+            assert!(!debug.contains("hunter2"), "{}", debug);
+            assert!(
+                debug.contains("me@example.com") && debug.contains(REDACTED),
+                "{}",
+                debug
+            );
+        }
+    }
+}
@@ -0,0 +1,99 @@
+//! # Error Module
+//!
+//! This module defines `Error`, the error type of the library's entry points and of the
+//! application's main flow. Each variant wraps the error of the step that failed, so callers
+//! can match on what went wrong and the binary can print it without a backtrace.
+
+use std::fmt;
+use std::io;
+use std::path::PathBuf;
+
+use crate::config::ConfigError;
+use crate::html_parser;
+use crate::web_requests::FetchError;
+
+/// Anything that can go wrong fetching and showing events.
+#[derive(Debug)]
+pub enum Error {
+    /// A source couldn't be fetched, or what it sent couldn't be read.
+    Fetch(FetchError),
+    /// A source's configuration can't be used, e.g. a selector that doesn't parse.
+    InvalidSource {
+        /// The source's name.
+        source: String,
+        /// Every problem found in its configuration.
+        errors: Vec<html_parser::ConfigError>,
+    },
+    /// The settings file is malformed.
+    Config {
+        /// The settings file.
+        path: PathBuf,
+        /// The first problem found in it.
+        error: ConfigError,
+    },
+    /// Reading or writing a file or the terminal failed.
+    Io(io::Error),
+    /// The `--email-to` digest couldn't be sent.
+    Email(io::Error),
+    /// The page piped to the `parse` subcommand can't be read, e.g. it isn't UTF-8.
+    Input(String),
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::Fetch(e) => write!(f, "{}", e),
+            Error::InvalidSource { source, errors } => {
+                let errors: Vec<String> = errors.iter().map(ToString::to_string).collect();
+                write!(
+                    f,
+                    "invalid configuration for source {}: {}",
+                    source,
+                    errors.join("; ")
+                )
+            }
+            Error::Config { path, error } => {
+                write!(f, "invalid config file {}: {}", path.display(), error)
+            }
+            Error::Io(e) => write!(f, "{}", e),
+            Error::Email(e) => write!(f, "emailing the listing: {}", e),
+            Error::Input(message) => write!(f, "{}", message),
+        }
+    }
+}
+
+impl Error {
+    /// A short name for what went wrong, e.g. `config`, for `--error-format json`.
+    pub fn kind(&self) -> &'static str {
+        match self {
+            Error::Fetch(e) => e.kind(),
+            Error::InvalidSource { .. } => "invalid_source",
+            Error::Config { .. } => "config",
+            Error::Io(_) => "io",
+            Error::Email(_) => "email",
+            Error::Input(_) => "input",
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Error::Fetch(FetchError::Http(e)) => Some(e),
+            Error::Io(e) | Error::Email(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<FetchError> for Error {
+    fn from(e: FetchError) -> Self {
+        Error::Fetch(e)
+    }
+}
+
+impl From<io::Error> for Error {
+    fn from(e: io::Error) -> Self {
+        Error::Io(e)
+    }
+}
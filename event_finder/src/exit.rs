@@ -0,0 +1,144 @@
+//! # Exit Module
+//!
+//! This module defines the statuses the application exits with, so scripts can tell a run
+//! that found nothing from one where every source failed or the arguments were wrong. The
+//! codes from 64 to 78 are the BSD `sysexits.h` ones, and 130 is what shells report for Ctrl-C.
+
+use event_finder::Error;
+
+use crate::interrupt;
+
+/// The run finished, even if no events were found, or some sources failed without `--strict`.
+pub const SUCCESS: i32 = 0;
+/// Some sources failed, with `--strict`.
+pub const PARTIAL: i32 = 1;
+/// Every source failed.
+pub const ALL_FAILED: i32 = 2;
+/// The command line was wrong: an unknown flag, command, source, or city, a value that can't
+/// be used, or an `--open N` that couldn't be opened.
+pub const USAGE: i32 = 64;
+/// The page piped to `parse` is empty or isn't UTF-8 text.
+pub const DATA_ERR: i32 = 65;
+/// A webhook or mail server didn't take the listing.
+pub const UNAVAILABLE: i32 = 69;
+/// Something that shouldn't fail did, such as creating the HTTP client.
+pub const SOFTWARE: i32 = 70;
+/// Reading or writing a file or the terminal failed, e.g. `--output` exists.
+pub const IO: i32 = 74;
+/// The config file or the sources file can't be used.
+pub const CONFIG: i32 = 78;
+/// Ctrl-C stopped the menu.
+pub const INTERRUPTED: i32 = interrupt::STATUS;
+
+/// Which of the sources fetched in a run failed.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct Outcome {
+    /// The sources fetched.
+    pub sources: usize,
+    /// The names of those that failed, each with what went wrong.
+    pub failed: Vec<(String, String)>,
+}
+
+impl Outcome {
+    /// The status to exit with: `ALL_FAILED` if every source failed, `PARTIAL` if some did
+    /// with `strict`, else `SUCCESS`.
+    pub fn code(&self, strict: bool) -> i32 {
+        let failed = self.failed.len();
+        if failed > 0 && failed == self.sources {
+            ALL_FAILED
+        } else if failed > 0 && strict {
+            PARTIAL
+        } else {
+            SUCCESS
+        }
+    }
+
+    /// Sums up the sources that failed, one line each, the last saying how many succeeded,
+    /// e.g. `⚠ perto failed: timeout after 30s (2 of 3 sources succeeded)`.
+    ///
+    /// # Returns
+    ///
+    /// The lines, or none if every source succeeded.
+    pub fn summary(&self) -> Vec<String> {
+        let mut lines: Vec<String> = self
+            .failed
+            .iter()
+            .map(|(source, why)| format!("⚠ {} failed: {}", source, why))
+            .collect();
+        if let Some(last) = lines.last_mut() {
+            last.push_str(&format!(
+                " ({} of {} sources succeeded)",
+                self.sources - self.failed.len(),
+                self.sources
+            ));
+        }
+        lines
+    }
+}
+
+/// The status to exit with when the run stopped with `error`.
+pub fn for_error(error: &Error) -> i32 {
+    match error {
+        Error::Fetch(_) | Error::Email(_) => UNAVAILABLE,
+        Error::InvalidSource { .. } | Error::Config { .. } => CONFIG,
+        Error::Io(_) => IO,
+        Error::Input(_) => DATA_ERR,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io;
+
+    fn failed(sources: usize, names: &[&str]) -> Outcome {
+        Outcome {
+            sources,
+            failed: names
+                .iter()
+                .map(|name| (name.to_string(), "timeout after 30s".to_string()))
+                .collect(),
+        }
+    }
+
+    #[test]
+    fn test_outcome_codes() {
+        assert_eq!(Outcome::default().code(true), SUCCESS);
+        assert_eq!(failed(3, &[]).code(true), SUCCESS);
+        assert_eq!(failed(3, &["perto"]).code(false), SUCCESS);
+        assert_eq!(failed(3, &["perto"]).code(true), PARTIAL);
+        assert_eq!(
+            failed(3, &["perto", "songkick", "eventbrite"]).code(false),
+            ALL_FAILED
+        );
+    }
+
+    #[test]
+    fn test_outcome_summary() {
+        assert!(failed(3, &[]).summary().is_empty());
+        assert_eq!(
+            failed(3, &["perto"]).summary(),
+            ["⚠ perto failed: timeout after 30s (2 of 3 sources succeeded)"]
+        );
+        assert_eq!(
+            failed(2, &["perto", "songkick"]).summary(),
+            [
+                "⚠ perto failed: timeout after 30s",
+                "⚠ songkick failed: timeout after 30s (0 of 2 sources succeeded)"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_error_codes() {
+        assert_eq!(for_error(&Error::Io(io::Error::other("disk full"))), IO);
+        assert_eq!(
+            for_error(&Error::Email(io::Error::other("refused"))),
+            UNAVAILABLE
+        );
+        assert_eq!(
+            for_error(&Error::Input("stdin is empty".to_string())),
+            DATA_ERR
+        );
+    }
+}
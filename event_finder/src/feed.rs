@@ -0,0 +1,479 @@
+//! # Feed Module
+//!
+//! This module parses RSS 2.0 and Atom feeds into events. Venues that publish their calendar
+//! as a feed are far more stable to follow than their HTML, which changes with every redesign.
+//!
+//! Feeds are read with a small XML reader that understands elements, attributes, text,
+//! CDATA sections, and entity references, which is all RSS and Atom need.
+
+use std::fmt;
+use std::sync::OnceLock;
+
+use regex::Regex;
+
+use crate::html_parser::{self, Event};
+
+/// An error raised when a document isn't a readable feed.
+#[derive(Debug, Clone, PartialEq)]
+pub enum ParseError {
+    /// The document isn't well-formed XML.
+    Xml(String),
+    /// The document is XML but its root is neither `<rss>` nor `<feed>`.
+    NotAFeed(String),
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ParseError::Xml(message) => write!(f, "malformed feed: {}", message),
+            ParseError::NotAFeed(root) => {
+                write!(f, "not an RSS or Atom feed (root element <{}>)", root)
+            }
+        }
+    }
+}
+
+/// Parses an RSS 2.0 or Atom feed into events.
+///
+/// Each RSS `<item>` or Atom `<entry>` becomes an event: the title is the name; `pubDate`
+/// (RSS) or `updated`/`published` (Atom) is the start date, falling back to a date written in
+/// the title; the link is the URL; and the description or summary is the description. Feeds
+/// don't say where an event is, so the location is left for `process_data` to default.
+/// Missing fields are left empty.
+///
+/// # Arguments
+///
+/// * `xml` - The feed document.
+/// * `base_url` - The base URL of the site for resolving relative links.
+///
+/// # Returns
+///
+/// The feed's events, or a `ParseError` if the document isn't a readable feed.
+pub fn parse_feed(xml: &str, base_url: &str) -> Result<Vec<Event>, ParseError> {
+    let root = parse_xml(xml)?;
+    let (items, atom) = match root.local_name() {
+        "rss" => (
+            root.child("channel")
+                .map(|c| c.children_named("item"))
+                .unwrap_or_default(),
+            false,
+        ),
+        "feed" => (root.children_named("entry"), true),
+        other => return Err(ParseError::NotAFeed(other.to_string())),
+    };
+
+    Ok(items
+        .into_iter()
+        .map(|item| feed_event(item, atom, base_url))
+        .collect())
+}
+
+/// Builds an `Event` from an RSS `<item>` or Atom `<entry>`.
+fn feed_event(item: &Element, atom: bool, base_url: &str) -> Event {
+    let text = |name: &str| {
+        item.child(name)
+            .map(Element::text)
+            .map(|t| t.trim().to_string())
+            .filter(|t| !t.is_empty())
+    };
+    let name = text("title").unwrap_or_default();
+
+    let date = if atom {
+        text("updated").or_else(|| text("published"))
+    } else {
+        text("pubDate")
+    };
+    let start_date = date.or_else(|| date_in(&name)).unwrap_or_default();
+
+    let link = if atom {
+        item.children_named("link")
+            .into_iter()
+            .find(|l| l.attr("rel").is_none_or(|rel| rel == "alternate"))
+            .and_then(|l| l.attr("href"))
+            .map(str::to_string)
+    } else {
+        text("link")
+    };
+    let url = link
+        .and_then(|l| html_parser::resolve_url(base_url, &l).ok())
+        .unwrap_or_default();
+
+    let description = if atom {
+        text("summary").or_else(|| text("content"))
+    } else {
+        text("description")
+    };
+
+    Event {
+        name,
+        start_date,
+        url,
+        description,
+        ..Default::default()
+    }
+}
+
+/// Finds a date written in free text, such as an event title.
+///
+/// Recognizes ISO dates (`2024-05-02`), US numeric dates (`5/2/2024`), and month names
+/// followed by a day (`May 2`, `Sept. 14, 2024`).
+fn date_in(text: &str) -> Option<String> {
+    static PATTERN: OnceLock<Regex> = OnceLock::new();
+    let pattern = PATTERN.get_or_init(|| {
+        Regex::new(concat!(
+            r"\b\d{4}-\d{2}-\d{2}\b",
+            r"|\b\d{1,2}/\d{1,2}/\d{2,4}\b",
+            r"|\b(?i:jan|feb|mar|apr|may|jun|jul|aug|sep|sept|oct|nov|dec)[a-z]*\.? \d{1,2}(?:st|nd|rd|th)?(?:, \d{4})?\b",
+        ))
+        .unwrap()
+    });
+    pattern.find(text).map(|m| m.as_str().to_string())
+}
+
+/// An XML element with its attributes and children.
+#[derive(Debug, Clone, PartialEq, Default)]
+struct Element {
+    name: String,
+    attrs: Vec<(String, String)>,
+    children: Vec<Node>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Node {
+    Element(Element),
+    Text(String),
+}
+
+impl Element {
+    /// The element name without its namespace prefix (`content:encoded` -> `encoded`).
+    fn local_name(&self) -> &str {
+        self.name.rsplit(':').next().unwrap_or(&self.name)
+    }
+
+    fn elements(&self) -> impl Iterator<Item = &Element> {
+        self.children.iter().filter_map(|node| match node {
+            Node::Element(element) => Some(element),
+            Node::Text(_) => None,
+        })
+    }
+
+    fn child(&self, name: &str) -> Option<&Element> {
+        self.elements().find(|e| e.local_name() == name)
+    }
+
+    fn children_named(&self, name: &str) -> Vec<&Element> {
+        self.elements().filter(|e| e.local_name() == name).collect()
+    }
+
+    fn attr(&self, name: &str) -> Option<&str> {
+        self.attrs
+            .iter()
+            .find(|(key, _)| key == name)
+            .map(|(_, value)| value.as_str())
+    }
+
+    /// All text inside the element, including that of nested elements.
+    fn text(&self) -> String {
+        self.children
+            .iter()
+            .map(|node| match node {
+                Node::Element(element) => element.text(),
+                Node::Text(text) => text.clone(),
+            })
+            .collect()
+    }
+}
+
+/// Parses an XML document into its root element.
+fn parse_xml(xml: &str) -> Result<Element, ParseError> {
+    let mut stack: Vec<Element> = vec![Element::default()];
+    let mut rest = xml;
+
+    while !rest.is_empty() {
+        if let Some(after) = rest.strip_prefix("<![CDATA[") {
+            let end = after
+                .find("]]>")
+                .ok_or_else(|| ParseError::Xml("unterminated CDATA section".to_string()))?;
+            push_node(&mut stack, Node::Text(after[..end].to_string()));
+            rest = &after[end + 3..];
+        } else if let Some(after) = rest.strip_prefix("<!--") {
+            let end = after
+                .find("-->")
+                .ok_or_else(|| ParseError::Xml("unterminated comment".to_string()))?;
+            rest = &after[end + 3..];
+        } else if rest.starts_with("<?") || rest.starts_with("<!") {
+            let end = rest
+                .find('>')
+                .ok_or_else(|| ParseError::Xml("unterminated declaration".to_string()))?;
+            rest = &rest[end + 1..];
+        } else if let Some(after) = rest.strip_prefix("</") {
+            let end = after
+                .find('>')
+                .ok_or_else(|| ParseError::Xml("unterminated closing tag".to_string()))?;
+            let name = after[..end].trim();
+            let element = stack
+                .pop()
+                .filter(|_| !stack.is_empty())
+                .ok_or_else(|| ParseError::Xml(format!("unexpected </{}>", name)))?;
+            if element.name != name {
+                return Err(ParseError::Xml(format!(
+                    "<{}> closed by </{}>",
+                    element.name, name
+                )));
+            }
+            push_node(&mut stack, Node::Element(element));
+            rest = &after[end + 1..];
+        } else if let Some(after) = rest.strip_prefix('<') {
+            let end =
+                tag_end(after).ok_or_else(|| ParseError::Xml("unterminated tag".to_string()))?;
+            let tag = &after[..end];
+            let (tag, self_closing) = match tag.strip_suffix('/') {
+                Some(tag) => (tag, true),
+                None => (tag, false),
+            };
+            let element = parse_tag(tag)?;
+            if self_closing {
+                push_node(&mut stack, Node::Element(element));
+            } else {
+                stack.push(element);
+            }
+            rest = &after[end + 1..];
+        } else {
+            let end = rest.find('<').unwrap_or(rest.len());
+            push_node(&mut stack, Node::Text(decode_entities(&rest[..end])));
+            rest = &rest[end..];
+        }
+    }
+
+    if stack.len() > 1 {
+        return Err(ParseError::Xml(format!(
+            "<{}> is never closed",
+            stack[stack.len() - 1].name
+        )));
+    }
+    let document = stack.pop().unwrap_or_default();
+    let root = document.elements().next().cloned();
+    root.ok_or_else(|| ParseError::Xml("no root element".to_string()))
+}
+
+fn push_node(stack: &mut [Element], node: Node) {
+    if let Some(parent) = stack.last_mut() {
+        parent.children.push(node);
+    }
+}
+
+/// Finds the `>` ending a tag, skipping any inside quoted attribute values.
+fn tag_end(tag: &str) -> Option<usize> {
+    let mut quote = None;
+    for (i, c) in tag.char_indices() {
+        match (quote, c) {
+            (None, '"') | (None, '\'') => quote = Some(c),
+            (Some(q), c) if c == q => quote = None,
+            (None, '>') => return Some(i),
+            _ => {}
+        }
+    }
+    None
+}
+
+/// Parses the inside of an opening tag (`name key="value" ...`).
+fn parse_tag(tag: &str) -> Result<Element, ParseError> {
+    let tag = tag.trim();
+    let name_end = tag.find(char::is_whitespace).unwrap_or(tag.len());
+    let name = &tag[..name_end];
+    if name.is_empty() {
+        return Err(ParseError::Xml("tag without a name".to_string()));
+    }
+
+    let mut attrs = Vec::new();
+    let mut rest = tag[name_end..].trim_start();
+    while !rest.is_empty() {
+        let eq = rest
+            .find('=')
+            .ok_or_else(|| ParseError::Xml(format!("attribute without a value in <{}>", name)))?;
+        let key = rest[..eq].trim().to_string();
+        let value = rest[eq + 1..].trim_start();
+        let quote = value
+            .chars()
+            .next()
+            .filter(|c| *c == '"' || *c == '\'')
+            .ok_or_else(|| {
+                ParseError::Xml(format!("unquoted attribute '{}' in <{}>", key, name))
+            })?;
+        let close = value[1..]
+            .find(quote)
+            .ok_or_else(|| ParseError::Xml(format!("unterminated attribute '{}'", key)))?;
+        attrs.push((key, decode_entities(&value[1..close + 1])));
+        rest = value[close + 2..].trim_start();
+    }
+    Ok(Element {
+        name: name.to_string(),
+        attrs,
+        children: Vec::new(),
+    })
+}
+
+/// Replaces XML entity and character references with the characters they stand for.
+///
+/// Unknown references are left as they are.
+fn decode_entities(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(start) = rest.find('&') {
+        out.push_str(&rest[..start]);
+        rest = &rest[start..];
+        let decoded = rest.find(';').and_then(|end| {
+            let entity = &rest[1..end];
+            let c = match entity {
+                "amp" => Some('&'),
+                "lt" => Some('<'),
+                "gt" => Some('>'),
+                "quot" => Some('"'),
+                "apos" => Some('\''),
+                _ => entity
+                    .strip_prefix("#x")
+                    .or_else(|| entity.strip_prefix("#X"))
+                    .map(|hex| u32::from_str_radix(hex, 16))
+                    .or_else(|| entity.strip_prefix('#').map(str::parse))
+                    .and_then(Result::ok)
+                    .and_then(char::from_u32),
+            };
+            c.map(|c| (c, end))
+        });
+        match decoded {
+            Some((c, end)) => {
+                out.push(c);
+                rest = &rest[end + 1..];
+            }
+            None => {
+                out.push('&');
+                rest = &rest[1..];
+            }
+        }
+    }
+    out.push_str(rest);
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::data_processing;
+    use crate::timezone::Zone;
+    use chrono::NaiveDate;
+
+    const RSS: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<rss version="2.0" xmlns:content="http://purl.org/rss/1.0/modules/content/">
+  <channel>
+    <title>Station Inn Calendar</title>
+    <link>https://stationinn.com</link>
+    <!-- upcoming shows -->
+    <item>
+      <title>Bluegrass Jam &amp; Potluck</title>
+      <link>https://stationinn.com/events/jam</link>
+      <pubDate>Thu, 02 May 2024 19:00:00 -0500</pubDate>
+      <description><![CDATA[<p>Bring an <b>instrument</b>.</p>]]></description>
+    </item>
+    <item>
+      <title>Songwriter Night – May 9</title>
+      <link>/events/songwriters</link>
+    </item>
+    <item>
+      <description>No title or date</description>
+    </item>
+  </channel>
+</rss>"#;
+
+    const ATOM: &str = r#"<?xml version="1.0" encoding="utf-8"?>
+<feed xmlns="http://www.w3.org/2005/Atom">
+  <title>Exit/In</title>
+  <entry>
+    <title type="html">Indie Night</title>
+    <link rel="self" href="https://exitin.com/feed/indie"/>
+    <link rel="alternate" href="https://exitin.com/events/indie-night"/>
+    <updated>2024-05-03T21:00:00-05:00</updated>
+    <summary>Three bands, one stage.</summary>
+  </entry>
+  <entry>
+    <title>Vinyl Swap 2024-05-04</title>
+    <link href='/events/vinyl-swap'/>
+  </entry>
+</feed>"#;
+
+    #[test]
+    fn test_parse_rss_feed() {
+        let events = parse_feed(RSS, "https://stationinn.com").unwrap();
+
+        assert_eq!(events.len(), 3);
+        assert_eq!(events[0].name, "Bluegrass Jam & Potluck");
+        assert_eq!(events[0].start_date, "Thu, 02 May 2024 19:00:00 -0500");
+        assert_eq!(events[0].url, "https://stationinn.com/events/jam");
+        assert_eq!(
+            events[0].description.as_deref(),
+            Some("<p>Bring an <b>instrument</b>.</p>")
+        );
+        assert_eq!(events[0].location, None);
+
+        assert_eq!(events[1].start_date, "May 9");
+        assert_eq!(events[1].url, "https://stationinn.com/events/songwriters");
+
+        assert_eq!(events[2].name, "");
+        assert_eq!(events[2].start_date, "");
+        assert_eq!(events[2].url, "");
+    }
+
+    #[test]
+    fn test_rss_pub_dates_process_to_dated_events() {
+        let events = parse_feed(RSS, "https://stationinn.com").unwrap();
+
+        let (processed, warnings) =
+            data_processing::process_data(events[..1].to_vec(), Zone::default());
+
+        let start = NaiveDate::from_ymd_opt(2024, 5, 2)
+            .unwrap()
+            .and_hms_opt(19, 0, 0)
+            .unwrap();
+        assert_eq!(processed[0].start, Some(start));
+        assert!(warnings.is_empty(), "{:?}", warnings);
+    }
+
+    #[test]
+    fn test_parse_atom_feed() {
+        let events = parse_feed(ATOM, "https://exitin.com").unwrap();
+
+        assert_eq!(events.len(), 2);
+        assert_eq!(events[0].name, "Indie Night");
+        assert_eq!(events[0].start_date, "2024-05-03T21:00:00-05:00");
+        assert_eq!(events[0].url, "https://exitin.com/events/indie-night");
+        assert_eq!(
+            events[0].description.as_deref(),
+            Some("Three bands, one stage.")
+        );
+        assert_eq!(events[1].start_date, "2024-05-04");
+        assert_eq!(events[1].url, "https://exitin.com/events/vinyl-swap");
+    }
+
+    #[test]
+    fn test_parse_feed_errors() {
+        assert_eq!(
+            parse_feed("<html><body></body></html>", "https://example.com"),
+            Err(ParseError::NotAFeed("html".to_string()))
+        );
+        assert!(matches!(
+            parse_feed("<rss><channel></rss>", "https://example.com"),
+            Err(ParseError::Xml(_))
+        ));
+        assert!(matches!(
+            parse_feed("just text", "https://example.com"),
+            Err(ParseError::Xml(_))
+        ));
+    }
+
+    #[test]
+    fn test_decode_entities() {
+        assert_eq!(
+            decode_entities("Rock &amp; Roll &#8211; &#x2014; &lt;3 &bogus; & more"),
+            "Rock & Roll – — <3 &bogus; & more"
+        );
+    }
+}
@@ -0,0 +1,603 @@
+//! # Geo Module
+//!
+//! This module places venues on the map so events can be filtered by how far they are from
+//! home. Venue coordinates come from the config file's `[venue_coordinates]` table, then from
+//! a cache in the data directory (`geocache.json`), and, only with `--geocode`, from the
+//! Nominatim API, whose answers are added to the cache.
+
+use std::collections::BTreeMap;
+use std::fs;
+use std::io;
+use std::path::PathBuf;
+use std::str::FromStr;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use serde_json::{json, Map, Value};
+use url::Url;
+
+use crate::data_processing::{normalize_key, ProcessedEvent};
+use crate::snapshot;
+use crate::web_requests::{FetchError, WebClient};
+
+/// The mean radius of the Earth, in miles.
+const EARTH_RADIUS_MILES: f64 = 3958.8;
+
+/// The number of kilometres in a mile.
+const KM_PER_MILE: f64 = 1.609344;
+
+/// Where geocoding requests are sent.
+pub const NOMINATIM_URL: &str = "https://nominatim.openstreetmap.org";
+
+/// The shortest gap between two Nominatim requests; its usage policy allows one per second.
+pub const NOMINATIM_INTERVAL: Duration = Duration::from_secs(1);
+
+/// The most venues geocoded in one run, so a first run with many venues stays short.
+pub const GEOCODE_LIMIT: usize = 25;
+
+/// What geocoding searches are narrowed to when the config file doesn't say.
+pub const DEFAULT_NEAR: &str = "Nashville, TN";
+
+/// A point on the Earth, in decimal degrees.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Coordinates {
+    pub lat: f64,
+    pub lng: f64,
+}
+
+impl Coordinates {
+    /// Creates coordinates after checking they are on the globe.
+    ///
+    /// # Returns
+    ///
+    /// The coordinates, or a message if the latitude or longitude is out of range.
+    pub fn new(lat: f64, lng: f64) -> Result<Coordinates, String> {
+        if !(-90.0..=90.0).contains(&lat) {
+            return Err(format!("latitude {} is outside -90 to 90", lat));
+        }
+        if !(-180.0..=180.0).contains(&lng) {
+            return Err(format!("longitude {} is outside -180 to 180", lng));
+        }
+        Ok(Coordinates { lat, lng })
+    }
+
+    /// Returns the great-circle distance to another point, in miles, by the haversine formula.
+    pub fn miles_to(self, other: Coordinates) -> f64 {
+        let (lat1, lat2) = (self.lat.to_radians(), other.lat.to_radians());
+        let d_lat = lat2 - lat1;
+        let d_lng = (other.lng - self.lng).to_radians();
+        let a = (d_lat / 2.0).sin().powi(2) + lat1.cos() * lat2.cos() * (d_lng / 2.0).sin().powi(2);
+        2.0 * EARTH_RADIUS_MILES * a.sqrt().asin()
+    }
+}
+
+/// A distance as given to `--within`, kept in miles.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Distance {
+    pub miles: f64,
+}
+
+/// Parses `5mi`, `5 miles`, `8km`, or a bare number of miles.
+impl FromStr for Distance {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let text = s.trim().to_lowercase();
+        let split = text
+            .find(|c: char| !(c.is_ascii_digit() || c == '.'))
+            .unwrap_or(text.len());
+        let (number, unit) = text.split_at(split);
+        let error = || format!("invalid distance '{}' (expected e.g. 5mi or 8km)", s);
+        let amount: f64 = number.parse().map_err(|_| error())?;
+        let miles = match unit.trim() {
+            "" | "mi" | "mile" | "miles" => amount,
+            "km" | "kilometre" | "kilometres" | "kilometer" | "kilometers" => amount / KM_PER_MILE,
+            _ => return Err(error()),
+        };
+        if miles <= 0.0 {
+            return Err(error());
+        }
+        Ok(Distance { miles })
+    }
+}
+
+/// Formats a distance in miles for the listing, e.g. `2.3 mi`.
+pub fn format_distance(miles: f64) -> String {
+    format!("{:.1} mi", miles)
+}
+
+/// Venue coordinates saved from earlier geocoding, including venues that couldn't be found so
+/// they aren't looked up again.
+#[derive(Debug, Clone, PartialEq)]
+pub struct GeoCache {
+    path: PathBuf,
+    /// Coordinates by folded venue name; `None` for venues Nominatim didn't find.
+    entries: BTreeMap<String, Option<Coordinates>>,
+    changed: bool,
+}
+
+impl GeoCache {
+    /// Loads the cache at a path; a missing or unreadable file is an empty cache.
+    pub fn load(path: PathBuf) -> GeoCache {
+        let entries = fs::read_to_string(&path)
+            .ok()
+            .and_then(|text| serde_json::from_str::<Value>(&text).ok())
+            .and_then(|json| {
+                json.as_object().map(|venues| {
+                    venues
+                        .iter()
+                        .map(|(venue, at)| (venue.clone(), coordinates_from_json(at)))
+                        .collect()
+                })
+            })
+            .unwrap_or_default();
+        GeoCache {
+            path,
+            entries,
+            changed: false,
+        }
+    }
+
+    /// Loads the cache in the default data directory.
+    pub fn open_default() -> GeoCache {
+        GeoCache::load(snapshot::data_dir().join("geocache.json"))
+    }
+
+    /// Returns what the cache knows about a venue: `None` if it was never looked up, and
+    /// `Some(None)` if it was looked up and not found.
+    pub fn get(&self, venue: &str) -> Option<Option<Coordinates>> {
+        self.entries.get(&normalize_key(venue.trim())).copied()
+    }
+
+    /// Records the result of looking up a venue.
+    pub fn insert(&mut self, venue: &str, coordinates: Option<Coordinates>) {
+        self.entries
+            .insert(normalize_key(venue.trim()), coordinates);
+        self.changed = true;
+    }
+
+    /// Writes the cache back to disk, if anything was added since it was loaded.
+    pub fn save(&mut self) -> io::Result<()> {
+        if !self.changed {
+            return Ok(());
+        }
+        if let Some(dir) = self.path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        let venues: Map<String, Value> = self
+            .entries
+            .iter()
+            .map(|(venue, at)| {
+                (
+                    venue.clone(),
+                    at.map_or(Value::Null, |at| json!({ "lat": at.lat, "lng": at.lng })),
+                )
+            })
+            .collect();
+        fs::write(&self.path, Value::Object(venues).to_string())?;
+        self.changed = false;
+        Ok(())
+    }
+}
+
+fn coordinates_from_json(json: &Value) -> Option<Coordinates> {
+    Coordinates::new(json["lat"].as_f64()?, json["lng"].as_f64()?).ok()
+}
+
+/// Looks venues up with the Nominatim API, no faster than its usage policy allows.
+#[derive(Debug)]
+pub struct Geocoder<'a> {
+    client: &'a WebClient,
+    base_url: String,
+    /// Added to every search, e.g. `Nashville, TN`, so venue names find the local venue.
+    near: String,
+    interval: Duration,
+    last_request: Option<Instant>,
+    /// How many more requests may be made this run.
+    remaining: usize,
+}
+
+impl<'a> Geocoder<'a> {
+    /// Creates a geocoder sending requests to `base_url` (normally `NOMINATIM_URL`).
+    pub fn new(
+        client: &'a WebClient,
+        base_url: &str,
+        near: &str,
+        interval: Duration,
+        limit: usize,
+    ) -> Geocoder<'a> {
+        Geocoder {
+            client,
+            base_url: base_url.trim_end_matches('/').to_string(),
+            near: near.to_string(),
+            interval,
+            last_request: None,
+            remaining: limit,
+        }
+    }
+
+    /// Returns `true` once this run's limit of requests is used up.
+    pub fn exhausted(&self) -> bool {
+        self.remaining == 0
+    }
+
+    /// Looks a venue up, waiting first if the previous request was too recent.
+    ///
+    /// # Returns
+    ///
+    /// `Ok(Some(..))` with the venue's coordinates, `Ok(None)` if Nominatim doesn't know it,
+    /// or the error if the request failed.
+    pub fn locate(&mut self, venue: &str) -> Result<Option<Coordinates>, FetchError> {
+        if let Some(last) = self.last_request {
+            thread::sleep(wait_time(last, Instant::now(), self.interval));
+        }
+        self.remaining -= 1;
+        self.last_request = Some(Instant::now());
+        let body = self
+            .client
+            .fetch_url(&search_url(&self.base_url, venue, &self.near))?;
+        Ok(parse_search_results(&body))
+    }
+}
+
+/// Returns how long to wait before the next request, given when the last one was made.
+fn wait_time(last: Instant, now: Instant, interval: Duration) -> Duration {
+    interval.saturating_sub(now.saturating_duration_since(last))
+}
+
+/// Builds the Nominatim search URL for a venue.
+fn search_url(base_url: &str, venue: &str, near: &str) -> String {
+    let query = if near.is_empty() {
+        venue.to_string()
+    } else {
+        format!("{}, {}", venue, near)
+    };
+    Url::parse_with_params(
+        &format!("{}/search", base_url),
+        &[("format", "json"), ("limit", "1"), ("q", query.as_str())],
+    )
+    .map(|url| url.to_string())
+    .unwrap_or_default()
+}
+
+/// Reads the first result of a Nominatim search, whose coordinates are strings.
+fn parse_search_results(json: &str) -> Option<Coordinates> {
+    let results: Value = serde_json::from_str(json).ok()?;
+    let first = results.as_array()?.first()?;
+    let number = |key: &str| first[key].as_str()?.parse::<f64>().ok();
+    Coordinates::new(number("lat")?, number("lon")?).ok()
+}
+
+/// Finds venue coordinates from the config file, the cache, and, if enabled, Nominatim.
+#[derive(Debug)]
+pub struct Locator<'a> {
+    /// The config file's `[venue_coordinates]`, by folded venue name.
+    known: BTreeMap<String, Coordinates>,
+    cache: GeoCache,
+    geocoder: Option<Geocoder<'a>>,
+    /// The first geocoding error, reported once after the run.
+    pub error: Option<FetchError>,
+    /// How many venues weren't looked up because the geocoding limit was reached.
+    pub deferred: usize,
+}
+
+impl<'a> Locator<'a> {
+    /// Creates a locator; without a geocoder only known and cached venues are found.
+    pub fn new(
+        known: &[(String, Coordinates)],
+        cache: GeoCache,
+        geocoder: Option<Geocoder<'a>>,
+    ) -> Locator<'a> {
+        let known = known
+            .iter()
+            .map(|(venue, at)| (normalize_key(venue.trim()), *at))
+            .collect();
+        Locator {
+            known,
+            cache,
+            geocoder,
+            error: None,
+            deferred: 0,
+        }
+    }
+
+    /// Returns a venue's coordinates, geocoding it (and caching the answer) if necessary.
+    ///
+    /// After the first failed request no more are made, so an outage doesn't stall the run.
+    /// Venues left over once the limit is reached are looked up on a later run.
+    pub fn locate(&mut self, venue: &str) -> Option<Coordinates> {
+        if let Some(at) = self.known.get(&normalize_key(venue.trim())) {
+            return Some(*at);
+        }
+        if let Some(cached) = self.cache.get(venue) {
+            log::debug!("geocoding cache hit for '{}'", venue);
+            return cached;
+        }
+        let geocoder = self.geocoder.as_mut().filter(|_| self.error.is_none())?;
+        if geocoder.exhausted() {
+            self.deferred += 1;
+            return None;
+        }
+        log::debug!("geocoding '{}'", venue);
+        match geocoder.locate(venue) {
+            Ok(found) => {
+                self.cache.insert(venue, found);
+                found
+            }
+            Err(e) => {
+                self.error = Some(e);
+                None
+            }
+        }
+    }
+
+    /// Saves anything geocoded this run to the cache file.
+    pub fn save(&mut self) -> io::Result<()> {
+        self.cache.save()
+    }
+}
+
+/// Sets each event's distance from home, for events whose venue can be located.
+///
+/// # Arguments
+///
+/// * `events` - The events to annotate.
+/// * `home` - Where distances are measured from.
+/// * `locate` - Finds a venue's coordinates.
+pub fn annotate_distances(
+    events: &mut [ProcessedEvent],
+    home: Coordinates,
+    mut locate: impl FnMut(&str) -> Option<Coordinates>,
+) {
+    for event in events {
+        event.distance = event
+            .location
+            .as_deref()
+            .filter(|venue| !venue.trim().is_empty())
+            .and_then(&mut locate)
+            .map(|at| home.miles_to(at));
+    }
+}
+
+/// Drops events further from home than a distance.
+///
+/// # Arguments
+///
+/// * `events` - The events, after `annotate_distances`.
+/// * `within` - The furthest distance to keep.
+///
+/// # Returns
+///
+/// The events kept (including those whose distance isn't known), how many were dropped, and
+/// how many were kept without a known distance.
+pub fn filter_within(
+    events: Vec<ProcessedEvent>,
+    within: Distance,
+) -> (Vec<ProcessedEvent>, usize, usize) {
+    let before = events.len();
+    let kept: Vec<ProcessedEvent> = events
+        .into_iter()
+        .filter(|event| event.distance.is_none_or(|miles| miles <= within.miles))
+        .collect();
+    let unknown = kept.iter().filter(|event| event.distance.is_none()).count();
+    let dropped = before - kept.len();
+    (kept, dropped, unknown)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::web_requests::NetworkPolicy;
+    use mockito::{mock, server_url};
+    use std::env;
+
+    const EAST_NASHVILLE: Coordinates = Coordinates {
+        lat: 36.1780,
+        lng: -86.7510,
+    };
+    const RYMAN: Coordinates = Coordinates {
+        lat: 36.1612,
+        lng: -86.7785,
+    };
+
+    fn temp_cache(name: &str) -> GeoCache {
+        let path = env::temp_dir().join(format!(
+            "event_finder_geocache_{}_{}.json",
+            name,
+            std::process::id()
+        ));
+        let _ = fs::remove_file(&path);
+        GeoCache::load(path)
+    }
+
+    fn at_venue(name: &str, venue: Option<&str>) -> ProcessedEvent {
+        ProcessedEvent {
+            name: name.to_string(),
+            location: venue.map(str::to_string),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_miles_to() {
+        assert!(EAST_NASHVILLE.miles_to(EAST_NASHVILLE).abs() < 1e-9);
+        let ryman = EAST_NASHVILLE.miles_to(RYMAN);
+        assert!((ryman - 1.95).abs() < 0.05, "{}", ryman);
+        assert!((ryman - RYMAN.miles_to(EAST_NASHVILLE)).abs() < 1e-9);
+        // Nashville to Memphis is about 197 miles as the crow flies
+        let memphis = Coordinates::new(35.1495, -90.0490).unwrap();
+        assert!((EAST_NASHVILLE.miles_to(memphis) - 197.0).abs() < 3.0);
+        assert!(Coordinates::new(91.0, 0.0).is_err());
+        assert!(Coordinates::new(0.0, -181.0).is_err());
+    }
+
+    #[test]
+    fn test_parse_distance() {
+        assert_eq!("5mi".parse::<Distance>(), Ok(Distance { miles: 5.0 }));
+        assert_eq!("2.5 miles".parse::<Distance>(), Ok(Distance { miles: 2.5 }));
+        assert_eq!("3".parse::<Distance>(), Ok(Distance { miles: 3.0 }));
+        assert!(("8km".parse::<Distance>().unwrap().miles - 4.97).abs() < 0.01);
+        assert!("0mi".parse::<Distance>().is_err());
+        assert!("far".parse::<Distance>().is_err());
+        assert!("5 leagues".parse::<Distance>().is_err());
+        assert_eq!(format_distance(1.95), "1.9 mi");
+    }
+
+    #[test]
+    fn test_cache_round_trip() {
+        let mut cache = temp_cache("round_trip");
+        assert_eq!(cache.get("Ryman Auditorium"), None);
+
+        cache.insert("Ryman Auditorium", Some(RYMAN));
+        cache.insert("Nowhere Bar", None);
+        cache.save().unwrap();
+
+        let loaded = GeoCache::load(cache.path.clone());
+        assert_eq!(loaded.get(" ryman auditorium "), Some(Some(RYMAN)));
+        assert_eq!(loaded.get("Nowhere Bar"), Some(None));
+        assert_eq!(loaded.get("The Basement"), None);
+        let _ = fs::remove_file(&cache.path);
+    }
+
+    #[test]
+    fn test_cache_ignores_corrupt_file() {
+        let cache = temp_cache("corrupt");
+        fs::write(&cache.path, "not json").unwrap();
+
+        assert_eq!(
+            GeoCache::load(cache.path.clone()).get("Ryman Auditorium"),
+            None
+        );
+        let _ = fs::remove_file(&cache.path);
+    }
+
+    #[test]
+    fn test_wait_time() {
+        let start = Instant::now();
+        let second = Duration::from_secs(1);
+
+        assert_eq!(wait_time(start, start, second), second);
+        assert_eq!(
+            wait_time(start, start + Duration::from_millis(300), second),
+            Duration::from_millis(700)
+        );
+        assert_eq!(
+            wait_time(start, start + Duration::from_secs(5), second),
+            Duration::ZERO
+        );
+    }
+
+    #[test]
+    fn test_search_url_and_results() {
+        assert_eq!(search_url("https://nominatim.example", "The Basement", "Nashville, TN"),
+            "https://nominatim.example/search?format=json&limit=1&q=The+Basement%2C+Nashville%2C+TN");
+        assert_eq!(
+            parse_search_results(
+                r#"[{"lat": "36.1612", "lon": "-86.7785", "display_name": "Ryman"}]"#
+            ),
+            Some(RYMAN)
+        );
+        assert_eq!(parse_search_results("[]"), None);
+        assert_eq!(parse_search_results("<html>"), None);
+    }
+
+    #[test]
+    fn test_locator_prefers_config_then_cache_then_geocoder() {
+        let _m = mock("GET", "/locator/search")
+            .match_query(mockito::Matcher::UrlEncoded(
+                "q".into(),
+                "The Basement, Nashville, TN".into(),
+            ))
+            .with_body(r#"[{"lat": "36.1300", "lon": "-86.7600"}]"#)
+            .expect(1)
+            .create();
+        let client = WebClient::new(NetworkPolicy::Online).unwrap();
+        let mut cache = temp_cache("locator");
+        cache.insert("Ryman", Some(EAST_NASHVILLE));
+        let geocoder = Geocoder::new(
+            &client,
+            &format!("{}/locator", server_url()),
+            DEFAULT_NEAR,
+            Duration::ZERO,
+            GEOCODE_LIMIT,
+        );
+        let mut locator = Locator::new(&[("Ryman".to_string(), RYMAN)], cache, Some(geocoder));
+
+        assert_eq!(locator.locate("ryman"), Some(RYMAN));
+        assert_eq!(
+            locator.locate("The Basement"),
+            Some(Coordinates {
+                lat: 36.13,
+                lng: -86.76
+            })
+        );
+        // Answered from the cache the second time
+        assert_eq!(
+            locator.locate("The Basement"),
+            Some(Coordinates {
+                lat: 36.13,
+                lng: -86.76
+            })
+        );
+        assert!(locator.error.is_none());
+        _m.assert();
+        let _ = fs::remove_file(&locator.cache.path);
+    }
+
+    #[test]
+    fn test_locator_defers_venues_past_the_limit() {
+        let _m = mock("GET", "/limit/search")
+            .match_query(mockito::Matcher::Any)
+            .with_body("[]")
+            .expect(1)
+            .create();
+        let client = WebClient::new(NetworkPolicy::Online).unwrap();
+        let geocoder = Geocoder::new(
+            &client,
+            &format!("{}/limit", server_url()),
+            "",
+            Duration::ZERO,
+            1,
+        );
+        let mut locator = Locator::new(&[], temp_cache("limit"), Some(geocoder));
+
+        assert_eq!(locator.locate("Nowhere Bar"), None);
+        assert_eq!(locator.locate("Other Bar"), None);
+
+        assert_eq!(locator.deferred, 1);
+        assert_eq!(locator.cache.get("Nowhere Bar"), Some(None));
+        assert_eq!(locator.cache.get("Other Bar"), None);
+        _m.assert();
+    }
+
+    #[test]
+    fn test_locator_without_geocoder_stays_offline() {
+        let mut locator = Locator::new(&[], temp_cache("offline"), None);
+
+        assert_eq!(locator.locate("The Basement"), None);
+        assert!(!locator.cache.changed);
+    }
+
+    #[test]
+    fn test_annotate_and_filter_within() {
+        let mut events = vec![
+            at_venue("Near", Some("Ryman")),
+            at_venue("Far", Some("Memphis Arena")),
+            at_venue("Unknown venue", Some("Somewhere")),
+            at_venue("No venue", None),
+        ];
+        let memphis = Coordinates::new(35.1495, -90.0490).unwrap();
+        annotate_distances(&mut events, EAST_NASHVILLE, |venue| match venue {
+            "Ryman" => Some(RYMAN),
+            "Memphis Arena" => Some(memphis),
+            _ => None,
+        });
+
+        let (kept, dropped, unknown) = filter_within(events, Distance { miles: 5.0 });
+
+        let names: Vec<&str> = kept.iter().map(|e| e.name.as_str()).collect();
+        assert_eq!(names, vec!["Near", "Unknown venue", "No venue"]);
+        assert_eq!((dropped, unknown), (1, 2));
+        assert!((kept[0].distance.unwrap() - 1.95).abs() < 0.05);
+    }
+}
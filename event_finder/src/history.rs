@@ -0,0 +1,417 @@
+//! # History Module
+//!
+//! This module keeps a history of every event fetched, for the `history` subcommand. Each
+//! online fetch upserts its events, keyed by their stable `ProcessedEvent::id`: a new event is
+//! inserted with the time it was first fetched, and one fetched before is updated in place,
+//! keeping when it was first seen and counting the fetches it was in.
+//!
+//! The history is one JSON document, `history.json` in the data directory unless the config
+//! file's `history_file` names another file, stamped with `SCHEMA_VERSION`. Runs at the same
+//! time take turns through a lock file, as `seen` does.
+
+use std::collections::HashMap;
+use std::fs;
+use std::io;
+use std::path::PathBuf;
+
+use chrono::{Duration, NaiveDate, NaiveDateTime};
+use serde_json::{json, Value};
+
+use crate::data_processing::{self, ProcessedEvent};
+use crate::output;
+use crate::snapshot::{self, FileLock};
+
+/// The version of the history file's layout, raised whenever it changes.
+pub const SCHEMA_VERSION: u64 = 1;
+
+const TIME_FORMAT: &str = "%Y-%m-%dT%H:%M:%S";
+
+/// An event in the history, as it was when last fetched.
+#[derive(Debug, Clone, PartialEq)]
+pub struct HistoryEntry {
+    pub event: ProcessedEvent,
+    /// When the event was first fetched.
+    pub first_seen: NaiveDateTime,
+    /// When it was most recently fetched.
+    pub last_seen: NaiveDateTime,
+    /// How many fetches it was in.
+    pub fetches: u64,
+}
+
+/// How many events an upsert inserted, and how many it updated.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Upserted {
+    pub inserted: usize,
+    pub updated: usize,
+}
+
+/// The history file.
+#[derive(Debug, Clone, PartialEq)]
+pub struct HistoryStore {
+    path: PathBuf,
+}
+
+impl HistoryStore {
+    /// Creates a store kept in the given file.
+    pub fn new(path: PathBuf) -> HistoryStore {
+        HistoryStore { path }
+    }
+
+    /// Creates a store in `history.json` in the default data directory.
+    pub fn open_default() -> HistoryStore {
+        HistoryStore::new(snapshot::data_dir().join("history.json"))
+    }
+
+    /// Loads the whole history, oldest first.
+    ///
+    /// # Returns
+    ///
+    /// The entries, none if the file doesn't exist yet, or an error if it can't be read or was
+    /// written by a newer version; the history isn't thrown away like a cache would be.
+    pub fn load(&self) -> io::Result<Vec<HistoryEntry>> {
+        let text = match fs::read_to_string(&self.path) {
+            Ok(text) => text,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(e) => return Err(e),
+        };
+        let invalid = |message: String| {
+            io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("{}: {}", self.path.display(), message),
+            )
+        };
+        let json: Value = serde_json::from_str(&text).map_err(|e| invalid(e.to_string()))?;
+        match json["version"].as_u64() {
+            Some(SCHEMA_VERSION) => {}
+            Some(version) if version > SCHEMA_VERSION => {
+                return Err(invalid(format!(
+                    "written by a newer event_finder (schema {}, this one reads {})",
+                    version, SCHEMA_VERSION
+                )));
+            }
+            _ => return Err(invalid("not an event_finder history file".to_string())),
+        }
+        let entries = json["events"]
+            .as_array()
+            .ok_or_else(|| invalid("no events list".to_string()))?;
+        Ok(entries.iter().filter_map(history_entry).collect())
+    }
+
+    /// Records a fetch of events at `now`.
+    ///
+    /// # Returns
+    ///
+    /// How many events were inserted and how many updated, or the error reading or saving the
+    /// history.
+    pub fn upsert(&self, events: &[ProcessedEvent], now: NaiveDateTime) -> io::Result<Upserted> {
+        let _lock = FileLock::acquire(&self.path)?;
+        let mut entries = self.load()?;
+        let mut index: HashMap<String, usize> = entries
+            .iter()
+            .enumerate()
+            .map(|(i, entry)| (entry.event.id(), i))
+            .collect();
+        let mut upserted = Upserted::default();
+        for event in events {
+            match index.get(&event.id()) {
+                Some(&i) => {
+                    let entry = &mut entries[i];
+                    entry.event = event.clone();
+                    entry.last_seen = now;
+                    entry.fetches += 1;
+                    upserted.updated += 1;
+                }
+                None => {
+                    index.insert(event.id(), entries.len());
+                    entries.push(HistoryEntry {
+                        event: event.clone(),
+                        first_seen: now,
+                        last_seen: now,
+                        fetches: 1,
+                    });
+                    upserted.inserted += 1;
+                }
+            }
+        }
+        self.save(&entries)?;
+        Ok(upserted)
+    }
+
+    fn save(&self, entries: &[HistoryEntry]) -> io::Result<()> {
+        let events: Vec<Value> = entries
+            .iter()
+            .map(|entry| {
+                json!({
+                    "event": entry.event.to_json(),
+                    "first_seen": entry.first_seen.format(TIME_FORMAT).to_string(),
+                    "last_seen": entry.last_seen.format(TIME_FORMAT).to_string(),
+                    "fetches": entry.fetches,
+                })
+            })
+            .collect();
+        let json = json!({ "version": SCHEMA_VERSION, "events": events });
+        output::save_output(&self.path, json.to_string().as_bytes(), true)
+    }
+}
+
+/// Reads one entry of the history, or `None` if it is malformed.
+fn history_entry(entry: &Value) -> Option<HistoryEntry> {
+    let time = |key: &str| NaiveDateTime::parse_from_str(entry[key].as_str()?, TIME_FORMAT).ok();
+    Some(HistoryEntry {
+        event: ProcessedEvent::from_json(&entry["event"])?,
+        first_seen: time("first_seen")?,
+        last_seen: time("last_seen")?,
+        fetches: entry["fetches"].as_u64().unwrap_or(1),
+    })
+}
+
+/// A point in time a history query reaches back to: a day, or a span before now.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Since {
+    /// The start of a day, e.g. `2024-05-01`.
+    Date(NaiveDate),
+    /// A span back from now, e.g. `7d`.
+    Ago(Duration),
+}
+
+impl Since {
+    /// Parses `YYYY-MM-DD` or a duration such as `7d` or `12h`.
+    pub fn parse(text: &str) -> Result<Since, String> {
+        if let Ok(date) = NaiveDate::parse_from_str(text.trim(), "%Y-%m-%d") {
+            return Ok(Since::Date(date));
+        }
+        data_processing::parse_duration(text)
+            .map(Since::Ago)
+            .map_err(|_| {
+                format!(
+                    "invalid time '{}' (expected YYYY-MM-DD or a span such as 7d)",
+                    text
+                )
+            })
+    }
+
+    /// Returns the moment this reaches back to from `now`.
+    pub fn resolve(self, now: NaiveDateTime) -> NaiveDateTime {
+        match self {
+            Since::Date(date) => date.and_hms_opt(0, 0, 0).unwrap_or(now),
+            Since::Ago(span) => now - span,
+        }
+    }
+}
+
+/// What the `history` subcommand looks for; every part given must match.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct HistoryQuery {
+    /// Part of the venue's name, compared as `normalize_key` would.
+    pub venue: Option<String>,
+    /// Only events fetched at or after this.
+    pub seen_since: Option<Since>,
+    /// Only events first fetched at or after this.
+    pub first_seen_since: Option<Since>,
+}
+
+impl HistoryQuery {
+    /// Whether the query asks for anything.
+    pub fn is_empty(&self) -> bool {
+        self.venue.is_none() && self.seen_since.is_none() && self.first_seen_since.is_none()
+    }
+}
+
+/// Finds the history's events matching a query.
+///
+/// # Arguments
+///
+/// * `entries` - The history, from `HistoryStore::load`.
+/// * `query` - What to look for.
+/// * `now` - The time `Since::Ago` spans reach back from.
+///
+/// # Returns
+///
+/// The matching events, as last fetched, in the history's order.
+pub fn query(
+    entries: &[HistoryEntry],
+    query: &HistoryQuery,
+    now: NaiveDateTime,
+) -> Vec<ProcessedEvent> {
+    let venue = query.venue.as_deref().map(data_processing::normalize_key);
+    let seen_since = query.seen_since.map(|since| since.resolve(now));
+    let first_seen_since = query.first_seen_since.map(|since| since.resolve(now));
+    entries
+        .iter()
+        .filter(|entry| {
+            let location = entry
+                .event
+                .location
+                .as_deref()
+                .map(data_processing::normalize_key)
+                .unwrap_or_default();
+            venue
+                .as_deref()
+                .is_none_or(|venue| location.contains(venue))
+                && seen_since.is_none_or(|since| entry.last_seen >= since)
+                && first_seen_since.is_none_or(|since| entry.first_seen >= since)
+        })
+        .map(|entry| entry.event.clone())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::env;
+
+    fn temp_store(name: &str) -> HistoryStore {
+        let dir = env::temp_dir().join(format!(
+            "event_finder_history_{}_{}",
+            name,
+            std::process::id()
+        ));
+        let _ = fs::remove_dir_all(&dir);
+        HistoryStore::new(dir.join("history.json"))
+    }
+
+    fn event(name: &str, url: &str, location: &str) -> ProcessedEvent {
+        ProcessedEvent {
+            name: name.to_string(),
+            url: url.to_string(),
+            location: Some(location.to_string()),
+            ..Default::default()
+        }
+    }
+
+    fn at(day: i64) -> NaiveDateTime {
+        NaiveDate::from_ymd_opt(2026, 5, 1)
+            .unwrap()
+            .and_hms_opt(9, 0, 0)
+            .unwrap()
+            + Duration::days(day)
+    }
+
+    #[test]
+    fn test_upsert_inserts_then_updates() {
+        let store = temp_store("upsert");
+        let first = vec![
+            event("Bluegrass Night", "https://example.com/1", "Station Inn"),
+            event("Jazz Jam", "https://example.com/2", "Rudy's"),
+        ];
+
+        assert_eq!(
+            store.upsert(&first, at(0)).unwrap(),
+            Upserted {
+                inserted: 2,
+                updated: 0
+            }
+        );
+
+        let renamed = ProcessedEvent {
+            price: Some("$15.00".to_string()),
+            ..first[0].clone()
+        };
+        let second = vec![
+            renamed.clone(),
+            event("Open Mic", "https://example.com/3", "The 5 Spot"),
+        ];
+        assert_eq!(
+            store.upsert(&second, at(3)).unwrap(),
+            Upserted {
+                inserted: 1,
+                updated: 1
+            }
+        );
+
+        let entries = store.load().unwrap();
+        assert_eq!(entries.len(), 3);
+        assert_eq!(
+            entries[0],
+            HistoryEntry {
+                event: renamed,
+                first_seen: at(0),
+                last_seen: at(3),
+                fetches: 2
+            }
+        );
+        assert_eq!((entries[1].last_seen, entries[1].fetches), (at(0), 1));
+        assert_eq!(entries[2].first_seen, at(3));
+        let _ = fs::remove_dir_all(store.path.parent().unwrap());
+    }
+
+    #[test]
+    fn test_query_by_venue_and_time() {
+        let store = temp_store("query");
+        store
+            .upsert(
+                &[event("Old Show", "https://example.com/1", "The Ryman")],
+                at(0),
+            )
+            .unwrap();
+        store
+            .upsert(
+                &[
+                    event("New Show", "https://example.com/2", "Ryman Auditorium"),
+                    event("Jam", "https://example.com/3", "Rudy's"),
+                ],
+                at(20),
+            )
+            .unwrap();
+        let entries = store.load().unwrap();
+        let names = |q: HistoryQuery| {
+            query(&entries, &q, at(21))
+                .into_iter()
+                .map(|e| e.name)
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(
+            names(HistoryQuery {
+                venue: Some("RYMAN".to_string()),
+                ..Default::default()
+            }),
+            vec!["Old Show", "New Show"]
+        );
+        let last_week = HistoryQuery {
+            venue: Some("ryman".to_string()),
+            seen_since: Some(Since::Ago(Duration::days(7))),
+            ..Default::default()
+        };
+        assert_eq!(names(last_week), vec!["New Show"]);
+        let this_month = HistoryQuery {
+            first_seen_since: Some(Since::Date(NaiveDate::from_ymd_opt(2026, 5, 10).unwrap())),
+            ..Default::default()
+        };
+        assert_eq!(names(this_month), vec!["New Show", "Jam"]);
+        assert_eq!(names(HistoryQuery::default()).len(), 3);
+        let _ = fs::remove_dir_all(store.path.parent().unwrap());
+    }
+
+    #[test]
+    fn test_unreadable_history_is_kept() {
+        let store = temp_store("unreadable");
+        fs::create_dir_all(store.path.parent().unwrap()).unwrap();
+        fs::write(&store.path, "not json").unwrap();
+
+        assert!(store.load().is_err());
+        assert!(store
+            .upsert(&[event("Show", "https://example.com/1", "Ryman")], at(0))
+            .is_err());
+        assert_eq!(fs::read_to_string(&store.path).unwrap(), "not json");
+
+        fs::write(&store.path, r#"{"version": 2, "events": []}"#).unwrap();
+        assert!(store
+            .load()
+            .unwrap_err()
+            .to_string()
+            .contains("written by a newer event_finder (schema 2, this one reads 1)"));
+        let _ = fs::remove_dir_all(store.path.parent().unwrap());
+    }
+
+    #[test]
+    fn test_since_parse() {
+        assert_eq!(
+            Since::parse("2024-05-01"),
+            Ok(Since::Date(NaiveDate::from_ymd_opt(2024, 5, 1).unwrap()))
+        );
+        assert_eq!(Since::parse("7d"), Ok(Since::Ago(Duration::days(7))));
+        assert_eq!(Since::parse("7d").unwrap().resolve(at(7)), at(0));
+        assert!(Since::parse("last month").is_err());
+    }
+}
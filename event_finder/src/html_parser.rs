@@ -1,193 +1,3916 @@
-//! # HTML Parser Module
-//!
-//! This module provides functionality for parsing HTML content to extract event data.
-//! It supports parsing both standard HTML structures and JSON data embedded within `<script>` tags.
-//! The module defines structures for site-specific configuration (`SiteConfig`) and event data (`Event`),
-//! and includes functions for parsing and URL resolution.
-
-use scraper::{Html, Selector};
-use serde_json::Value;
-use url::Url;
-
-/// Site-specific configuration for HTML parsing.
-#[derive(Debug, Clone, PartialEq)]
-pub struct SiteConfig {
-    pub event_selector: String,
-    pub name_selector: String,
-    pub start_date_selector: String,
-    pub end_date_selector: String,
-    pub location_selector: String,
-    pub url: String,
-}
-
-/// Parses HTML content to extract event data based on the provided site configuration.
-///
-/// # Arguments
-///
-/// * `html` - A string slice that holds the HTML content to be parsed.
-/// * `config` - Site configuration specifying CSS selectors for different event components.
-/// * `base_url` - The base URL of the site for resolving relative URLs.
-///
-/// # Returns
-///
-/// A vector of `Event` instances extracted from the HTML content.
-pub fn parse_html(html: &str, config: &SiteConfig, base_url: &str) -> Vec<Event> {
-    let mut events = Vec::new();
-
-    // Parse the HTML document
-    let document = Html::parse_document(html);
-
-    // Create a Selector for the HTML elements that contain the event data
-    // (Adjust the selector based on the actual HTML structure)
-    if &config.event_selector == "script[type='application/ld+json']" {
-        if let Some(script_content) = document.select(&Selector::parse(&config.event_selector).unwrap()).next() {
-            let json_content = script_content.inner_html();
-            // Inside the JSON parsing logic
-            if let Ok(json) = serde_json::from_str::<Value>(&json_content) {
-                // Assuming the JSON structure is an array of events or a single event object
-                let events_iter: Box<dyn Iterator<Item = &Value>> = match json.as_array() {
-                    Some(array) => Box::new(array.iter()), // Array of objects, create an iterator over the array
-                    None => Box::new(std::iter::once(&json)), // Single object, create an iterator with one element
-                };
-            
-                for event_json in events_iter {
-                    let name = event_json["name"].as_str().unwrap_or_default().to_string();
-                    let start_date = event_json["startDate"].as_str().unwrap_or_default().to_string();
-                    let end_date = event_json["endDate"].as_str().unwrap_or_default().to_string();
-                    let location = event_json["location"]["name"].as_str().unwrap_or_default().to_string();
-                    let url = event_json["url"].as_str().unwrap_or_default().to_string();
-            
-                    // Create an Event object and add it to the events vector
-                    let event = Event {
-                        name,
-                        start_date,
-                        end_date,
-                        location,
-                        url,
-                    };
-                    events.push(event);
-                }
-            }
-
-        }
-    } else {
-        let event_selector = Selector::parse(&config.event_selector).unwrap();
-        // Iterate over each event element
-        for event_element in document.select(&event_selector) {
-            // Extract event details like name, date, location, etc.
-            // (Adjust the selectors and extraction logic based on the actual HTML structure)
-            let name = event_element.select(&Selector::parse(&config.name_selector).unwrap())
-                                    .next()
-                                    .map(|e| e.inner_html())
-                                    .unwrap_or_default();
-            let start_date = event_element.select(&Selector::parse(&config.start_date_selector).unwrap())
-                                    .next()
-                                    .map(|e| e.inner_html())
-                                    .unwrap_or_default();
-            let end_date = event_element.select(&Selector::parse(&config.end_date_selector).unwrap())
-                                    .next()
-                                    .map(|e| e.inner_html())
-                                    .unwrap_or_default();
-            let location = event_element.select(&Selector::parse(&config.location_selector).unwrap()) 
-                                    .next()
-                                    .map(|e| e.inner_html())
-                                    .unwrap_or_default();
-            let relative_url = event_element.select(&Selector::parse(&config.url).unwrap())
-                                    .next()
-                                    .and_then(|e| e.value().attr("href"))
-                                    .unwrap_or_default()
-                                    .to_string();
-
-            // Create an absolute URL based on the base URL and the relative URL
-            let url = resolve_url(base_url, &relative_url);
-
-        // Create an Event object and add it to the events vector
-        let event = Event {
-            name,
-            start_date,
-            end_date,
-            location,
-            url,
-        };
-        events.push(event);
-        }
-   }
-   events
-}
-
-/// Resolves a relative URL against a base URL.
-///
-/// # Arguments
-///
-/// * `base` - The base URL as a string slice.
-/// * `relative` - The relative URL to be resolved against the base URL.
-///
-/// # Returns
-///
-/// A `String` representing the absolute URL.
-fn resolve_url(base: &str, relative: &str) -> String {
-    let base_url = Url::parse(base).expect("Failed to parse base URL");
-    base_url.join(relative).unwrap().to_string()
-}
-
-/// Represents an event with its details extracted from HTML content.
-#[derive(Debug, Clone, PartialEq)]
-pub struct Event {
-    pub name: String,
-    pub start_date: String,
-    pub end_date: String,
-    pub location: String,
-    pub url: String,
-}
-
-
-#[cfg(test)]
-mod tests {
-    use super::*;
-
-    #[test]
-    fn test_parse_html() {
-        // Mock HTML content
-        let html = r#"
-            <div class="event">
-                <h2 class="name">Event Name</h2>
-                <span class="start-date">2023-01-01</span>
-                <span class="end-date">2023-01-02</span>
-                <span class="location">Event Location</span>
-                <a class="url" href="http://example.com/event">Event Link</a>
-            </div>
-        "#;
-
-        // Configure SiteConfig with selectors that match the mock HTML
-        let config = SiteConfig {
-            event_selector: ".event".to_string(),
-            name_selector: ".name".to_string(),
-            start_date_selector: ".start-date".to_string(),
-            end_date_selector: ".end-date".to_string(),
-            location_selector: ".location".to_string(),
-            url: ".url".to_string(),
-        };
-
-        // Base URL for resolving relative URLs
-        let base_url = "http://example.com";
-
-        // Call the parse_html function
-        let events = parse_html(html, &config, base_url);
-
-        // Expected event
-        let expected_event = Event {
-            name: "Event Name".to_string(),
-            start_date: "2023-01-01".to_string(),
-            end_date: "2023-01-02".to_string(),
-            location: "Event Location".to_string(),
-            url: "http://example.com/event".to_string(),
-        };
-
-        // Assertions
-        assert_eq!(events.len(), 1);
-        assert_eq!(events[0], expected_event);
-    }
-}
-
-
-
+//! # HTML Parser Module
+//!
+//! This module provides functionality for parsing HTML content to extract event data.
+//! It supports parsing both standard HTML structures and JSON data embedded within `<script>` tags.
+//! The module defines structures for site-specific configuration (`SiteConfig`) and event data (`Event`),
+//! and includes functions for parsing and URL resolution.
+
+use crate::error::Error;
+use std::fmt;
+use std::fs;
+use std::path::Path;
+use std::str::FromStr;
+
+use regex::Regex;
+use scraper::{ElementRef, Html, Selector};
+use serde::de::Error as _;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use serde_json::{json, Value};
+use url::Url;
+
+use crate::data_processing::{
+    clean_description, clean_text, normalize_key, truncate_at_word, Transform, TRANSFORMABLE_FIELDS,
+};
+use crate::feed;
+use crate::ics;
+use crate::logging;
+use crate::sources::Categories;
+use crate::timezone::Zone;
+
+/// The default maximum length, in characters, of a description taken from JSON-LD.
+pub const DEFAULT_DESCRIPTION_LENGTH: usize = 300;
+
+/// Shared leading or trailing text shorter than this is not treated as boilerplate.
+const MIN_BOILERPLATE_CHARS: usize = 40;
+
+/// A description that is more than this fraction boilerplate is dropped entirely.
+const MAX_BOILERPLATE_RATIO: f64 = 0.8;
+
+/// The number of listing pages fetched when a source paginates and sets no `max_pages`.
+pub const DEFAULT_MAX_PAGES: usize = 3;
+
+/// The schema.org `@type`s accepted as events in JSON-LD: `Event` and its subtypes.
+const EVENT_TYPES: &[&str] = &[
+    "Event",
+    "BusinessEvent",
+    "ChildrensEvent",
+    "ComedyEvent",
+    "CourseInstance",
+    "DanceEvent",
+    "DeliveryEvent",
+    "EducationEvent",
+    "EventSeries",
+    "ExhibitionEvent",
+    "Festival",
+    "FoodEvent",
+    "Hackathon",
+    "LiteraryEvent",
+    "MusicEvent",
+    "PublicationEvent",
+    "SaleEvent",
+    "ScreeningEvent",
+    "SocialEvent",
+    "SportsEvent",
+    "TheaterEvent",
+    "VisualArtsEvent",
+];
+
+/// Text found in bot-challenge and access-denied pages, and how to describe it to the user.
+const BLOCK_MARKERS: &[(&str, &str)] = &[
+    ("/cdn-cgi/challenge-platform/", "a Cloudflare challenge"),
+    ("_cf_chl_", "a Cloudflare challenge"),
+    (
+        "attention required! | cloudflare",
+        "a Cloudflare block page",
+    ),
+    ("captcha", "a CAPTCHA"),
+    ("access denied", "\"Access denied\""),
+    ("are you a robot", "a robot check"),
+    ("unusual traffic", "an \"unusual traffic\" notice"),
+];
+
+/// Pages shorter than this many bytes that yield no events are treated as blocked; real
+/// listing pages are far larger even when empty.
+pub(crate) const MIN_PAGE_BYTES: usize = 200;
+
+/// The selector matching JSON-LD script blocks.
+const JSON_LD_SELECTOR: &str = "script[type='application/ld+json']";
+
+/// How `parse_html` finds events in a page.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ParseStrategy {
+    /// Match event elements and their fields with the config's CSS selectors.
+    #[default]
+    Css,
+    /// Read schema.org events from JSON-LD script blocks; the config's selectors are ignored.
+    JsonLd,
+    /// Read schema.org events from microdata (`itemscope`/`itemprop`) markup; the config's
+    /// selectors are ignored.
+    Microdata,
+    /// Read events from an RSS 2.0 or Atom feed with `feed::parse_feed`; the config's
+    /// selectors are ignored.
+    Feed,
+    /// Read events from an iCalendar (`.ics`) export with `ics::parse_ics`; the config's
+    /// selectors are ignored.
+    Ics,
+    /// Read events from a JSON API response with `parse_json_api`. `event_selector` is the
+    /// dotted path to the events array and each field selector is a dotted path within an event.
+    JsonApi,
+    /// Read events from Ticketmaster's Discovery API with `ticketmaster::TicketmasterSource`;
+    /// the config's selectors are ignored.
+    Ticketmaster,
+}
+
+impl FromStr for ParseStrategy {
+    type Err = String;
+
+    /// Parses a strategy name as written in a sources file: `css`, `json-ld`, `microdata`,
+    /// `feed`, `ics`, `json-api`, or `ticketmaster`.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_lowercase().replace('_', "-").as_str() {
+            "css" => Ok(ParseStrategy::Css),
+            "json-ld" | "jsonld" => Ok(ParseStrategy::JsonLd),
+            "microdata" => Ok(ParseStrategy::Microdata),
+            "feed" | "rss" | "atom" => Ok(ParseStrategy::Feed),
+            "ics" | "ical" => Ok(ParseStrategy::Ics),
+            "json-api" => Ok(ParseStrategy::JsonApi),
+            "ticketmaster" => Ok(ParseStrategy::Ticketmaster),
+            _ => Err(format!("unknown strategy '{}' (expected css, json-ld, microdata, feed, ics, json-api, or ticketmaster)", s)),
+        }
+    }
+}
+
+/// Site-specific configuration for HTML parsing.
+///
+/// `strategy` picks how events are found. The selectors below only apply to
+/// `ParseStrategy::Css`; `description_length` applies to all strategies.
+///
+/// Each field selector may be paired with an optional `*_attr` naming an attribute to read
+/// from the matched element (e.g. `datetime` on a `<time>` tag). When the attribute is absent
+/// on the element, extraction falls back to the element's text. The URL field reads `href`
+/// unless `url_attr` says otherwise.
+///
+/// `end_date_selector` and `location_selector` are optional: leaving them as `None` means the
+/// source doesn't provide that field, and the parsed `Event` will carry `None` for it.
+///
+/// The name and location normally come from the first match of their selector. Setting
+/// `name_separator` or `location_separator` collects every match instead and joins their text
+/// with the separator, e.g. all artists on a bill as `"Band A, Band B"`.
+///
+/// `description_length` bounds descriptions taken from JSON-LD and defaults to
+/// `DEFAULT_DESCRIPTION_LENGTH`. `price_selector`, `description_selector`, and `image_selector`
+/// are optional like the end date and location; the image reads `src` unless `image_attr` says
+/// otherwise and is resolved against the base URL like event links.
+///
+/// `exclude_selector` skips event elements such as sponsored cards before any field is
+/// extracted. An element is skipped if it matches the selector itself (e.g. `.pt_col.promoted`)
+/// or if any element inside it does (e.g. `.sponsored-badge`). Skipped elements still count
+/// towards the `event_index` of warnings, so indices match the page's element order.
+///
+/// `name_regex`, `start_date_regex`, `end_date_regex`, `location_regex`, and `price_regex`
+/// post-process the extracted text: the first capture group (or the whole match, if the
+/// pattern has no groups) replaces the text. When the pattern doesn't match, the raw text is
+/// kept and a `WarningKind::PatternMismatch` warning is recorded. Patterns are checked by
+/// `SiteConfig::validate`.
+///
+/// `start_time_selector` and `end_time_selector` are for listings that show the time of day
+/// in its own element next to the date; `data_processing` combines each with its date.
+///
+/// `tags_selector` collects the text of every match inside an event element as its tags,
+/// e.g. genre or category labels.
+///
+/// `status_selector` names a badge such as "Sold out" or "Cancelled" that is read into the
+/// event's `EventStatus`; events without the badge are `EventStatus::Unknown`.
+///
+/// `date_header_selector` is for listings that put the date in a header between groups of
+/// event elements rather than inside each one. Every event element takes the text of the
+/// closest header before it in the document as its start date when `start_date_selector`
+/// finds nothing inside the element; `start_date_regex` applies to it as usual.
+///
+/// `transforms` lists, per field, the `Transform`s `data_processing::apply_transforms` runs
+/// on the cleaned text in order, e.g. `("location", vec!["remove_suffix(\" - Nashville, TN\")"])`.
+/// Transform names are checked by `SiteConfig::validate`.
+///
+/// `next_page_selector` names the link to a listing's next page; its `href` is followed for up
+/// to `max_pages` pages in total (default `DEFAULT_MAX_PAGES`). `max_events` caps how many
+/// events are parsed; `None` means no limit.
+///
+/// `default_duration_minutes` gives events without a usable end one that long after their
+/// start, e.g. 180 for concerts, so exports have an end time to write.
+///
+/// `api_key` is sent with each request to an API that needs one, such as Ticketmaster's. It
+/// isn't read from the sources file: the application sets it from the environment or the
+/// settings file before fetching.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct SiteConfig {
+    pub strategy: ParseStrategy,
+    pub event_selector: String,
+    pub name_selector: String,
+    pub start_date_selector: String,
+    pub end_date_selector: Option<String>,
+    pub location_selector: Option<String>,
+    pub url: String,
+    pub name_attr: Option<String>,
+    pub start_date_attr: Option<String>,
+    pub end_date_attr: Option<String>,
+    pub location_attr: Option<String>,
+    pub url_attr: Option<String>,
+    pub description_length: Option<usize>,
+    pub price_selector: Option<String>,
+    pub price_attr: Option<String>,
+    pub description_selector: Option<String>,
+    pub description_attr: Option<String>,
+    pub image_selector: Option<String>,
+    pub image_attr: Option<String>,
+    pub exclude_selector: Option<String>,
+    pub date_header_selector: Option<String>,
+    pub start_time_selector: Option<String>,
+    pub end_time_selector: Option<String>,
+    pub status_selector: Option<String>,
+    pub tags_selector: Option<String>,
+    pub transforms: Vec<(String, Vec<String>)>,
+    pub next_page_selector: Option<String>,
+    pub max_pages: Option<usize>,
+    pub max_events: Option<usize>,
+    pub name_regex: Option<String>,
+    pub start_date_regex: Option<String>,
+    pub end_date_regex: Option<String>,
+    pub location_regex: Option<String>,
+    pub price_regex: Option<String>,
+    pub name_separator: Option<String>,
+    pub location_separator: Option<String>,
+    pub default_duration_minutes: Option<usize>,
+    pub api_key: Option<String>,
+}
+
+impl SiteConfig {
+    /// The configured post-processing patterns, keyed by the config field that holds them.
+    fn patterns(&self) -> [(&'static str, Option<&str>); 5] {
+        [
+            ("name_regex", self.name_regex.as_deref()),
+            ("start_date_regex", self.start_date_regex.as_deref()),
+            ("end_date_regex", self.end_date_regex.as_deref()),
+            ("location_regex", self.location_regex.as_deref()),
+            ("price_regex", self.price_regex.as_deref()),
+        ]
+    }
+
+    /// Checks the whole configuration before it is used.
+    ///
+    /// Every selector the strategy uses must parse, every `*_attr` must be a plausible
+    /// attribute name, every pattern must compile, and page limits must be at least 1.
+    ///
+    /// # Returns
+    ///
+    /// `Ok(())`, or every problem found, each naming the config field it is in.
+    pub fn validate(&self) -> Result<(), Vec<ConfigError>> {
+        let mut errors = Vec::new();
+
+        let mut selectors: Vec<(&'static str, Option<&String>)> = Vec::new();
+        if self.strategy == ParseStrategy::Css {
+            selectors.extend([
+                ("event_selector", Some(&self.event_selector)),
+                ("name_selector", Some(&self.name_selector)),
+                ("start_date_selector", Some(&self.start_date_selector)),
+                ("url", Some(&self.url)),
+                ("end_date_selector", self.end_date_selector.as_ref()),
+                ("location_selector", self.location_selector.as_ref()),
+                ("price_selector", self.price_selector.as_ref()),
+                ("description_selector", self.description_selector.as_ref()),
+                ("image_selector", self.image_selector.as_ref()),
+                ("exclude_selector", self.exclude_selector.as_ref()),
+                ("date_header_selector", self.date_header_selector.as_ref()),
+                ("start_time_selector", self.start_time_selector.as_ref()),
+                ("end_time_selector", self.end_time_selector.as_ref()),
+                ("status_selector", self.status_selector.as_ref()),
+                ("tags_selector", self.tags_selector.as_ref()),
+            ]);
+        }
+        selectors.push(("next_page_selector", self.next_page_selector.as_ref()));
+        for (field, selector) in selectors {
+            if let Err(e) = compile_optional(field, selector.map(String::as_str)) {
+                errors.push(e);
+            }
+        }
+
+        let attrs = [
+            ("name_attr", &self.name_attr),
+            ("start_date_attr", &self.start_date_attr),
+            ("end_date_attr", &self.end_date_attr),
+            ("location_attr", &self.location_attr),
+            ("url_attr", &self.url_attr),
+            ("price_attr", &self.price_attr),
+            ("description_attr", &self.description_attr),
+            ("image_attr", &self.image_attr),
+        ];
+        for (field, attr) in attrs {
+            if let Some(attr) = attr {
+                let invalid = |c: char| c.is_whitespace() || "\"'<>/=".contains(c);
+                if attr.is_empty() || attr.chars().any(invalid) {
+                    errors.push(ConfigError::new(
+                        field,
+                        format!("invalid attribute name '{}'", attr),
+                    ));
+                }
+            }
+        }
+
+        for (field, pattern) in self.patterns() {
+            if let Some(pattern) = pattern {
+                if let Err(e) = Regex::new(pattern) {
+                    errors.push(ConfigError::new(
+                        field,
+                        format!("invalid regex '{}': {}", pattern, e),
+                    ));
+                }
+            }
+        }
+
+        for (field, transforms) in &self.transforms {
+            if !TRANSFORMABLE_FIELDS.contains(&field.as_str()) {
+                errors.push(ConfigError::new(
+                    "transforms",
+                    format!("'{}' isn't a field that can be transformed", field),
+                ));
+            }
+            for transform in transforms {
+                if let Err(e) = transform.parse::<Transform>() {
+                    errors.push(ConfigError::new("transforms", format!("{}: {}", field, e)));
+                }
+            }
+        }
+
+        let limits = [
+            ("max_pages", self.max_pages),
+            ("max_events", self.max_events),
+            ("default_duration_minutes", self.default_duration_minutes),
+        ];
+        for (field, limit) in limits {
+            if limit == Some(0) {
+                errors.push(ConfigError::new(field, String::from("must be at least 1")));
+            }
+        }
+
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(errors)
+        }
+    }
+}
+
+/// A problem found while validating a source's configuration.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ConfigError {
+    /// The config field the problem is in, e.g. `"location_selector"`.
+    pub field: &'static str,
+    pub message: String,
+}
+
+impl ConfigError {
+    pub fn new(field: &'static str, message: String) -> ConfigError {
+        ConfigError { field, message }
+    }
+}
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {}", self.field, self.message)
+    }
+}
+
+/// Parses HTML content to extract event data based on the provided site configuration.
+///
+/// A calendar's recurring events are listed from today in Nashville; `parse_page` takes the
+/// home timezone.
+///
+/// # Arguments
+///
+/// * `html` - A string slice that holds the HTML content to be parsed.
+/// * `config` - Site configuration specifying CSS selectors for different event components.
+/// * `base_url` - The base URL of the site for resolving relative URLs. A `<base href>` in the
+///   document takes precedence.
+///
+/// # Returns
+///
+/// A vector of `Event` instances extracted from the HTML content, or the `ConfigError` of a
+/// selector in `config` that doesn't parse.
+pub fn parse_html(
+    html: &str,
+    config: &SiteConfig,
+    base_url: &str,
+) -> Result<Vec<Event>, ConfigError> {
+    Ok(parse_page(html, config, base_url, Zone::default())?.events)
+}
+
+/// Why a page couldn't be parsed for events.
+#[derive(Debug, Clone, PartialEq)]
+pub enum ParseError {
+    /// The page looks like a bot challenge or block page rather than the listing; holds a
+    /// description of what gave it away.
+    LikelyBlocked(String),
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ParseError::LikelyBlocked(marker) => {
+                write!(
+                    f,
+                    "the site appears to be blocking automated requests (the page contains {})",
+                    marker
+                )
+            }
+        }
+    }
+}
+
+/// Checks whether a page that yielded no events is a bot challenge or block page.
+///
+/// The page is searched, ignoring case, for markers such as CAPTCHA forms, "Access denied",
+/// and Cloudflare challenge scripts; a page shorter than `MIN_PAGE_BYTES` counts as blocked
+/// too. Only call this for pages without events, since a real listing may mention a CAPTCHA
+/// in, say, its newsletter form.
+///
+/// # Arguments
+///
+/// * `html` - The page content.
+///
+/// # Returns
+///
+/// `Ok(())`, or `ParseError::LikelyBlocked` naming what was found.
+pub fn check_blocked(html: &str) -> Result<(), ParseError> {
+    let text = html.to_lowercase();
+    if let Some((_, marker)) = BLOCK_MARKERS
+        .iter()
+        .find(|(needle, _)| text.contains(needle))
+    {
+        return Err(ParseError::LikelyBlocked(marker.to_string()));
+    }
+    if html.trim().len() < MIN_PAGE_BYTES {
+        return Err(ParseError::LikelyBlocked(format!(
+            "only {} bytes",
+            html.trim().len()
+        )));
+    }
+    Ok(())
+}
+
+/// Parses a saved page like `parse_html`.
+///
+/// # Arguments
+///
+/// * `path` - The file holding the page.
+/// * `config` - Site configuration specifying how to find events.
+/// * `base_url` - The base URL of the site the page was saved from.
+///
+/// # Returns
+///
+/// The events in the page, `Error::Io` if the file can't be read, or `Error::InvalidSource`
+/// if a selector in `config` doesn't parse.
+pub fn parse_file(
+    path: impl AsRef<Path>,
+    config: &SiteConfig,
+    base_url: &str,
+) -> Result<Vec<Event>, Error> {
+    let html = fs::read_to_string(path.as_ref())?;
+    parse_html(&html, config, base_url).map_err(|e| Error::InvalidSource {
+        source: path.as_ref().display().to_string(),
+        errors: vec![e],
+    })
+}
+
+/// The events parsed from one page, and how many the page had before `max_events` applied.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct ParsedPage {
+    pub events: Vec<Event>,
+    /// The number of events found on the page, including those past the cap.
+    pub matched: usize,
+    /// The number of JSON-LD or microdata objects skipped because their type isn't an event
+    /// type, such as `WebSite` or `Organization` records.
+    pub skipped: usize,
+}
+
+/// Parses HTML content like `parse_html`, also reporting how many events the page matched.
+///
+/// Once `max_events` events have been parsed, further matches are only counted, not extracted.
+/// A selector that doesn't parse is an error; `SiteConfig::validate` reports every problem in
+/// a config at once, before anything is fetched.
+///
+/// # Arguments
+///
+/// * `html` - The HTML content to be parsed.
+/// * `config` - Site configuration specifying how to find events.
+/// * `base_url` - The base URL of the site for resolving relative URLs.
+/// * `zone` - The home timezone, which a calendar's recurring events are listed from today in.
+///
+/// # Returns
+///
+/// A `ParsedPage` with the parsed events and the number of matches, or the `ConfigError` of
+/// the first selector that doesn't parse.
+pub fn parse_page(
+    html: &str,
+    config: &SiteConfig,
+    base_url: &str,
+    zone: Zone,
+) -> Result<ParsedPage, ConfigError> {
+    let mut events = Vec::new();
+    let max_events = config.max_events.unwrap_or(usize::MAX);
+    let mut matched = 0;
+    let mut skipped = 0;
+
+    // Parse the HTML document
+    let document = Html::parse_document(html);
+    let base_url = &document_base(&document, base_url);
+    log::debug!(
+        "parsing {} with the {:?} strategy",
+        base_url,
+        config.strategy
+    );
+
+    // Create a Selector for the HTML elements that contain the event data
+    // (Adjust the selector based on the actual HTML structure)
+    match config.strategy {
+        ParseStrategy::JsonLd => {
+            // Pages often carry several JSON-LD blocks (Organization, BreadcrumbList, ...); look at all of them
+            for script_content in document.select(&Selector::parse(JSON_LD_SELECTOR).unwrap()) {
+                // The script's raw text: `inner_html` would escape the `&` of entities in the JSON again
+                let json_content: String = script_content.text().collect();
+                // Inside the JSON parsing logic
+                if let Ok(json) = serde_json::from_str::<Value>(&json_content) {
+                    let mut event_objects = Vec::new();
+                    skipped += collect_event_objects(&json, &mut event_objects);
+
+                    for event_json in event_objects {
+                        // Create an Event object and add it to the events vector
+                        let index = events.len();
+                        events.push(event_from_json_ld(event_json, index));
+                    }
+                }
+            }
+
+            matched = events.len();
+            events.truncate(max_events);
+            bound_descriptions(&mut events, config);
+        }
+        ParseStrategy::Microdata => {
+            let item_selector = Selector::parse("[itemscope][itemtype]").unwrap();
+            for item in document.select(&item_selector) {
+                let item_type = Value::Array(
+                    item.value()
+                        .attr("itemtype")
+                        .unwrap_or_default()
+                        .split_whitespace()
+                        .map(|t| Value::String(t.to_string()))
+                        .collect(),
+                );
+                // Nested events (e.g. sub-events) are read as properties of their parent
+                if item.value().attr("itemprop").is_some() {
+                    continue;
+                }
+                if !is_event_type(&item_type) {
+                    skipped += 1;
+                    continue;
+                }
+                matched += 1;
+                if events.len() < max_events {
+                    let index = events.len();
+                    events.push(event_from_json_ld(&microdata_item(&item, base_url), index));
+                }
+            }
+
+            bound_descriptions(&mut events, config);
+        }
+        ParseStrategy::Feed => {
+            // `sources::ScrapedSource` reports feed and calendar errors; here they just have no events
+            events = feed::parse_feed(html, base_url).unwrap_or_default();
+            matched = events.len();
+            events.truncate(max_events);
+            bound_descriptions(&mut events, config);
+        }
+        ParseStrategy::Ics => {
+            events = ics::parse_ics(html, base_url, zone).unwrap_or_default();
+            matched = events.len();
+            events.truncate(max_events);
+            bound_descriptions(&mut events, config);
+        }
+        ParseStrategy::JsonApi => {
+            events = parse_json_api(html, config, base_url);
+            matched = events.len();
+            events.truncate(max_events);
+            bound_descriptions(&mut events, config);
+        }
+        // `ticketmaster::TicketmasterSource` reads the API's answers, which need its key to fetch
+        ParseStrategy::Ticketmaster => {}
+        ParseStrategy::Css => {
+            let (_, event_selector) = compile("event_selector", &config.event_selector)?;
+            let exclude_selector =
+                compile_optional("exclude_selector", config.exclude_selector.as_deref())?;
+            let name_selector = compile("name_selector", &config.name_selector)?;
+            let start_date_selector = compile("start_date_selector", &config.start_date_selector)?;
+            let end_date_selector =
+                compile_optional("end_date_selector", config.end_date_selector.as_deref())?;
+            let start_time_selector =
+                compile_optional("start_time_selector", config.start_time_selector.as_deref())?;
+            let end_time_selector =
+                compile_optional("end_time_selector", config.end_time_selector.as_deref())?;
+            let location_selector =
+                compile_optional("location_selector", config.location_selector.as_deref())?;
+            let price_selector =
+                compile_optional("price_selector", config.price_selector.as_deref())?;
+            let description_selector = compile_optional(
+                "description_selector",
+                config.description_selector.as_deref(),
+            )?;
+            let status_selector =
+                compile_optional("status_selector", config.status_selector.as_deref())?;
+            let tags_selector = compile_optional("tags_selector", config.tags_selector.as_deref())?;
+            let (_, url_selector) = compile("url", &config.url)?;
+            let image_selector =
+                compile_optional("image_selector", config.image_selector.as_deref())?;
+            // Invalid patterns are reported by `SiteConfig::validate`; here they are skipped
+            let [name_regex, start_date_regex, end_date_regex, location_regex, price_regex] =
+                config
+                    .patterns()
+                    .map(|(_, pattern)| pattern.and_then(|p| Regex::new(p).ok()));
+            let header_dates = match compile_optional(
+                "date_header_selector",
+                config.date_header_selector.as_deref(),
+            )? {
+                Some((_, header_selector)) => {
+                    header_dates(&document, &header_selector, &event_selector)
+                }
+                None => Vec::new(),
+            };
+            // Iterate over each event element
+            for (index, event_element) in document.select(&event_selector).enumerate() {
+                // Skip ads and other cards that match (or contain a match for) the exclude selector
+                if let Some((_, exclude)) = &exclude_selector {
+                    if exclude.matches(&event_element)
+                        || event_element.select(exclude).next().is_some()
+                    {
+                        continue;
+                    }
+                }
+                // Past the cap, matches are only counted
+                matched += 1;
+                if events.len() >= max_events {
+                    continue;
+                }
+                log::trace!(
+                    "event {}: {}",
+                    index,
+                    logging::snippet(&event_element.html(), 300)
+                );
+                // Extract event details like name, date, location, etc.
+                // (Adjust the selectors and extraction logic based on the actual HTML structure)
+                let mut warnings = Vec::new();
+                let mut field =
+                    |field: &'static str,
+                     (text, selector): &Compiled,
+                     attr: Option<&str>,
+                     pattern: Option<&Regex>| {
+                        let separator = match field {
+                            "name" => config.name_separator.as_deref(),
+                            "location" => config.location_separator.as_deref(),
+                            _ => None,
+                        };
+                        let found = extract_field(&event_element, selector, attr, separator)
+                            .or_else(|| match field {
+                                "start_date" => header_dates.get(index).cloned().flatten(),
+                                _ => None,
+                            });
+                        log::debug!(
+                            "event {}: {} from '{}': {:?}",
+                            index,
+                            field,
+                            text,
+                            found.as_deref().map(|raw| logging::snippet(raw, 80))
+                        );
+                        let raw = match found {
+                            Some(raw) => raw,
+                            None => {
+                                warnings.push(FieldWarning {
+                                    event_index: index,
+                                    field,
+                                    selector: text.to_string(),
+                                    kind: WarningKind::NoMatch,
+                                });
+                                return String::new();
+                            }
+                        };
+                        match pattern {
+                            Some(pattern) => apply_pattern(pattern, &raw).unwrap_or_else(|| {
+                                warnings.push(FieldWarning {
+                                    event_index: index,
+                                    field,
+                                    selector: pattern.to_string(),
+                                    kind: WarningKind::PatternMismatch,
+                                });
+                                raw
+                            }),
+                            None => raw,
+                        }
+                    };
+                let name = field(
+                    "name",
+                    &name_selector,
+                    config.name_attr.as_deref(),
+                    name_regex.as_ref(),
+                );
+                let start_date = field(
+                    "start_date",
+                    &start_date_selector,
+                    config.start_date_attr.as_deref(),
+                    start_date_regex.as_ref(),
+                );
+                let end_date = end_date_selector.as_ref().map(|selector| {
+                    field(
+                        "end_date",
+                        selector,
+                        config.end_date_attr.as_deref(),
+                        end_date_regex.as_ref(),
+                    )
+                });
+                let start_time = start_time_selector
+                    .as_ref()
+                    .map(|selector| field("start_time", selector, None, None));
+                let end_time = end_time_selector
+                    .as_ref()
+                    .map(|selector| field("end_time", selector, None, None));
+                let location = location_selector.as_ref().map(|selector| {
+                    field(
+                        "location",
+                        selector,
+                        config.location_attr.as_deref(),
+                        location_regex.as_ref(),
+                    )
+                });
+                let price = price_selector.as_ref().map(|selector| {
+                    field(
+                        "price",
+                        selector,
+                        config.price_attr.as_deref(),
+                        price_regex.as_ref(),
+                    )
+                });
+                let description = description_selector.as_ref().map(|selector| {
+                    field(
+                        "description",
+                        selector,
+                        config.description_attr.as_deref(),
+                        None,
+                    )
+                });
+
+                // Most events carry no badge, so a status that matched nothing isn't worth a warning
+                let status = status_selector
+                    .as_ref()
+                    .and_then(|(_, selector)| extract_field(&event_element, selector, None, None))
+                    .map_or(EventStatus::Unknown, |badge| EventStatus::from_text(&badge));
+
+                let tags = match &tags_selector {
+                    Some((_, selector)) => event_element
+                        .select(selector)
+                        .map(|tag| clean_text(&tag.inner_html()))
+                        .filter(|t| !t.is_empty())
+                        .collect(),
+                    None => Vec::new(),
+                };
+
+                let url_attr = config.url_attr.as_deref().unwrap_or("href");
+                let relative_url = event_element
+                    .select(&url_selector)
+                    .next()
+                    .and_then(|e| e.value().attr(url_attr))
+                    .map(str::to_string);
+
+                // Create an absolute URL based on the base URL and the relative URL
+                let url = match relative_url {
+                    Some(href) => resolve_url(base_url, &href).unwrap_or_else(|_| {
+                        warnings.push(FieldWarning {
+                            event_index: index,
+                            field: "url",
+                            selector: href,
+                            kind: WarningKind::UnresolvableUrl,
+                        });
+                        String::new()
+                    }),
+                    None => {
+                        warnings.push(FieldWarning {
+                            event_index: index,
+                            field: "url",
+                            selector: config.url.clone(),
+                            kind: WarningKind::NoMatch,
+                        });
+                        String::new()
+                    }
+                };
+                let image_attr = config.image_attr.as_deref().unwrap_or("src");
+                let image_url = image_selector.as_ref().and_then(|(text, selector)| {
+                    let src = event_element
+                        .select(selector)
+                        .next()
+                        .and_then(|e| e.value().attr(image_attr));
+                    let src = match src {
+                        Some(src) => src,
+                        None => {
+                            warnings.push(FieldWarning {
+                                event_index: index,
+                                field: "image",
+                                selector: text.to_string(),
+                                kind: WarningKind::NoMatch,
+                            });
+                            return None;
+                        }
+                    };
+                    resolve_url(base_url, src)
+                        .map_err(|_| {
+                            warnings.push(FieldWarning {
+                                event_index: index,
+                                field: "image",
+                                selector: src.to_string(),
+                                kind: WarningKind::UnresolvableUrl,
+                            });
+                        })
+                        .ok()
+                });
+
+                // Create an Event object and add it to the events vector
+                let event = Event {
+                    name,
+                    start_date,
+                    end_date,
+                    start_time,
+                    end_time,
+                    location,
+                    url,
+                    price,
+                    description,
+                    image_url,
+                    status,
+                    tags,
+                    warnings,
+                    ..Default::default()
+                };
+                events.push(event);
+            }
+            log::debug!("'{}' matched {} elements", config.event_selector, matched);
+        }
+    }
+
+    // A page whose selectors found nothing may still describe its headline event in meta tags
+    if events.is_empty() {
+        let searched = match config.strategy {
+            ParseStrategy::Css => Some(config.event_selector.as_str()),
+            ParseStrategy::JsonLd => Some(JSON_LD_SELECTOR),
+            ParseStrategy::Microdata => Some("[itemscope][itemtype]"),
+            ParseStrategy::Feed
+            | ParseStrategy::Ics
+            | ParseStrategy::JsonApi
+            | ParseStrategy::Ticketmaster => None,
+        };
+        if let Some(event) = searched.and_then(|searched| meta_event(&document, searched, base_url))
+        {
+            events.push(event);
+            matched = 1;
+        }
+    }
+    Ok(ParsedPage {
+        events,
+        matched,
+        skipped,
+    })
+}
+
+/// A selector from a site config, with the text it was parsed from.
+type Compiled<'a> = (&'a str, Selector);
+
+/// Parses a selector from a site config.
+///
+/// # Arguments
+///
+/// * `field` - The config field the selector is in, e.g. `"name_selector"`.
+/// * `selector` - The selector's text.
+///
+/// # Returns
+///
+/// The selector with its text, or a `ConfigError` naming the field if it doesn't parse.
+fn compile<'a>(field: &'static str, selector: &'a str) -> Result<Compiled<'a>, ConfigError> {
+    match Selector::parse(selector) {
+        Ok(parsed) if !selector.trim().is_empty() => Ok((selector, parsed)),
+        _ => Err(ConfigError::new(
+            field,
+            format!("invalid CSS selector '{}'", selector),
+        )),
+    }
+}
+
+/// Parses an optional selector from a site config, like `compile`.
+fn compile_optional<'a>(
+    field: &'static str,
+    selector: Option<&'a str>,
+) -> Result<Option<Compiled<'a>>, ConfigError> {
+    selector
+        .map(|selector| compile(field, selector))
+        .transpose()
+}
+
+/// Finds the date header each event element falls under.
+///
+/// Walks the document in order, remembering the text of the most recent header, so an event
+/// element is paired with the last header before it even when the two aren't siblings.
+///
+/// # Arguments
+///
+/// * `document` - The parsed page.
+/// * `header_selector` - Selects the date headers.
+/// * `event_selector` - Selects the event elements.
+///
+/// # Returns
+///
+/// The header text for each event element in document order, or `None` for elements before
+/// the first header.
+fn header_dates(
+    document: &Html,
+    header_selector: &Selector,
+    event_selector: &Selector,
+) -> Vec<Option<String>> {
+    let mut dates = Vec::new();
+    let mut current = None;
+    for element in document
+        .root_element()
+        .descendants()
+        .filter_map(ElementRef::wrap)
+    {
+        if header_selector.matches(&element) {
+            current = Some(clean_text(&element.text().collect::<String>()));
+        }
+        if event_selector.matches(&element) {
+            dates.push(current.clone());
+        }
+    }
+    dates
+}
+
+/// Builds a single event from a page's Open Graph and standard meta tags.
+///
+/// The event carries a `MetaFallback` warning so the output can point out that the source's
+/// selectors found nothing.
+///
+/// # Arguments
+///
+/// * `document` - The parsed page.
+/// * `searched` - The selector that found no events, for the warning.
+/// * `base_url` - The base URL for resolving the page and image URLs.
+///
+/// # Returns
+///
+/// The event, or `None` if the page has no `og:title`.
+fn meta_event(document: &Html, searched: &str, base_url: &str) -> Option<Event> {
+    let meta = |key: &str| {
+        let selector = Selector::parse(&format!(
+            "meta[property='{0}'][content], meta[name='{0}'][content]",
+            key
+        ))
+        .unwrap();
+        document
+            .select(&selector)
+            .filter_map(|meta| meta.value().attr("content"))
+            .map(clean_text)
+            .find(|content| !content.is_empty())
+    };
+    let canonical = Selector::parse("link[rel='canonical'][href]").unwrap();
+
+    let name = meta("og:title")?;
+    let url = meta("og:url")
+        .or_else(|| {
+            document
+                .select(&canonical)
+                .next()
+                .and_then(|link| link.value().attr("href"))
+                .map(str::to_string)
+        })
+        .and_then(|href| resolve_url(base_url, &href).ok())
+        .unwrap_or_default();
+    Some(Event {
+        name,
+        start_date: meta("event:start_time").unwrap_or_default(),
+        end_date: meta("event:end_time"),
+        url,
+        description: meta("og:description").or_else(|| meta("description")),
+        image_url: meta("og:image").and_then(|src| resolve_url(base_url, &src).ok()),
+        warnings: vec![FieldWarning {
+            event_index: 0,
+            field: "event",
+            selector: searched.to_string(),
+            kind: WarningKind::MetaFallback,
+        }],
+        ..Default::default()
+    })
+}
+
+/// Strips boilerplate shared by all descriptions and truncates each to the configured length.
+pub fn bound_descriptions(events: &mut [Event], config: &SiteConfig) {
+    // Site disclaimers repeated on every event are not event information
+    let mut descriptions: Vec<Option<String>> =
+        events.iter_mut().map(|e| e.description.take()).collect();
+    strip_boilerplate(&mut descriptions);
+    let max_len = config
+        .description_length
+        .unwrap_or(DEFAULT_DESCRIPTION_LENGTH);
+    for (event, description) in events.iter_mut().zip(descriptions) {
+        event.description = description.map(|d| truncate_at_word(&d, max_len));
+    }
+}
+
+/// Converts a microdata item into the equivalent JSON-LD object.
+///
+/// Each `itemprop` belonging to the item becomes a key; nested `itemscope` items (such as a
+/// `Place` location or an `Offer`) become nested objects with their own `@type`. Only the first
+/// value of a repeated property is kept. This lets microdata events share the JSON-LD readers.
+///
+/// # Arguments
+///
+/// * `item` - An element carrying `itemscope`.
+/// * `base_url` - The base URL of the site for resolving link and image properties.
+///
+/// # Returns
+///
+/// A JSON object holding the item's properties.
+fn microdata_item(item: &ElementRef, base_url: &str) -> Value {
+    let mut properties = serde_json::Map::new();
+    if let Some(item_type) = item.value().attr("itemtype") {
+        properties.insert("@type".to_string(), Value::String(item_type.to_string()));
+    }
+    collect_microdata_properties(item, base_url, &mut properties);
+    Value::Object(properties)
+}
+
+/// Collects the `itemprop`s below `element` that belong to the enclosing item, without
+/// descending into nested items.
+fn collect_microdata_properties(
+    element: &ElementRef,
+    base_url: &str,
+    properties: &mut serde_json::Map<String, Value>,
+) {
+    for child in element.children().filter_map(ElementRef::wrap) {
+        let is_item = child.value().attr("itemscope").is_some();
+        if let Some(names) = child.value().attr("itemprop") {
+            let value = if is_item {
+                microdata_item(&child, base_url)
+            } else {
+                Value::String(microdata_value(&child, base_url))
+            };
+            for name in names.split_whitespace() {
+                properties
+                    .entry(name.to_string())
+                    .or_insert_with(|| value.clone());
+            }
+        }
+        if !is_item {
+            collect_microdata_properties(&child, base_url, properties);
+        }
+    }
+}
+
+/// Reads the value of a microdata property element.
+///
+/// Machine-readable attributes are preferred over text: `content` (e.g. on `<meta>`),
+/// `datetime` on `<time>`, and the resolved `href` or `src` of links and images.
+fn microdata_value(element: &ElementRef, base_url: &str) -> String {
+    let value = element.value();
+    if let Some(content) = value.attr("content").or_else(|| value.attr("datetime")) {
+        return content.trim().to_string();
+    }
+    match value.name() {
+        "a" | "link" | "area" => value
+            .attr("href")
+            .and_then(|href| resolve_url(base_url, href).ok()),
+        "img" | "source" | "video" | "audio" => value
+            .attr("src")
+            .and_then(|src| resolve_url(base_url, src).ok()),
+        _ => None,
+    }
+    .unwrap_or_else(|| clean_text(&element.inner_html()))
+}
+
+/// Drops parsed events that don't look like events because their name is empty.
+///
+/// Elements matching the event selector without a name are usually ads, spacers, or a sign
+/// that `name_selector` no longer matches the site's markup, so they are counted for reporting.
+///
+/// # Arguments
+///
+/// * `events` - The events returned by `parse_html`.
+/// * `keep_nameless` - Keep nameless events anyway, for debugging selectors.
+///
+/// # Returns
+///
+/// The remaining events and the number of nameless events found.
+pub fn drop_nameless(events: Vec<Event>, keep_nameless: bool) -> (Vec<Event>, usize) {
+    let nameless = events
+        .iter()
+        .filter(|e| clean_text(&e.name).is_empty())
+        .count();
+    if keep_nameless {
+        return (events, nameless);
+    }
+    let events = events
+        .into_iter()
+        .filter(|e| !clean_text(&e.name).is_empty())
+        .collect();
+    (events, nameless)
+}
+
+/// Collapses events listed more than once, e.g. in a featured carousel and the main list.
+///
+/// Events are the same when their URLs match after normalization (no fragment, no `utm_*`
+/// tracking parameters, no trailing slash), or, for events without a URL, when their name,
+/// start date, and location match (the name and location compared by `normalize_key`). Of each group, the event with the most populated fields
+/// is kept, in the position of the group's first event.
+///
+/// # Arguments
+///
+/// * `events` - The parsed events.
+///
+/// # Returns
+///
+/// The remaining events and the number of duplicates collapsed.
+pub fn dedupe(events: Vec<Event>) -> (Vec<Event>, usize) {
+    fn populated(event: &Event) -> usize {
+        let optional = [
+            &event.end_date,
+            &event.location,
+            &event.description,
+            &event.price,
+            &event.image_url,
+        ];
+        [&event.name, &event.start_date, &event.url]
+            .iter()
+            .filter(|v| !clean_text(v).is_empty())
+            .count()
+            + optional
+                .iter()
+                .filter(|v| v.as_deref().is_some_and(|v| !clean_text(v).is_empty()))
+                .count()
+    }
+
+    let total = events.len();
+    let mut kept: Vec<(String, Event)> = Vec::new();
+    for event in events {
+        let key = dedupe_key(&event);
+        match kept.iter_mut().find(|(k, _)| *k == key) {
+            Some((_, existing)) => {
+                if populated(&event) > populated(existing) {
+                    *existing = event;
+                }
+            }
+            None => kept.push((key, event)),
+        }
+    }
+    let duplicates = total - kept.len();
+    (
+        kept.into_iter().map(|(_, event)| event).collect(),
+        duplicates,
+    )
+}
+
+/// The key `dedupe` compares events by: the normalized URL, or the name, start date, and
+/// location when the event has no URL.
+fn dedupe_key(event: &Event) -> String {
+    if let Some(url) = normalize_url(&event.url) {
+        return format!("url:{}", url);
+    }
+    let location = event
+        .location
+        .as_deref()
+        .map(normalize_key)
+        .unwrap_or_default();
+    format!(
+        "event:{}\n{}\n{}",
+        normalize_key(&event.name),
+        clean_text(&event.start_date),
+        location
+    )
+}
+
+/// Normalizes an event URL so listings of the same page compare equal: the fragment and
+/// `utm_` tracking parameters are dropped, as is a trailing slash.
+///
+/// # Arguments
+///
+/// * `url` - The URL to normalize.
+///
+/// # Returns
+///
+/// The normalized URL, or `None` if `url` isn't an absolute URL.
+pub fn normalize_url(url: &str) -> Option<String> {
+    let mut url = Url::parse(url.trim()).ok()?;
+    url.set_fragment(None);
+    let query: Vec<(String, String)> = url
+        .query_pairs()
+        .filter(|(key, _)| !key.starts_with("utm_"))
+        .map(|(key, value)| (key.into_owned(), value.into_owned()))
+        .collect();
+    if query.is_empty() {
+        url.set_query(None);
+    } else {
+        url.query_pairs_mut().clear().extend_pairs(query);
+    }
+    Some(url.as_str().trim_end_matches('/').to_string())
+}
+
+/// Applies a post-processing pattern to an extracted value.
+///
+/// The pattern is matched against the value's text with markup stripped and whitespace
+/// collapsed.
+///
+/// # Arguments
+///
+/// * `pattern` - The compiled pattern.
+/// * `raw` - The extracted value.
+///
+/// # Returns
+///
+/// The first capture group, or the whole match if the pattern has no groups; `None` if the
+/// pattern doesn't match.
+fn apply_pattern(pattern: &Regex, raw: &str) -> Option<String> {
+    let text = clean_text(raw);
+    let captures = pattern.captures(&text)?;
+    let matched = captures.get(1).or_else(|| captures.get(0))?;
+    Some(matched.as_str().trim().to_string())
+}
+
+/// Extracts a single field from an event element.
+///
+/// The first element matching `selector` is used. If `attr` is given and present on that
+/// element, its value is returned; otherwise the element's inner HTML is returned.
+///
+/// With a `separator`, every matching element is used instead: their values are cleaned, empty
+/// ones skipped, and the rest joined with the separator.
+///
+/// # Arguments
+///
+/// * `element` - The event element to search within.
+/// * `selector` - The CSS selector for the field.
+/// * `attr` - An optional attribute to prefer over the element's text.
+/// * `separator` - Joins all matches when given; otherwise only the first match is used.
+///
+/// # Returns
+///
+/// The extracted value, or `None` if the selector matched nothing.
+fn extract_field(
+    element: &ElementRef,
+    selector: &Selector,
+    attr: Option<&str>,
+    separator: Option<&str>,
+) -> Option<String> {
+    let mut values = element
+        .select(selector)
+        .map(|e| match attr.and_then(|a| e.value().attr(a)) {
+            Some(value) => value.to_string(),
+            None => e.inner_html(),
+        });
+    match separator {
+        None => values.next(),
+        Some(separator) => {
+            let values: Vec<String> = values.map(|v| clean_text(&v)).collect();
+            if values.is_empty() {
+                return None;
+            }
+            Some(
+                values
+                    .into_iter()
+                    .filter(|v| !v.is_empty())
+                    .collect::<Vec<_>>()
+                    .join(separator),
+            )
+        }
+    }
+}
+
+/// Builds an `Event` from a JSON-LD event object.
+///
+/// # Arguments
+///
+/// * `event_json` - A JSON-LD object whose `@type` is an event type.
+/// * `index` - The position of the event on the page, used in warnings.
+///
+/// # Returns
+///
+/// The extracted `Event`; fields missing from the object are left empty or `None` and, for
+/// the name, start date, and location, recorded as warnings.
+fn event_from_json_ld(event_json: &Value, index: usize) -> Event {
+    let warnings = [
+        ("name", "name"),
+        ("start_date", "startDate"),
+        ("location", "location"),
+    ]
+    .iter()
+    .filter(|(_, key)| event_json[*key].is_null())
+    .map(|(field, key)| FieldWarning {
+        event_index: index,
+        field,
+        selector: key.to_string(),
+        kind: WarningKind::NoMatch,
+    })
+    .collect();
+    Event {
+        name: event_json["name"].as_str().unwrap_or_default().to_string(),
+        start_date: event_json["startDate"]
+            .as_str()
+            .unwrap_or_default()
+            .to_string(),
+        end_date: event_json["endDate"].as_str().map(str::to_string),
+        location: json_ld_location(&event_json["location"]),
+        url: event_json["url"].as_str().unwrap_or_default().to_string(),
+        description: event_json["description"]
+            .as_str()
+            .map(clean_description)
+            .filter(|d| !d.is_empty()),
+        price: json_ld_price(&event_json["offers"]),
+        image_url: json_ld_image(&event_json["image"]),
+        status: json_ld_status(event_json),
+        tags: json_ld_tags(event_json),
+        warnings,
+        ..Default::default()
+    }
+}
+
+/// Reads the tags of a JSON-LD event from its `keywords` and `genre`, each either a
+/// comma-separated string or an array of strings.
+fn json_ld_tags(event_json: &Value) -> Vec<String> {
+    ["keywords", "genre"]
+        .iter()
+        .flat_map(|key| match &event_json[*key] {
+            Value::String(s) => s.split(',').map(str::to_string).collect(),
+            Value::Array(items) => items
+                .iter()
+                .filter_map(Value::as_str)
+                .map(str::to_string)
+                .collect(),
+            _ => Vec::new(),
+        })
+        .filter(|tag| !tag.trim().is_empty())
+        .collect()
+}
+
+/// Reads the status of a JSON-LD event from its `eventStatus` and its offers' `availability`.
+///
+/// A cancelled or postponed event is reported as such whatever its offers say.
+fn json_ld_status(event_json: &Value) -> EventStatus {
+    let event_status = event_json["eventStatus"]
+        .as_str()
+        .map(EventStatus::from_text)
+        .unwrap_or_default();
+    if matches!(
+        event_status,
+        EventStatus::Cancelled | EventStatus::Postponed
+    ) {
+        return event_status;
+    }
+    let offers: Vec<&Value> = match &event_json["offers"] {
+        Value::Array(items) => items.iter().collect(),
+        offer => vec![offer],
+    };
+    let availability: Vec<EventStatus> = offers
+        .iter()
+        .filter_map(|offer| offer["availability"].as_str())
+        .map(EventStatus::from_text)
+        .collect();
+    if availability.contains(&EventStatus::Available) {
+        EventStatus::Available
+    } else if availability.contains(&EventStatus::SoldOut) {
+        EventStatus::SoldOut
+    } else {
+        event_status
+    }
+}
+
+/// Reads an image URL from a JSON-LD `image` value: a URL string, an `ImageObject` with a
+/// `url`, or an array of either (the first is used).
+fn json_ld_image(image: &Value) -> Option<String> {
+    match image {
+        Value::String(url) => Some(url.trim().to_string()).filter(|u| !u.is_empty()),
+        Value::Array(images) => images.first().and_then(json_ld_image),
+        Value::Object(_) => json_ld_image(&image["url"]),
+        _ => None,
+    }
+}
+
+/// Reads the price of a JSON-LD event from its `offers`.
+///
+/// `offers` may be a single `Offer` or an array of them. Each offer contributes its `price`,
+/// or its `lowPrice`/`highPrice` for an `AggregateOffer`; numbers and numeric strings are both
+/// accepted. Several prices become a `low-high` range. The currency, when given, is appended
+/// (`"25 USD"`) for `process_data` to normalize.
+///
+/// # Arguments
+///
+/// * `offers` - The `offers` value of a JSON-LD event.
+///
+/// # Returns
+///
+/// The raw price text, or `None` if no offer carries a price.
+fn json_ld_price(offers: &Value) -> Option<String> {
+    let offers: Vec<&Value> = match offers {
+        Value::Array(items) => items.iter().collect(),
+        Value::Object(_) => vec![offers],
+        _ => return None,
+    };
+
+    let amount = |value: &Value| match value {
+        Value::Number(n) => n.as_f64(),
+        Value::String(s) => s.trim().trim_start_matches('$').parse::<f64>().ok(),
+        _ => None,
+    };
+    let mut prices: Vec<f64> = Vec::new();
+    let mut text_price = None;
+    for offer in &offers {
+        for key in ["price", "lowPrice", "highPrice"] {
+            match amount(&offer[key]) {
+                Some(price) => prices.push(price),
+                // A non-numeric price such as "Free" is kept as text
+                None => {
+                    if let Some(text) = offer[key].as_str().filter(|t| !t.trim().is_empty()) {
+                        text_price.get_or_insert_with(|| text.trim().to_string());
+                    }
+                }
+            }
+        }
+    }
+
+    if prices.is_empty() {
+        return text_price;
+    }
+    let low = prices.iter().cloned().fold(f64::INFINITY, f64::min);
+    let high = prices.iter().cloned().fold(f64::NEG_INFINITY, f64::max);
+    let mut price = if low == high {
+        format!("{}", low)
+    } else {
+        format!("{}-{}", low, high)
+    };
+    if let Some(currency) = offers.iter().find_map(|o| o["priceCurrency"].as_str()) {
+        price = format!("{} {}", price, currency);
+    }
+    Some(price)
+}
+
+/// Collects the JSON-LD objects that describe events.
+///
+/// Arrays are searched element by element and `@graph` containers are descended into.
+/// Only objects whose `@type` is `Event` or one of its subtypes are collected.
+///
+/// # Arguments
+///
+/// * `json` - A parsed JSON-LD value.
+/// * `found` - The list the event objects are appended to.
+///
+/// # Returns
+///
+/// The number of objects skipped because they have a `@type` that isn't an event type.
+fn collect_event_objects<'a>(json: &'a Value, found: &mut Vec<&'a Value>) -> usize {
+    match json {
+        Value::Array(items) => items
+            .iter()
+            .map(|item| collect_event_objects(item, found))
+            .sum(),
+        Value::Object(object) => {
+            let skipped = object
+                .get("@graph")
+                .map_or(0, |graph| collect_event_objects(graph, found));
+            if is_event_type(&json["@type"]) {
+                found.push(json);
+                skipped
+            } else {
+                skipped + usize::from(object.contains_key("@type"))
+            }
+        }
+        _ => 0,
+    }
+}
+
+/// Returns `true` if a JSON-LD `@type` (a string or an array of strings) names an event type.
+fn is_event_type(json_type: &Value) -> bool {
+    let accepts = |t: &str| {
+        let t = t.rsplit('/').next().unwrap_or(t);
+        EVENT_TYPES.contains(&t)
+    };
+    match json_type {
+        Value::String(t) => accepts(t),
+        Value::Array(types) => types.iter().filter_map(Value::as_str).any(accepts),
+        _ => false,
+    }
+}
+
+/// Reads a readable location from a JSON-LD `location` value.
+///
+/// In priority order this accepts a bare string, a place object with a `name`, or a place whose
+/// `address` is a string or a `PostalAddress`, composed from its street, locality, and region.
+/// An array of places uses the first one.
+///
+/// # Arguments
+///
+/// * `location` - The `location` value of a JSON-LD event.
+///
+/// # Returns
+///
+/// The location text, or `None` if nothing usable is present.
+fn json_ld_location(location: &Value) -> Option<String> {
+    let non_empty = |text: &str| Some(text.trim().to_string()).filter(|t| !t.is_empty());
+    match location {
+        Value::String(text) => non_empty(text),
+        Value::Array(places) => places.first().and_then(json_ld_location),
+        Value::Object(place) => {
+            if let Some(name) = place
+                .get("name")
+                .and_then(Value::as_str)
+                .and_then(non_empty)
+            {
+                return Some(name);
+            }
+            // A PostalAddress given directly as the location has its fields at the top level
+            let address = place.get("address").unwrap_or(location);
+            if let Some(text) = address.as_str() {
+                return non_empty(text);
+            }
+            let parts: Vec<&str> = ["streetAddress", "addressLocality", "addressRegion"]
+                .iter()
+                .filter_map(|key| address[*key].as_str())
+                .map(str::trim)
+                .filter(|part| !part.is_empty())
+                .collect();
+            non_empty(&parts.join(", "))
+        }
+        _ => None,
+    }
+}
+
+/// Removes text shared by every description from one source, such as ticketing terms.
+///
+/// The longest common prefix and suffix (snapped to word boundaries) count as boilerplate
+/// when they are at least `MIN_BOILERPLATE_CHARS` long. Boilerplate is cut from each
+/// description, and descriptions that were more than `MAX_BOILERPLATE_RATIO` boilerplate are
+/// dropped. Fewer than two descriptions give nothing to compare, so they are left alone.
+///
+/// # Arguments
+///
+/// * `descriptions` - The descriptions of all events from one source, `None` where absent.
+fn strip_boilerplate(descriptions: &mut [Option<String>]) {
+    let texts: Vec<Vec<char>> = descriptions
+        .iter()
+        .flatten()
+        .map(|d| d.chars().collect())
+        .collect();
+    if texts.len() < 2 {
+        return;
+    }
+    let shortest = texts.iter().map(Vec::len).min().unwrap_or(0);
+
+    let mut prefix = (0..shortest)
+        .take_while(|&i| texts.iter().all(|t| t[i] == texts[0][i]))
+        .count();
+    if prefix < shortest {
+        // The shared run ended mid-word; back up to the last word boundary
+        while prefix > 0 && !texts[0][prefix - 1].is_whitespace() {
+            prefix -= 1;
+        }
+    }
+
+    let mut suffix = (0..shortest - prefix)
+        .take_while(|&i| {
+            texts
+                .iter()
+                .all(|t| t[t.len() - 1 - i] == texts[0][texts[0].len() - 1 - i])
+        })
+        .count();
+    if prefix + suffix < shortest {
+        let first = &texts[0];
+        while suffix > 0 && !first[first.len() - suffix].is_whitespace() {
+            suffix -= 1;
+        }
+    }
+
+    if prefix < MIN_BOILERPLATE_CHARS {
+        prefix = 0;
+    }
+    if suffix < MIN_BOILERPLATE_CHARS {
+        suffix = 0;
+    }
+    if prefix + suffix == 0 {
+        return;
+    }
+
+    for description in descriptions.iter_mut() {
+        if let Some(text) = description.take() {
+            let chars: Vec<char> = text.chars().collect();
+            let boilerplate = (prefix + suffix) as f64 / chars.len() as f64;
+            let remaining: String = chars[prefix..chars.len() - suffix].iter().collect();
+            let remaining = remaining.trim();
+            if boilerplate <= MAX_BOILERPLATE_RATIO && !remaining.is_empty() {
+                *description = Some(remaining.to_string());
+            }
+        }
+    }
+}
+
+/// What a source's selectors matched on a page, for diagnosing broken selectors.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct SelectorReport {
+    /// The number of elements `event_selector` matched.
+    pub event_matches: usize,
+    /// One entry per configured field selector, in `SiteConfig` order.
+    pub fields: Vec<FieldReport>,
+}
+
+/// What one field selector matched.
+#[derive(Debug, Clone, PartialEq)]
+pub struct FieldReport {
+    pub field: &'static str,
+    pub selector: String,
+    /// How many of the matched event elements the selector found something in.
+    pub matched: usize,
+    /// The raw value extracted from the first event element, or `None` if nothing matched there.
+    pub first: Option<String>,
+}
+
+/// Reports how many elements each of a config's CSS selectors matches in a page.
+///
+/// The field selectors are run inside every element the event selector matched; the report
+/// counts the elements each field was found in and shows the raw value from the first one.
+/// Only `ParseStrategy::Css` configs have field selectors; for the other strategies only the
+/// number of events found is reported.
+///
+/// # Arguments
+///
+/// * `html` - The HTML content of the page.
+/// * `config` - The site configuration to check.
+/// * `zone` - The home timezone.
+///
+/// # Returns
+///
+/// A `SelectorReport` for the page, or the `ConfigError` of a selector that doesn't parse.
+pub fn debug_selectors(
+    html: &str,
+    config: &SiteConfig,
+    zone: Zone,
+) -> Result<SelectorReport, ConfigError> {
+    if config.strategy != ParseStrategy::Css {
+        let config = SiteConfig {
+            max_events: None,
+            ..config.clone()
+        };
+        return Ok(SelectorReport {
+            event_matches: parse_page(html, &config, "http://localhost/", zone)?.matched,
+            fields: Vec::new(),
+        });
+    }
+
+    let document = Html::parse_document(html);
+    let (_, event_selector) = compile("event_selector", &config.event_selector)?;
+    let elements: Vec<ElementRef> = document.select(&event_selector).collect();
+    let url_attr = Some(config.url_attr.as_deref().unwrap_or("href"));
+    let image_attr = Some(config.image_attr.as_deref().unwrap_or("src"));
+    let selectors = [
+        (
+            "name",
+            "name_selector",
+            Some(&config.name_selector),
+            config.name_attr.as_deref(),
+        ),
+        (
+            "start_date",
+            "start_date_selector",
+            Some(&config.start_date_selector),
+            config.start_date_attr.as_deref(),
+        ),
+        (
+            "end_date",
+            "end_date_selector",
+            config.end_date_selector.as_ref(),
+            config.end_date_attr.as_deref(),
+        ),
+        (
+            "start_time",
+            "start_time_selector",
+            config.start_time_selector.as_ref(),
+            None,
+        ),
+        (
+            "end_time",
+            "end_time_selector",
+            config.end_time_selector.as_ref(),
+            None,
+        ),
+        (
+            "location",
+            "location_selector",
+            config.location_selector.as_ref(),
+            config.location_attr.as_deref(),
+        ),
+        ("url", "url", Some(&config.url), url_attr),
+        (
+            "price",
+            "price_selector",
+            config.price_selector.as_ref(),
+            config.price_attr.as_deref(),
+        ),
+        (
+            "description",
+            "description_selector",
+            config.description_selector.as_ref(),
+            config.description_attr.as_deref(),
+        ),
+        (
+            "image",
+            "image_selector",
+            config.image_selector.as_ref(),
+            image_attr,
+        ),
+        (
+            "status",
+            "status_selector",
+            config.status_selector.as_ref(),
+            None,
+        ),
+        ("tags", "tags_selector", config.tags_selector.as_ref(), None),
+        (
+            "exclude",
+            "exclude_selector",
+            config.exclude_selector.as_ref(),
+            None,
+        ),
+    ];
+    let mut fields = Vec::new();
+    for (field, key, selector, attr) in selectors {
+        let Some((text, selector)) = compile_optional(key, selector.map(String::as_str))? else {
+            continue;
+        };
+        let matched = elements
+            .iter()
+            .filter(|e| extract_field(e, &selector, attr, None).is_some())
+            .count();
+        let first = elements
+            .first()
+            .and_then(|e| extract_field(e, &selector, attr, None));
+        fields.push(FieldReport {
+            field,
+            selector: text.to_string(),
+            matched,
+            first,
+        });
+    }
+    Ok(SelectorReport {
+        event_matches: elements.len(),
+        fields,
+    })
+}
+
+/// Parses a JSON API response using the dotted paths in the config.
+///
+/// `event_selector` is the path to the array of events (e.g. `data.events`; empty for a
+/// top-level array) and the field selectors are paths within each event (e.g. `venue.name`,
+/// `dates.start.localDate`). Numeric path segments index arrays. Strings and numbers are used
+/// as they are; a configured path that leads nowhere leaves its field empty and records a
+/// warning.
+///
+/// # Arguments
+///
+/// * `json` - The response body.
+/// * `config` - Site configuration with the paths to read.
+/// * `base_url` - The base URL of the site for resolving relative event and image URLs.
+///
+/// # Returns
+///
+/// The events in the response, or none if it isn't JSON or the events path isn't an array.
+pub fn parse_json_api(json: &str, config: &SiteConfig, base_url: &str) -> Vec<Event> {
+    let json = match serde_json::from_str::<Value>(json) {
+        Ok(json) => json,
+        Err(_) => return Vec::new(),
+    };
+    let items = match json_path(&json, &config.event_selector).and_then(Value::as_array) {
+        Some(items) => items,
+        None => return Vec::new(),
+    };
+
+    items
+        .iter()
+        .enumerate()
+        .map(|(index, item)| {
+            let mut warnings = Vec::new();
+            let mut field = |field: &'static str, path: &str| {
+                json_path(item, path)
+                    .and_then(json_scalar)
+                    .unwrap_or_else(|| {
+                        warnings.push(FieldWarning {
+                            event_index: index,
+                            field,
+                            selector: path.to_string(),
+                            kind: WarningKind::NoMatch,
+                        });
+                        String::new()
+                    })
+            };
+            let name = field("name", &config.name_selector);
+            let start_date = field("start_date", &config.start_date_selector);
+            let end_date = config
+                .end_date_selector
+                .as_deref()
+                .map(|path| field("end_date", path));
+            let location = config
+                .location_selector
+                .as_deref()
+                .map(|path| field("location", path));
+            let price = config
+                .price_selector
+                .as_deref()
+                .map(|path| field("price", path));
+            let description = config
+                .description_selector
+                .as_deref()
+                .map(|path| field("description", path));
+            let url = field("url", &config.url);
+            let image_url = config
+                .image_selector
+                .as_deref()
+                .map(|path| field("image", path));
+
+            let url = resolve_url(base_url, &url).unwrap_or_default();
+            let image_url = image_url.and_then(|src| resolve_url(base_url, &src).ok());
+            let description = description
+                .map(|d| clean_text(&d))
+                .filter(|d| !d.is_empty());
+            Event {
+                name,
+                start_date,
+                end_date,
+                location,
+                url,
+                price,
+                description,
+                image_url,
+                warnings,
+                ..Default::default()
+            }
+        })
+        .collect()
+}
+
+/// Follows a dotted path (`venue.address.0.city`) into a JSON value.
+///
+/// # Returns
+///
+/// The value at the path, or `None` if any segment is missing. An empty path is the value itself.
+fn json_path<'a>(value: &'a Value, path: &str) -> Option<&'a Value> {
+    path.split('.')
+        .filter(|segment| !segment.is_empty())
+        .try_fold(value, |value, segment| match value {
+            Value::Array(items) => items.get(segment.parse::<usize>().ok()?),
+            _ => value.get(segment),
+        })
+        .filter(|value| !value.is_null())
+}
+
+/// Reads a string, number, or boolean as text.
+fn json_scalar(value: &Value) -> Option<String> {
+    match value {
+        Value::String(s) => Some(s.clone()),
+        Value::Number(n) => Some(n.to_string()),
+        Value::Bool(b) => Some(b.to_string()),
+        _ => None,
+    }
+}
+
+/// Extracts the JSON-LD events embedded in a page, such as an event's detail page.
+///
+/// # Arguments
+///
+/// * `html` - The HTML content of the page.
+/// * `base_url` - The base URL of the site.
+///
+/// # Returns
+///
+/// The events found in the page's JSON-LD script blocks.
+pub fn parse_json_ld(html: &str, base_url: &str) -> Vec<Event> {
+    let config = SiteConfig {
+        strategy: ParseStrategy::JsonLd,
+        ..Default::default()
+    };
+    // The JSON-LD strategy uses no selectors from the config, so there are none to fail
+    parse_html(html, &config, base_url).unwrap_or_default()
+}
+
+/// Finds the link to the next page of a paginated listing.
+///
+/// # Arguments
+///
+/// * `html` - The HTML content of the current page.
+/// * `config` - Site configuration; nothing is returned unless it sets `next_page_selector`.
+/// * `base_url` - The base URL of the site for resolving a relative link.
+///
+/// # Returns
+///
+/// The absolute URL of the next page, `None` if there is no next-page link, or the
+/// `ConfigError` if `next_page_selector` doesn't parse.
+pub fn next_page_url(
+    html: &str,
+    config: &SiteConfig,
+    base_url: &str,
+) -> Result<Option<String>, ConfigError> {
+    let Some((_, selector)) =
+        compile_optional("next_page_selector", config.next_page_selector.as_deref())?
+    else {
+        return Ok(None);
+    };
+    let document = Html::parse_document(html);
+    let href = document
+        .select(&selector)
+        .next()
+        .and_then(|link| link.value().attr("href"));
+    Ok(href.and_then(|href| resolve_url(&document_base(&document, base_url), href).ok()))
+}
+
+/// Returns the base URL relative links in a document resolve against.
+///
+/// A `<base href>` element takes precedence over the caller's base URL; a relative one is
+/// itself resolved against the caller's base URL.
+///
+/// # Arguments
+///
+/// * `document` - The parsed document.
+/// * `base_url` - The base URL of the site.
+///
+/// # Returns
+///
+/// The document's `<base href>` as an absolute URL, or `base_url` if there is none or it
+/// can't be resolved.
+fn document_base(document: &Html, base_url: &str) -> String {
+    let selector = Selector::parse("base[href]").unwrap();
+    document
+        .select(&selector)
+        .next()
+        .and_then(|base| base.value().attr("href"))
+        .and_then(|href| resolve_url(base_url, href).ok())
+        .unwrap_or_else(|| base_url.to_string())
+}
+
+/// Why an href couldn't be turned into a link to an event.
+#[derive(Debug, Clone, PartialEq)]
+pub enum UrlError {
+    /// The href was empty.
+    Empty,
+    /// The href only names a fragment (`#tickets`) of the page it appears on.
+    FragmentOnly,
+    /// The base URL, or the href resolved against it, is not a valid URL.
+    Invalid(url::ParseError),
+    /// The href uses a scheme that isn't a web page, such as `javascript:` or `mailto:`.
+    UnsupportedScheme(String),
+}
+
+/// Resolves a relative URL against a base URL.
+///
+/// Absolute `http`/`https` URLs are returned as-is and protocol-relative URLs (`//host/path`)
+/// take the base URL's scheme.
+///
+/// # Arguments
+///
+/// * `base` - The base URL as a string slice.
+/// * `relative` - The relative URL to be resolved against the base URL.
+///
+/// # Returns
+///
+/// A `String` representing the absolute URL, or a `UrlError` if the href is empty or
+/// fragment-only, either URL is malformed, or the result isn't an `http`/`https` URL.
+pub fn resolve_url(base: &str, relative: &str) -> Result<String, UrlError> {
+    let relative = relative.trim();
+    if relative.is_empty() {
+        return Err(UrlError::Empty);
+    }
+    if relative.starts_with('#') {
+        return Err(UrlError::FragmentOnly);
+    }
+    let base_url = Url::parse(base).map_err(UrlError::Invalid)?;
+    let url = base_url.join(relative).map_err(UrlError::Invalid)?;
+    match url.scheme() {
+        "http" | "https" => Ok(url.to_string()),
+        scheme => Err(UrlError::UnsupportedScheme(scheme.to_string())),
+    }
+}
+
+/// Represents an event with its details extracted from HTML content.
+///
+/// `end_date` and `location` are `None` when the source doesn't provide them at all, and
+/// `Some("")` when the configured selector matched nothing. `description` is only extracted
+/// from JSON-LD and is already sanitized and bounded. `price` is the raw price text, if any.
+/// `image_url` is already absolute. `warnings` lists the fields whose selector matched nothing.
+/// `categories` is empty when parsed and filled in from the source it was fetched from, and
+/// `source` is `None` until then too.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct Event {
+    pub name: String,
+    pub start_date: String,
+    pub end_date: Option<String>,
+    /// The time of day, when the source shows it apart from the start date.
+    pub start_time: Option<String>,
+    /// The end time of day, when the source shows it apart from the end date.
+    pub end_time: Option<String>,
+    pub location: Option<String>,
+    pub url: String,
+    pub description: Option<String>,
+    pub price: Option<String>,
+    pub image_url: Option<String>,
+    pub categories: Categories,
+    /// The name of the source the event was fetched from, e.g. `songkick`.
+    pub source: Option<String>,
+    pub status: EventStatus,
+    /// Genre or category labels, as the source wrote them.
+    pub tags: Vec<String>,
+    pub warnings: Vec<FieldWarning>,
+}
+
+/// Serializes an event as an object with the snake_case fields `name`, `start_date`, `url`
+/// (strings); `end_date`, `start_time`, `end_time`, `location`, `description`, `price`,
+/// `image_url`, and `source` (strings or `null`); `categories` and `tags` (arrays of strings);
+/// and `status` (as `EventStatus` displays it). These names are kept stable. `warnings` only
+/// describe the parse that produced the event and aren't serialized.
+impl Serialize for Event {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        self.to_json().serialize(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for Event {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let json = Value::deserialize(deserializer)?;
+        Event::from_json(&json)
+            .ok_or_else(|| D::Error::custom("an event must be an object with a string name"))
+    }
+}
+
+/// Whether an event can still be attended.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum EventStatus {
+    /// Tickets are on sale or the event is scheduled as planned.
+    Available,
+    /// No tickets are left.
+    SoldOut,
+    /// The event won't take place.
+    Cancelled,
+    /// The event was moved to a later date.
+    Postponed,
+    /// The source doesn't say.
+    #[default]
+    Unknown,
+}
+
+impl EventStatus {
+    /// Reads a status from badge text ("Sold out!", "CANCELLED") or a schema.org value
+    /// (`https://schema.org/SoldOut`, `EventPostponed`, `InStock`).
+    ///
+    /// # Returns
+    ///
+    /// The status, or `EventStatus::Unknown` if the text isn't recognized.
+    pub fn from_text(text: &str) -> EventStatus {
+        let text = clean_text(text).to_lowercase();
+        let text = text
+            .rsplit('/')
+            .next()
+            .unwrap_or_default()
+            .replace(['_', '-', ' '], "");
+        if text.contains("soldout") || text.contains("outofstock") {
+            EventStatus::SoldOut
+        } else if text.contains("cancel") {
+            EventStatus::Cancelled
+        } else if text.contains("postpone") || text.contains("movedonline") {
+            EventStatus::Postponed
+        } else if [
+            "available",
+            "instock",
+            "onsale",
+            "tickets",
+            "scheduled",
+            "rescheduled",
+            "preorder",
+            "presale",
+        ]
+        .iter()
+        .any(|word| text.contains(word))
+        {
+            EventStatus::Available
+        } else {
+            EventStatus::Unknown
+        }
+    }
+
+    /// Returns `true` for events that can't be attended: sold out or cancelled.
+    pub fn is_unavailable(self) -> bool {
+        matches!(self, EventStatus::SoldOut | EventStatus::Cancelled)
+    }
+}
+
+impl fmt::Display for EventStatus {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            EventStatus::Available => "available",
+            EventStatus::SoldOut => "sold out",
+            EventStatus::Cancelled => "cancelled",
+            EventStatus::Postponed => "postponed",
+            EventStatus::Unknown => "unknown",
+        })
+    }
+}
+
+impl Event {
+    /// Returns the event in its serialized form, described on the `Serialize` impl.
+    pub fn to_json(&self) -> Value {
+        json!({
+            "name": self.name,
+            "start_date": self.start_date,
+            "end_date": self.end_date,
+            "start_time": self.start_time,
+            "end_time": self.end_time,
+            "location": self.location,
+            "url": self.url,
+            "description": self.description,
+            "price": self.price,
+            "image_url": self.image_url,
+            "categories": self.categories.iter().map(|c| c.to_string()).collect::<Vec<_>>(),
+            "source": self.source,
+            "status": self.status.to_string(),
+            "tags": self.tags,
+        })
+    }
+
+    /// Reads an event from its serialized form; only `name` is required.
+    ///
+    /// # Returns
+    ///
+    /// The event, or `None` if the value isn't an object with a string `name`.
+    pub fn from_json(json: &Value) -> Option<Event> {
+        let text = |key: &str| json[key].as_str().map(str::to_string);
+        let texts = |key: &str| -> Vec<String> {
+            json[key]
+                .as_array()
+                .map(|items| {
+                    items
+                        .iter()
+                        .filter_map(|t| Some(t.as_str()?.to_string()))
+                        .collect()
+                })
+                .unwrap_or_default()
+        };
+        Some(Event {
+            name: text("name")?,
+            start_date: text("start_date").unwrap_or_default(),
+            end_date: text("end_date"),
+            start_time: text("start_time"),
+            end_time: text("end_time"),
+            location: text("location"),
+            url: text("url").unwrap_or_default(),
+            description: text("description"),
+            price: text("price"),
+            image_url: text("image_url"),
+            categories: texts("categories")
+                .iter()
+                .filter_map(|c| c.parse().ok())
+                .collect(),
+            source: text("source"),
+            status: text("status")
+                .map(|s| EventStatus::from_text(&s))
+                .unwrap_or_default(),
+            tags: texts("tags"),
+            warnings: Vec::new(),
+        })
+    }
+
+    /// Fills in the start date, end date, location, and price from `detail` where this event
+    /// has none, without overwriting anything the listing already provided.
+    ///
+    /// Warnings for fields that were filled in are dropped.
+    ///
+    /// # Arguments
+    ///
+    /// * `detail` - The event parsed from this event's detail page.
+    pub fn fill_missing(&mut self, detail: Event) {
+        fn is_blank(value: &Option<String>) -> bool {
+            value.as_deref().is_none_or(|v| clean_text(v).is_empty())
+        }
+
+        if clean_text(&self.start_date).is_empty() && !detail.start_date.is_empty() {
+            self.start_date = detail.start_date;
+        }
+        if is_blank(&self.end_date) && !is_blank(&detail.end_date) {
+            self.end_date = detail.end_date;
+        }
+        if is_blank(&self.location) && !is_blank(&detail.location) {
+            self.location = detail.location;
+        }
+        if is_blank(&self.price) && !is_blank(&detail.price) {
+            self.price = detail.price;
+        }
+        if self.status == EventStatus::Unknown {
+            self.status = detail.status;
+        }
+        if self.tags.is_empty() {
+            self.tags = detail.tags;
+        }
+
+        let start_date = self.start_date.clone();
+        let (end_date, location, price) = (
+            self.end_date.clone(),
+            self.location.clone(),
+            self.price.clone(),
+        );
+        self.warnings.retain(|w| {
+            w.kind != WarningKind::NoMatch
+                || match w.field {
+                    "start_date" => clean_text(&start_date).is_empty(),
+                    "end_date" => is_blank(&end_date),
+                    "location" => is_blank(&location),
+                    "price" => is_blank(&price),
+                    _ => true,
+                }
+        });
+    }
+}
+
+/// A field that could not be extracted cleanly for one event.
+#[derive(Debug, Clone, PartialEq)]
+pub struct FieldWarning {
+    /// The position of the event's element among all elements matched on the page.
+    pub event_index: usize,
+    /// The affected field, e.g. `"location"`.
+    pub field: &'static str,
+    /// The selector or JSON-LD key that matched nothing, the pattern that didn't match, or the
+    /// href that couldn't be resolved.
+    pub selector: String,
+    /// What went wrong.
+    pub kind: WarningKind,
+}
+
+/// Why a `FieldWarning` was recorded.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WarningKind {
+    /// The selector matched nothing, so the field is empty.
+    NoMatch,
+    /// The field's post-processing pattern didn't match, so the raw text was kept.
+    PatternMismatch,
+    /// The link's href couldn't be resolved to a web URL, so the field is empty.
+    UnresolvableUrl,
+    /// The selector found no events, so the page's meta tags were read instead.
+    MetaFallback,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sources::Category;
+
+    #[test]
+    fn test_event_serde_round_trip() {
+        let event = Event {
+            name: "Jazz Night".to_string(),
+            start_date: "May 2".to_string(),
+            start_time: Some("7:30 PM".to_string()),
+            location: Some("Café Lex".to_string()),
+            url: "https://example.com/jazz".to_string(),
+            price: Some("$10".to_string()),
+            categories: Categories::from([Category::Music, Category::Unique]),
+            source: Some("perto".to_string()),
+            status: EventStatus::Postponed,
+            tags: vec!["Jazz".to_string()],
+            ..Default::default()
+        };
+
+        let text = serde_json::to_string(&event).unwrap();
+        assert_eq!(serde_json::from_str::<Event>(&text).unwrap(), event);
+        let json: Value = serde_json::from_str(&text).unwrap();
+        assert_eq!(json.as_object().unwrap().len(), 14);
+        assert_eq!(
+            (json["start_time"].as_str(), json["end_time"].as_str()),
+            (Some("7:30 PM"), None)
+        );
+
+        // Parse diagnostics aren't part of the serialized form
+        let warned = Event {
+            warnings: vec![FieldWarning {
+                event_index: 0,
+                field: "price",
+                selector: ".price".to_string(),
+                kind: WarningKind::NoMatch,
+            }],
+            ..event.clone()
+        };
+        assert_eq!(
+            serde_json::from_str::<Event>(&serde_json::to_string(&warned).unwrap()).unwrap(),
+            event
+        );
+        assert!(serde_json::from_str::<Event>("[]").is_err());
+    }
+
+    #[test]
+    fn test_parse_html() {
+        // Mock HTML content
+        let html = r#"
+            <div class="event">
+                <h2 class="name">Event Name</h2>
+                <span class="start-date">2023-01-01</span>
+                <span class="end-date">2023-01-02</span>
+                <span class="location">Event Location</span>
+                <a class="url" href="http://example.com/event">Event Link</a>
+            </div>
+        "#;
+
+        // Configure SiteConfig with selectors that match the mock HTML
+        let config = SiteConfig {
+            event_selector: ".event".to_string(),
+            name_selector: ".name".to_string(),
+            start_date_selector: ".start-date".to_string(),
+            end_date_selector: Some(".end-date".to_string()),
+            location_selector: Some(".location".to_string()),
+            url: ".url".to_string(),
+            ..Default::default()
+        };
+
+        // Base URL for resolving relative URLs
+        let base_url = "http://example.com";
+
+        // Call the parse_html function
+        let events = parse_html(html, &config, base_url).unwrap();
+
+        // Expected event
+        let expected_event = Event {
+            name: "Event Name".to_string(),
+            start_date: "2023-01-01".to_string(),
+            end_date: Some("2023-01-02".to_string()),
+            location: Some("Event Location".to_string()),
+            url: "http://example.com/event".to_string(),
+            ..Default::default()
+        };
+
+        // Assertions
+        assert_eq!(events.len(), 1);
+        assert_eq!(events[0], expected_event);
+    }
+
+    fn time_config() -> SiteConfig {
+        SiteConfig {
+            event_selector: ".event".to_string(),
+            name_selector: ".name".to_string(),
+            start_date_selector: "time.start".to_string(),
+            end_date_selector: Some("time.end".to_string()),
+            location_selector: Some(".location".to_string()),
+            url: "a".to_string(),
+            start_date_attr: Some("datetime".to_string()),
+            end_date_attr: Some("datetime".to_string()),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_parse_html_reads_attribute_when_present() {
+        let html = r#"
+            <div class="event">
+                <h2 class="name">Show</h2>
+                <time class="start" datetime="2024-05-02T19:00:00-05:00">May 2 @ 7:00 pm</time>
+                <time class="end" datetime="2024-05-02T22:00:00-05:00">10:00 pm</time>
+                <span class="location">Venue</span>
+                <a href="/show">Link</a>
+            </div>
+        "#;
+
+        let events = parse_html(html, &time_config(), "http://example.com").unwrap();
+
+        assert_eq!(events[0].start_date, "2024-05-02T19:00:00-05:00");
+        assert_eq!(
+            events[0].end_date.as_deref(),
+            Some("2024-05-02T22:00:00-05:00")
+        );
+        assert_eq!(events[0].url, "http://example.com/show");
+    }
+
+    #[test]
+    fn test_parse_html_falls_back_to_text_when_attribute_missing() {
+        let html = r#"
+            <div class="event">
+                <h2 class="name">Show</h2>
+                <time class="start">May 2 @ 7:00 pm</time>
+                <span class="location">Venue</span>
+                <a href="/show">Link</a>
+            </div>
+        "#;
+
+        let events = parse_html(html, &time_config(), "http://example.com").unwrap();
+
+        assert_eq!(events[0].start_date, "May 2 @ 7:00 pm");
+        assert_eq!(events[0].end_date.as_deref(), Some(""));
+    }
+
+    #[test]
+    fn test_parse_html_custom_url_attribute() {
+        let html = r#"
+            <div class="event">
+                <h2 class="name">Show</h2>
+                <a class="url" data-href="/real" href="/tracking">Link</a>
+            </div>
+        "#;
+        let config = SiteConfig {
+            event_selector: ".event".to_string(),
+            name_selector: ".name".to_string(),
+            start_date_selector: ".start".to_string(),
+            end_date_selector: None,
+            location_selector: None,
+            url: ".url".to_string(),
+            url_attr: Some("data-href".to_string()),
+            ..Default::default()
+        };
+
+        let events = parse_html(html, &config, "http://example.com").unwrap();
+
+        assert_eq!(events[0].url, "http://example.com/real");
+    }
+
+    #[test]
+    fn test_parse_html_without_optional_selectors() {
+        let html = r#"
+            <div class="event">
+                <h2 class="name">Show</h2>
+                <span class="start">May 2</span>
+                <span class="location">Venue</span>
+            </div>
+        "#;
+        let config = SiteConfig {
+            event_selector: ".event".to_string(),
+            name_selector: ".name".to_string(),
+            start_date_selector: ".start".to_string(),
+            end_date_selector: None,
+            location_selector: None,
+            url: "a".to_string(),
+            ..Default::default()
+        };
+
+        let events = parse_html(html, &config, "http://example.com").unwrap();
+
+        assert_eq!(events[0].end_date, None);
+        assert_eq!(events[0].location, None);
+    }
+
+    fn json_ld_config() -> SiteConfig {
+        SiteConfig {
+            strategy: ParseStrategy::JsonLd,
+            ..Default::default()
+        }
+    }
+
+    fn json_ld_page(descriptions: &[&str]) -> String {
+        let events: Vec<Value> = descriptions
+            .iter()
+            .enumerate()
+            .map(|(i, d)| serde_json::json!({ "@type": "Event", "name": format!("Event {}", i), "description": d }))
+            .collect();
+        format!(
+            r#"<script type="application/ld+json">{}</script>"#,
+            Value::Array(events)
+        )
+    }
+
+    #[test]
+    fn test_json_ld_description_is_sanitized() {
+        let html = json_ld_page(&["<p>Live&nbsp;music &amp; <b>dancing</b></p>\n\n   all   night"]);
+
+        let events = parse_html(&html, &json_ld_config(), "http://example.com").unwrap();
+
+        assert_eq!(
+            events[0].description.as_deref(),
+            Some("Live music & dancing all night")
+        );
+    }
+
+    #[test]
+    fn test_json_ld_description_loses_escaped_markup_but_names_keep_brackets() {
+        let html = r#"<script type="application/ld+json">{"@type": "Event", "name": "Live: &lt;Band Name&gt;", "startDate": "2024-05-02T19:00", "description": "&lt;p&gt;Doors at 7&lt;/p&gt;"}</script>"#;
+
+        let events = parse_html(html, &json_ld_config(), "http://example.com").unwrap();
+
+        assert_eq!(events[0].description.as_deref(), Some("Doors at 7"));
+        assert_eq!(events[0].name, "Live: &lt;Band Name&gt;");
+        assert_eq!(clean_text(&events[0].name), "Live: <Band Name>");
+    }
+
+    #[test]
+    fn test_json_ld_description_is_truncated_at_word_boundary() {
+        let html = json_ld_page(&["one two three four five six"]);
+        let config = SiteConfig {
+            description_length: Some(12),
+            ..json_ld_config()
+        };
+
+        let events = parse_html(&html, &config, "http://example.com").unwrap();
+
+        assert_eq!(events[0].description.as_deref(), Some("one two…"));
+    }
+
+    #[test]
+    fn test_json_ld_shared_disclaimer_is_dropped() {
+        let disclaimer = "All sales are final. ".repeat(25);
+        assert!(disclaimer.len() >= 500);
+        let long_blurb =
+            "An evening of bluegrass standards and new songs from the band's upcoming record, \
+            with a guest fiddler and a late set of requests. "
+                .repeat(20);
+        let descriptions = [
+            format!("Jazz trio. {}", disclaimer),
+            format!("Comedy night. {}", disclaimer),
+            format!("{} {}", long_blurb, disclaimer),
+        ];
+        let html = json_ld_page(&descriptions.iter().map(String::as_str).collect::<Vec<_>>());
+        let config = SiteConfig {
+            description_length: Some(10_000),
+            ..json_ld_config()
+        };
+
+        let events = parse_html(&html, &config, "http://example.com").unwrap();
+
+        // Mostly-boilerplate descriptions disappear; long ones lose only the disclaimer
+        assert_eq!(events[0].description, None);
+        assert_eq!(events[1].description, None);
+        let kept = events[2].description.as_deref().unwrap();
+        assert!(kept.starts_with("An evening of bluegrass"));
+        assert!(!kept.contains("All sales are final"));
+    }
+
+    #[test]
+    fn test_json_ld_distinct_descriptions_are_kept() {
+        let html = json_ld_page(&["A night of jazz standards.", "A night of stand-up comedy."]);
+
+        let events = parse_html(&html, &json_ld_config(), "http://example.com").unwrap();
+
+        assert_eq!(
+            events[0].description.as_deref(),
+            Some("A night of jazz standards.")
+        );
+        assert_eq!(
+            events[1].description.as_deref(),
+            Some("A night of stand-up comedy.")
+        );
+    }
+
+    #[test]
+    fn test_json_ld_multiple_scripts_only_events_extracted() {
+        let html = r#"
+            <html><head>
+            <script type="application/ld+json">{"@type": "Organization", "name": "Venue Inc"}</script>
+            <script type="application/ld+json">
+                {"@type": "BreadcrumbList", "itemListElement": [{"@type": "ListItem", "name": "Home"}]}
+            </script>
+            <script type="application/ld+json">
+                [
+                    {"@type": "MusicEvent", "name": "Concert", "startDate": "2024-05-02T19:00"},
+                    {"@type": "Event", "name": "Market", "startDate": "2024-05-03"}
+                ]
+            </script>
+            </head></html>
+        "#;
+
+        let events = parse_html(html, &json_ld_config(), "http://example.com").unwrap();
+
+        let names: Vec<&str> = events.iter().map(|e| e.name.as_str()).collect();
+        assert_eq!(names, vec!["Concert", "Market"]);
+        assert_eq!(events[0].start_date, "2024-05-02T19:00");
+    }
+
+    #[test]
+    fn test_json_ld_graph_is_descended() {
+        let html = r#"
+            <script type="application/ld+json">
+                {
+                    "@context": "https://schema.org",
+                    "@graph": [
+                        {"@type": "WebSite", "name": "Calendar"},
+                        {"@type": ["Event", "Thing"], "name": "Festival Day", "location": {"name": "Park"}},
+                        {"@type": "https://schema.org/TheaterEvent", "name": "Play"}
+                    ]
+                }
+            </script>
+        "#;
+
+        let events = parse_html(html, &json_ld_config(), "http://example.com").unwrap();
+
+        let names: Vec<&str> = events.iter().map(|e| e.name.as_str()).collect();
+        assert_eq!(names, vec!["Festival Day", "Play"]);
+        assert_eq!(events[0].location.as_deref(), Some("Park"));
+    }
+
+    fn json_ld_location_of(location: Value) -> Option<String> {
+        let json = serde_json::json!({ "@type": "Event", "name": "Show", "location": location });
+        let html = format!(r#"<script type="application/ld+json">{}</script>"#, json);
+        parse_html(&html, &json_ld_config(), "http://example.com")
+            .unwrap()
+            .remove(0)
+            .location
+    }
+
+    #[test]
+    fn test_json_ld_location_as_string() {
+        assert_eq!(
+            json_ld_location_of(serde_json::json!("The Basement")).as_deref(),
+            Some("The Basement")
+        );
+    }
+
+    #[test]
+    fn test_json_ld_location_as_named_place() {
+        let location = serde_json::json!({ "@type": "Place", "name": "Ryman Auditorium", "address": "116 5th Ave N" });
+        assert_eq!(
+            json_ld_location_of(location).as_deref(),
+            Some("Ryman Auditorium")
+        );
+    }
+
+    #[test]
+    fn test_json_ld_location_from_postal_address() {
+        let location = serde_json::json!({
+            "@type": "Place",
+            "address": {
+                "@type": "PostalAddress",
+                "streetAddress": "116 5th Ave N",
+                "addressLocality": "Nashville",
+                "addressRegion": "TN"
+            }
+        });
+        assert_eq!(
+            json_ld_location_of(location).as_deref(),
+            Some("116 5th Ave N, Nashville, TN")
+        );
+    }
+
+    #[test]
+    fn test_json_ld_location_address_as_string() {
+        let location =
+            serde_json::json!({ "@type": "Place", "address": "Centennial Park, Nashville" });
+        assert_eq!(
+            json_ld_location_of(location).as_deref(),
+            Some("Centennial Park, Nashville")
+        );
+    }
+
+    #[test]
+    fn test_json_ld_location_array_takes_first() {
+        let location = serde_json::json!([{ "name": "Exit/In" }, { "name": "The End" }]);
+        assert_eq!(json_ld_location_of(location).as_deref(), Some("Exit/In"));
+    }
+
+    #[test]
+    fn test_json_ld_location_missing_or_unusable() {
+        assert_eq!(json_ld_location_of(Value::Null), None);
+        assert_eq!(
+            json_ld_location_of(serde_json::json!({ "@type": "Place" })),
+            None
+        );
+        assert_eq!(json_ld_location_of(serde_json::json!([])), None);
+    }
+
+    fn json_ld_price_of(offers: Value) -> Option<String> {
+        let json = serde_json::json!({ "@type": "Event", "name": "Show", "offers": offers });
+        let html = format!(r#"<script type="application/ld+json">{}</script>"#, json);
+        parse_html(&html, &json_ld_config(), "http://example.com")
+            .unwrap()
+            .remove(0)
+            .price
+    }
+
+    #[test]
+    fn test_json_ld_price_single_offer() {
+        let offers =
+            serde_json::json!({ "@type": "Offer", "price": "25.00", "priceCurrency": "USD" });
+        assert_eq!(json_ld_price_of(offers).as_deref(), Some("25 USD"));
+    }
+
+    #[test]
+    fn test_json_ld_price_numeric_without_currency() {
+        assert_eq!(
+            json_ld_price_of(serde_json::json!({ "price": 12.5 })).as_deref(),
+            Some("12.5")
+        );
+    }
+
+    #[test]
+    fn test_json_ld_price_array_of_offers_is_a_range() {
+        let offers = serde_json::json!([
+            { "price": 40, "priceCurrency": "USD" },
+            { "price": "15", "priceCurrency": "USD" },
+            { "price": 25, "priceCurrency": "USD" }
+        ]);
+        assert_eq!(json_ld_price_of(offers).as_deref(), Some("15-40 USD"));
+    }
+
+    #[test]
+    fn test_json_ld_price_aggregate_offer() {
+        let offers =
+            serde_json::json!({ "@type": "AggregateOffer", "lowPrice": "10", "highPrice": "30" });
+        assert_eq!(json_ld_price_of(offers).as_deref(), Some("10-30"));
+    }
+
+    #[test]
+    fn test_json_ld_price_zero_and_free() {
+        assert_eq!(
+            json_ld_price_of(serde_json::json!({ "price": "0", "priceCurrency": "USD" }))
+                .as_deref(),
+            Some("0 USD")
+        );
+        assert_eq!(
+            json_ld_price_of(serde_json::json!({ "price": "Free" })).as_deref(),
+            Some("Free")
+        );
+        assert_eq!(
+            json_ld_price_of(serde_json::json!({ "url": "http://tickets" })),
+            None
+        );
+        assert_eq!(json_ld_price_of(Value::Null), None);
+    }
+
+    #[test]
+    fn test_parse_html_price_selector() {
+        let html = r#"
+            <div class="event"><h2 class="name">Paid</h2><span class="price">$25</span></div>
+            <div class="event"><h2 class="name">Unpriced</h2></div>
+        "#;
+        let config = SiteConfig {
+            event_selector: ".event".to_string(),
+            name_selector: ".name".to_string(),
+            start_date_selector: ".start".to_string(),
+            url: "a".to_string(),
+            price_selector: Some(".price".to_string()),
+            ..Default::default()
+        };
+
+        let events = parse_html(html, &config, "http://example.com").unwrap();
+
+        assert_eq!(events[0].price.as_deref(), Some("$25"));
+        assert_eq!(events[1].price.as_deref(), Some(""));
+    }
+
+    #[test]
+    fn test_parse_html_description_and_image() {
+        let html = r#"
+            <div class="event">
+                <h2 class="name">Show</h2>
+                <p class="blurb">An evening of &amp; music</p>
+                <img class="thumb" src="/images/show.jpg">
+            </div>
+            <div class="event"><h2 class="name">Bare</h2></div>
+        "#;
+        let config = SiteConfig {
+            event_selector: ".event".to_string(),
+            name_selector: ".name".to_string(),
+            start_date_selector: ".start".to_string(),
+            url: "a".to_string(),
+            description_selector: Some(".blurb".to_string()),
+            image_selector: Some("img.thumb".to_string()),
+            ..Default::default()
+        };
+
+        let events = parse_html(html, &config, "http://example.com").unwrap();
+
+        assert_eq!(
+            events[0].description.as_deref(),
+            Some("An evening of &amp; music")
+        );
+        assert_eq!(
+            events[0].image_url.as_deref(),
+            Some("http://example.com/images/show.jpg")
+        );
+        assert_eq!(events[1].description.as_deref(), Some(""));
+        assert_eq!(events[1].image_url, None);
+    }
+
+    #[test]
+    fn test_json_ld_image_shapes() {
+        let image_of = |image: Value| {
+            let json = serde_json::json!({ "@type": "Event", "name": "Show", "image": image });
+            let html = format!(r#"<script type="application/ld+json">{}</script>"#, json);
+            parse_html(&html, &json_ld_config(), "http://example.com")
+                .unwrap()
+                .remove(0)
+                .image_url
+        };
+
+        assert_eq!(
+            image_of(serde_json::json!("http://img/a.jpg")).as_deref(),
+            Some("http://img/a.jpg")
+        );
+        assert_eq!(
+            image_of(serde_json::json!(["http://img/b.jpg", "http://img/c.jpg"])).as_deref(),
+            Some("http://img/b.jpg")
+        );
+        assert_eq!(
+            image_of(serde_json::json!({ "@type": "ImageObject", "url": "http://img/d.jpg" }))
+                .as_deref(),
+            Some("http://img/d.jpg")
+        );
+        assert_eq!(image_of(Value::Null), None);
+    }
+
+    #[test]
+    fn test_drop_nameless_skips_ad_cards() {
+        let html = r#"
+            <div class="event"><h2 class="name">Real Show</h2><a href="/1">x</a></div>
+            <div class="event"><div class="ad">Sponsored</div></div>
+            <div class="event"><h2 class="name">Another Show</h2><a href="/2">x</a></div>
+            <div class="event"><h2 class="name">  <span></span> </h2></div>
+        "#;
+        let config = SiteConfig {
+            event_selector: ".event".to_string(),
+            name_selector: ".name".to_string(),
+            start_date_selector: ".start".to_string(),
+            url: "a".to_string(),
+            ..Default::default()
+        };
+        let events = parse_html(html, &config, "http://example.com").unwrap();
+        assert_eq!(events.len(), 4);
+
+        let (kept, skipped) = drop_nameless(events.clone(), false);
+        let names: Vec<&str> = kept.iter().map(|e| e.name.as_str()).collect();
+        assert_eq!(names, vec!["Real Show", "Another Show"]);
+        assert_eq!(skipped, 2);
+
+        let (kept, skipped) = drop_nameless(events, true);
+        assert_eq!(kept.len(), 4);
+        assert_eq!(skipped, 2);
+    }
+
+    #[test]
+    fn test_parse_html_warns_about_broken_fields() {
+        let html = r#"
+            <div class="event">
+                <h2 class="name">Complete</h2>
+                <span class="start">May 2</span>
+                <span class="location">Ryman</span>
+                <a href="/1">x</a>
+            </div>
+            <div class="event">
+                <h2 class="name">No venue</h2>
+                <span class="start">May 3</span>
+                <a href="/2">x</a>
+            </div>
+            <div class="event">
+                <h2 class="name">Nothing else</h2>
+            </div>
+        "#;
+        let config = SiteConfig {
+            event_selector: ".event".to_string(),
+            name_selector: ".name".to_string(),
+            start_date_selector: ".start".to_string(),
+            location_selector: Some(".location".to_string()),
+            url: "a".to_string(),
+            ..Default::default()
+        };
+
+        let events = parse_html(html, &config, "http://example.com").unwrap();
+
+        assert!(events[0].warnings.is_empty());
+        assert_eq!(
+            events[1].warnings,
+            vec![FieldWarning {
+                event_index: 1,
+                field: "location",
+                selector: ".location".to_string(),
+                kind: WarningKind::NoMatch
+            },]
+        );
+        let fields: Vec<&str> = events[2].warnings.iter().map(|w| w.field).collect();
+        assert_eq!(fields, vec!["start_date", "location", "url"]);
+        assert!(events[2].warnings.iter().all(|w| w.event_index == 2));
+    }
+
+    #[test]
+    fn test_json_ld_warns_about_missing_keys() {
+        let html =
+            r#"<script type="application/ld+json">{"@type": "Event", "name": "Show"}</script>"#;
+
+        let events = parse_html(html, &json_ld_config(), "http://example.com").unwrap();
+
+        let fields: Vec<&str> = events[0].warnings.iter().map(|w| w.field).collect();
+        assert_eq!(fields, vec!["start_date", "location"]);
+        assert_eq!(events[0].warnings[0].selector, "startDate");
+    }
+
+    #[test]
+    fn test_parse_html_skips_excluded_elements() {
+        let html = r#"
+            <div class="pt_col"><a href="/1"><strong>Real show</strong></a></div>
+            <div class="pt_col promoted"><a href="/ad1"><strong>Sponsored</strong></a></div>
+            <div class="pt_col"><span class="sponsored-badge">Ad</span><a href="/ad2"><strong>Sponsored</strong></a></div>
+            <div class="pt_col"><a href="/2"><strong>Another show</strong></a></div>
+        "#;
+        let config = SiteConfig {
+            event_selector: ".pt_col".to_string(),
+            name_selector: "a > strong".to_string(),
+            start_date_selector: ".date".to_string(),
+            url: "a".to_string(),
+            exclude_selector: Some(".promoted, .sponsored-badge".to_string()),
+            ..Default::default()
+        };
+
+        let events = parse_html(html, &config, "http://example.com").unwrap();
+
+        let names: Vec<&str> = events.iter().map(|e| e.name.as_str()).collect();
+        assert_eq!(names, vec!["Real show", "Another show"]);
+        assert_eq!(events[1].warnings[0].event_index, 3);
+    }
+
+    #[test]
+    fn test_next_page_url() {
+        let html = r#"<nav><a class="next" href="/events/page/2/">Next</a></nav>"#;
+        let mut config = SiteConfig::default();
+
+        assert_eq!(
+            next_page_url(html, &config, "http://example.com").unwrap(),
+            None
+        );
+
+        config.next_page_selector = Some("a.next".to_string());
+        assert_eq!(
+            next_page_url(html, &config, "http://example.com").unwrap(),
+            Some("http://example.com/events/page/2/".to_string())
+        );
+        assert_eq!(
+            next_page_url("<p>last page</p>", &config, "http://example.com").unwrap(),
+            None
+        );
+    }
+
+    #[test]
+    fn test_bad_selectors_are_errors_not_panics() {
+        let html = r#"<div class="event"><h2>Show</h2><a class="next" href="/2">Next</a></div>"#;
+        let config = SiteConfig {
+            event_selector: ".event".to_string(),
+            name_selector: "h2".to_string(),
+            start_date_selector: "time".to_string(),
+            url: "a".to_string(),
+            price_selector: Some("span[".to_string()),
+            next_page_selector: Some("a..next".to_string()),
+            ..Default::default()
+        };
+
+        let error = parse_html(html, &config, "http://example.com").unwrap_err();
+        assert_eq!(error.field, "price_selector");
+        assert_eq!(
+            error.to_string(),
+            "price_selector: invalid CSS selector 'span['"
+        );
+        assert_eq!(
+            debug_selectors(html, &config, Zone::default())
+                .unwrap_err()
+                .field,
+            "price_selector"
+        );
+        assert_eq!(
+            next_page_url(html, &config, "http://example.com")
+                .unwrap_err()
+                .field,
+            "next_page_selector"
+        );
+
+        let config = SiteConfig {
+            event_selector: String::new(),
+            ..config
+        };
+        assert_eq!(
+            parse_html(html, &config, "http://example.com")
+                .unwrap_err()
+                .field,
+            "event_selector"
+        );
+    }
+
+    #[test]
+    fn test_fill_missing_keeps_listing_fields() {
+        let mut listing = Event {
+            name: "Show".to_string(),
+            start_date: String::new(),
+            location: Some("Ryman".to_string()),
+            price: Some(String::new()),
+            warnings: vec![
+                FieldWarning {
+                    event_index: 0,
+                    field: "start_date",
+                    selector: ".time".to_string(),
+                    kind: WarningKind::NoMatch,
+                },
+                FieldWarning {
+                    event_index: 0,
+                    field: "price",
+                    selector: ".price".to_string(),
+                    kind: WarningKind::NoMatch,
+                },
+            ],
+            ..Default::default()
+        };
+        let detail = Event {
+            name: "Show (detail)".to_string(),
+            start_date: "2024-05-02T20:00:00-05:00".to_string(),
+            end_date: Some("2024-05-02T23:00:00-05:00".to_string()),
+            location: Some("Somewhere else".to_string()),
+            price: Some("25 USD".to_string()),
+            ..Default::default()
+        };
+
+        listing.fill_missing(detail);
+
+        assert_eq!(listing.name, "Show");
+        assert_eq!(listing.start_date, "2024-05-02T20:00:00-05:00");
+        assert_eq!(
+            listing.end_date.as_deref(),
+            Some("2024-05-02T23:00:00-05:00")
+        );
+        assert_eq!(listing.location.as_deref(), Some("Ryman"));
+        assert_eq!(listing.price.as_deref(), Some("25 USD"));
+        assert!(listing.warnings.is_empty());
+    }
+
+    #[test]
+    fn test_parse_html_microdata() {
+        let html = r#"
+            <div itemscope itemtype="https://schema.org/Organization"><span itemprop="name">The Venue</span></div>
+            <article itemscope itemtype="https://schema.org/MusicEvent">
+                <h2><a itemprop="url" href="/events/jazz-night"><span itemprop="name">Jazz Night</span></a></h2>
+                <time itemprop="startDate" datetime="2024-05-02T20:00">Thursday at 8</time>
+                <meta itemprop="endDate" content="2024-05-02T23:00">
+                <div itemprop="location" itemscope itemtype="https://schema.org/Place">
+                    <span itemprop="name">Station Inn</span>
+                    <div itemprop="address" itemscope itemtype="https://schema.org/PostalAddress">
+                        <span itemprop="streetAddress">402 12th Ave S</span>
+                        <span itemprop="addressLocality">Nashville</span>
+                    </div>
+                </div>
+                <img itemprop="image" src="/img/jazz.jpg" alt="">
+                <p itemprop="description">An evening of <b>bebop</b>.</p>
+                <div itemprop="offers" itemscope itemtype="https://schema.org/Offer">
+                    <span itemprop="price" content="15">$15</span>
+                    <meta itemprop="priceCurrency" content="USD">
+                </div>
+            </article>
+            <article itemscope itemtype="https://schema.org/Event">
+                <span itemprop="name">Open Mic</span>
+                <div itemprop="location" itemscope itemtype="https://schema.org/Place">
+                    <div itemprop="address" itemscope itemtype="https://schema.org/PostalAddress">
+                        <span itemprop="streetAddress">1 Main St</span>
+                        <span itemprop="addressLocality">Nashville</span>
+                    </div>
+                </div>
+            </article>
+        "#;
+        let config = SiteConfig {
+            strategy: ParseStrategy::Microdata,
+            ..Default::default()
+        };
+
+        let events = parse_html(html, &config, "http://example.com").unwrap();
+
+        assert_eq!(events.len(), 2);
+        assert_eq!(events[0].name, "Jazz Night");
+        assert_eq!(events[0].start_date, "2024-05-02T20:00");
+        assert_eq!(events[0].end_date.as_deref(), Some("2024-05-02T23:00"));
+        assert_eq!(events[0].location.as_deref(), Some("Station Inn"));
+        assert_eq!(events[0].url, "http://example.com/events/jazz-night");
+        assert_eq!(
+            events[0].image_url.as_deref(),
+            Some("http://example.com/img/jazz.jpg")
+        );
+        assert_eq!(
+            events[0].description.as_deref(),
+            Some("An evening of bebop.")
+        );
+        assert_eq!(events[0].price.as_deref(), Some("15 USD"));
+        assert!(events[0].warnings.is_empty());
+
+        assert_eq!(events[1].name, "Open Mic");
+        assert_eq!(events[1].location.as_deref(), Some("1 Main St, Nashville"));
+        let fields: Vec<&str> = events[1].warnings.iter().map(|w| w.field).collect();
+        assert_eq!(fields, vec!["start_date"]);
+    }
+
+    fn regex_config() -> SiteConfig {
+        SiteConfig {
+            event_selector: ".event".to_string(),
+            name_selector: ".name".to_string(),
+            start_date_selector: ".time".to_string(),
+            location_selector: Some(".location".to_string()),
+            url: "a".to_string(),
+            start_date_regex: Some(r"^(.+?)(?:\s*—.*)?$".to_string()),
+            location_regex: Some(r"^(.+?)\s*·".to_string()),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_field_regex_extracts_capture_group() {
+        let html = r#"<div class="event"><h2 class="name">Show</h2>
+            <span class="time">Thursday 2 May 2024, 7:00 PM — Doors 6:00</span>
+            <span class="location">Exit/In · Nashville, TN</span><a href="/1">x</a></div>"#;
+
+        let events = parse_html(html, &regex_config(), "http://example.com").unwrap();
+
+        assert_eq!(events[0].start_date, "Thursday 2 May 2024, 7:00 PM");
+        assert_eq!(events[0].location.as_deref(), Some("Exit/In"));
+        assert!(events[0].warnings.is_empty());
+    }
+
+    #[test]
+    fn test_field_regex_mismatch_keeps_raw_text_and_warns() {
+        let html = r#"<div class="event"><h2 class="name">Show</h2><span class="time">Tonight</span>
+            <span class="location">Exit/In</span><a href="/1">x</a></div>"#;
+
+        let events = parse_html(html, &regex_config(), "http://example.com").unwrap();
+
+        assert_eq!(events[0].start_date, "Tonight");
+        assert_eq!(events[0].location.as_deref(), Some("Exit/In"));
+        assert_eq!(
+            events[0].warnings,
+            vec![FieldWarning {
+                event_index: 0,
+                field: "location",
+                selector: r"^(.+?)\s*·".to_string(),
+                kind: WarningKind::PatternMismatch,
+            }]
+        );
+    }
+
+    #[test]
+    fn test_validate_rejects_invalid_regex() {
+        assert_eq!(regex_config().validate(), Ok(()));
+
+        let config = SiteConfig {
+            price_regex: Some(r"(\d+".to_string()),
+            ..regex_config()
+        };
+        let errors = config.validate().unwrap_err();
+        assert_eq!(errors.len(), 1);
+        assert_eq!(errors[0].field, "price_regex");
+        assert!(
+            errors[0]
+                .to_string()
+                .starts_with(r"price_regex: invalid regex '(\d+':"),
+            "{}",
+            errors[0]
+        );
+    }
+
+    #[test]
+    fn test_validate_reports_every_problem() {
+        let config = SiteConfig {
+            event_selector: ".event".to_string(),
+            name_selector: "h2[".to_string(),
+            start_date_selector: String::new(),
+            url: "a".to_string(),
+            location_selector: Some(">>".to_string()),
+            start_date_attr: Some("date time".to_string()),
+            url_attr: Some(String::new()),
+            name_regex: Some("(".to_string()),
+            max_pages: Some(0),
+            ..Default::default()
+        };
+
+        let errors = config.validate().unwrap_err();
+
+        let fields: Vec<&str> = errors.iter().map(|e| e.field).collect();
+        assert_eq!(
+            fields,
+            vec![
+                "name_selector",
+                "start_date_selector",
+                "location_selector",
+                "start_date_attr",
+                "url_attr",
+                "name_regex",
+                "max_pages",
+            ]
+        );
+        assert_eq!(
+            errors[0].to_string(),
+            "name_selector: invalid CSS selector 'h2['"
+        );
+        assert_eq!(
+            errors[3].to_string(),
+            "start_date_attr: invalid attribute name 'date time'"
+        );
+        assert_eq!(errors[6].to_string(), "max_pages: must be at least 1");
+    }
+
+    #[test]
+    fn test_validate_skips_css_selectors_for_other_strategies() {
+        assert_eq!(json_ld_config().validate(), Ok(()));
+
+        let config = SiteConfig {
+            next_page_selector: Some("[".to_string()),
+            ..json_ld_config()
+        };
+        assert_eq!(
+            config.validate().unwrap_err()[0].field,
+            "next_page_selector"
+        );
+    }
+
+    #[test]
+    fn test_resolve_url_href_shapes() {
+        let base = "https://example.com/events/";
+        let cases: &[(&str, Result<&str, UrlError>)] = &[
+            ("/show/1", Ok("https://example.com/show/1")),
+            ("show/1", Ok("https://example.com/events/show/1")),
+            ("http://other.com/x", Ok("http://other.com/x")),
+            (
+                "//cdn.example.com/img.jpg",
+                Ok("https://cdn.example.com/img.jpg"),
+            ),
+            ("  /padded  ", Ok("https://example.com/padded")),
+            ("#tickets", Err(UrlError::FragmentOnly)),
+            ("", Err(UrlError::Empty)),
+            ("   ", Err(UrlError::Empty)),
+            (
+                "javascript:void(0)",
+                Err(UrlError::UnsupportedScheme("javascript".to_string())),
+            ),
+            (
+                "mailto:box@example.com",
+                Err(UrlError::UnsupportedScheme("mailto".to_string())),
+            ),
+            (
+                "http://[::1",
+                Err(UrlError::Invalid(url::ParseError::InvalidIpv6Address)),
+            ),
+        ];
+
+        for (href, expected) in cases {
+            assert_eq!(
+                resolve_url(base, href),
+                expected.clone().map(str::to_string),
+                "href {:?}",
+                href
+            );
+        }
+        assert_eq!(
+            resolve_url("not a url", "/x"),
+            Err(UrlError::Invalid(url::ParseError::RelativeUrlWithoutBase))
+        );
+    }
+
+    #[test]
+    fn test_parse_html_warns_instead_of_panicking_on_bad_hrefs() {
+        let html = r##"
+            <div class="event"><h2 class="name">Good</h2><a href="/good">x</a></div>
+            <div class="event"><h2 class="name">Script</h2><a href="javascript:void(0)">x</a></div>
+            <div class="event"><h2 class="name">Anchor</h2><a href="#tickets">x</a></div>
+        "##;
+        let config = SiteConfig {
+            event_selector: ".event".to_string(),
+            name_selector: ".name".to_string(),
+            start_date_selector: ".name".to_string(),
+            url: "a".to_string(),
+            ..Default::default()
+        };
+
+        let events = parse_html(html, &config, "http://example.com").unwrap();
+
+        assert_eq!(events[0].url, "http://example.com/good");
+        assert_eq!(events[1].url, "");
+        assert_eq!(
+            events[1].warnings,
+            vec![FieldWarning {
+                event_index: 1,
+                field: "url",
+                selector: "javascript:void(0)".to_string(),
+                kind: WarningKind::UnresolvableUrl,
+            }]
+        );
+        assert_eq!(events[2].warnings[0].kind, WarningKind::UnresolvableUrl);
+
+        assert!(parse_html(html, &config, "not a url")
+            .unwrap()
+            .iter()
+            .all(|e| e.url.is_empty()));
+    }
+
+    fn link_config() -> SiteConfig {
+        SiteConfig {
+            event_selector: ".event".to_string(),
+            name_selector: "a".to_string(),
+            start_date_selector: "a".to_string(),
+            url: "a".to_string(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_parse_html_honors_base_href() {
+        let html = r#"<html><head><base href="/us/nashville/"></head><body>
+            <div class="event"><a href="events/jazz">Jazz</a></div>
+            <div class="event"><a href="/top-level">Top</a></div>
+            <div class="event"><a href="https://tickets.example.org/1">Elsewhere</a></div>
+        </body></html>"#;
+
+        let events = parse_html(html, &link_config(), "https://en.perto.com").unwrap();
+
+        let urls: Vec<&str> = events.iter().map(|e| e.url.as_str()).collect();
+        assert_eq!(
+            urls,
+            vec![
+                "https://en.perto.com/us/nashville/events/jazz",
+                "https://en.perto.com/top-level",
+                "https://tickets.example.org/1",
+            ]
+        );
+
+        let absolute_base = html.replace("/us/nashville/", "https://cdn.perto.com/city/");
+        let events = parse_html(&absolute_base, &link_config(), "https://en.perto.com").unwrap();
+        assert_eq!(events[0].url, "https://cdn.perto.com/city/events/jazz");
+    }
+
+    #[test]
+    fn test_parse_html_without_base_href_uses_caller_base() {
+        let html = r#"<div class="event"><a href="events/jazz">Jazz</a></div>"#;
+
+        let events = parse_html(html, &link_config(), "https://en.perto.com/us/").unwrap();
+
+        assert_eq!(events[0].url, "https://en.perto.com/us/events/jazz");
+    }
+
+    fn numbered_events(count: usize) -> String {
+        (1..=count)
+            .map(|i| format!(r#"<div class="event"><a href="/{0}">Show {0}</a></div>"#, i))
+            .collect()
+    }
+
+    #[test]
+    fn test_parse_page_caps_events() {
+        let html = numbered_events(30);
+        let mut config = link_config();
+
+        let page = parse_page(&html, &config, "http://example.com", Zone::default()).unwrap();
+        assert_eq!((page.events.len(), page.matched), (30, 30));
+
+        config.max_events = Some(25);
+        let page = parse_page(&html, &config, "http://example.com", Zone::default()).unwrap();
+        assert_eq!((page.events.len(), page.matched), (25, 30));
+        assert_eq!(page.events[24].name, "Show 25");
+    }
+
+    #[test]
+    fn test_parse_page_caps_json_ld_events() {
+        let html = json_ld_page(&["One", "Two", "Three"]);
+        let config = SiteConfig {
+            max_events: Some(2),
+            ..json_ld_config()
+        };
+
+        let page = parse_page(&html, &config, "http://example.com", Zone::default()).unwrap();
+
+        assert_eq!((page.events.len(), page.matched), (2, 3));
+    }
+
+    #[test]
+    fn test_parse_html_joins_multi_valued_names() {
+        let html = r#"<div class="event-listings-element">
+            <p class="artists"><a href="/concerts/1"><span><strong>Headliner</strong></span></a>
+               <a href="/concerts/1"><span><strong>Co-headliner</strong></span></a>
+               <a href="/concerts/1"><span><strong> The  Opener </strong></span></a></p>
+            <p class="location"><span><a>Ryman</a></span><span><a>Nashville</a></span></p>
+        </div>"#;
+        let mut config = SiteConfig {
+            event_selector: ".event-listings-element".to_string(),
+            name_selector: ".artists > a > span > strong".to_string(),
+            start_date_selector: ".time".to_string(),
+            location_selector: Some(".location > span > a".to_string()),
+            url: ".artists > a".to_string(),
+            ..Default::default()
+        };
+
+        let events = parse_html(html, &config, "https://www.songkick.com").unwrap();
+        assert_eq!(events[0].name, "Headliner");
+        assert_eq!(events[0].location.as_deref(), Some("Ryman"));
+
+        config.name_separator = Some(", ".to_string());
+        let events = parse_html(html, &config, "https://www.songkick.com").unwrap();
+        assert_eq!(events[0].name, "Headliner, Co-headliner, The Opener");
+        assert_eq!(events[0].location.as_deref(), Some("Ryman"));
+    }
+
+    #[test]
+    fn test_debug_selectors_reports_matches() {
+        let html = r#"
+            <div class="event"><h2 class="name">One</h2><time datetime="2024-05-02">May 2</time><a href="/1">x</a></div>
+            <div class="event"><h2 class="name">Two</h2><a href="/2">x</a></div>
+            <div class="event"><h2 class="title">Three</h2></div>
+        "#;
+        let config = SiteConfig {
+            event_selector: ".event".to_string(),
+            name_selector: ".name".to_string(),
+            start_date_selector: "time".to_string(),
+            start_date_attr: Some("datetime".to_string()),
+            location_selector: Some(".venue".to_string()),
+            url: "a".to_string(),
+            ..Default::default()
+        };
+
+        let report = debug_selectors(html, &config, Zone::default()).unwrap();
+
+        assert_eq!(report.event_matches, 3);
+        let summary: Vec<(&str, usize, Option<&str>)> = report
+            .fields
+            .iter()
+            .map(|f| (f.field, f.matched, f.first.as_deref()))
+            .collect();
+        assert_eq!(
+            summary,
+            vec![
+                ("name", 2, Some("One")),
+                ("start_date", 1, Some("2024-05-02")),
+                ("location", 0, None),
+                ("url", 2, Some("/1")),
+            ]
+        );
+    }
+
+    fn json_api_config() -> SiteConfig {
+        SiteConfig {
+            strategy: ParseStrategy::JsonApi,
+            event_selector: "data.events".to_string(),
+            name_selector: "name".to_string(),
+            start_date_selector: "dates.start.localDate".to_string(),
+            location_selector: Some("_embedded.venues.0.name".to_string()),
+            price_selector: Some("priceRanges.0.min".to_string()),
+            url: "url".to_string(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_parse_json_api_reads_nested_paths() {
+        let json = r#"{"data": {"events": [
+            {"name": "Jazz Night", "url": "/e/1", "dates": {"start": {"localDate": "2024-05-02"}},
+             "_embedded": {"venues": [{"name": "Station Inn"}]}, "priceRanges": [{"min": 15.5}]},
+            {"name": "Mystery Show", "dates": {}}
+        ]}}"#;
+
+        let events = parse_json_api(json, &json_api_config(), "https://api.example.com");
+
+        assert_eq!(events.len(), 2);
+        assert_eq!(events[0].name, "Jazz Night");
+        assert_eq!(events[0].start_date, "2024-05-02");
+        assert_eq!(events[0].location.as_deref(), Some("Station Inn"));
+        assert_eq!(events[0].price.as_deref(), Some("15.5"));
+        assert_eq!(events[0].url, "https://api.example.com/e/1");
+        assert!(events[0].warnings.is_empty());
+
+        assert_eq!(events[1].start_date, "");
+        assert_eq!(events[1].location.as_deref(), Some(""));
+        let missing: Vec<(&str, &str)> = events[1]
+            .warnings
+            .iter()
+            .map(|w| (w.field, w.selector.as_str()))
+            .collect();
+        assert_eq!(
+            missing,
+            vec![
+                ("start_date", "dates.start.localDate"),
+                ("location", "_embedded.venues.0.name"),
+                ("price", "priceRanges.0.min"),
+                ("url", "url"),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_parse_json_api_with_missing_events_path() {
+        let config = SiteConfig {
+            event_selector: "results".to_string(),
+            ..json_api_config()
+        };
+
+        assert!(parse_json_api(
+            r#"{"data": {"events": []}}"#,
+            &config,
+            "https://api.example.com"
+        )
+        .is_empty());
+        assert!(parse_json_api("not json", &config, "https://api.example.com").is_empty());
+
+        let top_level = SiteConfig {
+            event_selector: String::new(),
+            ..json_api_config()
+        };
+        let events = parse_page(
+            r#"[{"name": "A"}, {"name": "B"}]"#,
+            &SiteConfig {
+                max_events: Some(1),
+                ..top_level
+            },
+            "https://api.example.com",
+            Zone::default(),
+        )
+        .unwrap();
+        assert_eq!((events.events.len(), events.matched), (1, 2));
+    }
+
+    #[test]
+    fn test_parse_page_falls_back_to_meta_tags() {
+        let html = r#"<html><head>
+            <meta property="og:title" content="Jazz Night">
+            <meta property="og:url" content="/events/jazz-night">
+            <meta property="og:image" content="/img/jazz.jpg">
+            <meta name="description" content="An evening of jazz.">
+            <meta property="event:start_time" content="2024-05-02T20:00:00-05:00">
+        </head><body><p>Tickets at the door.</p></body></html>"#;
+
+        let page = parse_page(html, &time_config(), "http://example.com", Zone::default()).unwrap();
+
+        assert_eq!(page.matched, 1);
+        let event = &page.events[0];
+        assert_eq!(event.name, "Jazz Night");
+        assert_eq!(event.start_date, "2024-05-02T20:00:00-05:00");
+        assert_eq!(event.url, "http://example.com/events/jazz-night");
+        assert_eq!(
+            event.image_url.as_deref(),
+            Some("http://example.com/img/jazz.jpg")
+        );
+        assert_eq!(event.description.as_deref(), Some("An evening of jazz."));
+        assert_eq!(
+            event.warnings,
+            vec![FieldWarning {
+                event_index: 0,
+                field: "event",
+                selector: ".event".to_string(),
+                kind: WarningKind::MetaFallback
+            },]
+        );
+        assert!(parse_page(
+            "<p>Nothing here</p>",
+            &time_config(),
+            "http://example.com",
+            Zone::default()
+        )
+        .unwrap()
+        .events
+        .is_empty());
+    }
+
+    #[test]
+    fn test_parse_page_skips_meta_fallback_when_selectors_match() {
+        let html = r#"<html><head><meta property="og:title" content="All events"></head><body>
+            <div class="event"><h2 class="name">Show</h2><a href="/show">x</a></div>
+        </body></html>"#;
+
+        let events = parse_html(html, &time_config(), "http://example.com").unwrap();
+
+        assert_eq!(events.len(), 1);
+        assert_eq!(events[0].name, "Show");
+        assert!(events[0]
+            .warnings
+            .iter()
+            .all(|w| w.kind != WarningKind::MetaFallback));
+    }
+
+    #[test]
+    fn test_parse_html_takes_start_date_from_date_headers() {
+        let html = r#"<div class="list">
+            <h3 class="day">Friday, May 3</h3>
+            <div class="event"><h2 class="name">Early show</h2><a href="/1">x</a></div>
+            <div class="event"><h2 class="name">Late show</h2><a href="/2">x</a></div>
+            <h3 class="day">Saturday, May 4</h3>
+            <div class="wrapper"><div class="event"><h2 class="name">Matinee</h2><a href="/3">x</a></div></div>
+            <div class="event"><h2 class="name">Moved show</h2><time class="start" datetime="2024-05-10">May 10</time><a href="/4">x</a></div>
+        </div>"#;
+        let config = SiteConfig {
+            date_header_selector: Some(".day".to_string()),
+            ..time_config()
+        };
+
+        let events = parse_html(html, &config, "http://example.com").unwrap();
+
+        let dates: Vec<(&str, &str)> = events
+            .iter()
+            .map(|e| (e.name.as_str(), e.start_date.as_str()))
+            .collect();
+        assert_eq!(
+            dates,
+            vec![
+                ("Early show", "Friday, May 3"),
+                ("Late show", "Friday, May 3"),
+                ("Matinee", "Saturday, May 4"),
+                ("Moved show", "2024-05-10"),
+            ]
+        );
+        assert!(events
+            .iter()
+            .all(|e| e.warnings.iter().all(|w| w.field != "start_date")));
+    }
+
+    fn listed(name: &str, start_date: &str, url: &str) -> Event {
+        Event {
+            name: name.to_string(),
+            start_date: start_date.to_string(),
+            url: url.to_string(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_dedupe_collapses_exact_duplicates() {
+        let featured = Event {
+            location: Some("Station Inn".to_string()),
+            ..listed("Jazz Night", "May 2", "")
+        };
+
+        let (events, duplicates) = dedupe(vec![
+            featured.clone(),
+            featured.clone(),
+            listed("Other", "May 3", ""),
+        ]);
+
+        assert_eq!(duplicates, 1);
+        assert_eq!(events, vec![featured, listed("Other", "May 3", "")]);
+    }
+
+    #[test]
+    fn test_dedupe_by_url_keeps_most_populated() {
+        let carousel = listed(
+            "Jazz Night!",
+            "",
+            "https://example.com/events/jazz/?utm_source=home#tickets",
+        );
+        let listing = Event {
+            location: Some("Station Inn".to_string()),
+            ..listed("Jazz Night", "May 2", "https://example.com/events/jazz")
+        };
+
+        let (events, duplicates) = dedupe(vec![carousel, listing.clone()]);
+
+        assert_eq!(duplicates, 1);
+        assert_eq!(events, vec![listing]);
+    }
+
+    #[test]
+    fn test_dedupe_keeps_near_duplicates() {
+        let events = vec![
+            listed(
+                "Jazz Night",
+                "May 2",
+                "https://example.com/events/jazz?date=2",
+            ),
+            listed(
+                "Jazz Night",
+                "May 3",
+                "https://example.com/events/jazz?date=3",
+            ),
+            listed("Open Mic", "May 2", ""),
+            listed("Open Mic", "May 9", ""),
+            Event {
+                location: Some("Basement".to_string()),
+                ..listed("Open Mic", "May 2", "")
+            },
+        ];
+
+        let (deduped, duplicates) = dedupe(events.clone());
+
+        assert_eq!(duplicates, 0);
+        assert_eq!(deduped, events);
+    }
+
+    #[test]
+    fn test_json_ld_skips_and_counts_non_event_types() {
+        let html = r#"
+            <script type="application/ld+json">{"@type": "WebSite", "name": "Nashville Calendar", "url": "/"}</script>
+            <script type="application/ld+json">{"@type": ["Organization", "LocalBusiness"], "name": "Venue Inc"}</script>
+            <script type="application/ld+json">
+                {"@context": "https://schema.org", "@graph": [
+                    {"@type": "MusicEvent", "name": "Jazz Night", "startDate": "2024-05-02T20:00"},
+                    {"@type": ["MusicEvent", "Thing"], "name": "Bluegrass Jam", "startDate": "2024-05-03T19:00"}
+                ]}
+            </script>
+        "#;
+
+        let page = parse_page(
+            html,
+            &json_ld_config(),
+            "http://example.com",
+            Zone::default(),
+        )
+        .unwrap();
+
+        let names: Vec<&str> = page.events.iter().map(|e| e.name.as_str()).collect();
+        assert_eq!(names, vec!["Jazz Night", "Bluegrass Jam"]);
+        assert_eq!((page.matched, page.skipped), (2, 2));
+    }
+
+    #[test]
+    fn test_parse_html_captures_separate_times() {
+        let html = r#"<div class="event"><h2 class="name">Show</h2>
+            <time class="start" datetime="2024-05-02">May 2</time><span class="time">7:30 PM</span><a href="/show">x</a>
+        </div>"#;
+        let config = SiteConfig {
+            start_time_selector: Some(".time".to_string()),
+            end_time_selector: Some(".until".to_string()),
+            ..time_config()
+        };
+
+        let event = parse_html(html, &config, "http://example.com")
+            .unwrap()
+            .remove(0);
+
+        assert_eq!(event.start_date, "2024-05-02");
+        assert_eq!(event.start_time.as_deref(), Some("7:30 PM"));
+        assert_eq!(event.end_time.as_deref(), Some(""));
+        assert!(event
+            .warnings
+            .iter()
+            .any(|w| w.field == "end_time" && w.kind == WarningKind::NoMatch));
+    }
+
+    #[test]
+    fn test_event_status_from_text() {
+        assert_eq!(EventStatus::from_text(" Sold Out! "), EventStatus::SoldOut);
+        assert_eq!(
+            EventStatus::from_text("<span>CANCELLED</span>"),
+            EventStatus::Cancelled
+        );
+        assert_eq!(
+            EventStatus::from_text("https://schema.org/EventPostponed"),
+            EventStatus::Postponed
+        );
+        assert_eq!(
+            EventStatus::from_text("http://schema.org/InStock"),
+            EventStatus::Available
+        );
+        assert_eq!(EventStatus::from_text("Featured"), EventStatus::Unknown);
+    }
+
+    #[test]
+    fn test_parse_html_reads_status_badges() {
+        let html = r#"
+            <div class="event"><h2 class="name">Gone</h2><span class="badge">Sold out</span><a href="/1">x</a></div>
+            <div class="event"><h2 class="name">Off</h2><span class="badge">Cancelled</span><a href="/2">x</a></div>
+            <div class="event"><h2 class="name">On</h2><a href="/3">x</a></div>
+        "#;
+        let config = SiteConfig {
+            status_selector: Some(".badge".to_string()),
+            ..time_config()
+        };
+
+        let statuses: Vec<EventStatus> = parse_html(html, &config, "http://example.com")
+            .unwrap()
+            .iter()
+            .map(|e| e.status)
+            .collect();
+
+        assert_eq!(
+            statuses,
+            vec![
+                EventStatus::SoldOut,
+                EventStatus::Cancelled,
+                EventStatus::Unknown
+            ]
+        );
+    }
+
+    #[test]
+    fn test_json_ld_event_status_and_availability() {
+        let status_of = |json: Value| {
+            let html = format!(r#"<script type="application/ld+json">{}</script>"#, json);
+            parse_html(&html, &json_ld_config(), "http://example.com")
+                .unwrap()
+                .remove(0)
+                .status
+        };
+
+        assert_eq!(
+            status_of(serde_json::json!({"@type": "Event", "name": "A"})),
+            EventStatus::Unknown
+        );
+        assert_eq!(
+            status_of(
+                serde_json::json!({"@type": "Event", "name": "A", "eventStatus": "https://schema.org/EventScheduled"})
+            ),
+            EventStatus::Available
+        );
+        assert_eq!(
+            status_of(
+                serde_json::json!({"@type": "Event", "name": "A", "offers": [
+                    {"availability": "https://schema.org/SoldOut"}, {"availability": "https://schema.org/SoldOut"}
+                ]})
+            ),
+            EventStatus::SoldOut
+        );
+        assert_eq!(
+            status_of(
+                serde_json::json!({"@type": "Event", "name": "A", "offers": [
+                    {"availability": "https://schema.org/SoldOut"}, {"availability": "https://schema.org/InStock"}
+                ]})
+            ),
+            EventStatus::Available
+        );
+        assert_eq!(
+            status_of(
+                serde_json::json!({"@type": "Event", "name": "A", "eventStatus": "https://schema.org/EventCancelled",
+            "offers": {"availability": "https://schema.org/InStock"}})
+            ),
+            EventStatus::Cancelled
+        );
+    }
+
+    #[test]
+    fn test_validate_reports_unknown_transforms() {
+        let config = SiteConfig {
+            transforms: vec![
+                (
+                    "name".to_string(),
+                    vec!["strip_emoji".to_string(), "shout".to_string()],
+                ),
+                ("venue".to_string(), vec!["trim".to_string()]),
+            ],
+            ..time_config()
+        };
+
+        let messages: Vec<String> = config
+            .validate()
+            .unwrap_err()
+            .iter()
+            .map(|e| e.to_string())
+            .collect();
+
+        assert_eq!(
+            messages,
+            vec![
+                "transforms: name: unknown transform 'shout'",
+                "transforms: 'venue' isn't a field that can be transformed",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_parse_html_collects_tags() {
+        let html = r#"<div class="event"><h2 class="name">Show</h2>
+            <ul class="tags"><li>Family</li><li> <b>Free</b> </li><li></li></ul><a href="/show">x</a>
+        </div>"#;
+        let config = SiteConfig {
+            tags_selector: Some(".tags li".to_string()),
+            ..time_config()
+        };
+
+        let event = parse_html(html, &config, "http://example.com")
+            .unwrap()
+            .remove(0);
+
+        assert_eq!(event.tags, vec!["Family", "Free"]);
+    }
+
+    #[test]
+    fn test_json_ld_keywords_and_genre() {
+        let tags_of = |json: Value| {
+            let html = format!(r#"<script type="application/ld+json">{}</script>"#, json);
+            parse_html(&html, &json_ld_config(), "http://example.com")
+                .unwrap()
+                .remove(0)
+                .tags
+        };
+
+        assert_eq!(
+            tags_of(
+                serde_json::json!({"@type": "MusicEvent", "name": "A", "keywords": "Jazz, Live Music,"})
+            ),
+            vec!["Jazz", " Live Music"]
+        );
+        assert_eq!(
+            tags_of(
+                serde_json::json!({"@type": "MusicEvent", "name": "A", "keywords": ["Jazz", "Outdoors"], "genre": "Bebop"})
+            ),
+            vec!["Jazz", "Outdoors", "Bebop"]
+        );
+        assert!(tags_of(serde_json::json!({"@type": "MusicEvent", "name": "A"})).is_empty());
+    }
+
+    fn fixture(file: &str) -> String {
+        fs::read_to_string(
+            Path::new(env!("CARGO_MANIFEST_DIR"))
+                .join("tests")
+                .join("fixtures")
+                .join(file),
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn test_check_blocked_detects_challenge_page() {
+        let error = check_blocked(&fixture("cloudflare_challenge.html")).unwrap_err();
+
+        assert_eq!(
+            error,
+            ParseError::LikelyBlocked("a Cloudflare challenge".to_string())
+        );
+        assert_eq!(error.to_string(), "the site appears to be blocking automated requests (the page contains a Cloudflare challenge)");
+        assert_eq!(
+            check_blocked("<html><body><h1>Access Denied</h1></body></html>"),
+            Err(ParseError::LikelyBlocked("\"Access denied\"".to_string()))
+        );
+        assert_eq!(
+            check_blocked("<html></html>"),
+            Err(ParseError::LikelyBlocked("only 13 bytes".to_string()))
+        );
+    }
+
+    #[test]
+    fn test_check_blocked_passes_normal_pages() {
+        assert_eq!(check_blocked(&fixture("songkick.html")), Ok(()));
+        assert_eq!(check_blocked(&fixture("nashville.html")), Ok(()));
+    }
+}
@@ -0,0 +1,862 @@
+//! # iCalendar Module
+//!
+//! This module parses iCalendar (`.ics`) exports, which many venue and city calendars offer,
+//! into events. Each `VEVENT` becomes one event; recurring events are reduced to their next
+//! occurrence. It also writes processed events back out as a calendar for `--format ics`.
+
+use std::fmt;
+
+use chrono::{Duration, FixedOffset, Months, NaiveDate, NaiveDateTime, NaiveTime, TimeZone, Utc};
+
+use crate::data_processing::ProcessedEvent;
+use crate::html_parser::{self, Event, EventStatus};
+use crate::timezone::Zone;
+
+/// How far ahead the next occurrence of a recurring event may be and still be listed.
+pub const RECURRENCE_LOOKAHEAD_DAYS: i64 = 30;
+
+/// An error raised when a document isn't a readable calendar.
+#[derive(Debug, Clone, PartialEq)]
+pub enum ParseError {
+    /// The document has no `BEGIN:VCALENDAR`.
+    NotACalendar,
+    /// A component was opened but never closed.
+    Unterminated(String),
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ParseError::NotACalendar => write!(f, "not an iCalendar document"),
+            ParseError::Unterminated(component) => {
+                write!(f, "malformed calendar: {} is never closed", component)
+            }
+        }
+    }
+}
+
+/// Parses an iCalendar document into events, relative to the current time in the home zone.
+///
+/// See `parse_ics_at`.
+pub fn parse_ics(ics: &str, base_url: &str, zone: Zone) -> Result<Vec<Event>, ParseError> {
+    parse_ics_at(ics, base_url, zone.now(), zone)
+}
+
+/// Parses an iCalendar document into events.
+///
+/// `SUMMARY` is the name, `LOCATION` the location, `URL` the URL, and `DESCRIPTION` the
+/// description. `DTSTART` and `DTEND` are written as `2024-05-02` for all-day events,
+/// `2024-05-02T19:00:00` for floating times, which are read in the home zone, and RFC 3339
+/// with an offset for UTC times and those with a `TZID`. A `TZID` naming a zone that isn't
+/// known (see `Zone::names`) is warned about, and its times are read as floating ones.
+///
+/// Events with an `RRULE` are listed once, at their next occurrence from `now`, and left out
+/// if that is more than `RECURRENCE_LOOKAHEAD_DAYS` away. Only `FREQ`, `INTERVAL`, `COUNT`,
+/// and `UNTIL` are understood.
+///
+/// # Arguments
+///
+/// * `ics` - The iCalendar document.
+/// * `base_url` - The base URL of the site for resolving relative URLs.
+/// * `now` - The current time in the home zone.
+/// * `zone` - The home zone, which floating times are in.
+///
+/// # Returns
+///
+/// The calendar's events, or a `ParseError` if the document isn't a readable calendar.
+pub fn parse_ics_at(
+    ics: &str,
+    base_url: &str,
+    now: NaiveDateTime,
+    zone: Zone,
+) -> Result<Vec<Event>, ParseError> {
+    let lines = unfold(ics);
+    if !lines
+        .iter()
+        .any(|l| l.eq_ignore_ascii_case("BEGIN:VCALENDAR"))
+    {
+        return Err(ParseError::NotACalendar);
+    }
+
+    let mut events = Vec::new();
+    let mut current: Option<Vec<Property>> = None;
+    let mut nested = Vec::new();
+    for line in &lines {
+        let property = parse_line(line);
+        match (property.name.as_str(), current.as_mut()) {
+            ("BEGIN", None) if property.value.eq_ignore_ascii_case("VEVENT") => {
+                current = Some(Vec::new())
+            }
+            ("BEGIN", Some(_)) => nested.push(property.value.to_uppercase()),
+            ("END", Some(_)) if !nested.is_empty() => {
+                nested.pop();
+            }
+            ("END", Some(_)) if property.value.eq_ignore_ascii_case("VEVENT") => {
+                let properties = current.take().unwrap_or_default();
+                events.extend(vevent(&properties, base_url, now, zone));
+            }
+            // Properties of nested components such as VALARM don't describe the event
+            (_, Some(properties)) if nested.is_empty() => properties.push(property),
+            _ => {}
+        }
+    }
+    if current.is_some() {
+        return Err(ParseError::Unterminated(String::from("VEVENT")));
+    }
+    Ok(events)
+}
+
+/// One content line: `NAME;PARAM=VALUE:value`.
+#[derive(Debug, Clone, PartialEq)]
+struct Property {
+    name: String,
+    params: Vec<(String, String)>,
+    value: String,
+}
+
+impl Property {
+    fn param(&self, name: &str) -> Option<&str> {
+        self.params
+            .iter()
+            .find(|(key, _)| key == name)
+            .map(|(_, value)| value.as_str())
+    }
+}
+
+/// Joins folded lines: a line starting with a space or tab continues the previous one.
+fn unfold(ics: &str) -> Vec<String> {
+    let mut lines: Vec<String> = Vec::new();
+    for line in ics.split('\n').map(|l| l.strip_suffix('\r').unwrap_or(l)) {
+        match (
+            line.strip_prefix(' ').or_else(|| line.strip_prefix('\t')),
+            lines.last_mut(),
+        ) {
+            (Some(continuation), Some(last)) => last.push_str(continuation),
+            _ if line.trim().is_empty() => {}
+            _ => lines.push(line.to_string()),
+        }
+    }
+    lines
+}
+
+/// Splits a content line into its name, parameters, and value.
+fn parse_line(line: &str) -> Property {
+    // The value starts at the first colon outside a quoted parameter value
+    let mut in_quotes = false;
+    let split = line.char_indices().find(|(_, c)| {
+        if *c == '"' {
+            in_quotes = !in_quotes;
+        }
+        *c == ':' && !in_quotes
+    });
+    let (head, value) = match split {
+        Some((i, _)) => (&line[..i], &line[i + 1..]),
+        None => (line, ""),
+    };
+    let mut parts = head.split(';');
+    let name = parts.next().unwrap_or_default().trim().to_uppercase();
+    let params = parts
+        .filter_map(|p| p.split_once('='))
+        .map(|(key, value)| {
+            (
+                key.trim().to_uppercase(),
+                value.trim_matches('"').to_string(),
+            )
+        })
+        .collect();
+    Property {
+        name,
+        params,
+        value: value.to_string(),
+    }
+}
+
+/// Undoes iCalendar text escaping (`\,`, `\;`, `\n`, `\\`).
+fn unescape(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    let mut chars = text.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            out.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('n') | Some('N') => out.push('\n'),
+            Some(other) => out.push(other),
+            None => out.push('\\'),
+        }
+    }
+    out
+}
+
+/// A `DTSTART` or `DTEND` value.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum CalendarTime {
+    /// An all-day date.
+    Date(NaiveDate),
+    /// A floating wall-clock time, in whatever zone the reader is in.
+    Local(NaiveDateTime),
+    /// A wall-clock time in the zone a `TZID` names.
+    Zoned(NaiveDateTime, Zone),
+    /// A UTC time.
+    Utc(NaiveDateTime),
+}
+
+impl CalendarTime {
+    fn parse(property: &Property) -> Option<CalendarTime> {
+        let value = property.value.trim();
+        if property.param("VALUE") == Some("DATE") || value.len() == 8 {
+            return NaiveDate::parse_from_str(value, "%Y%m%d")
+                .ok()
+                .map(CalendarTime::Date);
+        }
+        if let Some(utc) = value.strip_suffix('Z') {
+            return NaiveDateTime::parse_from_str(utc, "%Y%m%dT%H%M%S")
+                .ok()
+                .map(CalendarTime::Utc);
+        }
+        let time = NaiveDateTime::parse_from_str(value, "%Y%m%dT%H%M%S").ok()?;
+        match property.param("TZID") {
+            Some(tzid) => match tzid.parse::<Zone>() {
+                Ok(zone) => Some(CalendarTime::Zoned(time, zone)),
+                Err(_) => {
+                    log::warn!(
+                        "calendar: unknown TZID '{}' on {}; reading {} as the home zone's time",
+                        tzid,
+                        property.name,
+                        value
+                    );
+                    Some(CalendarTime::Local(time))
+                }
+            },
+            None => Some(CalendarTime::Local(time)),
+        }
+    }
+
+    fn format(&self) -> String {
+        match self {
+            CalendarTime::Date(date) => date.format("%Y-%m-%d").to_string(),
+            CalendarTime::Local(time) => time.format("%Y-%m-%dT%H:%M:%S").to_string(),
+            CalendarTime::Utc(time) => Utc.from_utc_datetime(time).to_rfc3339(),
+            CalendarTime::Zoned(time, zone) => {
+                let offset = zone.offset_at(zone.to_utc(*time)).num_seconds() as i32;
+                match FixedOffset::east_opt(offset)
+                    .and_then(|offset| offset.from_local_datetime(time).single())
+                {
+                    Some(zoned) => zoned.to_rfc3339(),
+                    None => time.format("%Y-%m-%dT%H:%M:%S").to_string(),
+                }
+            }
+        }
+    }
+
+    /// The time as written, in whichever zone it is in.
+    fn wall_clock(self) -> NaiveDateTime {
+        match self {
+            CalendarTime::Date(date) => date.and_hms_opt(0, 0, 0).unwrap_or_default(),
+            CalendarTime::Local(time) | CalendarTime::Utc(time) | CalendarTime::Zoned(time, _) => {
+                time
+            }
+        }
+    }
+
+    /// The time in the home zone, for comparing against now.
+    fn to_local(self, home: Zone) -> NaiveDateTime {
+        match self {
+            CalendarTime::Date(date) => date.and_hms_opt(0, 0, 0).unwrap_or_default(),
+            CalendarTime::Local(time) => time,
+            CalendarTime::Utc(time) => home.to_local(time),
+            CalendarTime::Zoned(time, zone) => home.to_local(zone.to_utc(time)),
+        }
+    }
+
+    fn map(self, f: impl Fn(NaiveDateTime) -> Option<NaiveDateTime>) -> Option<CalendarTime> {
+        match self {
+            CalendarTime::Date(date) => {
+                f(date.and_hms_opt(0, 0, 0)?).map(|t| CalendarTime::Date(t.date()))
+            }
+            CalendarTime::Local(time) => f(time).map(CalendarTime::Local),
+            CalendarTime::Utc(time) => f(time).map(CalendarTime::Utc),
+            // Stepped in the zone's wall-clock time, so a weekly 7 PM stays at 7 PM across DST
+            CalendarTime::Zoned(time, zone) => f(time).map(|t| CalendarTime::Zoned(t, zone)),
+        }
+    }
+}
+
+/// Builds the event for a `VEVENT`, or `None` if it recurs and has no occurrence soon enough.
+fn vevent(
+    properties: &[Property],
+    base_url: &str,
+    now: NaiveDateTime,
+    zone: Zone,
+) -> Option<Event> {
+    let property = |name: &str| properties.iter().find(|p| p.name == name);
+    let text = |name: &str| {
+        property(name)
+            .map(|p| unescape(&p.value).trim().to_string())
+            .filter(|t| !t.is_empty())
+    };
+
+    let mut start = property("DTSTART").and_then(CalendarTime::parse);
+    let mut end = property("DTEND").and_then(CalendarTime::parse);
+    if let (Some(rule), Some(first)) = (property("RRULE"), start) {
+        let next = next_occurrence(first, &rule.value, now, zone)?;
+        let offset = next.wall_clock() - first.wall_clock();
+        start = Some(next);
+        end = end.and_then(|e| e.map(|t| t.checked_add_signed(offset)));
+    }
+
+    Some(Event {
+        name: text("SUMMARY").unwrap_or_default(),
+        start_date: start.map(|s| s.format()).unwrap_or_default(),
+        end_date: end.map(|e| e.format()),
+        location: text("LOCATION"),
+        url: text("URL")
+            .and_then(|u| html_parser::resolve_url(base_url, &u).ok())
+            .unwrap_or_default(),
+        description: text("DESCRIPTION"),
+        status: match text("STATUS").as_deref() {
+            Some("CANCELLED") => EventStatus::Cancelled,
+            Some("CONFIRMED") => EventStatus::Available,
+            _ => EventStatus::Unknown,
+        },
+        ..Default::default()
+    })
+}
+
+/// Finds the first occurrence of a recurrence rule that starts at or after `now`.
+///
+/// # Returns
+///
+/// The occurrence, or `None` if the rule has ended, isn't understood, or its next occurrence
+/// is more than `RECURRENCE_LOOKAHEAD_DAYS` away.
+fn next_occurrence(
+    first: CalendarTime,
+    rule: &str,
+    now: NaiveDateTime,
+    zone: Zone,
+) -> Option<CalendarTime> {
+    let part = |name: &str| {
+        rule.split(';')
+            .filter_map(|p| p.split_once('='))
+            .find(|(k, _)| k.eq_ignore_ascii_case(name))
+            .map(|(_, v)| v)
+    };
+    let interval: u32 = part("INTERVAL")
+        .and_then(|i| i.parse().ok())
+        .filter(|i| *i > 0)
+        .unwrap_or(1);
+    let count: Option<u32> = part("COUNT").and_then(|c| c.parse().ok());
+    let until = part("UNTIL").and_then(|u| {
+        CalendarTime::parse(&Property {
+            name: String::new(),
+            params: Vec::new(),
+            value: u.to_string(),
+        })
+    });
+    let step = |occurrence: u32| -> Option<CalendarTime> {
+        first.map(|t| {
+            // INTERVAL comes from the feed, so a huge one ends the rule rather than overflowing
+            let steps = interval.checked_mul(occurrence)?;
+            match part("FREQ")?.to_uppercase().as_str() {
+                "DAILY" => t.checked_add_signed(Duration::days(i64::from(steps))),
+                "WEEKLY" => t.checked_add_signed(Duration::weeks(i64::from(steps))),
+                "MONTHLY" => t.checked_add_months(Months::new(steps)),
+                "YEARLY" => t.checked_add_months(Months::new(steps.checked_mul(12)?)),
+                _ => None,
+            }
+        })
+    };
+
+    let horizon = now + Duration::days(RECURRENCE_LOOKAHEAD_DAYS);
+    let mut occurrence = 0;
+    loop {
+        if count.is_some_and(|c| occurrence >= c) {
+            return None;
+        }
+        let candidate = step(occurrence)?;
+        let local = candidate.to_local(zone);
+        if until.is_some_and(|u| local > u.to_local(zone)) || local > horizon {
+            return None;
+        }
+        // All-day occurrences still count on the day itself
+        let cutoff = match candidate {
+            CalendarTime::Date(_) => now.date().and_hms_opt(0, 0, 0).unwrap_or(now),
+            _ => now,
+        };
+        if local >= cutoff {
+            return Some(candidate);
+        }
+        occurrence += 1;
+    }
+}
+
+/// The `PRODID` of exported calendars.
+const PRODID: &str = "-//Event Finder//Event Finder CLI//EN";
+
+/// The longest a content line may be, in octets, before it is folded.
+const LINE_LIMIT: usize = 75;
+
+/// Writes events as an iCalendar document with one `VEVENT` each.
+///
+/// The `UID` is the event's `ProcessedEvent::id`, so importing a newer export updates the
+/// events imported before rather than adding copies. Times are written in UTC. An event starting
+/// at midnight that has no end, or ends at midnight too, is written as all-day dates from its
+/// start through its end day. Events whose start couldn't be read are left out. Text is
+/// escaped and lines are folded as RFC 5545 requires.
+///
+/// # Arguments
+///
+/// * `events` - The events to write.
+/// * `zone` - The home timezone the events' times are in.
+/// * `stamp` - The UTC time of the export, written as each event's `DTSTAMP`.
+///
+/// # Returns
+///
+/// The document, with CRLF line endings, and how many events were left out.
+pub fn render_ics(events: &[ProcessedEvent], zone: Zone, stamp: NaiveDateTime) -> (String, usize) {
+    let mut lines = vec![
+        "BEGIN:VCALENDAR".to_string(),
+        "VERSION:2.0".to_string(),
+        format!("PRODID:{}", PRODID),
+        "CALSCALE:GREGORIAN".to_string(),
+    ];
+    let mut skipped = 0;
+    for event in events {
+        let Some(start) = event.start else {
+            skipped += 1;
+            continue;
+        };
+        lines.push("BEGIN:VEVENT".to_string());
+        lines.push(format!("UID:{}@event-finder", event.id()));
+        lines.push(format!("DTSTAMP:{}", stamp.format("%Y%m%dT%H%M%SZ")));
+        let midnight = |time: NaiveDateTime| time.time() == NaiveTime::MIN;
+        if midnight(start) && event.end.is_none_or(midnight) {
+            // DTEND of a date is exclusive, so the end day itself is included by the next day
+            let last_day = event.end.map_or(start.date(), |end| end.date());
+            lines.push(format!("DTSTART;VALUE=DATE:{}", start.format("%Y%m%d")));
+            lines.push(format!(
+                "DTEND;VALUE=DATE:{}",
+                (last_day + Duration::days(1)).format("%Y%m%d")
+            ));
+        } else {
+            let utc = |local: NaiveDateTime| zone.to_utc(local).format("%Y%m%dT%H%M%SZ");
+            lines.push(format!("DTSTART:{}", utc(start)));
+            if let Some(end) = event.end {
+                lines.push(format!("DTEND:{}", utc(end)));
+            }
+        }
+        lines.push(format!("SUMMARY:{}", escape(&event.name)));
+        if let Some(location) = &event.location {
+            lines.push(format!("LOCATION:{}", escape(location)));
+        }
+        if !event.url.is_empty() {
+            lines.push(format!("URL:{}", event.url));
+        }
+        if let Some(description) = &event.description {
+            lines.push(format!("DESCRIPTION:{}", escape(description)));
+        }
+        if event.status == EventStatus::Cancelled {
+            lines.push("STATUS:CANCELLED".to_string());
+        }
+        lines.push("END:VEVENT".to_string());
+    }
+    lines.push("END:VCALENDAR".to_string());
+    let document: String = lines.iter().map(|line| fold(line) + "\r\n").collect();
+    (document, skipped)
+}
+
+/// Escapes text for a `TEXT` value: backslashes, semicolons, commas, and line breaks, dropping
+/// other control characters, which the format doesn't allow.
+fn escape(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '\\' | ';' | ',' => {
+                out.push('\\');
+                out.push(c);
+            }
+            '\n' => out.push_str("\\n"),
+            '\t' => out.push(c),
+            c if c.is_control() => {}
+            c => out.push(c),
+        }
+    }
+    out
+}
+
+/// Folds a content line into lines of at most `LINE_LIMIT` octets, each continuation starting
+/// with a space, without splitting a character.
+fn fold(line: &str) -> String {
+    let mut out = String::with_capacity(line.len() + line.len() / LINE_LIMIT * 3);
+    let mut length = 0;
+    for c in line.chars() {
+        if length + c.len_utf8() > LINE_LIMIT {
+            out.push_str("\r\n ");
+            length = 1;
+        }
+        out.push(c);
+        length += c.len_utf8();
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CALENDAR: &str = "BEGIN:VCALENDAR\r
+VERSION:2.0\r
+PRODID:-//Station Inn//Calendar//EN\r
+BEGIN:VEVENT\r
+UID:1@stationinn.com\r
+SUMMARY:Bluegrass Jam\\, Potluck & Pickin'\r
+DTSTART;TZID=America/Chicago:20240502T190000\r
+DTEND;TZID=America/Chicago:20240502T220000\r
+LOCATION:Station Inn\\, 402 12th Ave S\r
+URL:/events/jam\r
+DESCRIPTION:Bring an instrument.\\nAll levels wel\r
+ come.\r
+BEGIN:VALARM\r
+ACTION:DISPLAY\r
+DESCRIPTION:Reminder\r
+END:VALARM\r
+END:VEVENT\r
+BEGIN:VEVENT\r
+SUMMARY:Flea Market\r
+DTSTART;VALUE=DATE:20240504\r
+DTEND;VALUE=DATE:20240506\r
+END:VEVENT\r
+BEGIN:VEVENT\r
+SUMMARY:Livestream\r
+DTSTART:20240503T010000Z\r
+END:VEVENT\r
+END:VCALENDAR\r
+";
+
+    fn now() -> NaiveDateTime {
+        NaiveDate::from_ymd_opt(2024, 5, 1)
+            .unwrap()
+            .and_hms_opt(12, 0, 0)
+            .unwrap()
+    }
+
+    #[test]
+    fn test_parse_ics_events() {
+        let events =
+            parse_ics_at(CALENDAR, "https://stationinn.com", now(), Zone::default()).unwrap();
+
+        assert_eq!(events.len(), 3);
+        assert_eq!(events[0].name, "Bluegrass Jam, Potluck & Pickin'");
+        assert_eq!(events[0].start_date, "2024-05-02T19:00:00-05:00");
+        assert_eq!(
+            events[0].end_date.as_deref(),
+            Some("2024-05-02T22:00:00-05:00")
+        );
+        assert_eq!(
+            events[0].location.as_deref(),
+            Some("Station Inn, 402 12th Ave S")
+        );
+        assert_eq!(events[0].url, "https://stationinn.com/events/jam");
+        assert_eq!(
+            events[0].description.as_deref(),
+            Some("Bring an instrument.\nAll levels welcome.")
+        );
+
+        assert_eq!(events[1].start_date, "2024-05-04");
+        assert_eq!(events[1].end_date.as_deref(), Some("2024-05-06"));
+        assert_eq!(events[1].location, None);
+
+        assert_eq!(events[2].start_date, "2024-05-03T01:00:00+00:00");
+        assert_eq!(events[2].end_date, None);
+    }
+
+    #[test]
+    fn test_parse_ics_converts_tzid_times_to_the_home_zone() {
+        let calendar = "BEGIN:VCALENDAR\nBEGIN:VEVENT\nSUMMARY:Webcast\nDTSTART;TZID=America/New_York:20240502T190000\nDTEND;TZID=\"America/New_York\":20240502T210000\nEND:VEVENT\n\
+            BEGIN:VEVENT\nSUMMARY:Far away\nDTSTART;TZID=Mars/Olympus_Mons:20240502T190000\nEND:VEVENT\n\
+            BEGIN:VEVENT\nSUMMARY:Floating\nDTSTART:20240502T190000\nEND:VEVENT\nEND:VCALENDAR\n";
+
+        let events = parse_ics_at(calendar, "https://example.com", now(), Zone::default()).unwrap();
+        assert_eq!(events[0].start_date, "2024-05-02T19:00:00-04:00");
+        assert_eq!(events[2].start_date, "2024-05-02T19:00:00");
+
+        let (processed, _) = crate::data_processing::process_data(events, Zone::default());
+        let chicago =
+            |time: &str| Some(NaiveDateTime::parse_from_str(time, "%Y-%m-%d %H:%M").unwrap());
+        assert_eq!(processed[0].start, chicago("2024-05-02 18:00"));
+        assert_eq!(processed[0].end, chicago("2024-05-02 20:00"));
+        // An unknown zone and a floating time are both read as Nashville's time
+        assert_eq!(processed[1].start, chicago("2024-05-02 19:00"));
+        assert_eq!(processed[2].start, chicago("2024-05-02 19:00"));
+    }
+
+    #[test]
+    fn test_tzid_recurrences_keep_their_wall_clock_time() {
+        // Weekly at 7 PM New York time since January, in standard time
+        let calendar = "BEGIN:VCALENDAR\nBEGIN:VEVENT\nSUMMARY:Open Mic\nDTSTART;TZID=America/New_York:20240104T190000\nRRULE:FREQ=WEEKLY\nEND:VEVENT\nEND:VCALENDAR\n";
+
+        let events = parse_ics_at(calendar, "https://example.com", now(), Zone::default()).unwrap();
+
+        assert_eq!(events[0].start_date, "2024-05-02T19:00:00-04:00");
+    }
+
+    fn recurring(rule: &str) -> String {
+        format!("BEGIN:VCALENDAR\nBEGIN:VEVENT\nSUMMARY:Open Mic\nDTSTART:20240101T200000\nDTEND:20240101T230000\nRRULE:{}\nEND:VEVENT\nEND:VCALENDAR\n", rule)
+    }
+
+    #[test]
+    fn test_parse_ics_recurring_events_use_next_occurrence() {
+        let weekly = parse_ics_at(
+            &recurring("FREQ=WEEKLY"),
+            "https://example.com",
+            now(),
+            Zone::default(),
+        )
+        .unwrap();
+        assert_eq!(weekly[0].start_date, "2024-05-06T20:00:00");
+        assert_eq!(weekly[0].end_date.as_deref(), Some("2024-05-06T23:00:00"));
+
+        let monthly = parse_ics_at(
+            &recurring("FREQ=MONTHLY;INTERVAL=2"),
+            "https://example.com",
+            now(),
+            Zone::default(),
+        )
+        .unwrap();
+        assert_eq!(monthly[0].start_date, "2024-05-01T20:00:00");
+
+        let ended = parse_ics_at(
+            &recurring("FREQ=WEEKLY;COUNT=4"),
+            "https://example.com",
+            now(),
+            Zone::default(),
+        )
+        .unwrap();
+        assert!(ended.is_empty());
+        let until = parse_ics_at(
+            &recurring("FREQ=DAILY;UNTIL=20240301T000000Z"),
+            "https://example.com",
+            now(),
+            Zone::default(),
+        )
+        .unwrap();
+        assert!(until.is_empty());
+        let yearly = parse_ics_at(
+            &recurring("FREQ=YEARLY"),
+            "https://example.com",
+            now(),
+            Zone::default(),
+        )
+        .unwrap();
+        assert!(
+            yearly.is_empty(),
+            "next occurrence is beyond the lookahead window"
+        );
+    }
+
+    #[test]
+    fn test_huge_recurrence_intervals_end_the_rule() {
+        for freq in ["DAILY", "WEEKLY", "MONTHLY", "YEARLY"] {
+            let events = parse_ics_at(
+                &recurring(&format!("FREQ={};INTERVAL=4000000000", freq)),
+                "https://example.com",
+                now(),
+                Zone::default(),
+            )
+            .unwrap();
+            assert!(events.is_empty(), "{}", freq);
+        }
+    }
+
+    /// Checks the rules of RFC 5545 an exported calendar must follow, panicking on the first
+    /// broken one.
+    fn assert_strictly_valid(ics: &str) {
+        assert!(ics.ends_with("\r\n"), "the document must end in CRLF");
+        let lines: Vec<&str> = ics.strip_suffix("\r\n").unwrap().split("\r\n").collect();
+        for line in &lines {
+            assert!(
+                line.len() <= LINE_LIMIT,
+                "line longer than 75 octets: {:?}",
+                line
+            );
+            assert!(
+                !line.contains('\n') && !line.contains('\r'),
+                "bare line break in {:?}",
+                line
+            );
+            assert!(
+                !line.chars().any(|c| c.is_control() && c != '\t'),
+                "control character in {:?}",
+                line
+            );
+        }
+        let unfolded = unfold(ics);
+        assert_eq!(
+            unfolded.first().map(String::as_str),
+            Some("BEGIN:VCALENDAR")
+        );
+        assert_eq!(unfolded.last().map(String::as_str), Some("END:VCALENDAR"));
+        let calendar: Vec<Property> = unfolded.iter().map(|l| parse_line(l)).collect();
+        for required in ["VERSION", "PRODID"] {
+            assert_eq!(
+                calendar.iter().filter(|p| p.name == required).count(),
+                1,
+                "{} must appear once",
+                required
+            );
+        }
+        let mut current: Option<Vec<&Property>> = None;
+        for property in &calendar {
+            match (property.name.as_str(), property.value.as_str()) {
+                ("BEGIN", "VEVENT") => {
+                    assert!(current.is_none(), "nested VEVENT");
+                    current = Some(Vec::new());
+                }
+                ("END", "VEVENT") => {
+                    let event = current.take().expect("END:VEVENT without BEGIN");
+                    for required in ["UID", "DTSTAMP", "DTSTART"] {
+                        assert_eq!(
+                            event.iter().filter(|p| p.name == required).count(),
+                            1,
+                            "{} must appear once",
+                            required
+                        );
+                    }
+                    let start = event.iter().find(|p| p.name == "DTSTART").unwrap();
+                    assert!(
+                        CalendarTime::parse(start).is_some(),
+                        "unreadable DTSTART {:?}",
+                        start.value
+                    );
+                    if let Some(end) = event.iter().find(|p| p.name == "DTEND") {
+                        assert_eq!(
+                            end.param("VALUE"),
+                            start.param("VALUE"),
+                            "DTSTART and DTEND must have the same type"
+                        );
+                        assert!(end.value > start.value, "DTEND must be after DTSTART");
+                    }
+                }
+                (_, _) if current.is_some() => current.as_mut().unwrap().push(property),
+                _ => {}
+            }
+        }
+        assert!(current.is_none(), "unterminated VEVENT");
+    }
+
+    #[test]
+    fn test_render_ics_is_valid_and_round_trips() {
+        let at = |text: &str| NaiveDateTime::parse_from_str(text, "%Y-%m-%d %H:%M").ok();
+        let events = vec![
+            ProcessedEvent {
+                name: "Bluegrass Jam, Potluck; Pickin' \\ more".to_string(),
+                start: at("2024-07-02 19:00"),
+                end: at("2024-07-02 22:00"),
+                location: Some("Station Inn, 402 12th Ave S".to_string()),
+                url: "https://stationinn.com/events/jam".to_string(),
+                description: Some(format!(
+                    "Bring an instrument.\nAll levels welcome. {}",
+                    "Café ☕ ".repeat(20)
+                )),
+                ..Default::default()
+            },
+            ProcessedEvent {
+                name: "Flea Market".to_string(),
+                start: at("2024-05-04 00:00"),
+                end: at("2024-05-05 00:00"),
+                ..Default::default()
+            },
+            ProcessedEvent {
+                name: "Someday".to_string(),
+                start_date: "TBA".to_string(),
+                ..Default::default()
+            },
+        ];
+        let stamp = at("2024-05-01 12:00").unwrap();
+
+        let (ics, skipped) = render_ics(&events, Zone::default(), stamp);
+
+        assert_strictly_valid(&ics);
+        assert_eq!(skipped, 1);
+        assert!(ics.contains(&format!("UID:{}@event-finder\r\n", events[0].id())));
+        assert!(ics.contains("DTSTART:20240703T000000Z\r\nDTEND:20240703T030000Z\r\n"));
+        assert!(ics.contains("DTSTART;VALUE=DATE:20240504\r\nDTEND;VALUE=DATE:20240506\r\n"));
+        assert!(ics.contains("SUMMARY:Bluegrass Jam\\, Potluck\\; Pickin' \\\\ more\r\n"));
+
+        let parsed = parse_ics_at(&ics, "https://example.com", stamp, Zone::default()).unwrap();
+        assert_eq!(parsed.len(), 2);
+        assert_eq!(parsed[0].name, events[0].name);
+        assert_eq!(parsed[0].start_date, "2024-07-03T00:00:00+00:00");
+        assert_eq!(parsed[0].location, events[0].location);
+        assert_eq!(parsed[0].url, events[0].url);
+        assert_eq!(
+            parsed[0].description.as_deref(),
+            events[0].description.as_deref().map(str::trim)
+        );
+        assert_eq!(parsed[1].start_date, "2024-05-04");
+    }
+
+    #[test]
+    fn test_render_ics_uid_is_stable() {
+        let event = ProcessedEvent {
+            name: "Open Mic".to_string(),
+            url: "https://example.com/open-mic?utm_source=x".to_string(),
+            ..Default::default()
+        };
+        let mut reworded = event.clone();
+        reworded.description = Some("Now with a host".to_string());
+        let at = NaiveDate::from_ymd_opt(2024, 5, 1)
+            .unwrap()
+            .and_hms_opt(19, 0, 0);
+        let (first, second) = (
+            ProcessedEvent { start: at, ..event },
+            ProcessedEvent {
+                start: at,
+                ..reworded
+            },
+        );
+
+        let uid = |event: &ProcessedEvent| {
+            let (ics, _) = render_ics(std::slice::from_ref(event), Zone::default(), now());
+            unfold(&ics)
+                .into_iter()
+                .find(|l| l.starts_with("UID:"))
+                .unwrap()
+        };
+
+        assert_eq!(uid(&first), uid(&second));
+    }
+
+    #[test]
+    fn test_fold_keeps_characters_whole() {
+        let line = format!("DESCRIPTION:{}", "é".repeat(60));
+
+        let folded = fold(&line);
+
+        assert!(folded.split("\r\n").all(|l| l.len() <= LINE_LIMIT));
+        assert_eq!(unfold(&folded), vec![line]);
+    }
+
+    #[test]
+    fn test_parse_ics_errors() {
+        assert_eq!(
+            parse_ics_at(
+                "<html></html>",
+                "https://example.com",
+                now(),
+                Zone::default()
+            ),
+            Err(ParseError::NotACalendar)
+        );
+        assert_eq!(
+            parse_ics_at(
+                "BEGIN:VCALENDAR\nBEGIN:VEVENT\nSUMMARY:x\n",
+                "https://example.com",
+                now(),
+                Zone::default()
+            ),
+            Err(ParseError::Unterminated("VEVENT".to_string()))
+        );
+    }
+}
@@ -0,0 +1,95 @@
+//! # Interrupt Module
+//!
+//! This module catches Ctrl-C, so the menu and `--watch` can stop what they are doing and save
+//! what they fetched instead of being killed halfway through writing it. Once it is caught, a
+//! wait for input returns, fetching stops after the source being fetched, and a second Ctrl-C
+//! exits at once, for a request stuck on a slow site.
+
+use std::io;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+/// The status to exit with after Ctrl-C: 128 plus the number of SIGINT, as shells report it.
+pub const STATUS: i32 = 130;
+
+/// Set by the SIGINT handler `catch` installs.
+static INTERRUPTED: AtomicBool = AtomicBool::new(false);
+
+/// Makes Ctrl-C set `interrupted` instead of ending the process.
+///
+/// Reads of stdin then fail with `io::ErrorKind::Interrupted` rather than carrying on, so
+/// `read_stdin_line` can return; the standard library's readers retry them instead.
+pub fn catch() {
+    #[cfg(unix)]
+    // SAFETY: the handler only touches an atomic and calls `_exit`, which are both
+    // async-signal-safe, and `action` is fully set up before it is installed
+    unsafe {
+        let mut action: libc::sigaction = std::mem::zeroed();
+        action.sa_sigaction = on_interrupt as extern "C" fn(libc::c_int) as libc::sighandler_t;
+        // No SA_RESTART, so a read waiting for input stops
+        action.sa_flags = 0;
+        libc::sigemptyset(&mut action.sa_mask);
+        libc::sigaction(libc::SIGINT, &action, std::ptr::null_mut());
+    }
+}
+
+/// Whether Ctrl-C was pressed since `catch`.
+pub fn interrupted() -> bool {
+    INTERRUPTED.load(Ordering::SeqCst)
+}
+
+#[cfg(unix)]
+extern "C" fn on_interrupt(_: libc::c_int) {
+    if INTERRUPTED.swap(true, Ordering::SeqCst) {
+        // SAFETY: `_exit` ends the process without running anything else
+        unsafe { libc::_exit(STATUS) }
+    }
+}
+
+/// Reads a line from stdin, stopping early if Ctrl-C is pressed while it waits.
+///
+/// Bytes are read one at a time straight from the file descriptor, so none are left in a
+/// buffer for the next read, and a Ctrl-C caught by `catch` isn't retried away.
+///
+/// # Returns
+///
+/// The line with its newline, an empty string at the end of input (Ctrl-D, or the end of a
+/// pipe), or an error, `io::ErrorKind::Interrupted` for Ctrl-C.
+#[cfg(unix)]
+pub fn read_stdin_line() -> io::Result<String> {
+    let mut line = Vec::new();
+    loop {
+        // Pressed while the prompt was printed, before there was a read to stop
+        if interrupted() {
+            return Err(io::Error::from(io::ErrorKind::Interrupted));
+        }
+        let mut byte = 0u8;
+        // SAFETY: reads at most one byte into `byte`, which outlives the call
+        let read = unsafe { libc::read(libc::STDIN_FILENO, (&mut byte as *mut u8).cast(), 1) };
+        match read {
+            0 => break,
+            1 => {
+                line.push(byte);
+                if byte == b'\n' {
+                    break;
+                }
+            }
+            _ => {
+                let e = io::Error::last_os_error();
+                // Another signal, such as a resized terminal, doesn't end the read
+                if e.kind() == io::ErrorKind::Interrupted && !interrupted() {
+                    continue;
+                }
+                return Err(e);
+            }
+        }
+    }
+    String::from_utf8(line).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+}
+
+/// Reads a line from stdin; without signals to interrupt it, Ctrl-C is left to end the process.
+#[cfg(not(unix))]
+pub fn read_stdin_line() -> io::Result<String> {
+    let mut line = String::new();
+    io::stdin().read_line(&mut line)?;
+    Ok(line)
+}
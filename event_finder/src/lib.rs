@@ -0,0 +1,185 @@
+//! # Event Finder
+//!
+//! This library fetches event listings from websites, feeds, and calendars, reads them into
+//! `ProcessedEvent`s with cleaned text and typed dates, and filters, merges, and sorts them.
+//! The `event_finder` binary is a command-line front end to it.
+//!
+//! `fetch_events` and `parse_events` run the whole pipeline for one `Source`; the modules
+//! expose each step for finer control.
+
+pub mod browser;
+pub mod city;
+pub mod config;
+pub mod data_processing;
+pub mod diff;
+pub mod email;
+pub mod error;
+pub mod feed;
+pub mod geo;
+pub mod history;
+pub mod html_parser;
+pub mod ics;
+pub mod logging;
+pub mod notify;
+pub mod output;
+pub mod pipeline;
+pub mod prefs;
+pub mod seen;
+pub mod snapshot;
+pub mod sources;
+pub mod table;
+pub mod template;
+pub mod ticketmaster;
+pub mod timezone;
+pub mod web_requests;
+pub mod webhook;
+
+pub use data_processing::ProcessedEvent;
+pub use error::Error;
+pub use sources::Source;
+pub use web_requests::FetchError;
+
+use timezone::Zone;
+use web_requests::{NetworkPolicy, WebClient};
+
+/// Fetches one source and processes its events.
+///
+/// Every page the source's config follows is fetched. Events without a name and duplicate
+/// listings are dropped, and dates are read as Nashville time; use `sources::fetch_source`
+/// and `pipeline::process_events` to choose the timezone or see what was dropped.
+///
+/// # Arguments
+///
+/// * `source` - The source to fetch, e.g. one of `sources::default_sources()`.
+///
+/// # Returns
+///
+/// The source's events in page order; `Error::InvalidSource` if its configuration can't be
+/// used, or `Error::Fetch` if a request failed or the response couldn't be read.
+pub fn fetch_events(source: &Source) -> Result<Vec<ProcessedEvent>, Error> {
+    check(source)?;
+    let client = WebClient::new(NetworkPolicy::Online)?;
+    let page = sources::fetch_source(source, &client, Zone::default())?;
+    Ok(process(page.events, source))
+}
+
+/// Parses a page already fetched from a source, like `fetch_events` without the network.
+///
+/// # Arguments
+///
+/// * `content` - The page, in the format the source's `config.strategy` reads.
+/// * `source` - The source the page came from.
+///
+/// # Returns
+///
+/// The page's events in page order; `Error::InvalidSource` if the source's configuration
+/// can't be used, or `Error::Fetch` if the page is a broken feed or calendar, or looks like
+/// a bot challenge.
+///
+/// # Examples
+///
+/// ```
+/// use event_finder::html_parser::SiteConfig;
+/// use event_finder::sources::{Categories, Category};
+/// use event_finder::Source;
+///
+/// let source = Source {
+///     name: "example".to_string(),
+///     url: "https://example.com/events".to_string(),
+///     base_url: "https://example.com".to_string(),
+///     categories: Categories::from([Category::Music]),
+///     cities: Vec::new(),
+///     config: SiteConfig {
+///         event_selector: ".event".to_string(),
+///         name_selector: ".name".to_string(),
+///         start_date_selector: ".start".to_string(),
+///         url: "a".to_string(),
+///         ..Default::default()
+///     },
+/// };
+/// let html = r#"<div class="event"><h2 class="name">Open  Mic</h2>
+///     <span class="start">2024-05-02 19:30</span><a href="/open-mic">Details</a></div>"#;
+///
+/// let events = event_finder::parse_events(html, &source)?;
+///
+/// assert_eq!(events.len(), 1);
+/// assert_eq!(events[0].name, "Open Mic");
+/// assert_eq!(events[0].url, "https://example.com/open-mic");
+/// assert_eq!(events[0].source.as_deref(), Some("example"));
+/// assert_eq!(events[0].start.unwrap().to_string(), "2024-05-02 19:30:00");
+/// # Ok::<(), event_finder::Error>(())
+/// ```
+pub fn parse_events(content: &str, source: &Source) -> Result<Vec<ProcessedEvent>, Error> {
+    check(source)?;
+    let page = sources::event_source(source.clone()).parse(content, Zone::default())?;
+    Ok(process(page.events, source))
+}
+
+/// Checks a source's configuration, so every problem in it is reported at once.
+fn check(source: &Source) -> Result<(), Error> {
+    source.validate().map_err(|errors| Error::InvalidSource {
+        source: source.name.clone(),
+        errors,
+    })
+}
+
+/// Drops nameless and duplicate events and processes the rest.
+fn process(events: Vec<html_parser::Event>, source: &Source) -> Vec<ProcessedEvent> {
+    let (events, _) = html_parser::drop_nameless(events, false);
+    let (events, _) = html_parser::dedupe(events);
+    pipeline::process_events(events, source, Zone::default()).0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use html_parser::SiteConfig;
+    use sources::{Categories, Category};
+    use std::net::TcpListener;
+
+    fn source(url: &str, event_selector: &str) -> Source {
+        Source {
+            name: "example".to_string(),
+            url: url.to_string(),
+            base_url: "http://example.com".to_string(),
+            categories: Categories::from([Category::General]),
+            cities: Vec::new(),
+            config: SiteConfig {
+                event_selector: event_selector.to_string(),
+                name_selector: ".name".to_string(),
+                start_date_selector: ".start".to_string(),
+                url: "a".to_string(),
+                ..Default::default()
+            },
+        }
+    }
+
+    #[test]
+    fn test_bad_selector_is_invalid_source() {
+        match parse_events("<div></div>", &source("http://example.com", "div[")) {
+            Err(Error::InvalidSource { source, errors }) => {
+                assert_eq!(source, "example");
+                assert_eq!(errors[0].field, "event_selector");
+            }
+            other => panic!("expected InvalidSource, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_unreachable_host_is_fetch_error() {
+        // Bind and release a port so nothing is listening on it
+        let port = TcpListener::bind("127.0.0.1:0")
+            .unwrap()
+            .local_addr()
+            .unwrap()
+            .port();
+
+        match fetch_events(&source(
+            &format!("http://127.0.0.1:{}/events", port),
+            ".event",
+        )) {
+            Err(Error::Fetch(FetchError::Http(e))) => assert!(e.is_connect()),
+            other => panic!("expected a connection error, got {:?}", other),
+        }
+    }
+}
@@ -0,0 +1,433 @@
+//! # Logging Module
+//!
+//! This module prints the `log` records the library and binary emit, to stderr. Errors and
+//! warnings read as they always have, `Error: ...` and `Warning: ...`; info, debug, and trace
+//! records, shown with `-v`, `-vv`, and `-vvv`, are tagged with their level and module.
+//! `RUST_LOG` takes the usual `level` or `module=level` list and wins over the flags.
+//!
+//! With `--error-format json` every record is printed as a JSON object instead, one per line,
+//! and failures reported through `report` also say what kind of failure they were and which
+//! source it was.
+
+use std::env;
+use std::io::Write;
+use std::str::FromStr;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use log::{Level, LevelFilter, Log, Metadata, Record};
+use serde_json::{json, Value};
+
+use crate::output::Styler;
+
+/// Whether the installed logger prints JSON, for `report`.
+static JSON: AtomicBool = AtomicBool::new(false);
+
+/// How records are printed, chosen with `--error-format`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ErrorFormat {
+    /// Lines meant for people, see `format_record`.
+    #[default]
+    Text,
+    /// One JSON object per line, see `format_json`.
+    Json,
+}
+
+impl FromStr for ErrorFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "text" => Ok(ErrorFormat::Text),
+            "json" => Ok(ErrorFormat::Json),
+            _ => Err(format!(
+                "unknown error format '{}' (expected text or json)",
+                s
+            )),
+        }
+    }
+}
+
+/// Which records are printed: a level per module prefix, and one for everything else.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Filter {
+    default: LevelFilter,
+    modules: Vec<(String, LevelFilter)>,
+}
+
+impl Filter {
+    /// Creates a filter printing records up to `level` from every module.
+    pub fn new(level: LevelFilter) -> Filter {
+        Filter {
+            default: level,
+            modules: Vec::new(),
+        }
+    }
+
+    /// Parses a `RUST_LOG` value, e.g. `info` or `warn,event_finder::pipeline=debug`.
+    ///
+    /// # Arguments
+    ///
+    /// * `spec` - Comma-separated directives, each a level or `module=level`.
+    /// * `default` - The level for modules the spec doesn't name, if it has no bare level.
+    ///
+    /// # Returns
+    ///
+    /// The filter, or a message naming the directive that isn't a level.
+    pub fn parse(spec: &str, default: LevelFilter) -> Result<Filter, String> {
+        let mut filter = Filter::new(default);
+        for directive in spec.split(',').map(str::trim).filter(|d| !d.is_empty()) {
+            let invalid = || {
+                format!(
+                    "invalid directive '{}' (expected a level such as debug, or module=level)",
+                    directive
+                )
+            };
+            match directive.split_once('=') {
+                Some((module, level)) => {
+                    let level = level.trim().parse().map_err(|_| invalid())?;
+                    filter.modules.push((module.trim().to_string(), level));
+                }
+                // A bare module name turns everything on for it, as env_logger does
+                None => match directive.parse() {
+                    Ok(level) => filter.default = level,
+                    Err(_) => filter
+                        .modules
+                        .push((directive.to_string(), LevelFilter::Trace)),
+                },
+            }
+        }
+        // The longest matching prefix wins, so it is checked first
+        filter
+            .modules
+            .sort_by_key(|(module, _)| std::cmp::Reverse(module.len()));
+        Ok(filter)
+    }
+
+    /// Whether a record from `target` at `level` is printed.
+    pub fn enabled(&self, target: &str, level: Level) -> bool {
+        let named = self.modules.iter().find(|(module, _)| {
+            target == module
+                || target
+                    .strip_prefix(module.as_str())
+                    .is_some_and(|rest| rest.starts_with("::"))
+        });
+        level <= named.map_or(self.default, |(_, level)| *level)
+    }
+
+    /// The most verbose level any module is printed at.
+    pub fn max_level(&self) -> LevelFilter {
+        self.modules
+            .iter()
+            .map(|(_, level)| *level)
+            .fold(self.default, Ord::max)
+    }
+}
+
+/// Returns the level the verbosity flags ask for.
+///
+/// # Arguments
+///
+/// * `verbosity` - How many times `-v` was given.
+/// * `quiet` - Whether `--quiet` was given, which leaves only errors.
+pub fn level_for(verbosity: u8, quiet: bool) -> LevelFilter {
+    match (quiet, verbosity) {
+        (true, _) => LevelFilter::Error,
+        (false, 0) => LevelFilter::Warn,
+        (false, 1) => LevelFilter::Info,
+        (false, 2) => LevelFilter::Debug,
+        (false, _) => LevelFilter::Trace,
+    }
+}
+
+/// Renders one record as the line printed for it, without the newline.
+///
+/// # Arguments
+///
+/// * `level` - The record's level.
+/// * `target` - The module it came from, e.g. `event_finder::pipeline`.
+/// * `message` - What it says.
+/// * `styler` - How the line is styled: errors red, warnings yellow, the rest dimmed.
+pub fn format_record(level: Level, target: &str, message: &str, styler: Styler) -> String {
+    match level {
+        Level::Error => styler.error(&format!("Error: {}", message)),
+        Level::Warn => styler.warning(&format!("Warning: {}", message)),
+        _ => styler.summary(&format!(
+            "[{} {}] {}",
+            level.as_str().to_lowercase(),
+            target,
+            message
+        )),
+    }
+}
+
+/// Renders one record as a JSON object, for `--error-format json`.
+///
+/// # Arguments
+///
+/// * `level` - The record's level, e.g. `"level": "error"`.
+/// * `target` - The module it came from.
+/// * `message` - What it says.
+/// * `kind` - What kind of failure it reports, e.g. `http`, if it came from `report`.
+/// * `source` - The source that failed, if any.
+pub fn format_json(
+    level: Level,
+    target: &str,
+    message: &str,
+    kind: Option<&str>,
+    source: Option<&str>,
+) -> String {
+    let mut json =
+        json!({"level": level.as_str().to_lowercase(), "target": target, "message": message});
+    if let Value::Object(fields) = &mut json {
+        if let Some(kind) = kind {
+            fields.insert("kind".to_string(), json!(kind));
+        }
+        if let Some(source) = source {
+            fields.insert("source".to_string(), json!(source));
+        }
+    }
+    json.to_string()
+}
+
+/// Logs a failure the exit status depends on, with what kind it was and the source it
+/// concerns, which `--error-format json` includes as fields of its object.
+///
+/// # Arguments
+///
+/// * `level` - The level to log at.
+/// * `kind` - What kind of failure it is, e.g. `FetchError::kind`.
+/// * `source` - The source that failed, if any.
+/// * `message` - What went wrong, as it is printed in text.
+pub fn report(level: Level, kind: &str, source: Option<&str>, message: &str) {
+    if !JSON.load(Ordering::Relaxed) {
+        log::log!(level, "{}", message);
+    } else if log::log_enabled!(level) {
+        let _ = writeln!(
+            std::io::stderr().lock(),
+            "{}",
+            format_json(level, module_path!(), message, Some(kind), source)
+        );
+    }
+}
+
+/// Shortens text for a trace record to at most `max` characters, on one line.
+pub fn snippet(text: &str, max: usize) -> String {
+    let text = text.split_whitespace().collect::<Vec<_>>().join(" ");
+    match text.char_indices().nth(max) {
+        Some((at, _)) => format!("{}…", &text[..at]),
+        None => text,
+    }
+}
+
+/// A logger printing the records its filter lets through to stderr.
+#[derive(Debug)]
+pub struct StderrLogger {
+    filter: Filter,
+    styler: Styler,
+    format: ErrorFormat,
+}
+
+impl Log for StderrLogger {
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        self.filter.enabled(metadata.target(), metadata.level())
+    }
+
+    fn log(&self, record: &Record) {
+        if self.enabled(record.metadata()) {
+            let message = record.args().to_string();
+            let line = match self.format {
+                ErrorFormat::Text => {
+                    format_record(record.level(), record.target(), &message, self.styler)
+                }
+                ErrorFormat::Json => {
+                    format_json(record.level(), record.target(), &message, None, None)
+                }
+            };
+            let _ = writeln!(std::io::stderr().lock(), "{}", line);
+        }
+    }
+
+    fn flush(&self) {
+        let _ = std::io::stderr().flush();
+    }
+}
+
+/// Installs the stderr logger for the rest of the process.
+///
+/// `RUST_LOG`, when set, picks what is printed instead of `level`; if it can't be read, that
+/// is logged as a warning and `level` is used.
+///
+/// # Arguments
+///
+/// * `level` - The level from the verbosity flags, see `level_for`.
+/// * `styler` - How lines are styled, for stderr.
+/// * `format` - Whether records are printed as text or JSON.
+pub fn init(level: LevelFilter, styler: Styler, format: ErrorFormat) {
+    let (filter, problem) = match env::var("RUST_LOG") {
+        Ok(spec) if !spec.trim().is_empty() => match Filter::parse(&spec, level) {
+            Ok(filter) => (filter, None),
+            Err(e) => (Filter::new(level), Some(e)),
+        },
+        _ => (Filter::new(level), None),
+    };
+    log::set_max_level(filter.max_level());
+    // Only the first call installs a logger; later ones keep it
+    if log::set_boxed_logger(Box::new(StderrLogger {
+        filter,
+        styler,
+        format,
+    }))
+    .is_ok()
+    {
+        JSON.store(format == ErrorFormat::Json, Ordering::Relaxed);
+        if let Some(e) = problem {
+            log::warn!("RUST_LOG: {}", e);
+        }
+    }
+}
+
+/// A logger for tests that keeps every record, so they can check what was logged.
+#[cfg(test)]
+pub(crate) mod capture {
+    use std::sync::{Mutex, Once};
+
+    use log::{Level, LevelFilter, Log, Metadata, Record};
+
+    static RECORDS: Mutex<Vec<(Level, String, String)>> = Mutex::new(Vec::new());
+    static INSTALL: Once = Once::new();
+
+    struct CaptureLogger;
+
+    impl Log for CaptureLogger {
+        fn enabled(&self, _: &Metadata) -> bool {
+            true
+        }
+
+        fn log(&self, record: &Record) {
+            RECORDS.lock().unwrap().push((
+                record.level(),
+                record.target().to_string(),
+                record.args().to_string(),
+            ));
+        }
+
+        fn flush(&self) {}
+    }
+
+    /// Installs the capturing logger, once for the whole test binary.
+    pub(crate) fn install() {
+        INSTALL.call_once(|| {
+            log::set_logger(&CaptureLogger).unwrap();
+            log::set_max_level(LevelFilter::Trace);
+        });
+    }
+
+    /// Returns the records so far whose message contains `text`, as tests run in parallel.
+    pub(crate) fn records_containing(text: &str) -> Vec<(Level, String, String)> {
+        RECORDS
+            .lock()
+            .unwrap()
+            .iter()
+            .filter(|(_, _, message)| message.contains(text))
+            .cloned()
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_filter_from_rust_log() {
+        let filter = Filter::parse(
+            "warn,event_finder::pipeline=debug, event_finder::html_parser=trace",
+            LevelFilter::Error,
+        )
+        .unwrap();
+
+        assert!(filter.enabled("event_finder::pipeline", Level::Debug));
+        assert!(!filter.enabled("event_finder::pipeline", Level::Trace));
+        assert!(filter.enabled("event_finder::html_parser", Level::Trace));
+        assert!(filter.enabled("event_finder::sources", Level::Warn));
+        assert!(!filter.enabled("event_finder::sources", Level::Info));
+        assert!(!filter.enabled("event_finder::pipelines", Level::Debug));
+        assert_eq!(filter.max_level(), LevelFilter::Trace);
+
+        let filter = Filter::parse("event_finder", LevelFilter::Warn).unwrap();
+        assert!(filter.enabled("event_finder::geo", Level::Trace));
+        assert!(!filter.enabled("reqwest::connect", Level::Info));
+        assert_eq!(
+            Filter::parse("debug", LevelFilter::Warn).unwrap(),
+            Filter::new(LevelFilter::Debug)
+        );
+        assert!(Filter::parse("pipeline=loud", LevelFilter::Warn)
+            .unwrap_err()
+            .starts_with("invalid directive 'pipeline=loud'"));
+    }
+
+    #[test]
+    fn test_snippet() {
+        assert_eq!(
+            snippet("<div>\n  <b>Jazz</b>\n</div>", 100),
+            "<div> <b>Jazz</b> </div>"
+        );
+        assert_eq!(snippet("<div class='event'>", 5), "<div …");
+    }
+
+    #[test]
+    fn test_level_for_flags() {
+        assert_eq!(level_for(0, false), LevelFilter::Warn);
+        assert_eq!(level_for(1, false), LevelFilter::Info);
+        assert_eq!(level_for(2, false), LevelFilter::Debug);
+        assert_eq!(level_for(5, false), LevelFilter::Trace);
+        assert_eq!(level_for(2, true), LevelFilter::Error);
+    }
+
+    #[test]
+    fn test_format_record() {
+        let plain = Styler::plain();
+        assert_eq!(
+            format_record(Level::Error, "event_finder", "offline", plain),
+            "Error: offline"
+        );
+        assert_eq!(
+            format_record(Level::Warn, "event_finder", "sources.toml: line 3", plain),
+            "Warning: sources.toml: line 3"
+        );
+        assert_eq!(
+            format_record(
+                Level::Debug,
+                "event_finder::pipeline",
+                "songkick: 12 events",
+                plain
+            ),
+            "[debug event_finder::pipeline] songkick: 12 events"
+        );
+        assert_eq!(
+            format_record(Level::Warn, "event_finder", "x", Styler::new(true)),
+            "\u{1b}[33mWarning: x\u{1b}[0m"
+        );
+    }
+
+    #[test]
+    fn test_format_json() {
+        assert_eq!(
+            format_json(Level::Warn, "event_finder", "say \"hi\"", None, None),
+            r#"{"level":"warn","message":"say \"hi\"","target":"event_finder"}"#
+        );
+        let line = format_json(
+            Level::Error,
+            "event_finder::logging",
+            "fetching perto: timed out",
+            Some("timeout"),
+            Some("perto"),
+        );
+        assert_eq!(
+            line,
+            r#"{"kind":"timeout","level":"error","message":"fetching perto: timed out","source":"perto","target":"event_finder::logging"}"#
+        );
+        assert_eq!("JSON".parse(), Ok(ErrorFormat::Json));
+        assert!("xml".parse::<ErrorFormat>().is_err());
+    }
+}
@@ -5,212 +5,1994 @@
 //! such as music, unique, general, and all. The application fetches event data from
 //! specified URLs, processes it, and displays it in a user-friendly format.
 //!
-//! The program demonstrates the use of external crates like `reqwest` for web requests,
-//! `chrono` for date and time handling, and custom modules for HTML parsing and data processing.
+//! The fetching, parsing, and processing live in the `event_finder` library; this binary
+//! parses the command line, runs the menu, and prints.
 
-mod web_requests;
-mod html_parser;
-mod data_processing;
+mod cli;
+mod completions;
+mod dry_run;
+mod exit;
+mod interrupt;
+mod pager;
+mod prompt;
+mod session;
+mod watch;
 
-use chrono::{Local, Datelike, Timelike};
-use std::io::{self, Write};
+use chrono::{Datelike, Timelike};
+use rand::rngs::StdRng;
+use rand::SeedableRng;
+use std::collections::HashSet;
+use std::io::{self, IsTerminal, Read, Write};
+
+use cli::{Command, Options};
+use event_finder::browser::{Launcher, SystemBrowser};
+use event_finder::city::City;
+use event_finder::config::Config;
+use event_finder::data_processing::{ExcludeField, ExcludeRule, GroupKey, Match, ProcessedEvent};
+use event_finder::diff::EventDiff;
+use event_finder::email::{Mailer, SmtpMailer};
+use event_finder::history::HistoryStore;
+use event_finder::logging::ErrorFormat;
+use event_finder::notify::{DesktopNotifier, Notifier};
+use event_finder::output::Styler;
+use event_finder::prefs::PrefsStore;
+use event_finder::seen::SeenStore;
+use event_finder::snapshot::SnapshotStore;
+use event_finder::sources::{Category, EventSource, Source};
+use event_finder::table::{self, TableLayout};
+use event_finder::timezone::Zone;
+use event_finder::web_requests::{FetchError, NetworkPolicy, ResponseCache, WebClient};
+use event_finder::Error;
+use event_finder::{
+    browser, config, data_processing, diff, email, geo, history, html_parser, ics, logging, notify,
+    output, pipeline, seen, sources, webhook,
+};
+use exit::Outcome;
+use prompt::LineReader;
+use session::{FetchCache, Session, ViewCommand};
+
+/// Prints a line like `println!`, but to stderr when `--format` or `--template` writes data
+/// for other programs, so stdout holds nothing else.
+macro_rules! statusln {
+    ($options:expr, $($arg:tt)*) => {
+        if $options.writes_data() { eprintln!($($arg)*) } else { println!($($arg)*) }
+    };
+}
+
+/// Prints like `print!`, to stderr when `--format` or `--template` writes data; see `statusln!`.
+macro_rules! status {
+    ($options:expr, $($arg:tt)*) => {
+        if $options.writes_data() { eprint!($($arg)*) } else { print!($($arg)*) }
+    };
+}
 
 /// The entry point of the Event Finder CLI application.
 ///
 /// This function handles user input to select different event types to view,
 /// calls appropriate functions to fetch and display events, and manages the application flow.
+/// Without a subcommand it runs the menu, which keeps a session's events until `refresh` and
+/// quits at the end of its input or on Ctrl-C; a subcommand lists once and exits. What each
+/// subcommand and flag does is in `cli::usage`, which `--help` prints, and the statuses a run
+/// exits with are in the `exit` module.
 fn main() {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let mut options = match cli::parse_args(args.iter().cloned()) {
+        Ok(options) => options,
+        Err(e) => {
+            // The flag can't be read from options that didn't parse, so it is looked for directly
+            let json = args
+                .windows(2)
+                .any(|pair| pair[0] == "--error-format" && pair[1].eq_ignore_ascii_case("json"));
+            let format = if json {
+                ErrorFormat::Json
+            } else {
+                ErrorFormat::Text
+            };
+            logging::init(
+                logging::level_for(0, false),
+                Styler::detect(io::stderr().is_terminal(), false),
+                format,
+            );
+            fail("usage", e, exit::USAGE);
+        }
+    };
+    if let Some(Command::Help(topic)) = &options.command {
+        print!("{}", cli::usage(topic.as_deref()));
+        return;
+    }
+    logging::init(
+        logging::level_for(options.verbosity, options.quiet),
+        options.error_styler(),
+        options.error_format,
+    );
+    let sources_path = options.config.clone().unwrap_or_else(config::sources_path);
+    if let Some(Command::Completions(shell)) = options.command {
+        // Before the config file is loaded, and without failing on the sources file, so a
+        // broken one doesn't break completion; its names are left out instead
+        let file = sources::load_sources(&sources_path).unwrap_or_default();
+        let names = completions::Names {
+            sources: file.sources.into_iter().map(|s| s.name).collect(),
+            cities: file.cities.into_iter().map(|c| c.name).collect(),
+        };
+        print!("{}", completions::script(shell, &names));
+        return;
+    }
+    if options.init_config {
+        match output::save_output(
+            &sources_path,
+            sources::DEFAULT_SOURCES.as_bytes(),
+            options.force,
+        ) {
+            Ok(()) => println!(
+                "Wrote the built-in sources to {}; edit it to add your own.",
+                sources_path.display()
+            ),
+            Err(e) => fail("io", e, exit::IO),
+        }
+        return;
+    }
+    if options.config.is_some() && !sources_path.exists() {
+        fail(
+            "usage",
+            format!("--config: {} doesn't exist", sources_path.display()),
+            exit::USAGE,
+        );
+    }
+    let mut settings = match config::load(&config::config_path()) {
+        Ok(settings) => settings,
+        Err(e) => fail("config", e, exit::CONFIG),
+    };
+    // Every source is checked as it is loaded, before any network request is made
+    let file = match sources::load_sources(&sources_path) {
+        Ok(file) => file,
+        Err(e) => fail("config", e, exit::CONFIG),
+    };
+    for e in &file.errors {
+        log::warn!("{}: {} (entry left out)", sources_path.display(), e);
+    }
+    let city = match file.city(options.city.as_deref().or(settings.city.as_deref())) {
+        Ok(city) => city,
+        Err(e) if options.city.is_some() => fail("usage", e, exit::USAGE),
+        Err(e) => fail("config", e, exit::CONFIG),
+    };
+    let (registry, errors) = file.sources_for(city);
+    for e in errors {
+        log::warn!("{}: {} (source left out)", sources_path.display(), e);
+    }
+    if registry.is_empty() {
+        fail(
+            "config",
+            format!("{} defines no usable sources", sources_path.display()),
+            exit::CONFIG,
+        );
+    }
+    for name in &options.sources {
+        if let Err(e) = sources::find(&registry, name) {
+            fail("usage", e, exit::USAGE);
+        }
+    }
+    if let Some(city) = city {
+        city.apply(&mut settings);
+    }
+    if options.within.is_some() && settings.home.is_none() {
+        fail("config", "--within needs a home location: set lat and lng in the config file's [home] table, or the city's home", exit::CONFIG);
+    }
+    if options.remembers_prefs() {
+        options.apply_prefs(&PrefsStore::open_default().load());
+    }
+    let zone = options.timezone.or(settings.timezone).unwrap_or_default();
+    if options.dry_run {
+        // Before the client exists, so nothing can be fetched
+        let cache = (!options.offline).then(ResponseCache::open_default);
+        let selected =
+            selected_sources(&options.categories(), &options, &registry, &settings, zone);
+        let pipeline = pipeline_settings(&options, &settings, zone);
+        print!(
+            "{}",
+            dry_run::render(
+                &selected,
+                &pipeline,
+                &options,
+                &settings,
+                cache.as_ref(),
+                &SnapshotStore::open_default()
+            )
+        );
+        return;
+    }
+    let policy = if options.offline {
+        NetworkPolicy::Offline
+    } else {
+        NetworkPolicy::Online
+    };
+    let client = match WebClient::new(policy) {
+        Ok(client) => Some(client.with_cache(ResponseCache::open_default())),
+        Err(FetchError::Offline) => None,
+        Err(e) => fail(
+            e.kind(),
+            format!("creating the HTTP client: {}", e),
+            exit::SOFTWARE,
+        ),
+    };
+    if let Some(name) = &options.debug_selectors {
+        if let Err(e) = debug_selectors(name, &registry, client.as_ref(), zone) {
+            fail("source", e, exit::ALL_FAILED);
+        }
+        return;
+    }
+
+    let store = SnapshotStore::open_default();
+    let result = run(
+        &options,
+        client.as_ref(),
+        &store,
+        &registry,
+        &settings,
+        city,
+        zone,
+    );
+    // Only the menu catches Ctrl-C; `--watch` takes it for `q`
+    if interrupt::interrupted() && options.watch.is_none() {
+        let _ = io::stdout().flush();
+        std::process::exit(exit::INTERRUPTED);
+    }
+    match result {
+        Ok(outcome) => match outcome.code(options.strict) {
+            exit::SUCCESS => {}
+            exit::ALL_FAILED => fail(
+                "all_sources_failed",
+                format!("all {} sources failed", outcome.sources),
+                exit::ALL_FAILED,
+            ),
+            code => fail(
+                "sources_failed",
+                format!(
+                    "{} of {} sources failed (--strict)",
+                    outcome.failed.len(),
+                    outcome.sources
+                ),
+                code,
+            ),
+        },
+        Err(e) => fail(e.kind(), &e, exit::for_error(&e)),
+    }
+}
+
+/// Logs an error and exits with `code`; see the `exit` module.
+fn fail(kind: &str, message: impl std::fmt::Display, code: i32) -> ! {
+    logging::report(log::Level::Error, kind, None, &message.to_string());
+    std::process::exit(code);
+}
+
+/// Runs the subcommand, or the interactive menu until the user quits.
+///
+/// Errors from one source are reported and the other sources' events still shown; only
+/// failing to read the user's input ends the menu early.
+///
+/// # Arguments
+///
+/// * `options` - The command-line options.
+/// * `client` - The client to fetch with, or `None` in offline mode.
+/// * `store` - The snapshot store.
+/// * `registry` - The sources events are fetched from.
+/// * `settings` - The settings file.
+/// * `city` - The city the sources are for, if the sources file defines any.
+/// * `zone` - The home timezone.
+///
+/// # Returns
+///
+/// How the sources fetched by the subcommand or `--mark-seen` fared, for the exit status, or
+/// the `Error` that stopped the run. The menu and `--watch` always report none, as a source
+/// failing there is tried again with the next choice or refresh.
+fn run(
+    options: &Options,
+    client: Option<&WebClient>,
+    store: &SnapshotStore,
+    registry: &[Source],
+    settings: &Config,
+    city: Option<&City>,
+    zone: Zone,
+) -> Result<Outcome, Error> {
+    let mut rng = seeded_rng(options);
+    let seen = seen_store(settings);
+    if options.notify && settings.interests.is_empty() {
+        log::warn!("--notify tells about new events matching the config file's [interests], and it lists none");
+    }
+    if options.reset_seen {
+        let forgotten = seen.reset()?;
+        statusln!(
+            options,
+            "Forgot {}.",
+            counted(forgotten, "seen event", "seen events")
+        );
+        return Ok(Outcome::default());
+    }
+    if options.mark_seen {
+        let (_, events, outcome) = fetch_events(
+            &options.categories(),
+            Input::Fetch(client, store),
+            options,
+            registry,
+            settings,
+            zone,
+            None,
+        );
+        let added = seen.mark(&events, zone.now())?;
+        statusln!(
+            options,
+            "Marked {} as seen, {} for the first time.",
+            counted(events.len(), "event", "events"),
+            added
+        );
+        return Ok(outcome);
+    }
+    if options.command == Some(Command::History) {
+        let mut events = history::query(
+            &history_store(settings).load()?,
+            &options.history,
+            zone.now(),
+        );
+        data_processing::sort_events(&mut events, options.sort, options.order);
+        show_events(
+            &format_dates(events, options, settings, zone),
+            options,
+            zone,
+        )?;
+        return Ok(Outcome::default());
+    }
+    if let Some(interval) = options.watch {
+        watch_events(interval, client, store, options, registry, settings, zone)?;
+        return Ok(Outcome::default());
+    }
+    let listing = match &options.command {
+        Some(Command::List(categories)) => {
+            if client.is_none() {
+                log::warn!("Offline mode: showing the last saved events for each source.");
+            }
+            Some(fetch_events(
+                categories,
+                Input::Fetch(client, store),
+                options,
+                registry,
+                settings,
+                zone,
+                None,
+            ))
+        }
+        Some(Command::Parse) => {
+            if io::stdin().is_terminal() {
+                return Err(Error::Input("parse reads the page from stdin; pipe one in, e.g. event_finder parse --source NAME < page.html".to_string()));
+            }
+            let page = read_page(&mut io::stdin().lock())?;
+            Some(fetch_events(
+                &options.categories(),
+                Input::Page(&page),
+                options,
+                registry,
+                settings,
+                zone,
+                None,
+            ))
+        }
+        _ => None,
+    };
+    if let Some((_, events, outcome)) = listing {
+        if options.random {
+            print_pick(&events, options, zone, &mut rng)?;
+            report_failures(&outcome, options);
+        } else {
+            let listed = show_events(&events, options, zone)?;
+            report_failures(&outcome, options);
+            // A piped page may be old or half-built, so its events aren't remembered as listed
+            if options.command != Some(Command::Parse) {
+                if options.notify {
+                    notify_new(&listed, &seen, options, zone, &mut DesktopNotifier);
+                }
+                remember(&listed, &seen, zone);
+            }
+            if let Some(url) = &options.webhook {
+                post_listing(
+                    url,
+                    &listed,
+                    client.ok_or(FetchError::Offline)?,
+                    options,
+                    city,
+                )?;
+            }
+            if let Some(to) = &options.email_to {
+                email_listing(to, &listed, options, settings, city, zone)?;
+            }
+            if let Some(number) = options.open {
+                if !open_listed(&listed, number, options, &mut SystemBrowser) {
+                    std::process::exit(exit::USAGE);
+                }
+            }
+        }
+        return Ok(outcome);
+    }
+
+    // Ctrl-C stops the menu, after the source being fetched, instead of the process
+    interrupt::catch();
+    menu(
+        options,
+        Input::Fetch(client, store),
+        registry,
+        settings,
+        city,
+        zone,
+        &mut prompt::Terminal,
+    )?;
+    Ok(Outcome::default())
+}
+
+/// Returns the generator for random picks, seeded with `--seed` if given.
+fn seeded_rng(options: &Options) -> StdRng {
+    match options.seed {
+        Some(seed) => StdRng::seed_from_u64(seed),
+        None => StdRng::from_entropy(),
+    }
+}
+
+/// Runs the interactive menu until the user quits, the input ends, or Ctrl-C is pressed.
+///
+/// # Arguments
+///
+/// * `options` - The command-line options.
+/// * `input` - Where the sources' events come from.
+/// * `registry` - The sources events are fetched from.
+/// * `settings` - The settings file.
+/// * `city` - The city the sources are for, if the sources file defines any.
+/// * `zone` - The home timezone.
+/// * `lines` - Where the user's answers are read from.
+///
+/// # Returns
+///
+/// `Ok(())` once the menu ends, or the error reading the answers or writing the listing.
+fn menu(
+    options: &Options,
+    input: Input,
+    registry: &[Source],
+    settings: &Config,
+    city: Option<&City>,
+    zone: Zone,
+    lines: &mut dyn LineReader,
+) -> io::Result<()> {
+    let mut rng = seeded_rng(options);
+    let seen = seen_store(settings);
+
     // Welcome message
-    println!("Welcome to the Event Finder!\n");
+    match city {
+        Some(city) => statusln!(options, "Welcome to the Event Finder for {}!\n", city.label),
+        None => statusln!(options, "Welcome to the Event Finder!\n"),
+    }
 
     // Print today's date and time
-    let now = Local::now();
-    println!("Today's date is {}-{}-{}", now.year(), now.month(), now.day());
-    println!("Current time is {}:{}:{}\n", now.hour(), now.minute(), now.second());
+    let now = zone.now();
+    statusln!(
+        options,
+        "Today's date is {}-{}-{}",
+        now.year(),
+        now.month(),
+        now.day()
+    );
+    statusln!(
+        options,
+        "Current time is {}:{}:{}\n",
+        now.hour(),
+        now.minute(),
+        now.second()
+    );
+
+    if let Input::Fetch(None, _) = input {
+        statusln!(
+            options,
+            "Offline mode: showing the last saved events for each source.\n"
+        );
+    }
 
+    let mut session = Session::default();
+    // Categories no loaded source serves are left off the menu; the numbers stay the same
+    let offered: Vec<Category> = Category::iter()
+        .filter(|c| registry.iter().any(|s| s.matches_any(&[*c])))
+        .collect();
+    let quit = (Category::iter().count() + 1).to_string();
+    let surprise = (Category::iter().count() + 2).to_string();
+    // What an empty choice fetches: the last one, starting from the previous session's
+    let mut last_choice: Vec<Category> = options
+        .menu_default
+        .iter()
+        .copied()
+        .filter(|c| offered.contains(c))
+        .collect();
 
     loop {
         // Ask the user to choose an event type
-        println!("Please choose an event type:");
-        println!("1: Music");
-        println!("2: Unique");
-        println!("3: General");
-        println!("4: All");
-        println!("5: Quit");
-
-        // Read user input
-        let mut input = String::new();
-        io::stdout().flush().unwrap(); // Flush to make sure the prompt is printed before reading input
-        io::stdin().read_line(&mut input).unwrap();
-
-        // Process user input
-        match input.trim() {
-            "1" | "Music" | "music" => {
-                println!("Fetching music events...");
-                fetch_music_events("https://www.songkick.com/metro-areas/11104-us-nashville/tonight");
-            },
-            "2" | "Unique" | "unique" => {
-                println!("Fetching unique events...");
-                fetch_unique_events("https://en.perto.com/us/nashville-10005/events-today/");
-            },
-            "3" | "General" | "general" => {
-                println!("Fetching general events...");
-                fetch_general_events("https://www.nashville.com/calendar-of-events/");
-            },
-            "4" | "All" | "all" => {
-                println!("Fetching all events...");
-                fetch_music_events("https://www.songkick.com/metro-areas/11104-us-nashville/tonight");
-                fetch_unique_events("https://en.perto.com/us/nashville-10005/events-today/");
-                fetch_general_events("https://www.nashville.com/calendar-of-events/");
-            },
-            "5" | "quit" | "Quit" => {
-                println!("Exiting the Event Finder.");
+        statusln!(
+            options,
+            "Please choose an event type, or several separated by commas:"
+        );
+        for category in &offered {
+            statusln!(options, "{}: {}", category.number(), category.label());
+        }
+        statusln!(options, "{}: Quit", quit);
+        statusln!(options, "{}: Surprise me", surprise);
+        if !last_choice.is_empty() {
+            statusln!(options, "Enter: {} again", selection_label(&last_choice));
+        }
+
+        // Read user input; the end of it, or Ctrl-C, quits
+        let Some(answer) = lines.read_line()? else {
+            statusln!(options, "\nExiting the Event Finder.");
+            break;
+        };
+        let answer = answer.trim().to_lowercase();
+
+        // Process user input; a choice of categories leaves which sources failed to list after them
+        let fetched = match answer.as_str() {
+            choice if choice == quit || choice == "quit" => {
+                statusln!(options, "Exiting the Event Finder.");
                 break;
-            },
-            _ => {
-                println!("Invalid input. Please enter a number (1-4) or event type.");
-                // The loop will continue
             }
+            choice if choice == surprise || choice == "surprise" => {
+                statusln!(options, "Fetching all events to pick from...");
+                let (changes, events, outcome) = fetch_events(
+                    &Category::ALL,
+                    input,
+                    options,
+                    registry,
+                    settings,
+                    zone,
+                    Some(&mut session.cache),
+                );
+                if interrupt::interrupted() {
+                    statusln!(options, "Stopped fetching. Exiting the Event Finder.");
+                    break;
+                }
+                print_pick(&events, options, zone, &mut rng)?;
+                report_failures(&outcome, options);
+                session.fetched(&Category::ALL, changes, events);
+                None
+            }
+            choice => {
+                let (categories, errors) = if choice.is_empty() {
+                    (last_choice.clone(), Vec::new())
+                } else {
+                    sources::parse_selection(choice)
+                };
+                for error in &errors {
+                    statusln!(options, "Invalid input: {}.", error);
+                }
+                if categories.is_empty() {
+                    statusln!(options, "Invalid input. Please enter a number (1-{}) or event type, or several like 1,2.", surprise);
+                    // The loop will continue
+                    None
+                } else {
+                    if options.remembers_prefs() {
+                        if let Err(e) = PrefsStore::open_default().save(&options.prefs(&categories))
+                        {
+                            log::warn!("saving the preferences: {}", e);
+                        }
+                    }
+                    statusln!(
+                        options,
+                        "Fetching {} events...",
+                        selection_label(&categories)
+                    );
+                    // Each source is fetched once, and the events of every category deduplicated and sorted together
+                    // Sources fetched earlier in the session aren't fetched again
+                    let (changes, events, outcome) = fetch_events(
+                        &categories,
+                        input,
+                        options,
+                        registry,
+                        settings,
+                        zone,
+                        Some(&mut session.cache),
+                    );
+                    if interrupt::interrupted() {
+                        statusln!(options, "Stopped fetching. Exiting the Event Finder.");
+                        break;
+                    }
+                    session.fetched(&categories, changes, events);
+                    last_choice = categories;
+                    Some(outcome)
+                }
+            }
+        };
+        if let Some(outcome) = fetched {
+            show_session(&mut session, options, zone, &mut rng)?;
+            if !options.random {
+                remember(&session.listed, &seen, zone);
+            }
+            report_failures(&outcome, options);
         }
 
-        // Ask if the user wants to continue or quit
-        if !should_continue() {
-            println!("Thank you for using the Event Finder!");
-            break;
+        // Ask if the user wants to continue or quit, fetching the last choice again on `refresh`
+        loop {
+            match next_step(
+                &mut session,
+                options,
+                zone,
+                &mut rng,
+                &mut SystemBrowser,
+                lines,
+            )? {
+                Step::Menu => break,
+                Step::Quit => {
+                    statusln!(options, "Thank you for using the Event Finder!");
+                    return Ok(());
+                }
+                Step::Refresh => {
+                    statusln!(
+                        options,
+                        "Fetching {} events again...",
+                        selection_label(&session.categories)
+                    );
+                    let categories = session.categories.clone();
+                    let (changes, events, outcome) = fetch_events(
+                        &categories,
+                        input,
+                        options,
+                        registry,
+                        settings,
+                        zone,
+                        Some(&mut session.cache),
+                    );
+                    if interrupt::interrupted() {
+                        statusln!(options, "Stopped fetching. Exiting the Event Finder.");
+                        return Ok(());
+                    }
+                    session.fetched(&categories, changes, events);
+                    show_session(&mut session, options, zone, &mut rng)?;
+                    if !options.random {
+                        remember(&session.listed, &seen, zone);
+                    }
+                    report_failures(&outcome, options);
+                }
+            }
         }
-    }   
+    }
+    Ok(())
 }
 
+/// Prints which sources failed, after the listing their events are missing from, so the gap
+/// isn't lost among the errors logged while fetching.
+fn report_failures(outcome: &Outcome, options: &Options) {
+    for line in outcome.summary() {
+        statusln!(options, "{}", options.styler().warning(&line));
+    }
+}
+
+/// Names the categories of a menu choice, e.g. `music and unique`, or `all` for every one.
+fn selection_label(categories: &[Category]) -> String {
+    if categories == Category::ALL {
+        return Category::All.to_string();
+    }
+    let names: Vec<String> = categories.iter().map(ToString::to_string).collect();
+    names.join(" and ")
+}
 
-/// Fetches and displays general events from a specified URL.
+/// Fetches, filters, and sorts the events of the requested categories.
+///
+/// Every source serving at least one of the categories is fetched exactly once, even when it
+/// is listed under several of them. In offline mode (no client) each source's last snapshot
+/// is used instead, and `Input::Page` parses a page the user piped in. What each step left out
+/// is printed as it happens; the events themselves are left to `show_events`, so the menu and
+/// the subcommands list them the same way.
 ///
 /// # Arguments
 ///
-/// * `url` - The URL from which to fetch general events.
-fn fetch_general_events(url: &str) {
+/// * `categories` - The categories to fetch events for.
+/// * `input` - Where the sources' events come from.
+/// * `options` - The command-line options controlling display.
+/// * `registry` - The sources events are fetched from.
+/// * `config` - The settings file, with the venue aliases and the interests events are scored
+///   against.
+/// * `zone` - The home timezone.
+/// * `cache` - The events the session fetched before, reused instead of fetching a source
+///   again, and added to; `None` outside the menu.
+///
+/// # Returns
+///
+/// An `EventDiff` describing what changed since the last snapshot of the fetched sources, the
+/// events that passed the filters, before any `--search`, and how many sources failed.
+fn fetch_events(
+    categories: &[Category],
+    input: Input,
+    options: &Options,
+    registry: &[Source],
+    config: &Config,
+    zone: Zone,
+    mut cache: Option<&mut FetchCache>,
+) -> (EventDiff, Vec<ProcessedEvent>, Outcome) {
+    let settings = pipeline_settings(options, config, zone);
+    // A piped page is parsed without the network, for venues as much as for events
+    let client = match input {
+        Input::Fetch(client, _) => client,
+        Input::Page(_) => None,
+    };
+    let mut changes = EventDiff::default();
+    let mut shown = Vec::new();
+    // Everything fetched goes into the history, whatever the filters leave out
+    let mut fetched = Vec::new();
+    let mut outcome = Outcome::default();
 
-    let gen_url = url;
+    for event_source in &selected_sources(categories, options, registry, config, zone) {
+        let source = event_source.source();
+        // Ctrl-C skips the sources not fetched yet; those fetched are still saved
+        if interrupt::interrupted() {
+            break;
+        }
+        outcome.sources += 1;
+        let kept = match (input, cache.as_deref()) {
+            (Input::Fetch(..), Some(cache)) => cache.get(source, categories),
+            _ => None,
+        };
+        let reused = kept.is_some();
+        let result = match (kept, input) {
+            (Some(kept), _) => {
+                statusln!(options, "{}: listing the events fetched earlier this session (refresh fetches them again)\n", source.name);
+                Ok(kept)
+            }
+            (None, Input::Fetch(client, store)) => {
+                pipeline::run_source(event_source.as_ref(), categories, client, store, &settings)
+            }
+            (None, Input::Page(body)) => {
+                pipeline::parse_source(event_source.as_ref(), body, categories, &settings)
+            }
+        };
+        let result = match result {
+            Ok(result) => result,
+            Err(e) => {
+                let (level, message) = match &e {
+                    FetchError::Http(http) => (
+                        log::Level::Error,
+                        format!("fetching {}: {}", source.name, http),
+                    ),
+                    FetchError::Page(page) => {
+                        (log::Level::Error, format!("{}: {}", source.name, page))
+                    }
+                    FetchError::Ticketmaster(api) => {
+                        (log::Level::Error, format!("{}: {}", source.name, api))
+                    }
+                    FetchError::Status { .. } => (
+                        log::Level::Error,
+                        format!("fetching {}: {}", source.name, e),
+                    ),
+                    FetchError::NoCachedData(_) => (log::Level::Warn, e.to_string()),
+                    _ => (log::Level::Error, e.to_string()),
+                };
+                logging::report(level, e.kind(), Some(&source.name), &message);
+                outcome.failed.push((source.name.clone(), e.brief()));
+                continue;
+            }
+        };
+        if let Some(cache) = cache.as_deref_mut().filter(|_| !reused) {
+            cache.put(source, categories, &result);
+        }
+        changes.added.extend(result.changes.added);
+        changes.removed.extend(result.changes.removed);
+        changes.changed.extend(result.changes.changed);
+        // Reused events are in the history from when they were fetched
+        if client.is_some() && !reused {
+            fetched.extend(result.events.iter().cloned());
+        }
 
-    let html_content = match web_requests::fetch_url(gen_url) {
-        Ok(content) => content,
-        Err(e) => {
-            eprintln!("Error fetching URL: {}", e);
-            return;
+        let mut events = if options.hide_sold_out {
+            data_processing::hide_unavailable(result.events)
+        } else {
+            result.events
+        };
+        if let Some((from, to)) = options.date_range(zone.today()) {
+            // Listed on each day, so a festival shows up on the days in range rather than once
+            events = data_processing::expand_multi_day(events, options.max_span);
+            events = data_processing::filter_by_range(events, from, to, !options.strict_dates);
+        }
+        shown.extend(events);
+        if result.nameless > 0 {
+            let action = if options.keep_nameless {
+                "kept"
+            } else {
+                "skipped"
+            };
+            statusln!(
+                options,
+                "{}: {} {} that didn't look like {} (check name_selector)\n",
+                source.name,
+                action,
+                counted(result.nameless, "element", "elements"),
+                if result.nameless == 1 {
+                    "an event"
+                } else {
+                    "events"
+                }
+            );
+        }
+        if options.verbose && result.skipped > 0 {
+            statusln!(
+                options,
+                "{}: skipped {} that {} events\n",
+                source.name,
+                counted(
+                    result.skipped,
+                    "structured data object",
+                    "structured data objects"
+                ),
+                if result.skipped == 1 {
+                    "wasn't"
+                } else {
+                    "weren't"
+                }
+            );
+        }
+        if result.duplicates > 0 {
+            statusln!(
+                options,
+                "{}: collapsed {}\n",
+                source.name,
+                counted(result.duplicates, "duplicate listing", "duplicate listings")
+            );
+        }
+        if result.matched > result.parsed {
+            statusln!(
+                options,
+                "{}: showing first {} of ~{} matches (raise --max-events to see more)\n",
+                source.name,
+                result.parsed,
+                result.matched
+            );
+        }
+        let warnings = output::render_warnings(&source.name, &result.warnings, options.verbose)
+            + &output::render_data_warnings(&source.name, &result.data_warnings, options.verbose);
+        for line in warnings.lines() {
+            statusln!(options, "{}", options.styler().warning(line));
+        }
+    }
+
+    if !fetched.is_empty() {
+        match history_store(config).upsert(&fetched, zone.now()) {
+            Ok(upserted) => log::debug!(
+                "history: {} added, {} updated",
+                counted(upserted.inserted, "event", "events"),
+                upserted.updated
+            ),
+            Err(e) => log::error!("saving the fetch history: {}", e),
+        }
+    }
+
+    let (mut shown, merged) =
+        data_processing::merge_cross_source(shown, options.merge_threshold, &config.merge);
+    if merged > 0 {
+        statusln!(
+            options,
+            "merged {} listed by more than one source\n",
+            counted(merged, "event", "events")
+        );
+    }
+    if let Some(home) = config.home {
+        locate_venues(&mut shown, home, client, options, config);
+    }
+    let shown = match options.within {
+        Some(within) => {
+            let (shown, too_far, unknown) = geo::filter_within(shown, within);
+            if too_far > 0 {
+                statusln!(
+                    options,
+                    "hid {} further than {} away\n",
+                    counted(too_far, "event", "events"),
+                    geo::format_distance(within.miles)
+                );
+            }
+            if unknown > 0 && options.verbose {
+                statusln!(
+                    options,
+                    "kept {} whose venue couldn't be located (--geocode looks venues up)\n",
+                    counted(unknown, "event", "events")
+                );
+            }
+            shown
+        }
+        None => shown,
+    };
+    let shown = if options.include_past {
+        shown
+    } else {
+        let assumed_duration = chrono::Duration::minutes(options.assumed_duration);
+        let (shown, ended) = data_processing::drop_ended(shown, zone.now(), assumed_duration);
+        if ended > 0 {
+            statusln!(
+                options,
+                "hid {} already ended (--include-past to show them)\n",
+                counted(ended, "event that has", "events that have")
+            );
         }
+        shown
     };
+    let shown = match options.max_duration {
+        Some(max) => {
+            let (shown, too_long) =
+                data_processing::filter_by_duration(shown, max, !options.strict_duration);
+            if too_long > 0 {
+                statusln!(
+                    options,
+                    "hid {} lasting longer than {}\n",
+                    counted(too_long, "event", "events"),
+                    data_processing::format_duration(max)
+                );
+            }
+            shown
+        }
+        None => shown,
+    };
+    let shown = if options.free {
+        let (shown, not_free) = data_processing::filter_free(shown, options.free_or_unknown);
+        if not_free > 0 {
+            let hidden = if options.free_or_unknown {
+                counted(not_free, "event that costs money", "events that cost money")
+            } else {
+                let hidden = counted(
+                    not_free,
+                    "event that isn't known to be free",
+                    "events that aren't known to be free",
+                );
+                format!("{} (--free-or-unknown to show unpriced ones)", hidden)
+            };
+            statusln!(options, "hid {}\n", hidden);
+        }
+        shown
+    } else {
+        shown
+    };
+    let rules = exclusion_rules(options, &config.filters);
+    let (shown, removed) = data_processing::apply_exclusions(shown, &rules);
+    status!(
+        options,
+        "{}",
+        output::render_exclusions(&rules, &removed, options.verbose)
+    );
+    let shown = if options.new_only {
+        let (shown, hidden) = seen::unseen(shown, &seen_store(config).load());
+        if hidden > 0 {
+            statusln!(
+                options,
+                "hid {} listed before (--reset-seen forgets them)\n",
+                counted(hidden, "event", "events")
+            );
+        }
+        shown
+    } else {
+        shown
+    };
+    let mut shown = data_processing::rank_events(shown, &config.interests);
+
+    // Events from every source are listed together, in one order
+    data_processing::sort_events(&mut shown, options.sort, options.order);
+    (changes, format_dates(shown, options, config, zone), outcome)
+}
 
-    let gen_config = html_parser::SiteConfig {
-        event_selector: String::from(".tribe-events-calendar-list__event"),
-        name_selector: String::from(".tribe-events-calendar-list__event-title-link"),
-        start_date_selector: String::from(".tribe-event-date-start"),
-        end_date_selector: String::from(".tribe-event-date-end"),
-        location_selector: String::from(".tribe-events-calendar-list__event-venue-title"),
-        url: String::from(".tribe-events-calendar-list__event-title-link"),
+/// Returns a count with the noun it counts, e.g. `1 element` or `3 elements`.
+fn counted(count: usize, one: &str, many: &str) -> String {
+    format!("{} {}", count, if count == 1 { one } else { many })
+}
+
+/// Returns the sources serving the categories, narrowed to `--source` and built for the run:
+/// with `--max-events` applied to them, and the days in range for sources that can ask for
+/// only those.
+fn selected_sources(
+    categories: &[Category],
+    options: &Options,
+    registry: &[Source],
+    config: &Config,
+    zone: Zone,
+) -> Vec<Box<dyn EventSource>> {
+    let picked = |source: &&Source| {
+        options.sources.is_empty()
+            || options
+                .sources
+                .iter()
+                .any(|n| n.eq_ignore_ascii_case(&source.name))
+    };
+    let selected: Vec<Source> = sources::resolve(registry, categories)
+        .into_iter()
+        .filter(picked)
+        .cloned()
+        .collect();
+    let request = sources::Request {
+        max_events: options.max_events,
+        range: options.date_range(zone.today()),
+        zone,
+        ticketmaster_api_key: config.ticketmaster_api_key.clone(),
     };
+    sources::registry(selected, &request)
+}
 
-    let events = html_parser::parse_html(&html_content, &gen_config, "https://www.nashville.com");
+/// Returns how the pipeline treats each source's parsed events, from the options and the
+/// settings file.
+fn pipeline_settings(options: &Options, config: &Config, zone: Zone) -> pipeline::Settings {
+    pipeline::Settings {
+        keep_nameless: options.keep_nameless,
+        enrich: options.enrich,
+        enrich_limit: options.enrich_limit,
+        request_delay: pipeline::ENRICH_REQUEST_DELAY,
+        zone,
+        venue_aliases: config.venue_aliases.clone(),
+    }
+}
 
-    // Process the raw events to get processed events
-    let processed_events = data_processing::process_data(events);
+/// Where `fetch_events` gets the sources' events.
+#[derive(Clone, Copy)]
+enum Input<'a> {
+    /// Fetch each source with the client, or serve its snapshot in offline mode (no client),
+    /// comparing against and replacing the store's snapshots.
+    Fetch(Option<&'a WebClient>, &'a SnapshotStore),
+    /// Parse this page for the one `--source` the `parse` subcommand names.
+    Page(&'a str),
+}
 
-    for event in processed_events {
-        println!("Name: {}\nStart Date: {}\nEnd Date: {}\nLocation: {}\nURL: {}", 
-        event.name, event.start_date, event.end_date, event.location, event.url);
-        println!(""); // Add a blank line between events
+/// Shows the events' dates in `--date-format`, the config file's `date_format`, or with
+/// `--format table` the table's format; otherwise as the sources wrote them.
+fn format_dates(
+    events: Vec<ProcessedEvent>,
+    options: &Options,
+    config: &Config,
+    zone: Zone,
+) -> Vec<ProcessedEvent> {
+    let table_format =
+        (options.format == output::Format::Table).then_some(table::TABLE_DATE_FORMAT);
+    match options
+        .date_format
+        .as_deref()
+        .or(config.date_format.as_deref())
+        .or(table_format)
+    {
+        Some(format) => data_processing::apply_date_format(events, format, zone),
+        None => events,
     }
 }
 
-/// Fetches and displays music events from a specified URL.
+/// Lists fetched events in `--format`: those matching `--search`, if given, then a summary.
 ///
-/// # Arguments
+/// # Returns
 ///
-/// * `url` - The URL from which to fetch music events.
-fn fetch_music_events(url: &str) {
-    let songkick_url = url;
+/// The events in the order listed, or the error writing `--output`.
+fn show_events(
+    events: &[ProcessedEvent],
+    options: &Options,
+    zone: Zone,
+) -> io::Result<Vec<ProcessedEvent>> {
+    to_output(options, |out| {
+        list_events(
+            events,
+            &options.search,
+            options.search_mode,
+            options,
+            zone,
+            &HashSet::new(),
+            out,
+        )
+    })
+}
 
-    let song_html_content = match web_requests::fetch_url(songkick_url) {
-        Ok(content) => content,
-        Err(e) => {
-            eprintln!("Error fetching URL: {}", e);
-            return;
+/// Runs `write` against `--output`, or else stdout.
+///
+/// The file is only written once `write` has succeeded, replacing it in one step; it must not
+/// exist yet unless `--force` is given.
+fn to_output<T>(
+    options: &Options,
+    write: impl FnOnce(&mut dyn Write) -> io::Result<T>,
+) -> io::Result<T> {
+    match &options.output {
+        Some(path) => {
+            let mut buffer = Vec::new();
+            let written = write(&mut buffer)?;
+            output::save_output(path, &buffer, options.force)?;
+            statusln!(options, "wrote {}", path.display());
+            Ok(written)
         }
-    };
+        None => write(&mut io::stdout().lock()),
+    }
+}
 
-    let song_config = html_parser::SiteConfig {
-        event_selector: String::from(".event-listings-element"),
-        name_selector: String::from(".artists > a > span > strong"),
-        start_date_selector: String::from(".time"),
-        end_date_selector: String::from(".time"),
-        location_selector: String::from(".location > span > a"),
-        url: String::from(".artists > .event-link"),
+/// Writes events matching search terms in `--format`.
+///
+/// # Arguments
+///
+/// * `events` - The fetched events.
+/// * `terms` - The search terms; with none every event is listed.
+/// * `mode` - Whether all or any of the terms must match.
+/// * `options` - The command-line options controlling display.
+/// * `zone` - The home timezone.
+/// * `fresh` - The ids of the events to mark new since the last `--watch` refresh.
+/// * `out` - Where the listing is written.
+///
+/// # Returns
+///
+/// The events in the order listed, or the error writing them.
+fn list_events(
+    events: &[ProcessedEvent],
+    terms: &[String],
+    mode: Match,
+    options: &Options,
+    zone: Zone,
+    fresh: &HashSet<String>,
+    out: &mut dyn Write,
+) -> io::Result<Vec<ProcessedEvent>> {
+    let matches = if terms.is_empty() {
+        events.to_vec()
+    } else {
+        data_processing::search_events(events.to_vec(), terms, mode)
     };
+    if options.writes_data() {
+        out.write_all(render_data(&matches, options, zone).as_bytes())?;
+        return Ok(matches);
+    }
+    let listed = print_events(&matches, options, zone, fresh, out)?;
+    if !terms.is_empty() {
+        write!(
+            out,
+            "{}",
+            output::render_search_summary(matches.len(), events.len(), terms, mode)
+        )?;
+    }
+    print_summary(&matches, options, zone, out)?;
+    Ok(listed)
+}
 
-    let events = html_parser::parse_html(&song_html_content, &song_config, "https://www.songkick.com");
+/// Renders at most `--limit` events through `--template`, one line each, or else in a
+/// structured `--format`.
+///
+/// The Markdown and HTML reports are grouped by `--group-by`, or by date if it isn't given.
+fn render_data(events: &[ProcessedEvent], options: &Options, zone: Zone) -> String {
+    if let Some(template) = &options.template {
+        let (events, _) = data_processing::limit_events(events.to_vec(), options.limit);
+        return events
+            .iter()
+            .map(|event| template.render(event) + "\n")
+            .collect();
+    }
+    let mut events = events.to_vec();
+    let report = matches!(
+        options.format,
+        output::Format::Markdown | output::Format::Html
+    );
+    let key = options.group_by.unwrap_or(GroupKey::Date);
+    if report && key == GroupKey::Date {
+        // As in `print_events`, each day of a multi-day event goes under its own day
+        events = data_processing::expand_multi_day(events, options.max_span);
+        data_processing::sort_events(&mut events, options.sort, options.order);
+    }
+    let (events, _) = data_processing::limit_events(events, options.limit);
+    match options.format {
+        output::Format::Markdown => output::render_markdown(
+            &data_processing::group_events(events, key),
+            options.description_length,
+        ),
+        output::Format::Html => output::render_html(
+            &data_processing::group_events(events, key),
+            options.description_length,
+        ),
+        output::Format::Csv => output::render_csv(&events, options.crlf),
+        output::Format::Ics => {
+            let (text, skipped) = ics::render_ics(&events, zone, chrono::Utc::now().naive_utc());
+            if skipped > 0 {
+                statusln!(
+                    options,
+                    "left out {} whose start date couldn't be read",
+                    counted(skipped, "event", "events")
+                );
+            }
+            text
+        }
+        output::Format::Json | output::Format::Plain | output::Format::Table => {
+            output::render_json(&events, options.compact) + "\n"
+        }
+    }
+}
 
-    // Process the raw events to get processed events
-    let processed_events = data_processing::process_data(events);
+/// Builds the exclusion rules from `--exclude` and the config file's `[filters]` table.
+fn exclusion_rules(options: &Options, filters: &config::Filters) -> Vec<ExcludeRule> {
+    let whole_word = options.whole_word || filters.whole_word;
+    let text = options
+        .exclude
+        .iter()
+        .chain(&filters.exclude)
+        .map(|p| (p, ExcludeField::Text));
+    let venues = filters
+        .blocked_venues
+        .iter()
+        .map(|p| (p, ExcludeField::Venue));
+    text.chain(venues)
+        .map(|(pattern, field)| ExcludeRule {
+            pattern: pattern.clone(),
+            field,
+            whole_word,
+        })
+        .collect()
+}
+
+/// Prints events, separated by blank lines, with how far off each start is.
+///
+/// With `--group-by`, the events are printed under a header per group. With `--format table`
+/// each event is a row of a table as wide as the terminal, unless it is too narrow for one.
+/// At most `--limit` events are printed, and on a terminal they are shown `pager::PAGE_SIZE`
+/// at a time, except while watching. Blocks are numbered for `o N` and `--open N`, and those
+/// whose id is in `fresh` are marked new.
+///
+/// # Returns
+///
+/// The events in the order they were listed, so the first is number 1.
+fn print_events(
+    events: &[ProcessedEvent],
+    options: &Options,
+    zone: Zone,
+    fresh: &HashSet<String>,
+    out: &mut dyn Write,
+) -> io::Result<Vec<ProcessedEvent>> {
+    let now = Some(zone.now());
+    let styler = options.listing_styler();
+    let mut events = events.to_vec();
+    if options.group_by == Some(GroupKey::Date) {
+        // Each day of a multi-day event goes under its own day, so the days are sorted in
+        events = data_processing::expand_multi_day(events, options.max_span);
+        data_processing::sort_events(&mut events, options.sort, options.order);
+    }
+    let (events, left_out) = data_processing::limit_events(events, options.limit);
+    let layout = match options.format {
+        output::Format::Table => TableLayout::fit(&events, table::terminal_width(options.width)),
+        _ => None,
+    };
+    // Each event with the header of the group it starts, if any
+    let listed: Vec<(Option<String>, ProcessedEvent)> = match options.group_by {
+        Some(key) => data_processing::group_events(events, key)
+            .into_iter()
+            .flat_map(|(title, members)| {
+                members
+                    .into_iter()
+                    .enumerate()
+                    .map(move |(i, event)| ((i == 0).then(|| title.clone()), event))
+            })
+            .collect(),
+        None => events.into_iter().map(|event| (None, event)).collect(),
+    };
+    let mut blocks: Vec<String> = listed
+        .iter()
+        .enumerate()
+        .map(|(i, (title, event))| {
+            // The header goes with the group's first event so it's on the same page
+            let header = title
+                .as_deref()
+                .map(|title| output::render_group_header(title, styler))
+                .unwrap_or_default();
+            let is_fresh = !fresh.is_empty() && fresh.contains(&event.id());
+            let block = output::EventBlock {
+                event,
+                description_length: options.description_length,
+                now,
+                styler,
+                number: Some(i + 1),
+                fresh: is_fresh,
+            };
+            header
+                + &match layout {
+                    Some(layout) => layout.row(event, is_fresh, styler),
+                    None if options.group_by.is_some() => output::render_group_member(block),
+                    None => format!("{}\n", block),
+                }
+        })
+        .collect();
+    if let (Some(layout), Some(first)) = (layout, blocks.first_mut()) {
+        first.insert_str(0, &layout.header(styler));
+    }
+    // While watching, stdin is read for `q`, and a refresh replaces the screen anyway
+    if options.output.is_none()
+        && options.watch.is_none()
+        && io::stdin().is_terminal()
+        && io::stdout().is_terminal()
+    {
+        pager::page(&blocks, pager::PAGE_SIZE, &mut io::stdin().lock(), out)?;
+    } else {
+        write!(out, "{}", blocks.concat())?;
+    }
+    if left_out > 0 {
+        writeln!(
+            out,
+            "showing the first {} of {} events (raise --limit to see more)",
+            blocks.len(),
+            blocks.len() + left_out
+        )?;
+    }
+    Ok(listed.into_iter().map(|(_, event)| event).collect())
+}
 
-    for event in processed_events {
-        println!("Name: {}\nStart Date: {}\nEnd Date: {}\nLocation: {}\nURL: {}", 
-        event.name, event.start_date, event.end_date, event.location, event.url);
-        println!(""); // Add a blank line between events
+/// Prints the summary footer of a listing, and with `-v` its busiest venues.
+fn print_summary(
+    events: &[ProcessedEvent],
+    options: &Options,
+    zone: Zone,
+    out: &mut dyn Write,
+) -> io::Result<()> {
+    let summary = data_processing::summarize(events, zone.today());
+    writeln!(
+        out,
+        "{}",
+        options.listing_styler().summary(&summary.to_string())
+    )?;
+    if options.verbose && !summary.top_venues.is_empty() {
+        let venues: Vec<String> = summary
+            .top_venues
+            .iter()
+            .map(|(venue, n)| format!("{} ({})", venue, n))
+            .collect();
+        writeln!(out, "busiest venues: {}", venues.join(", "))?;
     }
+    Ok(())
 }
 
-/// Fetches and displays unique events from a specified URL.
+/// Lists the subcommand's categories, or all of them, again every `--watch` interval until
+/// the user presses `q` or Ctrl-C.
+///
+/// Each refresh clears the terminal, fetches as `fetch_events` does, through the client's
+/// conditional requests, and lists the events with those that weren't there at the previous
+/// refresh marked `[NEW]`. A source failing on one refresh is logged and tried again at the
+/// next one.
 ///
 /// # Arguments
 ///
-/// * `url` - The URL from which to fetch unique events.
-fn fetch_unique_events(url: &str) {
+/// * `interval` - How long to wait between refreshes.
+/// * `client` - The client to fetch with, or `None` in offline mode.
+/// * `store` - The snapshot store.
+/// * `options` - The command-line options.
+/// * `registry` - The sources events are fetched from.
+/// * `settings` - The settings file.
+/// * `zone` - The home timezone.
+///
+/// # Returns
+///
+/// `Ok(())` once the user quits, or the error writing the listing.
+fn watch_events(
+    interval: chrono::Duration,
+    client: Option<&WebClient>,
+    store: &SnapshotStore,
+    options: &Options,
+    registry: &[Source],
+    settings: &Config,
+    zone: Zone,
+) -> io::Result<()> {
+    let categories = options.categories();
+    let seen = seen_store(settings);
+    if client.is_none() {
+        log::warn!("Offline mode: showing the last saved events for each source.");
+    }
+    let keys = watch::Keys::stdin();
+    interrupt::catch();
+    let styler = options.listing_styler();
+    let every = data_processing::format_duration(interval);
+    let mut previous: Option<HashSet<String>> = None;
+    loop {
+        if io::stdout().is_terminal() {
+            print!("{}", watch::CLEAR_SCREEN);
+        }
+        println!(
+            "{}\n",
+            styler.header(&format!(
+                "Watching for events, refreshing every {} (q to quit)",
+                every
+            ))
+        );
+        let (_, events, _) = fetch_events(
+            &categories,
+            Input::Fetch(client, store),
+            options,
+            registry,
+            settings,
+            zone,
+            None,
+        );
+        if interrupt::interrupted() {
+            break;
+        }
+        let fresh = previous
+            .as_ref()
+            .map(|previous| diff::new_since(previous, &events))
+            .unwrap_or_default();
+        let listed = list_events(
+            &events,
+            &options.search,
+            options.search_mode,
+            options,
+            zone,
+            &fresh,
+            &mut io::stdout().lock(),
+        )?;
+        if options.notify {
+            notify_new(&listed, &seen, options, zone, &mut DesktopNotifier);
+        }
+        remember(&listed, &seen, zone);
+        let new = if previous.is_some() {
+            format!(", {} new since the last refresh", fresh.len())
+        } else {
+            String::new()
+        };
+        println!(
+            "{}",
+            styler.summary(&format!(
+                "Refreshed at {}{}",
+                zone.now().format("%-I:%M:%S %p"),
+                new
+            ))
+        );
+        io::stdout().flush()?;
+        previous = Some(events.iter().map(ProcessedEvent::id).collect());
+        if keys.wait(interval.to_std().unwrap_or_default()) == watch::Wake::Quit {
+            break;
+        }
+    }
+    drop(keys);
+    println!("Stopped watching.");
+    Ok(())
+}
 
-    let unique_url = url;
+/// Returns the store of listed events, which remembers them for the config file's
+/// `seen_days`.
+fn seen_store(config: &Config) -> SeenStore {
+    SeenStore::open_default(chrono::Duration::days(
+        config.seen_days.unwrap_or(seen::DEFAULT_HORIZON_DAYS),
+    ))
+}
 
-    let html_content = match web_requests::fetch_url(unique_url) {
-        Ok(content) => content,
-        Err(e) => {
-            eprintln!("Error fetching URL: {}", e);
-            return;
-        }
+/// Returns the fetch history, in the config file's `history_file` or else the data directory.
+fn history_store(config: &Config) -> HistoryStore {
+    match &config.history_file {
+        Some(path) => HistoryStore::new(path.clone()),
+        None => HistoryStore::open_default(),
+    }
+}
+
+/// Remembers listed events for `--new-only`, logging it if they can't be saved.
+fn remember(listed: &[ProcessedEvent], seen: &SeenStore, zone: Zone) {
+    if let Err(e) = seen.mark(listed, zone.now()) {
+        log::error!("saving the seen events: {}", e);
+    }
+}
+
+/// Posts at most `--limit` of the listed events to `--webhook`, in `--webhook-format`.
+///
+/// # Returns
+///
+/// `Ok(())` once the webhook accepted them, or the `FetchError` that stopped it, with the
+/// webhook's answer.
+fn post_listing(
+    url: &str,
+    listed: &[ProcessedEvent],
+    client: &WebClient,
+    options: &Options,
+    city: Option<&City>,
+) -> Result<(), FetchError> {
+    let (events, _) = data_processing::limit_events(listed.to_vec(), options.limit);
+    let noun = if events.len() == 1 { "event" } else { "events" };
+    let title = match city {
+        Some(city) => format!("{} {} in {}", events.len(), noun, city.label),
+        None => format!("{} {}", events.len(), noun),
+    };
+    webhook::deliver(
+        client,
+        url,
+        &webhook::payload(&events, &title, options.webhook_format),
+    )?;
+    statusln!(options, "Posted {} {} to the webhook.", events.len(), noun);
+    Ok(())
+}
+
+/// Emails at most `--limit` of the listed events to `--email-to` as a digest. With no events
+/// none is sent, unless the config file's `[email]` table sets `send_empty`.
+///
+/// # Returns
+///
+/// `Ok(())` once sent or skipped, or the `Error` if no mail server is set or it refused the
+/// message.
+fn email_listing(
+    to: &str,
+    listed: &[ProcessedEvent],
+    options: &Options,
+    settings: &Config,
+    city: Option<&City>,
+    zone: Zone,
+) -> Result<(), Error> {
+    let email = settings
+        .email
+        .clone()
+        .with_env(|name| std::env::var(name).ok());
+    let (events, _) = data_processing::limit_events(listed.to_vec(), options.limit);
+    if events.is_empty() && !email.send_empty {
+        statusln!(options, "No events to email (set send_empty = true in the config file's [email] table to send one anyway).");
+        return Ok(());
+    }
+    let mailer = SmtpMailer::new(&email).map_err(Error::Email)?;
+    let (Some(from), Some(mut mailer)) = (email.sender(), mailer) else {
+        let message = format!(
+            "--email-to needs a mail server and a sender: set smtp_url and from in the config file's [email] table, or {} and {}",
+            email::SMTP_URL_VAR,
+            email::EMAIL_FROM_VAR
+        );
+        return Err(Error::Email(io::Error::other(message)));
     };
+    let digest = email::Digest {
+        from,
+        to,
+        label: city.map(|city| city.label.as_str()),
+        day: zone.today(),
+        group_by: options.group_by.unwrap_or(GroupKey::Date),
+        description_length: options.description_length,
+        now: zone.now(),
+        sent: chrono::Utc::now(),
+    };
+    mailer
+        .send(&email::compose(&events, &digest))
+        .map_err(Error::Email)?;
+    statusln!(
+        options,
+        "Emailed {} to {}.",
+        counted(events.len(), "event", "events"),
+        to
+    );
+    Ok(())
+}
+
+/// Notifies about the listed events that no earlier run listed and that match the config
+/// file's interests, in one notification; if it can't be shown, it is printed instead.
+fn notify_new(
+    listed: &[ProcessedEvent],
+    seen: &SeenStore,
+    options: &Options,
+    zone: Zone,
+    notifier: &mut dyn Notifier,
+) {
+    let matching = notify::qualifying(listed, &seen.load());
+    if let Some(notification) = notify::compose(&matching, zone.now(), notify::MAX_NAMES) {
+        if let Err(e) = notifier.notify(&notification) {
+            log::debug!("showing the notification: {}", e);
+            statusln!(
+                options,
+                "{}: {}",
+                notification.title,
+                notification.body.replace('\n', ", ")
+            );
+        }
+    }
+}
 
-    let unique_config = html_parser::SiteConfig {
-        event_selector: String::from(".pt_col"),
-        name_selector: String::from(".infos > a > strong"),
-        start_date_selector: String::from(".infos > ul > li > span"),
-        end_date_selector: String::from(".time"),
-        location_selector: String::from(".infos > ul > .pt_list-item.event-location > span"),
-        url: String::from("a"),
+/// Prints one event picked at random from those matching `--search`, if given.
+///
+/// With `--soonest` the pick favors events starting soon. In a structured `--format` the pick
+/// is written like a listing of one event.
+///
+/// # Returns
+///
+/// `Ok(())` once shown, or the error writing `--output`.
+fn print_pick(
+    events: &[ProcessedEvent],
+    options: &Options,
+    zone: Zone,
+    rng: &mut StdRng,
+) -> io::Result<()> {
+    let candidates = if options.search.is_empty() {
+        events.to_vec()
+    } else {
+        data_processing::search_events(events.to_vec(), &options.search, options.search_mode)
     };
+    let now = zone.now();
+    match data_processing::pick_random(&candidates, now, options.soonest, rng) {
+        Some(event) if options.writes_data() => {
+            return to_output(options, |out| {
+                out.write_all(render_data(std::slice::from_ref(event), options, zone).as_bytes())
+            });
+        }
+        Some(event) => {
+            statusln!(
+                options,
+                "\n{}",
+                output::render_pick(
+                    event,
+                    candidates.len(),
+                    options.description_length,
+                    Some(now),
+                    options.styler()
+                )
+            );
+            if options.command.is_none() {
+                statusln!(options, "(enter r at the next prompt to pick again)");
+            }
+        }
+        None => statusln!(options, "No events to pick from."),
+    }
+    Ok(())
+}
 
-    let events = html_parser::parse_html(&html_content, &unique_config, "https://en.perto.com");
+/// Opens a listed event's page, and says what was opened or why nothing was.
+///
+/// # Returns
+///
+/// Whether the page was opened.
+fn open_listed(
+    listed: &[ProcessedEvent],
+    number: usize,
+    options: &Options,
+    launcher: &mut dyn Launcher,
+) -> bool {
+    match browser::open_event(listed, number, launcher) {
+        Ok(opened) => {
+            statusln!(options, "{}", opened);
+            true
+        }
+        Err(e) => {
+            log::error!("{}", e);
+            false
+        }
+    }
+}
 
-    // Process the raw events to get processed events
-    let processed_events = data_processing::process_data(events);
+/// Sets each event's distance from home, geocoding unknown venues with `--geocode`.
+///
+/// Geocoding only happens online; the answers are saved to the cache in the data directory
+/// so each venue is looked up once.
+///
+/// # Arguments
+///
+/// * `events` - The events to annotate.
+/// * `home` - The home location from the config file.
+/// * `client` - The client to geocode with, or `None` in offline mode.
+/// * `options` - The command-line options, for `--geocode`.
+/// * `config` - The settings file, with the known venue coordinates.
+fn locate_venues(
+    events: &mut [ProcessedEvent],
+    home: geo::Coordinates,
+    client: Option<&WebClient>,
+    options: &Options,
+    config: &Config,
+) {
+    let near = config.near.as_deref().unwrap_or(geo::DEFAULT_NEAR);
+    let geocoder = client.filter(|_| options.geocode).map(|client| {
+        geo::Geocoder::new(
+            client,
+            geo::NOMINATIM_URL,
+            near,
+            geo::NOMINATIM_INTERVAL,
+            geo::GEOCODE_LIMIT,
+        )
+    });
+    let mut locator = geo::Locator::new(
+        &config.venue_coordinates,
+        geo::GeoCache::open_default(),
+        geocoder,
+    );
+    geo::annotate_distances(events, home, |venue| locator.locate(venue));
+    if let Some(e) = &locator.error {
+        log::error!("geocoding venues: {}", e);
+    }
+    if locator.deferred > 0 {
+        statusln!(options, "{} venues weren't geocoded this run (at most {} are); the rest are looked up next time\n", locator.deferred, geo::GEOCODE_LIMIT);
+    }
+    if let Err(e) = locator.save() {
+        log::error!("saving the geocoding cache: {}", e);
+    }
+}
 
-    for event in processed_events {
-        println!("Name: {}\nStart Date: {}\nEnd Date: {}\nLocation: {}\nURL: {}", 
-        event.name, event.start_date, event.end_date, event.location, event.url);
-        println!(""); // Add a blank line between events
+/// Fetches one source's listing page and prints what each of its selectors matched, and how
+/// the first start date was read.
+///
+/// # Arguments
+///
+/// * `name` - The name of the source to check.
+/// * `registry` - The sources to find it in.
+/// * `client` - The client to fetch with, or `None` in offline mode.
+/// * `zone` - The home timezone dates are read in.
+///
+/// # Returns
+///
+/// `Ok(())` once the report is printed, or a message if the source is unknown, can't be fetched,
+/// or has a selector that doesn't parse.
+fn debug_selectors(
+    name: &str,
+    registry: &[Source],
+    client: Option<&WebClient>,
+    zone: Zone,
+) -> Result<(), String> {
+    let source = sources::find(registry, name)?;
+    let client =
+        client.ok_or("--debug-selectors needs network access and can't run with --offline")?;
+    let html_content = client
+        .fetch_url(&source.url)
+        .map_err(|e| format!("fetching {}: {}", source.url, e))?;
+    let report = html_parser::debug_selectors(&html_content, &source.config, zone)
+        .map_err(|e| format!("{}: {}", source.name, e))?;
+    print!(
+        "{}",
+        output::render_selector_report(&source.name, &source.config.event_selector, &report)
+    );
+    if let Some(start) = report
+        .fields
+        .iter()
+        .find(|f| f.field == "start_date")
+        .and_then(|f| f.first.as_deref())
+    {
+        let parsed = data_processing::parse_datetime_traced(start, zone.today(), zone);
+        print!("{}", output::render_date_parse(start, parsed));
     }
+    Ok(())
+}
+
+/// Reads the page piped to the `parse` subcommand.
+///
+/// # Returns
+///
+/// The page, or an `Error::Input` if it is empty or isn't UTF-8 text.
+fn read_page(input: &mut dyn Read) -> Result<String, Error> {
+    let mut bytes = Vec::new();
+    input.read_to_end(&mut bytes)?;
+    if bytes.iter().all(u8::is_ascii_whitespace) {
+        return Err(Error::Input("stdin is empty: pipe the page to parse in, e.g. event_finder parse --source NAME < page.html".to_string()));
+    }
+    String::from_utf8(bytes).map_err(|e| Error::Input(format!(
+        "stdin isn't UTF-8 text (invalid byte at offset {}); convert the page first, e.g. with iconv -f latin1 -t utf-8",
+        e.utf8_error().valid_up_to())))
+}
+
+/// What the user chose to do after a listing.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Step {
+    /// Choose again from the menu.
+    Menu,
+    /// Fetch the last choice again, as `Session::apply` forgot what was fetched.
+    Refresh,
+    /// Quit the application.
+    Quit,
 }
 
 /// Prompts the user to choose whether to continue using the application.
 ///
+/// When a fetch has just happened, the user can also enter `d` to see what changed since
+/// the previous snapshot, `s` to search the fetched events, or `r` to have one picked at
+/// random, before answering. `sort KEY [desc]`, `group KEY`, and `filter WORDS` list the
+/// fetched events again another way, without fetching them, and `refresh` fetches them
+/// again. Once events are listed, `o 3` opens the third one's page in the browser; a bare
+/// `o` asks which.
+///
+/// # Arguments
+///
+/// * `session` - The fetched events, as last listed and viewed.
+/// * `options` - The command-line options controlling display.
+/// * `zone` - The home timezone.
+/// * `rng` - The random number generator for `r`.
+/// * `launcher` - What opens an event's page for `o`.
+/// * `lines` - Where the answers are read from.
+///
 /// # Returns
 ///
-/// A boolean value indicating whether the user wants to continue (`true`) or exit (`false`).
-fn should_continue() -> bool {
+/// What to do next, `Step::Quit` at the end of the input too, or the error reading the
+/// answer.
+fn next_step(
+    session: &mut Session,
+    options: &Options,
+    zone: Zone,
+    rng: &mut StdRng,
+    launcher: &mut dyn Launcher,
+    lines: &mut dyn LineReader,
+) -> io::Result<Step> {
     loop {
-        println!("\nWould you like to choose another option? (yes/no)");
-        let mut input = String::new();
-        io::stdout().flush().unwrap();
-        io::stdin().read_line(&mut input).unwrap();
+        if session.last_fetch.is_some() {
+            let open = if session.listed.is_empty() {
+                ""
+            } else {
+                ", o N to open event N"
+            };
+            statusln!(options, "\nWould you like to choose another option? (yes/no, d to see what changed, s to search, r for a random pick{}, sort KEY, group KEY, or filter WORDS to list them again, refresh to fetch them again)", open);
+        } else {
+            statusln!(
+                options,
+                "\nWould you like to choose another option? (yes/no)"
+            );
+        }
+        let Some(input) = lines.read_line()? else {
+            return Ok(Step::Quit);
+        };
 
+        if let Some(number) =
+            browser::parse_open_command(&input).filter(|_| !session.listed.is_empty())
+        {
+            let number = match number {
+                Some(number) => number.to_string(),
+                None => {
+                    statusln!(options, "Which event? (1-{})", session.listed.len());
+                    match lines.read_line()? {
+                        Some(answer) => answer.trim().to_string(),
+                        None => return Ok(Step::Quit),
+                    }
+                }
+            };
+            match number.parse() {
+                Ok(number) => {
+                    open_listed(&session.listed, number, options, launcher);
+                }
+                Err(_) => statusln!(
+                    options,
+                    "Invalid input. Please enter an event's number (1-{}).",
+                    session.listed.len()
+                ),
+            }
+            continue;
+        }
+        if let Some(command) =
+            session::parse_command(&input).filter(|_| session.last_fetch.is_some())
+        {
+            match command {
+                Ok(command) => {
+                    let refresh = command == ViewCommand::Refresh;
+                    session.apply(command);
+                    if refresh {
+                        return Ok(Step::Refresh);
+                    }
+                    show_session(session, options, zone, rng)?;
+                }
+                Err(e) => statusln!(options, "Invalid input: {}.", e),
+            }
+            continue;
+        }
         match input.trim().to_lowercase().as_str() {
-            "yes" | "y" => return true,
-            "no" | "n" => return false,
-            _ => println!("Invalid input. Please enter 'yes' or 'no'."),
+            "yes" | "y" => return Ok(Step::Menu),
+            "no" | "n" => return Ok(Step::Quit),
+            "d" | "diff" => match &session.last_fetch {
+                Some((changes, _)) => status!(options, "\n{}", output::render_diff(changes)),
+                None => statusln!(options, "Invalid input. Please enter 'yes' or 'no'."),
+            },
+            "s" | "search" if session.last_fetch.is_some() => {
+                statusln!(options, "Search for:");
+                let Some(input) = lines.read_line()? else {
+                    return Ok(Step::Quit);
+                };
+                let terms: Vec<String> = input.split_whitespace().map(str::to_string).collect();
+                let (events, view) = (session.shown(), session.options(options));
+                session.listed = to_output(&view, |out| {
+                    list_events(
+                        &events,
+                        &terms,
+                        Match::All,
+                        &view,
+                        zone,
+                        &HashSet::new(),
+                        out,
+                    )
+                })?;
+            }
+            "r" | "random" if session.last_fetch.is_some() => {
+                print_pick(&session.shown(), options, zone, rng)?
+            }
+            _ => statusln!(options, "Invalid input. Please enter 'yes' or 'no'."),
         }
     }
-}
\ No newline at end of file
+}
+
+/// Lists the session's last fetch the way its view shows it, or picks one of its events with
+/// `--random`.
+fn show_session(
+    session: &mut Session,
+    options: &Options,
+    zone: Zone,
+    rng: &mut StdRng,
+) -> io::Result<()> {
+    let events = session.shown();
+    let options = session.options(options);
+    if let Some((_, fetched)) = session
+        .last_fetch
+        .as_ref()
+        .filter(|_| !session.view.filter.is_empty())
+    {
+        statusln!(
+            options,
+            "{} of {} events mention {} (a bare filter lists them all)\n",
+            events.len(),
+            fetched.len(),
+            session.view.filter.join(" ")
+        );
+    }
+    if options.random {
+        return print_pick(&events, &options, zone, rng);
+    }
+    session.listed = show_events(&events, &options, zone)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn run_menu(answers: &str) -> io::Result<()> {
+        let options = Options {
+            no_prefs: true,
+            ..Default::default()
+        };
+        let store = SnapshotStore::new(std::env::temp_dir().join("event_finder_menu_snapshots"));
+        menu(
+            &options,
+            Input::Fetch(None, &store),
+            &[],
+            &Config::default(),
+            None,
+            Zone::default(),
+            &mut answers.as_bytes(),
+        )
+    }
+
+    #[test]
+    fn test_counted_uses_the_singular_for_one() {
+        assert_eq!(counted(1, "element", "elements"), "1 element");
+        assert_eq!(counted(0, "element", "elements"), "0 elements");
+        assert_eq!(
+            counted(3, "duplicate listing", "duplicate listings"),
+            "3 duplicate listings"
+        );
+    }
+
+    #[test]
+    fn test_menu_quits_at_the_end_of_input() {
+        // Each would ask again forever if the end of input read as an empty answer
+        assert!(run_menu("").is_ok());
+        assert!(run_menu("x\n").is_ok());
+        assert!(run_menu("9\nyes\n\n").is_ok());
+    }
+
+    #[test]
+    fn test_next_step_is_quit_at_the_end_of_input() {
+        let mut rng = StdRng::seed_from_u64(1);
+        let mut session = Session::default();
+        let mut ask = |answers: &str| {
+            next_step(
+                &mut session,
+                &Options::default(),
+                Zone::default(),
+                &mut rng,
+                &mut SystemBrowser,
+                &mut answers.as_bytes(),
+            )
+            .unwrap()
+        };
+
+        assert_eq!(ask(""), Step::Quit);
+        assert_eq!(ask("maybe\n"), Step::Quit);
+        assert_eq!(ask("maybe\nyes\n"), Step::Menu);
+        // Without a fetch there is nothing to refresh
+        assert_eq!(ask("refresh\nno\n"), Step::Quit);
+    }
+
+    #[test]
+    fn test_next_step_relists_the_fetch_until_refresh() {
+        let mut rng = StdRng::seed_from_u64(1);
+        let mut session = Session::default();
+        session.fetched(
+            &[Category::Music],
+            EventDiff::default(),
+            vec![ProcessedEvent {
+                name: "Jazz Night".to_string(),
+                ..Default::default()
+            }],
+        );
+        let options = Options {
+            quiet: true,
+            ..Default::default()
+        };
+
+        let step = next_step(
+            &mut session,
+            &options,
+            Zone::default(),
+            &mut rng,
+            &mut SystemBrowser,
+            &mut "sort name desc\nfilter blues\nsort price\nrefresh\n".as_bytes(),
+        )
+        .unwrap();
+
+        assert_eq!(step, Step::Refresh);
+        assert_eq!(session.view.filter, ["blues"]);
+        assert!(session.shown().is_empty());
+        assert!(session.listed.is_empty());
+    }
+}
@@ -0,0 +1,266 @@
+//! # Notify Module
+//!
+//! This module tells the user about new events matching their interests, for `--notify`.
+//! Which events qualify and what the notification says are worked out by `qualifying` and
+//! `compose`; showing it goes through the `Notifier` trait, so tests can check what would have
+//! been shown without a desktop.
+
+use std::collections::HashMap;
+use std::io;
+use std::process::{Command, Stdio};
+
+use chrono::{NaiveDateTime, Timelike};
+
+use crate::data_processing::ProcessedEvent;
+use crate::seen::SeenEvent;
+
+/// The most event names a notification lists.
+pub const MAX_NAMES: usize = 5;
+
+/// The hour from which an event today counts as tonight.
+const EVENING_HOUR: u32 = 17;
+
+/// A notification's title and text.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Notification {
+    pub title: String,
+    pub body: String,
+}
+
+/// Something that can show a notification, such as the desktop's notification center.
+pub trait Notifier {
+    /// Shows `notification`, returning once it has been handed off.
+    fn notify(&mut self, notification: &Notification) -> io::Result<()>;
+}
+
+/// Shows notifications on the desktop through `notify-send` on Linux and `osascript` on macOS.
+/// Elsewhere showing one fails, and the caller falls back to printing it.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct DesktopNotifier;
+
+impl Notifier for DesktopNotifier {
+    fn notify(&mut self, notification: &Notification) -> io::Result<()> {
+        let mut command = if cfg!(target_os = "macos") {
+            let script = format!(
+                "display notification {} with title {}",
+                apple_script_string(&notification.body),
+                apple_script_string(&notification.title)
+            );
+            let mut command = Command::new("osascript");
+            command.args(["-e", &script]);
+            command
+        } else if cfg!(unix) {
+            let mut command = Command::new("notify-send");
+            command.args([
+                "--app-name=event_finder",
+                &notification.title,
+                &notification.body,
+            ]);
+            command
+        } else {
+            return Err(io::Error::new(
+                io::ErrorKind::Unsupported,
+                "desktop notifications aren't supported on this platform",
+            ));
+        };
+        let status = command
+            .stdin(Stdio::null())
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .status()?;
+        if status.success() {
+            Ok(())
+        } else {
+            Err(io::Error::other(format!(
+                "the notifier exited with {}",
+                status
+            )))
+        }
+    }
+}
+
+/// Quotes text as an AppleScript string.
+fn apple_script_string(text: &str) -> String {
+    format!("\"{}\"", text.replace('\\', "\\\\").replace('"', "\\\""))
+}
+
+/// Picks the events worth a notification: those not listed before that match an interest.
+///
+/// # Arguments
+///
+/// * `events` - The events listed, ranked by `rank_events`.
+/// * `seen` - The events listed before this run, from `SeenStore::load`.
+///
+/// # Returns
+///
+/// The new events with a positive score, in order.
+pub fn qualifying(
+    events: &[ProcessedEvent],
+    seen: &HashMap<String, SeenEvent>,
+) -> Vec<ProcessedEvent> {
+    events
+        .iter()
+        .filter(|event| event.score > 0 && !seen.contains_key(&event.id()))
+        .cloned()
+        .collect()
+}
+
+/// Writes the notification for the qualifying events, e.g. `3 new jazz events tonight`.
+///
+/// The title names the interest when every event matched the same one, and says `today` or
+/// `tonight` when they all start today. The text lists up to `max_names` names.
+///
+/// # Arguments
+///
+/// * `events` - The events from `qualifying`.
+/// * `now` - The current time, for `today` and `tonight`.
+/// * `max_names` - The most names to list.
+///
+/// # Returns
+///
+/// The notification, or `None` if there are no events to tell about.
+pub fn compose(
+    events: &[ProcessedEvent],
+    now: NaiveDateTime,
+    max_names: usize,
+) -> Option<Notification> {
+    let first = events.first()?;
+    let shared = first.matched_interests.iter().find(|term| {
+        events
+            .iter()
+            .all(|event| event.matched_interests.contains(term))
+    });
+    let what = match shared {
+        Some(term) => format!("{} ", term),
+        None => "matching ".to_string(),
+    };
+    let starts: Vec<Option<NaiveDateTime>> = events.iter().map(|event| event.start).collect();
+    let when = if starts
+        .iter()
+        .all(|start| start.is_some_and(|s| s.date() == now.date() && s.hour() >= EVENING_HOUR))
+    {
+        " tonight"
+    } else if starts
+        .iter()
+        .all(|start| start.is_some_and(|s| s.date() == now.date()))
+    {
+        " today"
+    } else {
+        ""
+    };
+    let noun = if events.len() == 1 { "event" } else { "events" };
+    let mut lines: Vec<String> = events
+        .iter()
+        .take(max_names)
+        .map(|event| event.name.clone())
+        .collect();
+    if events.len() > max_names {
+        lines.push(format!("and {} more", events.len() - max_names));
+    }
+    Some(Notification {
+        title: format!("{} new {}{}{}", events.len(), what, noun, when),
+        body: lines.join("\n"),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::NaiveDate;
+
+    fn event(name: &str, hour: u32, interests: &[&str]) -> ProcessedEvent {
+        ProcessedEvent {
+            name: name.to_string(),
+            url: format!(
+                "https://example.com/{}",
+                name.to_lowercase().replace(' ', "-")
+            ),
+            start: NaiveDate::from_ymd_opt(2024, 5, 3)
+                .unwrap()
+                .and_hms_opt(hour, 0, 0),
+            score: interests.len() as i64,
+            matched_interests: interests.iter().map(|term| term.to_string()).collect(),
+            ..Default::default()
+        }
+    }
+
+    fn now() -> NaiveDateTime {
+        NaiveDate::from_ymd_opt(2024, 5, 3)
+            .unwrap()
+            .and_hms_opt(12, 0, 0)
+            .unwrap()
+    }
+
+    #[test]
+    fn test_qualifying_events_are_new_and_interesting() {
+        let known = event("Jazz Jam", 20, &["jazz"]);
+        let seen = HashMap::from([(
+            known.id(),
+            SeenEvent {
+                name: known.name.clone(),
+                start_date: String::new(),
+                source: None,
+                first_seen: now(),
+                last_seen: now(),
+            },
+        )]);
+        let events = vec![
+            known,
+            event("Jazz Brunch", 11, &["jazz"]),
+            event("Trivia", 19, &[]),
+        ];
+
+        assert_eq!(qualifying(&events, &seen), vec![events[1].clone()]);
+    }
+
+    #[test]
+    fn test_compose_names_the_shared_interest_and_evening() {
+        let events = vec![
+            event("Jazz Jam", 20, &["jazz"]),
+            event("Late Set", 22, &["jazz", "blues"]),
+            event("Big Band", 19, &["jazz"]),
+        ];
+
+        let notification = compose(&events, now(), MAX_NAMES).unwrap();
+
+        assert_eq!(
+            notification,
+            Notification {
+                title: "3 new jazz events tonight".to_string(),
+                body: "Jazz Jam\nLate Set\nBig Band".to_string()
+            }
+        );
+    }
+
+    #[test]
+    fn test_compose_mixed_events() {
+        let mut events = vec![
+            event("Jazz Brunch", 11, &["jazz"]),
+            event("Bluegrass", 20, &["bluegrass"]),
+        ];
+        assert_eq!(
+            compose(&events, now(), MAX_NAMES).unwrap().title,
+            "2 new matching events today"
+        );
+
+        events[1].start = None;
+        events.push(event("Blues", 21, &["blues"]));
+        let notification = compose(&events, now(), 2).unwrap();
+        assert_eq!(notification.title, "3 new matching events");
+        assert_eq!(notification.body, "Jazz Brunch\nBluegrass\nand 1 more");
+
+        assert_eq!(
+            compose(&events[..1], now(), 2).unwrap().title,
+            "1 new jazz event today"
+        );
+        assert_eq!(compose(&[], now(), 2), None);
+    }
+
+    #[test]
+    fn test_apple_script_string_escapes_quotes() {
+        assert_eq!(
+            apple_script_string(r#"Say "hi" \ bye"#),
+            r#""Say \"hi\" \\ bye""#
+        );
+    }
+}
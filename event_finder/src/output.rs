@@ -0,0 +1,1754 @@
+//! # Output Module
+//!
+//! This module renders processed data into the text shown on the console.
+//! Rendering functions return `String`s rather than printing so their output can be tested;
+//! `save_output` writes one to the `--output` file.
+
+use std::env;
+use std::fmt::{self, Write};
+use std::fs;
+use std::io;
+use std::path::Path;
+use std::str::FromStr;
+
+use chrono::NaiveDateTime;
+use colored::{ColoredString, Colorize};
+use serde_json::Value;
+
+use crate::data_processing::{
+    self, relative_time, truncate_at_word, DataIssue, DataWarning, DateParse, ExcludeField,
+    ExcludeRule, Match, ProcessedEvent,
+};
+use crate::diff::EventDiff;
+use crate::geo;
+use crate::html_parser::{EventStatus, FieldWarning, SelectorReport, WarningKind};
+
+/// The default number of description characters shown per event on the console.
+pub const DEFAULT_DESCRIPTION_LENGTH: usize = 160;
+
+/// Marks the events `--watch` found since its previous refresh.
+pub const NEW_MARKER: &str = "[NEW]";
+
+/// How the final listing is written, chosen with `--format`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Format {
+    /// The labelled blocks shown on the console, with status lines and a summary.
+    #[default]
+    Plain,
+    /// An array of the events' serialized form (see `ProcessedEvent`'s `Serialize` impl).
+    Json,
+    /// A header row and one row per event, in `CSV_COLUMNS` order.
+    Csv,
+    /// An iCalendar document with one `VEVENT` per event, from `ics::render_ics`.
+    Ics,
+    /// A Markdown list of linked events under a heading per group.
+    Markdown,
+    /// A self-contained HTML page of linked events in a section per group.
+    Html,
+    /// An aligned table of one row per event, from `table::TableLayout`, shown like `Plain`.
+    Table,
+}
+
+impl Format {
+    /// Whether the listing is written whole, as data or a document, so status lines belong on
+    /// stderr.
+    pub fn is_structured(self) -> bool {
+        !matches!(self, Format::Plain | Format::Table)
+    }
+}
+
+impl FromStr for Format {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "plain" | "text" => Ok(Format::Plain),
+            "json" => Ok(Format::Json),
+            "csv" => Ok(Format::Csv),
+            "ics" | "ical" => Ok(Format::Ics),
+            "markdown" | "md" => Ok(Format::Markdown),
+            "html" => Ok(Format::Html),
+            "table" => Ok(Format::Table),
+            _ => Err(format!(
+                "unknown format '{}' (expected plain, table, json, csv, ics, markdown, or html)",
+                s
+            )),
+        }
+    }
+}
+
+impl fmt::Display for Format {
+    /// Writes the name `--format` takes, which prefs.json saves too.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Format::Plain => "plain",
+            Format::Json => "json",
+            Format::Csv => "csv",
+            Format::Ics => "ics",
+            Format::Markdown => "markdown",
+            Format::Html => "html",
+            Format::Table => "table",
+        })
+    }
+}
+
+/// One event as the block of labelled lines shown on the console, for `Display`.
+///
+/// Optional fields are only printed when present, and the description is truncated to
+/// `description_length` characters; structured exports keep it intact. Sold-out, cancelled,
+/// and postponed events are marked after their name, e.g. `Name: Concert [SOLD OUT]`, after
+/// the source they came from, if known: `Name: Concert [songkick] [SOLD OUT]`. When the source
+/// gave the end too, it is followed by how long the event lasts, e.g.
+/// `End Date: May 2 9:30 PM (2h 30m)`. Every line ends in a newline.
+#[derive(Debug, Clone, Copy)]
+pub struct EventBlock<'a> {
+    pub event: &'a ProcessedEvent,
+    /// The maximum number of description characters to show.
+    pub description_length: usize,
+    /// The current time; when given, a parsed start is followed by how far off it is, e.g.
+    /// `Start Date: May 2 7:00 PM (starts in 2h 15m)`.
+    pub now: Option<NaiveDateTime>,
+    /// How the name, dates, venue, and status marker are styled.
+    pub styler: Styler,
+    /// The event's number in the listing, shown before its name as in `3. Name: ...`, which
+    /// `o 3` and `--open 3` open.
+    pub number: Option<usize>,
+    /// Whether the event is new since `--watch` last refreshed, marked `[NEW]` after its name.
+    pub fresh: bool,
+}
+
+impl fmt::Display for EventBlock<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let (event, styler) = (self.event, self.styler);
+        let mut marker = status_label(event.status)
+            .map(|label| format!(" {}", styler.warning(&format!("[{}]", label))))
+            .unwrap_or_default();
+        if self.fresh {
+            marker += &format!(" {}", styler.fresh(NEW_MARKER));
+        }
+        let relative = match (event.start, self.now) {
+            (Some(start), Some(now)) => format!(" ({})", relative_time(start, now)),
+            _ => String::new(),
+        };
+        let source = event
+            .source
+            .as_ref()
+            .map(|source| format!(" [{}]", source))
+            .unwrap_or_default();
+        let length = data_processing::duration(event)
+            .map(|d| format!(" ({})", data_processing::format_duration(d)))
+            .unwrap_or_default();
+        let distance = event
+            .distance
+            .map(|miles| format!(" ({} away)", geo::format_distance(miles)))
+            .unwrap_or_default();
+        if let Some(number) = self.number {
+            write!(f, "{}. ", number)?;
+        }
+        writeln!(
+            f,
+            "Name: {}{}{}\nStart Date: {}{}\nEnd Date: {}{}\nLocation: {}{}\nURL: {}",
+            styler.name(&event.name),
+            source,
+            marker,
+            styler.date(&event.start_date),
+            relative,
+            styler.date(event.end_date.as_deref().unwrap_or("N/A")),
+            length,
+            styler.venue(event.location.as_deref().unwrap_or("N/A")),
+            distance,
+            event.url
+        )?;
+        if !event.alt_urls.is_empty() {
+            writeln!(f, "Also listed at: {}", event.alt_urls.join(", "))?;
+        }
+        if !event.matched_interests.is_empty() {
+            writeln!(
+                f,
+                "Matched: {} (score {})",
+                event.matched_interests.join(", "),
+                event.score
+            )?;
+        }
+        if let Some(price) = &event.price {
+            writeln!(f, "Price: {}", price)?;
+        }
+        if !event.tags.is_empty() {
+            writeln!(f, "Tags: {}", event.tags.join(", "))?;
+        }
+        if let Some(description) = &event.description {
+            writeln!(
+                f,
+                "Description: {}",
+                truncate_at_word(description, self.description_length)
+            )?;
+        }
+        if let Some(image_url) = &event.image_url {
+            writeln!(f, "Image: {}", image_url)?;
+        }
+        Ok(())
+    }
+}
+
+/// Displays the event's `EventBlock` without relative times. The precision, as in `{:.80}`,
+/// sets how many description characters are shown (`DEFAULT_DESCRIPTION_LENGTH` without one).
+impl fmt::Display for ProcessedEvent {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let description_length = f.precision().unwrap_or(DEFAULT_DESCRIPTION_LENGTH);
+        write!(
+            f,
+            "{}",
+            EventBlock {
+                event: self,
+                description_length,
+                now: None,
+                styler: Styler::plain(),
+                number: None,
+                fresh: false
+            }
+        )
+    }
+}
+
+/// Writes a rendered listing to a file, all at once.
+///
+/// The listing goes to a temporary file beside `path` that is renamed over it when complete,
+/// so a crash never leaves a truncated file behind. Missing parent directories are created.
+///
+/// # Arguments
+///
+/// * `path` - The file to write.
+/// * `contents` - The rendered listing.
+/// * `force` - Whether to replace `path` if it already exists.
+///
+/// # Returns
+///
+/// `Ok(())` once written, or an error whose message names the file and what went wrong.
+pub fn save_output(path: &Path, contents: &[u8], force: bool) -> io::Result<()> {
+    let failed = |e: io::Error| {
+        let reason = match e.kind() {
+            io::ErrorKind::PermissionDenied => "permission denied".to_string(),
+            _ => e.to_string(),
+        };
+        io::Error::new(
+            e.kind(),
+            format!("can't write {}: {}", path.display(), reason),
+        )
+    };
+    if !force && path.exists() {
+        let message = format!("{} already exists (--force replaces it)", path.display());
+        return Err(io::Error::new(io::ErrorKind::AlreadyExists, message));
+    }
+    let Some(file_name) = path.file_name() else {
+        return Err(failed(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "not a file name",
+        )));
+    };
+    if let Some(parent) = path
+        .parent()
+        .filter(|parent| !parent.as_os_str().is_empty())
+    {
+        fs::create_dir_all(parent).map_err(failed)?;
+    }
+    let temp = path.with_file_name(format!(
+        ".{}.{}.tmp",
+        file_name.to_string_lossy(),
+        std::process::id()
+    ));
+    let written = fs::write(&temp, contents)
+        .and_then(|_| fs::File::open(&temp)?.sync_all())
+        .and_then(|_| fs::rename(&temp, path));
+    if written.is_err() {
+        let _ = fs::remove_file(&temp);
+    }
+    written.map_err(failed)
+}
+
+/// Returns how a sold-out, cancelled, or postponed event is marked, e.g. `SOLD OUT`.
+pub(crate) fn status_label(status: EventStatus) -> Option<&'static str> {
+    match status {
+        EventStatus::SoldOut => Some("SOLD OUT"),
+        EventStatus::Cancelled => Some("CANCELLED"),
+        EventStatus::Postponed => Some("POSTPONED"),
+        EventStatus::Available | EventStatus::Unknown => None,
+    }
+}
+
+/// Colors and weights text for the terminal, or leaves it plain.
+///
+/// Rendering functions take a `Styler` so the same code produces both the colored console
+/// listing and the plain text written to files, pipes, and tests. `Styler::default()` is plain.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Styler {
+    color: bool,
+}
+
+impl Styler {
+    /// Creates a styler that colors text if `color` is set, whatever the terminal.
+    pub fn new(color: bool) -> Styler {
+        if color {
+            // `colored` checks the terminal itself; the caller has decided already
+            colored::control::set_override(true);
+        }
+        Styler { color }
+    }
+
+    /// Creates a styler that leaves text as it is.
+    pub fn plain() -> Styler {
+        Styler { color: false }
+    }
+
+    /// Creates a styler for a stream, coloring only if it is a terminal, `no_color` (from
+    /// `--no-color`) isn't set, and neither is a non-empty `$NO_COLOR`.
+    pub fn detect(is_terminal: bool, no_color: bool) -> Styler {
+        let no_color_env = env::var_os("NO_COLOR").is_some_and(|value| !value.is_empty());
+        Styler::new(is_terminal && !no_color && !no_color_env)
+    }
+
+    /// Whether text is colored.
+    pub fn is_colored(self) -> bool {
+        self.color
+    }
+
+    fn paint(self, text: &str, style: fn(&str) -> ColoredString) -> String {
+        if self.color {
+            style(text).to_string()
+        } else {
+            text.to_string()
+        }
+    }
+
+    /// Styles an event's name: bold.
+    pub fn name(self, text: &str) -> String {
+        self.paint(text, |t| t.bold())
+    }
+
+    /// Styles a date or time: cyan.
+    pub fn date(self, text: &str) -> String {
+        self.paint(text, |t| t.cyan())
+    }
+
+    /// Styles a venue: green.
+    pub fn venue(self, text: &str) -> String {
+        self.paint(text, |t| t.green())
+    }
+
+    /// Styles a warning, or a sold-out or cancelled marker: yellow.
+    pub fn warning(self, text: &str) -> String {
+        self.paint(text, |t| t.yellow())
+    }
+
+    /// Styles an error: bold red.
+    pub fn error(self, text: &str) -> String {
+        self.paint(text, |t| t.red().bold())
+    }
+
+    /// Styles a group header: bold magenta.
+    pub fn header(self, text: &str) -> String {
+        self.paint(text, |t| t.magenta().bold())
+    }
+
+    /// Styles the summary footer: dimmed.
+    pub fn summary(self, text: &str) -> String {
+        self.paint(text, |t| t.dimmed())
+    }
+
+    /// Styles the marker of an event new since the last `--watch` refresh: bold bright green.
+    pub fn fresh(self, text: &str) -> String {
+        self.paint(text, |t| t.bright_green().bold())
+    }
+}
+
+/// Renders one event as its `EventBlock`.
+///
+/// # Arguments
+///
+/// * `event` - The event to render.
+/// * `description_length` - The maximum number of description characters to show.
+/// * `now` - The current time, for how far off the start is.
+/// * `styler` - How the fields are styled.
+///
+/// # Returns
+///
+/// A `String` with one line per field, each ending in a newline.
+pub fn render_event(
+    event: &ProcessedEvent,
+    description_length: usize,
+    now: Option<NaiveDateTime>,
+    styler: Styler,
+) -> String {
+    EventBlock {
+        event,
+        description_length,
+        now,
+        styler,
+        number: None,
+        fresh: false,
+    }
+    .to_string()
+}
+
+/// Renders a randomly picked event between banner lines, with its link repeated at the end so
+/// it stands out.
+///
+/// # Arguments
+///
+/// * `event` - The picked event.
+/// * `candidates` - How many events it was picked from.
+/// * `description_length` - The maximum number of description characters to show.
+/// * `now` - The current time, passed on to `render_event`.
+/// * `styler` - How the banner and the event are styled.
+///
+/// # Returns
+///
+/// A `String` with the banner, the event, and its link.
+pub fn render_pick(
+    event: &ProcessedEvent,
+    candidates: usize,
+    description_length: usize,
+    now: Option<NaiveDateTime>,
+    styler: Styler,
+) -> String {
+    let banner = format!(
+        "★★★ Your pick, out of {} event{} ★★★",
+        candidates,
+        if candidates == 1 { "" } else { "s" }
+    );
+    let mut out = format!("{}\n", styler.header(&banner));
+    out.push_str(&render_event(event, description_length, now, styler));
+    let _ = writeln!(
+        out,
+        "★★★ {} ★★★",
+        if event.url.is_empty() {
+            "no link given"
+        } else {
+            &event.url
+        }
+    );
+    out
+}
+
+/// Renders the header line a group of events is listed under, e.g. `— Friday, May 3 —`.
+pub fn render_group_header(title: &str, styler: Styler) -> String {
+    format!("{}\n", styler.header(&format!("— {} —", title)))
+}
+
+/// Renders one event of a group, indented beneath the group's header.
+///
+/// # Arguments
+///
+/// * `block` - The event and how it is shown.
+///
+/// # Returns
+///
+/// A `String` with the event's lines indented, followed by a blank line.
+pub fn render_group_member(block: EventBlock) -> String {
+    let mut out = String::new();
+    for line in block.to_string().lines() {
+        let _ = writeln!(out, "  {}", line);
+    }
+    out.push('\n');
+    out
+}
+
+/// Renders how many events a search matched, e.g. `12 of 87 events match 'jazz'`.
+///
+/// # Arguments
+///
+/// * `matched` - The number of events that matched.
+/// * `total` - The number of events searched.
+/// * `terms` - The search terms.
+/// * `mode` - Whether all or any of the terms had to match.
+///
+/// # Returns
+///
+/// A `String` with the rendered line, ending in a newline.
+pub fn render_search_summary(
+    matched: usize,
+    total: usize,
+    terms: &[String],
+    mode: Match,
+) -> String {
+    let quoted: Vec<String> = terms.iter().map(|t| format!("'{}'", t)).collect();
+    let joiner = match mode {
+        Match::All => " and ",
+        Match::Any => " or ",
+    };
+    let verb = if total == 1 { "matches" } else { "match" };
+    format!(
+        "{} of {} event{} {} {}\n",
+        matched,
+        total,
+        if total == 1 { "" } else { "s" },
+        verb,
+        quoted.join(joiner)
+    )
+}
+
+/// Renders events as a JSON array for `--format json`.
+///
+/// # Arguments
+///
+/// * `events` - The events, in the order they are listed.
+/// * `compact` - Whether to write everything on one line rather than indented.
+///
+/// # Returns
+///
+/// A `String` with the array, without a trailing newline.
+pub fn render_json(events: &[ProcessedEvent], compact: bool) -> String {
+    let json = Value::Array(events.iter().map(ProcessedEvent::to_json).collect());
+    if compact {
+        json.to_string()
+    } else {
+        format!("{:#}", json)
+    }
+}
+
+/// Renders grouped events as a Markdown list for `--format markdown`.
+///
+/// Each group is a `##` heading over a bullet per event: its name linked to its URL, then its
+/// start, venue, and price, and its description on an indented line. Text is escaped so
+/// names like `*NSYNC Tribute` show as written.
+///
+/// # Arguments
+///
+/// * `groups` - The group titles with their events, e.g. from `data_processing::group_events`.
+/// * `description_length` - The maximum number of description characters to show.
+///
+/// # Returns
+///
+/// A `String` with the document, ending in a newline.
+pub fn render_markdown(
+    groups: &[(String, Vec<ProcessedEvent>)],
+    description_length: usize,
+) -> String {
+    let mut out = String::from("# Events\n");
+    for (title, events) in groups {
+        let _ = write!(out, "\n## {}\n\n", markdown_escape(title));
+        for event in events {
+            let name = match link_target(&event.url) {
+                Some(url) => format!(
+                    "[{}]({})",
+                    markdown_escape(&event.name),
+                    url.replace(' ', "%20")
+                        .replace('(', "%28")
+                        .replace(')', "%29")
+                ),
+                None => markdown_escape(&event.name),
+            };
+            let details: Vec<String> = report_details(event)
+                .iter()
+                .map(|d| markdown_escape(d))
+                .collect();
+            let _ = write!(out, "- {}", name);
+            if !details.is_empty() {
+                let _ = write!(out, " — {}", details.join(" · "));
+            }
+            if let Some(label) = status_label(event.status) {
+                let _ = write!(out, " **{}**", label);
+            }
+            out.push('\n');
+            if let Some(description) = &event.description {
+                let _ = writeln!(
+                    out,
+                    "  {}",
+                    markdown_escape(&truncate_at_word(description, description_length))
+                );
+            }
+        }
+    }
+    out
+}
+
+/// Renders grouped events as a self-contained HTML page for `--format html`.
+///
+/// Each group is a `<section>` with an `<h2>` over a list of events laid out like
+/// `render_markdown`'s; the page has its own small stylesheet and loads nothing else. Text and
+/// links are escaped, and only `http` and `https` URLs are linked.
+///
+/// # Arguments
+///
+/// * `groups` - The group titles with their events, e.g. from `data_processing::group_events`.
+/// * `description_length` - The maximum number of description characters to show.
+///
+/// # Returns
+///
+/// A `String` with the page, ending in a newline.
+pub fn render_html(groups: &[(String, Vec<ProcessedEvent>)], description_length: usize) -> String {
+    let mut out = String::from(HTML_HEAD);
+    for (title, events) in groups {
+        let _ = write!(out, "<section>\n<h2>{}</h2>\n<ul>\n", html_escape(title));
+        for event in events {
+            let name = match link_target(&event.url) {
+                Some(url) => format!(
+                    "<a href=\"{}\">{}</a>",
+                    html_escape(url),
+                    html_escape(&event.name)
+                ),
+                None => html_escape(&event.name),
+            };
+            let _ = write!(out, "<li>{}", name);
+            for detail in report_details(event) {
+                let _ = write!(
+                    out,
+                    " <span class=\"detail\">{}</span>",
+                    html_escape(&detail)
+                );
+            }
+            if let Some(label) = status_label(event.status) {
+                let _ = write!(out, " <strong class=\"status\">{}</strong>", label);
+            }
+            if let Some(description) = &event.description {
+                let _ = write!(
+                    out,
+                    "\n<p>{}</p>",
+                    html_escape(&truncate_at_word(description, description_length))
+                );
+            }
+            out.push_str("</li>\n");
+        }
+        out.push_str("</ul>\n</section>\n");
+    }
+    out.push_str("</body>\n</html>\n");
+    out
+}
+
+/// The start of `render_html`'s page, up to the first section.
+const HTML_HEAD: &str = r#"<!DOCTYPE html>
+<html lang="en">
+<head>
+<meta charset="utf-8">
+<meta name="viewport" content="width=device-width, initial-scale=1">
+<title>Events</title>
+<style>
+body { font-family: system-ui, sans-serif; max-width: 40rem; margin: 2rem auto; padding: 0 1rem; line-height: 1.4; }
+h2 { border-bottom: 1px solid #ccc; margin-top: 2rem; }
+ul { list-style: none; padding: 0; }
+li { margin: 0.75rem 0; }
+.detail { color: #555; }
+.detail::before { content: "· "; }
+.status { color: #b00; font-size: 0.8em; }
+p { margin: 0.25rem 0 0; color: #333; }
+</style>
+</head>
+<body>
+<h1>Events</h1>
+"#;
+
+/// The start, venue, and price of an event, as far as they are known, for the reports.
+fn report_details(event: &ProcessedEvent) -> Vec<String> {
+    [
+        Some(event.start_date.as_str()),
+        event.location.as_deref(),
+        event.price.as_deref(),
+    ]
+    .into_iter()
+    .flatten()
+    .filter(|detail| !detail.trim().is_empty())
+    .map(str::to_string)
+    .collect()
+}
+
+/// The URL to link an event's name to, if it is a web address.
+fn link_target(url: &str) -> Option<&str> {
+    Some(url).filter(|url| url.starts_with("https://") || url.starts_with("http://"))
+}
+
+/// Escapes text so Markdown shows it as written.
+fn markdown_escape(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    for c in text.chars() {
+        if matches!(
+            c,
+            '\\' | '`' | '*' | '_' | '[' | ']' | '<' | '>' | '#' | '|' | '~'
+        ) {
+            out.push('\\');
+        }
+        // A line break would end the list item
+        out.push(if c == '\n' { ' ' } else { c });
+    }
+    out
+}
+
+/// Escapes text for HTML element content and attribute values.
+fn html_escape(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => out.push_str("&amp;"),
+            '<' => out.push_str("&lt;"),
+            '>' => out.push_str("&gt;"),
+            '"' => out.push_str("&quot;"),
+            '\'' => out.push_str("&#39;"),
+            c => out.push(c),
+        }
+    }
+    out
+}
+
+/// The columns of `--format csv`, in order; each holds the serialized field of the same name.
+pub const CSV_COLUMNS: [&str; 15] = [
+    "id",
+    "name",
+    "start_date",
+    "end_date",
+    "start",
+    "end",
+    "location",
+    "url",
+    "source",
+    "price",
+    "status",
+    "categories",
+    "tags",
+    "description",
+    "image_url",
+];
+
+/// Renders events as CSV for `--format csv`.
+///
+/// Missing fields are empty cells and lists are joined with `"; "`. Cells containing a comma,
+/// quote, or line break are quoted, with quotes doubled, as RFC 4180 and spreadsheets expect.
+///
+/// # Arguments
+///
+/// * `events` - The events, one row each.
+/// * `crlf` - Whether rows end in `\r\n`, as Excel prefers, rather than `\n`.
+///
+/// # Returns
+///
+/// A `String` with the header row and the rows, each ending in the row terminator.
+pub fn render_csv(events: &[ProcessedEvent], crlf: bool) -> String {
+    let terminator = if crlf { "\r\n" } else { "\n" };
+    let mut out = CSV_COLUMNS.join(",") + terminator;
+    for event in events {
+        let json = event.to_json();
+        let cells: Vec<String> = CSV_COLUMNS
+            .iter()
+            .map(|column| match &json[*column] {
+                Value::String(text) => csv_cell(text),
+                Value::Array(items) => csv_cell(
+                    &items
+                        .iter()
+                        .filter_map(Value::as_str)
+                        .collect::<Vec<_>>()
+                        .join("; "),
+                ),
+                _ => String::new(),
+            })
+            .collect();
+        out += &cells.join(",");
+        out += terminator;
+    }
+    out
+}
+
+/// Quotes a CSV cell if it contains a delimiter, quote, or line break.
+fn csv_cell(text: &str) -> String {
+    if text.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", text.replace('"', "\"\""))
+    } else {
+        text.to_string()
+    }
+}
+
+/// Renders how many events the exclusion rules removed.
+///
+/// The summary is one line; verbose mode lists every rule, including those that removed
+/// nothing, so stale blocklist entries can be spotted.
+///
+/// # Arguments
+///
+/// * `rules` - The rules applied.
+/// * `removed` - How many events each rule removed, as returned by `apply_exclusions`.
+/// * `verbose` - Whether to list each rule.
+///
+/// # Returns
+///
+/// A `String` with the rendered lines; empty when nothing was removed and not verbose.
+pub fn render_exclusions(rules: &[ExcludeRule], removed: &[usize], verbose: bool) -> String {
+    let total: usize = removed.iter().sum();
+    let mut out = String::new();
+    if verbose {
+        for (rule, count) in rules.iter().zip(removed) {
+            let target = match rule.field {
+                ExcludeField::Text => "mentioning",
+                ExcludeField::Venue => "at",
+            };
+            let _ = writeln!(
+                out,
+                "excluded {} event{} {} '{}'",
+                count,
+                if *count == 1 { "" } else { "s" },
+                target,
+                rule.pattern
+            );
+        }
+    } else if total > 0 {
+        let _ = writeln!(
+            out,
+            "excluded {} event{} (-v to see which rules matched)",
+            total,
+            if total == 1 { "" } else { "s" }
+        );
+    }
+    out
+}
+
+/// Renders an `EventDiff` as three sections: new, removed, and changed events.
+///
+/// Changed events list each differing field with the old and new value side by side.
+///
+/// # Arguments
+///
+/// * `diff` - The diff to render.
+///
+/// # Returns
+///
+/// A `String` with the rendered report.
+pub fn render_diff(diff: &EventDiff) -> String {
+    let mut out = String::new();
+    if diff.is_empty() {
+        out.push_str("No changes since the last fetch.\n");
+        return out;
+    }
+
+    let _ = writeln!(out, "New events ({}):", diff.added.len());
+    for event in &diff.added {
+        let _ = writeln!(
+            out,
+            "  + {} ({}, {})",
+            event.name,
+            event.start_date,
+            event.location.as_deref().unwrap_or("N/A")
+        );
+    }
+
+    let _ = writeln!(out, "\nRemoved events ({}):", diff.removed.len());
+    for event in &diff.removed {
+        let _ = writeln!(
+            out,
+            "  - {} ({}, {})",
+            event.name,
+            event.start_date,
+            event.location.as_deref().unwrap_or("N/A")
+        );
+    }
+
+    let _ = writeln!(out, "\nChanged events ({}):", diff.changed.len());
+    for change in &diff.changed {
+        let _ = writeln!(out, "  ~ {}", change.new.name);
+        let width = change
+            .fields
+            .iter()
+            .map(|f| f.old.chars().count())
+            .max()
+            .unwrap_or(0);
+        for field in &change.fields {
+            let _ = writeln!(
+                out,
+                "      {:<10} {:<width$}  ->  {}",
+                field.field,
+                field.old,
+                field.new,
+                width = width
+            );
+        }
+    }
+    out
+}
+
+/// Renders a source's field warnings.
+///
+/// Normally this is a single line counting the events missing each field, e.g.
+/// `songkick: 3 events missing location`, with fields whose pattern didn't match counted
+/// separately. With `verbose`, every warning is listed with its event index and the selector
+/// or pattern that failed.
+///
+/// # Arguments
+///
+/// * `source` - The name of the source the warnings came from.
+/// * `warnings` - The warnings to render.
+/// * `verbose` - Whether to list every warning.
+///
+/// # Returns
+///
+/// A `String` with the rendered warnings, or an empty string if there are none.
+pub fn render_warnings(source: &str, warnings: &[FieldWarning], verbose: bool) -> String {
+    let mut out = String::new();
+    if warnings.is_empty() {
+        return out;
+    }
+
+    let mut counts: Vec<(&str, WarningKind, usize)> = Vec::new();
+    for warning in warnings {
+        match counts
+            .iter_mut()
+            .find(|(field, kind, _)| *field == warning.field && *kind == warning.kind)
+        {
+            Some((_, _, count)) => *count += 1,
+            None => counts.push((warning.field, warning.kind, 1)),
+        }
+    }
+    let summary: Vec<String> = counts
+        .iter()
+        .map(|(field, kind, count)| {
+            let events = format!("{} event{}", count, if *count == 1 { "" } else { "s" });
+            match kind {
+                WarningKind::NoMatch => format!("{} missing {}", events, field),
+                WarningKind::PatternMismatch => {
+                    format!("{} with unmatched {} pattern", events, field)
+                }
+                WarningKind::UnresolvableUrl => format!("{} with unusable {} link", events, field),
+                WarningKind::MetaFallback => format!("{} read from page meta tags", events),
+            }
+        })
+        .collect();
+    let _ = writeln!(out, "{}: {}", source, summary.join(", "));
+
+    if verbose {
+        for warning in warnings {
+            let _ = match warning.kind {
+                WarningKind::NoMatch => writeln!(out, "  event {}: no {} (selector `{}` matched nothing)",
+                    warning.event_index, warning.field, warning.selector),
+                WarningKind::PatternMismatch => writeln!(out, "  event {}: raw {} kept (pattern `{}` didn't match)",
+                    warning.event_index, warning.field, warning.selector),
+                WarningKind::UnresolvableUrl => writeln!(out, "  event {}: no {} (href `{}` isn't a web link)",
+                    warning.event_index, warning.field, warning.selector),
+                WarningKind::MetaFallback => writeln!(out, "  event {}: read from the page's meta tags (`{}` found no events; the selectors need fixing)",
+                    warning.event_index, warning.selector),
+            };
+        }
+    }
+    out
+}
+
+/// Renders the data-quality warnings `process_data` found in a source's events.
+///
+/// Like `render_warnings`, this is a single line counting each kind of problem, e.g.
+/// `songkick: 2 events with unreadable start dates, 1 event with a long name`. With `verbose`,
+/// every warning is listed with its event index and the text it was found in.
+///
+/// # Arguments
+///
+/// * `source` - The name of the source the events came from.
+/// * `warnings` - The warnings to render.
+/// * `verbose` - Whether to list every warning.
+///
+/// # Returns
+///
+/// A `String` with the rendered warnings, or an empty string if there are none.
+pub fn render_data_warnings(source: &str, warnings: &[DataWarning], verbose: bool) -> String {
+    let mut out = String::new();
+    if warnings.is_empty() {
+        return out;
+    }
+
+    let mut counts: Vec<(DataIssue, usize)> = Vec::new();
+    for warning in warnings {
+        match counts.iter_mut().find(|(kind, _)| *kind == warning.kind) {
+            Some((_, count)) => *count += 1,
+            None => counts.push((warning.kind, 1)),
+        }
+    }
+    let summary: Vec<String> = counts
+        .iter()
+        .map(|(kind, count)| {
+            let (one, many) = match kind {
+                DataIssue::UnreadableDate => ("an unreadable start date", "unreadable start dates"),
+                DataIssue::EndBeforeStart => {
+                    ("an end before its start", "ends before their starts")
+                }
+                DataIssue::LongName => ("a long name", "long names"),
+                DataIssue::EmptyLocation => ("an empty location", "empty locations"),
+            };
+            if *count == 1 {
+                format!("1 event with {}", one)
+            } else {
+                format!("{} events with {}", count, many)
+            }
+        })
+        .collect();
+    let _ = writeln!(out, "{}: {}", source, summary.join(", "));
+
+    if verbose {
+        for warning in warnings {
+            let _ = match warning.kind {
+                DataIssue::UnreadableDate => writeln!(
+                    out,
+                    "  event {}: start date `{}` isn't a date in a known format",
+                    warning.event_index, warning.text
+                ),
+                DataIssue::EndBeforeStart => writeln!(
+                    out,
+                    "  event {}: end `{}` is before the start, so it was dropped",
+                    warning.event_index, warning.text
+                ),
+                DataIssue::LongName => writeln!(
+                    out,
+                    "  event {}: name is {} characters (check name_selector): `{}`",
+                    warning.event_index,
+                    warning.text.chars().count(),
+                    truncate_at_word(&warning.text, 60)
+                ),
+                DataIssue::EmptyLocation => writeln!(
+                    out,
+                    "  event {}: location `{}` is empty once cleaned",
+                    warning.event_index, warning.text
+                ),
+            };
+        }
+    }
+    out
+}
+
+/// Renders a `SelectorReport` for a source.
+///
+/// # Arguments
+///
+/// * `source` - The name of the source that was checked.
+/// * `event_selector` - The source's event selector.
+/// * `report` - The report to render.
+///
+/// # Returns
+///
+/// A `String` with one line for the event selector and one per field selector.
+pub fn render_selector_report(
+    source: &str,
+    event_selector: &str,
+    report: &SelectorReport,
+) -> String {
+    let mut out = String::new();
+    let _ = writeln!(
+        out,
+        "{}: `{}` matched {} elements",
+        source, event_selector, report.event_matches
+    );
+    let width = report
+        .fields
+        .iter()
+        .map(|f| f.field.len())
+        .max()
+        .unwrap_or(0);
+    for field in &report.fields {
+        let first = match &field.first {
+            Some(value) => format!("{:?}", value.trim()),
+            None => String::from("NO MATCH"),
+        };
+        let _ = writeln!(
+            out,
+            "  {:<width$}  `{}` in {}/{}, first: {}",
+            field.field,
+            field.selector,
+            field.matched,
+            report.event_matches,
+            first,
+            width = width
+        );
+    }
+    out
+}
+
+/// Renders how a start date from `--debug-selectors` was read, e.g.
+/// `  start_date "Doors 7PM" read as 2024-05-02 19:00 (informal)`.
+pub fn render_date_parse(text: &str, parsed: Option<(NaiveDateTime, DateParse)>) -> String {
+    match parsed {
+        Some((datetime, pass)) => format!(
+            "  start_date {:?} read as {} ({})\n",
+            text.trim(),
+            datetime.format("%Y-%m-%d %H:%M"),
+            pass
+        ),
+        None => format!(
+            "  start_date {:?} isn't a date any pass can read\n",
+            text.trim()
+        ),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::diff::{ChangedEvent, FieldChange};
+    use crate::html_parser::FieldReport;
+
+    #[test]
+    fn test_render_event_basic_format() {
+        let event = ProcessedEvent {
+            name: "Concert".to_string(),
+            start_date: "May 2".to_string(),
+            location: Some("Park".to_string()),
+            url: "http://example.com/concert".to_string(),
+            ..Default::default()
+        };
+
+        assert_eq!(
+            render_event(&event, 100, None, Styler::plain()),
+            "Name: Concert\nStart Date: May 2\nEnd Date: N/A\nLocation: Park\nURL: http://example.com/concert\n"
+        );
+    }
+
+    #[test]
+    fn test_display_matches_render_event() {
+        let mut event = event("Concert", "May 2", "Park");
+        event.url = "http://example.com/concert".to_string();
+
+        assert_eq!(
+            event.to_string(),
+            "Name: Concert\nStart Date: May 2\nEnd Date: N/A\nLocation: Park\nURL: http://example.com/concert\n"
+        );
+        event.description = Some("A long description of the evening's concert".to_string());
+        assert_eq!(
+            event.to_string(),
+            render_event(&event, DEFAULT_DESCRIPTION_LENGTH, None, Styler::plain())
+        );
+        assert_eq!(
+            format!("{:.20}", event),
+            render_event(&event, 20, None, Styler::plain())
+        );
+        assert!(format!("{:.20}", event).contains("\nDescription: A long description…\n"));
+    }
+
+    #[test]
+    fn test_render_event_truncates_description_and_shows_image() {
+        let event = ProcessedEvent {
+            name: "Concert".to_string(),
+            description: Some("A long evening of music under the stars".to_string()),
+            image_url: Some("http://example.com/thumb.jpg".to_string()),
+            ..Default::default()
+        };
+
+        let rendered = render_event(&event, 20, None, Styler::plain());
+
+        assert!(rendered.contains("Description: A long evening of…\n"));
+        assert!(rendered.ends_with("Image: http://example.com/thumb.jpg\n"));
+    }
+
+    #[test]
+    fn test_render_json_snapshot() {
+        let events = [
+            ProcessedEvent {
+                name: "Bluegrass Night".to_string(),
+                start_date: "May 2 7:00 PM".to_string(),
+                end_date: Some("May 2 9:30 PM".to_string()),
+                start: NaiveDateTime::parse_from_str("2024-05-02T19:00:00", "%Y-%m-%dT%H:%M:%S")
+                    .ok(),
+                end: NaiveDateTime::parse_from_str("2024-05-02T21:30:00", "%Y-%m-%dT%H:%M:%S").ok(),
+                location: Some("Station Inn".to_string()),
+                url: "https://example.com/bluegrass".to_string(),
+                price: Some("$15.00".to_string()),
+                categories: crate::sources::Categories::from([crate::sources::Category::Music]),
+                source: Some("songkick".to_string()),
+                status: EventStatus::SoldOut,
+                tags: vec!["bluegrass".to_string()],
+                ..Default::default()
+            },
+            event("Open \"Mic\"", "Sometime", "Café"),
+        ];
+
+        assert_eq!(
+            render_json(&events[..1], false),
+            r#"[
+  {
+    "alt_urls": [],
+    "categories": [
+      "music"
+    ],
+    "description": null,
+    "end": "2024-05-02T21:30:00",
+    "end_date": "May 2 9:30 PM",
+    "id": "a6f8c9445b522da1",
+    "image_url": null,
+    "location": "Station Inn",
+    "name": "Bluegrass Night",
+    "price": "$15.00",
+    "source": "songkick",
+    "start": "2024-05-02T19:00:00",
+    "start_date": "May 2 7:00 PM",
+    "status": "sold out",
+    "tags": [
+      "bluegrass"
+    ],
+    "url": "https://example.com/bluegrass"
+  }
+]"#
+        );
+        assert_eq!(
+            render_json(&events[1..], true),
+            r#"[{"alt_urls":[],"categories":[],"description":null,"end":null,"end_date":null,"id":"429814d6f920fd16","image_url":null,"location":"Café","name":"Open \"Mic\"","price":null,"source":null,"start":null,"start_date":"Sometime","status":"unknown","tags":[],"url":""}]"#
+        );
+        assert_eq!(render_json(&[], true), "[]");
+    }
+
+    #[test]
+    fn test_render_csv_quotes_awkward_values() {
+        let mut tricky = event("Say \"Hi\", Y'all", "May 3", "Café Müller");
+        tricky.description = Some("Line one\nline two".to_string());
+        tricky.tags = vec!["folk".to_string(), "all ages".to_string()];
+        let plain = ProcessedEvent {
+            name: "Open Mic".to_string(),
+            ..Default::default()
+        };
+        let (tricky_id, plain_id) = (tricky.id(), plain.id());
+
+        let csv = render_csv(&[tricky, plain], false);
+
+        assert_eq!(csv, format!(
+            "id,name,start_date,end_date,start,end,location,url,source,price,status,categories,tags,description,image_url\n\
+             {},\"Say \"\"Hi\"\", Y'all\",May 3,,,,Café Müller,,,,unknown,,folk; all ages,\"Line one\nline two\",\n\
+             {},Open Mic,,,,,,,,,unknown,,,,\n",
+            tricky_id, plain_id
+        ));
+        assert!(render_csv(&[], true).ends_with("image_url\r\n"));
+    }
+
+    #[test]
+    fn test_render_csv_crlf_ends_rows_only() {
+        let mut event = event("Open Mic", "May 3", "Park");
+        event.description = Some("Line one\nline two".to_string());
+
+        let csv = render_csv(&[event], true);
+
+        assert_eq!(csv.matches("\r\n").count(), 2);
+        assert!(csv.contains("\"Line one\nline two\""));
+    }
+
+    fn temp_dir(name: &str) -> std::path::PathBuf {
+        let dir = std::env::temp_dir().join(format!(
+            "event_finder_output_{}_{}",
+            name,
+            std::process::id()
+        ));
+        let _ = fs::remove_dir_all(&dir);
+        dir
+    }
+
+    #[test]
+    fn test_save_output_creates_directories_and_refuses_to_overwrite() {
+        let dir = temp_dir("save");
+        let path = dir.join("reports").join("week.md");
+
+        save_output(&path, b"first", false).unwrap();
+        let error = save_output(&path, b"second", false).unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::AlreadyExists);
+        assert!(error.to_string().contains("--force"), "{}", error);
+        assert_eq!(fs::read_to_string(&path).unwrap(), "first");
+
+        save_output(&path, b"second", true).unwrap();
+        assert_eq!(fs::read_to_string(&path).unwrap(), "second");
+        let entries: Vec<_> = fs::read_dir(path.parent().unwrap()).unwrap().collect();
+        assert_eq!(entries.len(), 1, "the temporary file is gone");
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_save_output_error_names_the_file() {
+        let dir = temp_dir("save_error");
+        fs::create_dir_all(&dir).unwrap();
+        let blocker = dir.join("not-a-directory");
+        fs::write(&blocker, "").unwrap();
+
+        let error = save_output(&blocker.join("week.md"), b"report", false).unwrap_err();
+
+        assert!(
+            error.to_string().starts_with(&format!(
+                "can't write {}",
+                blocker.join("week.md").display()
+            )),
+            "{}",
+            error
+        );
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    /// Two days of events with text each report format must escape.
+    fn report_groups() -> Vec<(String, Vec<ProcessedEvent>)> {
+        let mut tribute = event(
+            "*NSYNC Tribute <Live> & [More]",
+            "Fri May 3, 9:00 PM",
+            "Tootsie's Orchid Lounge",
+        );
+        tribute.url = "https://example.com/events/nsync (tribute)".to_string();
+        tribute.price = Some("$10.00".to_string());
+        tribute.status = EventStatus::SoldOut;
+        tribute.description =
+            Some("Bye bye bye! Doors at 8 — \"all ages\" until 10pm.".to_string());
+        let mut market = event("Flea_Market #1", "Sat May 4", "Fairgrounds | Nashville");
+        market.url = "javascript:alert(1)".to_string();
+        let mut jam = event("Bluegrass Jam", "Fri May 3, 7:00 PM", "Station Inn");
+        jam.url = "https://stationinn.com/jam?day=fri&time=7".to_string();
+        vec![
+            ("Friday, May 3".to_string(), vec![jam, tribute]),
+            ("Saturday, May 4".to_string(), vec![market]),
+        ]
+    }
+
+    #[test]
+    fn test_render_markdown_golden() {
+        assert_eq!(
+            render_markdown(&report_groups(), 40),
+            include_str!("../tests/fixtures/report.md")
+        );
+    }
+
+    #[test]
+    fn test_render_html_golden() {
+        assert_eq!(
+            render_html(&report_groups(), 40),
+            include_str!("../tests/fixtures/report.html")
+        );
+    }
+
+    fn event(name: &str, start: &str, location: &str) -> ProcessedEvent {
+        ProcessedEvent {
+            name: name.to_string(),
+            start_date: start.to_string(),
+            location: Some(location.to_string()),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_render_diff_snapshot() {
+        let diff = EventDiff {
+            added: vec![event("Brand new", "May 4", "Station Inn")],
+            removed: vec![event("Goes away", "May 2", "Basement")],
+            changed: vec![ChangedEvent {
+                old: event("Moves", "May 2", "Exit/In"),
+                new: event("Moves", "May 3", "Basement East"),
+                fields: vec![
+                    FieldChange {
+                        field: "start_date",
+                        old: "May 2".to_string(),
+                        new: "May 3".to_string(),
+                    },
+                    FieldChange {
+                        field: "location",
+                        old: "Exit/In".to_string(),
+                        new: "Basement East".to_string(),
+                    },
+                ],
+            }],
+        };
+
+        let expected = "\
+New events (1):
+  + Brand new (May 4, Station Inn)
+
+Removed events (1):
+  - Goes away (May 2, Basement)
+
+Changed events (1):
+  ~ Moves
+      start_date May 2    ->  May 3
+      location   Exit/In  ->  Basement East
+";
+        assert_eq!(render_diff(&diff), expected);
+    }
+
+    #[test]
+    fn test_render_diff_empty() {
+        assert_eq!(
+            render_diff(&EventDiff::default()),
+            "No changes since the last fetch.\n"
+        );
+    }
+
+    fn warning(event_index: usize, field: &'static str, selector: &str) -> FieldWarning {
+        FieldWarning {
+            event_index,
+            field,
+            selector: selector.to_string(),
+            kind: WarningKind::NoMatch,
+        }
+    }
+
+    #[test]
+    fn test_render_warnings_summary_and_verbose() {
+        let warnings = vec![
+            warning(0, "location", ".venue"),
+            warning(2, "location", ".venue"),
+            warning(2, "url", "a"),
+            warning(3, "location", ".venue"),
+            FieldWarning {
+                event_index: 3,
+                field: "start_date",
+                selector: "^(.+?) —".to_string(),
+                kind: WarningKind::PatternMismatch,
+            },
+        ];
+
+        assert_eq!(render_warnings("songkick", &warnings, false),
+            "songkick: 3 events missing location, 1 event missing url, 1 event with unmatched start_date pattern\n");
+        assert_eq!(
+            render_warnings("songkick", &warnings, true),
+            "\
+songkick: 3 events missing location, 1 event missing url, 1 event with unmatched start_date pattern
+  event 0: no location (selector `.venue` matched nothing)
+  event 2: no location (selector `.venue` matched nothing)
+  event 2: no url (selector `a` matched nothing)
+  event 3: no location (selector `.venue` matched nothing)
+  event 3: raw start_date kept (pattern `^(.+?) —` didn't match)
+"
+        );
+        assert_eq!(render_warnings("songkick", &[], true), "");
+    }
+
+    #[test]
+    fn test_render_selector_report() {
+        let report = SelectorReport {
+            event_matches: 3,
+            fields: vec![
+                FieldReport {
+                    field: "name",
+                    selector: ".name".to_string(),
+                    matched: 3,
+                    first: Some(" Jazz Night ".to_string()),
+                },
+                FieldReport {
+                    field: "location",
+                    selector: ".venue".to_string(),
+                    matched: 0,
+                    first: None,
+                },
+            ],
+        };
+
+        assert_eq!(
+            render_selector_report("perto", ".pt_col", &report),
+            "\
+perto: `.pt_col` matched 3 elements
+  name      `.name` in 3/3, first: \"Jazz Night\"
+  location  `.venue` in 0/3, first: NO MATCH
+"
+        );
+    }
+
+    #[test]
+    fn test_render_date_parse() {
+        let start = NaiveDateTime::parse_from_str("2024-05-02 19:00", "%Y-%m-%d %H:%M").ok();
+
+        assert_eq!(
+            render_date_parse(" Doors 7PM ", start.map(|s| (s, DateParse::Informal))),
+            "  start_date \"Doors 7PM\" read as 2024-05-02 19:00 (informal)\n"
+        );
+        assert_eq!(
+            render_date_parse("Soon", None),
+            "  start_date \"Soon\" isn't a date any pass can read\n"
+        );
+    }
+
+    #[test]
+    fn test_render_data_warnings_summary_and_verbose() {
+        let warnings = vec![
+            DataWarning {
+                event_index: 1,
+                kind: DataIssue::UnreadableDate,
+                text: "Most Thursdays".to_string(),
+            },
+            DataWarning {
+                event_index: 2,
+                kind: DataIssue::LongName,
+                text: "Join us ".repeat(20),
+            },
+            DataWarning {
+                event_index: 4,
+                kind: DataIssue::UnreadableDate,
+                text: "TBA".to_string(),
+            },
+            DataWarning {
+                event_index: 5,
+                kind: DataIssue::EndBeforeStart,
+                text: "2024-05-02 18:00".to_string(),
+            },
+            DataWarning {
+                event_index: 6,
+                kind: DataIssue::EmptyLocation,
+                text: " ".to_string(),
+            },
+        ];
+
+        assert_eq!(render_data_warnings("perto", &warnings, false),
+            "perto: 2 events with unreadable start dates, 1 event with a long name, 1 event with an end before its start, 1 event with an empty location\n");
+        assert_eq!(render_data_warnings("perto", &warnings, true), "\
+            perto: 2 events with unreadable start dates, 1 event with a long name, 1 event with an end before its start, 1 event with an empty location\n  \
+            event 1: start date `Most Thursdays` isn't a date in a known format\n  \
+            event 2: name is 160 characters (check name_selector): `Join us Join us Join us Join us Join us Join us Join us…`\n  \
+            event 4: start date `TBA` isn't a date in a known format\n  \
+            event 5: end `2024-05-02 18:00` is before the start, so it was dropped\n  \
+            event 6: location ` ` is empty once cleaned\n");
+        assert_eq!(render_data_warnings("perto", &[], true), "");
+    }
+
+    #[test]
+    fn test_render_warnings_labels_meta_fallback() {
+        let warnings = vec![FieldWarning {
+            event_index: 0,
+            field: "event",
+            selector: ".event".to_string(),
+            kind: WarningKind::MetaFallback,
+        }];
+
+        assert_eq!(
+            render_warnings("perto", &warnings, true),
+            "\
+perto: 1 event read from page meta tags
+  event 0: read from the page's meta tags (`.event` found no events; the selectors need fixing)
+"
+        );
+    }
+
+    #[test]
+    fn test_render_event_marks_unavailable_events() {
+        let event = |status: EventStatus| ProcessedEvent {
+            name: "Concert".to_string(),
+            status,
+            ..Default::default()
+        };
+
+        assert!(
+            render_event(&event(EventStatus::SoldOut), 100, None, Styler::plain())
+                .starts_with("Name: Concert [SOLD OUT]\n")
+        );
+        assert!(
+            render_event(&event(EventStatus::Cancelled), 100, None, Styler::plain())
+                .starts_with("Name: Concert [CANCELLED]\n")
+        );
+        assert!(
+            render_event(&event(EventStatus::Available), 100, None, Styler::plain())
+                .starts_with("Name: Concert\n")
+        );
+        let fetched = ProcessedEvent {
+            source: Some("songkick".to_string()),
+            ..event(EventStatus::SoldOut)
+        };
+        assert!(render_event(&fetched, 100, None, Styler::plain())
+            .starts_with("Name: Concert [songkick] [SOLD OUT]\n"));
+    }
+
+    #[test]
+    fn test_render_event_shows_matched_interests() {
+        let ranked = ProcessedEvent {
+            score: 8,
+            matched_interests: vec!["jazz".to_string(), "The Basement".to_string()],
+            ..event("Jazz", "May 2", "The Basement")
+        };
+
+        assert!(render_event(&ranked, 100, None, Styler::plain())
+            .contains("\nMatched: jazz, The Basement (score 8)\n"));
+        assert!(
+            !render_event(&event("Jazz", "May 2", "Park"), 100, None, Styler::plain())
+                .contains("Matched")
+        );
+    }
+
+    #[test]
+    fn test_render_event_shows_distance() {
+        let near = ProcessedEvent {
+            distance: Some(2.34),
+            ..event("Jazz", "May 2", "The Basement")
+        };
+
+        assert!(render_event(&near, 100, None, Styler::plain())
+            .contains("\nLocation: The Basement (2.3 mi away)\n"));
+        assert!(
+            render_event(&event("Jazz", "May 2", "Park"), 100, None, Styler::plain())
+                .contains("\nLocation: Park\n")
+        );
+    }
+
+    #[test]
+    fn test_render_pick() {
+        let rendered = render_pick(
+            &event("Concert", "May 2", "Park"),
+            12,
+            100,
+            None,
+            Styler::plain(),
+        );
+
+        assert!(rendered.starts_with("★★★ Your pick, out of 12 events ★★★\nName: Concert\n"));
+        assert!(rendered.ends_with("★★★ no link given ★★★\n"));
+    }
+
+    #[test]
+    fn test_render_event_lists_tags() {
+        let event = ProcessedEvent {
+            name: "Concert".to_string(),
+            tags: vec!["jazz".to_string(), "free".to_string()],
+            ..Default::default()
+        };
+
+        assert!(
+            render_event(&event, 100, None, Styler::plain()).ends_with("URL: \nTags: jazz, free\n")
+        );
+    }
+
+    #[test]
+    fn test_render_search_summary() {
+        let terms = vec!["jazz".to_string(), "blues".to_string()];
+        assert_eq!(
+            render_search_summary(12, 87, &terms[..1], Match::All),
+            "12 of 87 events match 'jazz'\n"
+        );
+        assert_eq!(
+            render_search_summary(3, 87, &terms, Match::Any),
+            "3 of 87 events match 'jazz' or 'blues'\n"
+        );
+        assert_eq!(
+            render_search_summary(0, 1, &terms, Match::All),
+            "0 of 1 event matches 'jazz' and 'blues'\n"
+        );
+    }
+
+    #[test]
+    fn test_render_exclusions() {
+        let rules = vec![
+            ExcludeRule {
+                pattern: "trivia".to_string(),
+                field: ExcludeField::Text,
+                whole_word: false,
+            },
+            ExcludeRule {
+                pattern: "Exit/In".to_string(),
+                field: ExcludeField::Venue,
+                whole_word: false,
+            },
+        ];
+
+        assert_eq!(
+            render_exclusions(&rules, &[3, 0], false),
+            "excluded 3 events (-v to see which rules matched)\n"
+        );
+        assert_eq!(
+            render_exclusions(&rules, &[1, 0], true),
+            "excluded 1 event mentioning 'trivia'\nexcluded 0 events at 'Exit/In'\n"
+        );
+        assert_eq!(render_exclusions(&rules, &[0, 0], false), "");
+    }
+
+    #[test]
+    fn test_render_event_relative_start() {
+        let now = NaiveDateTime::parse_from_str("2024-05-02 17:00", "%Y-%m-%d %H:%M").unwrap();
+        let event = ProcessedEvent {
+            name: "Concert".to_string(),
+            start_date: "May 2 7:15 PM".to_string(),
+            start: NaiveDateTime::parse_from_str("2024-05-02 19:15", "%Y-%m-%d %H:%M").ok(),
+            ..Default::default()
+        };
+        let undated = ProcessedEvent {
+            start_date: "Most Thursdays".to_string(),
+            ..Default::default()
+        };
+
+        assert!(render_event(&event, 100, Some(now), Styler::plain())
+            .contains("Start Date: May 2 7:15 PM (starts in 2h 15m)\n"));
+        assert!(render_event(&event, 100, None, Styler::plain())
+            .contains("Start Date: May 2 7:15 PM\n"));
+        assert!(render_event(&undated, 100, Some(now), Styler::plain())
+            .contains("Start Date: Most Thursdays\n"));
+
+        let ended = ProcessedEvent {
+            end_date: Some("May 2 9:45 PM".to_string()),
+            end: NaiveDateTime::parse_from_str("2024-05-02 21:45", "%Y-%m-%d %H:%M").ok(),
+            ..event
+        };
+        assert!(render_event(&ended, 100, None, Styler::plain())
+            .contains("End Date: May 2 9:45 PM (2h 30m)\n"));
+    }
+
+    #[test]
+    fn test_render_group_header_and_members() {
+        let events = [
+            event("Show", "May 3", "Ryman"),
+            event("Jam", "May 3", "Ryman"),
+        ];
+
+        let members: String = events
+            .iter()
+            .enumerate()
+            .map(|(i, event)| {
+                render_group_member(EventBlock {
+                    event,
+                    description_length: 100,
+                    now: None,
+                    styler: Styler::plain(),
+                    number: Some(i + 1),
+                    fresh: i == 1,
+                })
+            })
+            .collect();
+        let rendered = render_group_header("The Ryman", Styler::plain()) + &members;
+
+        assert!(rendered.starts_with("— The Ryman —\n  1. Name: Show\n  Start Date: May 3\n"));
+        assert!(rendered.contains("\n\n  2. Name: Jam [NEW]\n"));
+        assert!(rendered.ends_with("  URL: \n\n"));
+    }
+
+    #[test]
+    fn test_colored_and_plain_rendering() {
+        let event = ProcessedEvent {
+            status: EventStatus::SoldOut,
+            ..event("Concert", "May 2", "Park")
+        };
+
+        assert_eq!(
+            render_event(&event, 100, None, Styler::new(true)),
+            "\
+Name: \u{1b}[1mConcert\u{1b}[0m \u{1b}[33m[SOLD OUT]\u{1b}[0m
+Start Date: \u{1b}[36mMay 2\u{1b}[0m
+End Date: \u{1b}[36mN/A\u{1b}[0m
+Location: \u{1b}[32mPark\u{1b}[0m
+URL: \n"
+        );
+        assert_eq!(
+            render_event(&event, 100, None, Styler::plain()),
+            "\
+Name: Concert [SOLD OUT]
+Start Date: May 2
+End Date: N/A
+Location: Park
+URL: \n"
+        );
+        assert_eq!(
+            render_group_header("May 2", Styler::new(true)),
+            "\u{1b}[1;35m— May 2 —\u{1b}[0m\n"
+        );
+        assert_eq!(render_group_header("May 2", Styler::plain()), "— May 2 —\n");
+
+        let color = Styler::new(true);
+        assert_eq!(color.summary("2 events"), "\u{1b}[2m2 events\u{1b}[0m");
+        assert_eq!(
+            color.warning("songkick: 1 event missing url"),
+            "\u{1b}[33msongkick: 1 event missing url\u{1b}[0m"
+        );
+        assert_eq!(
+            color.error("Error: offline"),
+            "\u{1b}[1;31mError: offline\u{1b}[0m"
+        );
+        for style in [
+            Styler::name,
+            Styler::date,
+            Styler::venue,
+            Styler::warning,
+            Styler::error,
+            Styler::header,
+            Styler::summary,
+            Styler::fresh,
+        ] {
+            assert_eq!(style(Styler::plain(), "text"), "text");
+        }
+    }
+
+    #[test]
+    fn test_styler_detect() {
+        assert!(!Styler::detect(false, false).is_colored());
+        assert!(!Styler::detect(true, true).is_colored());
+        assert_eq!(
+            Styler::detect(true, false).is_colored(),
+            std::env::var_os("NO_COLOR").is_none_or(|v| v.is_empty())
+        );
+        assert_eq!(Styler::default(), Styler::plain());
+    }
+}
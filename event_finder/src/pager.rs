@@ -0,0 +1,97 @@
+//! # Pager Module
+//!
+//! This module shows long listings a page at a time, asking before each further page. The
+//! reader and writer are passed in so paging can be driven by tests as well as the terminal.
+
+use std::io::{self, BufRead, Write};
+
+/// The number of events shown per page in interactive mode.
+pub const PAGE_SIZE: usize = 10;
+
+/// Writes rendered blocks a page at a time, prompting between pages.
+///
+/// After each full page the user is asked to press Enter for the next one; `q` (or the end of
+/// the input) stops. No prompt is shown after the last page.
+///
+/// # Arguments
+///
+/// * `blocks` - The rendered events, one block per event.
+/// * `page_size` - How many blocks to write per page.
+/// * `input` - Where the user's answers are read from.
+/// * `out` - Where the blocks and prompts are written.
+///
+/// # Returns
+///
+/// How many blocks were written, or the first I/O error.
+pub fn page<R: BufRead, W: Write + ?Sized>(
+    blocks: &[String],
+    page_size: usize,
+    input: &mut R,
+    out: &mut W,
+) -> io::Result<usize> {
+    let page_size = page_size.max(1);
+    let mut shown = 0;
+    for page in blocks.chunks(page_size) {
+        if shown > 0 {
+            let more = (blocks.len() - shown).min(page_size);
+            write!(
+                out,
+                "-- {} of {} shown: press Enter for {} more, q to stop --",
+                shown,
+                blocks.len(),
+                more
+            )?;
+            out.flush()?;
+            let mut answer = String::new();
+            if input.read_line(&mut answer)? == 0 || answer.trim().eq_ignore_ascii_case("q") {
+                writeln!(out)?;
+                break;
+            }
+        }
+        for block in page {
+            write!(out, "{}", block)?;
+        }
+        shown += page.len();
+    }
+    Ok(shown)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn blocks(count: usize) -> Vec<String> {
+        (1..=count).map(|i| format!("event {}\n", i)).collect()
+    }
+
+    #[test]
+    fn test_page_until_quit() {
+        let mut out = Vec::new();
+
+        let shown = page(&blocks(5), 2, &mut "\nq\n".as_bytes(), &mut out).unwrap();
+
+        assert_eq!(shown, 4);
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            "event 1\nevent 2\n\
+            -- 2 of 5 shown: press Enter for 2 more, q to stop --event 3\nevent 4\n\
+            -- 4 of 5 shown: press Enter for 1 more, q to stop --\n"
+        );
+    }
+
+    #[test]
+    fn test_page_stops_at_end_of_input_and_skips_prompt_for_one_page() {
+        let mut out = Vec::new();
+        assert_eq!(
+            page(&blocks(3), 2, &mut "".as_bytes(), &mut out).unwrap(),
+            2
+        );
+
+        let mut out = Vec::new();
+        assert_eq!(
+            page(&blocks(2), 2, &mut "".as_bytes(), &mut out).unwrap(),
+            2
+        );
+        assert_eq!(String::from_utf8(out).unwrap(), "event 1\nevent 2\n");
+    }
+}
@@ -0,0 +1,582 @@
+//! # Pipeline Module
+//!
+//! This module runs the per-source pipeline: fetch, parse, process, and compare against the
+//! last snapshot. Without a `WebClient` (offline mode) the pipeline serves the snapshot instead
+//! and never touches the network. `parse_source` runs the same steps on a page the caller
+//! already has, e.g. one saved by hand.
+
+use std::time::Duration;
+
+use crate::data_processing::{self, DataWarning, ProcessedEvent, VenueAliases};
+use crate::diff::{self, EventDiff};
+use crate::html_parser::{self, FieldWarning, ParsedPage};
+use crate::snapshot::SnapshotStore;
+use crate::sources::{self, Category, EventSource, Source};
+use crate::timezone::Zone;
+use crate::web_requests::{FetchError, WebClient};
+
+/// The pause between detail page requests when enriching events.
+pub const ENRICH_REQUEST_DELAY: Duration = Duration::from_millis(500);
+
+/// Settings that change how the pipeline treats parsed data.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct Settings {
+    /// Keep events whose name came back empty instead of dropping them.
+    pub keep_nameless: bool,
+    /// Follow each event's detail page to fill in fields the listing left empty.
+    pub enrich: bool,
+    /// The maximum number of detail pages fetched per source when enriching.
+    pub enrich_limit: usize,
+    /// How long to wait between detail page requests.
+    pub request_delay: Duration,
+    /// The home timezone dates are read in.
+    pub zone: Zone,
+    /// The canonical names of venues sources spell differently.
+    pub venue_aliases: VenueAliases,
+}
+
+/// The outcome of running the pipeline for one source.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct SourceEvents {
+    /// The processed events to display.
+    pub events: Vec<ProcessedEvent>,
+    /// What changed since the previous snapshot; empty when served from the snapshot.
+    pub changes: EventDiff,
+    /// How many matched elements had no name and didn't look like events.
+    pub nameless: usize,
+    /// How many events were listed more than once and collapsed.
+    pub duplicates: usize,
+    /// How many structured data objects were skipped because they weren't events.
+    pub skipped: usize,
+    /// How many events were parsed before any were dropped.
+    pub parsed: usize,
+    /// How many events the fetched pages matched; more than `parsed` when `max_events` applied.
+    pub matched: usize,
+    /// Fields that couldn't be extracted from the kept events.
+    pub warnings: Vec<FieldWarning>,
+    /// Problems found in the kept events' data, indexed among them.
+    pub data_warnings: Vec<DataWarning>,
+}
+
+/// Runs the pipeline for one source.
+///
+/// With a client, the source is fetched and processed, compared against its last snapshot,
+/// and the snapshot is replaced. Without one, the last snapshot is returned, with venue
+/// aliases applied again in case the map changed since it was saved.
+///
+/// # Arguments
+///
+/// * `source` - The source to load.
+/// * `categories` - The categories the user asked for.
+/// * `client` - The client to fetch with, or `None` in offline mode.
+/// * `store` - The snapshot store.
+/// * `settings` - How to treat parsed data.
+///
+/// # Returns
+///
+/// The source's events, or a `FetchError` if fetching failed or, offline, no snapshot exists.
+pub fn run_source(
+    source: &dyn EventSource,
+    categories: &[Category],
+    client: Option<&WebClient>,
+    store: &SnapshotStore,
+    settings: &Settings,
+) -> Result<SourceEvents, FetchError> {
+    let client = match client {
+        Some(client) => client,
+        None => {
+            let source = source.source();
+            let events = store
+                .get(&source.name)
+                .ok_or_else(|| FetchError::NoCachedData(source.name.clone()))?;
+            log::debug!("{}: {} events from the snapshot", source.name, events.len());
+            let events = data_processing::filter_by_category(events, categories);
+            let mut events = data_processing::canonicalize_venues(events, &settings.venue_aliases);
+            // Snapshots saved before events recorded their source
+            for event in events.iter_mut().filter(|e| e.source.is_none()) {
+                event.source = Some(source.name.clone());
+            }
+            return Ok(SourceEvents {
+                events,
+                ..Default::default()
+            });
+        }
+    };
+
+    log::info!("{}: fetching {}", source.name(), source.source().url);
+    let fetched = source.fetch_listing(client, settings.zone)?;
+    let source = source.source();
+    let mut result = finish(
+        fetched,
+        source,
+        categories,
+        Some(client).filter(|_| settings.enrich),
+        settings,
+    );
+
+    // Compare against the last snapshot of this source, then replace it
+    result.changes = diff::diff_events(&store.load(&source.name), &result.events);
+    if let Err(e) = store.save(&source.name, &result.events) {
+        log::error!("saving the {} snapshot: {}", source.name, e);
+    }
+    Ok(result)
+}
+
+/// Runs the pipeline for one source on a page that was already fetched, without the network.
+///
+/// The page is parsed and processed as `run_source` would a fetched one, but events aren't
+/// enriched and the snapshot is neither compared against nor replaced.
+///
+/// # Arguments
+///
+/// * `source` - The source whose configuration reads the page.
+/// * `body` - The page.
+/// * `categories` - The categories the user asked for.
+/// * `settings` - How to treat parsed data.
+///
+/// # Returns
+///
+/// The page's events, or a `FetchError` if it is a broken feed or calendar, or a bot
+/// challenge.
+pub fn parse_source(
+    source: &dyn EventSource,
+    body: &str,
+    categories: &[Category],
+    settings: &Settings,
+) -> Result<SourceEvents, FetchError> {
+    let parsed = source.parse(body, settings.zone)?;
+    Ok(finish(parsed, source.source(), categories, None, settings))
+}
+
+/// Drops, enriches with `enrich_client` if given, and processes a source's parsed events.
+fn finish(
+    fetched: ParsedPage,
+    source: &Source,
+    categories: &[Category],
+    enrich_client: Option<&WebClient>,
+    settings: &Settings,
+) -> SourceEvents {
+    let (parsed, matched, skipped) = (fetched.events.len(), fetched.matched, fetched.skipped);
+    let (events, nameless) = html_parser::drop_nameless(fetched.events, settings.keep_nameless);
+    let (mut events, duplicates) = html_parser::dedupe(events);
+    if let Some(client) = enrich_client {
+        sources::enrich_events(
+            &mut events,
+            client,
+            &source.base_url,
+            settings.enrich_limit,
+            settings.request_delay,
+            &mut |done, total| {
+                log::debug!("{}: enriched {}/{} detail pages", source.name, done, total);
+                if done == total {
+                    log::info!(
+                        "{}: enriched {} events from their detail pages",
+                        source.name,
+                        total
+                    );
+                }
+            },
+        );
+    }
+    let warnings: Vec<FieldWarning> = events
+        .iter()
+        .flat_map(|e| e.warnings.iter().cloned())
+        .collect();
+
+    let (processed_events, data_warnings) = process_events(events, source, settings.zone);
+    // After the transforms, so aliases match locations with per-source suffixes removed
+    let processed_events =
+        data_processing::canonicalize_venues(processed_events, &settings.venue_aliases);
+    let processed_events = data_processing::filter_by_category(processed_events, categories);
+    log::info!(
+        "{}: {} events ({} parsed, {} without a name, {} duplicates)",
+        source.name,
+        processed_events.len(),
+        parsed,
+        nameless,
+        duplicates
+    );
+
+    SourceEvents {
+        events: processed_events,
+        changes: EventDiff::default(),
+        nameless,
+        duplicates,
+        skipped,
+        parsed,
+        matched,
+        warnings,
+        data_warnings,
+    }
+}
+
+/// Processes a source's raw events and applies its per-source transforms and default duration.
+///
+/// # Arguments
+///
+/// * `events` - The source's raw events, with nameless and duplicate ones already dropped.
+/// * `source` - The source they came from.
+/// * `zone` - The home timezone dates are read in.
+///
+/// # Returns
+///
+/// The processed events, and the data warnings `process_data` found in them.
+pub fn process_events(
+    events: Vec<html_parser::Event>,
+    source: &Source,
+    zone: Zone,
+) -> (Vec<ProcessedEvent>, Vec<DataWarning>) {
+    let (processed_events, data_warnings) = data_processing::process_data(events, zone);
+    let mut processed_events =
+        data_processing::apply_transforms(processed_events, &source.config.transforms);
+    if let Some(minutes) = source.config.default_duration_minutes {
+        processed_events = data_processing::fill_missing_ends(
+            processed_events,
+            chrono::Duration::minutes(minutes as i64),
+        );
+    }
+    (processed_events, data_warnings)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::html_parser::{SiteConfig, WarningKind};
+    use crate::logging;
+    use crate::sources::{Categories, ScrapedSource};
+    use log::Level;
+    use mockito::{mock, server_url};
+    use std::{env, fs};
+
+    fn source(path: &str) -> ScrapedSource {
+        ScrapedSource::new(Source {
+            name: path.to_string(),
+            url: format!("{}/{}", server_url(), path),
+            base_url: server_url(),
+            categories: Categories::from([Category::Music]),
+            cities: Vec::new(),
+            config: SiteConfig {
+                event_selector: ".event".to_string(),
+                name_selector: ".name".to_string(),
+                start_date_selector: ".start".to_string(),
+                url: "a".to_string(),
+                ..Default::default()
+            },
+        })
+    }
+
+    fn temp_store(name: &str) -> SnapshotStore {
+        let dir = env::temp_dir().join(format!(
+            "event_finder_pipeline_{}_{}",
+            name,
+            std::process::id()
+        ));
+        let _ = fs::remove_dir_all(&dir);
+        SnapshotStore::new(dir)
+    }
+
+    #[test]
+    fn test_run_source_online_saves_snapshot_and_reports_changes() {
+        let _m = mock("GET", "/pipeline-online")
+            .with_status(200)
+            .with_body(
+                r#"<div class="event"><h2 class="name">Show</h2><a href="/show">x</a></div>"#,
+            )
+            .create();
+        let store = temp_store("online");
+        let client = WebClient::new(crate::web_requests::NetworkPolicy::Online).unwrap();
+
+        let first = run_source(
+            &source("pipeline-online"),
+            &[Category::Music],
+            Some(&client),
+            &store,
+            &Settings::default(),
+        )
+        .unwrap();
+        let second = run_source(
+            &source("pipeline-online"),
+            &[Category::Music],
+            Some(&client),
+            &store,
+            &Settings::default(),
+        )
+        .unwrap();
+
+        assert_eq!(first.events.len(), 1);
+        assert_eq!(first.changes.added.len(), 1);
+        assert!(second.changes.is_empty());
+        assert_eq!(store.load("pipeline-online"), second.events);
+    }
+
+    #[test]
+    fn test_run_source_logs_fetch_counts_and_extraction() {
+        logging::capture::install();
+        let _m = mock("GET", "/pipeline-logged")
+            .with_status(200)
+            .with_body(r#"<div class="event"><h2 class="name">Logged Show</h2><a href="/show">x</a></div>"#)
+            .create();
+        let store = temp_store("logged");
+        let client = WebClient::new(crate::web_requests::NetworkPolicy::Online).unwrap();
+
+        run_source(
+            &source("pipeline-logged"),
+            &[Category::Music],
+            Some(&client),
+            &store,
+            &Settings::default(),
+        )
+        .unwrap();
+
+        let logged = logging::capture::records_containing("pipeline-logged");
+        let has = |level: Level, text: &str| {
+            logged
+                .iter()
+                .any(|(l, _, message)| *l == level && message.contains(text))
+        };
+        assert!(
+            has(Level::Info, "pipeline-logged: fetching http"),
+            "{:?}",
+            logged
+        );
+        assert!(
+            has(
+                Level::Info,
+                "pipeline-logged: 1 events (1 parsed, 0 without a name, 0 duplicates)"
+            ),
+            "{:?}",
+            logged
+        );
+        assert!(has(Level::Debug, "GET http"), "{:?}", logged);
+        assert!(
+            logging::capture::records_containing("name from '.name': Some(\"Logged Show\")")
+                .iter()
+                .any(|(l, _, _)| *l == Level::Debug)
+        );
+        assert!(
+            logging::capture::records_containing("<h2 class=\"name\">Logged Show</h2>")
+                .iter()
+                .any(|(l, _, _)| *l == Level::Trace)
+        );
+
+        store.save("pipeline-logged", &[]).unwrap();
+        run_source(
+            &source("pipeline-logged"),
+            &[Category::Music],
+            None,
+            &store,
+            &Settings::default(),
+        )
+        .unwrap();
+        assert!(logging::capture::records_containing(
+            "pipeline-logged: 0 events from the snapshot"
+        )
+        .iter()
+        .any(|(l, _, _)| *l == Level::Debug));
+    }
+
+    #[test]
+    fn test_run_source_offline_serves_snapshot_without_network() {
+        let m = mock("GET", "/pipeline-offline").expect(0).create();
+        let store = temp_store("offline");
+        let cached = vec![ProcessedEvent {
+            name: "Cached show".to_string(),
+            categories: Categories::from([Category::Music]),
+            ..Default::default()
+        }];
+        store.save("pipeline-offline", &cached).unwrap();
+
+        let result = run_source(
+            &source("pipeline-offline"),
+            &[Category::Music],
+            None,
+            &store,
+            &Settings::default(),
+        )
+        .unwrap();
+
+        m.assert();
+        assert_eq!(
+            result.events,
+            vec![ProcessedEvent {
+                source: Some("pipeline-offline".to_string()),
+                ..cached[0].clone()
+            }]
+        );
+        assert!(result.changes.is_empty());
+    }
+
+    #[test]
+    fn test_run_source_offline_without_snapshot_errors() {
+        let m = mock("GET", "/pipeline-uncached").expect(0).create();
+        let store = temp_store("uncached");
+
+        let result = run_source(
+            &source("pipeline-uncached"),
+            &[Category::Music],
+            None,
+            &store,
+            &Settings::default(),
+        );
+
+        m.assert();
+        let error = result.unwrap_err();
+        assert!(matches!(&error, FetchError::NoCachedData(name) if name == "pipeline-uncached"));
+        assert_eq!(
+            error.to_string(),
+            "offline: no cached data for pipeline-uncached"
+        );
+    }
+
+    #[test]
+    fn test_run_source_counts_and_drops_nameless_elements() {
+        let _m = mock("GET", "/pipeline-nameless")
+            .with_status(200)
+            .with_body(
+                r#"<div class="event"><h2 class="name">Show</h2></div><div class="event">Ad</div>"#,
+            )
+            .create();
+        let store = temp_store("nameless");
+        let client = WebClient::new(crate::web_requests::NetworkPolicy::Online).unwrap();
+
+        let dropped = run_source(
+            &source("pipeline-nameless"),
+            &[Category::Music],
+            Some(&client),
+            &store,
+            &Settings::default(),
+        )
+        .unwrap();
+        let kept = run_source(
+            &source("pipeline-nameless"),
+            &[Category::Music],
+            Some(&client),
+            &store,
+            &Settings {
+                keep_nameless: true,
+                ..Default::default()
+            },
+        )
+        .unwrap();
+
+        assert_eq!((dropped.events.len(), dropped.nameless), (1, 1));
+        assert_eq!((kept.events.len(), kept.nameless), (2, 1));
+    }
+
+    #[test]
+    fn test_run_source_collects_field_warnings() {
+        let _m = mock("GET", "/pipeline-warnings")
+            .with_status(200)
+            .with_body(r#"<div class="event"><h2 class="name">Show</h2><span class="start">May 2</span></div>"#)
+            .create();
+        let store = temp_store("warnings");
+        let client = WebClient::new(crate::web_requests::NetworkPolicy::Online).unwrap();
+
+        let result = run_source(
+            &source("pipeline-warnings"),
+            &[Category::Music],
+            Some(&client),
+            &store,
+            &Settings::default(),
+        )
+        .unwrap();
+
+        assert_eq!(
+            result.warnings,
+            vec![FieldWarning {
+                event_index: 0,
+                field: "url",
+                selector: "a".to_string(),
+                kind: WarningKind::NoMatch
+            },]
+        );
+    }
+
+    #[test]
+    fn test_run_source_enriches_from_detail_pages() {
+        let listing = format!(
+            r#"<div class="event"><h2 class="name">Show</h2><a href="{}/pipeline-enrich/show">x</a></div>"#,
+            server_url()
+        );
+        let _listing = mock("GET", "/pipeline-enrich").with_body(listing).create();
+        let detail = mock("GET", "/pipeline-enrich/show")
+            .with_body(r#"<script type="application/ld+json">{"@type": "Event", "name": "Show", "startDate": "2024-05-02T20:00:00-05:00"}</script>"#)
+            .expect(1)
+            .create();
+        let store = temp_store("enrich");
+        let client = WebClient::new(crate::web_requests::NetworkPolicy::Online).unwrap();
+        let settings = Settings {
+            enrich: true,
+            enrich_limit: 5,
+            ..Default::default()
+        };
+
+        let result = run_source(
+            &source("pipeline-enrich"),
+            &[Category::Music],
+            Some(&client),
+            &store,
+            &settings,
+        )
+        .unwrap();
+
+        detail.assert();
+        assert_eq!(result.events[0].start_date, "2024-05-02T20:00:00-05:00");
+        assert!(result.warnings.is_empty());
+    }
+
+    #[test]
+    fn test_run_source_warns_about_unreadable_dates() {
+        let _m = mock("GET", "/pipeline-dates")
+            .with_body(r#"<div class="event"><h2 class="name">Show</h2><span class="start">May 2 @ 7:00 pm</span><a href="/1">x</a></div>
+                          <div class="event"><h2 class="name">Jam</h2><span class="start">Most Thursdays</span><a href="/2">x</a></div>"#)
+            .create();
+        let store = temp_store("dates");
+        let client = WebClient::new(crate::web_requests::NetworkPolicy::Online).unwrap();
+
+        let result = run_source(
+            &source("pipeline-dates"),
+            &[Category::Music],
+            Some(&client),
+            &store,
+            &Settings::default(),
+        )
+        .unwrap();
+
+        assert!(result.events[0].start.is_some());
+        assert!(result.warnings.is_empty());
+        assert_eq!(
+            result.data_warnings,
+            vec![DataWarning {
+                event_index: 1,
+                kind: data_processing::DataIssue::UnreadableDate,
+                text: "Most Thursdays".to_string()
+            },]
+        );
+    }
+
+    #[test]
+    fn test_parse_source_processes_a_page_without_the_snapshot() {
+        let m = mock("GET", "/pipeline-parsed").expect(0).create();
+        let html = r#"<div class="event"><h2 class="name">Show</h2><span class="start">May 2 @ 7:00 pm</span><a href="/1">x</a></div>
+                      <div class="event"><h2 class="name">Show</h2><span class="start">May 2 @ 7:00 pm</span><a href="/1">x</a></div>
+                      <div class="event">Ad</div>"#;
+
+        let result = parse_source(
+            &source("pipeline-parsed"),
+            html,
+            &[Category::Music],
+            &Settings::default(),
+        )
+        .unwrap();
+
+        m.assert();
+        assert_eq!(result.events.len(), 1);
+        assert!(result.events[0].start.is_some());
+        assert_eq!(
+            (result.parsed, result.nameless, result.duplicates),
+            (3, 1, 1)
+        );
+        assert!(result.changes.is_empty());
+    }
+}
@@ -0,0 +1,216 @@
+//! # Prefs Module
+//!
+//! This module remembers the choices of the last menu session, so the next one starts from
+//! them: the categories chosen, which an empty choice fetches again, and the `--format`,
+//! `--limit`, and `--group-by` the listing was shown with. They are kept in `prefs.json` in
+//! the data directory, rewritten whole each time so a crash never leaves half a file.
+
+use std::fs;
+use std::io;
+use std::path::PathBuf;
+
+use serde_json::{json, Value};
+
+use crate::data_processing::GroupKey;
+use crate::output::{self, Format};
+use crate::snapshot;
+use crate::sources::Category;
+
+/// The remembered choices. Each is `None`, or empty, when nothing was remembered for it.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Prefs {
+    /// The categories last chosen in the menu.
+    pub categories: Vec<Category>,
+    /// How the listing was written.
+    pub format: Option<Format>,
+    /// The most events listed.
+    pub limit: Option<usize>,
+    /// What the listing was grouped by.
+    pub group_by: Option<GroupKey>,
+}
+
+/// The file the preferences are kept in.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PrefsStore {
+    path: PathBuf,
+}
+
+impl PrefsStore {
+    /// Creates a store kept in the given file.
+    pub fn new(path: PathBuf) -> PrefsStore {
+        PrefsStore { path }
+    }
+
+    /// Creates a store in `prefs.json` in the default data directory.
+    pub fn open_default() -> PrefsStore {
+        PrefsStore::new(snapshot::data_dir().join("prefs.json"))
+    }
+
+    /// Loads the preferences, falling back to none with a warning if the file can't be read.
+    pub fn load(&self) -> Prefs {
+        self.read().unwrap_or_else(|e| {
+            log::warn!("ignoring the saved preferences: {}", e);
+            Prefs::default()
+        })
+    }
+
+    /// Reads the preferences.
+    ///
+    /// # Returns
+    ///
+    /// The preferences, none if the file doesn't exist yet, or why it couldn't be read.
+    pub fn read(&self) -> Result<Prefs, String> {
+        let text = match fs::read_to_string(&self.path) {
+            Ok(text) => text,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(Prefs::default()),
+            Err(e) => return Err(format!("{}: {}", self.path.display(), e)),
+        };
+        let json: Value = serde_json::from_str(&text)
+            .map_err(|e| format!("{} is corrupted: {}", self.path.display(), e))?;
+        parse_prefs(&json).map_err(|e| format!("{}: {}", self.path.display(), e))
+    }
+
+    /// Saves the preferences, replacing the file in one step.
+    pub fn save(&self, prefs: &Prefs) -> io::Result<()> {
+        let categories: Vec<String> = prefs.categories.iter().map(ToString::to_string).collect();
+        let json = json!({
+            "categories": categories,
+            "format": prefs.format.map(|format| format.to_string()),
+            "limit": prefs.limit,
+            "group_by": prefs.group_by.map(|group| group.to_string()),
+        });
+        output::save_output(&self.path, json.to_string().as_bytes(), true)
+    }
+}
+
+/// Reads the preferences from the file's JSON, or says which entry is wrong.
+fn parse_prefs(json: &Value) -> Result<Prefs, String> {
+    let Value::Object(entries) = json else {
+        return Err("expected a JSON object".to_string());
+    };
+    let text = |key: &str| match &entries.get(key) {
+        None | Some(Value::Null) => Ok(None),
+        Some(Value::String(value)) => Ok(Some(value.as_str())),
+        Some(other) => Err(format!("{}: expected text, found {}", key, other)),
+    };
+    let categories = match entries.get("categories") {
+        None | Some(Value::Null) => Vec::new(),
+        Some(Value::Array(items)) => items
+            .iter()
+            .map(|item| {
+                item.as_str()
+                    .ok_or_else(|| format!("categories: expected text, found {}", item))?
+                    .parse::<Category>()
+            })
+            .collect::<Result<_, _>>()
+            .map_err(|e| format!("categories: {}", e))?,
+        Some(other) => return Err(format!("categories: expected a list, found {}", other)),
+    };
+    let limit = match entries.get("limit") {
+        None | Some(Value::Null) => None,
+        Some(value) => Some(
+            value
+                .as_u64()
+                .filter(|limit| *limit > 0)
+                .ok_or_else(|| format!("limit: expected a positive number, found {}", value))?
+                as usize,
+        ),
+    };
+    Ok(Prefs {
+        categories,
+        format: text("format")?
+            .map(str::parse)
+            .transpose()
+            .map_err(|e| format!("format: {}", e))?,
+        limit,
+        group_by: text("group_by")?
+            .map(str::parse)
+            .transpose()
+            .map_err(|e| format!("group_by: {}", e))?,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::env;
+
+    fn temp_store(name: &str) -> PrefsStore {
+        let dir = env::temp_dir().join(format!(
+            "event_finder_prefs_{}_{}",
+            name,
+            std::process::id()
+        ));
+        let _ = fs::remove_dir_all(&dir);
+        PrefsStore::new(dir.join("prefs.json"))
+    }
+
+    #[test]
+    fn test_prefs_round_trip() {
+        let store = temp_store("round_trip");
+        assert_eq!(store.read(), Ok(Prefs::default()));
+
+        let prefs = Prefs {
+            categories: vec![Category::Music, Category::Unique],
+            format: Some(Format::Markdown),
+            limit: Some(10),
+            group_by: Some(GroupKey::Venue),
+        };
+        store.save(&prefs).unwrap();
+        assert_eq!(store.read(), Ok(prefs));
+        // Saved as the flags spell them, which older files have too
+        let saved = fs::read_to_string(&store.path).unwrap();
+        assert!(
+            saved.contains(r#""format":"markdown""#) && saved.contains(r#""group_by":"venue""#),
+            "{}",
+            saved
+        );
+
+        let nothing = Prefs {
+            categories: vec![Category::General],
+            ..Default::default()
+        };
+        store.save(&nothing).unwrap();
+        assert_eq!(store.load(), nothing);
+        // Nothing is left behind by the atomic write
+        assert_eq!(
+            fs::read_dir(store.path.parent().unwrap()).unwrap().count(),
+            1
+        );
+    }
+
+    #[test]
+    fn test_corrupted_prefs_fall_back_to_none() {
+        let store = temp_store("corrupted");
+        fs::create_dir_all(store.path.parent().unwrap()).unwrap();
+
+        for (text, error) in [
+            ("{\"format\": \"json\", \"lim", "is corrupted"),
+            ("[1, 2]", "expected a JSON object"),
+            ("{\"format\": \"xml\"}", "format: unknown format 'xml'"),
+            (
+                "{\"limit\": 0}",
+                "limit: expected a positive number, found 0",
+            ),
+            (
+                "{\"categories\": [\"music\", \"jazz\"]}",
+                "categories: unknown category 'jazz'",
+            ),
+            ("{\"group_by\": 3}", "group_by: expected text, found 3"),
+        ] {
+            fs::write(&store.path, text).unwrap();
+            let e = store.read().unwrap_err();
+            assert!(e.contains(error), "{:?}: {}", text, e);
+            assert_eq!(store.load(), Prefs::default());
+        }
+
+        // Saving replaces a corrupted file
+        store
+            .save(&Prefs {
+                limit: Some(3),
+                ..Default::default()
+            })
+            .unwrap();
+        assert_eq!(store.read().unwrap().limit, Some(3));
+    }
+}
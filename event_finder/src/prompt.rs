@@ -0,0 +1,71 @@
+//! # Prompt Module
+//!
+//! This module reads the user's answers to the menu's prompts. The menu reads through
+//! `LineReader`, so it can be given lines without a terminal: `Terminal` reads stdin, and
+//! any `BufRead`, such as a byte string, stands in for it.
+
+use std::io::{self, BufRead, Write};
+
+use crate::interrupt;
+
+/// Where the menu's answers come from.
+pub trait LineReader {
+    /// Reads the next answer, after flushing any prompt printed without a newline.
+    ///
+    /// # Returns
+    ///
+    /// The line, `None` once there are no more, or the error reading it.
+    fn read_line(&mut self) -> io::Result<Option<String>>;
+}
+
+/// Reads the answers typed at stdin. There are no more at the end of input, e.g. after
+/// Ctrl-D or at the end of a pipe, or once Ctrl-C is pressed.
+pub struct Terminal;
+
+impl LineReader for Terminal {
+    fn read_line(&mut self) -> io::Result<Option<String>> {
+        io::stdout().flush()?;
+        match interrupt::read_stdin_line() {
+            Ok(line) if line.is_empty() => Ok(None),
+            Ok(line) => Ok(Some(line)),
+            Err(e) if e.kind() == io::ErrorKind::Interrupted => Ok(None),
+            Err(e) => Err(e),
+        }
+    }
+}
+
+impl<R: BufRead> LineReader for R {
+    fn read_line(&mut self) -> io::Result<Option<String>> {
+        let mut line = String::new();
+        match BufRead::read_line(self, &mut line)? {
+            0 => Ok(None),
+            _ => Ok(Some(line)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_lines_end_with_none() {
+        let mut input: &[u8] = b"1\n\nno";
+
+        assert_eq!(
+            LineReader::read_line(&mut input).unwrap().as_deref(),
+            Some("1\n")
+        );
+        assert_eq!(
+            LineReader::read_line(&mut input).unwrap().as_deref(),
+            Some("\n")
+        );
+        // A last line without its newline is still an answer
+        assert_eq!(
+            LineReader::read_line(&mut input).unwrap().as_deref(),
+            Some("no")
+        );
+        assert_eq!(LineReader::read_line(&mut input).unwrap(), None);
+        assert_eq!(LineReader::read_line(&mut input).unwrap(), None);
+    }
+}
@@ -0,0 +1,326 @@
+//! # Seen Module
+//!
+//! This module remembers which events have been listed, so `--new-only` can leave them out
+//! next time. Each event is kept by its stable `ProcessedEvent::id`, with its name, start, and
+//! source and when it was first and last listed, in `seen.json` in the data directory. Events
+//! not listed again within the store's horizon are pruned whenever it is saved.
+//!
+//! Runs at the same time take turns through a lock file next to it: each one reads what is on
+//! disk, adds its own events, and saves, all while holding the lock, so none loses another's.
+
+use std::collections::HashMap;
+use std::fs;
+use std::io;
+use std::path::PathBuf;
+
+use chrono::{Duration, NaiveDateTime};
+use serde_json::{json, Map, Value};
+
+use crate::data_processing::ProcessedEvent;
+use crate::output;
+use crate::snapshot::{self, FileLock};
+
+/// How many days an event is remembered after it was last listed, unless the config file's
+/// `seen_days` says otherwise.
+pub const DEFAULT_HORIZON_DAYS: i64 = 90;
+
+const TIME_FORMAT: &str = "%Y-%m-%dT%H:%M:%S";
+
+/// What is remembered about a listed event.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SeenEvent {
+    pub name: String,
+    pub start_date: String,
+    pub source: Option<String>,
+    /// When the event was first listed.
+    pub first_seen: NaiveDateTime,
+    /// When it was most recently listed, which pruning goes by.
+    pub last_seen: NaiveDateTime,
+}
+
+/// The file of events listed so far, keyed by event id.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SeenStore {
+    path: PathBuf,
+    horizon: Duration,
+}
+
+impl SeenStore {
+    /// Creates a store kept in the given file.
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - The file the events are kept in.
+    /// * `horizon` - How long an event is remembered after it was last listed.
+    pub fn new(path: PathBuf, horizon: Duration) -> SeenStore {
+        SeenStore { path, horizon }
+    }
+
+    /// Creates a store in `seen.json` in the default data directory.
+    pub fn open_default(horizon: Duration) -> SeenStore {
+        SeenStore::new(snapshot::data_dir().join("seen.json"), horizon)
+    }
+
+    /// Loads the events listed so far.
+    ///
+    /// A missing or unreadable file yields none, as it just means nothing was listed yet.
+    pub fn load(&self) -> HashMap<String, SeenEvent> {
+        let json = fs::read_to_string(&self.path)
+            .ok()
+            .and_then(|text| serde_json::from_str::<Value>(&text).ok());
+        let Some(Value::Object(entries)) = json else {
+            return HashMap::new();
+        };
+        entries
+            .iter()
+            .filter_map(|(id, entry)| Some((id.clone(), seen_event(entry)?)))
+            .collect()
+    }
+
+    /// Remembers events as listed at `now`, and forgets those not listed within the horizon.
+    ///
+    /// # Returns
+    ///
+    /// How many of the events weren't remembered before, or the error reading or saving the
+    /// store.
+    pub fn mark(&self, events: &[ProcessedEvent], now: NaiveDateTime) -> io::Result<usize> {
+        let _lock = FileLock::acquire(&self.path)?;
+        let mut seen = self.load();
+        let mut added = 0;
+        for event in events {
+            let first_seen = match seen.get(&event.id()) {
+                Some(known) => known.first_seen,
+                None => {
+                    added += 1;
+                    now
+                }
+            };
+            let entry = SeenEvent {
+                name: event.name.clone(),
+                start_date: event.start_date.clone(),
+                source: event.source.clone(),
+                first_seen,
+                last_seen: now,
+            };
+            seen.insert(event.id(), entry);
+        }
+        seen.retain(|_, entry| entry.last_seen > now - self.horizon);
+        self.save(&seen)?;
+        Ok(added)
+    }
+
+    /// Forgets every event listed so far.
+    ///
+    /// # Returns
+    ///
+    /// How many events were forgotten, or the error removing the file.
+    pub fn reset(&self) -> io::Result<usize> {
+        let _lock = FileLock::acquire(&self.path)?;
+        let forgotten = self.load().len();
+        match fs::remove_file(&self.path) {
+            Err(e) if e.kind() != io::ErrorKind::NotFound => Err(e),
+            _ => Ok(forgotten),
+        }
+    }
+
+    fn save(&self, seen: &HashMap<String, SeenEvent>) -> io::Result<()> {
+        let entries: Map<String, Value> = seen
+            .iter()
+            .map(|(id, entry)| {
+                let value = json!({
+                    "name": entry.name,
+                    "start_date": entry.start_date,
+                    "source": entry.source,
+                    "first_seen": entry.first_seen.format(TIME_FORMAT).to_string(),
+                    "last_seen": entry.last_seen.format(TIME_FORMAT).to_string(),
+                });
+                (id.clone(), value)
+            })
+            .collect();
+        output::save_output(
+            &self.path,
+            Value::Object(entries).to_string().as_bytes(),
+            true,
+        )
+    }
+}
+
+/// Reads one entry of the store, or `None` if it is malformed.
+fn seen_event(entry: &Value) -> Option<SeenEvent> {
+    let time = |key: &str| NaiveDateTime::parse_from_str(entry[key].as_str()?, TIME_FORMAT).ok();
+    Some(SeenEvent {
+        name: entry["name"].as_str()?.to_string(),
+        start_date: entry["start_date"].as_str().unwrap_or_default().to_string(),
+        source: entry["source"].as_str().map(String::from),
+        first_seen: time("first_seen")?,
+        last_seen: time("last_seen")?,
+    })
+}
+
+/// Leaves out the events already listed.
+///
+/// # Arguments
+///
+/// * `events` - The events to filter.
+/// * `seen` - The events listed so far, from `SeenStore::load`.
+///
+/// # Returns
+///
+/// The events not in `seen`, in order, and how many were left out.
+pub fn unseen(
+    events: Vec<ProcessedEvent>,
+    seen: &HashMap<String, SeenEvent>,
+) -> (Vec<ProcessedEvent>, usize) {
+    let total = events.len();
+    let kept: Vec<ProcessedEvent> = events
+        .into_iter()
+        .filter(|event| !seen.contains_key(&event.id()))
+        .collect();
+    let hidden = total - kept.len();
+    (kept, hidden)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::env;
+    use std::thread;
+
+    fn temp_store(name: &str) -> SeenStore {
+        let dir =
+            env::temp_dir().join(format!("event_finder_seen_{}_{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        SeenStore::new(dir.join("seen.json"), Duration::days(DEFAULT_HORIZON_DAYS))
+    }
+
+    fn event(name: &str, url: &str) -> ProcessedEvent {
+        ProcessedEvent {
+            name: name.to_string(),
+            url: url.to_string(),
+            start_date: "May 2".to_string(),
+            ..Default::default()
+        }
+    }
+
+    fn at(day: u32) -> NaiveDateTime {
+        chrono::NaiveDate::from_ymd_opt(2026, 1, 1)
+            .unwrap()
+            .and_hms_opt(9, 0, 0)
+            .unwrap()
+            + Duration::days(i64::from(day))
+    }
+
+    #[test]
+    fn test_first_run_sees_everything_as_new() {
+        let store = temp_store("first");
+        let events = vec![
+            event("Bluegrass Night", "https://example.com/1"),
+            event("Jazz Jam", "https://example.com/2"),
+        ];
+
+        assert!(store.load().is_empty());
+        assert_eq!(unseen(events.clone(), &store.load()), (events.clone(), 0));
+        assert_eq!(store.mark(&events, at(0)).unwrap(), 2);
+
+        let seen = store.load();
+        assert_eq!(seen[&events[0].id()].name, "Bluegrass Night");
+        assert_eq!(seen[&events[0].id()].first_seen, at(0));
+        assert!(!store.path.with_extension("lock").exists());
+        let _ = fs::remove_dir_all(store.path.parent().unwrap());
+    }
+
+    #[test]
+    fn test_repeat_run_hides_what_was_listed() {
+        let store = temp_store("repeat");
+        let yesterday = vec![event("Bluegrass Night", "https://example.com/1")];
+        store.mark(&yesterday, at(0)).unwrap();
+        let today = vec![
+            event("Bluegrass Night", "https://example.com/1?utm_source=rss"),
+            event("Jazz Jam", "https://example.com/2"),
+        ];
+
+        assert_eq!(
+            unseen(today.clone(), &store.load()),
+            (vec![today[1].clone()], 1)
+        );
+        assert_eq!(store.mark(&today, at(1)).unwrap(), 1);
+
+        let seen = store.load();
+        assert_eq!(
+            (
+                seen[&today[0].id()].first_seen,
+                seen[&today[0].id()].last_seen
+            ),
+            (at(0), at(1))
+        );
+        assert_eq!(store.reset().unwrap(), 2);
+        assert!(store.load().is_empty());
+        assert_eq!(store.reset().unwrap(), 0);
+        let _ = fs::remove_dir_all(store.path.parent().unwrap());
+    }
+
+    #[test]
+    fn test_old_entries_are_pruned() {
+        let store = SeenStore {
+            horizon: Duration::days(30),
+            ..temp_store("prune")
+        };
+        store
+            .mark(&[event("Old Show", "https://example.com/old")], at(0))
+            .unwrap();
+        store
+            .mark(
+                &[event("Recent Show", "https://example.com/recent")],
+                at(20),
+            )
+            .unwrap();
+
+        store.mark(&[], at(35)).unwrap();
+
+        let names: Vec<String> = store.load().into_values().map(|entry| entry.name).collect();
+        assert_eq!(names, vec!["Recent Show".to_string()]);
+        let _ = fs::remove_dir_all(store.path.parent().unwrap());
+    }
+
+    #[test]
+    fn test_concurrent_runs_keep_each_others_events() {
+        let store = temp_store("concurrent");
+        let runs: Vec<_> = (0..4)
+            .map(|run| {
+                let store = store.clone();
+                thread::spawn(move || {
+                    for i in 0..5 {
+                        store
+                            .mark(
+                                &[event("Show", &format!("https://example.com/{}/{}", run, i))],
+                                at(0),
+                            )
+                            .unwrap();
+                    }
+                })
+            })
+            .collect();
+        for run in runs {
+            run.join().unwrap();
+        }
+
+        assert_eq!(store.load().len(), 20);
+        let _ = fs::remove_dir_all(store.path.parent().unwrap());
+    }
+
+    #[test]
+    fn test_corrupt_store_is_empty() {
+        let store = temp_store("corrupt");
+        fs::create_dir_all(store.path.parent().unwrap()).unwrap();
+        fs::write(&store.path, "not json").unwrap();
+
+        assert!(store.load().is_empty());
+        assert_eq!(
+            store
+                .mark(&[event("Show", "https://example.com/1")], at(0))
+                .unwrap(),
+            1
+        );
+        let _ = fs::remove_dir_all(store.path.parent().unwrap());
+    }
+}
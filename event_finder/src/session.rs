@@ -0,0 +1,352 @@
+//! # Session Module
+//!
+//! This module keeps what an interactive session has fetched, so choosing another category
+//! doesn't fetch a source again, and the listing on screen can be sorted, filtered, and
+//! grouped again without a request. `refresh` forgets what was fetched and fetches anew.
+
+use std::collections::HashMap;
+
+use event_finder::data_processing::{self, GroupKey, Match, Order, ProcessedEvent, SortKey};
+use event_finder::diff::EventDiff;
+use event_finder::pipeline::SourceEvents;
+use event_finder::sources::{Categories, Category, Source};
+
+use crate::cli::Options;
+
+/// Each source's events as fetched this session, with the categories they were fetched for.
+#[derive(Debug, Clone, Default)]
+pub struct FetchCache {
+    sources: HashMap<String, (Categories, SourceEvents)>,
+}
+
+impl FetchCache {
+    /// Returns the events kept for a source, narrowed to `categories`, if they were fetched
+    /// for every one of them the source serves. Nothing has changed in them since, as their
+    /// snapshot was saved when they were fetched.
+    pub fn get(&self, source: &Source, categories: &[Category]) -> Option<SourceEvents> {
+        let (covered, kept) = self.sources.get(&source.name)?;
+        if !served(source, categories).is_subset(covered) {
+            return None;
+        }
+        let mut kept = kept.clone();
+        kept.changes = EventDiff::default();
+        kept.events = data_processing::filter_by_category(kept.events, categories);
+        Some(kept)
+    }
+
+    /// Keeps a source's events, fetched for `categories`.
+    pub fn put(&mut self, source: &Source, categories: &[Category], events: &SourceEvents) {
+        self.sources.insert(
+            source.name.clone(),
+            (served(source, categories), events.clone()),
+        );
+    }
+
+    /// Forgets every source's events, so the next fetch requests them again.
+    pub fn clear(&mut self) {
+        self.sources.clear();
+    }
+}
+
+/// The categories of `categories` the source serves.
+fn served(source: &Source, categories: &[Category]) -> Categories {
+    categories
+        .iter()
+        .copied()
+        .filter(|category| source.categories.contains(category))
+        .collect()
+}
+
+/// How the events on screen are listed again, over the command-line options.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct View {
+    /// The order, instead of `--sort` and `--desc`.
+    pub sort: Option<(SortKey, Order)>,
+    /// The grouping, instead of `--group-by`; `Some(None)` lists them ungrouped.
+    pub group_by: Option<Option<GroupKey>>,
+    /// The words every listed event must mention.
+    pub filter: Vec<String>,
+}
+
+/// A command typed after a listing to list the same events another way, or fetch them again.
+#[derive(Debug, Clone, PartialEq)]
+pub enum ViewCommand {
+    /// `sort KEY [desc]`
+    Sort(SortKey, Order),
+    /// `group KEY`, or `group none`
+    Group(Option<GroupKey>),
+    /// `filter WORDS`, or a bare `filter` to list every event again
+    Filter(Vec<String>),
+    /// `refresh`
+    Refresh,
+}
+
+/// Parses a view command.
+///
+/// # Arguments
+///
+/// * `input` - The user's answer.
+///
+/// # Returns
+///
+/// `None` if the answer isn't a view command, else the command or why it can't be used.
+pub fn parse_command(input: &str) -> Option<Result<ViewCommand, String>> {
+    let mut words = input.split_whitespace();
+    let command = words.next()?.to_lowercase();
+    let rest: Vec<&str> = words.collect();
+    let parsed = match (command.as_str(), rest.as_slice()) {
+        ("refresh", []) => Ok(ViewCommand::Refresh),
+        ("sort", [key]) => key
+            .parse()
+            .map(|key| ViewCommand::Sort(key, Order::Ascending)),
+        ("sort", [key, order]) => match order.to_lowercase().as_str() {
+            "asc" => key
+                .parse()
+                .map(|key| ViewCommand::Sort(key, Order::Ascending)),
+            "desc" => key
+                .parse()
+                .map(|key| ViewCommand::Sort(key, Order::Descending)),
+            _ => Err(format!("unknown order '{}' (expected asc or desc)", order)),
+        },
+        ("sort", _) => {
+            Err("sort needs a key: date, name, location, or score, then asc or desc".to_string())
+        }
+        ("group", [key]) if key.eq_ignore_ascii_case("none") => Ok(ViewCommand::Group(None)),
+        ("group", [key]) => key.parse().map(|key| ViewCommand::Group(Some(key))),
+        ("group", _) => Err("group needs a key: date, venue, source, or none".to_string()),
+        ("filter", words) => Ok(ViewCommand::Filter(
+            words.iter().map(|word| word.to_string()).collect(),
+        )),
+        _ => return None,
+    };
+    Some(parsed)
+}
+
+/// The state of an interactive session.
+#[derive(Debug, Clone, Default)]
+pub struct Session {
+    /// What was fetched, which later choices reuse.
+    pub cache: FetchCache,
+    /// The categories of the last fetch, which `refresh` fetches again.
+    pub categories: Vec<Category>,
+    /// The changes and events of the last fetch.
+    pub last_fetch: Option<(EventDiff, Vec<ProcessedEvent>)>,
+    /// The events as last listed, which `o N` counts through.
+    pub listed: Vec<ProcessedEvent>,
+    /// How the events are listed again.
+    pub view: View,
+}
+
+impl Session {
+    /// Records a fetch of `categories`, whose events are listed next.
+    pub fn fetched(
+        &mut self,
+        categories: &[Category],
+        changes: EventDiff,
+        events: Vec<ProcessedEvent>,
+    ) {
+        self.categories = categories.to_vec();
+        self.last_fetch = Some((changes, events));
+    }
+
+    /// Applies a view command; `refresh` forgets what was fetched, and is left to the caller
+    /// to fetch again.
+    pub fn apply(&mut self, command: ViewCommand) {
+        match command {
+            ViewCommand::Sort(key, order) => self.view.sort = Some((key, order)),
+            ViewCommand::Group(key) => self.view.group_by = Some(key),
+            ViewCommand::Filter(words) => self.view.filter = words,
+            ViewCommand::Refresh => self.cache.clear(),
+        }
+    }
+
+    /// Returns the events of the last fetch to list: those mentioning every filter word, in
+    /// the view's order.
+    pub fn shown(&self) -> Vec<ProcessedEvent> {
+        let Some((_, events)) = &self.last_fetch else {
+            return Vec::new();
+        };
+        let mut events = if self.view.filter.is_empty() {
+            events.clone()
+        } else {
+            data_processing::search_events(events.clone(), &self.view.filter, Match::All)
+        };
+        if let Some((key, order)) = self.view.sort {
+            data_processing::sort_events(&mut events, key, order);
+        }
+        events
+    }
+
+    /// Returns the options to list the events with, with the view's order and grouping.
+    pub fn options(&self, options: &Options) -> Options {
+        let mut options = options.clone();
+        if let Some((key, order)) = self.view.sort {
+            options.sort = key;
+            options.order = order;
+        }
+        if let Some(key) = self.view.group_by {
+            options.group_by = key;
+        }
+        options
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use event_finder::sources::default_sources;
+
+    fn event(name: &str, location: &str, category: Category) -> ProcessedEvent {
+        ProcessedEvent {
+            name: name.to_string(),
+            location: Some(location.to_string()),
+            categories: Categories::from([category]),
+            ..Default::default()
+        }
+    }
+
+    fn names(events: &[ProcessedEvent]) -> Vec<&str> {
+        events.iter().map(|e| e.name.as_str()).collect()
+    }
+
+    #[test]
+    fn test_cache_reuses_sources_fetched_for_the_categories() {
+        let mut source = default_sources()
+            .into_iter()
+            .find(|s| s.name == "songkick")
+            .unwrap();
+        source.categories = Categories::from([Category::Music, Category::General]);
+        let fetched = SourceEvents {
+            events: vec![
+                event("Jazz Night", "Ryman", Category::Music),
+                event("Book Fair", "Library", Category::General),
+            ],
+            ..Default::default()
+        };
+        let mut cache = FetchCache::default();
+        assert_eq!(cache.get(&source, &[Category::Music]), None);
+
+        cache.put(
+            &source,
+            &[Category::Music],
+            &SourceEvents {
+                events: vec![fetched.events[0].clone()],
+                ..Default::default()
+            },
+        );
+        assert_eq!(
+            names(&cache.get(&source, &[Category::Music]).unwrap().events),
+            ["Jazz Night"]
+        );
+        // Unique is a category the source doesn't serve, so nothing is missing
+        assert!(cache
+            .get(&source, &[Category::Music, Category::Unique])
+            .is_some());
+        // General events weren't fetched
+        assert_eq!(cache.get(&source, &Category::ALL), None);
+
+        cache.put(&source, &Category::ALL, &fetched);
+        assert_eq!(
+            names(&cache.get(&source, &[Category::General]).unwrap().events),
+            ["Book Fair"]
+        );
+        assert_eq!(cache.get(&source, &Category::ALL).unwrap(), fetched);
+
+        cache.clear();
+        assert_eq!(cache.get(&source, &[Category::Music]), None);
+    }
+
+    #[test]
+    fn test_parse_command() {
+        assert_eq!(
+            parse_command("sort venue"),
+            Some(Ok(ViewCommand::Sort(SortKey::Location, Order::Ascending)))
+        );
+        assert_eq!(
+            parse_command("Sort name DESC"),
+            Some(Ok(ViewCommand::Sort(SortKey::Name, Order::Descending)))
+        );
+        assert_eq!(
+            parse_command("group source"),
+            Some(Ok(ViewCommand::Group(Some(GroupKey::Source))))
+        );
+        assert_eq!(
+            parse_command("group none"),
+            Some(Ok(ViewCommand::Group(None)))
+        );
+        assert_eq!(
+            parse_command("filter jazz  trio"),
+            Some(Ok(ViewCommand::Filter(vec![
+                "jazz".to_string(),
+                "trio".to_string()
+            ])))
+        );
+        assert_eq!(
+            parse_command("filter"),
+            Some(Ok(ViewCommand::Filter(Vec::new())))
+        );
+        assert_eq!(parse_command(" refresh\n"), Some(Ok(ViewCommand::Refresh)));
+
+        assert!(parse_command("sort")
+            .unwrap()
+            .unwrap_err()
+            .starts_with("sort needs a key"));
+        assert!(parse_command("sort price")
+            .unwrap()
+            .unwrap_err()
+            .starts_with("unknown sort key 'price'"));
+        assert!(parse_command("sort name sideways")
+            .unwrap()
+            .unwrap_err()
+            .starts_with("unknown order 'sideways'"));
+        assert!(parse_command("group").unwrap().is_err());
+        for other in ["", "yes", "d", "o 2", "refresh now", "sorted"] {
+            assert_eq!(parse_command(other), None, "{:?}", other);
+        }
+    }
+
+    #[test]
+    fn test_view_commands_relist_the_last_fetch() {
+        let mut session = Session::default();
+        assert!(session.shown().is_empty());
+        let events = vec![
+            event("Jazz Trio", "The Station Inn", Category::Music),
+            event("Bluegrass Jam", "Bluebird Cafe", Category::Music),
+            event("Late Jazz", "Alley Taps", Category::Music),
+        ];
+        session.fetched(&[Category::Music], EventDiff::default(), events);
+        assert_eq!(
+            names(&session.shown()),
+            ["Jazz Trio", "Bluegrass Jam", "Late Jazz"]
+        );
+
+        session.apply(ViewCommand::Sort(SortKey::Location, Order::Ascending));
+        assert_eq!(
+            names(&session.shown()),
+            ["Late Jazz", "Bluegrass Jam", "Jazz Trio"]
+        );
+        session.apply(ViewCommand::Filter(vec!["jazz".to_string()]));
+        assert_eq!(names(&session.shown()), ["Late Jazz", "Jazz Trio"]);
+        session.apply(ViewCommand::Sort(SortKey::Name, Order::Descending));
+        assert_eq!(names(&session.shown()), ["Late Jazz", "Jazz Trio"]);
+        session.apply(ViewCommand::Filter(Vec::new()));
+        assert_eq!(session.shown().len(), 3);
+
+        session.apply(ViewCommand::Group(Some(GroupKey::Venue)));
+        let options = session.options(&Options::default());
+        assert_eq!(
+            (options.sort, options.order, options.group_by),
+            (SortKey::Name, Order::Descending, Some(GroupKey::Venue))
+        );
+        session.apply(ViewCommand::Group(None));
+        assert_eq!(
+            session
+                .options(&Options {
+                    group_by: Some(GroupKey::Date),
+                    ..Default::default()
+                })
+                .group_by,
+            None
+        );
+    }
+}
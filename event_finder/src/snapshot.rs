@@ -0,0 +1,209 @@
+//! # Snapshot Module
+//!
+//! This module persists the most recent fetch of each source to disk so the next run can
+//! report what changed. Snapshots are stored as one JSON file per source in the data
+//! directory (`$EVENT_FINDER_DATA_DIR`, or `~/.local/share/event_finder` by default).
+
+use std::env;
+use std::fs::{self, OpenOptions};
+use std::io;
+use std::path::{Path, PathBuf};
+use std::thread;
+use std::time::{Duration, Instant, SystemTime};
+
+use serde_json::Value;
+
+use crate::data_processing::ProcessedEvent;
+
+/// A directory holding the last fetched events of each source.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SnapshotStore {
+    dir: PathBuf,
+}
+
+impl SnapshotStore {
+    /// Creates a store rooted at the given directory.
+    pub fn new(dir: PathBuf) -> SnapshotStore {
+        SnapshotStore { dir }
+    }
+
+    /// Creates a store in the default data directory.
+    pub fn open_default() -> SnapshotStore {
+        SnapshotStore::new(data_dir())
+    }
+
+    /// Loads the last saved events of a source.
+    ///
+    /// A missing or unreadable snapshot yields an empty list, since it just means there is
+    /// nothing to compare against yet.
+    pub fn load(&self, source: &str) -> Vec<ProcessedEvent> {
+        self.get(source).unwrap_or_default()
+    }
+
+    /// Returns the last saved events of a source, or `None` if no readable snapshot exists.
+    pub fn get(&self, source: &str) -> Option<Vec<ProcessedEvent>> {
+        fs::read_to_string(self.path(source))
+            .ok()
+            .and_then(|text| serde_json::from_str::<Value>(&text).ok())
+            .and_then(|json| {
+                json.as_array()
+                    .map(|items| items.iter().filter_map(ProcessedEvent::from_json).collect())
+            })
+    }
+
+    /// Saves the events of a source, replacing the previous snapshot.
+    pub fn save(&self, source: &str, events: &[ProcessedEvent]) -> io::Result<()> {
+        fs::create_dir_all(&self.dir)?;
+        fs::write(self.path(source), serde_json::to_string(events)?)
+    }
+
+    fn path(&self, source: &str) -> PathBuf {
+        let file_name: String = source
+            .chars()
+            .map(|c| {
+                if c.is_ascii_alphanumeric() || c == '-' {
+                    c
+                } else {
+                    '_'
+                }
+            })
+            .collect();
+        self.dir.join(format!("snapshot-{}.json", file_name))
+    }
+}
+
+/// How long a run waits for another to let go of a `FileLock`.
+const LOCK_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// How old a lock file must be to be taken for one left behind by a run that crashed.
+const STALE_LOCK: Duration = Duration::from_secs(60);
+
+/// A lock file next to a file in the data directory, so runs at the same time take turns
+/// reading and rewriting it. The lock file is removed when this is dropped.
+pub(crate) struct FileLock {
+    path: PathBuf,
+}
+
+impl FileLock {
+    /// Takes the lock on `file`, waiting for another run to let go of it.
+    ///
+    /// # Returns
+    ///
+    /// The lock, or an error if another run held it for `LOCK_TIMEOUT`.
+    pub(crate) fn acquire(file: &Path) -> io::Result<FileLock> {
+        let path = file.with_extension("lock");
+        if let Some(parent) = path
+            .parent()
+            .filter(|parent| !parent.as_os_str().is_empty())
+        {
+            fs::create_dir_all(parent)?;
+        }
+        let started = Instant::now();
+        loop {
+            match OpenOptions::new().write(true).create_new(true).open(&path) {
+                Ok(_) => return Ok(FileLock { path }),
+                Err(e) if e.kind() == io::ErrorKind::AlreadyExists => {
+                    let age = fs::metadata(&path)
+                        .and_then(|m| m.modified())
+                        .ok()
+                        .and_then(|t| SystemTime::now().duration_since(t).ok());
+                    if age.is_some_and(|age| age > STALE_LOCK) {
+                        let _ = fs::remove_file(&path);
+                    } else if started.elapsed() > LOCK_TIMEOUT {
+                        return Err(io::Error::other(format!(
+                            "{} is held by another run (delete it if none is running)",
+                            path.display()
+                        )));
+                    } else {
+                        thread::sleep(Duration::from_millis(20));
+                    }
+                }
+                Err(e) => return Err(e),
+            }
+        }
+    }
+}
+
+impl Drop for FileLock {
+    fn drop(&mut self) {
+        let _ = fs::remove_file(&self.path);
+    }
+}
+
+/// Returns the directory used for persistent application data.
+pub fn data_dir() -> PathBuf {
+    if let Some(dir) = env::var_os("EVENT_FINDER_DATA_DIR") {
+        return PathBuf::from(dir);
+    }
+    let home = env::var_os("HOME")
+        .or_else(|| env::var_os("USERPROFILE"))
+        .unwrap_or_default();
+    PathBuf::from(home)
+        .join(".local")
+        .join("share")
+        .join("event_finder")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::html_parser::EventStatus;
+    use crate::sources::{Categories, Category};
+    use chrono::NaiveDateTime;
+
+    fn temp_store(name: &str) -> SnapshotStore {
+        let dir = env::temp_dir().join(format!(
+            "event_finder_snapshot_{}_{}",
+            name,
+            std::process::id()
+        ));
+        let _ = fs::remove_dir_all(&dir);
+        SnapshotStore::new(dir)
+    }
+
+    #[test]
+    fn test_snapshot_round_trip() {
+        let store = temp_store("round_trip");
+        let events = vec![ProcessedEvent {
+            name: "Show".to_string(),
+            start_date: "May 2".to_string(),
+            end_date: None,
+            start: NaiveDateTime::parse_from_str("2024-05-02T19:00:00", "%Y-%m-%dT%H:%M:%S").ok(),
+            end: None,
+            location: Some("Ryman".to_string()),
+            url: "http://example.com/show".to_string(),
+            description: Some("A show".to_string()),
+            price: Some("$10.00".to_string()),
+            image_url: None,
+            categories: Categories::from([Category::Music, Category::Unique]),
+            source: Some("nashville.com".to_string()),
+            status: EventStatus::SoldOut,
+            tags: vec!["bluegrass".to_string()],
+            alt_urls: vec!["http://example.org/show".to_string()],
+            score: 0,
+            matched_interests: Vec::new(),
+            distance: None,
+        }];
+
+        store.save("nashville.com", &events).unwrap();
+
+        assert_eq!(store.load("nashville.com"), events);
+        let saved: Value =
+            serde_json::from_str(&fs::read_to_string(store.path("nashville.com")).unwrap())
+                .unwrap();
+        assert_eq!(saved[0]["id"], events[0].id());
+        assert!(store.load("songkick").is_empty());
+        assert_eq!(store.get("songkick"), None);
+        let _ = fs::remove_dir_all(&store.dir);
+    }
+
+    #[test]
+    fn test_snapshot_load_ignores_corrupt_file() {
+        let store = temp_store("corrupt");
+        fs::create_dir_all(&store.dir).unwrap();
+        fs::write(store.path("perto"), "not json").unwrap();
+
+        assert!(store.load("perto").is_empty());
+        let _ = fs::remove_dir_all(&store.dir);
+    }
+}
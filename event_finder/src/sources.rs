@@ -0,0 +1,2280 @@
+//! # Sources Module
+//!
+//! This module defines the registry of event sources the application knows about.
+//! Each source pairs a URL and `SiteConfig` with the set of categories it belongs to,
+//! so a single source can serve several categories while still being fetched only once per run.
+//! The registry is read from the user's sources file (see `config::sources_path`), a list of
+//! `[[source]]` entries in the settings file's TOML subset, or is the built-in
+//! `DEFAULT_SOURCES` when there is none.
+//!
+//! A run fetches `EventSource`s, which `registry` builds from the entries it selected: a
+//! `ScrapedSource` reads pages, feeds, and calendars with its `ParseStrategy`, and a kind of
+//! source that needs more, such as Ticketmaster's API with its key, implements the trait in
+//! its own module. `follow_pages` paginates for them all, and one path in `pipeline` fetches,
+//! snapshots, and reports on them alike.
+
+use std::collections::{BTreeSet, HashSet};
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::Path;
+use std::str::FromStr;
+use std::thread;
+use std::time::Duration;
+
+use chrono::NaiveDate;
+use reqwest::StatusCode;
+use url::Url;
+
+use crate::city::{self, City};
+use crate::data_processing::TRANSFORMABLE_FIELDS;
+use crate::error::Error;
+use crate::html_parser::{
+    self, ConfigError, Event, ParseStrategy, ParsedPage, SiteConfig, WarningKind,
+};
+use crate::timezone::Zone;
+use crate::web_requests::{FetchError, WebClient};
+use crate::{config, feed, ics, ticketmaster};
+
+/// An event category a source can be listed under, or `All` of them.
+///
+/// `All` is only ever asked for: sources and events are listed under the others, and
+/// `Category::members` turns a request into the categories it covers.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Category {
+    /// Concerts and other live music.
+    Music,
+    /// One-off and unusual events.
+    Unique,
+    /// Everything else the city lists.
+    General,
+    /// Every category at once.
+    All,
+}
+
+impl Category {
+    /// Every category sources can be listed under, in menu order.
+    pub const ALL: [Category; 3] = [Category::Music, Category::Unique, Category::General];
+
+    /// Returns every variant, `All` last, in menu order; a variant's menu number is its
+    /// position plus one.
+    pub fn iter() -> impl Iterator<Item = Category> {
+        [
+            Category::Music,
+            Category::Unique,
+            Category::General,
+            Category::All,
+        ]
+        .into_iter()
+    }
+
+    /// Returns the categories a request for this one covers: itself, or every category for
+    /// `All`.
+    pub fn members(self) -> Vec<Category> {
+        match self {
+            Category::All => Category::ALL.to_vec(),
+            category => vec![category],
+        }
+    }
+
+    /// Returns the number the menu lists the category under.
+    pub fn number(self) -> usize {
+        Category::iter()
+            .position(|c| c == self)
+            .map_or(0, |i| i + 1)
+    }
+
+    /// Returns the capitalized name the menu shows.
+    pub fn label(self) -> &'static str {
+        match self {
+            Category::Music => "Music",
+            Category::Unique => "Unique",
+            Category::General => "General",
+            Category::All => "All",
+        }
+    }
+}
+
+impl fmt::Display for Category {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.label().to_lowercase())
+    }
+}
+
+impl FromStr for Category {
+    type Err = String;
+
+    /// Parses a menu number (`1` to `4`), a name, or the start of a name such as `m` or
+    /// `gen`, ignoring case.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let input = s.trim().to_lowercase();
+        Category::iter()
+            .find(|c| {
+                let name = c.to_string();
+                input == c.number().to_string() || (!input.is_empty() && name.starts_with(&input))
+            })
+            .ok_or_else(|| format!("unknown category '{}'", s.trim()))
+    }
+}
+
+/// Parses a menu choice of one or more categories, such as `1,2` or `music unique`.
+///
+/// The choices are separated by commas, spaces, or both, and each is read like a single
+/// category, so `m, u` works too. A choice naming no category is reported without
+/// discarding the others.
+///
+/// # Arguments
+///
+/// * `input` - The user's input.
+///
+/// # Returns
+///
+/// The categories chosen, each once and in menu order with `All` expanded into its members,
+/// and an error for each choice that names no category.
+pub fn parse_selection(input: &str) -> (Vec<Category>, Vec<String>) {
+    let mut chosen = Categories::new();
+    let mut errors = Vec::new();
+    for choice in input
+        .split(|c: char| c == ',' || c.is_whitespace())
+        .filter(|choice| !choice.is_empty())
+    {
+        match choice.parse::<Category>() {
+            Ok(category) => chosen.extend(category.members()),
+            Err(e) => errors.push(e),
+        }
+    }
+    (chosen.into_iter().collect(), errors)
+}
+
+/// The set of categories attached to a source or an event.
+pub type Categories = BTreeSet<Category>;
+
+/// A single event source: where to fetch from, how to parse it, and which categories it serves.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Source {
+    /// The short name events are tagged with and `--source` picks, e.g. `songkick`.
+    pub name: String,
+    /// The listing page fetched first.
+    pub url: String,
+    /// The site's root, which relative links are resolved against.
+    pub base_url: String,
+    /// The categories the source's events are listed under.
+    pub categories: Categories,
+    /// The cities the source lists events for, or empty for every city.
+    pub cities: Vec<String>,
+    /// How the listing is parsed.
+    pub config: SiteConfig,
+}
+
+impl Source {
+    /// Returns `true` if this source serves any of the requested categories.
+    pub fn matches_any(&self, requested: &[Category]) -> bool {
+        requested
+            .iter()
+            .flat_map(|c| c.members())
+            .any(|c| self.categories.contains(&c))
+    }
+
+    /// Checks the source's URLs and its `SiteConfig`.
+    ///
+    /// # Returns
+    ///
+    /// `Ok(())`, or every problem found.
+    pub fn validate(&self) -> Result<(), Vec<ConfigError>> {
+        let mut errors = Vec::new();
+        for (field, url) in [("source url", &self.url), ("base_url", &self.base_url)] {
+            match Url::parse(url) {
+                Ok(parsed) if parsed.scheme() == "http" || parsed.scheme() == "https" => {}
+                Ok(_) => errors.push(ConfigError::new(
+                    field,
+                    format!("'{}' is not an http(s) URL", url),
+                )),
+                Err(e) => errors.push(ConfigError::new(
+                    field,
+                    format!("invalid URL '{}': {}", url, e),
+                )),
+            }
+        }
+        if let Err(config_errors) = self.config.validate() {
+            errors.extend(config_errors);
+        }
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(errors)
+        }
+    }
+}
+
+/// The built-in Nashville sources, written as a sources file. They are used when the user
+/// has no sources file, and `--init-config` writes them out as a starting point.
+pub const DEFAULT_SOURCES: &str = include_str!("default_sources.toml");
+
+/// The sources and cities a sources file defines.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct SourcesFile {
+    /// The valid `[[source]]` entries, in file order, with their URLs not yet templated.
+    pub sources: Vec<Source>,
+    /// The valid `[[city]]` entries, in file order.
+    pub cities: Vec<City>,
+    /// One error per entry that was left out.
+    pub errors: Vec<config::ConfigError>,
+}
+
+impl SourcesFile {
+    /// Picks the city sources are used for.
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - The city asked for with `--city` or the settings file, or `None` for the
+    ///   file's first city.
+    ///
+    /// # Returns
+    ///
+    /// The city, `None` if none was asked for and the file defines none, or a message listing
+    /// the known cities.
+    pub fn city(&self, name: Option<&str>) -> Result<Option<&City>, String> {
+        let Some(name) = name else {
+            return Ok(self.cities.first());
+        };
+        self.cities
+            .iter()
+            .find(|c| c.name.eq_ignore_ascii_case(name))
+            .map(Some)
+            .ok_or_else(|| {
+                let names: Vec<&str> = self.cities.iter().map(|c| c.name.as_str()).collect();
+                match names.is_empty() {
+                    true => format!(
+                        "unknown city '{}' (the sources file has no [[city]] entries)",
+                        name
+                    ),
+                    false => format!(
+                        "unknown city '{}' (known cities: {})",
+                        name,
+                        names.join(", ")
+                    ),
+                }
+            })
+    }
+
+    /// Returns the sources for a city, with their URLs filled in from it.
+    ///
+    /// Sources limited to other cities are left out, as are sources whose URL needs a value
+    /// the city doesn't have.
+    ///
+    /// # Arguments
+    ///
+    /// * `city` - The city picked with `SourcesFile::city`, or `None` if there are no cities.
+    ///
+    /// # Returns
+    ///
+    /// The sources in file order, and one message per source left out for a missing value.
+    pub fn sources_for(&self, city: Option<&City>) -> (Vec<Source>, Vec<String>) {
+        let mut sources = Vec::new();
+        let mut errors = Vec::new();
+        for source in &self.sources {
+            let Some(city) = city else {
+                match city::placeholders(&source.url).first() {
+                    Some(placeholder) => errors.push(format!(
+                        "source '{}': {{{}}} in its url needs a [[city]] entry",
+                        source.name, placeholder
+                    )),
+                    None => sources.push(source.clone()),
+                }
+                continue;
+            };
+            if !source.cities.is_empty()
+                && !source
+                    .cities
+                    .iter()
+                    .any(|c| c.eq_ignore_ascii_case(&city.name))
+            {
+                continue;
+            }
+            match city
+                .expand(&source.url)
+                .and_then(|url| Ok((url, city.expand(&source.base_url)?)))
+            {
+                Ok((url, base_url)) => sources.push(Source {
+                    url,
+                    base_url,
+                    ..source.clone()
+                }),
+                Err(e) => errors.push(format!("source '{}': {}", source.name, e)),
+            }
+        }
+        (sources, errors)
+    }
+}
+
+/// Returns the built-in Nashville sources.
+pub fn default_sources() -> Vec<Source> {
+    let file = builtin_sources();
+    match file.sources_for(file.cities.first()) {
+        (sources, errors) if errors.is_empty() => sources,
+        (_, errors) => panic!(
+            "the built-in sources don't fit the built-in city: {:?}",
+            errors
+        ),
+    }
+}
+
+/// Parses `DEFAULT_SOURCES`.
+fn builtin_sources() -> SourcesFile {
+    match parse_sources(DEFAULT_SOURCES) {
+        Ok(file) if file.errors.is_empty() => file,
+        result => panic!(
+            "the built-in sources file is invalid: {:?}",
+            result.map(|file| file.errors)
+        ),
+    }
+}
+
+/// Loads the user's sources file.
+///
+/// # Arguments
+///
+/// * `path` - The file to read.
+///
+/// # Returns
+///
+/// What the file defines, the built-in sources if the file doesn't exist, or an
+/// `Error::Config` if the file can't be parsed at all (`Error::Io` if it can't be read).
+pub fn load_sources(path: &Path) -> Result<SourcesFile, Error> {
+    match fs::read_to_string(path) {
+        Ok(text) => parse_sources(&text).map_err(|error| Error::Config {
+            path: path.to_path_buf(),
+            error,
+        }),
+        Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(builtin_sources()),
+        Err(e) => Err(Error::Io(io::Error::new(
+            e.kind(),
+            format!("reading sources file {}: {}", path.display(), e),
+        ))),
+    }
+}
+
+/// Parses the text of a sources file, a list of `[[source]]` and `[[city]]` entries.
+///
+/// Each source has a `name`, a listing `url`, an optional `base_url` (the URL's site root by
+/// default), a `category` or `categories`, and the `SiteConfig` fields under their own names,
+/// except that the link selector is `url_selector` and transforms are given per field as
+/// `<field>_transforms = [...]`. `city` or `cities` limits a source to those `[[city]]`
+/// entries, and its URLs may contain the `city::URL_PLACEHOLDERS`, filled in per city.
+///
+/// An entry with a mistake, whether a wrong type, an unknown key, or a config
+/// `Source::validate` rejects, is left out so the others can still be used.
+///
+/// # Arguments
+///
+/// * `text` - The file's contents.
+///
+/// # Returns
+///
+/// The valid entries and one error per entry left out, or the first syntax error if the
+/// file isn't a sources file at all.
+pub fn parse_sources(text: &str) -> Result<SourcesFile, config::ConfigError> {
+    let mut file = SourcesFile::default();
+    let mut source_lines = Vec::new();
+    for table in config::parse_tables(text)? {
+        match (table.name.as_str(), table.is_array) {
+            ("", false) => {
+                if let Some(entry) = table.entries.first() {
+                    return Err(config::ConfigError {
+                        line: entry.line,
+                        message: format!("'{}' must be inside a [[source]] entry", entry.key),
+                    });
+                }
+            }
+            ("source", true) => match parse_source(&table) {
+                Ok(source)
+                    if file
+                        .sources
+                        .iter()
+                        .any(|s| s.name.eq_ignore_ascii_case(&source.name)) =>
+                {
+                    file.errors.push(config::ConfigError {
+                        line: table.line,
+                        message: format!("source '{}' is defined twice", source.name),
+                    });
+                }
+                Ok(source) => {
+                    file.sources.push(source);
+                    let city_entry = table
+                        .entries
+                        .iter()
+                        .find(|e| e.key == "city" || e.key == "cities");
+                    source_lines.push(city_entry.map_or(table.line, |e| e.line));
+                }
+                Err(source_errors) => file.errors.extend(source_errors),
+            },
+            ("city", true) => match city::parse_city(&table) {
+                Ok(city)
+                    if file
+                        .cities
+                        .iter()
+                        .any(|c| c.name.eq_ignore_ascii_case(&city.name)) =>
+                {
+                    file.errors.push(config::ConfigError {
+                        line: table.line,
+                        message: format!("city '{}' is defined twice", city.name),
+                    });
+                }
+                Ok(city) => file.cities.push(city),
+                Err(error) => file.errors.push(error),
+            },
+            _ => {
+                let message = format!(
+                    "unknown table '{}' (expected [[source]] or [[city]])",
+                    table.name
+                );
+                return Err(config::ConfigError {
+                    line: table.line,
+                    message,
+                });
+            }
+        }
+    }
+    // Cities may be defined after the sources naming them, so check those last
+    let sources = std::mem::take(&mut file.sources);
+    for (source, line) in sources.into_iter().zip(source_lines) {
+        match source.cities.iter().find(|name| {
+            !file
+                .cities
+                .iter()
+                .any(|c| c.name.eq_ignore_ascii_case(name))
+        }) {
+            Some(name) => file.errors.push(config::ConfigError {
+                line,
+                message: format!("source '{}': unknown city '{}'", source.name, name),
+            }),
+            None => file.sources.push(source),
+        }
+    }
+    file.errors.sort_by_key(|e| e.line);
+    Ok(file)
+}
+
+/// Reads one `[[source]]` entry and checks it with `Source::validate`.
+///
+/// # Returns
+///
+/// The source, or every problem found, each with the line of the key it is in.
+fn parse_source(table: &config::Table) -> Result<Source, Vec<config::ConfigError>> {
+    let (source, derived_base) = read_source(table).map_err(|e| vec![e])?;
+    // Templated URLs are checked with their placeholders' names in place of values
+    let untemplated = |url: &str| url.replace(['{', '}'], "");
+    let checked = Source {
+        url: untemplated(&source.url),
+        base_url: untemplated(&source.base_url),
+        ..source.clone()
+    };
+    let errors: Vec<config::ConfigError> = match checked.validate() {
+        Ok(()) => return Ok(source),
+        Err(errors) => errors
+            .into_iter()
+            // A derived base URL is only wrong because the source URL is
+            .filter(|e| !(derived_base && e.field == "base_url"))
+            .map(|e| {
+                let key = match e.field {
+                    "source url" => "url",
+                    "url" => "url_selector",
+                    field => field,
+                };
+                let line = table
+                    .entries
+                    .iter()
+                    .find(|entry| {
+                        entry.key == key
+                            || (key == "transforms" && entry.key.ends_with("_transforms"))
+                    })
+                    .map_or(table.line, |entry| entry.line);
+                config::ConfigError {
+                    line,
+                    message: format!("source '{}': {}: {}", source.name, key, e.message),
+                }
+            })
+            .collect(),
+    };
+    Err(errors)
+}
+
+/// Reads the keys of one `[[source]]` entry into a `Source`, without checking its config.
+///
+/// # Returns
+///
+/// The source and whether its base URL was derived from its URL, or the first mistake found.
+fn read_source(table: &config::Table) -> Result<(Source, bool), config::ConfigError> {
+    let (mut name, mut url, mut base_url) = (None, None, None);
+    let mut categories = Categories::new();
+    let mut cities = Vec::new();
+    let mut site = SiteConfig::default();
+    for entry in &table.entries {
+        let category = |name: &str| {
+            name.parse::<Category>()
+                .map_err(|message| config::ConfigError {
+                    line: entry.line,
+                    message,
+                })
+        };
+        match entry.key.as_str() {
+            "name" => name = Some(config::string(entry)?),
+            "url" => url = Some(config::string(entry)?),
+            "base_url" => base_url = Some(config::string(entry)?),
+            "city" => cities.push(config::string(entry)?),
+            "cities" => cities.extend(config::string_array(entry)?),
+            "category" => {
+                categories.extend(category(&config::string(entry)?)?.members());
+            }
+            "categories" => {
+                for name in config::string_array(entry)? {
+                    categories.extend(category(&name)?.members());
+                }
+            }
+            _ => set_site_field(&mut site, entry)?,
+        }
+    }
+    let missing = |key: &str| config::ConfigError {
+        line: table.line,
+        message: format!("[[source]] needs a {}", key),
+    };
+    let name = name.ok_or_else(|| missing("name"))?;
+    let url = url.ok_or_else(|| missing("url"))?;
+    if categories.is_empty() {
+        return Err(missing("category"));
+    }
+    for entry in table
+        .entries
+        .iter()
+        .filter(|e| e.key == "url" || e.key == "base_url")
+    {
+        let text = config::string(entry)?;
+        if let Some(unknown) = city::placeholders(&text)
+            .into_iter()
+            .find(|p| !city::URL_PLACEHOLDERS.contains(p))
+        {
+            let expected = city::URL_PLACEHOLDERS
+                .map(|p| format!("{{{}}}", p))
+                .join(", ");
+            return Err(config::ConfigError {
+                line: entry.line,
+                message: format!(
+                    "unknown placeholder '{{{}}}' in {} (expected {})",
+                    unknown, entry.key, expected
+                ),
+            });
+        }
+    }
+    let derived_base = base_url.is_none();
+    let base_url = base_url
+        .or_else(|| {
+            Url::parse(&url)
+                .ok()
+                .map(|parsed| parsed.origin().ascii_serialization())
+        })
+        .unwrap_or_else(|| url.clone());
+    Ok((
+        Source {
+            name,
+            url,
+            base_url,
+            categories,
+            cities,
+            config: site,
+        },
+        derived_base,
+    ))
+}
+
+/// Sets the `SiteConfig` field a sources-file key names.
+fn set_site_field(site: &mut SiteConfig, entry: &config::Entry) -> Result<(), config::ConfigError> {
+    let key = entry.key.as_str();
+    match key {
+        "strategy" => {
+            site.strategy =
+                config::string(entry)?
+                    .parse()
+                    .map_err(|message| config::ConfigError {
+                        line: entry.line,
+                        message,
+                    })?;
+        }
+        "event_selector" => site.event_selector = config::string(entry)?,
+        "name_selector" => site.name_selector = config::string(entry)?,
+        "start_date_selector" => site.start_date_selector = config::string(entry)?,
+        "url_selector" => site.url = config::string(entry)?,
+        "description_length" => site.description_length = Some(count(entry)?),
+        "max_pages" => site.max_pages = Some(count(entry)?),
+        "max_events" => site.max_events = Some(count(entry)?),
+        "default_duration_minutes" => site.default_duration_minutes = Some(count(entry)?),
+        _ => {
+            if let Some(field) = key
+                .strip_suffix("_transforms")
+                .filter(|field| TRANSFORMABLE_FIELDS.contains(field))
+            {
+                site.transforms
+                    .push((field.to_string(), config::string_array(entry)?));
+            } else if let Some(value) = optional_field(site, key) {
+                *value = Some(config::string(entry)?);
+            } else {
+                return Err(config::ConfigError {
+                    line: entry.line,
+                    message: format!("unknown setting '{}' in [[source]]", key),
+                });
+            }
+        }
+    }
+    Ok(())
+}
+
+/// The sources-file keys of the optional text fields of a `SiteConfig`, in the order
+/// `site_settings` lists them; `optional_field` maps each to its field.
+const OPTIONAL_KEYS: [&str; 27] = [
+    "end_date_selector",
+    "location_selector",
+    "price_selector",
+    "description_selector",
+    "image_selector",
+    "exclude_selector",
+    "date_header_selector",
+    "start_time_selector",
+    "end_time_selector",
+    "status_selector",
+    "tags_selector",
+    "next_page_selector",
+    "name_attr",
+    "start_date_attr",
+    "end_date_attr",
+    "location_attr",
+    "url_attr",
+    "price_attr",
+    "description_attr",
+    "image_attr",
+    "name_regex",
+    "start_date_regex",
+    "end_date_regex",
+    "location_regex",
+    "price_regex",
+    "name_separator",
+    "location_separator",
+];
+
+/// Lists what a `SiteConfig` sets, as the sources-file keys and values that would set it.
+///
+/// Fields left empty or unset are left out, so the list is what a source's entry says rather
+/// than every field there is.
+///
+/// # Arguments
+///
+/// * `site` - The configuration to list.
+///
+/// # Returns
+///
+/// The keys and values, starting with the strategy and the required selectors; transforms are
+/// listed as `<field>_transforms` with their names joined by `, `.
+pub fn site_settings(site: &SiteConfig) -> Vec<(String, String)> {
+    let strategy = match site.strategy {
+        ParseStrategy::Css => "css",
+        ParseStrategy::JsonLd => "json-ld",
+        ParseStrategy::Microdata => "microdata",
+        ParseStrategy::Feed => "feed",
+        ParseStrategy::Ics => "ics",
+        ParseStrategy::JsonApi => "json-api",
+        ParseStrategy::Ticketmaster => "ticketmaster",
+    };
+    let mut settings = vec![("strategy".to_string(), strategy.to_string())];
+    let required = [
+        ("event_selector", &site.event_selector),
+        ("name_selector", &site.name_selector),
+        ("start_date_selector", &site.start_date_selector),
+        ("url_selector", &site.url),
+    ];
+    settings.extend(
+        required
+            .into_iter()
+            .filter(|(_, value)| !value.is_empty())
+            .map(|(key, value)| (key.to_string(), value.clone())),
+    );
+    let mut fields = site.clone();
+    for key in OPTIONAL_KEYS {
+        if let Some(Some(value)) = optional_field(&mut fields, key) {
+            settings.push((key.to_string(), value.clone()));
+        }
+    }
+    let counts = [
+        ("description_length", site.description_length),
+        ("max_pages", site.max_pages),
+        ("max_events", site.max_events),
+        ("default_duration_minutes", site.default_duration_minutes),
+    ];
+    settings.extend(
+        counts
+            .into_iter()
+            .filter_map(|(key, value)| Some((key.to_string(), value?.to_string()))),
+    );
+    settings.extend(
+        site.transforms
+            .iter()
+            .map(|(field, transforms)| (format!("{}_transforms", field), transforms.join(", "))),
+    );
+    settings
+}
+
+/// Returns the optional text field of a `SiteConfig` a sources-file key names.
+fn optional_field<'a>(site: &'a mut SiteConfig, key: &str) -> Option<&'a mut Option<String>> {
+    let field = match key {
+        "end_date_selector" => &mut site.end_date_selector,
+        "location_selector" => &mut site.location_selector,
+        "price_selector" => &mut site.price_selector,
+        "description_selector" => &mut site.description_selector,
+        "image_selector" => &mut site.image_selector,
+        "exclude_selector" => &mut site.exclude_selector,
+        "date_header_selector" => &mut site.date_header_selector,
+        "start_time_selector" => &mut site.start_time_selector,
+        "end_time_selector" => &mut site.end_time_selector,
+        "status_selector" => &mut site.status_selector,
+        "tags_selector" => &mut site.tags_selector,
+        "next_page_selector" => &mut site.next_page_selector,
+        "name_attr" => &mut site.name_attr,
+        "start_date_attr" => &mut site.start_date_attr,
+        "end_date_attr" => &mut site.end_date_attr,
+        "location_attr" => &mut site.location_attr,
+        "url_attr" => &mut site.url_attr,
+        "price_attr" => &mut site.price_attr,
+        "description_attr" => &mut site.description_attr,
+        "image_attr" => &mut site.image_attr,
+        "name_regex" => &mut site.name_regex,
+        "start_date_regex" => &mut site.start_date_regex,
+        "end_date_regex" => &mut site.end_date_regex,
+        "location_regex" => &mut site.location_regex,
+        "price_regex" => &mut site.price_regex,
+        "name_separator" => &mut site.name_separator,
+        "location_separator" => &mut site.location_separator,
+        _ => return None,
+    };
+    Some(field)
+}
+
+/// Reads an entry that must be a whole number that isn't negative.
+fn count(entry: &config::Entry) -> Result<usize, config::ConfigError> {
+    match entry.value {
+        config::Value::Integer(n) if n >= 0 => Ok(n as usize),
+        _ => Err(config::ConfigError {
+            line: entry.line,
+            message: format!("{} should be a whole number", entry.key),
+        }),
+    }
+}
+
+/// Checks the configuration of every source.
+///
+/// # Arguments
+///
+/// * `sources` - The source registry.
+///
+/// # Returns
+///
+/// `Ok(())`, or one message per problem, each naming the source and field it is in.
+pub fn validate(sources: &[Source]) -> Result<(), Vec<String>> {
+    let errors: Vec<String> = sources
+        .iter()
+        .filter_map(|source| source.validate().err().map(|errors| (source, errors)))
+        .flat_map(|(source, errors)| {
+            errors
+                .into_iter()
+                .map(move |e| format!("source {}: {}", source.name, e))
+        })
+        .collect();
+    if errors.is_empty() {
+        Ok(())
+    } else {
+        Err(errors)
+    }
+}
+
+/// Finds a source by name, ignoring case.
+///
+/// # Returns
+///
+/// The source, or a message listing the known sources.
+pub fn find<'a>(sources: &'a [Source], name: &str) -> Result<&'a Source, String> {
+    sources
+        .iter()
+        .find(|s| s.name.eq_ignore_ascii_case(name))
+        .ok_or_else(|| {
+            let names: Vec<&str> = sources.iter().map(|s| s.name.as_str()).collect();
+            format!(
+                "unknown source '{}' (known sources: {})",
+                name,
+                names.join(", ")
+            )
+        })
+}
+
+/// Resolves the requested categories to the sources that should be fetched.
+///
+/// Each source appears at most once, no matter how many of the requested categories it serves,
+/// and sources keep their registry order.
+///
+/// # Arguments
+///
+/// * `sources` - The source registry.
+/// * `requested` - The categories the user asked for.
+///
+/// # Returns
+///
+/// The matching sources, without duplicates.
+pub fn resolve<'a>(sources: &'a [Source], requested: &[Category]) -> Vec<&'a Source> {
+    let mut resolved: Vec<&Source> = Vec::new();
+    for source in sources.iter().filter(|s| s.matches_any(requested)) {
+        if !resolved.iter().any(|r| r.name == source.name) {
+            resolved.push(source);
+        }
+    }
+    resolved
+}
+
+/// A place events come from. `registry` builds one from each sources file entry: a
+/// `ScrapedSource` for a page, feed, or calendar, or a `ticketmaster::TicketmasterSource` for
+/// the Discovery API. A new kind of source implements this trait and gets a line in `registry`;
+/// the pipeline, the menu, and `--watch` run it like any other.
+pub trait EventSource {
+    /// The sources file entry the source was built from.
+    fn source(&self) -> &Source;
+
+    /// Fetches and parses every page of the listing, tagging each event with the source's name
+    /// and categories.
+    ///
+    /// # Arguments
+    ///
+    /// * `client` - The client used for the requests.
+    /// * `zone` - The home timezone.
+    ///
+    /// # Returns
+    ///
+    /// The parsed events and the number of matches, or the `FetchError` if a request failed.
+    fn fetch_listing(&self, client: &WebClient, zone: Zone) -> Result<ParsedPage, FetchError>;
+
+    /// Parses a page of the listing that was saved or fetched some other way, as
+    /// `fetch_listing` parses each page it fetches. Pagination isn't followed.
+    ///
+    /// # Arguments
+    ///
+    /// * `body` - The page: HTML, a feed, a calendar, or JSON, as the source expects.
+    /// * `zone` - The home timezone.
+    ///
+    /// # Returns
+    ///
+    /// The parsed events and the number of matches, or the `FetchError` if the page is a
+    /// broken feed or calendar, or looks like a bot challenge.
+    fn parse(&self, body: &str, zone: Zone) -> Result<ParsedPage, FetchError>;
+
+    /// Adjusts the source to what a run asks for, e.g. the days in range.
+    fn narrow(&mut self, _request: &Request) {}
+
+    /// The short name events are tagged with and `--source` picks, e.g. `songkick`.
+    fn name(&self) -> &str {
+        &self.source().name
+    }
+
+    /// The categories the source's events are listed under.
+    fn category(&self) -> &Categories {
+        &self.source().categories
+    }
+
+    /// Fetches the source's events, reading dates as Nashville time.
+    ///
+    /// # Returns
+    ///
+    /// The events in page order, or `Error::Fetch` if a request failed.
+    fn fetch(&self, client: &WebClient) -> Result<Vec<Event>, Error> {
+        Ok(self.fetch_listing(client, Zone::default())?.events)
+    }
+}
+
+/// What a run asks of the sources it fetches.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct Request {
+    /// `--max-events`, which replaces each source's own `max_events`.
+    pub max_events: Option<usize>,
+    /// The days asked for with `--today`, `--from`, `--to`, and the like.
+    pub range: Option<(NaiveDate, NaiveDate)>,
+    /// The home timezone the days are in.
+    pub zone: Zone,
+    /// The settings file's `[ticketmaster]` `api_key`.
+    pub ticketmaster_api_key: Option<String>,
+}
+
+/// Builds the sources a run fetches.
+///
+/// # Arguments
+///
+/// * `sources` - The sources file entries, e.g. those `resolve` picked.
+/// * `request` - What the run asks of them.
+///
+/// # Returns
+///
+/// One `EventSource` per entry, in the same order.
+pub fn registry(sources: Vec<Source>, request: &Request) -> Vec<Box<dyn EventSource>> {
+    sources
+        .into_iter()
+        .map(|mut source| {
+            source.config.max_events = request.max_events.or(source.config.max_events);
+            let mut built = event_source(source);
+            built.narrow(request);
+            built
+        })
+        .collect()
+}
+
+/// Builds the `EventSource` that reads a sources file entry, as it is.
+pub fn event_source(source: Source) -> Box<dyn EventSource> {
+    match source.config.strategy {
+        ParseStrategy::Ticketmaster => Box::new(ticketmaster::TicketmasterSource::new(source)),
+        _ => Box::new(ScrapedSource::new(source)),
+    }
+}
+
+/// Fetches and parses a single source, tagging every event with the source's name and categories.
+///
+/// This is `EventSource::fetch_listing` for the source `event_source` builds.
+///
+/// # Arguments
+///
+/// * `source` - The source to fetch.
+/// * `client` - The client used for the request.
+/// * `zone` - The home timezone.
+///
+/// # Returns
+///
+/// A `Result` containing the parsed events and the number of matches, or the `FetchError` if
+/// a request failed.
+pub fn fetch_source(
+    source: &Source,
+    client: &WebClient,
+    zone: Zone,
+) -> Result<ParsedPage, FetchError> {
+    event_source(source.clone()).fetch_listing(client, zone)
+}
+
+/// A source read from web pages, feeds, or calendars with its `SiteConfig`'s strategy.
+///
+/// If the config sets a `next_page_selector`, next-page links are followed until there are
+/// none, `max_pages` pages have been fetched, or a link points back to a page that was already
+/// visited. Events from all pages are concatenated in page order.
+///
+/// `max_events` caps the events across all pages; once it is reached no further pages are
+/// fetched, so the reported number of matches only covers the pages that were.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ScrapedSource {
+    source: Source,
+}
+
+impl ScrapedSource {
+    pub fn new(source: Source) -> ScrapedSource {
+        ScrapedSource { source }
+    }
+}
+
+impl EventSource for ScrapedSource {
+    fn source(&self) -> &Source {
+        &self.source
+    }
+
+    fn fetch_listing(&self, client: &WebClient, zone: Zone) -> Result<ParsedPage, FetchError> {
+        follow_pages(&self.source, self, client, zone)
+    }
+
+    fn parse(&self, body: &str, zone: Zone) -> Result<ParsedPage, FetchError> {
+        let mut parsed = self.parse_page(body, &self.source.config, zone)?;
+        tag(&mut parsed, &self.source);
+        Ok(parsed)
+    }
+}
+
+impl Pages for ScrapedSource {
+    fn get(&self, client: &WebClient, url: &str) -> Result<String, FetchError> {
+        client.fetch_url(url)
+    }
+
+    // A refused request is reported as a block when the page shows one, e.g. a bot challenge
+    fn refused(&self, url: String, status: StatusCode, body: String) -> FetchError {
+        match html_parser::check_blocked(&body) {
+            Err(e) if body.trim().len() >= html_parser::MIN_PAGE_BYTES => FetchError::Page(e),
+            _ => FetchError::Status { url, status, body },
+        }
+    }
+
+    fn parse_page(
+        &self,
+        body: &str,
+        config: &SiteConfig,
+        zone: Zone,
+    ) -> Result<ParsedPage, FetchError> {
+        let base_url = &self.source.base_url;
+        // Check feeds and calendars here so a broken one is reported instead of looking empty
+        match config.strategy {
+            ParseStrategy::Feed => feed::parse_feed(body, base_url)
+                .map(drop)
+                .map_err(FetchError::Feed)?,
+            ParseStrategy::Ics => ics::parse_ics(body, base_url, zone)
+                .map(drop)
+                .map_err(FetchError::Calendar)?,
+            _ => {}
+        }
+        let page = html_parser::parse_page(body, config, base_url, zone)?;
+        // A page without events may be a bot challenge; say so instead of showing nothing
+        let html_page = matches!(
+            config.strategy,
+            ParseStrategy::Css | ParseStrategy::JsonLd | ParseStrategy::Microdata
+        );
+        let found_events = page.events.iter().any(|e| {
+            e.warnings
+                .iter()
+                .all(|w| w.kind != WarningKind::MetaFallback)
+        });
+        if html_page && !found_events {
+            html_parser::check_blocked(body).map_err(FetchError::Page)?;
+        }
+        Ok(page)
+    }
+
+    fn next_page(&self, body: &str, _url: &str) -> Result<Option<String>, FetchError> {
+        Ok(html_parser::next_page_url(
+            body,
+            &self.source.config,
+            &self.source.base_url,
+        )?)
+    }
+}
+
+/// How a kind of source reads its listing one page at a time, for `follow_pages`.
+pub trait Pages {
+    /// Fetches one page of the listing.
+    fn get(&self, client: &WebClient, url: &str) -> Result<String, FetchError>;
+
+    /// Says why the first page was answered with an error `status`.
+    fn refused(&self, url: String, status: StatusCode, body: String) -> FetchError;
+
+    /// Parses one page with `config`, whose `max_events` leaves out the events already found.
+    fn parse_page(
+        &self,
+        body: &str,
+        config: &SiteConfig,
+        zone: Zone,
+    ) -> Result<ParsedPage, FetchError>;
+
+    /// Finds the URL of the page after `url`, whose content is `body`.
+    fn next_page(&self, body: &str, url: &str) -> Result<Option<String>, FetchError>;
+}
+
+/// Fetches the pages of a listing in order, until there is no next page, `max_pages` pages
+/// have been fetched, a page comes round again, or `max_events` events were found.
+///
+/// A page that fails after events were found ends the listing with those events; a failing
+/// first page is an error.
+///
+/// # Arguments
+///
+/// * `source` - The source whose listing it is.
+/// * `pages` - How the source's pages are fetched and read.
+/// * `client` - The client used for the requests.
+/// * `zone` - The home timezone.
+///
+/// # Returns
+///
+/// The events of every page, tagged with the source's name and categories, or the
+/// `FetchError` of the first page.
+pub fn follow_pages(
+    source: &Source,
+    pages: &impl Pages,
+    client: &WebClient,
+    zone: Zone,
+) -> Result<ParsedPage, FetchError> {
+    let max_pages = source
+        .config
+        .max_pages
+        .unwrap_or(html_parser::DEFAULT_MAX_PAGES);
+    let mut config = source.config.clone();
+    let mut visited = HashSet::new();
+    let mut next_url = Some(source.url.clone());
+    let mut parsed = ParsedPage::default();
+    while let Some(url) = next_url.take() {
+        if visited.len() >= max_pages || !visited.insert(url.clone()) {
+            break;
+        }
+        log::debug!(
+            "{}: page {} of at most {}: {}",
+            source.name,
+            visited.len(),
+            max_pages,
+            url
+        );
+        let body = match pages.get(client, &url) {
+            Ok(body) => body,
+            Err(e @ FetchError::Status { .. }) if !parsed.events.is_empty() => {
+                log::warn!(
+                    "{}: {}; keeping the {} events found before it",
+                    source.name,
+                    e,
+                    parsed.events.len()
+                );
+                break;
+            }
+            Err(FetchError::Status { url, status, body }) => {
+                return Err(pages.refused(url, status, body))
+            }
+            Err(e) => return Err(e),
+        };
+        config.max_events = source
+            .config
+            .max_events
+            .map(|max| max - parsed.events.len());
+        let page = match pages.parse_page(&body, &config, zone) {
+            Ok(page) => page,
+            // A later page that is a bot challenge ends the listing with the events found so far
+            Err(FetchError::Page(_)) if !parsed.events.is_empty() => break,
+            Err(e) => return Err(e),
+        };
+        parsed.events.extend(page.events);
+        parsed.matched += page.matched;
+        parsed.skipped += page.skipped;
+        if source
+            .config
+            .max_events
+            .is_some_and(|max| parsed.events.len() >= max)
+        {
+            break;
+        }
+        next_url = pages.next_page(&body, &url)?;
+    }
+    tag(&mut parsed, source);
+    Ok(parsed)
+}
+
+/// Marks parsed events as coming from `source`, under its categories.
+pub fn tag(parsed: &mut ParsedPage, source: &Source) {
+    for event in &mut parsed.events {
+        event.categories = source.categories.clone();
+        event.source = Some(source.name.clone());
+    }
+}
+
+/// Fetches the detail page of each event and fills in fields the listing left empty.
+///
+/// At most `limit` events are enriched, in listing order; events without a URL are skipped
+/// and don't count towards it. `delay` is waited between detail requests so a listing doesn't
+/// turn into a burst of requests. A detail page that fails to load or has no JSON-LD event
+/// leaves its event unchanged.
+///
+/// # Arguments
+///
+/// * `events` - The parsed listing events to enrich.
+/// * `client` - The client used for the detail requests.
+/// * `base_url` - The base URL of the source.
+/// * `limit` - The maximum number of detail pages to fetch.
+/// * `delay` - How long to wait between detail requests.
+/// * `progress` - Called with the number of detail pages fetched so far and the total.
+///
+/// # Returns
+///
+/// The number of events whose detail page was fetched successfully.
+pub fn enrich_events(
+    events: &mut [Event],
+    client: &WebClient,
+    base_url: &str,
+    limit: usize,
+    delay: Duration,
+    progress: &mut dyn FnMut(usize, usize),
+) -> usize {
+    let targets: Vec<&mut Event> = events
+        .iter_mut()
+        .filter(|e| !e.url.is_empty())
+        .take(limit)
+        .collect();
+    let total = targets.len();
+    let mut enriched = 0;
+    for (done, event) in targets.into_iter().enumerate() {
+        if done > 0 {
+            thread::sleep(delay);
+        }
+        if let Ok(html_content) = client.fetch_url(&event.url) {
+            if let Some(detail) = html_parser::parse_json_ld(&html_content, base_url)
+                .into_iter()
+                .next()
+            {
+                event.fill_missing(detail);
+            }
+            enriched += 1;
+        }
+        progress(done + 1, total);
+    }
+    enriched
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::data_processing::{self, ProcessedEvent};
+    use crate::web_requests::{NetworkPolicy, ResponseCache};
+    use mockito::{mock, server_url, Matcher};
+
+    fn source(name: &str, categories: &[Category]) -> Source {
+        Source {
+            name: name.to_string(),
+            url: format!("{}/{}", server_url(), name),
+            base_url: server_url(),
+            categories: categories.iter().copied().collect(),
+            cities: Vec::new(),
+            config: SiteConfig {
+                event_selector: ".event".to_string(),
+                name_selector: ".name".to_string(),
+                start_date_selector: ".start".to_string(),
+                end_date_selector: Some(".end".to_string()),
+                location_selector: Some(".location".to_string()),
+                url: "a".to_string(),
+                ..Default::default()
+            },
+        }
+    }
+
+    #[test]
+    fn test_resolve_lists_multi_category_source_once() {
+        let sources = vec![
+            source("venue", &[Category::Music, Category::Unique]),
+            source("calendar", &[Category::General]),
+        ];
+
+        let resolved = resolve(&sources, &[Category::Music, Category::Unique]);
+        assert_eq!(resolved.len(), 1);
+        assert_eq!(resolved[0].name, "venue");
+
+        let resolved = resolve(&sources, &Category::ALL);
+        let names: Vec<&str> = resolved.iter().map(|s| s.name.as_str()).collect();
+        assert_eq!(names, vec!["venue", "calendar"]);
+
+        assert!(resolve(&sources, &[]).is_empty());
+        assert_eq!(
+            find(&sources, "Calendar").map(|s| s.name.as_str()),
+            Ok("calendar")
+        );
+        assert_eq!(
+            find(&sources, "nope").unwrap_err(),
+            "unknown source 'nope' (known sources: venue, calendar)"
+        );
+    }
+
+    #[test]
+    fn test_multi_category_source_is_fetched_once_and_tagged() {
+        let m = mock("GET", "/multi-category-venue")
+            .with_status(200)
+            .with_body(r#"<div class="event"><h2 class="name">Show</h2></div>"#)
+            .expect(1)
+            .create();
+        let sources = vec![source(
+            "multi-category-venue",
+            &[Category::Music, Category::Unique],
+        )];
+
+        let client = WebClient::new(NetworkPolicy::Online).unwrap();
+        let mut events = Vec::new();
+        for source in resolve(
+            &sources,
+            &[Category::Music, Category::Unique, Category::General],
+        ) {
+            events.extend(
+                fetch_source(source, &client, Zone::default())
+                    .unwrap()
+                    .events,
+            );
+        }
+
+        m.assert();
+        assert_eq!(events.len(), 1);
+        assert_eq!(
+            events[0].categories,
+            Categories::from([Category::Music, Category::Unique])
+        );
+    }
+
+    fn page(name: &str, next: Option<&str>) -> String {
+        let link = next
+            .map(|n| format!(r#"<a class="next" href="{}">Next</a>"#, n))
+            .unwrap_or_default();
+        format!(
+            r#"<div class="event"><h2 class="name">{}</h2></div>{}"#,
+            name, link
+        )
+    }
+
+    #[test]
+    fn test_fetch_source_follows_pages() {
+        let mocks = [
+            mock("GET", "/paged")
+                .with_body(page("One", Some("/paged/2")))
+                .expect(1)
+                .create(),
+            mock("GET", "/paged/2")
+                .with_body(page("Two", Some("/paged/3")))
+                .expect(1)
+                .create(),
+            mock("GET", "/paged/3")
+                .with_body(page("Three", None))
+                .expect(1)
+                .create(),
+        ];
+        let mut paged = source("paged", &[Category::General]);
+        paged.config.next_page_selector = Some("a.next".to_string());
+
+        let client = WebClient::new(NetworkPolicy::Online).unwrap();
+        let events = fetch_source(&paged, &client, Zone::default())
+            .unwrap()
+            .events;
+
+        mocks.iter().for_each(|m| m.assert());
+        let names: Vec<&str> = events.iter().map(|e| e.name.as_str()).collect();
+        assert_eq!(names, vec!["One", "Two", "Three"]);
+    }
+
+    #[test]
+    fn test_fetch_source_stops_at_max_pages_and_loops() {
+        let capped = [
+            mock("GET", "/capped")
+                .with_body(page("One", Some("/capped/2")))
+                .expect(1)
+                .create(),
+            mock("GET", "/capped/2")
+                .with_body(page("Two", Some("/capped/3")))
+                .expect(1)
+                .create(),
+            mock("GET", "/capped/3").expect(0).create(),
+        ];
+        let looping = [
+            mock("GET", "/looping")
+                .with_body(page("One", Some("/looping/2")))
+                .expect(1)
+                .create(),
+            mock("GET", "/looping/2")
+                .with_body(page("Two", Some("/looping")))
+                .expect(1)
+                .create(),
+        ];
+        let client = WebClient::new(NetworkPolicy::Online).unwrap();
+
+        let mut source_capped = source("capped", &[Category::General]);
+        source_capped.config.next_page_selector = Some("a.next".to_string());
+        source_capped.config.max_pages = Some(2);
+        assert_eq!(
+            fetch_source(&source_capped, &client, Zone::default())
+                .unwrap()
+                .events
+                .len(),
+            2
+        );
+
+        let mut source_looping = source("looping", &[Category::General]);
+        source_looping.config.next_page_selector = Some("a.next".to_string());
+        assert_eq!(
+            fetch_source(&source_looping, &client, Zone::default())
+                .unwrap()
+                .events
+                .len(),
+            2
+        );
+
+        capped.iter().chain(&looping).for_each(|m| m.assert());
+    }
+
+    #[test]
+    fn test_enrich_events_fills_missing_fields_from_detail_pages() {
+        let detail = |start: &str, venue: &str| {
+            format!(
+                r#"<script type="application/ld+json">{{"@type": "MusicEvent", "name": "Detail", "startDate": "{}", "location": {{"@type": "Place", "name": "{}"}}, "offers": {{"price": 20, "priceCurrency": "USD"}}}}</script>"#,
+                start, venue
+            )
+        };
+        let listing = format!(
+            r#"<div class="event"><h2 class="name">First</h2><a href="{0}/enrich/1">x</a></div>
+               <div class="event"><h2 class="name">Second</h2><span class="location">Listed venue</span><a href="{0}/enrich/2">x</a></div>
+               <div class="event"><h2 class="name">Third</h2><a href="{0}/enrich/3">x</a></div>"#,
+            server_url()
+        );
+        let mocks = [
+            mock("GET", "/enrich").with_body(listing).expect(1).create(),
+            mock("GET", "/enrich/1")
+                .with_body(detail("2024-05-02T20:00:00-05:00", "Ryman"))
+                .expect(1)
+                .create(),
+            mock("GET", "/enrich/2")
+                .with_body(detail("2024-05-03T19:00:00-05:00", "Detail venue"))
+                .expect(1)
+                .create(),
+            mock("GET", "/enrich/3").expect(0).create(),
+        ];
+        let client = WebClient::new(NetworkPolicy::Online).unwrap();
+        let source = source("enrich", &[Category::Music]);
+
+        let mut events = fetch_source(&source, &client, Zone::default())
+            .unwrap()
+            .events;
+        let mut updates = Vec::new();
+        let enriched = enrich_events(
+            &mut events,
+            &client,
+            &source.base_url,
+            2,
+            Duration::ZERO,
+            &mut |done, total| updates.push((done, total)),
+        );
+
+        mocks.iter().for_each(|m| m.assert());
+        assert_eq!(enriched, 2);
+        assert_eq!(updates, vec![(1, 2), (2, 2)]);
+        assert_eq!(events[0].name, "First");
+        assert_eq!(events[0].start_date, "2024-05-02T20:00:00-05:00");
+        assert_eq!(events[0].location.as_deref(), Some("Ryman"));
+        assert_eq!(events[0].price.as_deref(), Some("20 USD"));
+        assert_eq!(events[1].location.as_deref(), Some("Listed venue"));
+        assert_eq!(events[1].start_date, "2024-05-03T19:00:00-05:00");
+        assert_eq!(events[2].start_date, "");
+    }
+
+    #[test]
+    fn test_category_spellings() {
+        let spellings = [
+            (
+                Category::Music,
+                [
+                    "1", "music", "Music", "MUSIC", "m", "M", "mu", "mus", " musi ",
+                ]
+                .as_slice(),
+            ),
+            (
+                Category::Unique,
+                &["2", "unique", "Unique", "u", "un", "uniq"],
+            ),
+            (
+                Category::General,
+                &["3", "general", "GENERAL", "g", "ge", "gen", "Gen"],
+            ),
+            (Category::All, &["4", "all", "All", "a", "al"]),
+        ];
+        for (category, inputs) in spellings {
+            for input in inputs {
+                assert_eq!(input.parse::<Category>(), Ok(category), "{:?}", input);
+            }
+        }
+        for garbage in [
+            "",
+            " ",
+            "0",
+            "5",
+            "10",
+            "-1",
+            "musical",
+            "x",
+            "mg",
+            "all events",
+            "1 music",
+            "ünique",
+        ] {
+            assert_eq!(
+                garbage.parse::<Category>(),
+                Err(format!("unknown category '{}'", garbage.trim())),
+                "{:?}",
+                garbage
+            );
+        }
+    }
+
+    #[test]
+    fn test_parse_selection() {
+        let music_unique = (vec![Category::Music, Category::Unique], Vec::new());
+        for input in [
+            "1,2",
+            "music unique",
+            " 2, 1 ",
+            "u,,m",
+            "Music,  u",
+            "1 1 2",
+        ] {
+            assert_eq!(parse_selection(input), music_unique, "{:?}", input);
+        }
+        assert_eq!(parse_selection("3"), (vec![Category::General], Vec::new()));
+        assert_eq!(
+            parse_selection("all, 1"),
+            (Category::ALL.to_vec(), Vec::new())
+        );
+
+        let (chosen, errors) = parse_selection("1, x, gen, 9");
+        assert_eq!(chosen, [Category::Music, Category::General]);
+        assert_eq!(errors, ["unknown category 'x'", "unknown category '9'"]);
+        assert_eq!(parse_selection(" , "), (Vec::new(), Vec::new()));
+    }
+
+    #[test]
+    fn test_category_iter_display_and_members() {
+        let names: Vec<String> = Category::iter()
+            .map(|c| format!("{} {} {}", c.number(), c.label(), c))
+            .collect();
+        assert_eq!(
+            names,
+            [
+                "1 Music music",
+                "2 Unique unique",
+                "3 General general",
+                "4 All all"
+            ]
+        );
+        assert_eq!(Category::All.members(), Category::ALL.to_vec());
+        assert_eq!(Category::Unique.members(), vec![Category::Unique]);
+
+        let sources = [
+            source("songkick", &[Category::Music]),
+            source("perto", &[Category::Unique]),
+        ];
+        assert_eq!(resolve(&sources, &[Category::All]).len(), 2);
+        let everything = parse_sources("[[source]]\nname = \"one\"\ncategory = \"all\"\nurl = \"https://example.com\"\nstrategy = \"feed\"").unwrap();
+        assert_eq!(
+            everything.sources[0].categories,
+            Categories::from(Category::ALL)
+        );
+    }
+
+    #[test]
+    fn test_default_sources_are_valid() {
+        assert_eq!(validate(&default_sources()), Ok(()));
+
+        let mut broken = vec![
+            source("broken", &[Category::Music]),
+            source("also-broken", &[Category::General]),
+        ];
+        broken[0].config.name_regex = Some("[".to_string());
+        broken[1].base_url = "ftp://example.com".to_string();
+        broken[1].config.event_selector = "..".to_string();
+        let errors = validate(&broken).unwrap_err();
+        assert_eq!(errors.len(), 3);
+        assert!(
+            errors[0].starts_with("source broken: name_regex: invalid regex '['"),
+            "{}",
+            errors[0]
+        );
+        assert_eq!(
+            errors[1],
+            "source also-broken: base_url: 'ftp://example.com' is not an http(s) URL"
+        );
+        assert_eq!(
+            errors[2],
+            "source also-broken: event_selector: invalid CSS selector '..'"
+        );
+    }
+
+    #[test]
+    fn test_parse_sources_leaves_out_invalid_entry() {
+        let text = r#"
+[[source]]
+name = "basement"
+categories = ["music", "unique"]
+url = "https://thebasementnashville.com/calendar/"
+event_selector = ".event"
+name_selector = "h2"
+start_date_selector = "time"
+start_date_attr = "datetime"
+url_selector = "a.details"
+location_transforms = ['remove_suffix(" - Nashville, TN")']
+next_page_selector = ".next"
+max_pages = 3
+
+[[source]]
+name = "broken"
+category = "general"
+url = "https://example.com/events"
+event_selector = "div["
+name_selector = "h2"
+start_date_selector = ".date"
+url_selector = "a"
+price_regex = "("
+
+[[source]]
+name = "library"
+category = "General"
+url = "https://library.nashville.org/events.rss"
+strategy = "feed"
+"#;
+        let SourcesFile {
+            sources, errors, ..
+        } = parse_sources(text).unwrap();
+
+        assert_eq!(
+            sources.iter().map(|s| s.name.as_str()).collect::<Vec<_>>(),
+            ["basement", "library"]
+        );
+        let basement = &sources[0];
+        assert_eq!(
+            basement.categories,
+            Categories::from([Category::Music, Category::Unique])
+        );
+        assert_eq!(basement.base_url, "https://thebasementnashville.com");
+        assert_eq!(basement.config.url, "a.details");
+        assert_eq!(basement.config.start_date_attr.as_deref(), Some("datetime"));
+        assert_eq!(
+            basement.config.transforms,
+            vec![(
+                "location".to_string(),
+                vec![r#"remove_suffix(" - Nashville, TN")"#.to_string()]
+            )]
+        );
+        assert_eq!(basement.config.max_pages, Some(3));
+        assert_eq!(sources[1].config.strategy, ParseStrategy::Feed);
+        assert_eq!(sources[1].base_url, "https://library.nashville.org");
+
+        assert_eq!(errors.len(), 2);
+        assert_eq!(
+            errors[0],
+            config::ConfigError {
+                line: 19,
+                message: "source 'broken': event_selector: invalid CSS selector 'div['".to_string()
+            }
+        );
+        assert_eq!(errors[1].line, 23);
+        assert!(
+            errors[1]
+                .message
+                .starts_with("source 'broken': price_regex: invalid regex '('"),
+            "{}",
+            errors[1]
+        );
+    }
+
+    #[test]
+    fn test_parse_sources_reports_lines() {
+        let entry_error = |text: &str| {
+            let SourcesFile {
+                sources, errors, ..
+            } = parse_sources(text).unwrap();
+            assert!(sources.is_empty());
+            errors.into_iter().next().unwrap().to_string()
+        };
+        let one =
+            "[[source]]\nname = \"one\"\ncategory = \"music\"\nurl = \"https://example.com\"\n";
+
+        assert_eq!(entry_error("[[source]]\nname = \"one\"\nurl = \"ftp://example.com\"\ncategory = \"music\"\nstrategy = \"json-ld\""),
+            "line 3: source 'one': url: 'ftp://example.com' is not an http(s) URL");
+        assert_eq!(
+            entry_error(&format!("{}max_events = -1", one)),
+            "line 5: max_events should be a whole number"
+        );
+        assert_eq!(
+            entry_error(&format!("{}event_selecter = \".event\"", one)),
+            "line 5: unknown setting 'event_selecter' in [[source]]"
+        );
+        assert_eq!(entry_error(&format!("{}strategy = \"xml\"", one)),
+            "line 5: unknown strategy 'xml' (expected css, json-ld, microdata, feed, ics, json-api, or ticketmaster)");
+        assert_eq!(
+            entry_error("\n[[source]]\ncategory = \"music\""),
+            "line 2: [[source]] needs a name"
+        );
+        assert_eq!(
+            entry_error("[[source]]\nname = \"one\"\ncategory = \"films\""),
+            "line 3: unknown category 'films'"
+        );
+
+        let twice = format!("{0}strategy = \"ics\"\n{0}strategy = \"ics\"", one);
+        let SourcesFile {
+            sources, errors, ..
+        } = parse_sources(&twice).unwrap();
+        assert_eq!(
+            (sources.len(), errors[0].to_string()),
+            (1, "line 6: source 'one' is defined twice".to_string())
+        );
+
+        assert_eq!(
+            parse_sources("name = \"one\"").unwrap_err().to_string(),
+            "line 1: 'name' must be inside a [[source]] entry"
+        );
+        assert_eq!(
+            parse_sources("[sources]").unwrap_err().to_string(),
+            "line 1: unknown table 'sources' (expected [[source]] or [[city]])"
+        );
+        assert_eq!(parse_sources("[[source]]\nname = one").unwrap_err().line, 2);
+    }
+
+    #[test]
+    fn test_load_sources_missing_file_is_default() {
+        let path = std::env::temp_dir().join(format!(
+            "event_finder_no_sources_{}.toml",
+            std::process::id()
+        ));
+
+        let file = load_sources(&path).unwrap();
+
+        assert_eq!(file, builtin_sources());
+        assert_eq!(
+            file.city(None).unwrap().map(|c| c.label.as_str()),
+            Some("Nashville")
+        );
+        assert_eq!(
+            default_sources()[0].url,
+            "https://www.songkick.com/metro-areas/11104-us-nashville/tonight"
+        );
+    }
+
+    #[test]
+    fn test_sources_for_city() {
+        let text = r#"
+[[source]]
+name = "songkick"
+category = "music"
+url = "https://www.songkick.com/metro-areas/{songkick_metro_id}-{songkick_metro_slug}/tonight"
+strategy = "json-ld"
+
+[[source]]
+name = "do512"
+category = "general"
+city = "austin"
+url = "https://do512.com/events/today"
+strategy = "json-ld"
+
+[[source]]
+name = "lost"
+category = "general"
+cities = ["atlantis"]
+url = "https://example.com/{city}"
+strategy = "json-ld"
+
+[[city]]
+name = "austin"
+songkick_metro_id = 9179
+songkick_metro_slug = "us-austin"
+
+[[city]]
+name = "chicago"
+label = "Chicago"
+"#;
+        let file = parse_sources(text).unwrap();
+        assert_eq!(
+            file.errors,
+            vec![config::ConfigError {
+                line: 18,
+                message: "source 'lost': unknown city 'atlantis'".to_string()
+            }]
+        );
+
+        let austin = file.city(Some("Austin")).unwrap();
+        assert_eq!(austin, file.city(None).unwrap());
+        let (sources, errors) = file.sources_for(austin);
+        assert!(errors.is_empty(), "{:?}", errors);
+        assert_eq!(
+            sources.iter().map(|s| s.url.as_str()).collect::<Vec<_>>(),
+            [
+                "https://www.songkick.com/metro-areas/9179-us-austin/tonight",
+                "https://do512.com/events/today",
+            ]
+        );
+        assert_eq!(sources[0].base_url, "https://www.songkick.com");
+
+        let (sources, errors) = file.sources_for(file.city(Some("chicago")).unwrap());
+        assert!(sources.is_empty());
+        assert_eq!(errors, ["source 'songkick': city 'chicago' has no songkick_metro_id for {songkick_metro_id}"]);
+        assert_eq!(
+            file.city(Some("denver")).unwrap_err(),
+            "unknown city 'denver' (known cities: austin, chicago)"
+        );
+
+        let no_cities = parse_sources("[[source]]\nname = \"one\"\ncategory = \"music\"\nurl = \"https://example.com/{city}\"\nstrategy = \"feed\"").unwrap();
+        assert_eq!(no_cities.city(None), Ok(None));
+        assert_eq!(
+            no_cities.sources_for(None).1,
+            ["source 'one': {city} in its url needs a [[city]] entry"]
+        );
+        let unknown = parse_sources("[[source]]\nname = \"one\"\ncategory = \"music\"\nurl = \"https://example.com/{town}\"").unwrap();
+        assert_eq!(unknown.errors[0].to_string(),
+            "line 4: unknown placeholder '{town}' in url (expected {city}, {songkick_metro_id}, {songkick_metro_slug})");
+    }
+
+    #[test]
+    fn test_fetch_source_caps_events_across_pages() {
+        let two_events = |next: &str| {
+            format!(
+                r#"<div class="event"><h2 class="name">A</h2></div><div class="event"><h2 class="name">B</h2></div><a class="next" href="{}">Next</a>"#,
+                next
+            )
+        };
+        let mocks = [
+            mock("GET", "/many")
+                .with_body(two_events("/many/2"))
+                .expect(1)
+                .create(),
+            mock("GET", "/many/2")
+                .with_body(two_events("/many/3"))
+                .expect(1)
+                .create(),
+            mock("GET", "/many/3").expect(0).create(),
+        ];
+        let mut many = source("many", &[Category::General]);
+        many.config.next_page_selector = Some("a.next".to_string());
+        many.config.max_events = Some(3);
+
+        let client = WebClient::new(NetworkPolicy::Online).unwrap();
+        let parsed = fetch_source(&many, &client, Zone::default()).unwrap();
+
+        mocks.iter().for_each(|m| m.assert());
+        assert_eq!((parsed.events.len(), parsed.matched), (3, 4));
+    }
+
+    #[test]
+    fn test_fetch_source_reads_feeds_and_reports_broken_ones() {
+        let _feed = mock("GET", "/feed.xml")
+            .with_body(r#"<rss version="2.0"><channel><item><title>Jam</title><link>/jam</link></item></channel></rss>"#)
+            .create();
+        let _broken = mock("GET", "/broken.xml")
+            .with_body("<rss><channel>")
+            .create();
+        let client = WebClient::new(NetworkPolicy::Online).unwrap();
+
+        let mut feed_source = source("feed.xml", &[Category::Music]);
+        feed_source.config = SiteConfig {
+            strategy: ParseStrategy::Feed,
+            ..Default::default()
+        };
+        let parsed = fetch_source(&feed_source, &client, Zone::default()).unwrap();
+        assert_eq!(parsed.events[0].name, "Jam");
+        assert_eq!(parsed.events[0].url, format!("{}/jam", server_url()));
+        assert_eq!(
+            parsed.events[0].categories,
+            Categories::from([Category::Music])
+        );
+        assert_eq!(
+            parsed.events[0].source.as_deref(),
+            Some(feed_source.name.as_str())
+        );
+
+        let mut broken = source("broken.xml", &[Category::Music]);
+        broken.config = SiteConfig {
+            strategy: ParseStrategy::Feed,
+            ..Default::default()
+        };
+        assert!(matches!(
+            fetch_source(&broken, &client, Zone::default()),
+            Err(FetchError::Feed(_))
+        ));
+    }
+
+    /// A Ticketmaster source searching `path` on the mock server two events at a time.
+    fn ticketmaster_source(path: &str, api_key: Option<&str>) -> Source {
+        let mut source = source(path, &[Category::Music]);
+        source.url = format!("{}/{}?city=nashville&size=2", server_url(), path);
+        source.config = SiteConfig {
+            strategy: ParseStrategy::Ticketmaster,
+            api_key: api_key.map(str::to_string),
+            ..Default::default()
+        };
+        source
+    }
+
+    fn ticketmaster_fixture(file: &str) -> String {
+        fs::read_to_string(
+            Path::new(env!("CARGO_MANIFEST_DIR"))
+                .join("tests")
+                .join("fixtures")
+                .join(file),
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn test_fetch_source_pages_through_ticketmaster() {
+        let first = mock("GET", "/tm-pages")
+            .match_query(Matcher::Exact(
+                "city=nashville&size=2&apikey=s3cret".to_string(),
+            ))
+            .with_body(ticketmaster_fixture("ticketmaster_page1.json"))
+            .create();
+        let second = mock("GET", "/tm-pages")
+            .match_query(Matcher::Exact(
+                "city=nashville&size=2&page=1&apikey=s3cret".to_string(),
+            ))
+            .with_body(ticketmaster_fixture("ticketmaster_page2.json"))
+            .create();
+        let client = WebClient::new(NetworkPolicy::Online).unwrap();
+
+        let parsed = fetch_source(
+            &ticketmaster_source("tm-pages", Some("s3cret")),
+            &client,
+            Zone::default(),
+        )
+        .unwrap();
+
+        first.assert();
+        second.assert();
+        let names: Vec<&str> = parsed.events.iter().map(|e| e.name.as_str()).collect();
+        assert_eq!(
+            names,
+            [
+                "Jason Isbell and the 400 Unit",
+                "Nashville Predators vs. Dallas Stars",
+                "Bluegrass Underground"
+            ]
+        );
+        assert_eq!(parsed.matched, 3);
+        assert_eq!(parsed.events[2].source.as_deref(), Some("tm-pages"));
+    }
+
+    #[test]
+    fn test_fetch_source_explains_ticketmaster_errors() {
+        let client = WebClient::new(NetworkPolicy::Online).unwrap();
+        let unasked = mock("GET", "/tm-no-key")
+            .match_query(Matcher::Any)
+            .expect(0)
+            .create();
+        let e = fetch_source(
+            &ticketmaster_source("tm-no-key", None),
+            &client,
+            Zone::default(),
+        )
+        .unwrap_err();
+        assert!(
+            matches!(e, FetchError::Ticketmaster(ticketmaster::ApiError::NoKey)),
+            "{}",
+            e
+        );
+        unasked.assert();
+
+        let _refused = mock("GET", "/tm-refused")
+            .match_query(Matcher::Any)
+            .with_status(401)
+            .with_body(r#"{"fault":{"faultstring":"Invalid ApiKey","detail":{"errorcode":"oauth.v2.InvalidApiKey"}}}"#)
+            .create();
+        let e = fetch_source(
+            &ticketmaster_source("tm-refused", Some("wrong")),
+            &client,
+            Zone::default(),
+        )
+        .unwrap_err();
+        assert!(
+            matches!(&e, FetchError::Ticketmaster(ticketmaster::ApiError::Unauthorized(detail)) if detail == "Invalid ApiKey"),
+            "{}",
+            e
+        );
+
+        let _down = mock("GET", "/tm-down")
+            .match_query(Matcher::Any)
+            .with_status(502)
+            .with_body("Bad Gateway")
+            .create();
+        let e = fetch_source(
+            &ticketmaster_source("tm-down", Some("s3cret")),
+            &client,
+            Zone::default(),
+        )
+        .unwrap_err();
+        assert!(matches!(e, FetchError::Status { .. }));
+        // The key is left out of what gets logged
+        assert!(!e.to_string().contains("s3cret"), "{}", e);
+
+        // Running into the rate limit partway keeps the pages already fetched
+        let _first = mock("GET", "/tm-limited")
+            .match_query(Matcher::Exact(
+                "city=nashville&size=2&apikey=s3cret".to_string(),
+            ))
+            .with_body(ticketmaster_fixture("ticketmaster_page1.json"))
+            .create();
+        let _limited = mock("GET", "/tm-limited")
+            .match_query(Matcher::UrlEncoded("page".to_string(), "1".to_string()))
+            .with_status(429)
+            .with_body(
+                r#"{"fault":{"faultstring":"Rate limit quota violation. Quota limit exceeded."}}"#,
+            )
+            .create();
+        assert_eq!(
+            fetch_source(
+                &ticketmaster_source("tm-limited", Some("s3cret")),
+                &client,
+                Zone::default()
+            )
+            .unwrap()
+            .events
+            .len(),
+            2
+        );
+    }
+
+    #[test]
+    fn test_ticketmaster_key_stays_out_of_logs_and_the_cache() {
+        crate::logging::capture::install();
+        let _m = mock("GET", "/tm-cached")
+            .match_query(Matcher::Exact(
+                "city=nashville&size=2&apikey=l0gged".to_string(),
+            ))
+            .with_header("ETag", "\"v1\"")
+            .with_body(ticketmaster_fixture("ticketmaster_page2.json"))
+            .create();
+        let dir =
+            std::env::temp_dir().join(format!("event_finder_tm_cache_{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        let client = WebClient::new(NetworkPolicy::Online)
+            .unwrap()
+            .with_cache(ResponseCache::new(dir.clone()));
+        let source = ticketmaster_source("tm-cached", Some("l0gged"));
+
+        assert_eq!(
+            fetch_source(&source, &client, Zone::default())
+                .unwrap()
+                .events
+                .len(),
+            1
+        );
+
+        assert!(!crate::logging::capture::records_containing("/tm-cached").is_empty());
+        // Only the mock server, which is playing Ticketmaster, sees the key
+        let leaked: Vec<_> = crate::logging::capture::records_containing("l0gged")
+            .into_iter()
+            .filter(|(_, target, _)| !target.starts_with("mockito"))
+            .collect();
+        assert_eq!(leaked, []);
+        let kept: Vec<String> = fs::read_dir(&dir)
+            .unwrap()
+            .map(|file| fs::read_to_string(file.unwrap().path()).unwrap())
+            .collect();
+        assert_eq!(kept.len(), 1);
+        assert!(!kept[0].contains("l0gged"), "{}", kept[0]);
+        // Kept under the URL without the key, so the next run revalidates it
+        assert!(ResponseCache::new(dir.clone()).get(&source.url).is_some());
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    /// Parses a saved page of a built-in source the way the pipeline does, without the network.
+    fn parse_fixture(source_name: &str, file: &str) -> Vec<ProcessedEvent> {
+        let source = default_sources()
+            .into_iter()
+            .find(|s| s.name == source_name)
+            .unwrap();
+        let path = std::path::Path::new(env!("CARGO_MANIFEST_DIR"))
+            .join("tests")
+            .join("fixtures")
+            .join(file);
+        let events = html_parser::parse_file(path, &source.config, &source.base_url).unwrap();
+        data_processing::process_data(
+            html_parser::drop_nameless(events, false).0,
+            crate::timezone::Zone::default(),
+        )
+        .0
+    }
+
+    #[test]
+    fn test_songkick_fixture() {
+        let events = parse_fixture("songkick", "songkick.html");
+
+        assert_eq!(events.len(), 3);
+        assert_eq!(events[0].name, "Brandi Carlile, Allison Russell");
+        assert_eq!(events[0].start_date, "Thursday 2 May 2024, 7:30 PM");
+        assert_eq!(events[0].location.as_deref(), Some("Ryman Auditorium"));
+        assert_eq!(
+            events[0].url,
+            "https://www.songkick.com/concerts/41955511-brandi-carlile-at-ryman-auditorium"
+        );
+        assert_eq!(events[2].name, "The War & Treaty");
+    }
+
+    #[test]
+    fn test_perto_fixture() {
+        let events = parse_fixture("perto", "perto.html");
+
+        assert_eq!(events.len(), 3);
+        assert_eq!(events[0].name, "Bluegrass Jam at the Station Inn");
+        assert_eq!(events[0].start_date, "May 2 19:30");
+        assert_eq!(events[0].location.as_deref(), Some("The Station Inn"));
+        assert_eq!(events[1].name, "First Saturday Art Crawl – Downtown");
+        assert_eq!(
+            events[2].url,
+            "https://en.perto.com/us/nashville-10005/events/trivia-night-at-the-pub-1230/"
+        );
+    }
+
+    #[test]
+    fn test_nashville_fixture() {
+        let source = default_sources()
+            .into_iter()
+            .find(|s| s.name == "nashville.com")
+            .unwrap();
+        let html = std::fs::read_to_string(
+            std::path::Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/nashville.html"),
+        )
+        .unwrap();
+        let events = parse_fixture("nashville.com", "nashville.html");
+
+        assert_eq!(events.len(), 3);
+        assert_eq!(events[0].name, "Iris Festival & Plant Sale");
+        assert_eq!(events[0].start_date, "2024-05-04T10:00:00-05:00");
+        assert_eq!(
+            events[0].end_date.as_deref(),
+            Some("2024-05-05T17:00:00-05:00")
+        );
+        assert_eq!(
+            events[0].location.as_deref(),
+            Some("Cheekwood Estate & Gardens")
+        );
+        assert_eq!(
+            events[0].description.as_deref(),
+            Some("Celebrate Tennessee’s state flower with hundreds of varieties on display.")
+        );
+        assert_eq!(
+            events[0].image_url.as_deref(),
+            Some("https://www.nashville.com/wp-content/uploads/2024/04/iris-festival.jpg")
+        );
+        assert_eq!(events[2].location, None);
+        assert_eq!(
+            html_parser::next_page_url(&html, &source.config, &source.base_url)
+                .unwrap()
+                .as_deref(),
+            Some("https://www.nashville.com/calendar-of-events/page/2/")
+        );
+    }
+
+    #[test]
+    fn test_fetch_source_reports_blocked_page() {
+        let challenge = std::fs::read_to_string(
+            std::path::Path::new(env!("CARGO_MANIFEST_DIR"))
+                .join("tests/fixtures/cloudflare_challenge.html"),
+        )
+        .unwrap();
+        let _m = mock("GET", "/blocked")
+            .with_status(403)
+            .with_body(challenge)
+            .create();
+        let client = WebClient::new(NetworkPolicy::Online).unwrap();
+
+        let error = fetch_source(
+            &source("blocked", &[Category::Music]),
+            &client,
+            Zone::default(),
+        )
+        .unwrap_err();
+
+        assert!(
+            matches!(
+                error,
+                FetchError::Page(html_parser::ParseError::LikelyBlocked(_))
+            ),
+            "{:?}",
+            error
+        );
+    }
+
+    #[test]
+    fn test_site_settings_lists_what_the_entry_sets() {
+        let file = parse_sources(
+            r#"[[source]]
+name = "basement"
+category = "music"
+url = "https://example.com/calendar/"
+event_selector = ".event"
+name_selector = "h2"
+start_date_selector = "time"
+start_date_attr = "datetime"
+url_selector = "a.details"
+location_separator = ", "
+max_pages = 3
+location_transforms = ["trim", "remove_suffix(\" - Nashville\")"]
+"#,
+        )
+        .unwrap();
+
+        assert_eq!(
+            site_settings(&file.sources[0].config),
+            [
+                ("strategy", "css"),
+                ("event_selector", ".event"),
+                ("name_selector", "h2"),
+                ("start_date_selector", "time"),
+                ("url_selector", "a.details"),
+                ("start_date_attr", "datetime"),
+                ("location_separator", ", "),
+                ("max_pages", "3"),
+                (
+                    "location_transforms",
+                    "trim, remove_suffix(\" - Nashville\")"
+                ),
+            ]
+            .map(|(key, value)| (key.to_string(), value.to_string()))
+        );
+        // Every optional key names a field
+        assert!(OPTIONAL_KEYS
+            .iter()
+            .all(|key| optional_field(&mut SiteConfig::default(), key).is_some()));
+        let feed = SiteConfig {
+            strategy: ParseStrategy::Feed,
+            ..Default::default()
+        };
+        assert_eq!(
+            site_settings(&feed),
+            [("strategy".to_string(), "feed".to_string())]
+        );
+    }
+
+    #[test]
+    fn test_scraped_source_parses_a_saved_page() {
+        let source = source("saved", &[Category::Music, Category::Unique]);
+        let scraped = ScrapedSource::new(source.clone());
+        let html = r#"<div class="event"><h2 class="name">Show</h2><a href="/show">x</a></div><a rel="next" href="/page/2">next</a>"#;
+
+        let parsed = scraped.parse(html, Zone::default()).unwrap();
+
+        assert_eq!(parsed.events.len(), 1);
+        assert_eq!(parsed.events[0].url, format!("{}/show", server_url()));
+        assert_eq!(parsed.events[0].source.as_deref(), Some("saved"));
+        assert_eq!(parsed.events[0].categories, source.categories);
+
+        let challenge = std::fs::read_to_string(
+            std::path::Path::new(env!("CARGO_MANIFEST_DIR"))
+                .join("tests/fixtures/cloudflare_challenge.html"),
+        )
+        .unwrap();
+        assert!(matches!(
+            scraped.parse(&challenge, Zone::default()),
+            Err(FetchError::Page(_))
+        ));
+    }
+
+    #[test]
+    fn test_registry_builds_each_source_for_the_run() {
+        let mut capped = source("capped", &[Category::Music]);
+        capped.config.max_events = Some(5);
+        let sources = vec![
+            source("plain", &[Category::Music, Category::Unique]),
+            capped,
+            ticketmaster_source("tm-ranged", None),
+        ];
+        let day = NaiveDate::from_ymd_opt(2024, 5, 3).unwrap();
+
+        let built = registry(sources.clone(), &Request::default());
+        let names: Vec<&str> = built.iter().map(|s| s.name()).collect();
+        assert_eq!(names, ["plain", "capped", "tm-ranged"]);
+        assert_eq!(
+            built[0].category(),
+            &Categories::from([Category::Music, Category::Unique])
+        );
+        assert_eq!(built[1].source().config.max_events, Some(5));
+        assert_eq!(built[2].source().url, sources[2].url);
+
+        let request = Request {
+            max_events: Some(2),
+            range: Some((day, day)),
+            ..Default::default()
+        };
+        let built = registry(sources.clone(), &request);
+        assert!(built
+            .iter()
+            .all(|s| s.source().config.max_events == Some(2)));
+        // Only the API can ask for the days in range
+        assert_eq!(built[0].source().url, sources[0].url);
+        assert!(
+            built[2]
+                .source()
+                .url
+                .contains("startDateTime=2024-05-03T05%3A00%3A00Z"),
+            "{}",
+            built[2].source().url
+        );
+    }
+}
@@ -0,0 +1,320 @@
+//! # Table Module
+//!
+//! This module lays events out as an aligned table for `--format table`, one row per event
+//! under the columns Time, Event, Venue, Price, and Source. Column widths follow the content
+//! and are squeezed to fit the terminal; below `MIN_TABLE_WIDTH` columns the listing falls
+//! back to the usual blocks.
+
+use std::env;
+
+use crate::data_processing::ProcessedEvent;
+use crate::output::{status_label, Styler, NEW_MARKER};
+
+/// The narrowest terminal a table is drawn for; narrower ones get the block listing.
+pub const MIN_TABLE_WIDTH: usize = 60;
+
+/// The width assumed when the terminal's width can't be found out.
+pub const DEFAULT_WIDTH: usize = 80;
+
+/// How the Time column shows dates, unless `--date-format` picks another format.
+pub const TABLE_DATE_FORMAT: &str = "%a %b %-d %-I:%M %p";
+
+/// The column headers, in order.
+pub const COLUMNS: [&str; 5] = ["Time", "Event", "Venue", "Price", "Source"];
+
+/// The narrowest each column is squeezed to.
+const MIN_WIDTHS: [usize; 5] = [8, 10, 8, 5, 6];
+
+/// The space between two columns.
+const GUTTER: &str = "  ";
+
+/// The column widths of a table of events.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TableLayout {
+    widths: [usize; 5],
+}
+
+impl TableLayout {
+    /// Works out the column widths for a set of events within a terminal width.
+    ///
+    /// Each column is as wide as its widest cell. While the table is too wide, the wider of
+    /// the Event and Venue columns is narrowed, down to a minimum; only then are the other
+    /// columns narrowed too.
+    ///
+    /// # Arguments
+    ///
+    /// * `events` - The events the table lists.
+    /// * `width` - The terminal width, in characters.
+    ///
+    /// # Returns
+    ///
+    /// The layout, or `None` if `width` is under `MIN_TABLE_WIDTH`.
+    pub fn fit(events: &[ProcessedEvent], width: usize) -> Option<TableLayout> {
+        if width < MIN_TABLE_WIDTH {
+            return None;
+        }
+        let mut widths = COLUMNS.map(|header| header.chars().count());
+        for event in events {
+            for (column, cell) in cells(event, false).iter().enumerate() {
+                widths[column] = widths[column].max(cell.chars().count());
+            }
+        }
+        let available = width - GUTTER.len() * (COLUMNS.len() - 1);
+        // The name and venue give way first; the time, price, and source only once they can't
+        for columns in [&[1, 2][..], &[0, 3, 4]] {
+            while widths.iter().sum::<usize>() > available {
+                let widest = columns
+                    .iter()
+                    .copied()
+                    .filter(|&c| widths[c] > MIN_WIDTHS[c])
+                    .max_by_key(|&c| (widths[c], c));
+                match widest {
+                    Some(column) => widths[column] -= 1,
+                    None => break,
+                }
+            }
+        }
+        Some(TableLayout { widths })
+    }
+
+    /// Renders the header row and the rule under it.
+    pub fn header(&self, styler: Styler) -> String {
+        let rule: Vec<String> = self.widths.iter().map(|width| "─".repeat(*width)).collect();
+        format!(
+            "{}\n{}\n",
+            styler.header(&self.line(COLUMNS.map(String::from))),
+            rule.join(GUTTER)
+        )
+    }
+
+    /// Renders one event as a row, cutting cells that don't fit with a trailing `…`.
+    ///
+    /// A `fresh` event, new since `--watch` last refreshed, has its name led by `[NEW]`, which
+    /// stays when the name is cut.
+    pub fn row(&self, event: &ProcessedEvent, fresh: bool, styler: Styler) -> String {
+        let padded = self.pad(cells(event, fresh));
+        let name = match padded[1].strip_prefix(NEW_MARKER).filter(|_| fresh) {
+            Some(rest) => format!("{}{}", styler.fresh(NEW_MARKER), styler.name(rest)),
+            None => styler.name(&padded[1]),
+        };
+        let styled = [
+            styler.date(&padded[0]),
+            name,
+            styler.venue(&padded[2]),
+            padded[3].clone(),
+            padded[4].clone(),
+        ];
+        format!("{}\n", styled.join(GUTTER).trim_end())
+    }
+
+    /// Pads or cuts each cell to its column's width.
+    fn pad(&self, cells: [String; 5]) -> [String; 5] {
+        let mut column = 0;
+        cells.map(|cell| {
+            let width = self.widths[column];
+            column += 1;
+            format!("{:<width$}", fit(&cell, width), width = width)
+        })
+    }
+
+    fn line(&self, cells: [String; 5]) -> String {
+        self.pad(cells).join(GUTTER).trim_end().to_string()
+    }
+}
+
+/// Returns an event's cells: its start, name and status, venue, price, and source.
+fn cells(event: &ProcessedEvent, fresh: bool) -> [String; 5] {
+    let mut name = match status_label(event.status) {
+        Some(label) => format!("{} [{}]", event.name, label),
+        None => event.name.clone(),
+    };
+    if fresh {
+        name = format!("{} {}", NEW_MARKER, name);
+    }
+    [
+        event.start_date.clone(),
+        name,
+        event.location.clone().unwrap_or_default(),
+        event.price.clone().unwrap_or_default(),
+        event.source.clone().unwrap_or_default(),
+    ]
+}
+
+/// Cuts text longer than `width` characters to `width`, ending in `…`.
+fn fit(text: &str, width: usize) -> String {
+    if text.chars().count() <= width {
+        return text.to_string();
+    }
+    let kept: String = text.chars().take(width.saturating_sub(1)).collect();
+    format!("{}…", kept)
+}
+
+/// Returns the width tables are drawn for.
+///
+/// # Arguments
+///
+/// * `requested` - The width given with `--width`, which wins.
+///
+/// # Returns
+///
+/// `requested`, else the width of the terminal stdout is on, else `$COLUMNS`, else
+/// `DEFAULT_WIDTH`.
+pub fn terminal_width(requested: Option<usize>) -> usize {
+    requested
+        .or_else(stdout_width)
+        .or_else(|| {
+            env::var("COLUMNS")
+                .ok()?
+                .trim()
+                .parse()
+                .ok()
+                .filter(|columns| *columns > 0)
+        })
+        .unwrap_or(DEFAULT_WIDTH)
+}
+
+/// Asks the terminal on stdout for its width.
+#[cfg(unix)]
+fn stdout_width() -> Option<usize> {
+    let mut size = libc::winsize {
+        ws_row: 0,
+        ws_col: 0,
+        ws_xpixel: 0,
+        ws_ypixel: 0,
+    };
+    // SAFETY: TIOCGWINSZ only writes a `winsize` through the pointer, which outlives the call
+    let status = unsafe { libc::ioctl(libc::STDOUT_FILENO, libc::TIOCGWINSZ, &mut size) };
+    (status == 0 && size.ws_col > 0).then_some(size.ws_col as usize)
+}
+
+#[cfg(not(unix))]
+fn stdout_width() -> Option<usize> {
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::html_parser::EventStatus;
+
+    fn event(
+        start: &str,
+        name: &str,
+        location: &str,
+        price: Option<&str>,
+        source: &str,
+    ) -> ProcessedEvent {
+        ProcessedEvent {
+            name: name.to_string(),
+            start_date: start.to_string(),
+            location: Some(location.to_string()),
+            price: price.map(String::from),
+            source: Some(source.to_string()),
+            ..Default::default()
+        }
+    }
+
+    fn events() -> Vec<ProcessedEvent> {
+        vec![
+            event(
+                "Thu May 2 7:00 PM",
+                "Bluegrass Night",
+                "Station Inn",
+                Some("$15.00"),
+                "songkick",
+            ),
+            event(
+                "Thu May 2 8:30 PM",
+                "An Evening With The Extraordinarily Long Band Name Tour",
+                "The Basement East",
+                None,
+                "songkick",
+            ),
+            ProcessedEvent {
+                status: EventStatus::SoldOut,
+                ..event(
+                    "Fri May 3 9:00 PM",
+                    "Jazz Jam",
+                    "Rudy's Jazz Room",
+                    Some("Free"),
+                    "nashville.com",
+                )
+            },
+        ]
+    }
+
+    fn render(width: usize) -> Option<String> {
+        let events = events();
+        let layout = TableLayout::fit(&events, width)?;
+        let rows: String = events
+            .iter()
+            .map(|e| layout.row(e, false, Styler::plain()))
+            .collect();
+        Some(layout.header(Styler::plain()) + &rows)
+    }
+
+    #[test]
+    fn test_table_golden_wide() {
+        assert_eq!(
+            render(120).unwrap(),
+            include_str!("../tests/fixtures/table_120.txt")
+        );
+    }
+
+    #[test]
+    fn test_table_golden_narrow() {
+        let table = render(72).unwrap();
+
+        assert_eq!(table, include_str!("../tests/fixtures/table_72.txt"));
+        assert!(
+            table.lines().all(|line| line.chars().count() <= 72),
+            "{}",
+            table
+        );
+    }
+
+    #[test]
+    fn test_narrow_terminal_falls_back() {
+        assert!(render(MIN_TABLE_WIDTH - 1).is_none());
+        let table = render(MIN_TABLE_WIDTH).unwrap();
+        assert!(
+            table
+                .lines()
+                .all(|line| line.chars().count() <= MIN_TABLE_WIDTH),
+            "{}",
+            table
+        );
+    }
+
+    #[test]
+    fn test_colored_row() {
+        let event = event("May 2", "Show", "Ryman", None, "songkick");
+        let layout = TableLayout::fit(std::slice::from_ref(&event), 80).unwrap();
+
+        assert_eq!(
+            layout.row(&event, false, Styler::plain()),
+            "May 2  Show   Ryman         songkick\n"
+        );
+        assert_eq!(
+            layout.row(&event, false, Styler::new(true)),
+            "\u{1b}[36mMay 2\u{1b}[0m  \u{1b}[1mShow \u{1b}[0m  \u{1b}[32mRyman\u{1b}[0m         songkick\n"
+        );
+    }
+
+    #[test]
+    fn test_fresh_row_keeps_its_marker() {
+        let events = events();
+        let layout = TableLayout::fit(&events, 72).unwrap();
+
+        let row = layout.row(&events[1], true, Styler::plain());
+        assert_eq!(
+            row,
+            "Thu May 2 8:30 PM  [NEW] An Even…  The Basement …          songkick\n"
+        );
+    }
+
+    #[test]
+    fn test_requested_width_wins() {
+        assert_eq!(terminal_width(Some(100)), 100);
+    }
+}
@@ -0,0 +1,309 @@
+//! # Template Module
+//!
+//! This module renders events through a user-supplied line template for `--template`, e.g.
+//! `{start_date} | {name} @ {location} ({url})`. Placeholders name the fields of an event's
+//! serialized form, so the same names work here, in `--format json`, and in CSV headers.
+
+use std::fmt::Write;
+use std::str::FromStr;
+
+use serde_json::Value;
+
+use crate::data_processing::ProcessedEvent;
+
+/// The fields a placeholder may name.
+pub const FIELDS: [&str; 16] = [
+    "id",
+    "name",
+    "start_date",
+    "end_date",
+    "start",
+    "end",
+    "location",
+    "url",
+    "source",
+    "price",
+    "status",
+    "categories",
+    "tags",
+    "description",
+    "image_url",
+    "alt_urls",
+];
+
+/// A parsed line template.
+///
+/// Text is copied as written, except for the escapes `\n`, `\t`, and `\\`, and `{{` and `}}`
+/// for literal braces. `{field}` is replaced by the field's value, or nothing if it is
+/// missing, and lists are joined with `", "`. `{field:40}` also pads the value to 40
+/// characters, or cuts it to 40 with a trailing `…`, to line up columns.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Template {
+    parts: Vec<Part>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Part {
+    Text(String),
+    Field {
+        name: &'static str,
+        width: Option<usize>,
+    },
+}
+
+impl FromStr for Template {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut parts = Vec::new();
+        let mut text = String::new();
+        let mut chars = s.chars().peekable();
+        while let Some(c) = chars.next() {
+            match c {
+                '\\' => match chars.next() {
+                    Some('n') => text.push('\n'),
+                    Some('t') => text.push('\t'),
+                    Some('\\') | None => text.push('\\'),
+                    Some(other) => {
+                        text.push('\\');
+                        text.push(other);
+                    }
+                },
+                '{' if chars.peek() == Some(&'{') => {
+                    chars.next();
+                    text.push('{');
+                }
+                '}' if chars.peek() == Some(&'}') => {
+                    chars.next();
+                    text.push('}');
+                }
+                '{' => {
+                    let mut placeholder = String::new();
+                    let mut closed = false;
+                    for c in chars.by_ref() {
+                        if c == '}' {
+                            closed = true;
+                            break;
+                        }
+                        placeholder.push(c);
+                    }
+                    if !closed {
+                        return Err(format!(
+                            "unclosed placeholder '{{{}' in template",
+                            placeholder
+                        ));
+                    }
+                    if !text.is_empty() {
+                        parts.push(Part::Text(std::mem::take(&mut text)));
+                    }
+                    parts.push(parse_placeholder(&placeholder)?);
+                }
+                '}' => {
+                    return Err(
+                        "unmatched '}' in template (write '}}' for a literal brace)".to_string()
+                    )
+                }
+                c => text.push(c),
+            }
+        }
+        if !text.is_empty() {
+            parts.push(Part::Text(text));
+        }
+        Ok(Template { parts })
+    }
+}
+
+/// Reads `name` or `name:width` from inside a placeholder's braces.
+fn parse_placeholder(placeholder: &str) -> Result<Part, String> {
+    let (name, width) = match placeholder.split_once(':') {
+        Some((name, width)) => {
+            let width = width
+                .trim()
+                .parse()
+                .ok()
+                .filter(|w| *w > 0)
+                .ok_or_else(|| {
+                    format!(
+                        "invalid width '{}' in placeholder '{{{}}}' (expected a number above 0)",
+                        width, placeholder
+                    )
+                })?;
+            (name, Some(width))
+        }
+        None => (placeholder, None),
+    };
+    let name = name.trim();
+    match FIELDS.iter().find(|field| **field == name) {
+        Some(field) => Ok(Part::Field { name: field, width }),
+        None => Err(format!(
+            "unknown field '{}' in template (valid fields: {})",
+            name,
+            FIELDS.join(", ")
+        )),
+    }
+}
+
+impl Template {
+    /// Renders one event through the template.
+    ///
+    /// # Arguments
+    ///
+    /// * `event` - The event to render.
+    ///
+    /// # Returns
+    ///
+    /// The rendered text, without a trailing newline.
+    pub fn render(&self, event: &ProcessedEvent) -> String {
+        let json = event.to_json();
+        let mut out = String::new();
+        for part in &self.parts {
+            match part {
+                Part::Text(text) => out.push_str(text),
+                Part::Field { name, width } => {
+                    let value = match &json[*name] {
+                        Value::String(text) => text.clone(),
+                        Value::Array(items) => items
+                            .iter()
+                            .filter_map(Value::as_str)
+                            .collect::<Vec<_>>()
+                            .join(", "),
+                        _ => String::new(),
+                    };
+                    match width {
+                        Some(width) => {
+                            let _ = write!(out, "{:<width$}", fit(&value, *width), width = width);
+                        }
+                        None => out.push_str(&value),
+                    }
+                }
+            }
+        }
+        out
+    }
+}
+
+/// Cuts text longer than `width` characters to `width`, ending in `…`.
+fn fit(text: &str, width: usize) -> String {
+    if text.chars().count() <= width {
+        return text.to_string();
+    }
+    let kept: String = text.chars().take(width - 1).collect();
+    format!("{}…", kept)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::html_parser::EventStatus;
+    use crate::sources::{Categories, Category};
+    use chrono::NaiveDateTime;
+
+    fn render(template: &str, event: &ProcessedEvent) -> String {
+        template.parse::<Template>().unwrap().render(event)
+    }
+
+    fn event() -> ProcessedEvent {
+        ProcessedEvent {
+            name: "Bluegrass Night".to_string(),
+            start_date: "May 2 7:00 PM".to_string(),
+            end_date: Some("May 2 9:30 PM".to_string()),
+            start: NaiveDateTime::parse_from_str("2024-05-02T19:00:00", "%Y-%m-%dT%H:%M:%S").ok(),
+            end: NaiveDateTime::parse_from_str("2024-05-02T21:30:00", "%Y-%m-%dT%H:%M:%S").ok(),
+            location: Some("Station Inn".to_string()),
+            url: "https://example.com/bluegrass".to_string(),
+            description: Some("Pickers welcome".to_string()),
+            price: Some("$15.00".to_string()),
+            image_url: Some("https://example.com/banjo.jpg".to_string()),
+            categories: Categories::from([Category::Music, Category::Unique]),
+            source: Some("songkick".to_string()),
+            status: EventStatus::SoldOut,
+            tags: vec!["bluegrass".to_string(), "live".to_string()],
+            alt_urls: vec!["https://example.org/bluegrass".to_string()],
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_every_placeholder() {
+        let event = event();
+        let expected = [
+            ("id", event.id()),
+            ("name", "Bluegrass Night".to_string()),
+            ("start_date", "May 2 7:00 PM".to_string()),
+            ("end_date", "May 2 9:30 PM".to_string()),
+            ("start", "2024-05-02T19:00:00".to_string()),
+            ("end", "2024-05-02T21:30:00".to_string()),
+            ("location", "Station Inn".to_string()),
+            ("url", "https://example.com/bluegrass".to_string()),
+            ("source", "songkick".to_string()),
+            ("price", "$15.00".to_string()),
+            ("status", "sold out".to_string()),
+            ("categories", "music, unique".to_string()),
+            ("tags", "bluegrass, live".to_string()),
+            ("description", "Pickers welcome".to_string()),
+            ("image_url", "https://example.com/banjo.jpg".to_string()),
+            ("alt_urls", "https://example.org/bluegrass".to_string()),
+        ];
+
+        assert_eq!(expected.each_ref().map(|(field, _)| *field), FIELDS);
+        for (field, value) in expected {
+            assert_eq!(
+                render(&format!("{{{}}}", field), &event),
+                value,
+                "{}",
+                field
+            );
+        }
+        assert_eq!(render("[{price}]", &ProcessedEvent::default()), "[]");
+    }
+
+    #[test]
+    fn test_template_text_and_escapes() {
+        assert_eq!(
+            render("{start_date} | {name} @ {location} ({url})", &event()),
+            "May 2 7:00 PM | Bluegrass Night @ Station Inn (https://example.com/bluegrass)"
+        );
+        assert_eq!(
+            render("{name}\\t{price}\\n{{literal}} \\\\ \\q", &event()),
+            "Bluegrass Night\t$15.00\n{literal} \\ \\q"
+        );
+    }
+
+    #[test]
+    fn test_width_pads_and_truncates() {
+        assert_eq!(render("{location:14}|", &event()), "Station Inn   |");
+        assert_eq!(render("{name:10}|", &event()), "Bluegrass…|");
+        assert_eq!(render("{name:15}|", &event()), "Bluegrass Night|");
+        assert_eq!(render("{end_date:3}|", &ProcessedEvent::default()), "   |");
+    }
+
+    #[test]
+    fn test_template_errors() {
+        let error = "{name} at {venue}".parse::<Template>().unwrap_err();
+        assert!(
+            error.starts_with(
+                "unknown field 'venue' in template (valid fields: id, name, start_date,"
+            ),
+            "{}",
+            error
+        );
+        assert!(error.contains("alt_urls"), "{}", error);
+
+        assert!("{name:0}"
+            .parse::<Template>()
+            .unwrap_err()
+            .contains("invalid width '0'"));
+        assert!("{name:wide}"
+            .parse::<Template>()
+            .unwrap_err()
+            .contains("invalid width 'wide'"));
+        assert!("{name} {name"
+            .parse::<Template>()
+            .unwrap_err()
+            .contains("unclosed placeholder '{name'"));
+        assert!("name}"
+            .parse::<Template>()
+            .unwrap_err()
+            .contains("unmatched '}'"));
+    }
+}
@@ -0,0 +1,514 @@
+//! # Ticketmaster Module
+//!
+//! This module reads events from Ticketmaster's Discovery API, which lists most of a city's
+//! concert, sports, and theater venues as clean JSON instead of pages that change with every
+//! redesign. A source with `strategy = "ticketmaster"` points its `url` at the events
+//! endpoint, e.g. `https://app.ticketmaster.com/discovery/v2/events.json?city={city}`; the API
+//! key is added to each request from `API_KEY_VAR` or the settings file's `[ticketmaster]`
+//! table, so it never sits in the sources file, and it is left out of every URL that is
+//! logged or reported. The API serves results a page at a time, which are followed like a
+//! listing's next-page links. `TicketmasterSource` is the `sources::EventSource` that does
+//! all of this for such a source.
+
+use std::fmt;
+
+use chrono::NaiveDate;
+use reqwest::StatusCode;
+use serde_json::Value;
+use url::Url;
+
+use crate::data_processing;
+use crate::html_parser::{self, Event, EventStatus, ParsedPage, SiteConfig};
+use crate::sources::{self, EventSource, Pages, Request, Source};
+use crate::timezone::Zone;
+use crate::web_requests::{FetchError, WebClient};
+
+/// The environment variable that overrides the settings file's `[ticketmaster]` `api_key`.
+pub const API_KEY_VAR: &str = "EVENT_FINDER_TICKETMASTER_API_KEY";
+
+/// The query parameter each request sends the API key in.
+const KEY_PARAM: &str = "apikey";
+
+/// How deep the API lets a search page: it serves at most the first 1000 results.
+const MAX_RESULTS: u64 = 1000;
+
+/// An error raised when the Discovery API doesn't give a source's events.
+#[derive(Debug, Clone, PartialEq)]
+pub enum ApiError {
+    /// No API key is set.
+    NoKey,
+    /// The API refused the key, with what it said.
+    Unauthorized(String),
+    /// Too many requests were made with the key, with what the API said.
+    RateLimited(String),
+    /// The answer isn't the Discovery API's JSON.
+    Malformed(String),
+}
+
+impl fmt::Display for ApiError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ApiError::NoKey => write!(f, "no Ticketmaster API key: set {} or api_key in the settings file's [ticketmaster] table", API_KEY_VAR),
+            ApiError::Unauthorized(detail) => write!(f, "Ticketmaster refused the API key ({}); check {} or api_key in [ticketmaster]", detail, API_KEY_VAR),
+            ApiError::RateLimited(detail) => write!(f, "Ticketmaster's rate limit was reached ({}); try again later", detail),
+            ApiError::Malformed(detail) => write!(f, "not a Discovery API answer: {}", detail),
+        }
+    }
+}
+
+/// Returns the API key to use: `API_KEY_VAR` if it is set, else the settings file's.
+///
+/// # Arguments
+///
+/// * `configured` - The settings file's `[ticketmaster]` `api_key`, if set.
+/// * `var` - Looks up an environment variable, e.g. `|name| env::var(name).ok()`.
+pub fn api_key(configured: Option<&str>, var: impl Fn(&str) -> Option<String>) -> Option<String> {
+    var(API_KEY_VAR)
+        .or(configured.map(str::to_string))
+        .map(|key| key.trim().to_string())
+        .filter(|key| !key.is_empty())
+}
+
+/// Parses a page of the Discovery API's events search into events.
+///
+/// Each of `_embedded.events` becomes an event: `dates.start.localDate` and `localTime` are
+/// its start, the first venue its location, its lowest and highest `priceRanges` its price
+/// (`49.5-125 USD`, for `process_data` to normalize), and its widest image its image. The
+/// classification's segment, genre, and sub-genre are its tags, and `dates.status.code` its
+/// status. A search that found nothing has no `_embedded` at all.
+///
+/// # Arguments
+///
+/// * `json` - The response body.
+/// * `base_url` - The base URL of the site for resolving relative event and image URLs.
+///
+/// # Returns
+///
+/// The page's events, or `ApiError::Malformed` if the body isn't a search answer.
+pub fn parse_events(json: &str, base_url: &str) -> Result<Vec<Event>, ApiError> {
+    let json: Value = serde_json::from_str(json).map_err(|e| ApiError::Malformed(e.to_string()))?;
+    if !json.is_object() || json.get("page").is_none() && json.get("_embedded").is_none() {
+        return Err(ApiError::Malformed(
+            "expected an object with _embedded.events or page".to_string(),
+        ));
+    }
+    let events = match json.pointer("/_embedded/events") {
+        None => return Ok(Vec::new()),
+        Some(Value::Array(events)) => events,
+        Some(other) => {
+            return Err(ApiError::Malformed(format!(
+                "_embedded.events should be a list, not {}",
+                other
+            )))
+        }
+    };
+    Ok(events
+        .iter()
+        .map(|event| api_event(event, base_url))
+        .collect())
+}
+
+/// Builds an `Event` from one of the search's events.
+fn api_event(event: &Value, base_url: &str) -> Event {
+    let text = |pointer: &str| {
+        event
+            .pointer(pointer)
+            .and_then(Value::as_str)
+            .map(str::trim)
+            .filter(|t| !t.is_empty())
+            .map(str::to_string)
+    };
+    let url = text("/url")
+        .and_then(|url| html_parser::resolve_url(base_url, &url).ok())
+        .unwrap_or_default();
+    let image_url = event["images"]
+        .as_array()
+        .and_then(|images| {
+            images
+                .iter()
+                .max_by_key(|image| image["width"].as_u64().unwrap_or(0))
+        })
+        .and_then(|image| image["url"].as_str())
+        .and_then(|src| html_parser::resolve_url(base_url, src).ok());
+    let tags = event
+        .pointer("/classifications/0")
+        .map_or_else(Vec::new, |classification| {
+            ["segment", "genre", "subGenre"]
+                .iter()
+                .filter_map(|level| classification[*level]["name"].as_str())
+                // The API's name for a level it has nothing for
+                .filter(|name| !name.is_empty() && *name != "Undefined")
+                .map(str::to_string)
+                .collect()
+        });
+    let description = text("/info").map(|info| data_processing::clean_text(&info));
+    Event {
+        name: text("/name").unwrap_or_default(),
+        start_date: text("/dates/start/localDate").unwrap_or_default(),
+        start_time: text("/dates/start/localTime"),
+        end_date: text("/dates/end/localDate"),
+        end_time: text("/dates/end/localTime"),
+        location: text("/_embedded/venues/0/name"),
+        url,
+        description,
+        price: price(&event["priceRanges"]),
+        image_url,
+        status: text("/dates/status/code")
+            .map_or(EventStatus::Unknown, |code| EventStatus::from_text(&code)),
+        tags,
+        ..Default::default()
+    }
+}
+
+/// Writes an event's price ranges as `low-high CURRENCY`, or one price when they agree.
+fn price(ranges: &Value) -> Option<String> {
+    let ranges = ranges.as_array()?;
+    let amounts: Vec<f64> = ranges
+        .iter()
+        .flat_map(|range| [&range["min"], &range["max"]])
+        .filter_map(Value::as_f64)
+        .collect();
+    if amounts.is_empty() {
+        return None;
+    }
+    let low = amounts.iter().cloned().fold(f64::INFINITY, f64::min);
+    let high = amounts.iter().cloned().fold(f64::NEG_INFINITY, f64::max);
+    let mut price = if low == high {
+        format!("{}", low)
+    } else {
+        format!("{}-{}", low, high)
+    };
+    if let Some(currency) = ranges.iter().find_map(|range| range["currency"].as_str()) {
+        price = format!("{} {}", price, currency);
+    }
+    Some(price)
+}
+
+/// Finds the URL of the search's next page from the page the answer says it is.
+///
+/// # Arguments
+///
+/// * `json` - The response body.
+/// * `url` - The URL the body was fetched from, without the API key.
+///
+/// # Returns
+///
+/// `url` asking for the next page, or `None` on the last page, or past the first 1000
+/// results, which the API doesn't serve.
+pub fn next_page_url(json: &str, url: &str) -> Option<String> {
+    let json: Value = serde_json::from_str(json).ok()?;
+    let page = json.get("page")?;
+    let (number, size, total) = (
+        page["number"].as_u64()?,
+        page["size"].as_u64()?,
+        page["totalPages"].as_u64()?,
+    );
+    let next = number + 1;
+    if next >= total || next * size >= MAX_RESULTS {
+        return None;
+    }
+    set_query(url, &[("page", next.to_string())])
+}
+
+/// Narrows a search to the events starting on the days from `from` to `to` in `zone`.
+///
+/// `NaiveDate::MIN` and `NaiveDate::MAX`, which `--from` or `--to` alone leave, don't narrow
+/// that end. The API takes the bounds in UTC.
+pub fn with_range(url: &str, from: NaiveDate, to: NaiveDate, zone: Zone) -> String {
+    let mut bounds = Vec::new();
+    if from != NaiveDate::MIN {
+        bounds.push((
+            "startDateTime",
+            zone.to_utc(from.and_hms_opt(0, 0, 0).unwrap()),
+        ));
+    }
+    if to != NaiveDate::MAX {
+        bounds.push((
+            "endDateTime",
+            zone.to_utc(to.and_hms_opt(23, 59, 59).unwrap()),
+        ));
+    }
+    let bounds: Vec<(&str, String)> = bounds
+        .into_iter()
+        .map(|(key, at)| (key, at.format("%Y-%m-%dT%H:%M:%SZ").to_string()))
+        .collect();
+    set_query(url, &bounds).unwrap_or_else(|| url.to_string())
+}
+
+/// Replaces or adds query parameters, keeping the others in order.
+///
+/// # Returns
+///
+/// The new URL, or `None` if `url` isn't one.
+fn set_query(url: &str, params: &[(&str, String)]) -> Option<String> {
+    let mut parsed = Url::parse(url).ok()?;
+    let kept: Vec<(String, String)> = parsed
+        .query_pairs()
+        .into_owned()
+        .filter(|(key, _)| params.iter().all(|(name, _)| key != name))
+        .collect();
+    parsed
+        .query_pairs_mut()
+        .clear()
+        .extend_pairs(kept)
+        .extend_pairs(params.iter().map(|(key, value)| (*key, value.as_str())));
+    Some(parsed.to_string())
+}
+
+/// Turns the API's answer to a request it refused into a `FetchError` that says what to do.
+///
+/// # Arguments
+///
+/// * `url` - The URL requested, without the API key.
+/// * `status` - The status it answered with.
+/// * `body` - The body, whose `fault.faultstring` or `errors[0].detail` says what was wrong.
+///
+/// # Returns
+///
+/// `FetchError::Ticketmaster` for a refused key or the rate limit, else `FetchError::Status`.
+pub fn status_error(url: String, status: StatusCode, body: String) -> FetchError {
+    let json: Value = serde_json::from_str(&body).unwrap_or(Value::Null);
+    let detail = json
+        .pointer("/fault/faultstring")
+        .or_else(|| json.pointer("/errors/0/detail"))
+        .and_then(Value::as_str)
+        .map_or_else(|| status.to_string(), str::to_string);
+    match status {
+        StatusCode::UNAUTHORIZED | StatusCode::FORBIDDEN => {
+            FetchError::Ticketmaster(ApiError::Unauthorized(detail))
+        }
+        StatusCode::TOO_MANY_REQUESTS => FetchError::Ticketmaster(ApiError::RateLimited(detail)),
+        _ => FetchError::Status { url, status, body },
+    }
+}
+
+/// A source read from the Discovery API: each request carries the API key, a refused key or
+/// the rate limit is explained, and the API's pages are followed up to `max_pages`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TicketmasterSource {
+    source: Source,
+}
+
+impl TicketmasterSource {
+    /// Creates the source, which is fetched with its config's `api_key`.
+    pub fn new(source: Source) -> TicketmasterSource {
+        TicketmasterSource { source }
+    }
+}
+
+impl EventSource for TicketmasterSource {
+    fn source(&self) -> &Source {
+        &self.source
+    }
+
+    fn fetch_listing(&self, client: &WebClient, zone: Zone) -> Result<ParsedPage, FetchError> {
+        sources::follow_pages(&self.source, self, client, zone)
+    }
+
+    fn parse(&self, body: &str, zone: Zone) -> Result<ParsedPage, FetchError> {
+        let mut parsed = self.parse_page(body, &self.source.config, zone)?;
+        sources::tag(&mut parsed, &self.source);
+        Ok(parsed)
+    }
+
+    /// Takes the key from `API_KEY_VAR` or the settings file, and asks only for the days in range.
+    fn narrow(&mut self, request: &Request) {
+        self.source.config.api_key = api_key(request.ticketmaster_api_key.as_deref(), |name| {
+            std::env::var(name).ok()
+        });
+        if let Some((from, to)) = request.range {
+            self.source.url = with_range(&self.source.url, from, to, request.zone);
+        }
+    }
+}
+
+impl Pages for TicketmasterSource {
+    fn get(&self, client: &WebClient, url: &str) -> Result<String, FetchError> {
+        let key = self
+            .source
+            .config
+            .api_key
+            .as_deref()
+            .ok_or(FetchError::Ticketmaster(ApiError::NoKey))?;
+        client.fetch_url_with_secret(url, KEY_PARAM, key)
+    }
+
+    fn refused(&self, url: String, status: StatusCode, body: String) -> FetchError {
+        status_error(url, status, body)
+    }
+
+    fn parse_page(
+        &self,
+        body: &str,
+        config: &SiteConfig,
+        _zone: Zone,
+    ) -> Result<ParsedPage, FetchError> {
+        let mut events =
+            parse_events(body, &self.source.base_url).map_err(FetchError::Ticketmaster)?;
+        let matched = events.len();
+        events.truncate(config.max_events.unwrap_or(usize::MAX));
+        html_parser::bound_descriptions(&mut events, config);
+        Ok(ParsedPage {
+            events,
+            matched,
+            skipped: 0,
+        })
+    }
+
+    fn next_page(&self, body: &str, url: &str) -> Result<Option<String>, FetchError> {
+        Ok(next_page_url(body, url))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PAGE_1: &str = include_str!("../tests/fixtures/ticketmaster_page1.json");
+    const PAGE_2: &str = include_str!("../tests/fixtures/ticketmaster_page2.json");
+    const BASE: &str = "https://www.ticketmaster.com";
+
+    #[test]
+    fn test_parse_events() {
+        let events = parse_events(PAGE_1, BASE).unwrap();
+
+        assert_eq!(events.len(), 2);
+        let isbell = &events[0];
+        assert_eq!(isbell.name, "Jason Isbell and the 400 Unit");
+        assert_eq!(
+            (isbell.start_date.as_str(), isbell.start_time.as_deref()),
+            ("2026-10-20", Some("19:30:00"))
+        );
+        assert_eq!(isbell.location.as_deref(), Some("Ryman Auditorium"));
+        assert!(isbell.url.ends_with("/event/1B00612345"));
+        assert_eq!(isbell.price.as_deref(), Some("49.5-125 USD"));
+        assert_eq!(
+            isbell.image_url.as_deref(),
+            Some("https://s1.ticketm.net/dam/a/1d1/isbell_TABLET_LANDSCAPE_LARGE_16_9.jpg")
+        );
+        assert_eq!(isbell.tags, ["Music", "Rock", "Alternative Rock"]);
+        assert_eq!(isbell.status, EventStatus::Available);
+        assert_eq!(
+            isbell.description.as_deref(),
+            Some("Doors open at 6:30 PM. All ages.")
+        );
+        assert!(isbell.warnings.is_empty());
+
+        let hockey = &events[1];
+        assert_eq!(
+            (hockey.price.as_deref(), hockey.location.as_deref()),
+            (None, Some("Bridgestone Arena"))
+        );
+
+        let later = parse_events(PAGE_2, BASE).unwrap();
+        // A time still to be announced leaves just the date; a sub-genre of "Undefined" is none
+        assert_eq!(
+            (later[0].start_date.as_str(), later[0].start_time.as_deref()),
+            ("2026-11-07", None)
+        );
+        assert_eq!(later[0].tags, ["Music", "Country"]);
+        assert_eq!(
+            (later[0].price.as_deref(), later[0].image_url.as_deref()),
+            (Some("35 USD"), None)
+        );
+        assert_eq!(later[0].status, EventStatus::Cancelled);
+    }
+
+    #[test]
+    fn test_parse_events_without_results_or_json() {
+        let nothing = r#"{"_links": {"self": {"href": "/discovery/v2/events.json?city=nowhere"}}, "page": {"size": 20, "totalElements": 0, "totalPages": 0, "number": 0}}"#;
+        assert_eq!(parse_events(nothing, BASE), Ok(Vec::new()));
+
+        assert!(matches!(
+            parse_events("<html>Service Unavailable</html>", BASE),
+            Err(ApiError::Malformed(_))
+        ));
+        assert!(matches!(
+            parse_events("[1, 2]", BASE),
+            Err(ApiError::Malformed(_))
+        ));
+        assert!(matches!(
+            parse_events(r#"{"_embedded": {"events": "none"}}"#, BASE),
+            Err(ApiError::Malformed(_))
+        ));
+    }
+
+    #[test]
+    fn test_next_page_url() {
+        let url = "https://app.ticketmaster.com/discovery/v2/events.json?city=nashville&size=2";
+
+        assert_eq!(next_page_url(PAGE_1, url).as_deref(), Some("https://app.ticketmaster.com/discovery/v2/events.json?city=nashville&size=2&page=1"));
+        assert_eq!(next_page_url(PAGE_2, url), None);
+        // The API serves no results past the first 1000
+        let deep =
+            r#"{"page": {"size": 200, "totalElements": 4000, "totalPages": 20, "number": 4}}"#;
+        assert_eq!(next_page_url(deep, url), None);
+        assert_eq!(next_page_url("not json", url), None);
+    }
+
+    #[test]
+    fn test_request_urls() {
+        let url = "https://app.ticketmaster.com/discovery/v2/events.json?city=nashville";
+        let zone: Zone = "America/Chicago".parse().unwrap();
+        let day = |d| NaiveDate::from_ymd_opt(2026, 10, d).unwrap();
+        // Midnight to midnight in Nashville, in UTC
+        assert_eq!(
+            with_range(url, day(20), day(21), zone),
+            format!(
+                "{}&startDateTime=2026-10-20T05%3A00%3A00Z&endDateTime=2026-10-22T04%3A59%3A59Z",
+                url
+            )
+        );
+        assert_eq!(
+            with_range(url, NaiveDate::MIN, day(21), zone),
+            format!("{}&endDateTime=2026-10-22T04%3A59%3A59Z", url)
+        );
+        assert_eq!(with_range(url, NaiveDate::MIN, NaiveDate::MAX, zone), url);
+    }
+
+    #[test]
+    fn test_api_key_prefers_the_environment() {
+        let unset = |_: &str| None;
+        assert_eq!(
+            api_key(Some("from-config"), unset).as_deref(),
+            Some("from-config")
+        );
+        assert_eq!(
+            api_key(Some("from-config"), |_: &str| Some("from-env".to_string())).as_deref(),
+            Some("from-env")
+        );
+        assert_eq!(api_key(Some("  "), unset), None);
+        assert_eq!(api_key(None, unset), None);
+    }
+
+    #[test]
+    fn test_status_errors_say_what_to_do() {
+        let url = "https://app.ticketmaster.com/discovery/v2/events.json".to_string();
+        let invalid_key = r#"{"fault":{"faultstring":"Invalid ApiKey","detail":{"errorcode":"oauth.v2.InvalidApiKey"}}}"#;
+        let e = status_error(
+            url.clone(),
+            StatusCode::UNAUTHORIZED,
+            invalid_key.to_string(),
+        );
+        assert_eq!(e.to_string(), format!("Ticketmaster refused the API key (Invalid ApiKey); check {} or api_key in [ticketmaster]", API_KEY_VAR));
+
+        let quota = r#"{"fault":{"faultstring":"Rate limit quota violation. Quota limit exceeded.","detail":{"errorcode":"policies.ratelimit.QuotaViolation"}}}"#;
+        let e = status_error(
+            url.clone(),
+            StatusCode::TOO_MANY_REQUESTS,
+            quota.to_string(),
+        );
+        assert!(
+            matches!(&e, FetchError::Ticketmaster(ApiError::RateLimited(detail)) if detail.starts_with("Rate limit quota violation")),
+            "{}",
+            e
+        );
+
+        let e = status_error(url.clone(), StatusCode::FORBIDDEN, String::new());
+        assert_eq!(e.to_string(), format!("Ticketmaster refused the API key (403 Forbidden); check {} or api_key in [ticketmaster]", API_KEY_VAR));
+        assert!(matches!(
+            status_error(url, StatusCode::BAD_GATEWAY, "down".to_string()),
+            FetchError::Status { .. }
+        ));
+    }
+}
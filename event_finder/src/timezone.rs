@@ -0,0 +1,328 @@
+//! # Timezone Module
+//!
+//! This module converts between UTC and the user's home timezone, which event times are
+//! shown in and naive source dates are read in. Only the zones in `ZONES` are known: each has
+//! a fixed standard offset and follows either the US or the EU daylight saving rules (or none).
+
+use std::fmt;
+use std::str::FromStr;
+
+use chrono::{Datelike, Duration, NaiveDate, NaiveDateTime, Utc, Weekday};
+
+/// When a zone observes daylight saving time, an hour ahead of its standard offset.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum DstRule {
+    None,
+    /// From 2 AM local time on the second Sunday in March to 2 AM on the first Sunday in November.
+    UnitedStates,
+    /// From 01:00 UTC on the last Sunday in March to 01:00 UTC on the last Sunday in October.
+    EuropeanUnion,
+}
+
+/// A named timezone.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Zone {
+    name: &'static str,
+    /// The offset from UTC outside daylight saving time, in minutes.
+    standard_offset: i64,
+    dst: DstRule,
+}
+
+/// The zones that can be chosen as the home timezone.
+const ZONES: &[Zone] = &[
+    Zone {
+        name: "UTC",
+        standard_offset: 0,
+        dst: DstRule::None,
+    },
+    Zone {
+        name: "America/New_York",
+        standard_offset: -300,
+        dst: DstRule::UnitedStates,
+    },
+    Zone {
+        name: "America/Chicago",
+        standard_offset: -360,
+        dst: DstRule::UnitedStates,
+    },
+    Zone {
+        name: "America/Denver",
+        standard_offset: -420,
+        dst: DstRule::UnitedStates,
+    },
+    Zone {
+        name: "America/Phoenix",
+        standard_offset: -420,
+        dst: DstRule::None,
+    },
+    Zone {
+        name: "America/Los_Angeles",
+        standard_offset: -480,
+        dst: DstRule::UnitedStates,
+    },
+    Zone {
+        name: "America/Anchorage",
+        standard_offset: -540,
+        dst: DstRule::UnitedStates,
+    },
+    Zone {
+        name: "Pacific/Honolulu",
+        standard_offset: -600,
+        dst: DstRule::None,
+    },
+    Zone {
+        name: "Europe/London",
+        standard_offset: 0,
+        dst: DstRule::EuropeanUnion,
+    },
+    Zone {
+        name: "Europe/Dublin",
+        standard_offset: 0,
+        dst: DstRule::EuropeanUnion,
+    },
+    Zone {
+        name: "Europe/Paris",
+        standard_offset: 60,
+        dst: DstRule::EuropeanUnion,
+    },
+    Zone {
+        name: "Europe/Berlin",
+        standard_offset: 60,
+        dst: DstRule::EuropeanUnion,
+    },
+    Zone {
+        name: "Europe/Madrid",
+        standard_offset: 60,
+        dst: DstRule::EuropeanUnion,
+    },
+    Zone {
+        name: "Europe/Amsterdam",
+        standard_offset: 60,
+        dst: DstRule::EuropeanUnion,
+    },
+    Zone {
+        name: "Europe/Athens",
+        standard_offset: 120,
+        dst: DstRule::EuropeanUnion,
+    },
+];
+
+/// The zone the built-in sources write their times in.
+const NASHVILLE: Zone = Zone {
+    name: "America/Chicago",
+    standard_offset: -360,
+    dst: DstRule::UnitedStates,
+};
+
+impl Default for Zone {
+    fn default() -> Self {
+        NASHVILLE
+    }
+}
+
+impl Zone {
+    /// Returns the names of the zones that can be chosen, in `ZONES` order.
+    pub fn names() -> impl Iterator<Item = &'static str> {
+        ZONES.iter().map(|zone| zone.name)
+    }
+
+    /// Returns the zone's offset from UTC at a UTC time.
+    pub fn offset_at(self, utc: NaiveDateTime) -> Duration {
+        let standard = Duration::minutes(self.standard_offset);
+        let year = utc.year();
+        let (dst_start, dst_end) = match self.dst {
+            DstRule::None => return standard,
+            DstRule::UnitedStates => (
+                // 2 AM local time: standard time before the change, daylight time before the end
+                nth_weekday(year, 3, 2)
+                    .and_hms_opt(2, 0, 0)
+                    .unwrap_or_default()
+                    - standard,
+                nth_weekday(year, 11, 1)
+                    .and_hms_opt(2, 0, 0)
+                    .unwrap_or_default()
+                    - standard
+                    - Duration::hours(1),
+            ),
+            DstRule::EuropeanUnion => (
+                last_sunday(year, 3)
+                    .and_hms_opt(1, 0, 0)
+                    .unwrap_or_default(),
+                last_sunday(year, 10)
+                    .and_hms_opt(1, 0, 0)
+                    .unwrap_or_default(),
+            ),
+        };
+        if (dst_start..dst_end).contains(&utc) {
+            standard + Duration::hours(1)
+        } else {
+            standard
+        }
+    }
+
+    /// Converts a UTC time to the zone's wall-clock time.
+    pub fn to_local(self, utc: NaiveDateTime) -> NaiveDateTime {
+        utc + self.offset_at(utc)
+    }
+
+    /// Converts a wall-clock time in the zone to UTC.
+    ///
+    /// A time repeated when clocks go back is read as the later one, and a time skipped when
+    /// they go forward is shifted by the gap.
+    pub fn to_utc(self, local: NaiveDateTime) -> NaiveDateTime {
+        local - self.offset_at(local - Duration::minutes(self.standard_offset))
+    }
+
+    /// Returns the current wall-clock time in the zone.
+    pub fn now(self) -> NaiveDateTime {
+        self.to_local(Utc::now().naive_utc())
+    }
+
+    /// Returns today's date in the zone.
+    pub fn today(self) -> NaiveDate {
+        self.now().date()
+    }
+}
+
+/// Displays the zone's IANA name, e.g. `America/Chicago`.
+impl fmt::Display for Zone {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(self.name)
+    }
+}
+
+impl FromStr for Zone {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        ZONES
+            .iter()
+            .find(|zone| zone.name.eq_ignore_ascii_case(s))
+            .copied()
+            .ok_or_else(|| {
+                let names: Vec<&str> = Zone::names().collect();
+                format!(
+                    "unknown timezone '{}' (known timezones: {})",
+                    s,
+                    names.join(", ")
+                )
+            })
+    }
+}
+
+/// Returns the `n`th Sunday of a month.
+fn nth_weekday(year: i32, month: u32, n: u8) -> NaiveDate {
+    NaiveDate::from_weekday_of_month_opt(year, month, Weekday::Sun, n).unwrap_or_default()
+}
+
+/// Returns the last Sunday of a month.
+fn last_sunday(year: i32, month: u32) -> NaiveDate {
+    NaiveDate::from_weekday_of_month_opt(year, month, Weekday::Sun, 5)
+        .unwrap_or_else(|| nth_weekday(year, month, 4))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn at(text: &str) -> NaiveDateTime {
+        NaiveDateTime::parse_from_str(text, "%Y-%m-%d %H:%M").unwrap()
+    }
+
+    #[test]
+    fn test_us_dst_boundaries() {
+        let chicago: Zone = "America/Chicago".parse().unwrap();
+        // 2024: daylight time from March 10 to November 3
+        assert_eq!(
+            chicago.to_local(at("2024-03-10 07:59")),
+            at("2024-03-10 01:59")
+        );
+        assert_eq!(
+            chicago.to_local(at("2024-03-10 08:00")),
+            at("2024-03-10 03:00")
+        );
+        assert_eq!(
+            chicago.to_local(at("2024-11-03 06:59")),
+            at("2024-11-03 01:59")
+        );
+        assert_eq!(
+            chicago.to_local(at("2024-11-03 07:00")),
+            at("2024-11-03 01:00")
+        );
+        assert_eq!(
+            chicago.to_local(at("2024-01-15 12:00")),
+            at("2024-01-15 06:00")
+        );
+
+        let new_york: Zone = "america/new_york".parse().unwrap();
+        assert_eq!(
+            new_york.to_local(at("2024-03-10 07:00")),
+            at("2024-03-10 03:00")
+        );
+        let phoenix: Zone = "America/Phoenix".parse().unwrap();
+        assert_eq!(
+            phoenix.to_local(at("2024-07-01 12:00")),
+            at("2024-07-01 05:00")
+        );
+    }
+
+    #[test]
+    fn test_to_utc_inverts_to_local() {
+        let chicago: Zone = "America/Chicago".parse().unwrap();
+        for utc in [
+            "2024-01-15 12:00",
+            "2024-03-10 08:00",
+            "2024-07-04 01:30",
+            "2024-11-03 07:00",
+        ] {
+            assert_eq!(chicago.to_utc(chicago.to_local(at(utc))), at(utc));
+        }
+        assert_eq!(
+            chicago.to_utc(at("2024-11-03 01:30")),
+            at("2024-11-03 07:30")
+        );
+    }
+
+    #[test]
+    fn test_eu_dst_boundaries() {
+        let london: Zone = "Europe/London".parse().unwrap();
+        // 2024: summer time from March 31 to October 27
+        assert_eq!(
+            london.to_local(at("2024-03-31 00:59")),
+            at("2024-03-31 00:59")
+        );
+        assert_eq!(
+            london.to_local(at("2024-03-31 01:00")),
+            at("2024-03-31 02:00")
+        );
+        assert_eq!(
+            london.to_local(at("2024-10-27 00:59")),
+            at("2024-10-27 01:59")
+        );
+        assert_eq!(
+            london.to_local(at("2024-10-27 01:00")),
+            at("2024-10-27 01:00")
+        );
+        let berlin: Zone = "Europe/Berlin".parse().unwrap();
+        assert_eq!(
+            berlin.to_local(at("2024-07-01 12:00")),
+            at("2024-07-01 14:00")
+        );
+    }
+
+    #[test]
+    fn test_parse_zone() {
+        assert_eq!(Zone::default().to_string(), "America/Chicago");
+        assert_eq!(
+            "UTC"
+                .parse::<Zone>()
+                .unwrap()
+                .to_local(at("2024-07-01 12:00")),
+            at("2024-07-01 12:00")
+        );
+        assert!("Mars/Olympus_Mons"
+            .parse::<Zone>()
+            .unwrap_err()
+            .contains("America/Chicago"));
+    }
+}
@@ -0,0 +1,164 @@
+//! # Watch Module
+//!
+//! This module does the waiting between the refreshes of `--watch`: a wait lasts until the
+//! next refresh is due, or ends early when the user presses `q` or Ctrl-C. On a terminal, keys
+//! are read as they are pressed rather than once Enter is, and the terminal is put back as it
+//! was when watching stops. Working out which events are new is `diff::new_since`.
+
+use std::io::{self, Read};
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use crate::interrupt;
+
+/// Clears the terminal and moves the cursor to its top left corner.
+pub const CLEAR_SCREEN: &str = "\x1b[2J\x1b[H";
+
+/// How often a wait checks whether Ctrl-C was pressed.
+const POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+/// Why a wait ended.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Wake {
+    /// The interval is over; it's time to refresh.
+    Refresh,
+    /// The user pressed `q` or Ctrl-C.
+    Quit,
+}
+
+/// The keys pressed while watching.
+pub struct Keys {
+    keys: Receiver<char>,
+    // Dropped with the keys, which puts the terminal back
+    _raw: Option<RawMode>,
+}
+
+impl Keys {
+    /// Reads keys from stdin on a thread of its own, as they are pressed if it is a terminal.
+    pub fn stdin() -> Keys {
+        let raw = RawMode::enable();
+        let (sender, keys) = mpsc::channel();
+        thread::spawn(move || {
+            for byte in io::stdin().lock().bytes().map_while(Result::ok) {
+                if sender.send(char::from(byte)).is_err() {
+                    break;
+                }
+            }
+        });
+        Keys { keys, _raw: raw }
+    }
+
+    /// Takes keys from a channel instead of stdin, as the tests press them.
+    #[cfg(test)]
+    pub fn new(keys: Receiver<char>) -> Keys {
+        Keys { keys, _raw: None }
+    }
+
+    /// Waits for the next refresh.
+    ///
+    /// Keys other than `q` are ignored. Once stdin is closed, only the interval ending or
+    /// Ctrl-C ends a wait, so a watch running with no input keeps refreshing.
+    ///
+    /// # Arguments
+    ///
+    /// * `interval` - How long until the next refresh.
+    ///
+    /// # Returns
+    ///
+    /// `Wake::Quit` if `q` or Ctrl-C was pressed, else `Wake::Refresh` once `interval` is over.
+    pub fn wait(&self, interval: Duration) -> Wake {
+        let deadline = Instant::now() + interval;
+        loop {
+            if interrupt::interrupted() {
+                return Wake::Quit;
+            }
+            let left = deadline.saturating_duration_since(Instant::now());
+            if left.is_zero() {
+                return Wake::Refresh;
+            }
+            match self.keys.recv_timeout(left.min(POLL_INTERVAL)) {
+                Ok('q' | 'Q') => return Wake::Quit,
+                Ok(_) | Err(RecvTimeoutError::Timeout) => {}
+                Err(RecvTimeoutError::Disconnected) => thread::sleep(left.min(POLL_INTERVAL)),
+            }
+        }
+    }
+}
+
+/// The terminal on stdin switched to passing on each key as it is pressed, without echoing
+/// it, until dropped.
+#[cfg(unix)]
+struct RawMode {
+    saved: libc::termios,
+}
+
+#[cfg(unix)]
+impl RawMode {
+    /// Switches the terminal over, or returns `None` if stdin isn't a terminal.
+    fn enable() -> Option<RawMode> {
+        // SAFETY: tcgetattr and tcsetattr only read and write the `termios` passed to them
+        unsafe {
+            let mut saved: libc::termios = std::mem::zeroed();
+            if libc::tcgetattr(libc::STDIN_FILENO, &mut saved) != 0 {
+                return None;
+            }
+            let mut raw = saved;
+            // Ctrl-C still raises SIGINT, as `ISIG` is left on
+            raw.c_lflag &= !(libc::ICANON | libc::ECHO);
+            raw.c_cc[libc::VMIN] = 1;
+            raw.c_cc[libc::VTIME] = 0;
+            (libc::tcsetattr(libc::STDIN_FILENO, libc::TCSANOW, &raw) == 0)
+                .then_some(RawMode { saved })
+        }
+    }
+}
+
+#[cfg(unix)]
+impl Drop for RawMode {
+    fn drop(&mut self) {
+        // SAFETY: as in `enable`
+        unsafe {
+            libc::tcsetattr(libc::STDIN_FILENO, libc::TCSANOW, &self.saved);
+        }
+    }
+}
+
+#[cfg(not(unix))]
+struct RawMode;
+
+#[cfg(not(unix))]
+impl RawMode {
+    fn enable() -> Option<RawMode> {
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_q_ends_the_wait() {
+        let (sender, receiver) = mpsc::channel();
+        let keys = Keys::new(receiver);
+        sender.send('x').unwrap();
+        sender.send('q').unwrap();
+
+        let started = Instant::now();
+        assert_eq!(keys.wait(Duration::from_secs(30)), Wake::Quit);
+        assert!(started.elapsed() < Duration::from_secs(5));
+    }
+
+    #[test]
+    fn test_wait_refreshes_once_the_interval_is_over() {
+        let (sender, receiver) = mpsc::channel();
+        let keys = Keys::new(receiver);
+        sender.send('\n').unwrap();
+
+        assert_eq!(keys.wait(Duration::from_millis(50)), Wake::Refresh);
+        drop(sender);
+        // Closed input doesn't stop the watch
+        assert_eq!(keys.wait(Duration::from_millis(50)), Wake::Refresh);
+    }
+}
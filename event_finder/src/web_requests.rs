@@ -1,50 +1,638 @@
-//! # Web Request Module
-//!
-//! This module provides functionality for making web requests to fetch HTML content.
-//! It utilizes the `reqwest` crate for making HTTP requests and is designed to perform
-//! synchronous (blocking) web requests to retrieve data from specified URLs.
-
-use reqwest;
-
-/// Fetches HTML content from a specified URL using a synchronous (blocking) HTTP GET request.
-///
-/// This function is designed to retrieve the raw HTML content of a web page for further processing
-/// or parsing. It uses the `reqwest` crate's blocking client to perform the HTTP request.
-///
-/// # Arguments
-///
-/// * `url` - A string slice representing the URL from which to fetch the HTML content.
-///
-/// # Returns
-///
-/// A `Result` containing the HTML content as a `String` if successful, or a `reqwest::Error` if the request fails.
-pub fn fetch_url(url: &str) -> Result<String, reqwest::Error> {
-    // Make a blocking GET request to the URL
-    let response = reqwest::blocking::get(url)?;
-
-    // Extract the text (HTML) from the response
-    let body = response.text()?;
-
-    // Return the HTML content
-    Ok(body)
-}
-
-#[cfg(test)]
-mod tests {
-    use super::*;
-    use mockito::{mock, server_url};
-
-    #[test]
-    fn test_fetch_url() {
-        let _m = mock("GET", "/test")
-            .with_status(200)
-            .with_body("mocked response")
-            .create();
-
-        let url = &format!("{}/test", server_url());
-        let response = fetch_url(url).unwrap();
-
-        assert_eq!(response, "mocked response");
-    }
-}
-
+//! # Web Request Module
+//!
+//! This module provides functionality for making web requests to fetch HTML content.
+//! It utilizes the `reqwest` crate for making HTTP requests and is designed to perform
+//! synchronous (blocking) web requests to retrieve data from specified URLs.
+//!
+//! All requests go through a `WebClient`, which can only be built when the `NetworkPolicy`
+//! allows network access. In offline mode no `reqwest` client ever exists.
+//!
+//! A client given a `ResponseCache` makes conditional requests: a page that came with an
+//! `ETag` or `Last-Modified` header is kept on disk, and asked for again with
+//! `If-None-Match`/`If-Modified-Since`, so an unchanged page is answered `304 Not Modified`
+//! and not sent again.
+//!
+//! Every request gives up after `TIMEOUT`. `post_json`, which delivers `--webhook` payloads,
+//! also tries again when the server fails with a 5xx status or can't be reached.
+
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::PathBuf;
+use std::thread;
+use std::time::Duration;
+
+use reqwest::header::{CONTENT_TYPE, ETAG, IF_MODIFIED_SINCE, IF_NONE_MATCH, LAST_MODIFIED};
+use reqwest::StatusCode;
+use serde_json::{json, Value};
+use url::Url;
+
+use crate::{data_processing, feed, html_parser, ics, logging, snapshot, ticketmaster};
+
+/// How long a request may take before it is given up on.
+pub const TIMEOUT: Duration = Duration::from_secs(30);
+
+/// How many more times `post_json` tries a request that failed in a way that may pass.
+pub const DEFAULT_RETRIES: u32 = 2;
+
+/// How long `post_json` waits before its first retry; each later one waits twice as long.
+pub const DEFAULT_RETRY_DELAY: Duration = Duration::from_secs(1);
+
+/// Whether the application may touch the network.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NetworkPolicy {
+    /// Requests may be made.
+    Online,
+    /// No request may be made; `WebClient::new` refuses to build a client.
+    Offline,
+}
+
+/// An error raised while fetching a URL.
+#[derive(Debug)]
+pub enum FetchError {
+    /// The network policy forbids requests.
+    Offline,
+    /// Offline mode found no snapshot to serve for the named source.
+    NoCachedData(String),
+    /// The HTTP request itself failed.
+    Http(reqwest::Error),
+    /// The response was supposed to be a feed but couldn't be read as one.
+    Feed(feed::ParseError),
+    /// The response was supposed to be an iCalendar document but couldn't be read as one.
+    Calendar(ics::ParseError),
+    /// The page had no events and looks like the site refused the request.
+    Page(html_parser::ParseError),
+    /// The source's config has a selector that doesn't parse.
+    Config(html_parser::ConfigError),
+    /// The Ticketmaster API couldn't be asked, or refused to answer.
+    Ticketmaster(ticketmaster::ApiError),
+    /// The server answered with an error status.
+    Status {
+        /// The URL requested.
+        url: String,
+        /// The status it answered with.
+        status: StatusCode,
+        /// The body of the answer, which usually says what was wrong.
+        body: String,
+    },
+}
+
+impl fmt::Display for FetchError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            FetchError::Offline => write!(f, "network access is disabled in offline mode"),
+            FetchError::NoCachedData(source) => write!(f, "offline: no cached data for {}", source),
+            FetchError::Http(e) => write!(f, "{}", e),
+            FetchError::Feed(e) => write!(f, "{}", e),
+            FetchError::Calendar(e) => write!(f, "{}", e),
+            FetchError::Page(e) => write!(f, "{}", e),
+            FetchError::Config(e) => write!(f, "invalid config: {}", e),
+            FetchError::Ticketmaster(e) => write!(f, "{}", e),
+            FetchError::Status { url, status, body } if body.trim().is_empty() => {
+                write!(f, "{} answered {}", url, status)
+            }
+            // An error page is often a whole site's worth of HTML, so only its start is shown
+            FetchError::Status { url, status, body } => write!(
+                f,
+                "{} answered {}: {}",
+                url,
+                status,
+                logging::snippet(body, 300)
+            ),
+        }
+    }
+}
+
+impl FetchError {
+    /// A short name for what went wrong, e.g. `http`, for `--error-format json`.
+    pub fn kind(&self) -> &'static str {
+        match self {
+            FetchError::Offline => "offline",
+            FetchError::NoCachedData(_) => "no_cached_data",
+            FetchError::Http(e) if e.is_timeout() => "timeout",
+            FetchError::Http(_) => "http",
+            FetchError::Feed(_) => "feed",
+            FetchError::Calendar(_) => "calendar",
+            FetchError::Page(_) => "blocked",
+            FetchError::Config(_) => "invalid_config",
+            FetchError::Ticketmaster(_) => "api",
+            FetchError::Status { .. } => "status",
+        }
+    }
+
+    /// Says what went wrong in a few words, for the summary of the sources that failed,
+    /// e.g. `timeout after 30s` or `answered 503 Service Unavailable`.
+    pub fn brief(&self) -> String {
+        match self {
+            FetchError::NoCachedData(_) => "no events saved to show offline".to_string(),
+            FetchError::Http(e) if e.is_timeout() => {
+                format!("timeout after {}s", TIMEOUT.as_secs())
+            }
+            FetchError::Http(e) if e.is_connect() => "couldn't connect".to_string(),
+            FetchError::Status { status, .. } => format!("answered {}", status),
+            _ => self.to_string(),
+        }
+    }
+}
+
+impl From<html_parser::ConfigError> for FetchError {
+    fn from(e: html_parser::ConfigError) -> Self {
+        FetchError::Config(e)
+    }
+}
+
+impl From<reqwest::Error> for FetchError {
+    fn from(e: reqwest::Error) -> Self {
+        FetchError::Http(e)
+    }
+}
+
+/// A page kept by a `ResponseCache`, with the validators it was served with.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CachedResponse {
+    pub etag: Option<String>,
+    pub last_modified: Option<String>,
+    pub body: String,
+}
+
+/// A directory of pages that can be revalidated instead of downloaded again, one JSON file
+/// per URL.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ResponseCache {
+    dir: PathBuf,
+}
+
+impl ResponseCache {
+    /// Creates a cache rooted at the given directory.
+    pub fn new(dir: PathBuf) -> ResponseCache {
+        ResponseCache { dir }
+    }
+
+    /// Creates a cache in the `http` directory of the data directory.
+    pub fn open_default() -> ResponseCache {
+        ResponseCache::new(snapshot::data_dir().join("http"))
+    }
+
+    /// Returns the kept page for `url`, or `None` if there is none or it can't be read.
+    pub fn get(&self, url: &str) -> Option<CachedResponse> {
+        let json: Value = serde_json::from_str(&fs::read_to_string(self.path(url)).ok()?).ok()?;
+        // Two URLs could share a file name; the one stored wins
+        if json["url"].as_str() != Some(url) {
+            return None;
+        }
+        Some(CachedResponse {
+            etag: json["etag"].as_str().map(String::from),
+            last_modified: json["last_modified"].as_str().map(String::from),
+            body: json["body"].as_str()?.to_string(),
+        })
+    }
+
+    /// Keeps a page for `url`, replacing the one kept before.
+    pub fn put(&self, url: &str, response: &CachedResponse) -> io::Result<()> {
+        let json = json!({
+            "url": url,
+            "etag": response.etag,
+            "last_modified": response.last_modified,
+            "body": response.body,
+        });
+        fs::create_dir_all(&self.dir)?;
+        fs::write(self.path(url), json.to_string())
+    }
+
+    fn path(&self, url: &str) -> PathBuf {
+        self.dir.join(format!(
+            "{:016x}.json",
+            data_processing::fnv1a(url.as_bytes())
+        ))
+    }
+}
+
+/// A blocking HTTP client that exists only when the network policy allows it.
+#[derive(Debug, Clone)]
+pub struct WebClient {
+    client: reqwest::blocking::Client,
+    cache: Option<ResponseCache>,
+    retries: u32,
+    retry_delay: Duration,
+}
+
+impl WebClient {
+    /// Creates a client for the given network policy.
+    ///
+    /// # Arguments
+    ///
+    /// * `policy` - The network policy in effect for this run.
+    ///
+    /// # Returns
+    ///
+    /// A `WebClient`, or `FetchError::Offline` if the policy forbids network access.
+    pub fn new(policy: NetworkPolicy) -> Result<WebClient, FetchError> {
+        match policy {
+            NetworkPolicy::Offline => Err(FetchError::Offline),
+            // Nominatim, among others, refuses requests that don't say who is making them
+            NetworkPolicy::Online => Ok(WebClient {
+                client: reqwest::blocking::Client::builder()
+                    .user_agent(concat!("event_finder/", env!("CARGO_PKG_VERSION")))
+                    .timeout(TIMEOUT)
+                    .build()?,
+                cache: None,
+                retries: DEFAULT_RETRIES,
+                retry_delay: DEFAULT_RETRY_DELAY,
+            }),
+        }
+    }
+
+    /// Makes the client revalidate pages kept in `cache` rather than download them again.
+    pub fn with_cache(self, cache: ResponseCache) -> WebClient {
+        WebClient {
+            cache: Some(cache),
+            ..self
+        }
+    }
+
+    /// Sets how many times `post_json` tries again, and how long it waits before the first try.
+    pub fn with_retries(self, retries: u32, delay: Duration) -> WebClient {
+        WebClient {
+            retries,
+            retry_delay: delay,
+            ..self
+        }
+    }
+
+    /// Fetches HTML content from a specified URL using a synchronous (blocking) HTTP GET request.
+    ///
+    /// This function is designed to retrieve the raw HTML content of a web page for further processing
+    /// or parsing.
+    ///
+    /// # Arguments
+    ///
+    /// * `url` - A string slice representing the URL from which to fetch the HTML content.
+    ///
+    /// With a cache, a page kept from an earlier fetch is asked for conditionally, and its kept
+    /// copy returned if the server answers that it hasn't changed.
+    ///
+    /// # Returns
+    ///
+    /// A `Result` containing the HTML content as a `String` if successful, or a `FetchError` if the request fails.
+    pub fn fetch_url(&self, url: &str) -> Result<String, FetchError> {
+        self.fetch(url, None)
+    }
+
+    /// Fetches a page like `fetch_url`, adding a secret such as an API key to the request's
+    /// query. Only the request carries it: the page is logged, cached, and reported as `url`.
+    ///
+    /// # Arguments
+    ///
+    /// * `url` - The URL of the page, without the secret.
+    /// * `name` - The query parameter the secret is sent in, e.g. `apikey`.
+    /// * `secret` - Its value.
+    ///
+    /// # Returns
+    ///
+    /// The page, or a `FetchError` that doesn't mention the secret.
+    pub fn fetch_url_with_secret(
+        &self,
+        url: &str,
+        name: &str,
+        secret: &str,
+    ) -> Result<String, FetchError> {
+        self.fetch(url, Some((name, secret)))
+    }
+
+    fn fetch(&self, url: &str, secret: Option<(&str, &str)>) -> Result<String, FetchError> {
+        // reqwest's errors name the URL requested, which has the secret in it
+        let hide = |e: reqwest::Error| match (secret, Url::parse(url)) {
+            (None, _) => e,
+            (Some(_), Ok(url)) => e.with_url(url),
+            (Some(_), Err(_)) => e.without_url(),
+        };
+        let cached = self.cache.as_ref().and_then(|cache| cache.get(url));
+        // Make a blocking GET request to the URL
+        let mut request = self.client.get(url);
+        if let Some(secret) = secret {
+            request = request.query(&[secret]);
+        }
+        if let Some(cached) = &cached {
+            if let Some(etag) = &cached.etag {
+                request = request.header(IF_NONE_MATCH, etag);
+            }
+            if let Some(last_modified) = &cached.last_modified {
+                request = request.header(IF_MODIFIED_SINCE, last_modified);
+            }
+        }
+        log::debug!(
+            "GET {}{}",
+            url,
+            if cached.is_some() {
+                " (conditional)"
+            } else {
+                ""
+            }
+        );
+        let response = request.send().map_err(hide)?;
+        let status = response.status();
+        if let (StatusCode::NOT_MODIFIED, Some(cached)) = (status, cached) {
+            log::debug!(
+                "{} not modified, using the cached copy ({} bytes)",
+                url,
+                cached.body.len()
+            );
+            return Ok(cached.body);
+        }
+        if status.is_client_error() || status.is_server_error() {
+            let body = response.text().unwrap_or_default();
+            log::debug!("{} from {} ({} bytes)", status, url, body.len());
+            return Err(FetchError::Status {
+                url: url.to_string(),
+                status,
+                body,
+            });
+        }
+        let header = |name| {
+            response
+                .headers()
+                .get(name)
+                .and_then(|value| value.to_str().ok())
+                .map(String::from)
+        };
+        let (etag, last_modified) = (header(ETAG), header(LAST_MODIFIED));
+
+        // Extract the text (HTML) from the response
+        let body = response.text().map_err(hide)?;
+        log::debug!("{} from {} ({} bytes)", status, url, body.len());
+        log::trace!("{}: {}", url, logging::snippet(&body, 500));
+        if let Some(cache) = self
+            .cache
+            .as_ref()
+            .filter(|_| status.is_success() && (etag.is_some() || last_modified.is_some()))
+        {
+            let response = CachedResponse {
+                etag,
+                last_modified,
+                body,
+            };
+            if let Err(e) = cache.put(url, &response) {
+                log::warn!("caching {}: {}", url, e);
+            }
+            return Ok(response.body);
+        }
+
+        // Return the HTML content
+        Ok(body)
+    }
+
+    /// Sends a JSON document to a URL in a POST request.
+    ///
+    /// A request that couldn't be sent, timed out, or was answered with a 5xx status is tried
+    /// again, up to the client's number of retries, waiting twice as long each time. Other
+    /// error statuses are returned right away, as sending the same document again won't help.
+    ///
+    /// # Arguments
+    ///
+    /// * `url` - The URL to post to.
+    /// * `body` - The document to send.
+    ///
+    /// # Returns
+    ///
+    /// The body of the answer, or the `FetchError` of the last try, with the answer's body if
+    /// the server sent one.
+    pub fn post_json(&self, url: &str, body: &Value) -> Result<String, FetchError> {
+        let body = body.to_string();
+        let mut delay = self.retry_delay;
+        let mut attempt = 0;
+        loop {
+            log::debug!(
+                "POST {} ({} bytes){}",
+                url,
+                body.len(),
+                if attempt > 0 {
+                    format!(", retry {}", attempt)
+                } else {
+                    String::new()
+                }
+            );
+            let error = match self
+                .client
+                .post(url)
+                .header(CONTENT_TYPE, "application/json")
+                .body(body.clone())
+                .send()
+            {
+                Ok(response) => {
+                    let status = response.status();
+                    let text = response.text()?;
+                    log::debug!("{} from {} ({} bytes)", status, url, text.len());
+                    if status.is_success() {
+                        return Ok(text);
+                    }
+                    let error = FetchError::Status {
+                        url: url.to_string(),
+                        status,
+                        body: text,
+                    };
+                    if !status.is_server_error() {
+                        return Err(error);
+                    }
+                    error
+                }
+                Err(e) if e.is_connect() || e.is_timeout() => FetchError::Http(e),
+                Err(e) => return Err(FetchError::Http(e)),
+            };
+            if attempt >= self.retries {
+                return Err(error);
+            }
+            log::warn!("{}; trying again in {:?}", error, delay);
+            thread::sleep(delay);
+            delay *= 2;
+            attempt += 1;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use mockito::{mock, server_url, Matcher};
+
+    #[test]
+    fn test_brief_errors() {
+        let status = FetchError::Status {
+            url: "https://example.com/events".to_string(),
+            status: StatusCode::SERVICE_UNAVAILABLE,
+            body: "<html>busy</html>".to_string(),
+        };
+        assert_eq!(status.brief(), "answered 503 Service Unavailable");
+        assert_eq!(
+            FetchError::NoCachedData("songkick".to_string()).brief(),
+            "no events saved to show offline"
+        );
+        assert_eq!(FetchError::Offline.brief(), FetchError::Offline.to_string());
+
+        let refused = reqwest::blocking::get("http://127.0.0.1:1/").unwrap_err();
+        assert_eq!(FetchError::Http(refused).brief(), "couldn't connect");
+    }
+
+    #[test]
+    fn test_fetch_url() {
+        let _m = mock("GET", "/test")
+            .with_status(200)
+            .with_body("mocked response")
+            .create();
+
+        let client = WebClient::new(NetworkPolicy::Online).unwrap();
+        let url = &format!("{}/test", server_url());
+        let response = client.fetch_url(url).unwrap();
+
+        assert_eq!(response, "mocked response");
+    }
+
+    fn temp_cache(name: &str) -> ResponseCache {
+        let dir =
+            std::env::temp_dir().join(format!("event_finder_http_{}_{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        ResponseCache::new(dir)
+    }
+
+    #[test]
+    fn test_unchanged_page_is_revalidated_from_the_cache() {
+        let cache = temp_cache("revalidate");
+        let client = WebClient::new(NetworkPolicy::Online)
+            .unwrap()
+            .with_cache(cache.clone());
+        let url = &format!("{}/cached", server_url());
+
+        let first = mock("GET", "/cached")
+            .with_status(200)
+            .with_header("etag", "\"v1\"")
+            .with_body("first body")
+            .create();
+        assert_eq!(client.fetch_url(url).unwrap(), "first body");
+        first.assert();
+        assert_eq!(cache.get(url).unwrap().etag.as_deref(), Some("\"v1\""));
+
+        let revalidated = mock("GET", "/cached")
+            .match_header("if-none-match", "\"v1\"")
+            .with_status(304)
+            .create();
+        assert_eq!(client.fetch_url(url).unwrap(), "first body");
+        revalidated.assert();
+        let _ = fs::remove_dir_all(&cache.dir);
+    }
+
+    #[test]
+    fn test_changed_page_replaces_the_cached_copy() {
+        let cache = temp_cache("changed");
+        let client = WebClient::new(NetworkPolicy::Online)
+            .unwrap()
+            .with_cache(cache.clone());
+        let url = &format!("{}/changed", server_url());
+        let old = CachedResponse {
+            etag: None,
+            last_modified: Some("Wed, 01 May 2024 10:00:00 GMT".to_string()),
+            body: "old".to_string(),
+        };
+        cache.put(url, &old).unwrap();
+
+        let _m = mock("GET", "/changed")
+            .match_header("if-modified-since", "Wed, 01 May 2024 10:00:00 GMT")
+            .with_status(200)
+            .with_header("last-modified", "Thu, 02 May 2024 10:00:00 GMT")
+            .with_body("new")
+            .create();
+
+        assert_eq!(client.fetch_url(url).unwrap(), "new");
+        assert_eq!(
+            cache.get(url).unwrap().last_modified.as_deref(),
+            Some("Thu, 02 May 2024 10:00:00 GMT")
+        );
+        assert_eq!(cache.get("http://example.com/other"), None);
+        let _ = fs::remove_dir_all(&cache.dir);
+    }
+
+    #[test]
+    fn test_error_status_fails_the_fetch() {
+        let _m = mock("GET", "/missing")
+            .with_status(404)
+            .with_body("<html>\n  <h1>Not   Found</h1></html>")
+            .create();
+
+        let client = WebClient::new(NetworkPolicy::Online).unwrap();
+        let error = client
+            .fetch_url(&format!("{}/missing", server_url()))
+            .unwrap_err();
+
+        assert_eq!(error.kind(), "status");
+        assert!(
+            error
+                .to_string()
+                .ends_with("/missing answered 404 Not Found: <html> <h1>Not Found</h1></html>"),
+            "{}",
+            error
+        );
+    }
+
+    #[test]
+    fn test_post_json_retries_server_errors() {
+        let client = WebClient::new(NetworkPolicy::Online)
+            .unwrap()
+            .with_retries(2, Duration::ZERO);
+        let url = &format!("{}/retry", server_url());
+        let failing = mock("POST", "/retry")
+            .with_status(503)
+            .with_body("busy")
+            .expect(2)
+            .create();
+        let working = mock("POST", "/retry")
+            .match_header("content-type", "application/json")
+            .match_body(Matcher::Json(json!({"text": "hi"})))
+            .with_status(200)
+            .with_body("ok")
+            .create();
+
+        assert_eq!(client.post_json(url, &json!({"text": "hi"})).unwrap(), "ok");
+        failing.assert();
+        working.assert();
+    }
+
+    #[test]
+    fn test_post_json_gives_up_with_the_response_body() {
+        let client = WebClient::new(NetworkPolicy::Online)
+            .unwrap()
+            .with_retries(1, Duration::ZERO);
+        let failing = mock("POST", "/down")
+            .with_status(500)
+            .with_body("internal error")
+            .expect(2)
+            .create();
+        let error = client
+            .post_json(&format!("{}/down", server_url()), &json!({}))
+            .unwrap_err();
+        failing.assert();
+        assert!(
+            matches!(&error, FetchError::Status { status, body, .. } if *status == StatusCode::INTERNAL_SERVER_ERROR && body == "internal error")
+        );
+        assert!(error
+            .to_string()
+            .ends_with("/down answered 500 Internal Server Error: internal error"));
+
+        // A client error isn't retried
+        let rejected = mock("POST", "/rejected")
+            .with_status(400)
+            .with_body("invalid_payload")
+            .expect(1)
+            .create();
+        let error = client
+            .post_json(&format!("{}/rejected", server_url()), &json!({}))
+            .unwrap_err();
+        rejected.assert();
+        assert!(error.to_string().ends_with("invalid_payload"));
+    }
+
+    #[test]
+    fn test_client_cannot_be_built_offline() {
+        assert!(matches!(
+            WebClient::new(NetworkPolicy::Offline),
+            Err(FetchError::Offline)
+        ));
+    }
+}
@@ -0,0 +1,247 @@
+//! # Webhook Module
+//!
+//! This module posts a listing to a webhook for `--webhook`, e.g. a Slack channel's incoming
+//! webhook from a nightly cron job. `payload` builds the document in either format, and
+//! `deliver` sends it through `WebClient::post_json`, so it gets the client's timeout and
+//! retries.
+
+use std::fmt;
+use std::str::FromStr;
+
+use serde_json::{json, Value};
+
+use crate::data_processing::ProcessedEvent;
+use crate::web_requests::{FetchError, WebClient};
+
+/// The most events a Slack message lists; Slack takes at most 50 blocks per message.
+pub const MAX_SLACK_EVENTS: usize = 45;
+
+/// What the document posted to a webhook looks like, chosen with `--webhook-format`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum WebhookFormat {
+    /// A title, a count, and the events' serialized form, for any program to read.
+    #[default]
+    Json,
+    /// A Slack message of Block Kit blocks: a header, then a section per event.
+    Slack,
+}
+
+impl FromStr for WebhookFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "json" => Ok(WebhookFormat::Json),
+            "slack" => Ok(WebhookFormat::Slack),
+            _ => Err(format!(
+                "unknown webhook format '{}' (expected slack or json)",
+                s
+            )),
+        }
+    }
+}
+
+impl fmt::Display for WebhookFormat {
+    /// Writes the name `--webhook-format` takes.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            WebhookFormat::Json => "json",
+            WebhookFormat::Slack => "slack",
+        })
+    }
+}
+
+/// Builds the document posted for a listing.
+///
+/// # Arguments
+///
+/// * `events` - The events listed, filtered and limited.
+/// * `title` - What the listing is, e.g. `12 events in Nashville`.
+/// * `format` - The shape of the document.
+///
+/// # Returns
+///
+/// The document to post.
+pub fn payload(events: &[ProcessedEvent], title: &str, format: WebhookFormat) -> Value {
+    match format {
+        WebhookFormat::Json => json!({
+            "title": title,
+            "count": events.len(),
+            "events": events.iter().map(ProcessedEvent::to_json).collect::<Vec<_>>(),
+        }),
+        WebhookFormat::Slack => slack_payload(events, title),
+    }
+}
+
+/// Builds a Slack message, listing at most `MAX_SLACK_EVENTS` events.
+fn slack_payload(events: &[ProcessedEvent], title: &str) -> Value {
+    let mut blocks = vec![json!({"type": "header", "text": {"type": "plain_text", "text": title}})];
+    for event in events.iter().take(MAX_SLACK_EVENTS) {
+        let name = slack_escape(&event.name);
+        let mut text = if event.url.is_empty() {
+            format!("*{}*", name)
+        } else {
+            format!("*<{}|{}>*", event.url, name)
+        };
+        let details: Vec<String> = [
+            Some(&event.start_date).filter(|date| !date.is_empty()),
+            event.location.as_ref(),
+            event.price.as_ref(),
+        ]
+        .into_iter()
+        .flatten()
+        .map(|detail| slack_escape(detail))
+        .collect();
+        if !details.is_empty() {
+            text.push('\n');
+            text.push_str(&details.join(" · "));
+        }
+        blocks.push(json!({"type": "section", "text": {"type": "mrkdwn", "text": text}}));
+    }
+    if events.len() > MAX_SLACK_EVENTS {
+        let more = format!("and {} more", events.len() - MAX_SLACK_EVENTS);
+        blocks.push(json!({"type": "context", "elements": [{"type": "mrkdwn", "text": more}]}));
+    }
+    // `text` is what notifications and clients without blocks show
+    json!({"text": title, "blocks": blocks})
+}
+
+/// Escapes the characters Slack's mrkdwn reads as markup.
+fn slack_escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+}
+
+/// Posts a document to a webhook.
+///
+/// # Arguments
+///
+/// * `client` - The client to post with.
+/// * `url` - The webhook's URL.
+/// * `payload` - The document, from `payload`.
+///
+/// # Returns
+///
+/// `Ok(())` once the webhook accepted it, or the `FetchError` of the last try, which carries
+/// the answer's body if the webhook rejected it.
+pub fn deliver(client: &WebClient, url: &str, payload: &Value) -> Result<(), FetchError> {
+    client.post_json(url, payload).map(|_| ())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::web_requests::NetworkPolicy;
+    use mockito::{mock, server_url, Matcher};
+    use std::time::Duration;
+
+    fn events() -> Vec<ProcessedEvent> {
+        vec![
+            ProcessedEvent {
+                name: "Rock & Roll <Live>".to_string(),
+                url: "https://example.com/rock".to_string(),
+                start_date: "Fri May 3 8:00 PM".to_string(),
+                location: Some("The Basement".to_string()),
+                price: Some("$15".to_string()),
+                ..Default::default()
+            },
+            ProcessedEvent {
+                name: "Open Mic".to_string(),
+                ..Default::default()
+            },
+        ]
+    }
+
+    fn client() -> WebClient {
+        WebClient::new(NetworkPolicy::Online)
+            .unwrap()
+            .with_retries(2, Duration::ZERO)
+    }
+
+    #[test]
+    fn test_slack_payload_is_delivered_as_blocks() {
+        let expected = json!({
+            "text": "2 events",
+            "blocks": [
+                {"type": "header", "text": {"type": "plain_text", "text": "2 events"}},
+                {"type": "section", "text": {"type": "mrkdwn", "text": "*<https://example.com/rock|Rock &amp; Roll &lt;Live&gt;>*\nFri May 3 8:00 PM · The Basement · $15"}},
+                {"type": "section", "text": {"type": "mrkdwn", "text": "*Open Mic*"}},
+            ],
+        });
+        let hook = mock("POST", "/slack")
+            .match_body(Matcher::Json(expected))
+            .with_status(200)
+            .with_body("ok")
+            .create();
+
+        let payload = payload(&events(), "2 events", WebhookFormat::Slack);
+        deliver(&client(), &format!("{}/slack", server_url()), &payload).unwrap();
+        hook.assert();
+    }
+
+    #[test]
+    fn test_json_payload_is_delivered_with_every_event() {
+        let expected = json!({
+            "title": "2 events",
+            "count": 2,
+            "events": events().iter().map(ProcessedEvent::to_json).collect::<Vec<_>>(),
+        });
+        let hook = mock("POST", "/json")
+            .match_body(Matcher::Json(expected))
+            .with_status(204)
+            .create();
+
+        let payload = payload(&events(), "2 events", WebhookFormat::Json);
+        deliver(&client(), &format!("{}/json", server_url()), &payload).unwrap();
+        hook.assert();
+    }
+
+    #[test]
+    fn test_failed_delivery_is_retried_then_reported() {
+        let hook = mock("POST", "/flaky")
+            .with_status(502)
+            .with_body("upstream unavailable")
+            .expect(3)
+            .create();
+
+        let error = deliver(
+            &client(),
+            &format!("{}/flaky", server_url()),
+            &payload(&events(), "2 events", WebhookFormat::Json),
+        )
+        .unwrap_err();
+        hook.assert();
+        assert!(
+            error
+                .to_string()
+                .ends_with("answered 502 Bad Gateway: upstream unavailable"),
+            "{}",
+            error
+        );
+    }
+
+    #[test]
+    fn test_slack_payload_caps_the_events() {
+        let many = vec![
+            ProcessedEvent {
+                name: "Show".to_string(),
+                ..Default::default()
+            };
+            MAX_SLACK_EVENTS + 3
+        ];
+        let blocks = payload(&many, "lots", WebhookFormat::Slack)["blocks"]
+            .as_array()
+            .unwrap()
+            .clone();
+        assert_eq!(blocks.len(), MAX_SLACK_EVENTS + 2);
+        assert_eq!(blocks.last().unwrap()["elements"][0]["text"], "and 3 more");
+    }
+
+    #[test]
+    fn test_parse_webhook_format() {
+        assert_eq!("Slack".parse(), Ok(WebhookFormat::Slack));
+        assert_eq!("json".parse(), Ok(WebhookFormat::Json));
+        assert!("xml".parse::<WebhookFormat>().is_err());
+    }
+}
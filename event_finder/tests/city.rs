@@ -0,0 +1,47 @@
+//! Integration tests of `--city` and the config file's `city`.
+
+mod common;
+
+use std::fs;
+use std::path::PathBuf;
+
+use common::{run_offline, DataDir};
+
+#[test]
+fn offline_city_picks_its_sources_and_banner() {
+    let data_dir = DataDir::new("city");
+    let austin =
+        fs::read_to_string(PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("cities/austin.toml"))
+            .unwrap();
+    fs::write(data_dir.join("sources.toml"), format!("{}\n[[city]]\nname = \"nashville\"\nlabel = \"Nashville\"\nsongkick_metro_id = 11104\nsongkick_metro_slug = \"us-nashville\"\n", austin)).unwrap();
+    fs::write(data_dir.join("config.toml"), "city = \"nashville\"\n").unwrap();
+
+    let output = run_offline(&data_dir, &["--city", "austin"], "5\n");
+
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(
+        stdout.contains("Welcome to the Event Finder for Austin!"),
+        "{}",
+        stdout
+    );
+    // Only songkick serves Austin, so the menu offers music and all
+    assert!(
+        stdout.contains("1: Music\n4: All\n5: Quit\n6: Surprise me"),
+        "{}",
+        stdout
+    );
+
+    let output = run_offline(&data_dir, &[], "5\n");
+    assert!(String::from_utf8_lossy(&output.stdout)
+        .contains("Welcome to the Event Finder for Nashville!"));
+
+    let output = run_offline(&data_dir, &["--city", "denver"], "5\n");
+    assert_eq!(output.status.code(), Some(64));
+    assert!(String::from_utf8_lossy(&output.stderr)
+        .contains("unknown city 'denver' (known cities: austin, nashville)"));
+}
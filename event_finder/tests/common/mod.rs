@@ -0,0 +1,127 @@
+//! Helpers the integration tests share: a data directory of each test's own, and the binary run
+//! against it.
+
+// Each test file uses only some of the helpers
+#![allow(dead_code)]
+
+use std::env;
+use std::fs;
+use std::io::Write;
+use std::ops::Deref;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Output, Stdio};
+
+/// An empty temporary directory the binary keeps its data, config, and sources files in;
+/// removed when the test is done with it.
+pub struct DataDir(PathBuf);
+
+impl DataDir {
+    /// Creates the directory, replacing one left behind by an earlier run.
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - A name no other test of the same file uses.
+    ///
+    /// # Returns
+    ///
+    /// The empty directory.
+    pub fn new(name: &str) -> DataDir {
+        let dir = env::temp_dir().join(format!(
+            "event_finder_it_{}_{}_{}",
+            env!("CARGO_CRATE_NAME"),
+            name,
+            std::process::id()
+        ));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        DataDir(dir)
+    }
+}
+
+impl Deref for DataDir {
+    type Target = Path;
+
+    fn deref(&self) -> &Path {
+        &self.0
+    }
+}
+
+impl Drop for DataDir {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.0);
+    }
+}
+
+/// The binary, reading and writing only the files of `data_dir`, without `RUST_LOG` or a
+/// Ticketmaster key from the environment, and with nothing on stdin.
+///
+/// # Arguments
+///
+/// * `data_dir` - The directory of the run's files.
+///
+/// # Returns
+///
+/// The command, to add arguments to and run.
+pub fn command(data_dir: &Path) -> Command {
+    let mut command = Command::new(env!("CARGO_BIN_EXE_event_finder"));
+    command
+        .env("EVENT_FINDER_DATA_DIR", data_dir)
+        .env("EVENT_FINDER_CONFIG", data_dir.join("config.toml"))
+        .env("EVENT_FINDER_SOURCES", data_dir.join("sources.toml"))
+        .env_remove("RUST_LOG")
+        .env_remove("EVENT_FINDER_TICKETMASTER_API_KEY")
+        .stdin(Stdio::null());
+    command
+}
+
+/// Runs the binary with `args` and waits for it to exit.
+///
+/// # Arguments
+///
+/// * `data_dir` - The directory of the run's files.
+/// * `args` - The command-line arguments.
+///
+/// # Returns
+///
+/// The exit status and everything written to stdout and stderr.
+pub fn run(data_dir: &Path, args: &[&str]) -> Output {
+    command(data_dir).args(args).output().unwrap()
+}
+
+/// Runs the binary with `args`, writing `input` to its stdin, and waits for it to exit.
+///
+/// # Arguments
+///
+/// * `data_dir` - The directory of the run's files.
+/// * `args` - The command-line arguments.
+/// * `input` - What stdin reads before it ends, such as answers to the menu's prompts.
+///
+/// # Returns
+///
+/// The exit status and everything written to stdout and stderr.
+pub fn run_with_input(data_dir: &Path, args: &[&str], input: &[u8]) -> Output {
+    let mut child = command(data_dir)
+        .args(args)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .unwrap();
+    child.stdin.take().unwrap().write_all(input).unwrap();
+    child.wait_with_output().unwrap()
+}
+
+/// Runs the binary with `--offline` and `args`, answering its prompts with `input`.
+///
+/// # Arguments
+///
+/// * `data_dir` - The directory of the run's files, with the snapshots it serves.
+/// * `args` - The command-line arguments after `--offline`.
+/// * `input` - What stdin reads before it ends.
+///
+/// # Returns
+///
+/// The exit status and everything written to stdout and stderr.
+pub fn run_offline(data_dir: &Path, args: &[&str], input: &str) -> Output {
+    run_with_input(data_dir, &[&["--offline"], args].concat(), input.as_bytes())
+}
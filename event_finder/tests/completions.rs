@@ -0,0 +1,75 @@
+//! Integration tests of the `completions` subcommand.
+
+mod common;
+
+use std::fs;
+use std::path::Path;
+use std::process::Output;
+
+use common::DataDir;
+
+fn completions(data_dir: &Path, shell: &str) -> Output {
+    common::run(data_dir, &["completions", shell])
+}
+
+#[test]
+fn every_shell_gets_a_script_with_the_subcommands() {
+    let data_dir = DataDir::new("shells");
+    fs::write(data_dir.join("sources.toml"), "[[source]]\nname = \"basement\"\ncategory = \"music\"\nurl = \"https://example.com/calendar\"\nstrategy = \"feed\"\n").unwrap();
+
+    for shell in ["bash", "zsh", "fish", "powershell"] {
+        let output = completions(&data_dir, shell);
+
+        assert!(
+            output.status.success(),
+            "{}: {}",
+            shell,
+            String::from_utf8_lossy(&output.stderr)
+        );
+        let script = String::from_utf8_lossy(&output.stdout);
+        for command in [
+            "music",
+            "unique",
+            "general",
+            "all",
+            "history",
+            "parse",
+            "completions",
+        ] {
+            assert!(script.contains(command), "{} is missing {}", shell, command);
+        }
+        assert!(
+            script.contains("basement"),
+            "{} is missing the source names",
+            shell
+        );
+    }
+}
+
+#[test]
+fn completions_work_with_broken_config_files() {
+    let data_dir = DataDir::new("broken");
+    fs::write(data_dir.join("config.toml"), "nonsense = \n").unwrap();
+    fs::write(data_dir.join("sources.toml"), "[[source\n").unwrap();
+
+    let output = completions(&data_dir, "bash");
+
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    assert!(
+        output.stderr.is_empty(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    let script = String::from_utf8_lossy(&output.stdout);
+    assert!(script.contains("complete -F _event_finder event_finder"));
+    assert!(
+        script
+            .contains("        --source)\n            COMPREPLY=($(compgen -W \"\" -- \"$cur\"))"),
+        "{}",
+        script
+    );
+}
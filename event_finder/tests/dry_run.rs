@@ -0,0 +1,131 @@
+//! Integration tests of `--dry-run`, which prints what a run would fetch without fetching it.
+
+mod common;
+
+use std::fs;
+
+use mockito::{mock, server_url};
+
+use common::{run, DataDir};
+
+#[test]
+fn dry_run_lists_every_source_without_a_request() {
+    let data_dir = DataDir::new("sources");
+    let url = server_url();
+    fs::write(
+        data_dir.join("sources.toml"),
+        format!(
+            r#"[[source]]
+name = "basement"
+category = "music"
+url = "{url}/basement"
+event_selector = ".show"
+name_selector = "h3.title"
+start_date_selector = "time"
+start_date_attr = "datetime"
+url_selector = "a.tickets"
+price_selector = ".price"
+
+[[source]]
+name = "library"
+category = "general"
+url = "{url}/library.rss"
+strategy = "feed"
+"#,
+            url = url
+        ),
+    )
+    .unwrap();
+    fs::write(
+        data_dir.join("config.toml"),
+        "[filters]\nexclude = [\"trivia\"]\n",
+    )
+    .unwrap();
+    let basement = mock("GET", "/basement").expect(0).create();
+    let library = mock("GET", "/library.rss").expect(0).create();
+
+    let output = run(&data_dir, &["all", "--dry-run", "--format", "csv"]);
+
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    let plan = String::from_utf8_lossy(&output.stdout);
+    for expected in [
+        "basement (music)",
+        "library (general)",
+        &format!("{}/basement", url),
+        &format!("{}/library.rss", url),
+        "strategy",
+        "h3.title",
+        "start_date_attr      datetime",
+        "price_selector",
+        "events mentioning 'trivia' are left out",
+        "format  csv",
+    ] {
+        assert!(
+            plan.contains(expected),
+            "missing {:?} in:\n{}",
+            expected,
+            plan
+        );
+    }
+    basement.assert();
+    library.assert();
+    assert!(!data_dir.join("snapshot-basement.json").exists());
+}
+
+#[test]
+fn dry_run_says_whether_a_ticketmaster_key_is_set_without_showing_it() {
+    let data_dir = DataDir::new("ticketmaster");
+    fs::write(
+        data_dir.join("sources.toml"),
+        format!(
+            r#"[[source]]
+name = "ticketmaster"
+category = "music"
+url = "{}/discovery/v2/events.json?city=Nashville"
+strategy = "ticketmaster"
+"#,
+            server_url()
+        ),
+    )
+    .unwrap();
+    let api = mock("GET", mockito::Matcher::Any).expect(0).create();
+
+    let missing = run(&data_dir, &["music", "--dry-run"]);
+    fs::write(
+        data_dir.join("config.toml"),
+        "[ticketmaster]\napi_key = \"s3cret\"\n",
+    )
+    .unwrap();
+    let set = run(&data_dir, &["music", "--dry-run", "--when", "today"]);
+
+    let missing = String::from_utf8_lossy(&missing.stdout);
+    assert!(
+        missing.contains("missing (EVENT_FINDER_TICKETMASTER_API_KEY or [ticketmaster] api_key)"),
+        "{}",
+        missing
+    );
+    assert!(missing.contains("following the API's pages"), "{}", missing);
+    let set = String::from_utf8_lossy(&set.stdout);
+    assert!(set.contains("api_key   set"), "{}", set);
+    assert!(set.contains("startDateTime="), "{}", set);
+    assert!(!set.contains("s3cret"), "{}", set);
+    api.assert();
+}
+
+#[test]
+fn dry_run_only_runs_with_a_listing() {
+    let data_dir = DataDir::new("history");
+
+    let output = run(&data_dir, &["history", "--dry-run"]);
+
+    assert_eq!(output.status.code(), Some(64));
+    assert!(
+        String::from_utf8_lossy(&output.stderr).contains("--dry-run shows what"),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+}
@@ -0,0 +1,213 @@
+//! Integration tests of the binary's exit statuses and `--error-format json`.
+//!
+//! The sources are feeds served by mockito, so each test can make some or all of them fail.
+
+mod common;
+
+use std::fs;
+use std::path::Path;
+use std::process::Output;
+
+use mockito::{mock, server_url, Mock};
+use serde_json::Value;
+
+use common::{run, DataDir};
+
+const FEED: &str = r#"<?xml version="1.0"?>
+<rss version="2.0"><channel><title>Events</title>
+<item><title>Jazz on the Lawn</title><link>https://example.com/jazz</link><pubDate>Fri, 01 Jan 2100 19:00:00 GMT</pubDate></item>
+</channel></rss>"#;
+
+const EMPTY_FEED: &str =
+    r#"<?xml version="1.0"?><rss version="2.0"><channel><title>Events</title></channel></rss>"#;
+
+/// A data directory whose sources file lists a feed at each of the named paths.
+fn setup(name: &str, paths: &[&str]) -> DataDir {
+    let dir = DataDir::new(name);
+    let sources: String = paths
+        .iter()
+        .map(|path| format!("[[source]]\nname = \"{}\"\ncategory = \"general\"\nurl = \"{}/{}\"\nstrategy = \"feed\"\n\n", path, server_url(), path))
+        .collect();
+    fs::write(dir.join("sources.toml"), sources).unwrap();
+    dir
+}
+
+fn feed(path: &str, status: usize, body: &str) -> Mock {
+    mock("GET", format!("/{}", path).as_str())
+        .with_status(status)
+        .with_body(body)
+        .create()
+}
+
+/// Runs the menu, answering its prompts with `input`.
+fn run_menu(data_dir: &Path, input: &str) -> Output {
+    common::run_with_input(data_dir, &[], input.as_bytes())
+}
+
+/// Reads each line of stderr as a JSON object.
+fn json_errors(output: &Output) -> Vec<Value> {
+    String::from_utf8_lossy(&output.stderr)
+        .lines()
+        .map(|line| serde_json::from_str(line).unwrap_or_else(|_| panic!("not JSON: {}", line)))
+        .collect()
+}
+
+#[test]
+fn no_events_is_still_success() {
+    let _a = feed("quiet_a", 200, EMPTY_FEED);
+    let _b = feed("quiet_b", 200, EMPTY_FEED);
+    let data_dir = setup("empty", &["quiet_a", "quiet_b"]);
+
+    let output = run(&data_dir, &["general", "--strict"]);
+
+    assert_eq!(
+        output.status.code(),
+        Some(0),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    assert!(
+        String::from_utf8_lossy(&output.stdout).contains("no events"),
+        "{}",
+        String::from_utf8_lossy(&output.stdout)
+    );
+}
+
+#[test]
+fn some_sources_failing_only_fails_with_strict() {
+    let _ok = feed("partial_ok", 200, FEED);
+    let _down = feed("partial_down", 500, "internal error");
+    let data_dir = setup("partial", &["partial_ok", "partial_down"]);
+
+    let output = run(&data_dir, &["general"]);
+    assert_eq!(
+        output.status.code(),
+        Some(0),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    assert!(String::from_utf8_lossy(&output.stdout).contains("Name: Jazz on the Lawn"));
+    assert!(String::from_utf8_lossy(&output.stderr).contains("Error: fetching partial_down: "));
+
+    let output = run(&data_dir, &["general", "--strict"]);
+    assert_eq!(output.status.code(), Some(1));
+    assert!(
+        String::from_utf8_lossy(&output.stderr).contains("Error: 1 of 2 sources failed (--strict)")
+    );
+}
+
+#[test]
+fn failed_sources_are_summed_up_after_the_listing() {
+    let _first = feed("summary_first", 200, FEED);
+    let _down = feed("summary_down", 503, "maintenance");
+    let _last = feed(
+        "summary_last",
+        200,
+        FEED.replace("Jazz on the Lawn", "Blues by the River")
+            .as_str(),
+    );
+    let data_dir = setup(
+        "summary",
+        &["summary_first", "summary_down", "summary_last"],
+    );
+    let summary =
+        "⚠ summary_down failed: answered 503 Service Unavailable (2 of 3 sources succeeded)";
+
+    let output = run(&data_dir, &["all"]);
+    assert_eq!(
+        output.status.code(),
+        Some(0),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(
+        stdout.contains("Name: Jazz on the Lawn") && stdout.contains("Name: Blues by the River"),
+        "{}",
+        stdout
+    );
+    assert_eq!(
+        stdout.trim_end().lines().last(),
+        Some(summary),
+        "{}",
+        stdout
+    );
+
+    // The menu's "All" sums them up after its listing too
+    let output = run_menu(&data_dir, "4\nno\n");
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let listing = stdout.find("Name: Blues by the River").unwrap();
+    assert!(stdout[listing..].contains(summary), "{}", stdout);
+
+    // With --strict the listing is still shown, but the run fails
+    let output = run(&data_dir, &["all", "--strict", "--format", "json"]);
+    assert_eq!(output.status.code(), Some(1));
+    assert_eq!(
+        serde_json::from_slice::<Value>(&output.stdout)
+            .unwrap()
+            .as_array()
+            .unwrap()
+            .len(),
+        2
+    );
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains(summary), "{}", stderr);
+}
+
+#[test]
+fn every_source_failing_is_reported_as_json() {
+    let _a = feed("down_a", 503, "maintenance");
+    let _b = feed("down_b", 404, "");
+    let data_dir = setup("down", &["down_a", "down_b"]);
+
+    let output = run(&data_dir, &["general", "--error-format", "json"]);
+
+    assert_eq!(output.status.code(), Some(2));
+    let errors = json_errors(&output);
+    let down_a = errors.iter().find(|e| e["source"] == "down_a").unwrap();
+    assert_eq!(
+        (down_a["level"].as_str(), down_a["kind"].as_str()),
+        (Some("error"), Some("status"))
+    );
+    assert!(
+        down_a["message"]
+            .as_str()
+            .unwrap()
+            .ends_with("answered 503 Service Unavailable: maintenance"),
+        "{}",
+        down_a
+    );
+    assert!(errors
+        .iter()
+        .any(|e| e["source"] == "down_b" && e["kind"] == "status"));
+    assert_eq!(errors.last().unwrap()["kind"], "all_sources_failed");
+    assert_eq!(errors.last().unwrap()["message"], "all 2 sources failed");
+}
+
+#[test]
+fn bad_arguments_are_a_usage_error() {
+    let data_dir = setup("usage", &["unused"]);
+
+    let output = run(&data_dir, &["--limit", "none", "--error-format", "json"]);
+    assert_eq!(output.status.code(), Some(64));
+    let errors = json_errors(&output);
+    assert_eq!(errors.len(), 1);
+    assert_eq!(
+        (errors[0]["kind"].as_str(), errors[0]["message"].as_str()),
+        (Some("usage"), Some("invalid --limit 'none'"))
+    );
+
+    let output = run(&data_dir, &["general", "--source", "nowhere"]);
+    assert_eq!(output.status.code(), Some(64));
+}
+
+#[test]
+fn a_broken_config_file_is_a_config_error() {
+    let data_dir = setup("config", &["unused"]);
+    fs::write(data_dir.join("config.toml"), "nonsense = 1\n").unwrap();
+
+    let output = run(&data_dir, &["general"]);
+
+    assert_eq!(output.status.code(), Some(78));
+    assert!(String::from_utf8_lossy(&output.stderr).contains("unknown setting 'nonsense'"));
+}
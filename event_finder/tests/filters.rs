@@ -0,0 +1,31 @@
+//! Integration tests of the config file's filters.
+
+mod common;
+
+use std::fs;
+
+use common::{run_offline, DataDir};
+
+#[test]
+fn offline_applies_venue_blocklist() {
+    let data_dir = DataDir::new("blocklist");
+    fs::write(
+        data_dir.join("snapshot-songkick.json"),
+        r#"[{"name": "Cached Band", "start_date": "May 2", "location": "Ryman", "url": "https://www.songkick.com/concerts/1", "categories": ["music"]},
+            {"name": "Other Band", "start_date": "May 3", "location": "Tootsie's", "url": "https://www.songkick.com/concerts/2", "categories": ["music"]}]"#,
+    )
+    .unwrap();
+    fs::write(
+        data_dir.join("config.toml"),
+        "[filters]\nblocked_venues = [\"tootsie's\"]\n",
+    )
+    .unwrap();
+
+    let output = run_offline(&data_dir, &[], "1\nno\n");
+
+    assert!(output.status.success());
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("Name: Cached Band"), "{}", stdout);
+    assert!(!stdout.contains("Name: Other Band"), "{}", stdout);
+    assert!(stdout.contains("excluded 1 event"), "{}", stdout);
+}
@@ -0,0 +1,164 @@
+//! Integration tests of `--format` and `--template`, the ways a listing is written.
+
+mod common;
+
+use std::fs;
+
+use common::{run_offline, DataDir};
+
+#[test]
+fn offline_json_format_writes_only_json_to_stdout() {
+    let data_dir = DataDir::new("json");
+    fs::write(
+        data_dir.join("snapshot-songkick.json"),
+        r#"[{"name": "Cached Band", "start_date": "May 2, 2024 7:00 PM", "start": "2024-05-02T19:00:00", "location": "Ryman",
+             "url": "https://www.songkick.com/concerts/1", "categories": ["music"], "tags": ["rock"]}]"#,
+    )
+    .unwrap();
+
+    let output = run_offline(
+        &data_dir,
+        &["music", "--format", "json", "--compact", "--include-past"],
+        "",
+    );
+
+    assert!(output.status.success());
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert_eq!(
+        stdout,
+        concat!(
+            r#"[{"alt_urls":[],"categories":["music"],"description":null,"end":null,"end_date":null,"id":"#,
+            r#""d4264bdbf8b9a4d6","image_url":null,"location":"Ryman","name":"Cached Band","price":null,"source":"songkick","#,
+            r#""start":"2024-05-02T19:00:00","start_date":"May 2, 2024 7:00 PM","status":"unknown","tags":["rock"],"#,
+            r#""url":"https://www.songkick.com/concerts/1"}]"#,
+            "\n"
+        )
+    );
+    assert!(String::from_utf8_lossy(&output.stderr).contains("Offline mode"));
+
+    let output = run_offline(
+        &data_dir,
+        &["--format", "json", "--include-past"],
+        "1\nno\n",
+    );
+
+    assert!(output.status.success());
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let events: serde_json::Value =
+        serde_json::from_str(&stdout).unwrap_or_else(|e| panic!("{}: {}", e, stdout));
+    assert_eq!(events[0]["name"], "Cached Band");
+    assert!(String::from_utf8_lossy(&output.stderr).contains("Welcome"));
+}
+
+#[test]
+fn offline_csv_output_writes_file() {
+    let data_dir = DataDir::new("csv");
+    fs::write(
+        data_dir.join("snapshot-songkick.json"),
+        r#"[{"name": "Band, With \"Quotes\"", "start_date": "May 2", "location": "Ryman", "url": "https://www.songkick.com/concerts/1", "categories": ["music"]}]"#,
+    )
+    .unwrap();
+    let path = data_dir.join("plans.csv");
+
+    let output = run_offline(
+        &data_dir,
+        &[
+            "music",
+            "--format",
+            "csv",
+            "--crlf",
+            "--output",
+            path.to_str().unwrap(),
+        ],
+        "",
+    );
+
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    assert!(output.stdout.is_empty());
+    let csv = fs::read_to_string(&path).unwrap();
+    assert!(csv.starts_with("id,name,start_date,"), "{}", csv);
+    assert!(
+        csv.contains(",\"Band, With \"\"Quotes\"\"\",May 2,"),
+        "{}",
+        csv
+    );
+    assert_eq!(csv.lines().count(), 2);
+    assert!(csv.ends_with("\r\n"));
+}
+
+#[test]
+fn offline_template_prints_one_line_per_event() {
+    let data_dir = DataDir::new("template");
+    fs::write(
+        data_dir.join("snapshot-songkick.json"),
+        r#"[{"name": "Cached Band", "start_date": "May 2", "location": "Ryman", "url": "https://www.songkick.com/concerts/1", "categories": ["music"]},
+            {"name": "Other Band", "start_date": "May 3", "url": "https://www.songkick.com/concerts/2", "categories": ["music"]}]"#,
+    )
+    .unwrap();
+
+    let output = run_offline(
+        &data_dir,
+        &[
+            "music",
+            "--template",
+            "{start_date} | {name:8} @ {location}",
+        ],
+        "",
+    );
+
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    assert_eq!(
+        String::from_utf8_lossy(&output.stdout),
+        "May 2 | Cached … @ Ryman\nMay 3 | Other B… @ \n"
+    );
+}
+
+#[test]
+fn offline_table_fits_the_width_or_falls_back() {
+    let data_dir = DataDir::new("table");
+    fs::write(
+        data_dir.join("snapshot-songkick.json"),
+        r#"[{"name": "Cached Band With A Very Long Name For A Table", "start_date": "May 2", "location": "Ryman", "url": "https://www.songkick.com/concerts/1", "categories": ["music"]}]"#,
+    )
+    .unwrap();
+
+    let output = run_offline(
+        &data_dir,
+        &["music", "--format", "table", "--width", "60"],
+        "",
+    );
+
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("Time   Event"), "{}", stdout);
+    let row = stdout
+        .lines()
+        .find(|line| line.starts_with("May 2"))
+        .unwrap();
+    assert!(
+        row.contains("Cached Band With") && row.contains("…  Ryman") && row.ends_with("songkick"),
+        "{}",
+        stdout
+    );
+    assert!(row.chars().count() <= 60, "{}", row);
+
+    let output = run_offline(
+        &data_dir,
+        &["music", "--format", "table", "--width", "40"],
+        "",
+    );
+    assert!(String::from_utf8_lossy(&output.stdout)
+        .contains("Name: Cached Band With A Very Long Name For A Table [songkick]\n"));
+}
@@ -0,0 +1,75 @@
+//! Integration tests of `--help`, `-h`, and the `help` subcommand.
+
+mod common;
+
+use std::fs;
+
+use common::{run, DataDir};
+
+#[test]
+fn help_prints_the_usage_and_exits_zero() {
+    let data_dir = DataDir::new("general");
+    // A broken sources file doesn't get in the way of reading how to fix it
+    fs::write(data_dir.join("sources.toml"), "[[source\n").unwrap();
+
+    for args in [&["--help"][..], &["-h"], &["help"]] {
+        let output = run(&data_dir, args);
+
+        assert_eq!(
+            output.status.code(),
+            Some(0),
+            "{:?}: {}",
+            args,
+            String::from_utf8_lossy(&output.stderr)
+        );
+        let usage = String::from_utf8_lossy(&output.stdout);
+        assert!(
+            usage.starts_with("Usage: event_finder [COMMAND] [FLAGS]\n"),
+            "{:?}: {}",
+            args,
+            usage
+        );
+        for command in ["music", "history", "parse", "completions", "help"] {
+            assert!(usage.contains(command), "{:?} is missing {}", args, command);
+        }
+        assert!(
+            usage.contains("--format") && usage.contains("-q, --quiet"),
+            "{}",
+            usage
+        );
+    }
+}
+
+#[test]
+fn each_subcommand_has_its_own_usage() {
+    let data_dir = DataDir::new("subcommands");
+
+    for (args, expected) in [
+        (
+            &["help", "music"][..],
+            "Usage: event_finder music [FLAGS]\n",
+        ),
+        (&["music", "--help"], "Usage: event_finder music [FLAGS]\n"),
+        (&["history", "-h"], "Usage: event_finder history [FLAGS]\n"),
+        (
+            &["help", "parse"],
+            "Usage: event_finder parse --source NAME [FLAGS] < PAGE\n",
+        ),
+        (
+            &["completions", "--help"],
+            "Usage: event_finder completions <bash|zsh|fish|powershell>\n",
+        ),
+    ] {
+        let output = run(&data_dir, args);
+
+        assert_eq!(
+            output.status.code(),
+            Some(0),
+            "{:?}: {}",
+            args,
+            String::from_utf8_lossy(&output.stderr)
+        );
+        let usage = String::from_utf8_lossy(&output.stdout);
+        assert!(usage.starts_with(expected), "{:?}: {}", args, usage);
+    }
+}
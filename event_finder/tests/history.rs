@@ -0,0 +1,54 @@
+//! Integration tests of the `history` subcommand, which lists the events of past fetches.
+
+mod common;
+
+use std::fs;
+
+use common::{run_offline, DataDir};
+
+#[test]
+fn offline_history_lists_past_fetches() {
+    let data_dir = DataDir::new("history");
+    fs::write(
+        data_dir.join("history.json"),
+        r#"{"version": 1, "events": [
+            {"event": {"name": "Old Show", "start_date": "2020-05-02", "location": "Ryman Auditorium", "url": "https://example.com/1"},
+             "first_seen": "2020-04-01T09:00:00", "last_seen": "2020-04-20T09:00:00", "fetches": 3},
+            {"event": {"name": "Jazz Jam", "start_date": "2020-05-03", "location": "Rudy's", "url": "https://example.com/2"},
+             "first_seen": "2020-04-01T09:00:00", "last_seen": "2020-04-01T09:00:00", "fetches": 1}]}"#,
+    )
+    .unwrap();
+
+    let output = run_offline(
+        &data_dir,
+        &[
+            "history",
+            "--venue",
+            "ryman",
+            "--format",
+            "json",
+            "--compact",
+        ],
+        "",
+    );
+
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(
+        stdout.starts_with('[') && stdout.contains(r#""name":"Old Show""#),
+        "{}",
+        stdout
+    );
+    assert!(!stdout.contains("Jazz Jam"), "{}", stdout);
+
+    let output = run_offline(&data_dir, &["history", "--first-seen-since", "7d"], "");
+    assert!(
+        String::from_utf8_lossy(&output.stdout).contains("no events"),
+        "{}",
+        String::from_utf8_lossy(&output.stdout)
+    );
+}
@@ -0,0 +1,64 @@
+//! Integration tests of `--init-config` and the sources file it writes.
+
+mod common;
+
+use std::fs;
+
+use common::{run_offline, DataDir};
+
+#[test]
+fn offline_init_config_writes_sources_the_user_can_edit() {
+    let data_dir = DataDir::new("sources");
+    let path = data_dir.join("sources.toml");
+
+    let output = run_offline(&data_dir, &["--init-config"], "");
+
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    assert!(fs::read_to_string(&path)
+        .unwrap()
+        .contains("name = \"songkick\""));
+    assert_eq!(
+        run_offline(&data_dir, &["--init-config"], "").status.code(),
+        Some(74)
+    );
+
+    let mut sources = fs::read_to_string(&path).unwrap();
+    sources.push_str("\n[[source]]\nname = \"library\"\ncategory = \"general\"\nurl = \"https://library.nashville.org/events.rss\"\nstrategy = \"feed\"\n");
+    sources.push_str("\n[[source]]\nname = \"broken\"\ncategory = \"general\"\nurl = \"https://example.com\"\nevent_selector = \"div[\"\n");
+    fs::write(&path, sources).unwrap();
+
+    let output = run_offline(&data_dir, &["general"], "");
+
+    // No source has a snapshot yet, so every one of them fails
+    assert_eq!(
+        output.status.code(),
+        Some(2),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(
+        stderr.contains("offline: no cached data for library"),
+        "{}",
+        stderr
+    );
+    assert!(
+        stderr.contains(
+            "source 'broken': event_selector: invalid CSS selector 'div[' (entry left out)"
+        ),
+        "{}",
+        stderr
+    );
+
+    let missing = data_dir.join("missing.toml");
+    let output = run_offline(
+        &data_dir,
+        &["general", "--config", missing.to_str().unwrap()],
+        "",
+    );
+    assert_eq!(output.status.code(), Some(64));
+}
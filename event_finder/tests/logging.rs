@@ -0,0 +1,47 @@
+//! Integration tests of `-v`, `--quiet`, and `RUST_LOG`, which pick what is logged.
+
+mod common;
+
+use std::fs;
+
+use common::{command, run_offline, DataDir};
+
+#[test]
+fn offline_verbosity_flags_pick_what_is_logged() {
+    let data_dir = DataDir::new("logging");
+    fs::write(
+        data_dir.join("snapshot-songkick.json"),
+        r#"[{"name": "Cached Band", "start_date": "May 2", "url": "", "categories": ["music"]}]"#,
+    )
+    .unwrap();
+
+    let stderr = |args: &[&str]| {
+        String::from_utf8_lossy(&run_offline(&data_dir, args, "").stderr).into_owned()
+    };
+
+    let debug = stderr(&["music", "-vv"]);
+    assert!(
+        debug.contains("[debug event_finder::pipeline] songkick: 1 events from the snapshot"),
+        "{}",
+        debug
+    );
+    let default = stderr(&["music"]);
+    assert!(
+        default.contains("Warning: Offline mode") && !default.contains("[debug"),
+        "{}",
+        default
+    );
+    assert!(!stderr(&["music", "--quiet"]).contains("Offline mode"));
+
+    let output = command(&data_dir)
+        .args(["--offline", "music"])
+        .env("RUST_LOG", "event_finder::pipeline=debug")
+        .output()
+        .unwrap();
+    let logged = String::from_utf8_lossy(&output.stderr).into_owned();
+    assert!(
+        logged.contains("songkick: 1 events from the snapshot"),
+        "{}",
+        logged
+    );
+}
@@ -0,0 +1,225 @@
+//! Integration tests of the interactive menu, answered on stdin.
+
+mod common;
+
+use std::fs;
+use std::process::Stdio;
+
+use common::{command, run_offline, DataDir};
+
+#[test]
+fn offline_searches_fetched_events() {
+    let data_dir = DataDir::new("search");
+    fs::write(
+        data_dir.join("snapshot-songkick.json"),
+        r#"[{"name": "Cached Band", "start_date": "May 2", "location": "Café Roma", "url": "https://www.songkick.com/concerts/1", "categories": ["music"]},
+            {"name": "Other Band", "start_date": "May 3", "location": "Ryman", "url": "https://www.songkick.com/concerts/2", "categories": ["music"]}]"#,
+    )
+    .unwrap();
+
+    let output = run_offline(&data_dir, &[], "1\ns\ncafe\nno\n");
+
+    assert!(output.status.success());
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("1 of 2 events match 'cafe'"), "{}", stdout);
+}
+
+#[test]
+fn offline_surprise_me_picks_and_rerolls_without_refetching() {
+    let data_dir = DataDir::new("surprise");
+    fs::write(
+        data_dir.join("snapshot-songkick.json"),
+        r#"[{"name": "Cached Band", "start_date": "May 2", "location": "Ryman", "url": "https://www.songkick.com/concerts/1", "categories": ["music"]},
+            {"name": "Other Band", "start_date": "May 3", "location": "Station Inn", "url": "https://www.songkick.com/concerts/2", "categories": ["music"]}]"#,
+    )
+    .unwrap();
+
+    let output = run_offline(&data_dir, &[], "6\nr\nno\n");
+
+    assert!(output.status.success());
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert_eq!(
+        stdout.matches("Your pick, out of 2 events").count(),
+        2,
+        "{}",
+        stdout
+    );
+    assert_eq!(stdout.matches("Fetching").count(), 1, "{}", stdout);
+}
+
+#[test]
+fn offline_menu_lists_several_categories_together() {
+    let data_dir = DataDir::new("multi_select");
+    fs::write(
+        data_dir.join("snapshot-songkick.json"),
+        r#"[{"name": "Bluegrass Night", "start_date": "May 2", "start": "2100-05-02T20:00:00", "location": "Station Inn", "url": "https://www.songkick.com/concerts/1", "categories": ["music"]},
+            {"name": "Early Band", "start_date": "May 1", "start": "2100-05-01T20:00:00", "location": "Ryman", "url": "https://www.songkick.com/concerts/2", "categories": ["music"]}]"#,
+    )
+    .unwrap();
+    fs::write(
+        data_dir.join("snapshot-perto.json"),
+        r#"[{"name": "Bluegrass Night", "start_date": "May 2", "start": "2100-05-02T20:00:00", "location": "The Station Inn", "url": "https://en.perto.com/events/1", "categories": ["unique"]},
+            {"name": "Pub Trivia", "start_date": "May 3", "start": "2100-05-03T19:00:00", "location": "The Pub", "url": "https://en.perto.com/events/2", "categories": ["unique"]}]"#,
+    )
+    .unwrap();
+    fs::write(
+        data_dir.join("snapshot-nashville.com.json"),
+        r#"[{"name": "Noisy Fair", "start_date": "May 1", "start": "2100-05-01T10:00:00", "location": "Fairgrounds", "url": "https://www.nashville.com/events/1", "categories": ["general"]}]"#,
+    )
+    .unwrap();
+
+    let output = run_offline(&data_dir, &[], "1,x unique\nno\n");
+
+    assert!(output.status.success());
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(
+        stdout.contains("Invalid input: unknown category 'x'."),
+        "{}",
+        stdout
+    );
+    assert!(
+        stdout.contains("Fetching music and unique events..."),
+        "{}",
+        stdout
+    );
+    assert!(
+        stdout.contains("merged 1 event listed by more than one source"),
+        "{}",
+        stdout
+    );
+    assert_eq!(
+        stdout.matches("Name: Bluegrass Night").count(),
+        1,
+        "{}",
+        stdout
+    );
+    assert!(!stdout.contains("Noisy Fair"), "{}", stdout);
+    let early = stdout.find("Name: Early Band").unwrap();
+    let bluegrass = stdout.find("Name: Bluegrass Night").unwrap();
+    let trivia = stdout.find("Name: Pub Trivia").unwrap();
+    assert!(early < bluegrass && bluegrass < trivia, "{}", stdout);
+}
+
+#[test]
+fn offline_menu_relists_the_session_without_fetching_again() {
+    let data_dir = DataDir::new("session");
+    fs::write(
+        data_dir.join("snapshot-songkick.json"),
+        r#"[{"name": "Another Band", "start_date": "May 2", "location": "Ryman", "url": "https://www.songkick.com/concerts/1", "categories": ["music"]},
+            {"name": "Other Band", "start_date": "May 3", "location": "Station Inn", "url": "https://www.songkick.com/concerts/2", "categories": ["music"]}]"#,
+    )
+    .unwrap();
+
+    let output = run_offline(
+        &data_dir,
+        &[],
+        "1\nsort name desc\nfilter another\nsort price\nyes\n1\nrefresh\nno\n",
+    );
+
+    assert!(output.status.success());
+    let all = format!(
+        "{}{}",
+        String::from_utf8_lossy(&output.stdout),
+        String::from_utf8_lossy(&output.stderr)
+    );
+    // Listed by name, then by name backwards, then only the filter's match
+    let names: Vec<&str> = all
+        .lines()
+        .filter_map(|line| line.split("Name: ").nth(1))
+        .map(|name| name.trim_end_matches(" [songkick]"))
+        .collect();
+    assert_eq!(
+        names[..5],
+        [
+            "Another Band",
+            "Other Band",
+            "Other Band",
+            "Another Band",
+            "Another Band"
+        ],
+        "{}",
+        all
+    );
+    assert!(all.contains("1 of 2 events mention another"), "{}", all);
+    assert!(
+        all.contains("Invalid input: unknown sort key 'price'"),
+        "{}",
+        all
+    );
+    // Choosing music again reuses the events, until refresh fetches them
+    assert_eq!(
+        all.matches("songkick: listing the events fetched earlier this session")
+            .count(),
+        1,
+        "{}",
+        all
+    );
+    assert!(all.contains("Fetching music events again..."), "{}", all);
+    assert!(
+        !all[all.find("Fetching music events again").unwrap()..]
+            .contains("fetched earlier this session"),
+        "{}",
+        all
+    );
+}
+
+#[test]
+fn offline_menu_quits_when_the_input_ends() {
+    let data_dir = DataDir::new("eof");
+
+    // As `echo 1 | event_finder`: the listing, then the end of input, which answers no
+    let output = run_offline(&data_dir, &[], "1\n");
+
+    assert!(output.status.success());
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(!stdout.contains("Invalid input"), "{}", stdout);
+    assert!(
+        stdout.contains("Thank you for using the Event Finder!"),
+        "{}",
+        stdout
+    );
+
+    let output = run_offline(&data_dir, &[], "");
+    assert!(output.status.success());
+    assert!(String::from_utf8_lossy(&output.stdout).contains("Exiting the Event Finder."));
+}
+
+#[test]
+fn offline_menu_exits_on_ctrl_c_at_a_prompt() {
+    use std::io::Read;
+
+    let data_dir = DataDir::new("menu_interrupt");
+    let mut child = command(&data_dir)
+        .args(["--offline", "--no-prefs"])
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::null())
+        .spawn()
+        .unwrap();
+    // stdin is kept open until the end, so the menu waits at its first prompt
+    let stdin = child.stdin.take().unwrap();
+    let mut stdout = child.stdout.take().unwrap();
+    let mut seen = Vec::new();
+    while !String::from_utf8_lossy(&seen).contains("Surprise me") {
+        let mut chunk = [0; 256];
+        let read = stdout.read(&mut chunk).unwrap();
+        assert!(read > 0, "the menu ended before its prompt");
+        seen.extend_from_slice(&chunk[..read]);
+    }
+    // The prompt is out before the read starts; give the menu a moment to block on it
+    std::thread::sleep(std::time::Duration::from_millis(200));
+
+    // SAFETY: kill only sends a signal to the child spawned above
+    unsafe {
+        libc::kill(child.id() as libc::pid_t, libc::SIGINT);
+    }
+
+    assert_eq!(child.wait().unwrap().code(), Some(130));
+    stdout.read_to_end(&mut seen).unwrap();
+    assert!(
+        String::from_utf8_lossy(&seen).contains("Exiting the Event Finder."),
+        "{}",
+        String::from_utf8_lossy(&seen)
+    );
+    drop(stdin);
+}
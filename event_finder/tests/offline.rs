@@ -0,0 +1,55 @@
+//! Integration tests of `--offline`, which serves the events saved by earlier fetches.
+//!
+//! In offline mode the binary must never build an HTTP client, so these tests point the data
+//! directory at a temporary location and check that only snapshot data is shown.
+
+mod common;
+
+use std::fs;
+
+use common::{run_offline, DataDir};
+
+#[test]
+fn offline_without_snapshots_reports_each_source() {
+    let data_dir = DataDir::new("empty");
+
+    let output = run_offline(&data_dir, &[], "4\nno\n");
+
+    assert!(output.status.success());
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(
+        stderr.contains("offline: no cached data for songkick"),
+        "{}",
+        stderr
+    );
+    assert!(
+        stderr.contains("offline: no cached data for perto"),
+        "{}",
+        stderr
+    );
+    assert!(
+        stderr.contains("offline: no cached data for nashville.com"),
+        "{}",
+        stderr
+    );
+    assert!(!stderr.contains("Error fetching URL"), "{}", stderr);
+}
+
+#[test]
+fn offline_serves_snapshot() {
+    let data_dir = DataDir::new("snapshot");
+    fs::write(
+        data_dir.join("snapshot-songkick.json"),
+        r#"[{"name": "Cached Band", "start_date": "May 2", "end_date": null, "location": "Ryman",
+             "url": "https://www.songkick.com/concerts/1", "categories": ["music"]}]"#,
+    )
+    .unwrap();
+
+    let output = run_offline(&data_dir, &[], "1\nno\n");
+
+    assert!(output.status.success());
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("Offline mode"), "{}", stdout);
+    assert!(stdout.contains("Name: Cached Band"), "{}", stdout);
+    assert!(stdout.contains("Location: Ryman"), "{}", stdout);
+}
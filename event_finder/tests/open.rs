@@ -0,0 +1,28 @@
+//! Integration tests of `--open`, which opens a listed event in the browser.
+
+mod common;
+
+use std::fs;
+
+use common::{run_offline, DataDir};
+
+#[test]
+fn offline_open_reports_events_it_cannot_open() {
+    let data_dir = DataDir::new("open");
+    fs::write(
+        data_dir.join("snapshot-songkick.json"),
+        r#"[{"name": "Linkless Band", "start_date": "May 2", "location": "Ryman", "url": "", "categories": ["music"]}]"#,
+    )
+    .unwrap();
+
+    let output = run_offline(&data_dir, &["music", "--open", "1"], "");
+    assert_eq!(output.status.code(), Some(64));
+    assert!(String::from_utf8_lossy(&output.stdout).contains("1. Name: Linkless Band"));
+    assert!(String::from_utf8_lossy(&output.stderr)
+        .contains("Error: event 1, Linkless Band, has no link to open"));
+
+    let output = run_offline(&data_dir, &["music", "--open", "2"], "");
+    assert!(
+        String::from_utf8_lossy(&output.stderr).contains("Error: there is no event 2 (pick 1-1)")
+    );
+}
@@ -0,0 +1,61 @@
+//! Integration tests of `--output`, which writes the listing to a file.
+
+mod common;
+
+use std::fs;
+
+use common::{run_offline, DataDir};
+
+#[test]
+fn offline_output_refuses_to_overwrite_without_force() {
+    let data_dir = DataDir::new("output");
+    fs::write(
+        data_dir.join("snapshot-songkick.json"),
+        r#"[{"name": "Cached Band", "start_date": "May 2", "location": "Ryman", "url": "https://www.songkick.com/concerts/1", "categories": ["music"]}]"#,
+    )
+    .unwrap();
+    let path = data_dir.join("reports").join("music.txt");
+    let path_arg = path.to_str().unwrap();
+
+    let output = run_offline(&data_dir, &["music", "--output", path_arg], "");
+
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    assert!(fs::read_to_string(&path)
+        .unwrap()
+        .contains("Name: Cached Band"));
+    assert!(!String::from_utf8_lossy(&output.stdout).contains("Name: Cached Band"));
+
+    let output = run_offline(
+        &data_dir,
+        &["music", "--format", "json", "--output", path_arg],
+        "",
+    );
+
+    assert_eq!(output.status.code(), Some(74));
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(
+        stderr.contains("already exists (--force replaces it)"),
+        "{}",
+        stderr
+    );
+    assert!(fs::read_to_string(&path)
+        .unwrap()
+        .contains("Name: Cached Band"));
+
+    let output = run_offline(
+        &data_dir,
+        &["music", "--format", "json", "--output", path_arg, "--force"],
+        "",
+    );
+
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    assert!(fs::read_to_string(&path).unwrap().starts_with('['));
+}
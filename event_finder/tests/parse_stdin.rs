@@ -0,0 +1,145 @@
+//! Integration tests of the `parse` subcommand, which lists the events of a page piped to it.
+
+mod common;
+
+use std::fs;
+use std::path::Path;
+use std::process::Output;
+
+use serde_json::Value;
+
+use common::DataDir;
+
+fn fixture(name: &str) -> Vec<u8> {
+    fs::read(
+        Path::new(env!("CARGO_MANIFEST_DIR"))
+            .join("tests/fixtures")
+            .join(name),
+    )
+    .unwrap()
+}
+
+fn parse(data_dir: &Path, args: &[&str], page: &[u8]) -> Output {
+    common::run_with_input(data_dir, &[&["parse"], args].concat(), page)
+}
+
+#[test]
+fn parse_lists_a_piped_fixture_as_json() {
+    let data_dir = DataDir::new("fixture");
+
+    let output = parse(
+        &data_dir,
+        &["--source", "perto", "--format", "json", "--include-past"],
+        &fixture("perto.html"),
+    );
+
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    let events: Value = serde_json::from_slice(&output.stdout).unwrap();
+    let names: Vec<&str> = events
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|e| e["name"].as_str().unwrap())
+        .collect();
+    assert_eq!(names.len(), 3, "{:?}", names);
+    assert!(
+        names.contains(&"Bluegrass Jam at the Station Inn"),
+        "{:?}",
+        names
+    );
+    assert!(events[0]["source"] == "perto", "{}", events[0]);
+    // Nothing is fetched or saved for a piped page
+    assert!(!data_dir.join("snapshot-perto.json").exists());
+    assert!(!data_dir.join("history.json").exists());
+}
+
+#[test]
+fn parse_reads_a_source_from_a_custom_sources_file() {
+    let data_dir = DataDir::new("custom");
+    let custom = data_dir.join("custom.toml");
+    fs::write(
+        &custom,
+        r#"[[source]]
+name = "mysite"
+category = "general"
+url = "https://mysite.example/events"
+event_selector = ".event"
+name_selector = "h2"
+start_date_selector = ".when"
+url_selector = "a"
+"#,
+    )
+    .unwrap();
+    let page = br#"<div class="event"><h2>Quilt Show</h2><span class="when">2100-05-02 10:00</span><a href="/quilts">More</a></div>"#;
+
+    let output = parse(
+        &data_dir,
+        &[
+            "--config-file",
+            custom.to_str().unwrap(),
+            "--source",
+            "mysite",
+            "--template",
+            "{name} | {url}",
+        ],
+        page,
+    );
+
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    assert_eq!(
+        String::from_utf8_lossy(&output.stdout),
+        "Quilt Show | https://mysite.example/quilts\n"
+    );
+}
+
+#[test]
+fn parse_rejects_empty_and_non_utf8_input() {
+    let data_dir = DataDir::new("input");
+
+    let output = parse(&data_dir, &["--source", "perto"], b"  \n");
+    assert_eq!(output.status.code(), Some(65));
+    assert!(
+        String::from_utf8_lossy(&output.stderr).contains("stdin is empty"),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+
+    let output = parse(&data_dir, &["--source", "perto"], b"<p>Caf\xe9</p>");
+    assert_eq!(output.status.code(), Some(65));
+    assert!(
+        String::from_utf8_lossy(&output.stderr)
+            .contains("stdin isn't UTF-8 text (invalid byte at offset 6)"),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+
+    let output = parse(&data_dir, &["--source", "nowhere"], b"<p>page</p>");
+    assert_eq!(output.status.code(), Some(64));
+}
+
+#[test]
+fn parse_reports_a_piped_bot_challenge() {
+    let data_dir = DataDir::new("blocked");
+
+    let output = parse(
+        &data_dir,
+        &["--source", "perto"],
+        &fixture("cloudflare_challenge.html"),
+    );
+
+    assert_eq!(output.status.code(), Some(2));
+    assert!(
+        String::from_utf8_lossy(&output.stderr)
+            .contains("perto: the site appears to be blocking automated requests"),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+}
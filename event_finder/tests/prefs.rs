@@ -0,0 +1,49 @@
+//! Integration tests of the menu's remembered preferences.
+
+mod common;
+
+use std::fs;
+
+use common::{run_offline, DataDir};
+
+#[test]
+fn offline_menu_remembers_the_last_session() {
+    let data_dir = DataDir::new("prefs");
+    fs::write(
+        data_dir.join("snapshot-songkick.json"),
+        r#"[{"name": "Cached Band", "start_date": "May 2", "location": "Ryman", "url": "https://www.songkick.com/concerts/1", "categories": ["music"]},
+            {"name": "Other Band", "start_date": "May 3", "location": "Station Inn", "url": "https://www.songkick.com/concerts/2", "categories": ["music"]}]"#,
+    )
+    .unwrap();
+
+    let first = run_offline(&data_dir, &["--limit", "1"], "music\nno\n");
+    assert!(first.status.success());
+    assert!(data_dir.join("prefs.json").exists());
+
+    // An empty choice fetches music again, still limited to one event
+    let second = run_offline(&data_dir, &[], "\nno\n");
+    let stdout = String::from_utf8_lossy(&second.stdout);
+    assert!(stdout.contains("Enter: music again"), "{}", stdout);
+    assert!(stdout.contains("Name: Cached Band"), "{}", stdout);
+    assert!(!stdout.contains("Name: Other Band"), "{}", stdout);
+
+    let ignored = run_offline(&data_dir, &["--no-prefs"], "1\nno\n");
+    let stdout = String::from_utf8_lossy(&ignored.stdout);
+    assert!(!stdout.contains("Enter: music again"), "{}", stdout);
+    assert!(stdout.contains("Name: Other Band"), "{}", stdout);
+
+    fs::write(data_dir.join("prefs.json"), "{\"limit\": ").unwrap();
+    let corrupted = run_offline(&data_dir, &[], "1\nno\n");
+    assert!(corrupted.status.success());
+    let stderr = String::from_utf8_lossy(&corrupted.stderr);
+    assert!(
+        stderr.contains("ignoring the saved preferences"),
+        "{}",
+        stderr
+    );
+    assert!(String::from_utf8_lossy(&corrupted.stdout).contains("Name: Other Band"));
+    // The session's choices replace the corrupted file
+    assert!(fs::read_to_string(data_dir.join("prefs.json"))
+        .unwrap()
+        .contains("\"music\""));
+}
@@ -0,0 +1,41 @@
+//! Integration tests of `--new-only`, `--mark-seen`, and `--reset-seen`, which track the events listed before.
+
+mod common;
+
+use std::fs;
+
+use common::{run_offline, DataDir};
+
+#[test]
+fn offline_new_only_hides_events_listed_before() {
+    let data_dir = DataDir::new("new_only");
+    fs::write(
+        data_dir.join("snapshot-songkick.json"),
+        r#"[{"name": "Cached Band", "start_date": "May 2", "location": "Ryman", "url": "https://www.songkick.com/concerts/1", "categories": ["music"]}]"#,
+    )
+    .unwrap();
+
+    let first = run_offline(&data_dir, &["music", "--new-only"], "");
+    assert!(String::from_utf8_lossy(&first.stdout).contains("1. Name: Cached Band"));
+    assert!(data_dir.join("seen.json").exists());
+
+    let repeat = run_offline(&data_dir, &["music", "--new-only"], "");
+    let stdout = String::from_utf8_lossy(&repeat.stdout);
+    assert!(!stdout.contains("Cached Band"), "{}", stdout);
+    assert!(
+        stdout.contains("hid 1 event listed before (--reset-seen forgets them)"),
+        "{}",
+        stdout
+    );
+
+    let reset = run_offline(&data_dir, &["--reset-seen"], "");
+    assert!(String::from_utf8_lossy(&reset.stdout).contains("Forgot 1 seen event."));
+    let marked = run_offline(&data_dir, &["music", "--mark-seen"], "");
+    let stdout = String::from_utf8_lossy(&marked.stdout);
+    assert!(
+        stdout.contains("Marked 1 event as seen, 1 for the first time."),
+        "{}",
+        stdout
+    );
+    assert!(!stdout.contains("Name: Cached Band"), "{}", stdout);
+}
@@ -0,0 +1,43 @@
+//! Integration tests of the listing subcommands, which run without the menu.
+
+mod common;
+
+use std::fs;
+
+use common::{run_offline, DataDir};
+
+#[test]
+fn offline_subcommand_lists_events_without_the_menu() {
+    let data_dir = DataDir::new("subcommand");
+    fs::write(
+        data_dir.join("snapshot-songkick.json"),
+        r#"[{"name": "Cached Band", "start_date": "May 2", "location": "Ryman", "url": "https://www.songkick.com/concerts/1", "categories": ["music"]},
+            {"name": "Other Band", "start_date": "May 3", "location": "Station Inn", "url": "https://www.songkick.com/concerts/2", "categories": ["music"]}]"#,
+    )
+    .unwrap();
+
+    let output = run_offline(
+        &data_dir,
+        &["music", "--search", "other", "--format", "plain"],
+        "",
+    );
+
+    assert!(output.status.success());
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(!stdout.contains("Please choose"), "{}", stdout);
+    assert!(!stdout.contains("Welcome"), "{}", stdout);
+    assert!(stdout.contains("Name: Other Band"), "{}", stdout);
+    assert!(!stdout.contains("Name: Cached Band"), "{}", stdout);
+    assert!(stdout.contains("1 of 2 events match 'other'"), "{}", stdout);
+}
+
+#[test]
+fn unknown_subcommand_is_a_usage_error() {
+    let data_dir = DataDir::new("bad_subcommand");
+
+    let output = run_offline(&data_dir, &["concerts"], "");
+
+    assert_eq!(output.status.code(), Some(64));
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("unknown command 'concerts'"), "{}", stderr);
+}
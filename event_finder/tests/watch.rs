@@ -0,0 +1,77 @@
+//! Integration tests of `--watch`, which lists the events again every interval.
+
+mod common;
+
+use std::fs;
+use std::process::Stdio;
+
+use common::{command, run_offline, DataDir};
+
+#[test]
+fn offline_watch_lists_until_q() {
+    let data_dir = DataDir::new("watch");
+    fs::write(
+        data_dir.join("snapshot-songkick.json"),
+        r#"[{"name": "Cached Band", "start_date": "May 2", "location": "Ryman", "url": "https://www.songkick.com/concerts/1", "categories": ["music"]}]"#,
+    )
+    .unwrap();
+
+    let output = run_offline(&data_dir, &["music", "--watch", "10m"], "q");
+
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(
+        stdout.starts_with("Watching for events, refreshing every 10m (q to quit)\n"),
+        "{}",
+        stdout
+    );
+    assert!(
+        stdout.contains("1. Name: Cached Band [songkick]\n"),
+        "{}",
+        stdout
+    );
+    assert!(!stdout.contains("[NEW]"), "{}", stdout);
+    assert!(
+        stdout.contains("Refreshed at ") && stdout.ends_with("Stopped watching.\n"),
+        "{}",
+        stdout
+    );
+}
+
+#[cfg(unix)]
+#[test]
+fn offline_watch_stops_cleanly_on_ctrl_c() {
+    use std::io::{BufRead, BufReader};
+
+    let data_dir = DataDir::new("watch_interrupt");
+    let mut child = command(&data_dir)
+        .args(["--offline", "music", "--watch", "10m"])
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::null())
+        .spawn()
+        .unwrap();
+    let mut stdout = BufReader::new(child.stdout.take().unwrap());
+    let mut line = String::new();
+    while !line.starts_with("Refreshed at") {
+        line.clear();
+        assert!(
+            stdout.read_line(&mut line).unwrap() > 0,
+            "the watch ended before its first refresh"
+        );
+    }
+
+    // SAFETY: kill only sends a signal to the child spawned above
+    unsafe {
+        libc::kill(child.id() as libc::pid_t, libc::SIGINT);
+    }
+
+    assert!(child.wait().unwrap().success());
+    let mut rest = String::new();
+    stdout.read_line(&mut rest).unwrap();
+    assert_eq!(rest, "Stopped watching.\n");
+}